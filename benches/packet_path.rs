@@ -0,0 +1,76 @@
+//! Packet path benchmarks
+//!
+//! Compares crafting SYN probes into fresh `Vec`s against reused and pooled
+//! buffers, and owned against borrowed parsing of SYN-ACK replies.
+//!
+//! Run with `cargo bench --bench packet_path`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use nrmap::packet::crafting::TcpFlags;
+use nrmap::packet::{PacketBufferPool, PacketBuilder, PacketParser, TcpPacket};
+use std::net::{IpAddr, Ipv4Addr};
+
+fn syn(dest_port: u16) -> TcpPacket {
+    TcpPacket {
+        source_port: 40000,
+        dest_port,
+        sequence: 0x1234_5678,
+        acknowledgment: 0,
+        flags: TcpFlags::syn(),
+        window: 1024,
+        urgent_pointer: 0,
+        options: Vec::new(),
+        payload: Vec::new(),
+    }
+}
+
+fn builder() -> PacketBuilder {
+    PacketBuilder::new()
+        .source(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        .destination(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+}
+
+fn bench_crafting(c: &mut Criterion) {
+    let builder = builder();
+    let probe = syn(443);
+    let mut group = c.benchmark_group("craft_syn");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("vec", |b| b.iter(|| black_box(builder.build_tcp(black_box(&probe)).unwrap())));
+
+    let mut buffer = [0u8; 1500];
+    group.bench_function("reused_buffer", |b| {
+        b.iter(|| black_box(builder.build_tcp_into(black_box(&probe), &mut buffer).unwrap()))
+    });
+
+    let pool = PacketBufferPool::new(1500, 64);
+    group.bench_function("pooled", |b| {
+        b.iter(|| black_box(builder.build_tcp_pooled(black_box(&probe), &pool).unwrap()))
+    });
+
+    group.finish();
+}
+
+fn bench_parsing(c: &mut Criterion) {
+    let mut reply = syn(40000);
+    reply.source_port = 443;
+    reply.flags = TcpFlags::syn_ack();
+    reply.payload = vec![0; 32];
+    let packet = PacketBuilder::new()
+        .source(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+        .destination(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        .build_tcp(&reply)
+        .unwrap();
+    let parser = PacketParser::new(false);
+
+    let mut group = c.benchmark_group("parse_syn_ack");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("owned", |b| b.iter(|| black_box(parser.parse(black_box(&packet)).unwrap())));
+    group.bench_function("borrowed", |b| {
+        b.iter(|| black_box(parser.parse_ref(black_box(&packet)).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_crafting, bench_parsing);
+criterion_main!(benches);
@@ -83,6 +83,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .duration_since(UNIX_EPOCH)?
                 .as_micros() as u64 + (i as u64 * 100000),
             df_flag: true,
+            tcp_timestamp: None,
         };
         passive_analyzer.add_observation(observation);
     }
@@ -162,7 +163,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let engine = OsFingerprintEngine::new();
     
-    match engine.fingerprint(target, port).await {
+    match engine.fingerprint(target, port, None, false).await {
         Ok(fingerprint) => {
             println!("   ✓ Complete Fingerprint Results:");
             println!("     Target: {}", fingerprint.target);
@@ -3,7 +3,7 @@
 /// This example demonstrates the distributed scanning capabilities including
 /// job scheduling, agent management, and result aggregation.
 
-use nrmap::{ScanScheduler, distributed::{DistributedConfig, DistributedScanner}};
+use nrmap::{ScanScheduler, distributed::{DistributedConfig, DistributedScanner, PreemptionPolicy}};
 use std::net::{IpAddr, Ipv4Addr};
 
 #[tokio::main]
@@ -21,6 +21,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         max_agents: 5,
         job_timeout_seconds: 3600,
         result_retention_hours: 24,
+        preemption: PreemptionPolicy::Queue,
+        api_keys: Vec::new(),
     };
     
     let mut scanner = DistributedScanner::new(config)?;
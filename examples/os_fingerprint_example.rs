@@ -47,7 +47,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Collecting fingerprints...");
     
     // This uses framework implementations
-    let fingerprint = engine.fingerprint(target, port).await?;
+    let fingerprint = engine.fingerprint(target, port, None, false).await?;
     
     println!("  Detection time: {} ms", fingerprint.detection_time_ms);
     
@@ -14,6 +14,7 @@ try:
         PyScanner as Scanner,
         PyHostStatus as HostStatus,
         PyScanResult as ScanResult,
+        PyScanStream as ScanStream,
         
         # Phase 2: Detection Engine
         PyDetectionEngine as DetectionEngine,
@@ -26,6 +27,11 @@ try:
         # Phase 4: Reporting
         PyReportEngine as ReportEngine,
         PyReportFormat as ReportFormat,
+        
+        # Phase 5: Distributed Scanning and Policies
+        PyDistributedScanner as DistributedScanner,
+        PyScanAgent as ScanAgent,
+        PyPolicy as Policy,
     )
 except ImportError as e:
     raise ImportError(
@@ -47,12 +53,16 @@ __all__ = [
     "Scanner",
     "HostStatus",
     "ScanResult",
+    "ScanStream",
     "DetectionEngine",
     "ServiceInfo",
     "OsFingerprintEngine",
     "OsMatchResult",
     "ReportEngine",
     "ReportFormat",
+    "DistributedScanner",
+    "ScanAgent",
+    "Policy",
     
     # High-level API
     "quick_scan",
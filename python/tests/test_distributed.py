@@ -0,0 +1,42 @@
+"""
+Tests for Distributed Scanning and Policy bindings
+"""
+
+import pytest
+from nrmap import DistributedScanner, ScanAgent, Policy
+
+@pytest.mark.asyncio
+async def test_run_job():
+    """Test a job run end-to-end on an in-process agent"""
+    distributed = DistributedScanner(max_agents=2)
+    await distributed.register_agent(ScanAgent("agent-1"))
+
+    job_id = await distributed.submit_job(["127.0.0.1"], [22, 80])
+    assert await distributed.job_status(job_id) == "Assigned"
+    assert (await distributed.get_job(job_id))["assigned_agent"] == "agent-1"
+
+    await distributed.run_job(job_id, ["tcp"])
+    assert await distributed.job_status(job_id) == "Completed"
+    results = await distributed.get_results(job_id)
+    assert results["total_targets"] == 1
+    assert results["agent_count"] == 1
+
+@pytest.mark.asyncio
+async def test_unknown_job():
+    """Test lookups of a job that was never submitted"""
+    distributed = DistributedScanner()
+    assert await distributed.job_status("job_missing") is None
+    assert await distributed.get_results("job_missing") is None
+    with pytest.raises(ValueError):
+        await distributed.run_job("job_missing")
+
+def test_policy_evaluate(tmp_path):
+    """Test Policy.evaluate"""
+    path = tmp_path / "policy.toml"
+    path.write_text('[[rules]]\nname = "no-telnet"\ncheck = "forbidden_ports"\nports = [23]\n')
+    policy = Policy(str(path))
+    assert policy.rules == ["no-telnet"]
+
+    report = policy.evaluate([])
+    assert report["passed"]
+    assert report["violations"] == 0
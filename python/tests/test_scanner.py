@@ -37,6 +37,32 @@ async def test_scanner_scan():
     assert isinstance(result["scan_duration_ms"], int)
     assert isinstance(result["tcp_results"], list)
 
+@pytest.mark.asyncio
+async def test_scanner_stream():
+    """Test Scanner.stream async iteration"""
+    scanner = Scanner()
+    targets = []
+    async for result in scanner.stream(["127.0.0.1"], [22, 80], ["tcp"]):
+        targets.append(result["target"])
+        assert isinstance(result["tcp_results"], list)
+    assert targets == ["127.0.0.1"]
+
+@pytest.mark.asyncio
+async def test_scanner_scan_many_callback():
+    """Test Scanner.scan_many per-host callback"""
+    scanner = Scanner()
+    seen = []
+    results = await scanner.scan_many(["127.0.0.1"], [22], on_result=lambda r: seen.append(r["target"]))
+    assert seen == ["127.0.0.1"]
+    assert len(results) == 1
+
+def test_scanner_cancel():
+    """Test Scanner.cancel"""
+    scanner = Scanner()
+    assert not scanner.is_cancelled()
+    scanner.cancel()
+    assert scanner.is_cancelled()
+
 def test_scanner_repr():
     """Test Scanner __repr__"""
     scanner = Scanner()
@@ -0,0 +1,154 @@
+//! Blocking API for applications that do not run tokio
+//!
+//! The scanners are async and tokio-based. These wrappers own a private
+//! current-thread runtime and expose plain synchronous calls, so connect
+//! scans can be embedded in threaded or other-runtime applications. Report
+//! processing needs no wrapper: [`ReportBuilder`](crate::ReportBuilder) and
+//! [`ReportEngine::generate`](crate::ReportEngine::generate) /
+//! [`ReportEngine::write_to`](crate::ReportEngine::write_to) are already
+//! synchronous.
+//!
+//! The wrappers must not be called from inside a tokio runtime; use the async
+//! API there instead.
+
+use crate::config::{ScannerConfig, TcpConnectConfig};
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::tcp_connect::{TcpConnectResult, TcpConnectScanner};
+use crate::scanner::{CompleteScanResult, ScanType, Scanner};
+use std::future::Future;
+use std::net::IpAddr;
+use tokio::runtime::Runtime;
+
+/// Private runtime driving the async scanners
+struct BlockingRuntime {
+    runtime: Option<Runtime>,
+}
+
+impl BlockingRuntime {
+    fn new() -> ScanResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ScanError::scanner_error(format!("Failed to start scan runtime: {}", e)))?;
+        Ok(Self { runtime: Some(runtime) })
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> ScanResult<F::Output> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(ScanError::scanner_error(
+                "Blocking scan API called from inside a tokio runtime; use the async API instead",
+            ));
+        }
+        let runtime = self.runtime.as_ref().expect("runtime is only taken on drop");
+        Ok(runtime.block_on(future))
+    }
+}
+
+impl Drop for BlockingRuntime {
+    fn drop(&mut self) {
+        // A plain drop panics if the wrapper is dropped inside async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Synchronous TCP connect scanner
+pub struct BlockingTcpConnectScanner {
+    runtime: BlockingRuntime,
+    scanner: TcpConnectScanner,
+}
+
+impl BlockingTcpConnectScanner {
+    /// Create a scanner with its own runtime
+    pub fn new(config: TcpConnectConfig) -> ScanResult<Self> {
+        Ok(Self {
+            runtime: BlockingRuntime::new()?,
+            scanner: TcpConnectScanner::new(config),
+        })
+    }
+
+    /// Scan a single port, blocking until it completes
+    pub fn scan_port(&self, target: IpAddr, port: u16) -> ScanResult<TcpConnectResult> {
+        self.runtime.block_on(self.scanner.scan_port(target, port))?
+    }
+
+    /// Scan several ports with up to `max_concurrent` in flight
+    pub fn scan_ports(&self, target: IpAddr, ports: Vec<u16>, max_concurrent: usize) -> ScanResult<Vec<TcpConnectResult>> {
+        self.runtime.block_on(self.scanner.scan_ports(target, ports, max_concurrent))?
+    }
+}
+
+/// Synchronous front end to [`Scanner`]
+pub struct BlockingScanner {
+    runtime: BlockingRuntime,
+    scanner: Scanner,
+}
+
+impl BlockingScanner {
+    /// Create a scanner from configuration
+    pub fn new(config: ScannerConfig) -> ScanResult<Self> {
+        Self::from_scanner(Scanner::new(config))
+    }
+
+    /// Wrap an already configured scanner
+    pub fn from_scanner(scanner: Scanner) -> ScanResult<Self> {
+        Ok(Self {
+            runtime: BlockingRuntime::new()?,
+            scanner,
+        })
+    }
+
+    /// The wrapped scanner
+    pub fn scanner(&self) -> &Scanner {
+        &self.scanner
+    }
+
+    /// Scan one host, blocking until it completes
+    pub fn scan(&self, target: IpAddr, ports: Vec<u16>, scan_types: Vec<ScanType>) -> ScanResult<CompleteScanResult> {
+        self.runtime.block_on(self.scanner.scan(target, ports, scan_types))?
+    }
+
+    /// Scan several hosts, blocking until all complete
+    pub fn scan_multiple(
+        &self,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+    ) -> ScanResult<Vec<CompleteScanResult>> {
+        self.runtime.block_on(self.scanner.scan_multiple(targets, ports, scan_types))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::tcp_connect::PortStatus;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    fn config() -> TcpConnectConfig {
+        TcpConnectConfig {
+            enabled: true,
+            timeout_ms: 1000,
+            retries: 0,
+            retry_delay_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_blocking_connect_scan() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let scanner = BlockingTcpConnectScanner::new(config()).unwrap();
+        let results = scanner.scan_ports(IpAddr::V4(Ipv4Addr::LOCALHOST), vec![port], 4).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, PortStatus::Open);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_rejected_inside_runtime() {
+        let scanner = BlockingTcpConnectScanner::new(config()).unwrap();
+        assert!(scanner.scan_port(IpAddr::V4(Ipv4Addr::LOCALHOST), 9).is_err());
+    }
+}
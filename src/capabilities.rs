@@ -0,0 +1,131 @@
+//! Support matrix of the running build and platform
+//!
+//! [`capabilities()`] reports what this binary can do where it runs: raw
+//! sockets, IPv6, live packet capture, the distributed subsystem and the
+//! Python bindings, plus the scan types that follow from them. `nrmap doctor`
+//! prints it and scan agents advertise it to the scheduler, so callers check
+//! one structure instead of probing sockets or feature flags themselves.
+
+use crate::scanner::privileges::PrivilegeStatus;
+use crate::scanner::ScanType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{Ipv6Addr, UdpSocket};
+use std::sync::OnceLock;
+
+/// What the current build and platform support
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: String,
+    /// Target OS and architecture, e.g. "linux" and "x86_64"
+    pub os: String,
+    pub arch: String,
+    /// Raw sockets can be opened (root, CAP_NET_RAW or Administrator)
+    pub raw_sockets: bool,
+    /// Unprivileged ICMP "ping" sockets can be opened
+    pub unprivileged_icmp: bool,
+    /// An IPv6 stack is available
+    pub ipv6: bool,
+    /// Frames can be captured from an interface (SYN scans, passive
+    /// fingerprinting); pcapng export works regardless
+    pub packet_capture: bool,
+    /// The distributed scheduler and agents are built in
+    pub distributed: bool,
+    /// Built with the `python` feature
+    pub python: bool,
+    /// Scan types that can run with the above
+    pub scan_types: Vec<ScanType>,
+}
+
+impl Capabilities {
+    /// Probe the platform, with privileges as detected at startup
+    pub fn detect() -> Self {
+        Self::with_privileges(PrivilegeStatus::detect(), ipv6_available(), interfaces_available())
+    }
+
+    fn with_privileges(privileges: PrivilegeStatus, ipv6: bool, interfaces: bool) -> Self {
+        let all = [ScanType::TcpConnect, ScanType::TcpSyn, ScanType::Udp];
+        Self {
+            version: crate::VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            raw_sockets: privileges.raw_sockets,
+            unprivileged_icmp: privileges.unprivileged_icmp,
+            ipv6,
+            // Capture channels need the same privilege as raw sockets
+            packet_capture: privileges.raw_sockets && interfaces,
+            distributed: true,
+            python: cfg!(feature = "python"),
+            scan_types: all
+                .into_iter()
+                .filter(|&scan_type| privileges.raw_sockets || !PrivilegeStatus::requires_raw(scan_type))
+                .collect(),
+        }
+    }
+
+    /// Whether `scan_type` can run
+    pub fn supports(&self, scan_type: ScanType) -> bool {
+        self.scan_types.contains(&scan_type)
+    }
+
+    /// Capability names paired with whether they are available
+    pub fn features(&self) -> [(&'static str, bool); 6] {
+        [
+            ("raw_sockets", self.raw_sockets),
+            ("unprivileged_icmp", self.unprivileged_icmp),
+            ("ipv6", self.ipv6),
+            ("packet_capture", self.packet_capture),
+            ("distributed", self.distributed),
+            ("python", self.python),
+        ]
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {} ({}/{})", crate::NAME, self.version, self.os, self.arch)?;
+        for (name, available) in self.features() {
+            writeln!(f, "  {:<18} {}", name, if available { "yes" } else { "no" })?;
+        }
+        let scan_types: Vec<String> = self.scan_types.iter().map(|t| format!("{:?}", t)).collect();
+        write!(f, "  {:<18} {}", "scan_types", scan_types.join(", "))
+    }
+}
+
+/// Capabilities of this process, detected once
+pub fn capabilities() -> &'static Capabilities {
+    static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+    CAPABILITIES.get_or_init(Capabilities::detect)
+}
+
+fn ipv6_available() -> bool {
+    UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_ok()
+}
+
+fn interfaces_available() -> bool {
+    pnet::datalink::interfaces().iter().any(|iface| iface.is_up())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_follow_privileges() {
+        let unprivileged = PrivilegeStatus {
+            raw_sockets: false,
+            unprivileged_icmp: true,
+        };
+        let caps = Capabilities::with_privileges(unprivileged, true, true);
+        assert!(!caps.packet_capture);
+        assert!(!caps.supports(ScanType::TcpSyn));
+        assert_eq!(caps.scan_types, vec![ScanType::TcpConnect, ScanType::Udp]);
+
+        let caps = Capabilities::with_privileges(PrivilegeStatus::assume_privileged(), false, true);
+        assert!(caps.packet_capture && !caps.ipv6);
+        assert!(caps.supports(ScanType::TcpSyn));
+
+        let json = serde_json::to_string(&caps).unwrap();
+        assert_eq!(serde_json::from_str::<Capabilities>(&json).unwrap(), caps);
+    }
+}
@@ -0,0 +1,84 @@
+//! Confirmation before large scans
+//!
+//! Runs whose expanded target set exceeds `scanner.confirm_targets_above`
+//! print their planned scope and ask before sending anything. Non-interactive
+//! runs must pass `--yes` instead, so a typo'd CIDR in a cron job fails fast
+//! rather than scanning a /8.
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::targets::ScanScope;
+use std::io::{BufRead, Write};
+
+/// Ask whether `scope` may proceed
+///
+/// Small scopes and `assume_yes` pass without prompting. Otherwise the scope
+/// is written to `out` and a `y`/`yes` answer is read from `input`; when the
+/// session is not interactive the scan is refused outright.
+pub fn confirm_scope(
+    scope: &ScanScope,
+    threshold: usize,
+    assume_yes: bool,
+    interactive: bool,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> ScanResult<()> {
+    if threshold == 0 || scope.targets <= threshold || assume_yes {
+        return Ok(());
+    }
+
+    if !interactive {
+        return Err(ScanError::validation_error(
+            "targets",
+            format!(
+                "{} targets exceed the confirmation threshold of {}; pass --yes to scan {}",
+                scope.targets, threshold, scope
+            ),
+        ));
+    }
+
+    let io_error = |e: std::io::Error| ScanError::scanner_error(format!("Failed to confirm scan scope: {}", e));
+    writeln!(out, "About to scan {} targets (threshold {}):", scope.targets, threshold).map_err(io_error)?;
+    writeln!(out, "  {}", scope).map_err(io_error)?;
+    write!(out, "Proceed? [y/N] ").map_err(io_error)?;
+    out.flush().map_err(io_error)?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer).map_err(io_error)?;
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(ScanError::validation_error("targets", "Scan aborted by user")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::scanner::ScanType;
+    use std::io::Cursor;
+
+    fn scope(targets: usize) -> ScanScope {
+        ScanScope::new(targets, 100, &[ScanType::TcpConnect], &AppConfig::default().scanner)
+    }
+
+    #[test]
+    fn test_confirm_scope() {
+        let mut out = Vec::new();
+        let mut no_input = Cursor::new(Vec::new());
+
+        // Under the threshold, or with --yes, nothing is asked
+        assert!(confirm_scope(&scope(4096), 4096, false, false, &mut no_input, &mut out).is_ok());
+        assert!(confirm_scope(&scope(65536), 4096, true, false, &mut no_input, &mut out).is_ok());
+        assert!(out.is_empty());
+
+        // Non-interactive runs are refused
+        assert!(confirm_scope(&scope(65536), 4096, false, false, &mut no_input, &mut out).is_err());
+
+        let mut yes = Cursor::new(b"Y\n".to_vec());
+        assert!(confirm_scope(&scope(65536), 4096, false, true, &mut yes, &mut out).is_ok());
+        assert!(String::from_utf8_lossy(&out).contains("65536 hosts x 100 ports"));
+
+        let mut no = Cursor::new(b"\n".to_vec());
+        assert!(confirm_scope(&scope(65536), 4096, false, true, &mut no, &mut out).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Exit codes reflecting what a scan found
+//!
+//! CI jobs and wrapper scripts branch on the exit status rather than parse
+//! reports. A run ends in one [`ExitOutcome`], the most serious that applies:
+//! a runtime error, a violated `--policy` or `--allowed-ports`, targets that
+//! could not be reached, open ports found, or a clean run. Each outcome maps
+//! to an exit code through an [`ExitCodePolicy`]; the default is
+//!
+//! | outcome       | code |
+//! |---------------|------|
+//! | `success`     | 0    |
+//! | `error`       | 1    |
+//! | `policy`      | 2    |
+//! | `unreachable` | 3    |
+//! | `findings`    | 4    |
+//!
+//! so 0 always means a clean run with nothing found, and
+//! `--exit-code-policy` overrides single codes, e.g.
+//! `findings=0,unreachable=0` to only fail on errors and violations.
+
+use crate::cli::SummaryLine;
+use crate::error::{ScanError, ScanResult};
+use std::fmt;
+use std::str::FromStr;
+
+/// How a run ended, most serious first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitOutcome {
+    /// The run failed
+    Error,
+    /// A `--policy` rule or `--allowed-ports` was violated
+    PolicyViolation,
+    /// Some targets were down or got no result
+    Unreachable,
+    /// Open ports were found
+    Findings,
+    /// Nothing to report
+    Success,
+}
+
+impl ExitOutcome {
+    /// Outcome of a run that ended with `error` after scanning `summary`
+    /// (`None` for commands that do not scan)
+    pub fn of(error: Option<&ScanError>, summary: Option<&SummaryLine>) -> Self {
+        match error {
+            Some(ScanError::PolicyViolation { .. }) => ExitOutcome::PolicyViolation,
+            Some(_) => ExitOutcome::Error,
+            None => match summary {
+                Some(summary) if summary.down + summary.errors > 0 => ExitOutcome::Unreachable,
+                Some(summary) if summary.open_ports > 0 => ExitOutcome::Findings,
+                _ => ExitOutcome::Success,
+            },
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            ExitOutcome::Error => "error",
+            ExitOutcome::PolicyViolation => "policy",
+            ExitOutcome::Unreachable => "unreachable",
+            ExitOutcome::Findings => "findings",
+            ExitOutcome::Success => "success",
+        }
+    }
+}
+
+impl fmt::Display for ExitOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// Exit code for each outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitCodePolicy {
+    pub success: u8,
+    pub findings: u8,
+    pub error: u8,
+    pub policy: u8,
+    pub unreachable: u8,
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> Self {
+        Self {
+            success: 0,
+            error: 1,
+            policy: 2,
+            unreachable: 3,
+            findings: 4,
+        }
+    }
+}
+
+impl ExitCodePolicy {
+    pub fn code(&self, outcome: ExitOutcome) -> i32 {
+        i32::from(match outcome {
+            ExitOutcome::Success => self.success,
+            ExitOutcome::Findings => self.findings,
+            ExitOutcome::Error => self.error,
+            ExitOutcome::PolicyViolation => self.policy,
+            ExitOutcome::Unreachable => self.unreachable,
+        })
+    }
+}
+
+impl FromStr for ExitCodePolicy {
+    type Err = ScanError;
+
+    /// `OUTCOME=CODE` pairs over the default codes
+    fn from_str(s: &str) -> ScanResult<Self> {
+        let invalid = |message: String| ScanError::validation_error("exit_code_policy", message);
+        let mut policy = Self::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, code) = item
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("Expected OUTCOME=CODE, got '{}'", item)))?;
+            let code: u8 = code
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("Invalid exit code in '{}' (expected 0-255)", item)))?;
+            let slot = match key.trim() {
+                "success" => &mut policy.success,
+                "findings" => &mut policy.findings,
+                "error" => &mut policy.error,
+                "policy" => &mut policy.policy,
+                "unreachable" => &mut policy.unreachable,
+                other => {
+                    return Err(invalid(format!(
+                        "Unknown outcome '{}' (expected success, findings, error, policy or unreachable)",
+                        other
+                    )))
+                }
+            };
+            *slot = code;
+        }
+        // A failed run must never look like a clean one
+        if policy.error == 0 {
+            return Err(invalid("error must map to a non-zero exit code".to_string()));
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let mut summary = SummaryLine::new(2);
+        summary.errors = 0;
+        summary.up = 2;
+        assert_eq!(ExitOutcome::of(None, Some(&summary)), ExitOutcome::Success);
+        summary.open_ports = 3;
+        assert_eq!(ExitOutcome::of(None, Some(&summary)), ExitOutcome::Findings);
+        summary.down = 1;
+        assert_eq!(ExitOutcome::of(None, Some(&summary)), ExitOutcome::Unreachable);
+
+        let violated = ScanError::policy_violation("1 policy rule(s) failed");
+        assert_eq!(ExitOutcome::of(Some(&violated), Some(&summary)), ExitOutcome::PolicyViolation);
+        // A policy file that cannot be read is an error, not a violation
+        let unreadable = ScanError::validation_error("policy", "Failed to read policy.toml");
+        assert_eq!(ExitOutcome::of(Some(&unreadable), None), ExitOutcome::Error);
+        let failed = ScanError::scanner_error("Scan cancelled, results are partial");
+        assert_eq!(ExitOutcome::of(Some(&failed), None), ExitOutcome::Error);
+
+        let default = ExitCodePolicy::default();
+        assert_eq!(default.code(ExitOutcome::PolicyViolation), 2);
+        assert_eq!(default.code(ExitOutcome::Unreachable), 3);
+        assert_eq!(default.code(ExitOutcome::Findings), 4);
+        let policy: ExitCodePolicy = "findings=0, unreachable=0".parse().unwrap();
+        assert_eq!(policy.code(ExitOutcome::Findings), 0);
+        assert_eq!(policy.code(ExitOutcome::Unreachable), 0);
+        assert_eq!(policy.code(ExitOutcome::Error), 1);
+
+        assert!("error=0".parse::<ExitCodePolicy>().is_err());
+        assert!("findings=256".parse::<ExitCodePolicy>().is_err());
+        assert!("warnings=5".parse::<ExitCodePolicy>().is_err());
+    }
+}
@@ -5,9 +5,17 @@
 
 pub mod profiles;
 pub mod output;
+pub mod confirm;
+pub mod progress;
+pub mod summary;
+pub mod exit_code;
 
-pub use profiles::{ScanProfile, PortSpec, TimingProfile};
+pub use profiles::{ScanProfile, PortSpec, TimingProfile, ProfileDefinition, ProfileRegistry, ProfileSource};
 pub use output::{OutputFormatter, OutputFormat, FormattedOutput};
+pub use confirm::confirm_scope;
+pub use progress::ProgressBarDisplay;
+pub use summary::{RunSummary, SummaryLine};
+pub use exit_code::{ExitCodePolicy, ExitOutcome};
 
 use crate::error::ScanResult;
 use tracing::info;
@@ -1,9 +1,18 @@
 /// CLI scanning profiles
 /// 
 /// Predefined scanning profiles for common use cases, similar to Nmap profiles.
+/// Users add their own in `[profiles.NAME]` sections of config.toml or as
+/// `NAME.toml` files in a `profiles.d/` directory; a [`ProfileRegistry`]
+/// merges them with the built-ins.
 
+use crate::error::{ScanError, ScanResult};
+use crate::report::ReportFormat;
 use crate::scanner::ScanType;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::debug;
 
 /// Scan profile definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +23,9 @@ pub struct ScanProfile {
     pub scan_types: Vec<ScanType>,
     pub timing: TimingProfile,
     pub options: ProfileOptions,
+    /// Report format printed to stdout when no `--output` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<ReportFormat>,
 }
 
 /// Port specification
@@ -27,8 +39,33 @@ pub enum PortSpec {
     Preset(String),
 }
 
+impl PortSpec {
+    /// Expand to the list of ports to scan
+    pub fn resolve(&self) -> ScanResult<Vec<u16>> {
+        match self {
+            PortSpec::Ports(ports) => Ok(ports.clone()),
+            PortSpec::Range(start, end) if start <= end => Ok((*start..=*end).collect()),
+            PortSpec::Range(start, end) => Err(ScanError::validation_error(
+                "ports",
+                format!("Invalid port range {}-{}", start, end),
+            )),
+            PortSpec::Preset(name) => crate::parse_port_preset(name),
+        }
+    }
+}
+
+impl std::fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortSpec::Ports(ports) => write!(f, "{}", crate::scanner::port_presets::format_ports(ports)),
+            PortSpec::Range(start, end) => write!(f, "{}-{}", start, end),
+            PortSpec::Preset(name) => write!(f, "preset {}", name),
+        }
+    }
+}
+
 /// Timing profile
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimingProfile {
     /// Paranoid (T0): Very slow, evades IDS
     Paranoid,
@@ -44,6 +81,36 @@ pub enum TimingProfile {
     Insane,
 }
 
+impl TimingProfile {
+    /// Probes-per-second cap the template implies; the faster ones only
+    /// change timeouts and concurrency
+    pub fn max_rate(&self) -> Option<u32> {
+        match self {
+            TimingProfile::Paranoid => Some(1),
+            TimingProfile::Sneaky => Some(10),
+            TimingProfile::Polite => Some(100),
+            TimingProfile::Normal | TimingProfile::Aggressive | TimingProfile::Insane => None,
+        }
+    }
+}
+
+/// Parses template names or their Nmap shorthands (`T0`-`T5`)
+impl FromStr for TimingProfile {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "paranoid" | "t0" => Ok(TimingProfile::Paranoid),
+            "sneaky" | "t1" => Ok(TimingProfile::Sneaky),
+            "polite" | "t2" => Ok(TimingProfile::Polite),
+            "normal" | "t3" => Ok(TimingProfile::Normal),
+            "aggressive" | "t4" => Ok(TimingProfile::Aggressive),
+            "insane" | "t5" => Ok(TimingProfile::Insane),
+            _ => Err(ScanError::validation_error("timing", format!("Unknown timing profile: {}", s))),
+        }
+    }
+}
+
 /// Profile options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProfileOptions {
@@ -86,6 +153,7 @@ impl ScanProfile {
                 max_concurrent: 1000,
                 timeout_ms: 2000,
             },
+            output_format: None,
         }
     }
 
@@ -104,6 +172,7 @@ impl ScanProfile {
                 max_concurrent: 500,
                 timeout_ms: 3000,
             },
+            output_format: None,
         }
     }
 
@@ -122,6 +191,7 @@ impl ScanProfile {
                 max_concurrent: 100,
                 timeout_ms: 5000,
             },
+            output_format: None,
         }
     }
 
@@ -140,6 +210,7 @@ impl ScanProfile {
                 max_concurrent: 100,
                 timeout_ms: 5000,
             },
+            output_format: None,
         }
     }
 
@@ -158,6 +229,7 @@ impl ScanProfile {
                 max_concurrent: 10,
                 timeout_ms: 10000,
             },
+            output_format: None,
         }
     }
 
@@ -176,6 +248,7 @@ impl ScanProfile {
                 max_concurrent: 1000,
                 timeout_ms: 5000,
             },
+            output_format: None,
         }
     }
 
@@ -194,6 +267,7 @@ impl ScanProfile {
                 max_concurrent: 100,
                 timeout_ms: 5000,
             },
+            output_format: None,
         }
     }
 
@@ -212,6 +286,7 @@ impl ScanProfile {
                 max_concurrent: 50,
                 timeout_ms: 5000,
             },
+            output_format: None,
         }
     }
 
@@ -230,6 +305,7 @@ impl ScanProfile {
                 max_concurrent: 1000,
                 timeout_ms: 2000,
             },
+            output_format: None,
         }
     }
 
@@ -258,16 +334,262 @@ impl std::fmt::Display for ScanProfile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Profile: {}", self.name)?;
         writeln!(f, "  Description: {}", self.description)?;
-        writeln!(f, "  Ports: {:?}", self.ports)?;
+        writeln!(f, "  Ports: {}", self.ports)?;
         writeln!(f, "  Scan Types: {:?}", self.scan_types)?;
         writeln!(f, "  Timing: {:?}", self.timing)?;
         writeln!(f, "  Service Detection: {}", self.options.enable_service_detection)?;
         writeln!(f, "  OS Detection: {}", self.options.enable_os_detection)?;
         writeln!(f, "  Banner Grabbing: {}", self.options.enable_banner_grabbing)?;
+        writeln!(f, "  Max Concurrent: {}", self.options.max_concurrent)?;
+        writeln!(f, "  Timeout: {}ms", self.options.timeout_ms)?;
+        if let Some(format) = self.output_format {
+            writeln!(f, "  Output Format: {}", format)?;
+        }
         Ok(())
     }
 }
 
+/// A user-defined profile as written in config.toml or `profiles.d/`
+///
+/// Unset fields come from the profile named in `extends`, or from the
+/// `default` profile.
+///
+/// ```toml
+/// [profiles.dmz]
+/// description = "Internet-facing services"
+/// extends = "fast"
+/// ports = "22,80,443,8000-8100"
+/// scan_types = ["tcp", "udp"]
+/// timing = "polite"
+/// os_detection = true
+/// output_format = "table"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileDefinition {
+    pub description: Option<String>,
+    /// Built-in or user profile this one starts from
+    pub extends: Option<String>,
+    /// Ports and ranges, e.g. "22,80,8000-8100"
+    pub ports: Option<String>,
+    /// Port preset name (common, web, mail, database, all)
+    pub preset: Option<String>,
+    /// Scan types: tcp, syn, udp
+    pub scan_types: Option<Vec<String>>,
+    /// paranoid, sneaky, polite, normal, aggressive, insane (or T0-T5)
+    pub timing: Option<String>,
+    pub service_detection: Option<bool>,
+    pub os_detection: Option<bool>,
+    pub banner_grabbing: Option<bool>,
+    pub max_concurrent: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    /// json, json-pretty, yaml, html or table
+    pub output_format: Option<String>,
+}
+
+impl ProfileDefinition {
+    /// Apply this definition on top of `base`
+    pub fn build(&self, name: &str, base: ScanProfile) -> ScanResult<ScanProfile> {
+        let invalid = |e: ScanError| ScanError::validation_error("profile", format!("Profile '{}': {}", name, e));
+        let mut profile = ScanProfile {
+            name: name.to_string(),
+            description: self
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("User profile based on {}", base.name)),
+            ..base
+        };
+
+        if self.ports.is_some() && self.preset.is_some() {
+            return Err(invalid(ScanError::validation_error("ports", "set either ports or preset, not both")));
+        }
+        if let Some(ref ports) = self.ports {
+            profile.ports = PortSpec::Ports(crate::parse_port_range(ports).map_err(invalid)?);
+        }
+        if let Some(ref preset) = self.preset {
+            crate::parse_port_preset(preset).map_err(invalid)?;
+            profile.ports = PortSpec::Preset(preset.clone());
+        }
+        if let Some(ref scan_types) = self.scan_types {
+            profile.scan_types = scan_types
+                .iter()
+                .map(|s| s.parse())
+                .collect::<ScanResult<Vec<ScanType>>>()
+                .map_err(invalid)?;
+        }
+        if let Some(ref timing) = self.timing {
+            profile.timing = timing.parse().map_err(invalid)?;
+        }
+        if let Some(ref format) = self.output_format {
+            profile.output_format = Some(format.parse().map_err(invalid)?);
+        }
+
+        let options = &mut profile.options;
+        options.enable_service_detection = self.service_detection.unwrap_or(options.enable_service_detection);
+        options.enable_os_detection = self.os_detection.unwrap_or(options.enable_os_detection);
+        options.enable_banner_grabbing = self.banner_grabbing.unwrap_or(options.enable_banner_grabbing);
+        options.max_concurrent = self.max_concurrent.unwrap_or(options.max_concurrent);
+        options.timeout_ms = self.timeout_ms.unwrap_or(options.timeout_ms);
+        if options.max_concurrent == 0 || options.timeout_ms == 0 {
+            return Err(invalid(ScanError::validation_error(
+                "options",
+                "max_concurrent and timeout_ms must be greater than 0",
+            )));
+        }
+        Ok(profile)
+    }
+}
+
+/// Where a profile was defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileSource {
+    BuiltIn,
+    Config,
+    File(PathBuf),
+}
+
+impl std::fmt::Display for ProfileSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileSource::BuiltIn => write!(f, "built-in"),
+            ProfileSource::Config => write!(f, "config"),
+            ProfileSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Built-in profiles plus those defined by the user
+///
+/// A user profile with a built-in's name replaces it; `profiles.d/` files
+/// replace config.toml entries of the same name.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry {
+    user: BTreeMap<String, (ScanProfile, ProfileSource)>,
+}
+
+impl ProfileRegistry {
+    /// Registry knowing only the built-in profiles
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    /// Load user profiles from config definitions and `*.toml` files in `dir`
+    ///
+    /// A missing directory is not an error.
+    pub fn load(definitions: &HashMap<String, ProfileDefinition>, dir: Option<&Path>) -> ScanResult<Self> {
+        let mut pending: BTreeMap<String, (ProfileDefinition, ProfileSource)> = definitions
+            .iter()
+            .map(|(name, definition)| (name.to_lowercase(), (definition.clone(), ProfileSource::Config)))
+            .collect();
+
+        if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+            for (name, definition, path) in read_profile_dir(dir)? {
+                pending.insert(name, (definition, ProfileSource::File(path)));
+            }
+        }
+
+        let mut registry = Self::default();
+        for name in pending.keys() {
+            registry.resolve(name, &pending, &mut Vec::new())?;
+        }
+        debug!("Loaded {} user scan profiles", registry.user.len());
+        Ok(registry)
+    }
+
+    fn resolve(
+        &mut self,
+        name: &str,
+        pending: &BTreeMap<String, (ProfileDefinition, ProfileSource)>,
+        chain: &mut Vec<String>,
+    ) -> ScanResult<ScanProfile> {
+        if let Some((profile, _)) = self.user.get(name) {
+            return Ok(profile.clone());
+        }
+        let Some((definition, source)) = pending.get(name) else {
+            return ScanProfile::by_name(name)
+                .ok_or_else(|| ScanError::validation_error("profile", format!("Unknown profile: {}", name)));
+        };
+        if chain.iter().any(|seen| seen == name) {
+            return Err(ScanError::validation_error(
+                "profile",
+                format!("Profile inheritance cycle: {} -> {}", chain.join(" -> "), name),
+            ));
+        }
+
+        chain.push(name.to_string());
+        let base = match definition.extends.as_deref().map(str::to_lowercase) {
+            // Extending a built-in of the same name refines it instead of looping
+            Some(parent) if parent == name => ScanProfile::by_name(&parent)
+                .ok_or_else(|| ScanError::validation_error("profile", format!("Profile '{}' extends itself", name)))?,
+            Some(parent) => self.resolve(&parent, pending, chain)?,
+            None => ScanProfile::default_profile(),
+        };
+        chain.pop();
+
+        let profile = definition.build(name, base)?;
+        self.user.insert(name.to_string(), (profile.clone(), source.clone()));
+        Ok(profile)
+    }
+
+    /// Look a profile up by name (case-insensitive, built-in aliases included)
+    pub fn get(&self, name: &str) -> ScanResult<ScanProfile> {
+        match self.user.get(&name.to_lowercase()) {
+            Some((profile, _)) => Ok(profile.clone()),
+            None => ScanProfile::by_name(name)
+                .ok_or_else(|| ScanError::validation_error("profile", format!("Unknown profile: {}", name))),
+        }
+    }
+
+    /// Where the profile `name` comes from
+    pub fn source(&self, name: &str) -> Option<ProfileSource> {
+        match self.user.get(&name.to_lowercase()) {
+            Some((_, source)) => Some(source.clone()),
+            None => ScanProfile::by_name(name).map(|_| ProfileSource::BuiltIn),
+        }
+    }
+
+    /// Every available profile, sorted by name
+    pub fn list(&self) -> Vec<(ScanProfile, ProfileSource)> {
+        let mut profiles: BTreeMap<String, (ScanProfile, ProfileSource)> = ScanProfile::list_all()
+            .into_iter()
+            .filter_map(|name| ScanProfile::by_name(&name).map(|p| (name, (p, ProfileSource::BuiltIn))))
+            .collect();
+        profiles.extend(self.user.clone());
+        profiles.into_values().collect()
+    }
+}
+
+/// Parse every `NAME.toml` profile file in `dir`
+fn read_profile_dir(dir: &Path) -> ScanResult<Vec<(String, ProfileDefinition, PathBuf)>> {
+    let io_error = |e: std::io::Error| {
+        ScanError::scanner_error(format!("Failed to read profile directory {}: {}", dir.display(), e))
+    };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io_error)?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                ScanError::scanner_error(format!("Failed to read profile {}: {}", path.display(), e))
+            })?;
+            let definition = toml::from_str(&content).map_err(|e| {
+                ScanError::validation_error("profile", format!("Invalid profile {}: {}", path.display(), e))
+            })?;
+            Ok((name, definition, path))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -303,6 +625,68 @@ mod tests {
         assert!(profiles.contains(&"stealth".to_string()));
     }
 
+    #[test]
+    fn test_user_profiles_extend_and_override() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("dmz.toml"),
+            "extends = \"edge\"\nscan_types = [\"tcp\", \"udp\"]\noutput_format = \"table\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let definitions = HashMap::from([
+            (
+                "edge".to_string(),
+                ProfileDefinition {
+                    extends: Some("fast".to_string()),
+                    ports: Some("22,443,8000-8002".to_string()),
+                    timing: Some("T2".to_string()),
+                    ..ProfileDefinition::default()
+                },
+            ),
+            (
+                "quick".to_string(),
+                ProfileDefinition {
+                    extends: Some("quick".to_string()),
+                    timeout_ms: Some(500),
+                    ..ProfileDefinition::default()
+                },
+            ),
+        ]);
+        let registry = ProfileRegistry::load(&definitions, Some(dir.path())).unwrap();
+
+        let dmz = registry.get("DMZ").unwrap();
+        assert_eq!(dmz.ports.resolve().unwrap(), vec![22, 443, 8000, 8001, 8002]);
+        assert_eq!(dmz.scan_types, vec![ScanType::TcpConnect, ScanType::Udp]);
+        assert_eq!(dmz.timing, TimingProfile::Polite);
+        assert_eq!(dmz.options.max_concurrent, 500);
+        assert_eq!(dmz.output_format, Some(ReportFormat::Table));
+        assert!(matches!(registry.source("dmz"), Some(ProfileSource::File(_))));
+
+        // A user profile replaces the built-in of the same name
+        assert_eq!(registry.get("quick").unwrap().options.timeout_ms, 500);
+        assert_eq!(registry.source("quick"), Some(ProfileSource::Config));
+        assert_eq!(registry.source("stealth"), Some(ProfileSource::BuiltIn));
+        assert_eq!(registry.list().len(), ScanProfile::list_all().len() + 2);
+    }
+
+    #[test]
+    fn test_invalid_user_profiles() {
+        let load = |definition: ProfileDefinition| {
+            ProfileRegistry::load(&HashMap::from([("bad".to_string(), definition)]), None)
+        };
+        assert!(load(ProfileDefinition { scan_types: Some(vec!["icmp".to_string()]), ..Default::default() }).is_err());
+        assert!(load(ProfileDefinition { extends: Some("missing".to_string()), ..Default::default() }).is_err());
+        assert!(load(ProfileDefinition { extends: Some("bad".to_string()), ..Default::default() }).is_err());
+        assert!(load(ProfileDefinition {
+            ports: Some("80".to_string()),
+            preset: Some("web".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
     #[test]
     fn test_profile_display() {
         let profile = ScanProfile::quick();
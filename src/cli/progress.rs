@@ -0,0 +1,59 @@
+//! Terminal progress bar
+//!
+//! Renders the scanner's [`ScanProgress`] snapshots on stderr while a scan
+//! runs: probes done out of the total, hosts finished, current rate and ETA,
+//! and which pass is running when port expansion is on.
+
+use crate::report::time::format_duration_ms;
+use crate::scanner::ScanProgress;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+const TEMPLATE: &str = "{spinner} [{elapsed_precise}] {wide_bar} {pos}/{len} probes {msg}";
+
+/// Progress bar following one scan
+pub struct ProgressBarDisplay {
+    bar: ProgressBar,
+    task: JoinHandle<()>,
+}
+
+impl ProgressBarDisplay {
+    /// Draw progress from `receiver` until [`finish`](Self::finish) is called
+    pub fn start(mut receiver: watch::Receiver<ScanProgress>) -> Self {
+        let style = ProgressStyle::with_template(TEMPLATE).unwrap_or_else(|_| ProgressStyle::default_bar());
+        let bar = ProgressBar::new(0).with_style(style);
+
+        let task_bar = bar.clone();
+        let task = tokio::spawn(async move {
+            while receiver.changed().await.is_ok() {
+                let progress = *receiver.borrow_and_update();
+                task_bar.set_length(progress.ports_total);
+                task_bar.set_position(progress.ports_done);
+                task_bar.set_message(status_message(&progress));
+            }
+        });
+
+        Self { bar, task }
+    }
+
+    /// Stop updating and remove the bar
+    pub fn finish(self) {
+        self.task.abort();
+        self.bar.finish_and_clear();
+    }
+}
+
+fn status_message(progress: &ScanProgress) -> String {
+    let eta = progress
+        .eta
+        .map_or_else(|| "-".to_string(), |eta| format_duration_ms(eta.as_millis() as u64));
+    let pass = match progress.passes {
+        passes if passes > 1 => format!("| pass {}/{} ", progress.pass, passes),
+        _ => String::new(),
+    };
+    format!(
+        "{}| hosts {}/{} | {:.0} pps | ETA {}",
+        pass, progress.targets_done, progress.targets_total, progress.current_pps, eta
+    )
+}
@@ -0,0 +1,211 @@
+//! Machine-parsable exit summary
+//!
+//! Produces a single `NRMAP_SUMMARY key=value ...` line so shell wrappers can
+//! pick up key numbers without parsing JSON. With `--summary-json` the same
+//! numbers, the outcome and the report files are printed as one JSON object
+//! ([`RunSummary`]), the only thing the run writes to stdout.
+
+use crate::error::ScanError;
+use crate::scanner::host_discovery::HostStatus;
+use crate::scanner::CompleteScanResult;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Aggregate numbers for a finished run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SummaryLine {
+    pub hosts: usize,
+    pub up: usize,
+    /// Hosts found down (not counted in the summary line)
+    pub down: usize,
+    pub open_ports: usize,
+    pub errors: usize,
+    pub duration: Duration,
+}
+
+impl SummaryLine {
+    /// Build a summary from scan results
+    /// 
+    /// # Arguments
+    /// * `results` - Results for targets that were scanned successfully
+    /// * `hosts` - Number of targets requested (targets without a result count as errors)
+    /// * `duration` - Wall-clock duration of the run
+    pub fn from_results(results: &[CompleteScanResult], hosts: usize, duration: Duration) -> Self {
+        let mut summary = Self::new(hosts);
+        for result in results {
+            summary.record(result);
+        }
+        summary.with_duration(duration)
+    }
+
+    /// Start a summary for `hosts` targets, none of them scanned yet
+    ///
+    /// Used with [`SummaryLine::record`] when results are not kept in memory.
+    pub fn new(hosts: usize) -> Self {
+        Self {
+            hosts,
+            up: 0,
+            down: 0,
+            open_ports: 0,
+            errors: hosts,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Count one target's result
+    pub fn record(&mut self, result: &CompleteScanResult) {
+        match result.host_status {
+            HostStatus::Up => self.up += 1,
+            HostStatus::Down => self.down += 1,
+            _ => {}
+        }
+        self.open_ports += result.open_port_count();
+        self.errors = self.errors.saturating_sub(1);
+    }
+
+    /// Set the run's wall-clock duration
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+}
+
+impl std::fmt::Display for SummaryLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "NRMAP_SUMMARY hosts={} up={} open_ports={} errors={} duration={}s",
+            self.hosts,
+            self.up,
+            self.open_ports,
+            self.errors,
+            self.duration.as_secs_f64().round() as u64
+        )
+    }
+}
+
+/// Outcome of a run as printed by `--summary-json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunSummary {
+    /// "ok", or "error" when the run failed
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Empty when the run failed before a scanner existed
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub scan_id: String,
+    pub hosts: usize,
+    pub up: usize,
+    pub down: usize,
+    pub open_ports: usize,
+    pub errors: usize,
+    pub duration_ms: u64,
+    /// Files the full report was written to
+    pub reports: Vec<PathBuf>,
+}
+
+impl RunSummary {
+    /// Summarize a run that scanned `counts` (`None` if it never got to
+    /// scanning) and ended with `error`
+    pub fn new(
+        scan_id: String,
+        counts: Option<SummaryLine>,
+        duration: Duration,
+        reports: Vec<PathBuf>,
+        error: Option<&ScanError>,
+    ) -> Self {
+        let counts = counts.unwrap_or(SummaryLine::new(0));
+        Self {
+            status: if error.is_some() { "error" } else { "ok" },
+            error: error.map(ToString::to_string),
+            scan_id,
+            hosts: counts.hosts,
+            up: counts.up,
+            down: counts.down,
+            open_ports: counts.open_ports,
+            errors: counts.errors,
+            duration_ms: duration.as_millis() as u64,
+            reports,
+        }
+    }
+
+    /// Summarize a run that failed with `message` before its command ran
+    pub fn setup_failed(message: String, duration: Duration) -> Self {
+        Self {
+            status: "error",
+            error: Some(message),
+            ..Self::new(String::new(), None, duration, Vec::new(), None)
+        }
+    }
+
+    /// The summary as one line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("summary fields always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::tcp_connect::PortStatus;
+    use crate::scanner::tcp_syn::TcpSynResult;
+    use crate::scanner::ProbeError;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn result(status: HostStatus, open: &[u16]) -> CompleteScanResult {
+        CompleteScanResult {
+            scan_duration_ms: 10,
+            ..CompleteScanResult::with_open_tcp(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), status, open)
+        }
+    }
+
+    #[test]
+    fn test_summary_line_format() {
+        let results = vec![
+            result(HostStatus::Up, &[22, 80]),
+            result(HostStatus::Down, &[]),
+        ];
+        let summary = SummaryLine::from_results(&results, 3, Duration::from_millis(212_600));
+        assert_eq!(
+            summary.to_string(),
+            "NRMAP_SUMMARY hosts=3 up=1 open_ports=2 errors=1 duration=213s"
+        );
+
+        let run = RunSummary::new(
+            "scan-1".to_string(),
+            Some(summary),
+            Duration::from_millis(1500),
+            vec![PathBuf::from("scan.json")],
+            None,
+        );
+        assert_eq!(
+            run.to_json(),
+            r#"{"status":"ok","scan_id":"scan-1","hosts":3,"up":1,"down":1,"open_ports":2,"errors":1,"duration_ms":1500,"reports":["scan.json"]}"#
+        );
+        let failed = RunSummary::new("scan-2".to_string(), None, Duration::ZERO, Vec::new(), Some(&ScanError::scanner_error("boom")));
+        assert_eq!((failed.status, failed.hosts), ("error", 0));
+        assert!(failed.error.unwrap().contains("boom"));
+        let setup = RunSummary::setup_failed("Invalid --output: bad".to_string(), Duration::from_millis(3));
+        assert_eq!(
+            setup.to_json(),
+            r#"{"status":"error","error":"Invalid --output: bad","hosts":0,"up":0,"down":0,"open_ports":0,"errors":0,"duration_ms":3,"reports":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_port_open_to_connect_and_syn_counts_once() {
+        let mut host = result(HostStatus::Up, &[22, 80]);
+        host.syn_results = [22, 443]
+            .iter()
+            .map(|&port| TcpSynResult {
+                status: PortStatus::Open,
+                error: None,
+                ..TcpSynResult::failed(host.target, port, ProbeError::Timeout)
+            })
+            .collect();
+        assert_eq!(host.open_port_count(), 3);
+        let summary = SummaryLine::from_results(&[host], 1, Duration::ZERO);
+        assert_eq!(summary.open_ports, 3);
+    }
+}
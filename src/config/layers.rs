@@ -0,0 +1,251 @@
+//! Layered configuration sources
+//!
+//! Configuration is merged from several sources, each overriding the ones
+//! before it:
+//!
+//! 1. built-in defaults
+//! 2. the system config, `/etc/nrmap/config.toml`
+//! 3. the user config, `~/.config/nrmap/config.toml` (`$XDG_CONFIG_HOME`
+//!    is honored)
+//! 4. the project config, `--config` (default `./config.toml`)
+//! 5. `NRMAP_*` environment variables, with `__` separating sections:
+//!    `NRMAP_SCANNER__MAX_CONCURRENT_SCANS=200`
+//! 6. `--set scanner.max_concurrent_scans=200` overrides
+//!
+//! Missing files are skipped, except a project config named explicitly.
+
+use super::{AppConfig, SecurityConfig};
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// System-wide configuration file
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/nrmap/config.toml";
+
+/// Prefix of environment variables that override configuration keys
+pub const ENV_PREFIX: &str = "NRMAP";
+
+/// The per-user configuration file, if a home directory is known
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("nrmap").join("config.toml"))
+}
+
+/// One configuration source, in precedence order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigLayer {
+    Defaults,
+    /// A TOML file; optional files are skipped when missing
+    File { path: PathBuf, required: bool },
+    /// `NRMAP_*` variables, as name and value
+    Environment(Vec<(String, String)>),
+    /// `key=value` assignments
+    Overrides(Vec<(String, String)>),
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigLayer::Defaults => write!(f, "built-in defaults"),
+            ConfigLayer::File { path, .. } if path.exists() => write!(f, "{}", path.display()),
+            ConfigLayer::File { path, .. } => write!(f, "{} (not found)", path.display()),
+            ConfigLayer::Environment(vars) => {
+                let names: Vec<&str> = vars.iter().map(|(name, _)| name.as_str()).collect();
+                write!(f, "environment: {}", names.join(", "))
+            }
+            ConfigLayer::Overrides(assignments) => {
+                let assignments: Vec<String> = assignments.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                write!(f, "--set {}", assignments.join(" --set "))
+            }
+        }
+    }
+}
+
+/// Ordered configuration sources that merge into one [`AppConfig`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLayers {
+    layers: Vec<ConfigLayer>,
+}
+
+impl Default for ConfigLayers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLayers {
+    /// Built-in defaults only
+    pub fn new() -> Self {
+        Self {
+            layers: vec![ConfigLayer::Defaults],
+        }
+    }
+
+    /// System, user and project config files
+    ///
+    /// `project` is required to exist when `explicit` is set, i.e. when the
+    /// user named it rather than relying on `./config.toml`.
+    pub fn standard(project: impl AsRef<Path>, explicit: bool) -> Self {
+        let mut layers = Self::new().with_file(SYSTEM_CONFIG_PATH, false);
+        if let Some(user) = user_config_path() {
+            layers = layers.with_file(user, false);
+        }
+        layers.with_file(project.as_ref(), explicit)
+    }
+
+    /// Add a TOML file over the current layers
+    pub fn with_file(mut self, path: impl Into<PathBuf>, required: bool) -> Self {
+        self.layers.push(ConfigLayer::File {
+            path: path.into(),
+            required,
+        });
+        self
+    }
+
+    /// Add the `NRMAP_*` entries of `vars` (e.g. `std::env::vars()`)
+    pub fn with_environment(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let prefix = format!("{}_", ENV_PREFIX);
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&prefix) && name.contains("__"))
+            .collect();
+        if !vars.is_empty() {
+            vars.sort();
+            self.layers.push(ConfigLayer::Environment(vars));
+        }
+        self
+    }
+
+    /// Add `key=value` assignments such as `scanner.max_concurrent_scans=200`
+    pub fn with_overrides<S: AsRef<str>>(mut self, assignments: &[S]) -> Result<Self, ConfigError> {
+        let assignments = assignments
+            .iter()
+            .map(|assignment| {
+                let assignment = assignment.as_ref();
+                match assignment.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+                    _ => Err(ConfigError::Message(format!(
+                        "Invalid override '{}': expected KEY=VALUE",
+                        assignment
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !assignments.is_empty() {
+            self.layers.push(ConfigLayer::Overrides(assignments));
+        }
+        Ok(self)
+    }
+
+    /// The layers, lowest precedence first
+    pub fn layers(&self) -> &[ConfigLayer] {
+        &self.layers
+    }
+
+    /// Merge every layer and validate the result
+    pub fn load(&self) -> Result<AppConfig, ConfigError> {
+        let config: AppConfig = self.merge()?.try_deserialize()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Only the merged `[security]` section, for falling back to defaults
+    /// elsewhere without dropping the scope and blackout windows
+    pub fn load_security(&self) -> Result<SecurityConfig, ConfigError> {
+        self.merge()?.get("security")
+    }
+
+    fn merge(&self) -> Result<Config, ConfigError> {
+        let mut builder = Config::builder();
+        for layer in &self.layers {
+            builder = match layer {
+                ConfigLayer::Defaults => builder.add_source(Config::try_from(&AppConfig::default())?),
+                ConfigLayer::File { path, required } => {
+                    debug!("Loading configuration layer: {}", path.display());
+                    builder.add_source(File::from(path.as_path()).format(FileFormat::Toml).required(*required))
+                }
+                ConfigLayer::Environment(vars) => builder.add_source(
+                    Environment::with_prefix(ENV_PREFIX)
+                        .prefix_separator("_")
+                        .separator("__")
+                        .try_parsing(true)
+                        .source(Some(vars.iter().cloned().collect())),
+                ),
+                ConfigLayer::Overrides(assignments) => {
+                    for (key, value) in assignments {
+                        builder = builder.set_override(key.as_str(), value.as_str())?;
+                    }
+                    builder
+                }
+            };
+        }
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_layer_precedence() {
+        let dir = tempdir().unwrap();
+        let user = dir.path().join("user.toml");
+        let project = dir.path().join("project.toml");
+        fs::write(&user, "[scanner]\nmax_concurrent_scans = 300\ndefault_timeout_ms = 1500\n").unwrap();
+        fs::write(&project, "[scanner]\nmax_concurrent_scans = 400\n\n[general]\noperator = \"blue-team\"\n").unwrap();
+
+        let layers = ConfigLayers::new()
+            .with_file(dir.path().join("missing.toml"), false)
+            .with_file(&user, false)
+            .with_file(&project, true)
+            .with_environment([
+                ("NRMAP_SCANNER__MAX_CONCURRENT_SCANS".to_string(), "200".to_string()),
+                ("NRMAP_OPERATOR".to_string(), "ignored".to_string()),
+                ("PATH".to_string(), "/bin".to_string()),
+            ])
+            .with_overrides(&["logging.level=debug"])
+            .unwrap();
+        assert_eq!(layers.layers().len(), 6);
+
+        let config = layers.load().unwrap();
+        assert_eq!(config.scanner.max_concurrent_scans, 200);
+        assert_eq!(config.scanner.default_timeout_ms, 1500);
+        assert_eq!(config.general.operator.as_deref(), Some("blue-team"));
+        assert_eq!(config.logging.level, "debug");
+        // Untouched keys keep their defaults
+        assert_eq!(config.general.app_name, AppConfig::default().general.app_name);
+
+        let config = layers.with_overrides(&["scanner.max_concurrent_scans=250"]).unwrap().load().unwrap();
+        assert_eq!(config.scanner.max_concurrent_scans, 250);
+    }
+
+    #[test]
+    fn test_invalid_layers() {
+        assert!(ConfigLayers::new().with_overrides(&["scanner.max_concurrent_scans"]).is_err());
+        assert!(ConfigLayers::new()
+            .with_file("/nonexistent/nrmap.toml", true)
+            .load()
+            .is_err());
+        // Merged values are validated like a single file
+        let layers = ConfigLayers::new().with_overrides(&["logging.level=loud"]).unwrap();
+        assert!(layers.load().is_err());
+
+        // The security section survives a failure elsewhere, but not its own
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[logging]\nlevel = \"loud\"\n\n[security]\nscope = [\"10.0.0.0/8\"]\n").unwrap();
+        let layers = ConfigLayers::new().with_file(&path, true);
+        assert!(layers.load().is_err());
+        assert_eq!(layers.load_security().unwrap().scope, vec!["10.0.0.0/8".parse().unwrap()]);
+        let layers = ConfigLayers::new().with_overrides(&["security.max_targets=many"]).unwrap();
+        assert!(layers.load_security().is_err());
+    }
+}
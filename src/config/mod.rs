@@ -1,11 +1,26 @@
 /// Configuration module for NrMAP
 /// 
-/// This module handles loading and validating the single configuration file (config.toml)
-/// that controls all aspects of the scanner behavior.
-
+/// This module handles loading and validating the configuration (config.toml)
+/// that controls all aspects of the scanner behavior, merged from system, user
+/// and project files, environment variables and command-line overrides.
+
+mod layers;
+pub mod reload;
+
+pub use layers::{user_config_path, ConfigLayer, ConfigLayers, ENV_PREFIX, SYSTEM_CONFIG_PATH};
+pub use reload::{ConfigWatcher, ReloadOutcome};
+
+use crate::cli::profiles::ProfileDefinition;
+use crate::enrichment::EnrichmentConfig;
+use crate::report::DisplayTimezone;
+use crate::scanner::blackout::BlackoutWindow;
+use crate::scanner::ssh_tunnel::SshTunnelConfig;
+use crate::scanner::targets::IpNetwork;
+use crate::scanner::{ConsensusPolicy, ProbeEvasion, RetryPolicy, ScanOrder, UlimitCheck};
 use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 /// Main application configuration
@@ -17,12 +32,32 @@ pub struct AppConfig {
     pub throttling: ThrottlingConfig,
     pub output: OutputConfig,
     pub security: SecurityConfig,
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+    /// Job scheduling across scan agents (`[distributed]`)
+    #[cfg(not(feature = "no-net"))]
+    #[serde(default)]
+    pub distributed: crate::distributed::DistributedConfig,
+    /// User-defined scan profiles (`[profiles.NAME]`)
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileDefinition>,
+    /// Organization port presets, name to port list (`[presets]`)
+    #[serde(default)]
+    pub presets: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     pub app_name: String,
     pub version: String,
+    /// Person or team running scans, recorded in report metadata
+    /// (falls back to `NRMAP_OPERATOR`, then the login name)
+    #[serde(default)]
+    pub operator: Option<String>,
+    /// Directory of `NAME.toml` scan profiles; relative paths are resolved
+    /// against the config file's directory (default: `profiles.d`)
+    #[serde(default)]
+    pub profiles_dir: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,13 +68,117 @@ pub struct LoggingConfig {
     pub log_dir: String,
     pub max_file_size: usize,
     pub max_files: usize,
+    /// Write console log output to stderr, keeping stdout for reports
+    #[serde(default)]
+    pub stderr: bool,
+    /// Also write each scan's events to `<dir>/<scan_id>.ndjson`
+    #[serde(default)]
+    pub scan_log_dir: Option<String>,
+    /// Ship log events to a syslog server
+    #[serde(default)]
+    pub syslog: Option<SyslogSinkConfig>,
+    /// Ship log events as NDJSON to an HTTP collector such as Vector
+    #[serde(default)]
+    pub http_sink: Option<HttpSinkConfig>,
+}
+
+fn default_sink_queue_size() -> usize {
+    10_000
+}
+
+fn default_syslog_facility() -> u8 {
+    // user-level messages
+    1
+}
+
+fn default_syslog_app_name() -> String {
+    "nrmap".to_string()
+}
+
+fn default_http_batch_size() -> usize {
+    100
+}
+
+fn default_http_flush_interval_ms() -> u64 {
+    1000
+}
+
+/// Transport of syslog messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogTransport {
+    #[default]
+    Udp,
+    /// Octet-counted framing (RFC 6587)
+    Tcp,
+}
+
+/// RFC 5424 syslog sink
+///
+/// Events are queued and sent from a background thread; when the queue is
+/// full or `max_per_second` is reached they are dropped, never waited for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyslogSinkConfig {
+    /// Server as `host:port`, e.g. `logs.example.com:514`
+    pub address: String,
+    #[serde(default)]
+    pub transport: SyslogTransport,
+    /// Facility code (0-23), 1 = user, 16-23 = local0-local7
+    #[serde(default = "default_syslog_facility")]
+    pub facility: u8,
+    #[serde(default = "default_syslog_app_name")]
+    pub app_name: String,
+    #[serde(default = "default_sink_queue_size")]
+    pub queue_size: usize,
+    /// Events sent per second (0 for no limit)
+    #[serde(default)]
+    pub max_per_second: u32,
+}
+
+/// NDJSON-over-HTTP sink (Vector `http_server`, Fluent Bit, Logstash)
+///
+/// Events are batched and POSTed from a background thread with the same
+/// drop-on-overload behaviour as [`SyslogSinkConfig`]. Only plain `http://`
+/// URLs are supported; put a local collector in front for TLS.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpSinkConfig {
+    pub url: String,
+    #[serde(default = "default_http_batch_size")]
+    pub batch_size: usize,
+    /// Send a partial batch after this long
+    #[serde(default = "default_http_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    #[serde(default = "default_sink_queue_size")]
+    pub queue_size: usize,
+    /// Events sent per second (0 for no limit)
+    #[serde(default)]
+    pub max_per_second: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScannerConfig {
     pub default_timeout_ms: u64,
+    /// Ports probed at once on each target
     pub max_concurrent_scans: usize,
+    /// Targets scanned at once by `scan_multiple`
+    #[serde(default = "default_max_concurrent_targets")]
+    pub max_concurrent_targets: usize,
+    /// Port probes in flight across all targets; 0 leaves only the
+    /// per-target limit
+    #[serde(default)]
+    pub max_concurrent_probes_total: usize,
+    /// Finished hosts held between their port scan and the result
+    /// consumer, OS detection included; scanning waits while all are taken
+    #[serde(default = "default_result_buffer")]
+    pub result_buffer: usize,
+    /// Clamp concurrency to the open file limit, only warn, or skip the
+    /// check (`--ulimit-check`)
+    #[serde(default)]
+    pub ulimit_check: UlimitCheck,
     pub adaptive_throttling: bool,
+    /// Registered throttle strategy used when `adaptive_throttling` is on
+    #[serde(default = "default_throttle_strategy")]
+    pub throttle_strategy: String,
     pub initial_pps: usize,
     pub max_pps: usize,
     pub min_pps: usize,
@@ -47,6 +186,83 @@ pub struct ScannerConfig {
     pub tcp_connect: TcpConnectConfig,
     pub tcp_syn: TcpSynConfig,
     pub udp: UdpConfig,
+    /// Split crafted probe packets into IP fragments carrying this many bytes
+    /// of payload (a multiple of 8); unset sends whole packets
+    #[serde(default)]
+    pub fragment_mtu: Option<usize>,
+    #[serde(default)]
+    pub os_detection: OsDetectionConfig,
+    /// TTL and bad-checksum probe modes for firewall auditing
+    #[serde(default)]
+    pub evasion: ProbeEvasion,
+    /// Re-check open ports with the other TCP scan type and flag disagreements
+    #[serde(default)]
+    pub verify_open_ports: bool,
+    #[serde(default)]
+    pub adaptive_timeout: AdaptiveTimeoutConfig,
+    /// Run-wide probe rate cap in packets per second (`--max-rate`)
+    #[serde(default)]
+    pub max_rate: Option<u32>,
+    /// Floor the adaptive throttle never backs off below (`--min-rate`)
+    #[serde(default)]
+    pub min_rate: Option<u32>,
+    /// Fail instead of degrading when discovery or a scan type fails
+    #[serde(default)]
+    pub strict: bool,
+    /// Keep only open port results, replacing closed, filtered and failed
+    /// ones by per-host counters (`--open-only`)
+    #[serde(default)]
+    pub open_ports_only: bool,
+    /// After the requested ports, scan the rest of 1-65535 on hosts found
+    /// up or with an open port (`--expand-ports`)
+    #[serde(default)]
+    pub expand_ports: bool,
+    /// Record why each port got its state (`--reason`)
+    #[serde(default)]
+    pub port_reasons: bool,
+    /// Expanded target counts above this need `--yes` or an interactive
+    /// confirmation; 0 never asks
+    #[serde(default = "default_confirm_targets_above")]
+    pub confirm_targets_above: usize,
+    /// Shuffled host and port order for IDS-unfriendly sweeps
+    #[serde(default)]
+    pub scan_order: ScanOrder,
+    /// Backoff between retries and second-pass re-probing of filtered ports
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// `nmap-services` style file replacing the embedded port frequency
+    /// table behind `--top-ports` and the `top1000` preset
+    #[serde(default)]
+    pub port_frequency_file: Option<String>,
+    /// `/etc/services` style file merged over the embedded service names
+    /// used in reports and port specs
+    #[serde(default)]
+    pub services_file: Option<String>,
+    /// IEEE `oui.txt`, its CSV export or a Wireshark `manuf` file merged
+    /// over the embedded MAC vendor table
+    #[serde(default)]
+    pub oui_file: Option<String>,
+    /// Make TCP connects from this SSH bastion instead of the local host
+    #[serde(default)]
+    pub ssh_tunnel: Option<SshTunnelConfig>,
+    #[serde(default)]
+    pub deep_check: DeepCheckConfig,
+}
+
+fn default_throttle_strategy() -> String {
+    crate::scanner::throttle::ADAPTIVE_STRATEGY.to_string()
+}
+
+fn default_max_concurrent_targets() -> usize {
+    10
+}
+
+fn default_result_buffer() -> usize {
+    64
+}
+
+fn default_confirm_targets_above() -> usize {
+    4096
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +271,190 @@ pub struct HostDiscoveryConfig {
     pub method: String,
     pub timeout_ms: u64,
     pub retries: usize,
+    #[serde(default)]
+    pub ipv6: Ipv6DiscoveryConfig,
+    /// How votes from custom discovery probes and the built-in method combine
+    #[serde(default)]
+    pub consensus: ConsensusPolicy,
+    /// Resolve ARP for on-link IPv4 targets before scanning and treat
+    /// unresolved hosts as down
+    #[serde(default)]
+    pub arp_warmup: bool,
+    /// Skip hosts found down by a recent scan
+    #[serde(default)]
+    pub cache: DiscoveryCacheConfig,
+}
+
+/// Host liveness cache across scans (opt-in, `--skip-down-for`)
+///
+/// Hosts found down with no open port are reported down without probing
+/// for `ttl_secs`; `--no-cache` turns the cache off for one run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryCacheConfig {
+    pub enabled: bool,
+    /// Seconds a down host is skipped for
+    pub ttl_secs: u64,
+    /// File shared between runs; by default next to the `--history` file,
+    /// else `~/.config/nrmap/discovery-cache.json`
+    pub path: Option<PathBuf>,
+}
+
+impl Default for DiscoveryCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 600,
+            path: None,
+        }
+    }
+}
+
+/// IPv6 on-link discovery settings (opt-in)
+///
+/// IPv6 subnets are too large to sweep, so candidate targets are gathered from
+/// an all-nodes multicast echo, the kernel neighbor (NDP) cache and heuristic
+/// address generation instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ipv6DiscoveryConfig {
+    pub enabled: bool,
+    pub interface: Option<String>,
+    pub multicast_ping: bool,
+    pub neighbor_cache: bool,
+    pub heuristic_targets: bool,
+    pub low_byte_count: u16,
+    pub timeout_ms: u64,
+}
+
+impl Default for Ipv6DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interface: None,
+            multicast_ping: true,
+            neighbor_cache: true,
+            heuristic_targets: true,
+            low_byte_count: 16,
+            timeout_ms: 2000,
+        }
+    }
+}
+
+/// OS fingerprinting of scanned hosts (opt-in, `--os-detect`)
+///
+/// Fingerprinting runs on its own work queue so it overlaps with port scans
+/// of other hosts instead of holding up a scan slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OsDetectionConfig {
+    pub enabled: bool,
+    /// Hosts fingerprinted at the same time
+    pub max_concurrent: usize,
+    /// Fingerprint jobs started per second (0 for no limit)
+    pub max_per_second: u32,
+    /// Also send the intrusive T1-T7/SEQ/ECN probe set
+    pub active_probes: bool,
+    /// Custom OS signatures merged over the built-in database, managed with
+    /// `nrmap os-db` (default `~/.config/nrmap/os-db.json`)
+    pub user_database: Option<PathBuf>,
+    /// Record fingerprints no signature matches for later labeling
+    /// (`nrmap os-db unknown` / `nrmap os-db label`)
+    pub learning: bool,
+    /// Fuzzy match score below which a fingerprint counts as unknown
+    pub learning_threshold: f64,
+    /// Where unknown fingerprints are recorded (default
+    /// `~/.config/nrmap/unknown-fingerprints.jsonl`)
+    pub unknown_fingerprints: Option<PathBuf>,
+    /// Reuse OS detections of unchanged targets across scans
+    pub cache: OsCacheConfig,
+}
+
+/// OS detection result cache (`--no-cache` turns it off for one run)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OsCacheConfig {
+    pub enabled: bool,
+    /// Seconds a cached detection stays valid
+    pub ttl_secs: u64,
+    /// File shared between runs (default `~/.config/nrmap/os-cache.json`);
+    /// without one the cache lasts for a single run
+    pub path: Option<PathBuf>,
+}
+
+impl Default for OsCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 86400,
+            path: crate::os_fingerprint::cache::default_cache_path(),
+        }
+    }
+}
+
+/// Protocol liveness checks on open ports (opt-in, `--deep-check`)
+///
+/// HTTP, SMTP, FTP and RDP ports get a short exchange in their protocol,
+/// recording whether the service answered, how fast and with which error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeepCheckConfig {
+    pub enabled: bool,
+    /// Timeout for connecting and completing one exchange (milliseconds)
+    pub timeout_ms: u64,
+    /// Ports of one host checked at the same time
+    pub max_concurrent: usize,
+}
+
+impl Default for DeepCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 5000,
+            max_concurrent: 4,
+        }
+    }
+}
+
+impl Default for OsDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_concurrent: 4,
+            max_per_second: 0,
+            active_probes: false,
+            user_database: crate::os_fingerprint::user_db::default_user_database_path(),
+            learning: false,
+            learning_threshold: 0.5,
+            unknown_fingerprints: crate::os_fingerprint::learning::default_unknown_fingerprints_path(),
+            cache: OsCacheConfig::default(),
+        }
+    }
+}
+
+/// Per-host adaptive probe timeouts (opt-in, `--adaptive-timeout`)
+///
+/// Timeouts follow each host's measured RTT, bounded by the values here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveTimeoutConfig {
+    pub enabled: bool,
+    pub min_timeout_ms: u64,
+    pub max_timeout_ms: u64,
+    pub min_retries: usize,
+    pub max_retries: usize,
+}
+
+impl Default for AdaptiveTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_timeout_ms: 100,
+            max_timeout_ms: 10000,
+            min_retries: 0,
+            max_retries: 3,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +471,10 @@ pub struct TcpSynConfig {
     pub timeout_ms: u64,
     pub retries: usize,
     pub retry_delay_ms: u64,
+    /// Send every probe once with a SYN cookie sequence number and keep no
+    /// per-probe state: faster, but no retries and no response times
+    #[serde(default)]
+    pub stateless: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,9 +483,17 @@ pub struct UdpConfig {
     pub timeout_ms: u64,
     pub retries: usize,
     pub retry_delay_ms: u64,
+    /// Most timed-out ports re-probed at a host's ICMP unreachable rate
+    /// once the host is found to rate-limit them; 0 turns detection off
+    #[serde(default = "default_icmp_ratelimit_reprobes")]
+    pub icmp_ratelimit_reprobes: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_icmp_ratelimit_reprobes() -> usize {
+    256
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ThrottlingConfig {
     pub enabled: bool,
     pub success_threshold: f64,
@@ -92,12 +504,43 @@ pub struct ThrottlingConfig {
     pub adjustment_interval_ms: u64,
 }
 
+impl Default for ThrottlingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            success_threshold: 0.95,
+            failure_threshold: 0.80,
+            rate_increase_factor: 1.5,
+            rate_decrease_factor: 0.5,
+            window_size: 100,
+            adjustment_interval_ms: 1000,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputConfig {
     pub format: String,
     pub output_dir: String,
     pub include_timestamps: bool,
     pub verbose: bool,
+    /// Timezone for report timestamps: "UTC", "local", or an offset like "+05:30"
+    #[serde(default)]
+    pub timezone: DisplayTimezone,
+    /// Record scan traffic to this pcapng file (one rotated file per scan)
+    #[serde(default)]
+    pub pcap_file: Option<String>,
+    /// Spill `scan-file` results to JSONL segments in this directory and
+    /// stream reports from them instead of holding every result in memory
+    #[serde(default)]
+    pub spool_dir: Option<String>,
+    /// Host results per spool segment file
+    #[serde(default = "default_spool_segment_results")]
+    pub spool_segment_results: usize,
+}
+
+fn default_spool_segment_results() -> usize {
+    10_000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +548,13 @@ pub struct SecurityConfig {
     pub require_privileges_check: bool,
     pub max_targets: usize,
     pub max_ports: usize,
+    /// Address blocks this installation is authorized to scan; scans with
+    /// targets outside them are refused. Empty authorizes every target
+    #[serde(default)]
+    pub scope: Vec<IpNetwork>,
+    /// Windows during which scans are refused or hosts held back
+    #[serde(default)]
+    pub blackouts: Vec<BlackoutWindow>,
 }
 
 impl AppConfig {
@@ -144,6 +594,13 @@ impl AppConfig {
         }
     }
 
+    /// Directory holding profile files for a config loaded from `config_path`
+    pub fn profiles_dir<P: AsRef<Path>>(&self, config_path: P) -> PathBuf {
+        let dir = self.general.profiles_dir.as_deref().unwrap_or("profiles.d");
+        let base = config_path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        base.join(dir)
+    }
+
     /// Validate configuration values
     /// 
     /// Ensures all configuration values are within acceptable ranges
@@ -166,6 +623,25 @@ impl AppConfig {
             ));
         }
 
+        if let Some(ref syslog) = self.logging.syslog {
+            if syslog.facility > 23 {
+                return Err(ConfigError::Message(format!(
+                    "Invalid syslog facility: {}. Must be between 0 and 23",
+                    syslog.facility
+                )));
+            }
+        }
+        if let Some(ref http) = self.logging.http_sink {
+            crate::logging::remote::parse_http_url(&http.url).map_err(|e| ConfigError::Message(e.to_string()))?;
+        }
+        let queue_sizes = [
+            self.logging.syslog.as_ref().map(|s| s.queue_size),
+            self.logging.http_sink.as_ref().map(|h| h.queue_size),
+        ];
+        if queue_sizes.contains(&Some(0)) {
+            return Err(ConfigError::Message("Log sink queue_size must be greater than 0".to_string()));
+        }
+
         // Validate scanner PPS rates
         if self.scanner.min_pps >= self.scanner.max_pps {
             return Err(ConfigError::Message(
@@ -180,6 +656,29 @@ impl AppConfig {
             ));
         }
 
+        crate::scanner::throttle::validate_rate_limits(self.scanner.min_rate, self.scanner.max_rate)
+            .map_err(|e| ConfigError::Message(e.to_string()))?;
+
+        self.scanner.retry_policy.validate().map_err(ConfigError::Message)?;
+
+        let adaptive = &self.scanner.adaptive_timeout;
+        if adaptive.min_timeout_ms == 0 || adaptive.min_timeout_ms > adaptive.max_timeout_ms {
+            return Err(ConfigError::Message(
+                "adaptive_timeout.min_timeout_ms must be non-zero and not above max_timeout_ms".to_string(),
+            ));
+        }
+        if adaptive.min_retries > adaptive.max_retries {
+            return Err(ConfigError::Message(
+                "adaptive_timeout.min_retries must not exceed max_retries".to_string(),
+            ));
+        }
+
+        #[cfg(not(feature = "no-net"))]
+        if let Some(mtu) = self.scanner.fragment_mtu {
+            crate::packet::crafting::validate_fragment_size(mtu)
+                .map_err(|e| ConfigError::Message(e.to_string()))?;
+        }
+
         // Validate throttling thresholds
         if self.throttling.success_threshold <= self.throttling.failure_threshold {
             return Err(ConfigError::Message(
@@ -219,6 +718,8 @@ impl Default for AppConfig {
             general: GeneralConfig {
                 app_name: "NrMAP".to_string(),
                 version: "0.1.0".to_string(),
+                operator: None,
+                profiles_dir: None,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -227,11 +728,20 @@ impl Default for AppConfig {
                 log_dir: "./logs".to_string(),
                 max_file_size: 10,
                 max_files: 5,
+                stderr: false,
+                scan_log_dir: None,
+                syslog: None,
+                http_sink: None,
             },
             scanner: ScannerConfig {
                 default_timeout_ms: 5000,
                 max_concurrent_scans: 1000,
+                max_concurrent_targets: 10,
+                max_concurrent_probes_total: 0,
+                result_buffer: default_result_buffer(),
+                ulimit_check: UlimitCheck::default(),
                 adaptive_throttling: true,
+                throttle_strategy: default_throttle_strategy(),
                 initial_pps: 1000,
                 max_pps: 10000,
                 min_pps: 100,
@@ -240,6 +750,10 @@ impl Default for AppConfig {
                     method: "icmp".to_string(),
                     timeout_ms: 3000,
                     retries: 2,
+                    ipv6: Ipv6DiscoveryConfig::default(),
+                    consensus: ConsensusPolicy::Any,
+                    arp_warmup: false,
+                    cache: Default::default(),
                 },
                 tcp_connect: TcpConnectConfig {
                     enabled: true,
@@ -252,34 +766,58 @@ impl Default for AppConfig {
                     timeout_ms: 3000,
                     retries: 2,
                     retry_delay_ms: 50,
+                    stateless: false,
                 },
                 udp: UdpConfig {
                     enabled: true,
                     timeout_ms: 5000,
                     retries: 3,
                     retry_delay_ms: 200,
+                    icmp_ratelimit_reprobes: default_icmp_ratelimit_reprobes(),
                 },
+                fragment_mtu: None,
+                os_detection: OsDetectionConfig::default(),
+                evasion: ProbeEvasion::default(),
+                verify_open_ports: false,
+                adaptive_timeout: AdaptiveTimeoutConfig::default(),
+                max_rate: None,
+                min_rate: None,
+                strict: false,
+                open_ports_only: false,
+                expand_ports: false,
+                port_reasons: false,
+                confirm_targets_above: default_confirm_targets_above(),
+                scan_order: ScanOrder::default(),
+                retry_policy: RetryPolicy::default(),
+                port_frequency_file: None,
+                services_file: None,
+                oui_file: None,
+                ssh_tunnel: None,
+                deep_check: DeepCheckConfig::default(),
             },
-            throttling: ThrottlingConfig {
-                enabled: true,
-                success_threshold: 0.95,
-                failure_threshold: 0.80,
-                rate_increase_factor: 1.5,
-                rate_decrease_factor: 0.5,
-                window_size: 100,
-                adjustment_interval_ms: 1000,
-            },
+            throttling: ThrottlingConfig::default(),
             output: OutputConfig {
                 format: "json".to_string(),
                 output_dir: "./results".to_string(),
                 include_timestamps: true,
                 verbose: false,
+                timezone: DisplayTimezone::Utc,
+                pcap_file: None,
+                spool_dir: None,
+                spool_segment_results: default_spool_segment_results(),
             },
             security: SecurityConfig {
                 require_privileges_check: true,
                 max_targets: 65536,
                 max_ports: 65535,
+                scope: Vec::new(),
+                blackouts: Vec::new(),
             },
+            enrichment: EnrichmentConfig::default(),
+            #[cfg(not(feature = "no-net"))]
+            distributed: crate::distributed::DistributedConfig::default(),
+            profiles: HashMap::new(),
+            presets: BTreeMap::new(),
         }
     }
 }
@@ -380,6 +918,10 @@ verbose = true
 require_privileges_check = false
 max_targets = 1000
 max_ports = 10000
+
+[distributed]
+max_agents = 4
+preemption = "preempt"
 "#;
         
         fs::write(&config_path, config_content).unwrap();
@@ -388,6 +930,12 @@ max_ports = 10000
         assert_eq!(config.general.app_name, "TestApp");
         assert_eq!(config.scanner.max_concurrent_scans, 500);
         assert_eq!(config.logging.level, "debug");
+        #[cfg(not(feature = "no-net"))]
+        {
+            assert_eq!(config.distributed.max_agents, 4);
+            assert_eq!(config.distributed.job_timeout_seconds, 3600);
+            assert_eq!(config.distributed.preemption, crate::distributed::PreemptionPolicy::Preempt);
+        }
     }
 }
 
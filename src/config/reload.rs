@@ -0,0 +1,377 @@
+//! Hot reload of configuration for long-running processes
+//!
+//! A [`ConfigWatcher`] watches the configuration files of a [`ConfigLayers`]
+//! stack (notify: inotify, FSEvents, kqueue) and reloads them when one
+//! changes. The merged result is validated, then only settings that are
+//! safe to change mid-run (see [`HOT_RELOADABLE`]) are applied; the rest
+//! keep their running values until a restart. Every change applied or
+//! rejected is written to the `nrmap::audit` log target. Subscribers receive
+//! the live configuration through a watch channel.
+//!
+//! The directories holding the files are watched rather than the files,
+//! which also catches editors that replace a file instead of writing it in
+//! place.
+
+use super::{AppConfig, ConfigLayer, ConfigLayers};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+#[cfg(not(feature = "no-net"))]
+use tokio::task::JoinHandle;
+#[cfg(not(feature = "no-net"))]
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Log target of configuration audit entries
+pub const AUDIT_TARGET: &str = "nrmap::audit";
+
+/// Keys that can change without a restart; entries ending in `.` cover a section
+///
+/// The log level applies at once; the scanner and throttling settings from
+/// the next scan of a process that takes them up (see
+/// [`Scanner::apply_reloaded`]).
+///
+/// [`Scanner::apply_reloaded`]: crate::scanner::Scanner::apply_reloaded
+pub const HOT_RELOADABLE: &[&str] = &[
+    "logging.level",
+    "scanner.verify_open_ports",
+    "scanner.deep_check.enabled",
+    "throttling.",
+];
+
+/// Quiet time after a file event before reloading, so that a save written
+/// in several steps is read once, complete
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Whether `key` (e.g. `throttling.success_threshold`) can change mid-run
+pub fn is_hot_reloadable(key: &str) -> bool {
+    HOT_RELOADABLE.iter().any(|entry| match entry.strip_suffix('.') {
+        Some(section) => key.strip_prefix(section).is_some_and(|rest| rest.starts_with('.')),
+        None => key == *entry,
+    })
+}
+
+/// One configuration key whose value changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// What a reload did
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReloadOutcome {
+    /// Changes now in effect
+    pub applied: Vec<ConfigChange>,
+    /// Changes that need a restart and were left out
+    pub rejected: Vec<ConfigChange>,
+    /// Why the new configuration was rejected as a whole, if it was
+    pub error: Option<String>,
+}
+
+impl ReloadOutcome {
+    fn failed(error: impl ToString) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Write one audit entry per applied or rejected change
+    pub fn audit(&self) {
+        if let Some(ref error) = self.error {
+            warn!(target: AUDIT_TARGET, outcome = "rejected", error = %error, "Configuration reload rejected");
+        }
+        for change in &self.applied {
+            info!(
+                target: AUDIT_TARGET,
+                outcome = "applied",
+                key = %change.key,
+                old = %change.old,
+                new = %change.new,
+                "Configuration change applied"
+            );
+        }
+        for change in &self.rejected {
+            warn!(
+                target: AUDIT_TARGET,
+                outcome = "rejected",
+                key = %change.key,
+                old = %change.old,
+                new = %change.new,
+                "Configuration change requires a restart"
+            );
+        }
+    }
+}
+
+/// Merge the hot-reloadable changes of `new` into `current`
+pub fn plan_reload(current: &AppConfig, new: &AppConfig) -> (AppConfig, ReloadOutcome) {
+    let (old_value, new_value) = match (serde_json::to_value(current), serde_json::to_value(new)) {
+        (Ok(old_value), Ok(new_value)) => (old_value, new_value),
+        (Err(e), _) | (_, Err(e)) => return (current.clone(), ReloadOutcome::failed(e)),
+    };
+    let (old_keys, new_keys) = (flatten(&old_value), flatten(&new_value));
+    let mut live = old_value.clone();
+
+    let mut outcome = ReloadOutcome::default();
+    let keys: std::collections::BTreeSet<&String> = old_keys.keys().chain(new_keys.keys()).collect();
+    for key in keys {
+        let (old, new) = (old_keys.get(key), new_keys.get(key));
+        if old == new {
+            continue;
+        }
+        let change = ConfigChange {
+            key: key.clone(),
+            old: old.map_or("(unset)".to_string(), |v| v.to_string()),
+            new: new.map_or("(unset)".to_string(), |v| v.to_string()),
+        };
+
+        let pointer = format!("/{}", key.replace('.', "/"));
+        match (is_hot_reloadable(key), live.pointer_mut(&pointer), new) {
+            (true, Some(slot), Some(value)) => {
+                *slot = (*value).clone();
+                outcome.applied.push(change);
+            }
+            _ => outcome.rejected.push(change),
+        }
+    }
+
+    match serde_json::from_value(live) {
+        Ok(live) => (live, outcome),
+        Err(e) => (current.clone(), ReloadOutcome::failed(e)),
+    }
+}
+
+/// Leaf values of a JSON tree by dotted key
+fn flatten(value: &Value) -> BTreeMap<String, &Value> {
+    fn walk<'a>(prefix: String, value: &'a Value, out: &mut BTreeMap<String, &'a Value>) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (key, child) in map {
+                    let key = match prefix.is_empty() {
+                        true => key.clone(),
+                        false => format!("{}.{}", prefix, key),
+                    };
+                    walk(key, child, out);
+                }
+            }
+            _ => {
+                out.insert(prefix, value);
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    walk(String::new(), value, &mut out);
+    out
+}
+
+/// Modification time and size of a file, if it exists
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> FileStamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Reloads layered configuration when its files change
+pub struct ConfigWatcher {
+    layers: ConfigLayers,
+    files: Vec<(PathBuf, FileStamp)>,
+    sender: watch::Sender<Arc<AppConfig>>,
+}
+
+impl ConfigWatcher {
+    /// Watch the files of `layers`, starting from the running `config`
+    pub fn new(layers: ConfigLayers, config: AppConfig) -> Self {
+        let files = layers
+            .layers()
+            .iter()
+            .filter_map(|layer| match layer {
+                ConfigLayer::File { path, .. } => Some((path.clone(), stamp(path))),
+                _ => None,
+            })
+            .collect();
+        let (sender, _) = watch::channel(Arc::new(config));
+        Self { layers, files, sender }
+    }
+
+    /// Receiver of the live configuration, updated after every applied reload
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AppConfig>> {
+        self.sender.subscribe()
+    }
+
+    /// The configuration currently in effect
+    pub fn current(&self) -> Arc<AppConfig> {
+        self.sender.borrow().clone()
+    }
+
+    /// Reload if a configuration file changed since the last check
+    pub fn check(&mut self) -> Option<ReloadOutcome> {
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let now = stamp(path);
+            if now != *last {
+                debug!("Configuration file changed: {}", path.display());
+                *last = now;
+                changed = true;
+            }
+        }
+        changed.then(|| self.reload())
+    }
+
+    /// Reload, validate and apply the hot-reloadable changes, auditing each
+    pub fn reload(&mut self) -> ReloadOutcome {
+        let outcome = match self.layers.load() {
+            Ok(new) => {
+                let current = self.current();
+                let (live, outcome) = plan_reload(&current, &new);
+                if !outcome.applied.is_empty() {
+                    if live.logging.level != current.logging.level {
+                        if let Err(e) = crate::logging::set_level(&live.logging.level) {
+                            warn!("Could not apply reloaded log level: {}", e);
+                        }
+                    }
+                    self.sender.send_replace(Arc::new(live));
+                }
+                outcome
+            }
+            Err(e) => ReloadOutcome::failed(e),
+        };
+        outcome.audit();
+        outcome
+    }
+
+    /// Watch the files in the background until `cancel` fires
+    ///
+    /// Fails when the platform's file watcher cannot be set up; a directory
+    /// that does not exist is skipped with a warning.
+    #[cfg(not(feature = "no-net"))]
+    pub fn spawn(mut self, cancel: CancellationToken) -> notify::Result<JoinHandle<()>> {
+        use notify::{RecursiveMode, Watcher};
+
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        // Events name files by absolute path; check() compares the stamps
+        let names: Vec<std::ffi::OsString> =
+            self.files.iter().filter_map(|(path, _)| path.file_name().map(Into::into)).collect();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.paths.iter().any(|path| path.file_name().is_some_and(|name| names.iter().any(|n| n == name))) => {
+                let _ = events_tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Configuration watch error: {}", e),
+        })?;
+        let mut directories: Vec<&Path> = self.files.iter().filter_map(|(path, _)| path.parent()).collect();
+        directories.dedup();
+        for directory in directories {
+            let directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+            if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+                warn!("Not watching {} for configuration changes: {}", directory.display(), e);
+            }
+        }
+
+        Ok(tokio::spawn(async move {
+            // Dropping the watcher stops the events
+            let _watcher = watcher;
+            loop {
+                tokio::select! {
+                    Some(()) = events.recv() => {
+                        tokio::time::sleep(DEBOUNCE).await;
+                        while events.try_recv().is_ok() {}
+                        self.check();
+                    }
+                    _ = cancel.cancelled() => break,
+                }
+            }
+            debug!("Configuration watcher stopped");
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hot_reloadable_keys() {
+        assert!(is_hot_reloadable("logging.level"));
+        assert!(is_hot_reloadable("scanner.verify_open_ports"));
+        assert!(is_hot_reloadable("scanner.deep_check.enabled"));
+        assert!(is_hot_reloadable("throttling.success_threshold"));
+        assert!(!is_hot_reloadable("throttling"));
+        assert!(!is_hot_reloadable("logging.format"));
+        assert!(!is_hot_reloadable("scanner.deep_check.timeout_ms"));
+        assert!(!is_hot_reloadable("scanner.max_concurrent_scans"));
+    }
+
+    #[test]
+    fn test_plan_reload_applies_only_safe_changes() {
+        let current = AppConfig::default();
+        let mut new = current.clone();
+        new.logging.level = "debug".to_string();
+        new.scanner.verify_open_ports = !current.scanner.verify_open_ports;
+        new.scanner.max_concurrent_scans = 10;
+
+        let (live, outcome) = plan_reload(&current, &new);
+        assert_eq!(live.logging.level, "debug");
+        assert_eq!(live.scanner.verify_open_ports, new.scanner.verify_open_ports);
+        assert_eq!(live.scanner.max_concurrent_scans, current.scanner.max_concurrent_scans);
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.rejected.len(), 1);
+        assert_eq!(outcome.rejected[0].key, "scanner.max_concurrent_scans");
+        assert_eq!(outcome.rejected[0].new, "10");
+    }
+
+    #[test]
+    fn test_watcher_reloads_changed_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[scanner]\nverify_open_ports = false\n").unwrap();
+
+        let layers = ConfigLayers::new().with_file(&path, true);
+        let mut watcher = ConfigWatcher::new(layers.clone(), layers.load().unwrap());
+        let receiver = watcher.subscribe();
+        assert_eq!(watcher.check(), None);
+
+        std::fs::write(&path, "[scanner]\nverify_open_ports = true\nmin_pps = 5\n").unwrap();
+        let outcome = watcher.check().unwrap();
+        assert_eq!(outcome.applied.len(), 1);
+        assert_eq!(outcome.rejected[0].key, "scanner.min_pps");
+        assert!(receiver.borrow().scanner.verify_open_ports);
+
+        // Invalid configuration is rejected as a whole
+        std::fs::write(&path, "[logging]\nlevel = \"loud\"\n").unwrap();
+        let outcome = watcher.check().unwrap();
+        assert!(outcome.error.is_some());
+        assert_eq!(watcher.current().logging.level, "info");
+    }
+
+    #[tokio::test]
+    async fn test_spawned_watcher_follows_file_events() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[scanner]\nverify_open_ports = false\n").unwrap();
+
+        let layers = ConfigLayers::new().with_file(&path, true);
+        let watcher = ConfigWatcher::new(layers.clone(), layers.load().unwrap());
+        let mut receiver = watcher.subscribe();
+        let cancel = CancellationToken::new();
+        let handle = watcher.spawn(cancel.clone()).unwrap();
+
+        // Replaced rather than written in place, as many editors do
+        let staged = dir.path().join("config.toml.new");
+        std::fs::write(&staged, "[scanner]\nverify_open_ports = true\n").unwrap();
+        std::fs::rename(&staged, &path).unwrap();
+        tokio::time::timeout(Duration::from_secs(10), receiver.changed()).await.unwrap().unwrap();
+        assert!(receiver.borrow().scanner.verify_open_ports);
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+}
@@ -4,17 +4,21 @@
 /// using port numbers, banners, and behavior patterns.
 
 use crate::error::ScanResult;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
 /// Service fingerprint information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ServiceFingerprint {
     pub service_name: String,
     pub product: Option<String>,
     pub version: Option<String>,
     pub os_info: Option<String>,
     pub cpe: Option<String>,
+    /// Device and configuration details the response gave away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_info: Option<String>,
     pub confidence: f32,
 }
 
@@ -30,12 +34,29 @@ impl std::fmt::Display for ServiceFingerprint {
             write!(f, " v{}", version)?;
         }
         
+        if let Some(ref extra_info) = self.extra_info {
+            write!(f, " - {}", extra_info)?;
+        }
+        
         write!(f, " [{:.0}% confidence]", self.confidence * 100.0)?;
         
         Ok(())
     }
 }
 
+impl ServiceFingerprint {
+    /// Product, version and extra info, e.g. "dnsmasq 2.85" or
+    /// "MiniUPnPd 2.1, LOCATION http://192.0.2.1:5000/rootDesc.xml"
+    pub fn details(&self) -> Option<String> {
+        let product: Vec<&str> = [&self.product, &self.version].into_iter().filter_map(|p| p.as_deref()).collect();
+        let parts: Vec<String> = [(!product.is_empty()).then(|| product.join(" ")), self.extra_info.clone()]
+            .into_iter()
+            .flatten()
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
 /// Fingerprint pattern for matching
 #[derive(Debug, Clone)]
 pub(crate) struct FingerprintPattern {
@@ -123,6 +144,7 @@ impl FingerprintDatabase {
                 version: None,
                 os_info: None,
                 cpe: None,
+            extra_info: None,
                 confidence: 0.3, // Low confidence without banner
             });
         };
@@ -139,6 +161,7 @@ impl FingerprintDatabase {
                     version,
                     os_info: None,
                     cpe: None,
+            extra_info: None,
                     confidence: pattern.confidence,
                 });
             }
@@ -394,6 +417,7 @@ mod tests {
             version: Some("1.18.0".to_string()),
             os_info: None,
             cpe: None,
+            extra_info: None,
             confidence: 0.95,
         };
 
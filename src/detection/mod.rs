@@ -3,16 +3,26 @@
 /// This module provides service detection, fingerprinting, and OS identification
 /// capabilities for network scanning operations.
 
+#[cfg(not(feature = "no-net"))]
 pub mod banner;
 pub mod fingerprint;
 pub mod os_detection;
+#[cfg(not(feature = "no-net"))]
+pub mod tls;
+pub mod udp_fingerprint;
 
+#[cfg(not(feature = "no-net"))]
 pub use banner::{BannerGrabber, ServiceBanner};
 pub use fingerprint::{FingerprintMatcher, ServiceFingerprint, FingerprintDatabase};
 pub use os_detection::{OsDetector, OsFingerprint, OsMatch};
+#[cfg(not(feature = "no-net"))]
+pub use tls::{CertificateNames, TlsCertificateGrabber};
 
 use crate::error::ScanResult;
+use crate::os_fingerprint::cache as os_cache;
+use crate::os_fingerprint::OsResultCache;
 use std::net::IpAddr;
+use std::sync::Arc;
 use tracing::info;
 
 /// Detection engine configuration
@@ -40,13 +50,16 @@ impl Default for DetectionEngineConfig {
 }
 
 /// Detection engine facade providing high-level API
+#[cfg(not(feature = "no-net"))]
 pub struct DetectionEngine {
     config: DetectionEngineConfig,
     banner_grabber: BannerGrabber,
     fingerprint_matcher: FingerprintMatcher,
     os_detector: OsDetector,
+    os_cache: Option<Arc<OsResultCache>>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl DetectionEngine {
     /// Create a new detection engine
     pub fn new(config: DetectionEngineConfig) -> ScanResult<Self> {
@@ -68,9 +81,16 @@ impl DetectionEngine {
             banner_grabber,
             fingerprint_matcher,
             os_detector,
+            os_cache: None,
         })
     }
 
+    /// Share an OS detection result cache (e.g. with `OsFingerprintEngine`)
+    pub fn with_os_cache(mut self, cache: Arc<OsResultCache>) -> Self {
+        self.os_cache = Some(cache);
+        self
+    }
+
     /// Grab service banner from a host/port
     pub async fn grab_banner(
         &self,
@@ -107,7 +127,18 @@ impl DetectionEngine {
             return Ok(vec![]);
         }
         
-        self.os_detector.detect(target).await
+        let evidence = os_cache::evidence_hash(&["detection-engine"]);
+        if let Some(cached) = self.os_cache.as_ref().and_then(|cache| cache.get(target, evidence)) {
+            return Ok(cached.iter().map(OsMatch::from).collect());
+        }
+        
+        let matches = self.os_detector.detect(target).await?;
+        if let Some(ref cache) = self.os_cache {
+            if !matches.is_empty() {
+                cache.insert(target, evidence, matches.iter().map(Into::into).collect());
+            }
+        }
+        Ok(matches)
     }
 
     /// Perform comprehensive detection (banner + service + OS)
@@ -140,6 +171,7 @@ impl DetectionEngine {
 }
 
 /// Complete detection result
+#[cfg(not(feature = "no-net"))]
 #[derive(Debug, Clone)]
 pub struct DetectionResult {
     pub target: IpAddr,
@@ -149,6 +181,7 @@ pub struct DetectionResult {
     pub os_matches: Vec<OsMatch>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl std::fmt::Display for DetectionResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Detection Results for {}:{}", self.target, self.port)?;
@@ -172,7 +205,7 @@ impl std::fmt::Display for DetectionResult {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
 
@@ -190,5 +223,28 @@ mod tests {
         let result = DetectionEngine::new(config);
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_detect_os_uses_shared_cache() {
+        let cache = Arc::new(OsResultCache::in_memory(std::time::Duration::from_secs(60)));
+        let engine = DetectionEngine::new(DetectionEngineConfig::default())
+            .unwrap()
+            .with_os_cache(cache.clone());
+        let target: IpAddr = "192.0.2.5".parse().unwrap();
+        assert!(engine.detect_os(target).await.unwrap().is_empty());
+
+        let cached = OsMatch {
+            os_family: "Linux".to_string(),
+            os_name: Some("Linux 2.6+".to_string()),
+            os_version: None,
+            device_type: None,
+            confidence: 0.9,
+            matched_features: vec![],
+        };
+        cache.insert(target, os_cache::evidence_hash(&["detection-engine"]), vec![(&cached).into()]);
+        let matches = engine.detect_os(target).await.unwrap();
+        assert_eq!(matches[0].os_name.as_deref(), Some("Linux 2.6+"));
+        assert_eq!(matches[0].os_family, "Linux");
+    }
 }
 
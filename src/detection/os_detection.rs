@@ -36,6 +36,33 @@ impl std::fmt::Display for OsMatch {
     }
 }
 
+impl From<&crate::os_fingerprint::OsMatchResult> for OsMatch {
+    fn from(result: &crate::os_fingerprint::OsMatchResult) -> Self {
+        Self {
+            os_family: result.os_family.to_string(),
+            os_name: Some(result.os_name.clone()),
+            os_version: result.os_version.clone(),
+            device_type: None,
+            confidence: result.confidence_score as f32,
+            matched_features: result.matching_features.clone(),
+        }
+    }
+}
+
+impl From<&OsMatch> for crate::os_fingerprint::OsMatchResult {
+    fn from(os_match: &OsMatch) -> Self {
+        let score = f64::from(os_match.confidence);
+        Self {
+            os_name: os_match.os_name.clone().unwrap_or_else(|| os_match.os_family.clone()),
+            os_version: os_match.os_version.clone(),
+            os_family: os_match.os_family.parse().unwrap_or(crate::os_fingerprint::fingerprint_db::OsFamily::Unknown),
+            confidence: crate::os_fingerprint::MatchConfidence::from(score),
+            confidence_score: score,
+            matching_features: os_match.matched_features.clone(),
+        }
+    }
+}
+
 /// OS fingerprint data collected from probes
 #[derive(Debug, Clone)]
 pub struct OsFingerprint {
@@ -0,0 +1,427 @@
+//! TLS certificate grabbing
+//!
+//! Sends a TLS 1.2 ClientHello and reads the server's Certificate message,
+//! which TLS 1.2 still sends in the clear, then pulls the subject common name,
+//! DNS subject alternative names and expiry out of the leaf certificate. No key
+//! exchange is performed and the connection is dropped right after.
+//!
+//! Only the handful of DER structures needed to reach those names are
+//! parsed; the certificate is not validated.
+
+use crate::error::{ScanError, ScanResult};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+const RECORD_HANDSHAKE: u8 = 0x16;
+const RECORD_ALERT: u8 = 0x15;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+const HANDSHAKE_SERVER_HELLO_DONE: u8 = 14;
+
+/// Stop reading a handshake that grows past this size
+const MAX_HANDSHAKE_BYTES: usize = 64 * 1024;
+
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Names found in a server certificate
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateNames {
+    /// Subject CN, if present
+    pub common_name: Option<String>,
+    /// DNS names from the subjectAltName extension, lowercased
+    pub dns_names: Vec<String>,
+    /// End of the validity period
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+/// Fetches leaf certificates from TLS services
+#[derive(Debug, Clone)]
+pub struct TlsCertificateGrabber {
+    timeout: Duration,
+}
+
+impl TlsCertificateGrabber {
+    /// Create a grabber with an overall per-connection timeout
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+
+    /// Fetch the names in the certificate served on `target:port`
+    ///
+    /// `server_name` is sent as SNI when given. Returns None when the service
+    /// does not speak TLS 1.2 or sends no certificate.
+    pub async fn grab(
+        &self,
+        target: IpAddr,
+        port: u16,
+        server_name: Option<&str>,
+    ) -> ScanResult<Option<CertificateNames>> {
+        let addr = SocketAddr::new(target, port);
+        let certificate = tokio::time::timeout(self.timeout, fetch_certificate(addr, server_name))
+            .await
+            .map_err(|_| ScanError::timeout(self.timeout.as_millis() as u64))??;
+
+        Ok(certificate.and_then(|der| {
+            let names = parse_certificate_names(&der);
+            debug!("Certificate names for {}: {:?}", addr, names);
+            names
+        }))
+    }
+}
+
+async fn fetch_certificate(addr: SocketAddr, server_name: Option<&str>) -> ScanResult<Option<Vec<u8>>> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(&client_hello(server_name)).await?;
+
+    let mut handshake = Vec::new();
+    loop {
+        let mut header = [0u8; 5];
+        if stream.read_exact(&mut header).await.is_err() {
+            return Ok(None);
+        }
+        let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let mut fragment = vec![0u8; length];
+        stream.read_exact(&mut fragment).await?;
+
+        match header[0] {
+            RECORD_HANDSHAKE => handshake.extend_from_slice(&fragment),
+            RECORD_ALERT => {
+                debug!("{} answered the ClientHello with an alert", addr);
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        }
+
+        match certificate_from_handshake(&handshake) {
+            HandshakeScan::Found(der) => return Ok(Some(der)),
+            HandshakeScan::Absent => return Ok(None),
+            HandshakeScan::Incomplete if handshake.len() > MAX_HANDSHAKE_BYTES => return Ok(None),
+            HandshakeScan::Incomplete => continue,
+        }
+    }
+}
+
+enum HandshakeScan {
+    Found(Vec<u8>),
+    Absent,
+    Incomplete,
+}
+
+/// Look for the first certificate in the handshake messages received so far
+fn certificate_from_handshake(mut data: &[u8]) -> HandshakeScan {
+    while data.len() >= 4 {
+        let length = u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize;
+        let Some(body) = data.get(4..4 + length) else {
+            return HandshakeScan::Incomplete;
+        };
+
+        match data[0] {
+            HANDSHAKE_CERTIFICATE => {
+                // certificate_list<3>, then certificate<3> entries
+                let first = body
+                    .get(3..6)
+                    .map(|len| u32::from_be_bytes([0, len[0], len[1], len[2]]) as usize)
+                    .and_then(|len| body.get(6..6 + len));
+                return match first {
+                    Some(der) => HandshakeScan::Found(der.to_vec()),
+                    None => HandshakeScan::Absent,
+                };
+            }
+            HANDSHAKE_SERVER_HELLO_DONE => return HandshakeScan::Absent,
+            _ => data = &data[4 + length..],
+        }
+    }
+    HandshakeScan::Incomplete
+}
+
+/// Build a TLS 1.2 ClientHello offering common ECDHE and RSA suites
+fn client_hello(server_name: Option<&str>) -> Vec<u8> {
+    const CIPHER_SUITES: [u16; 13] = [
+        0xc02f, 0xc030, 0xc02b, 0xc02c, 0xcca8, 0xcca9, 0xc013, 0xc014, 0x009c, 0x009d, 0x002f, 0x0035, 0x000a,
+    ];
+    const SIGNATURE_ALGORITHMS: [u16; 11] = [
+        0x0401, 0x0501, 0x0601, 0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0201, 0x0203,
+    ];
+
+    let mut extensions = Vec::new();
+    if let Some(name) = server_name {
+        let name = name.as_bytes();
+        let mut sni = Vec::new();
+        put_u16(&mut sni, name.len() as u16 + 3);
+        sni.push(0); // host_name
+        put_u16(&mut sni, name.len() as u16);
+        sni.extend_from_slice(name);
+        put_extension(&mut extensions, 0x0000, &sni);
+    }
+    // supported_groups: x25519, secp256r1, secp384r1
+    put_extension(&mut extensions, 0x000a, &[0, 6, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18]);
+    // ec_point_formats: uncompressed
+    put_extension(&mut extensions, 0x000b, &[1, 0]);
+    let mut signatures = Vec::new();
+    put_u16(&mut signatures, SIGNATURE_ALGORITHMS.len() as u16 * 2);
+    SIGNATURE_ALGORITHMS.iter().for_each(|s| put_u16(&mut signatures, *s));
+    put_extension(&mut extensions, 0x000d, &signatures);
+
+    let mut hello = vec![0x03, 0x03];
+    let mut random = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut random);
+    hello.extend_from_slice(&random);
+    hello.push(0); // empty session id
+    put_u16(&mut hello, CIPHER_SUITES.len() as u16 * 2);
+    CIPHER_SUITES.iter().for_each(|c| put_u16(&mut hello, *c));
+    hello.extend_from_slice(&[1, 0]); // null compression
+    put_u16(&mut hello, extensions.len() as u16);
+    hello.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&hello);
+
+    let mut record = vec![RECORD_HANDSHAKE, 0x03, 0x01];
+    put_u16(&mut record, handshake.len() as u16);
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_extension(buffer: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    put_u16(buffer, kind);
+    put_u16(buffer, data.len() as u16);
+    buffer.extend_from_slice(data);
+}
+
+/// Extract the subject CN, DNS SANs and expiry from a DER-encoded X.509
+/// certificate
+pub fn parse_certificate_names(der: &[u8]) -> Option<CertificateNames> {
+    let (_, certificate, _) = read_tlv(der)?;
+    let (_, tbs, _) = read_tlv(certificate)?;
+
+    let mut fields = Vec::new();
+    let mut rest = tbs;
+    while !rest.is_empty() {
+        let (tag, content, remaining) = read_tlv(rest)?;
+        fields.push((tag, content));
+        rest = remaining;
+    }
+
+    // version [0] is optional; subject follows serial, signature, issuer, validity
+    let offset = usize::from(fields.first()?.0 == 0xa0);
+    let validity = fields.get(offset + 3)?.1;
+    let subject = fields.get(offset + 4)?.1;
+    let extensions = fields.iter().find(|(tag, _)| *tag == 0xa3).map(|(_, c)| *c);
+
+    let mut dns_names = extensions.map(subject_alt_names).unwrap_or_default();
+    dns_names.dedup();
+
+    Some(CertificateNames {
+        common_name: common_name(subject),
+        dns_names,
+        not_after: not_after(validity),
+    })
+}
+
+/// notAfter of a Validity SEQUENCE, as UTCTime or GeneralizedTime
+fn not_after(validity: &[u8]) -> Option<DateTime<Utc>> {
+    let (_, _, rest) = read_tlv(validity)?;
+    let (tag, time, _) = read_tlv(rest)?;
+    let time = std::str::from_utf8(time).ok()?;
+    let parsed = match tag {
+        // UTCTime: two-digit years 50-99 are 19xx (RFC 5280)
+        0x17 => {
+            let year: u32 = time.get(..2)?.parse().ok()?;
+            let century = if year >= 50 { "19" } else { "20" };
+            NaiveDateTime::parse_from_str(&format!("{}{}", century, time), "%Y%m%d%H%M%SZ").ok()?
+        }
+        0x18 => NaiveDateTime::parse_from_str(time, "%Y%m%d%H%M%SZ").ok()?,
+        _ => return None,
+    };
+    Some(parsed.and_utc())
+}
+
+fn common_name(mut name: &[u8]) -> Option<String> {
+    // Name ::= SEQUENCE OF SET OF AttributeTypeAndValue
+    while !name.is_empty() {
+        let (_, set, rest) = read_tlv(name)?;
+        name = rest;
+        let (_, attribute, _) = read_tlv(set)?;
+        let (_, oid, value) = read_tlv(attribute)?;
+        if oid == OID_COMMON_NAME {
+            let (_, value, _) = read_tlv(value)?;
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+    }
+    None
+}
+
+fn subject_alt_names(extensions: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    let Some((_, mut list, _)) = read_tlv(extensions) else {
+        return names;
+    };
+
+    while let Some((_, extension, rest)) = read_tlv(list) {
+        list = rest;
+        let Some((_, oid, mut fields)) = read_tlv(extension) else {
+            continue;
+        };
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+
+        // Skip the optional critical flag to reach the OCTET STRING
+        while let Some((tag, value, rest)) = read_tlv(fields) {
+            fields = rest;
+            if tag != 0x04 {
+                continue;
+            }
+            let Some((_, mut general_names, _)) = read_tlv(value) else {
+                break;
+            };
+            while let Some((tag, name, rest)) = read_tlv(general_names) {
+                general_names = rest;
+                // dNSName [2] IA5String
+                if tag == 0x82 {
+                    names.push(String::from_utf8_lossy(name).trim_end_matches('.').to_lowercase());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Read one DER tag-length-value, returning (tag, content, remaining input)
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first = *data.get(1)? as usize;
+    let (length, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let length = data
+            .get(2..2 + count)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (length, 2 + count)
+    };
+    let end = header.checked_add(length)?;
+    Some((tag, data.get(header..end)?, &data[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.push(0x82);
+            out.extend_from_slice(&(content.len() as u16).to_be_bytes());
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// Minimal certificate: only the fields the parser walks are meaningful
+    fn certificate(common_name: &str, dns_names: &[&str]) -> Vec<u8> {
+        let attribute = [tlv(0x06, OID_COMMON_NAME), tlv(0x0c, common_name.as_bytes())].concat();
+        let subject = tlv(0x30, &tlv(0x31, &tlv(0x30, &attribute)));
+
+        let general_names: Vec<u8> = dns_names.iter().flat_map(|n| tlv(0x82, n.as_bytes())).collect();
+        let san = [
+            tlv(0x06, OID_SUBJECT_ALT_NAME),
+            tlv(0x04, &tlv(0x30, &general_names)),
+        ]
+        .concat();
+        let extensions = tlv(0xa3, &tlv(0x30, &tlv(0x30, &san)));
+
+        let tbs = [
+            tlv(0xa0, &tlv(0x02, &[2])),
+            tlv(0x02, &[1]),
+            tlv(0x30, &[]),
+            tlv(0x30, &[]),
+            tlv(0x30, &[tlv(0x17, b"250101000000Z"), tlv(0x18, b"20300101120000Z")].concat()),
+            subject,
+            tlv(0x30, &[]),
+            extensions,
+        ]
+        .concat();
+        tlv(0x30, &[tlv(0x30, &tbs), tlv(0x30, &[]), tlv(0x03, &[0])].concat())
+    }
+
+    #[test]
+    fn test_parse_certificate_names() {
+        let der = certificate("www.example.com", &["www.example.com", "API.Example.com.", "*.example.net"]);
+        let names = parse_certificate_names(&der).unwrap();
+        assert_eq!(names.common_name.as_deref(), Some("www.example.com"));
+        assert_eq!(names.dns_names, vec!["www.example.com", "api.example.com", "*.example.net"]);
+        assert_eq!(names.not_after.unwrap().to_rfc3339(), "2030-01-01T12:00:00+00:00");
+        assert_eq!(not_after(&[tlv(0x17, b"250101000000Z"), tlv(0x17, b"990630235959Z")].concat()).unwrap().to_rfc3339(), "1999-06-30T23:59:59+00:00");
+
+        assert!(parse_certificate_names(&der[..der.len() / 2]).is_none());
+    }
+
+    #[test]
+    fn test_client_hello_layout() {
+        let hello = client_hello(Some("example.com"));
+        assert_eq!(hello[0], RECORD_HANDSHAKE);
+        assert_eq!(u16::from_be_bytes([hello[3], hello[4]]) as usize, hello.len() - 5);
+        assert_eq!(hello[5], 0x01);
+        assert_eq!(&hello[9..11], &[0x03, 0x03]);
+        assert!(hello.windows(11).any(|w| w == b"example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_grab_from_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let der = certificate("internal", &["portal.corp.example"]);
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut hello = [0u8; 512];
+            let _ = socket.read(&mut hello).await;
+
+            // ServerHello stub, then the Certificate message split over two records
+            let mut messages = vec![2, 0, 0, 2, 3, 3];
+            let mut body = Vec::new();
+            body.extend_from_slice(&((der.len() + 3) as u32).to_be_bytes()[1..]);
+            body.extend_from_slice(&(der.len() as u32).to_be_bytes()[1..]);
+            body.extend_from_slice(&der);
+            messages.push(HANDSHAKE_CERTIFICATE);
+            messages.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+            messages.extend_from_slice(&body);
+
+            for chunk in messages.chunks(messages.len() / 2 + 1) {
+                let mut record = vec![RECORD_HANDSHAKE, 3, 3];
+                record.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+                record.extend_from_slice(chunk);
+                socket.write_all(&record).await.unwrap();
+            }
+        });
+
+        let names = TlsCertificateGrabber::new(2000)
+            .grab("127.0.0.1".parse().unwrap(), port, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(names.dns_names, vec!["portal.corp.example"]);
+    }
+}
@@ -0,0 +1,680 @@
+//! UDP service response fingerprints
+//!
+//! A UDP port that answers its probe usually gives away more than "open":
+//! the nameserver's `version.bind`, the NTP daemon and its stratum (or an
+//! exposed monlist), the SNMP agent's `sysDescr`, the services a device
+//! announces over mDNS or SSDP, the NetBIOS names and MAC of a Windows or
+//! Samba host. The parsers here read the responses to the UDP scanner's
+//! probes into a [`ServiceFingerprint`]. An NTP server that answers is asked
+//! for its system variables ([`NTP_READVAR`]) and whether it serves monlist
+//! ([`NTP_MONLIST`]) as well.
+
+use super::fingerprint::ServiceFingerprint;
+use crate::scanner::dns_discovery::read_name;
+use std::net::Ipv4Addr;
+use std::ops::Range;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_NBSTAT: u16 = 33;
+
+/// OID of `sysDescr.0`, which answers the SNMP probe's GetNextRequest
+const SYS_DESCR: &[u8] = &[0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00];
+
+/// NTP control (mode 6) readvar request for the system variables
+pub const NTP_READVAR: &[u8] = &[0x16, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+/// NTP private (mode 7) `MON_GETLIST_1` request, answered by servers that
+/// can be abused for traffic amplification
+pub const NTP_MONLIST: &[u8] = &[0x17, 0x00, 0x03, 0x2a, 0x00, 0x00, 0x00, 0x00];
+
+/// Substrings of version strings naming the nameserver software
+const DNS_PRODUCTS: &[(&str, &str)] = &[
+    ("dnsmasq", "dnsmasq"),
+    ("unbound", "Unbound"),
+    ("powerdns", "PowerDNS"),
+    ("knot", "Knot DNS"),
+    ("bind", "ISC BIND"),
+    ("microsoft", "Microsoft DNS"),
+];
+
+/// Substrings of system descriptions naming the operating system
+const OS_NAMES: &[(&str, &str)] = &[
+    ("windows", "Windows"),
+    ("linux", "Linux"),
+    ("cisco ios", "Cisco IOS"),
+    ("junos", "Junos OS"),
+    ("freebsd", "FreeBSD"),
+    ("routeros", "RouterOS"),
+    ("darwin", "macOS"),
+];
+
+/// Fingerprint the response of the UDP service on `port`, if the port's
+/// protocol has a parser and the response parses
+pub fn fingerprint(port: u16, response: &[u8]) -> Option<ServiceFingerprint> {
+    match port {
+        53 => dns(response),
+        123 => ntp(response),
+        137 => netbios(response),
+        161 => snmp(response),
+        1900 => ssdp(response),
+        5353 => mdns(response),
+        _ => None,
+    }
+}
+
+fn service(name: &str, confidence: f32) -> ServiceFingerprint {
+    ServiceFingerprint {
+        service_name: name.to_string(),
+        product: None,
+        version: None,
+        os_info: None,
+        cpe: None,
+        extra_info: None,
+        confidence,
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn os_name(text: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    OS_NAMES
+        .iter()
+        .find(|(key, _)| lower.contains(key))
+        .map(|(_, name)| name.to_string())
+}
+
+/// First token of `text` that looks like a version number, e.g. "9.18.19"
+/// out of "9.18.19-1~deb12u1-Debian"
+fn version_in(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || c == '-' || c == '/')
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()) && token.contains('.'))
+        .map(|token| token.trim_end_matches(|c: char| !c.is_ascii_alphanumeric()).to_string())
+}
+
+/// A resource record of a DNS message and the range of its data
+struct Record {
+    name: String,
+    rtype: u16,
+    data: Range<usize>,
+}
+
+/// Resource records of every section of a DNS response, up to the first
+/// one that does not parse
+fn dns_records(message: &[u8]) -> Option<Vec<Record>> {
+    if u16_at(message, 2)? & 0x8000 == 0 {
+        return None;
+    }
+    let questions = u16_at(message, 4)?;
+    let count: usize = [6, 8, 10].iter().map(|&pos| u16_at(message, pos).map(usize::from)).sum::<Option<usize>>()?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(message, pos).ok()?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let Ok((name, after)) = read_name(message, pos) else { break };
+        let (Some(rtype), Some(len)) = (u16_at(message, after), u16_at(message, after + 8)) else { break };
+        let data = after + 10..after + 10 + len as usize;
+        if data.end > message.len() {
+            break;
+        }
+        pos = data.end;
+        records.push(Record { name, rtype, data });
+    }
+    Some(records)
+}
+
+/// Character strings of TXT record data
+fn txt_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut rest = data;
+    while let Some((&len, tail)) = rest.split_first() {
+        let len = (len as usize).min(tail.len());
+        strings.push(String::from_utf8_lossy(&tail[..len]).into_owned());
+        rest = &tail[len..];
+    }
+    strings
+}
+
+/// Nameserver answering the `version.bind` CHAOS TXT probe
+fn dns(response: &[u8]) -> Option<ServiceFingerprint> {
+    let records = dns_records(response)?;
+    let mut fp = service("domain", 0.7);
+    let text = records
+        .iter()
+        .find(|r| r.rtype == TYPE_TXT)
+        .map(|r| txt_strings(&response[r.data.clone()]).concat().trim().to_string())
+        .filter(|text| !text.is_empty());
+    let Some(text) = text else {
+        fp.extra_info = Some("version.bind not disclosed".to_string());
+        return Some(fp);
+    };
+
+    let lower = text.to_lowercase();
+    fp.product = DNS_PRODUCTS
+        .iter()
+        .find(|(key, _)| lower.contains(key))
+        .map(|(_, product)| product.to_string())
+        // BIND answers with its bare version number
+        .or_else(|| text.starts_with(|c: char| c.is_ascii_digit()).then(|| "ISC BIND".to_string()));
+    fp.version = version_in(&text);
+    if fp.product.is_none() && fp.version.is_none() {
+        fp.extra_info = Some(format!("version.bind \"{}\"", text));
+    }
+    fp.confidence = 0.9;
+    Some(fp)
+}
+
+/// Value of `name` in the `name=value, name="value"` list of an NTP mode 6
+/// readvar response
+fn ntp_variable(text: &str, name: &str) -> Option<String> {
+    let key = format!("{}=", name);
+    let (start, _) = text
+        .match_indices(&key)
+        .find(|(i, _)| *i == 0 || matches!(text.as_bytes()[i - 1], b' ' | b',' | b'\r' | b'\n'))?;
+    let rest = &text[start + key.len()..];
+    let value = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split(|c: char| c == ',' || c.is_whitespace()).next()?,
+    };
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// NTP server reply (mode 4), control readvar reply (mode 6) or private
+/// mode 7 reply, which answering monlist makes an amplifier
+fn ntp(response: &[u8]) -> Option<ServiceFingerprint> {
+    let first = *response.first()?;
+    let (version, mode) = ((first >> 3) & 0x07, first & 0x07);
+    let mut fp = service("ntp", 0.9);
+    match mode {
+        4 if response.len() >= 48 => {
+            let stratum = response[1];
+            let refid = &response[12..16];
+            // Stratum 0-1 carry a source code such as "GPS" or a kiss code, the rest an upstream server
+            let reference = if stratum <= 1 {
+                String::from_utf8_lossy(refid).trim_end_matches('\0').to_string()
+            } else {
+                Ipv4Addr::new(refid[0], refid[1], refid[2], refid[3]).to_string()
+            };
+            fp.extra_info = Some(format!("NTPv{}, stratum {}, reference {}", version, stratum, reference));
+        }
+        6 => {
+            let count = u16_at(response, 10)? as usize;
+            let data = response.get(12..(12 + count).min(response.len()))?;
+            let text = String::from_utf8_lossy(data);
+            // e.g. version="ntpd 4.2.8p15@1.3728-o Wed Sep 23 11:46:38 UTC 2020 (1)"
+            if let Some(daemon) = ntp_variable(&text, "version") {
+                let mut words = daemon.split_whitespace();
+                fp.product = words.next().map(str::to_string);
+                fp.version = words.next().map(|v| v.split('@').next().unwrap_or(v).to_string());
+            }
+            fp.os_info = ntp_variable(&text, "system");
+            fp.extra_info = ntp_variable(&text, "stratum").map(|stratum| format!("NTPv{}, stratum {}", version, stratum));
+        }
+        7 => {
+            // Mode 7 header: flags, sequence, implementation, request code
+            let request = *response.get(3)?;
+            fp.product = Some("ntpd".to_string());
+            fp.extra_info = Some(
+                match request {
+                    20 | 42 => "monlist enabled (traffic amplification risk)",
+                    _ => "mode 7 queries answered",
+                }
+                .to_string(),
+            );
+        }
+        _ => return None,
+    }
+    Some(fp)
+}
+
+/// Fold the reply to [`NTP_READVAR`] or [`NTP_MONLIST`] into the
+/// fingerprint of the server's mode 4 reply
+pub fn merge_ntp(fingerprint: &mut ServiceFingerprint, reply: &[u8]) {
+    let Some(more) = ntp(reply) else {
+        return;
+    };
+    fingerprint.product = fingerprint.product.take().or(more.product);
+    fingerprint.version = fingerprint.version.take().or(more.version);
+    fingerprint.os_info = fingerprint.os_info.take().or(more.os_info);
+    fingerprint.extra_info = match (fingerprint.extra_info.take(), more.extra_info) {
+        // Mode 7 adds a finding; readvar only repeats the stratum
+        (Some(info), Some(finding)) if reply[0] & 0x07 == 7 => Some(format!("{}; {}", info, finding)),
+        (info, more) => info.or(more),
+    };
+}
+
+/// Tag, contents and end offset of the BER element at `pos`
+fn ber(data: &[u8], pos: usize) -> Option<(u8, &[u8], usize)> {
+    let tag = *data.get(pos)?;
+    let first = *data.get(pos + 1)? as usize;
+    let (len, start) = if first & 0x80 == 0 {
+        (first, pos + 2)
+    } else {
+        let octets = first & 0x7f;
+        if octets == 0 || octets > 4 {
+            return None;
+        }
+        let bytes = data.get(pos + 2..pos + 2 + octets)?;
+        (bytes.iter().fold(0usize, |len, &b| len << 8 | b as usize), pos + 2 + octets)
+    };
+    let contents = data.get(start..start.checked_add(len)?)?;
+    Some((tag, contents, start + len))
+}
+
+/// SNMPv1/v2c GetResponse, with `sysDescr.0` when the agent returned it
+fn snmp(response: &[u8]) -> Option<ServiceFingerprint> {
+    let Some((0x30, message, _)) = ber(response, 0) else { return None };
+    let Some((0x02, version, after)) = ber(message, 0) else { return None };
+    let Some((0x04, community, after)) = ber(message, after) else { return None };
+    let Some((0xa2, pdu, _)) = ber(message, after) else { return None };
+    let version = match version {
+        [0] => "SNMPv1",
+        [1] => "SNMPv2c",
+        _ => return None,
+    };
+
+    // Request ID, error status and error index precede the bindings
+    let mut pos = 0;
+    for _ in 0..3 {
+        pos = ber(pdu, pos)?.2;
+    }
+    let description = ber(pdu, pos)
+        .filter(|(tag, _, _)| *tag == 0x30)
+        .and_then(|(_, bindings, _)| ber(bindings, 0))
+        .and_then(|(_, binding, _)| {
+            let (tag, oid, after) = ber(binding, 0)?;
+            let (value_tag, value, _) = ber(binding, after)?;
+            (tag == 0x06 && oid == SYS_DESCR && value_tag == 0x04).then(|| String::from_utf8_lossy(value).into_owned())
+        })
+        .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string));
+
+    let mut fp = service("snmp", 0.8);
+    let mut extra = format!("{}, community \"{}\"", version, String::from_utf8_lossy(community));
+    if let Some(description) = description {
+        fp.os_info = os_name(&description);
+        extra.push_str(&format!(", {}", description));
+        fp.confidence = 0.95;
+    }
+    fp.extra_info = Some(extra);
+    Some(fp)
+}
+
+/// Headers of an SSDP search answer or `NOTIFY` announcement
+pub(crate) fn ssdp_headers(response: &[u8]) -> Option<Vec<(&str, &str)>> {
+    let text = std::str::from_utf8(response).ok()?;
+    let mut lines = text.lines();
+    let status = lines.next()?;
+    if !status.starts_with("HTTP/1.") && !status.starts_with("NOTIFY ") {
+        return None;
+    }
+    Some(
+        lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect(),
+    )
+}
+
+/// Non-empty value of the SSDP header `name`, which is case-insensitive
+pub(crate) fn ssdp_header<'a>(headers: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.is_empty())
+        .map(|(_, value)| *value)
+}
+
+/// SSDP answer to M-SEARCH: the `SERVER` header names the OS, UPnP version
+/// and product, `LOCATION` the device description
+fn ssdp(response: &[u8]) -> Option<ServiceFingerprint> {
+    let headers = ssdp_headers(response)?;
+    let header = |name: &str| ssdp_header(&headers, name);
+
+    let mut fp = service("ssdp", 0.8);
+    if let Some(server) = header("server") {
+        // "Linux/3.14 UPnP/1.0 MiniUPnPd/2.1", some with comma separators
+        let tokens: Vec<&str> = if server.contains(',') {
+            server.split(',').map(str::trim).collect()
+        } else {
+            server.split_whitespace().collect()
+        };
+        if let Some(product) = tokens.iter().skip(1).rev().find(|t| !t.to_ascii_lowercase().starts_with("upnp/")) {
+            let (name, version) = product.split_once('/').unwrap_or((product, ""));
+            fp.product = Some(name.to_string());
+            fp.version = (!version.is_empty()).then(|| version.to_string());
+        }
+        fp.os_info = tokens.first().map(|os| os.replace('/', " "));
+        fp.confidence = 0.9;
+    }
+    let details: Vec<String> = [("location", "LOCATION"), ("st", "ST")]
+        .iter()
+        .filter_map(|(name, label)| header(name).map(|value| format!("{} {}", label, value)))
+        .collect();
+    fp.extra_info = (!details.is_empty()).then(|| details.join(", "));
+    Some(fp)
+}
+
+/// Computer name, workgroup and MAC of a NetBIOS node status answer
+type NodeStatus<'a> = (Option<String>, Option<String>, Option<&'a [u8]>);
+
+fn node_status(response: &[u8]) -> Option<NodeStatus<'_>> {
+    if u16_at(response, 2)? & 0x8000 == 0 || u16_at(response, 6)? == 0 {
+        return None;
+    }
+    let (_, after) = read_name(response, 12).ok()?;
+    if u16_at(response, after)? != TYPE_NBSTAT {
+        return None;
+    }
+    let start = after + 10;
+    let count = *response.get(start)? as usize;
+    let names = response.get(start + 1..start + 1 + count * 18)?;
+
+    let (mut computer, mut workgroup) = (None, None);
+    for entry in names.chunks(18) {
+        let name = String::from_utf8_lossy(&entry[..15]).trim_end().to_string();
+        let group = entry[16] & 0x80 != 0;
+        match (entry[15], group) {
+            (0x00, false) if computer.is_none() => computer = Some(name),
+            (0x00, true) if workgroup.is_none() => workgroup = Some(name),
+            _ => {}
+        }
+    }
+    let mac = response.get(start + 1 + count * 18..start + 7 + count * 18);
+    Some((computer, workgroup, mac))
+}
+
+/// Names a host gives itself in its answer to the NetBIOS or mDNS probe:
+/// the computer name and workgroup, or the mDNS host and instance names
+pub(crate) fn announced_names(port: u16, response: &[u8]) -> Vec<String> {
+    match port {
+        137 => node_status(response)
+            .map(|(computer, workgroup, _)| computer.into_iter().chain(workgroup).collect())
+            .unwrap_or_default(),
+        5353 => mdns_announcement(response)
+            .map(|announcement| announcement.hosts.into_iter().chain(announcement.instances).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// NetBIOS node status (NBSTAT) reply: the registered names and the MAC
+fn netbios(response: &[u8]) -> Option<ServiceFingerprint> {
+    let (computer, workgroup, mac) = node_status(response)?;
+
+    let mut fp = service("netbios-ns", 0.9);
+    let mut details = Vec::new();
+    if let Some(computer) = computer {
+        details.push(format!("name {}", computer));
+    }
+    if let Some(workgroup) = workgroup {
+        details.push(format!("workgroup {}", workgroup));
+    }
+    if let Some(mac) = mac {
+        // Samba's nmbd reports an all-zero MAC
+        if mac.iter().all(|&b| b == 0) {
+            fp.product = Some("Samba nmbd".to_string());
+        } else {
+            let mac: Vec<String> = mac.iter().map(|b| format!("{:02x}", b)).collect();
+            details.push(format!("MAC {}", mac.join(":")));
+        }
+    }
+    fp.extra_info = (!details.is_empty()).then(|| details.join(", "));
+    Some(fp)
+}
+
+/// What an mDNS response announces
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct MdnsAnnouncement {
+    /// Service types, e.g. "_googlecast._tcp"
+    pub services: Vec<String>,
+    /// Service instance names, e.g. "Living Room"
+    pub instances: Vec<String>,
+    pub hosts: Vec<String>,
+    /// Device model from a TXT record
+    pub model: Option<String>,
+}
+
+fn push_unique(list: &mut Vec<String>, value: String) {
+    if !value.is_empty() && !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+/// Service types, instances, host names and model of an mDNS response,
+/// whether it answers a query or is an unsolicited announcement
+pub(crate) fn mdns_announcement(response: &[u8]) -> Option<MdnsAnnouncement> {
+    let records = dns_records(response)?;
+    let mut announcement = MdnsAnnouncement::default();
+    // "Living Room._googlecast._tcp.local" names instance "Living Room" of "_googlecast._tcp"
+    let instance = |name: &str| name.split_once("._").map(|(instance, _)| instance.to_string());
+    for record in &records {
+        match record.rtype {
+            TYPE_PTR => {
+                let Ok((target, _)) = read_name(response, record.data.start) else { continue };
+                // The enumeration lists service types; other PTRs name an instance of one
+                if record.name.starts_with("_services._dns-sd.") {
+                    push_unique(&mut announcement.services, target.trim_end_matches(".local").to_string());
+                } else {
+                    push_unique(&mut announcement.services, record.name.trim_end_matches(".local").to_string());
+                    if let Some(name) = instance(&target) {
+                        push_unique(&mut announcement.instances, name);
+                    }
+                }
+            }
+            TYPE_SRV => {
+                if let Ok((host, _)) = read_name(response, record.data.start + 6) {
+                    push_unique(&mut announcement.hosts, host);
+                }
+                if let Some(name) = instance(&record.name) {
+                    push_unique(&mut announcement.instances, name);
+                }
+            }
+            TYPE_A => push_unique(&mut announcement.hosts, record.name.clone()),
+            TYPE_TXT if announcement.model.is_none() => {
+                announcement.model = txt_strings(&response[record.data.clone()]).into_iter().find_map(|entry| {
+                    let (key, value) = entry.split_once('=')?;
+                    let key = key.to_ascii_lowercase();
+                    (matches!(key.as_str(), "md" | "model" | "am" | "ty" | "usb_mdl") && !value.is_empty())
+                        .then(|| value.to_string())
+                });
+            }
+            _ => {}
+        }
+    }
+    Some(announcement)
+}
+
+/// mDNS answer to the DNS-SD service enumeration query: announced service
+/// types, host names and the device model from TXT records
+fn mdns(response: &[u8]) -> Option<ServiceFingerprint> {
+    let announcement = mdns_announcement(response)?;
+    let mut fp = service("mdns", 0.7);
+    let mut details = Vec::new();
+    if !announcement.services.is_empty() {
+        details.push(format!("services {}", announcement.services.join(" ")));
+        fp.confidence = 0.9;
+    }
+    if !announcement.hosts.is_empty() {
+        details.push(format!("host {}", announcement.hosts.join(" ")));
+    }
+    fp.product = announcement.model;
+    fp.extra_info = (!details.is_empty()).then(|| details.join(", "));
+    Some(fp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(text: &str) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for label in text.split('.') {
+            encoded.push(label.len() as u8);
+            encoded.extend_from_slice(label.as_bytes());
+        }
+        encoded.push(0);
+        encoded
+    }
+
+    fn txt(strings: &[&str]) -> Vec<u8> {
+        strings.iter().flat_map(|s| std::iter::once(s.len() as u8).chain(s.bytes())).collect()
+    }
+
+    /// DNS response with one question and the given answer records
+    fn dns_response(question: &str, qtype: u16, answers: &[(Vec<u8>, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut message = vec![0x00, 0x00, 0x84, 0x00, 0x00, 0x01, 0x00, answers.len() as u8, 0, 0, 0, 0];
+        message.extend(name(question));
+        message.extend_from_slice(&qtype.to_be_bytes());
+        message.extend_from_slice(&[0x00, 0x01]);
+        for (owner, rtype, data) in answers {
+            message.extend_from_slice(owner);
+            message.extend_from_slice(&rtype.to_be_bytes());
+            message.extend_from_slice(&[0x00, 0x01, 0, 0, 0x0e, 0x10]);
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+        message
+    }
+
+    #[test]
+    fn test_dns_and_mdns_fingerprints() {
+        let pointer = vec![0xc0, 0x0c];
+        let response = dns_response("version.bind", TYPE_TXT, &[(pointer.clone(), TYPE_TXT, txt(&["dnsmasq-2.85"]))]);
+        let fp = fingerprint(53, &response).unwrap();
+        assert_eq!(fp.product.as_deref(), Some("dnsmasq"));
+        assert_eq!(fp.version.as_deref(), Some("2.85"));
+
+        let bind = dns_response("version.bind", TYPE_TXT, &[(pointer, TYPE_TXT, txt(&["9.18.19-1~deb12u1-Debian"]))]);
+        assert_eq!(fingerprint(53, &bind).unwrap().to_string(), "domain (ISC BIND) v9.18.19 [90% confidence]");
+        assert_eq!(
+            fingerprint(53, &dns_response("version.bind", TYPE_TXT, &[])).unwrap().extra_info.as_deref(),
+            Some("version.bind not disclosed")
+        );
+
+        let services = "_services._dns-sd._udp.local";
+        let response = dns_response(
+            services,
+            TYPE_PTR,
+            &[
+                (vec![0xc0, 0x0c], TYPE_PTR, name("_googlecast._tcp.local")),
+                (vec![0xc0, 0x0c], TYPE_PTR, name("_airplay._tcp.local")),
+                (name("Living Room._googlecast._tcp.local"), TYPE_TXT, txt(&["id=42", "md=Chromecast Ultra"])),
+                (name("chromecast.local"), TYPE_A, vec![192, 0, 2, 10]),
+            ],
+        );
+        let fp = fingerprint(5353, &response).unwrap();
+        assert_eq!(fp.product.as_deref(), Some("Chromecast Ultra"));
+        assert_eq!(
+            fp.extra_info.as_deref(),
+            Some("services _googlecast._tcp _airplay._tcp, host chromecast.local")
+        );
+        assert_eq!(announced_names(5353, &response), vec!["chromecast.local"]);
+        // A query is not a response
+        let mut query = response;
+        query[2] = 0x00;
+        assert!(fingerprint(5353, &query).is_none());
+    }
+
+    #[test]
+    fn test_ntp_fingerprints() {
+        let mut reply = vec![0u8; 48];
+        reply[0] = 0x24; // v4, server
+        reply[1] = 2;
+        reply[12..16].copy_from_slice(&[192, 0, 2, 1]);
+        assert_eq!(
+            fingerprint(123, &reply).unwrap().extra_info.as_deref(),
+            Some("NTPv4, stratum 2, reference 192.0.2.1")
+        );
+        reply[1] = 1;
+        reply[12..16].copy_from_slice(b"GPS\0");
+        assert!(fingerprint(123, &reply).unwrap().extra_info.unwrap().ends_with("reference GPS"));
+
+        let vars = b"version=\"ntpd 4.2.8p15@1.3728-o Wed Sep 23 11:46:38 UTC 2020 (1)\", processor=\"x86_64\", system=\"Linux/5.10.0\", leap=00, stratum=3";
+        let mut control = vec![0x16, 0x82, 0, 1, 0, 0, 0, 0, 0, 0];
+        control.extend_from_slice(&(vars.len() as u16).to_be_bytes());
+        control.extend_from_slice(vars);
+        let fp = fingerprint(123, &control).unwrap();
+        assert_eq!((fp.product.as_deref(), fp.version.as_deref()), (Some("ntpd"), Some("4.2.8p15")));
+        assert_eq!(fp.os_info.as_deref(), Some("Linux/5.10.0"));
+        assert_eq!(fp.extra_info.as_deref(), Some("NTPv2, stratum 3"));
+
+        let monlist = [0x97, 0x00, 0x03, 0x2a, 0x00, 0x06, 0x00, 0x48];
+        assert!(fingerprint(123, &monlist).unwrap().extra_info.unwrap().starts_with("monlist enabled"));
+        assert!(fingerprint(123, &[0x1b]).is_none());
+
+        // The follow-up replies fill in the server reply's fingerprint
+        let mut fp = fingerprint(123, &reply).unwrap();
+        merge_ntp(&mut fp, &control);
+        merge_ntp(&mut fp, &monlist);
+        merge_ntp(&mut fp, &[0x1b]);
+        assert_eq!((fp.product.as_deref(), fp.os_info.as_deref()), (Some("ntpd"), Some("Linux/5.10.0")));
+        assert_eq!(
+            fp.extra_info.as_deref(),
+            Some("NTPv4, stratum 1, reference GPS; monlist enabled (traffic amplification risk)")
+        );
+        assert_eq!(NTP_READVAR[0] & 0x07, 6);
+        assert_eq!(NTP_MONLIST[0] & 0x07, 7);
+    }
+
+    #[test]
+    fn test_snmp_fingerprint() {
+        let descr = b"Linux gw 5.15.0-91-generic #101-Ubuntu SMP x86_64";
+        let mut binding = vec![0x06, SYS_DESCR.len() as u8];
+        binding.extend_from_slice(SYS_DESCR);
+        binding.extend_from_slice(&[0x04, descr.len() as u8]);
+        binding.extend_from_slice(descr);
+        let bindings = [&[0x30, binding.len() as u8][..], &binding].concat();
+        let varbinds = [&[0x30, bindings.len() as u8][..], &bindings].concat();
+        let pdu_body = [&[0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00][..], &varbinds].concat();
+        let pdu = [&[0xa2, pdu_body.len() as u8][..], &pdu_body].concat();
+        let body = [&[0x02, 0x01, 0x01, 0x04, 0x06][..], b"public", &pdu].concat();
+        // Long-form length on the outer sequence
+        let response = [&[0x30, 0x81, body.len() as u8][..], &body].concat();
+
+        let fp = fingerprint(161, &response).unwrap();
+        assert_eq!(fp.os_info.as_deref(), Some("Linux"));
+        assert_eq!(
+            fp.extra_info.as_deref(),
+            Some("SNMPv2c, community \"public\", Linux gw 5.15.0-91-generic #101-Ubuntu SMP x86_64")
+        );
+        assert!(fingerprint(161, &response[..response.len() - 4]).is_none());
+    }
+
+    #[test]
+    fn test_ssdp_and_netbios_fingerprints() {
+        let ssdp = b"HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nST: upnp:rootdevice\r\nServer: Linux/3.14 UPnP/1.0 MiniUPnPd/2.1\r\nLocation: http://192.0.2.1:5000/rootDesc.xml\r\n\r\n";
+        let fp = fingerprint(1900, ssdp).unwrap();
+        assert_eq!(fp.to_string(), "ssdp (MiniUPnPd) v2.1 - LOCATION http://192.0.2.1:5000/rootDesc.xml, ST upnp:rootdevice [90% confidence]");
+        assert_eq!(fp.os_info.as_deref(), Some("Linux 3.14"));
+        assert!(fingerprint(1900, b"\x00\x00").is_none());
+
+        let mut nbstat = vec![0x13, 0x37, 0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 0];
+        nbstat.push(0x20);
+        nbstat.extend_from_slice(b"CKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA");
+        nbstat.push(0);
+        nbstat.extend_from_slice(&[0x00, 0x21, 0x00, 0x01, 0, 0, 0, 0]);
+        let entries: [(&str, u8, u8); 3] = [("FILESRV", 0x00, 0x04), ("WORKGROUP", 0x00, 0x84), ("FILESRV", 0x20, 0x04)];
+        nbstat.extend_from_slice(&((1 + entries.len() * 18 + 6) as u16).to_be_bytes());
+        nbstat.push(entries.len() as u8);
+        for (name, suffix, flags) in entries {
+            nbstat.extend_from_slice(format!("{:<15}", name).as_bytes());
+            nbstat.extend_from_slice(&[suffix, flags, 0x00]);
+        }
+        nbstat.extend_from_slice(&[0x00, 0x0c, 0x29, 0xab, 0xcd, 0xef]);
+        let fp = fingerprint(137, &nbstat).unwrap();
+        assert_eq!(fp.extra_info.as_deref(), Some("name FILESRV, workgroup WORKGROUP, MAC 00:0c:29:ab:cd:ef"));
+        assert_eq!(announced_names(137, &nbstat), vec!["FILESRV", "WORKGROUP"]);
+        assert!(announced_names(161, &nbstat).is_empty());
+
+        let len = nbstat.len();
+        nbstat[len - 6..].fill(0);
+        assert_eq!(fingerprint(137, &nbstat).unwrap().product.as_deref(), Some("Samba nmbd"));
+    }
+}
@@ -3,12 +3,18 @@
 /// This module implements the agent side of distributed scanning, which receives
 /// scan jobs from the scheduler, executes them, and reports results back.
 
+use super::constraints::AgentPlacement;
+use super::spool::{ResultChunk, ResultSpool};
+use crate::capabilities::Capabilities;
+use crate::config::{AppConfig, ConfigWatcher};
 use crate::error::ScanResult;
-use crate::scanner::{Scanner, ScanType};
+use crate::scanner::{ResourceUsage, Scanner, ScanType};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
 /// Agent configuration
@@ -19,6 +25,15 @@ pub struct AgentConfig {
     pub listen_address: String,
     pub heartbeat_interval_seconds: u64,
     pub max_concurrent_jobs: usize,
+    /// Geographic region, advertised for job constraints
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Network zone (e.g. "dmz"), advertised for job constraints
+    #[serde(default)]
+    pub zone: Option<String>,
+    /// Keep completed results here until the scheduler acknowledged them
+    #[serde(default)]
+    pub spool_dir: Option<PathBuf>,
 }
 
 impl Default for AgentConfig {
@@ -29,6 +44,9 @@ impl Default for AgentConfig {
             listen_address: "0.0.0.0:8081".to_string(),
             heartbeat_interval_seconds: 30,
             max_concurrent_jobs: 1,
+            region: None,
+            zone: None,
+            spool_dir: None,
         }
     }
 }
@@ -61,6 +79,13 @@ pub struct ScanAgent {
     status: Arc<RwLock<AgentStatus>>,
     scanner: Scanner,
     current_job: Arc<RwLock<Option<String>>>,
+    /// Reloads configuration while the agent runs
+    config_watcher: Option<ConfigWatcher>,
+    /// Live configuration from the watcher, taken up before each job
+    reloaded: Option<watch::Receiver<Arc<AppConfig>>>,
+    stop_watcher: CancellationToken,
+    /// Results awaiting delivery, when `spool_dir` is set
+    spool: Option<ResultSpool>,
 }
 
 impl ScanAgent {
@@ -73,15 +98,50 @@ impl ScanAgent {
         info!("Initializing scan agent: {}", config.agent_id);
         
         let scanner = Scanner::new(scanner_config);
+        let spool = config.spool_dir.as_ref().map(ResultSpool::open).transpose()?;
         
         Ok(Self {
             config,
             status: Arc::new(RwLock::new(AgentStatus::Initializing)),
             scanner,
             current_job: Arc::new(RwLock::new(None)),
+            config_watcher: None,
+            reloaded: None,
+            stop_watcher: CancellationToken::new(),
+            spool,
         })
     }
 
+    /// Refuse jobs with targets outside the installation's authorized scope
+    /// (`security.scope`)
+    pub fn with_authorized_scope(mut self, scope: crate::scanner::AuthorizedScope) -> Self {
+        self.scanner = self.scanner.with_authorized_scope(scope);
+        self
+    }
+
+    /// Hold or refuse jobs during blackout windows (`security.blackouts`),
+    /// also when the scheduler that assigned them does not know the windows
+    pub fn with_blackouts(mut self, blackouts: crate::scanner::BlackoutCalendar) -> Self {
+        self.scanner = self.scanner.with_blackouts(blackouts);
+        self
+    }
+
+    /// Build the scanner's throttle with these `[throttling]` thresholds
+    pub fn with_throttling(mut self, throttling: crate::config::ThrottlingConfig) -> Self {
+        self.scanner = self.scanner.with_throttling(throttling);
+        self
+    }
+
+    /// Hot-reload configuration while the agent runs
+    ///
+    /// The watcher starts with the agent and stops at shutdown. Reloaded
+    /// scanner settings apply from the next job.
+    pub fn with_config_watcher(mut self, watcher: ConfigWatcher) -> Self {
+        self.reloaded = Some(watcher.subscribe());
+        self.config_watcher = Some(watcher);
+        self
+    }
+
     /// Start the agent
     /// 
     /// This will register with the scheduler and start listening for jobs
@@ -100,10 +160,25 @@ impl ScanAgent {
         
         // Start heartbeat task
         self.start_heartbeat_task();
+
+        self.watch_config();
         
         Ok(())
     }
 
+    /// Start the configuration watcher given with
+    /// [`with_config_watcher`](Self::with_config_watcher), if not yet running
+    ///
+    /// [`start`](Self::start) calls this; an in-process agent that is not
+    /// started calls it when it is put to work.
+    pub fn watch_config(&mut self) {
+        if let Some(watcher) = self.config_watcher.take() {
+            if let Err(e) = watcher.spawn(self.stop_watcher.clone()) {
+                warn!("Configuration changes will need a restart: {}", e);
+            }
+        }
+    }
+
     /// Register with the scheduler
     async fn register_with_scheduler(&self) -> ScanResult<()> {
         info!(
@@ -113,7 +188,11 @@ impl ScanAgent {
         
         // TODO: Implement actual HTTP/gRPC registration
         // For now, this is a framework implementation
-        debug!("Agent registration (framework mode)");
+        debug!(
+            "Agent registration (framework mode), advertising scan types {:?} and {:?}",
+            self.capabilities().scan_types,
+            self.placement()
+        );
         
         Ok(())
     }
@@ -167,6 +246,14 @@ impl ScanAgent {
             *current_job = Some(job_id.clone());
         }
         
+        // Settings reloaded since the last job
+        if let Some(ref mut reloaded) = self.reloaded {
+            if reloaded.has_changed().unwrap_or(false) {
+                let config = reloaded.borrow_and_update().clone();
+                self.scanner.apply_reloaded(&config);
+            }
+        }
+
         // Execute the scan
         let result = self.scanner
             .scan_multiple(targets, ports, scan_types)
@@ -196,6 +283,46 @@ impl ScanAgent {
         }
     }
 
+    /// Execute a scan job and package its results as a chunk for delivery
+    ///
+    /// With a spool the chunk is on disk before this returns, so it survives
+    /// until [`ScanAgent::acknowledge_chunk`] once the scheduler stored it.
+    pub async fn execute_chunk(
+        &mut self,
+        job_id: String,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+    ) -> ScanResult<ResultChunk> {
+        let results = self.execute_job(job_id.clone(), targets, ports, scan_types).await?;
+        let chunk = ResultChunk::new(job_id, self.config.agent_id.clone(), results);
+        if let Some(ref spool) = self.spool {
+            spool.store(&chunk)?;
+        }
+        Ok(chunk)
+    }
+
+    /// Drop a delivered chunk from the spool
+    pub fn acknowledge_chunk(&self, chunk_id: &str) -> ScanResult<()> {
+        match self.spool {
+            Some(ref spool) => spool.acknowledge(chunk_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Deliver spooled chunks, e.g. those left by a previous run, with
+    /// reconnect backoff until delivered or the agent shuts down
+    pub async fn deliver_spooled<F, Fut>(&self, deliver: F) -> ScanResult<usize>
+    where
+        F: FnMut(ResultChunk) -> Fut,
+        Fut: std::future::Future<Output = ScanResult<()>>,
+    {
+        match self.spool {
+            Some(ref spool) => spool.deliver(&self.stop_watcher, deliver).await,
+            None => Ok(0),
+        }
+    }
+
     /// Get agent status
     pub async fn get_status(&self) -> AgentStatus {
         *self.status.read().await
@@ -211,6 +338,21 @@ impl ScanAgent {
         &self.config.agent_id
     }
 
+    /// Capabilities this agent advertises to the scheduler
+    pub fn capabilities(&self) -> Capabilities {
+        crate::capabilities().clone()
+    }
+
+    /// Region, zone and probe rate cap (`scanner.max_rate`) this agent
+    /// advertises to the scheduler
+    pub fn placement(&self) -> AgentPlacement {
+        AgentPlacement {
+            region: self.config.region.clone(),
+            zone: self.config.zone.clone(),
+            max_rate: self.scanner.config().max_rate,
+        }
+    }
+
     /// Shutdown the agent
     pub async fn shutdown(&mut self) -> ScanResult<()> {
         info!("Shutting down agent: {}", self.config.agent_id);
@@ -224,6 +366,7 @@ impl ScanAgent {
         // Set status to shutdown
         let mut status = self.status.write().await;
         *status = AgentStatus::Shutdown;
+        self.stop_watcher.cancel();
         
         // TODO: Unregister from scheduler
         debug!("Agent unregistration (framework mode)");
@@ -242,6 +385,7 @@ impl ScanAgent {
             status,
             current_job,
             scheduler_address: self.config.scheduler_address.clone(),
+            last_job_resources: self.scanner.resource_usage(),
         }
     }
 }
@@ -253,6 +397,9 @@ pub struct AgentStats {
     pub status: AgentStatus,
     pub current_job: Option<String>,
     pub scheduler_address: String,
+    /// What the last finished job used, for sizing agents and their ulimits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_job_resources: Option<ResourceUsage>,
 }
 
 impl std::fmt::Display for AgentStats {
@@ -264,6 +411,9 @@ impl std::fmt::Display for AgentStats {
         if let Some(ref job) = self.current_job {
             writeln!(f, "  Current Job: {}", job)?;
         }
+        if let Some(ref resources) = self.last_job_resources {
+            writeln!(f, "  Last Job Resources: {}", resources)?;
+        }
         Ok(())
     }
 }
@@ -280,6 +430,9 @@ mod tests {
             listen_address: "0.0.0.0:8081".to_string(),
             heartbeat_interval_seconds: 30,
             max_concurrent_jobs: 1,
+            region: None,
+            zone: None,
+            spool_dir: None,
         }
     }
 
@@ -289,7 +442,12 @@ mod tests {
         ScannerConfig {
             default_timeout_ms: 1000,
             max_concurrent_scans: 100,
+            max_concurrent_targets: 10,
+            max_concurrent_probes_total: 0,
+            result_buffer: 64,
+            ulimit_check: Default::default(),
             adaptive_throttling: false,
+            throttle_strategy: "adaptive".to_string(),
             initial_pps: 1000,
             max_pps: 10000,
             min_pps: 100,
@@ -298,6 +456,10 @@ mod tests {
                 method: "tcp".to_string(),
                 timeout_ms: 1000,
                 retries: 1,
+                ipv6: Ipv6DiscoveryConfig::default(),
+                consensus: Default::default(),
+                arp_warmup: false,
+                cache: Default::default(),
             },
             tcp_connect: TcpConnectConfig {
                 enabled: true,
@@ -310,13 +472,34 @@ mod tests {
                 timeout_ms: 1000,
                 retries: 1,
                 retry_delay_ms: 50,
+                stateless: false,
             },
             udp: UdpConfig {
                 enabled: false,
                 timeout_ms: 1000,
                 retries: 1,
                 retry_delay_ms: 200,
+                icmp_ratelimit_reprobes: 0,
             },
+            fragment_mtu: None,
+            os_detection: OsDetectionConfig::default(),
+            evasion: Default::default(),
+            verify_open_ports: false,
+            adaptive_timeout: Default::default(),
+            max_rate: None,
+            min_rate: None,
+            strict: false,
+            open_ports_only: false,
+            expand_ports: false,
+            port_reasons: false,
+            confirm_targets_above: 0,
+            scan_order: Default::default(),
+            retry_policy: Default::default(),
+            port_frequency_file: None,
+            services_file: None,
+            oui_file: None,
+            ssh_tunnel: None,
+            deep_check: Default::default(),
         }
     }
 
@@ -3,10 +3,11 @@
 /// This module collects and aggregates scan results from multiple agents,
 /// provides result queries, and handles result storage.
 
+use super::spool::ResultChunk;
 use crate::error::ScanResult;
 use crate::scanner::CompleteScanResult;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info};
@@ -42,6 +43,8 @@ impl std::fmt::Display for AggregatedResults {
 struct ResultEntry {
     #[allow(dead_code)]
     job_id: String,
+    /// Chunk the results arrived in, if they came as one
+    chunk_id: Option<String>,
     agent_id: String,
     results: Vec<CompleteScanResult>,
     #[allow(dead_code)]
@@ -52,6 +55,9 @@ struct ResultEntry {
 pub struct ResultAggregator {
     results: Arc<RwLock<HashMap<String, Vec<ResultEntry>>>>,
     aggregated: Arc<RwLock<HashMap<String, AggregatedResults>>>,
+    /// IDs of the result chunks delivered so far by job, to drop
+    /// redeliveries; dropped with the job's results
+    chunk_ids: Arc<RwLock<HashMap<String, HashSet<String>>>>,
     retention_hours: u64,
 }
 
@@ -66,6 +72,7 @@ impl ResultAggregator {
         Self {
             results: Arc::new(RwLock::new(HashMap::new())),
             aggregated: Arc::new(RwLock::new(HashMap::new())),
+            chunk_ids: Arc::new(RwLock::new(HashMap::new())),
             retention_hours,
         }
     }
@@ -81,6 +88,16 @@ impl ResultAggregator {
         job_id: String,
         agent_id: String,
         results: Vec<CompleteScanResult>,
+    ) -> ScanResult<()> {
+        self.store_entry(job_id, None, agent_id, results).await
+    }
+
+    async fn store_entry(
+        &mut self,
+        job_id: String,
+        chunk_id: Option<String>,
+        agent_id: String,
+        results: Vec<CompleteScanResult>,
     ) -> ScanResult<()> {
         info!(
             "Storing results: job={}, agent={}, {} results",
@@ -91,16 +108,22 @@ impl ResultAggregator {
 
         let entry = ResultEntry {
             job_id: job_id.clone(),
+            chunk_id,
             agent_id,
             results,
             received_at: chrono::Utc::now(),
         };
 
         let mut all_results = self.results.write().await;
-        all_results
-            .entry(job_id.clone())
-            .or_insert_with(Vec::new)
-            .push(entry);
+        let entries = all_results.entry(job_id.clone()).or_insert_with(Vec::new);
+        // A chunk stored again before it was confirmed replaces itself
+        match entries
+            .iter_mut()
+            .find(|stored| stored.chunk_id.is_some() && stored.chunk_id == entry.chunk_id)
+        {
+            Some(stored) => *stored = entry,
+            None => entries.push(entry),
+        }
         drop(all_results);
 
         // Trigger aggregation
@@ -109,6 +132,41 @@ impl ResultAggregator {
         Ok(())
     }
 
+    /// Store a result chunk an agent delivered, once
+    ///
+    /// Agents deliver at least once, so a chunk may arrive again after its
+    /// acknowledgement was lost; returns `false` for such a duplicate. A
+    /// chunk counts as delivered only once [`ResultAggregator::confirm_chunk`]
+    /// recorded it, after its job was marked completed; until then storing
+    /// it again replaces the earlier copy.
+    pub async fn store_chunk(&mut self, chunk: ResultChunk) -> ScanResult<bool> {
+        if self.is_delivered(&chunk.job_id, &chunk.chunk_id).await {
+            debug!("Dropping duplicate chunk {} of job {}", chunk.chunk_id, chunk.job_id);
+            return Ok(false);
+        }
+        self.store_entry(chunk.job_id, Some(chunk.chunk_id), chunk.agent_id, chunk.results)
+            .await?;
+        Ok(true)
+    }
+
+    /// Record a stored chunk as delivered, so redeliveries are dropped
+    pub async fn confirm_chunk(&self, job_id: &str, chunk_id: &str) {
+        self.chunk_ids
+            .write()
+            .await
+            .entry(job_id.to_string())
+            .or_default()
+            .insert(chunk_id.to_string());
+    }
+
+    async fn is_delivered(&self, job_id: &str, chunk_id: &str) -> bool {
+        self.chunk_ids
+            .read()
+            .await
+            .get(job_id)
+            .is_some_and(|chunks| chunks.contains(chunk_id))
+    }
+
     /// Aggregate results for a job
     async fn aggregate_job_results(&mut self, job_id: &str) -> ScanResult<()> {
         debug!("Aggregating results for job {}", job_id);
@@ -211,6 +269,7 @@ impl ResultAggregator {
         
         let mut agg = self.aggregated.write().await;
         agg.remove(job_id);
+        self.chunk_ids.write().await.remove(job_id);
         
         info!("Deleted results for job {}", job_id);
         Ok(())
@@ -316,6 +375,22 @@ mod tests {
             udp_results: vec![],
             scan_duration_ms: 1000,
             throttle_stats: None,
+            geo: None,
+            hostname: None,
+            excluded_by: None,
+            discovered_hostnames: Vec::new(),
+            tls_certificates: Vec::new(),
+            os_matches: Vec::new(),
+            down_reason: None,
+            port_verifications: Vec::new(),
+            liveness: Vec::new(),
+            deception: None,
+            latency: None,
+            phase_timings: None,
+            dropped_ports: None,
+            expansion: None,
+            mac: None,
+            icmp_rate_limit: None,
         }
     }
 
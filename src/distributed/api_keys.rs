@@ -0,0 +1,228 @@
+//! Tenant-scoped API keys for job submission
+//!
+//! Once `[[distributed.api_keys]]` lists any key, jobs are only accepted
+//! with one of them. Each key belongs to a tenant, may only scan its
+//! `allowed_targets` and has hourly quotas of jobs and targets. Keys are
+//! stored as the SHA-256 of the secret, so the configuration never holds
+//! one in plain text: `printf %s "$SECRET" | sha256sum`. Every accepted or
+//! refused submission is recorded in the audit log under the key's ID.
+
+use crate::config::reload::AUDIT_TARGET;
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::targets::IpNetwork;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use tracing::{info, warn};
+
+/// One API key (`[[distributed.api_keys]]` in config.toml)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Name the key is audited under; never the secret
+    pub id: String,
+    /// Tenant the key's jobs belong to; also their submitter in the queue
+    pub tenant: String,
+    /// SHA-256 of the secret, in hex
+    pub sha256: String,
+    /// Address blocks the key may scan (`0.0.0.0/0` and `::/0` for any)
+    pub allowed_targets: Vec<IpNetwork>,
+    /// Jobs accepted per hour; 0 for no limit
+    #[serde(default)]
+    pub jobs_per_hour: u32,
+    /// Targets accepted per hour, over all jobs; 0 for no limit
+    #[serde(default)]
+    pub targets_per_hour: u32,
+}
+
+/// SHA-256 of `secret` in hex, as `ApiKeyConfig::sha256` stores it
+pub fn hash_api_key(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Key and tenant a submission was accepted for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyGrant {
+    pub key_id: String,
+    pub tenant: String,
+    targets: usize,
+}
+
+/// Configured keys and what each submitted in the past hour
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    keys: Vec<ApiKeyConfig>,
+    /// Accepted submissions by key ID: when, and how many targets
+    usage: HashMap<String, VecDeque<(DateTime<Utc>, usize)>>,
+}
+
+impl ApiKeys {
+    /// Check and load `keys`; none leaves submission open
+    pub fn new(keys: Vec<ApiKeyConfig>) -> ScanResult<Self> {
+        for (i, key) in keys.iter().enumerate() {
+            let field = |name: &str| format!("distributed.api_keys[{}].{}", i, name);
+            if key.id.trim().is_empty() || key.tenant.trim().is_empty() {
+                return Err(ScanError::validation_error(field("id"), "Every API key needs an id and a tenant"));
+            }
+            if keys[..i].iter().any(|other| other.id == key.id) {
+                return Err(ScanError::validation_error(field("id"), format!("Duplicate API key id '{}'", key.id)));
+            }
+            if key.sha256.len() != 64 || !key.sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ScanError::validation_error(
+                    field("sha256"),
+                    format!("API key '{}' needs the hex SHA-256 of its secret, not the secret", key.id),
+                ));
+            }
+            if key.allowed_targets.is_empty() {
+                return Err(ScanError::validation_error(
+                    field("allowed_targets"),
+                    format!("API key '{}' allows no targets", key.id),
+                ));
+            }
+        }
+        Ok(Self {
+            keys,
+            usage: HashMap::new(),
+        })
+    }
+
+    /// Whether submissions need a key
+    pub fn is_required(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Check `secret` may submit a job for `targets` at `now`
+    ///
+    /// Refusals are audited; an accepted job is audited and counted
+    /// against the quotas by [`ApiKeys::record`] once it is queued.
+    pub fn authorize(&mut self, secret: &str, targets: &[IpAddr], now: DateTime<Utc>) -> ScanResult<ApiKeyGrant> {
+        let hash = hash_api_key(secret);
+        let Some(key) = self.keys.iter().find(|key| constant_time_eq(&key.sha256.to_ascii_lowercase(), &hash)) else {
+            warn!(target: AUDIT_TARGET, outcome = "refused", targets = targets.len(), "Refused a job with an unknown API key");
+            return Err(ScanError::permission_denied("job submission with an unknown API key"));
+        };
+
+        let refuse = |reason: String| {
+            warn!(
+                target: AUDIT_TARGET,
+                outcome = "refused",
+                key = %key.id,
+                tenant = %key.tenant,
+                targets = targets.len(),
+                "Refused a job: {}",
+                reason
+            );
+            Err(ScanError::permission_denied(format!("API key '{}': {}", key.id, reason)))
+        };
+
+        let outside = targets
+            .iter()
+            .filter(|&&target| !key.allowed_targets.iter().any(|block| block.contains(target)))
+            .count();
+        if outside > 0 {
+            return refuse(format!("{} targets outside its allowed ranges", outside));
+        }
+
+        let usage = self.usage.entry(key.id.clone()).or_default();
+        while usage.front().is_some_and(|&(at, _)| now - at >= Duration::hours(1)) {
+            usage.pop_front();
+        }
+        if key.jobs_per_hour > 0 && usage.len() >= key.jobs_per_hour as usize {
+            return refuse(format!("quota of {} jobs per hour used", key.jobs_per_hour));
+        }
+        let recent: usize = usage.iter().map(|&(_, targets)| targets).sum();
+        if key.targets_per_hour > 0 && recent + targets.len() > key.targets_per_hour as usize {
+            return refuse(format!(
+                "quota of {} targets per hour would be exceeded ({} used)",
+                key.targets_per_hour, recent
+            ));
+        }
+
+        Ok(ApiKeyGrant {
+            key_id: key.id.clone(),
+            tenant: key.tenant.clone(),
+            targets: targets.len(),
+        })
+    }
+
+    /// Count a queued job against its key's quotas and audit it
+    pub fn record(&mut self, grant: &ApiKeyGrant, job_id: &str, now: DateTime<Utc>) {
+        self.usage.entry(grant.key_id.clone()).or_default().push_back((now, grant.targets));
+        info!(
+            target: AUDIT_TARGET,
+            outcome = "accepted",
+            key = %grant.key_id,
+            tenant = %grant.tenant,
+            targets = grant.targets,
+            job = job_id,
+            "Accepted job {} for tenant {}",
+            job_id,
+            grant.tenant
+        );
+    }
+}
+
+/// Compare without returning early, so timing does not reveal how much of a
+/// hash matched
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str, secret: &str, allowed: &str) -> ApiKeyConfig {
+        ApiKeyConfig {
+            id: id.to_string(),
+            tenant: format!("{}-tenant", id),
+            sha256: hash_api_key(secret),
+            allowed_targets: vec![allowed.parse().unwrap()],
+            jobs_per_hour: 2,
+            targets_per_hour: 3,
+        }
+    }
+
+    #[test]
+    fn test_keys_are_scoped_and_metered() {
+        let mut keys = ApiKeys::new(vec![key("red", "s3cret", "10.1.0.0/16"), key("blue", "other", "10.2.0.0/16")]).unwrap();
+        assert!(keys.is_required());
+        let now = Utc::now();
+        let red: Vec<IpAddr> = vec!["10.1.0.1".parse().unwrap()];
+
+        assert!(keys.authorize("wrong", &red, now).is_err());
+        // Another tenant's key does not reach this tenant's ranges
+        assert!(keys.authorize("other", &red, now).is_err());
+
+        let grant = keys.authorize("s3cret", &red, now).unwrap();
+        assert_eq!(grant.tenant, "red-tenant");
+        keys.record(&grant, "job_1", now);
+        let three: Vec<IpAddr> = (1..=3).map(|i| IpAddr::from([10, 1, 0, i])).collect();
+        let error = keys.authorize("s3cret", &three, now).unwrap_err().to_string();
+        assert!(error.contains("targets per hour"), "{}", error);
+
+        let grant = keys.authorize("s3cret", &red, now).unwrap();
+        keys.record(&grant, "job_2", now);
+        let error = keys.authorize("s3cret", &red, now).unwrap_err().to_string();
+        assert!(error.contains("2 jobs per hour"), "{}", error);
+        // The quota frees up an hour later
+        assert!(keys.authorize("s3cret", &red, now + Duration::hours(1)).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(!ApiKeys::new(Vec::new()).unwrap().is_required());
+        let plain = ApiKeyConfig {
+            sha256: "s3cret".to_string(),
+            ..key("red", "s3cret", "10.1.0.0/16")
+        };
+        assert!(ApiKeys::new(vec![plain]).is_err());
+        let unscoped = ApiKeyConfig {
+            allowed_targets: Vec::new(),
+            ..key("red", "s3cret", "10.1.0.0/16")
+        };
+        assert!(ApiKeys::new(vec![unscoped]).is_err());
+        assert!(ApiKeys::new(vec![key("red", "a", "10.1.0.0/16"), key("red", "b", "10.2.0.0/16")]).is_err());
+    }
+}
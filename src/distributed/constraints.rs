@@ -0,0 +1,192 @@
+//! Job constraints and agent placement
+//!
+//! Agents differ: one runs as root in the DMZ, another unprivileged in an
+//! office network without IPv6. Each agent advertises its [`Capabilities`]
+//! and where it sits ([`AgentPlacement`]: region, network zone and the probe
+//! rate it may send), and a job may declare what it needs from the agent
+//! that runs it ([`JobConstraints`]). The scheduler assigns a job only to an
+//! agent that meets every constraint; until one registers, the job stays
+//! pending. Constraints are written as a comma-separated list:
+//!
+//! ```text
+//! zone=dmz,raw_sockets,ipv6,scan_type=syn,min_rate=5000
+//! ```
+
+use crate::capabilities::Capabilities;
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::ScanType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where an agent runs and how fast it may probe, set by its operator
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentPlacement {
+    /// Geographic region, e.g. "eu-west"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Network zone, e.g. "dmz" or "corp"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Probe rate cap in packets per second; `None` is uncapped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rate: Option<u32>,
+}
+
+/// What a job needs from the agent that runs it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobConstraints {
+    #[serde(default)]
+    pub raw_sockets: bool,
+    #[serde(default)]
+    pub ipv6: bool,
+    /// Scan types the agent must be able to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scan_types: Vec<ScanType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Probe rate the agent must be allowed, in packets per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rate: Option<u32>,
+}
+
+impl JobConstraints {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Constraints an agent with `capabilities` (`None` until advertised)
+    /// and `placement` does not meet, as written in a constraint list
+    pub fn unmet(&self, capabilities: Option<&Capabilities>, placement: &AgentPlacement) -> Vec<String> {
+        let mut unmet = Vec::new();
+        if self.raw_sockets && !capabilities.is_some_and(|c| c.raw_sockets) {
+            unmet.push("raw_sockets".to_string());
+        }
+        if self.ipv6 && !capabilities.is_some_and(|c| c.ipv6) {
+            unmet.push("ipv6".to_string());
+        }
+        for &scan_type in &self.scan_types {
+            if !capabilities.is_some_and(|c| c.supports(scan_type)) {
+                unmet.push(format!("scan_type={}", scan_type_name(scan_type)));
+            }
+        }
+        if let Some(ref region) = self.region {
+            if placement.region.as_ref() != Some(region) {
+                unmet.push(format!("region={}", region));
+            }
+        }
+        if let Some(ref zone) = self.zone {
+            if placement.zone.as_ref() != Some(zone) {
+                unmet.push(format!("zone={}", zone));
+            }
+        }
+        if let Some(min_rate) = self.min_rate {
+            if placement.max_rate.is_some_and(|max_rate| max_rate < min_rate) {
+                unmet.push(format!("min_rate={}", min_rate));
+            }
+        }
+        unmet
+    }
+
+    /// Whether an agent meets every constraint
+    pub fn is_satisfied_by(&self, capabilities: Option<&Capabilities>, placement: &AgentPlacement) -> bool {
+        self.unmet(capabilities, placement).is_empty()
+    }
+}
+
+fn scan_type_name(scan_type: ScanType) -> &'static str {
+    match scan_type {
+        ScanType::TcpConnect => "tcp",
+        ScanType::TcpSyn => "syn",
+        ScanType::Udp => "udp",
+    }
+}
+
+impl FromStr for JobConstraints {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> ScanResult<Self> {
+        let invalid = |item: &str, message: &str| {
+            ScanError::validation_error("constraints", format!("Invalid constraint '{}': {}", item, message))
+        };
+        let mut constraints = Self::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = match item.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim()).filter(|value| !value.is_empty())),
+                None => (item, None),
+            };
+            match (key, value) {
+                ("raw_sockets", None) => constraints.raw_sockets = true,
+                ("ipv6", None) => constraints.ipv6 = true,
+                ("scan_type", Some(value)) => constraints
+                    .scan_types
+                    .push(value.parse().map_err(|_| invalid(item, "expected tcp, syn or udp"))?),
+                ("region", Some(value)) => constraints.region = Some(value.to_string()),
+                ("zone", Some(value)) => constraints.zone = Some(value.to_string()),
+                ("min_rate", Some(value)) => {
+                    constraints.min_rate = Some(value.parse().map_err(|_| invalid(item, "expected packets per second"))?)
+                }
+                _ => {
+                    return Err(invalid(
+                        item,
+                        "expected raw_sockets, ipv6, scan_type=, region=, zone= or min_rate=",
+                    ))
+                }
+            }
+        }
+        Ok(constraints)
+    }
+}
+
+impl fmt::Display for JobConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<String> = Vec::new();
+        if self.raw_sockets {
+            items.push("raw_sockets".to_string());
+        }
+        if self.ipv6 {
+            items.push("ipv6".to_string());
+        }
+        items.extend(self.scan_types.iter().map(|&t| format!("scan_type={}", scan_type_name(t))));
+        items.extend(self.region.iter().map(|region| format!("region={}", region)));
+        items.extend(self.zone.iter().map(|zone| format!("zone={}", zone)));
+        items.extend(self.min_rate.iter().map(|rate| format!("min_rate={}", rate)));
+        f.write_str(&items.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints_match_agents() {
+        let constraints: JobConstraints = "zone=dmz, raw_sockets, scan_type=syn, min_rate=5000".parse().unwrap();
+        assert_eq!(constraints.to_string(), "raw_sockets,scan_type=syn,zone=dmz,min_rate=5000");
+
+        let mut capabilities = crate::capabilities().clone();
+        capabilities.raw_sockets = true;
+        capabilities.scan_types = vec![ScanType::TcpConnect, ScanType::TcpSyn];
+        let dmz = AgentPlacement {
+            zone: Some("dmz".to_string()),
+            ..Default::default()
+        };
+        assert!(constraints.is_satisfied_by(Some(&capabilities), &dmz));
+
+        let capped = AgentPlacement {
+            max_rate: Some(1000),
+            ..dmz.clone()
+        };
+        assert_eq!(constraints.unmet(Some(&capabilities), &capped), vec!["min_rate=5000"]);
+        assert_eq!(
+            constraints.unmet(None, &AgentPlacement::default()),
+            vec!["raw_sockets", "scan_type=syn", "zone=dmz"]
+        );
+        assert!(JobConstraints::default().is_satisfied_by(None, &AgentPlacement::default()));
+
+        assert!("zone".parse::<JobConstraints>().is_err());
+        assert!("scan_type=icmp".parse::<JobConstraints>().is_err());
+    }
+}
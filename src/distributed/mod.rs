@@ -5,19 +5,30 @@
 
 pub mod scheduler;
 pub mod agent;
+pub mod api_keys;
 pub mod aggregator;
+pub mod constraints;
+pub mod queue;
+pub mod spool;
 
-pub use scheduler::{ScanScheduler, ScanJob, JobStatus};
+pub use scheduler::{ScanScheduler, ScanJob, JobOptions, JobStatus, SchedulerStats};
 pub use agent::{ScanAgent, AgentConfig, AgentStatus};
+pub use api_keys::{hash_api_key, ApiKeyConfig, ApiKeyGrant, ApiKeys};
 pub use aggregator::{ResultAggregator, AggregatedResults};
+pub use constraints::{AgentPlacement, JobConstraints};
+pub use queue::{JobPriority, PreemptionPolicy};
+pub use spool::{ResultChunk, ResultSpool};
 
-use crate::error::ScanResult;
+use crate::capabilities::Capabilities;
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::{BlackoutCalendar, CompleteScanResult, ScanType};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use tracing::info;
 
-/// Distributed scanning configuration
+/// Distributed scanning configuration (`[distributed]` in config.toml)
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DistributedConfig {
     pub enable_distributed: bool,
     pub scheduler_port: u16,
@@ -25,6 +36,11 @@ pub struct DistributedConfig {
     pub max_agents: usize,
     pub job_timeout_seconds: u64,
     pub result_retention_hours: u64,
+    /// Whether urgent jobs take agents from lower-priority jobs that have
+    /// not started (`preempt`) or only jump the queue (`queue`)
+    pub preemption: PreemptionPolicy,
+    /// Keys jobs must be submitted with; none leaves submission open
+    pub api_keys: Vec<ApiKeyConfig>,
 }
 
 impl Default for DistributedConfig {
@@ -36,6 +52,8 @@ impl Default for DistributedConfig {
             max_agents: 10,
             job_timeout_seconds: 3600,
             result_retention_hours: 24,
+            preemption: PreemptionPolicy::default(),
+            api_keys: Vec::new(),
         }
     }
 }
@@ -45,17 +63,20 @@ pub struct DistributedScanner {
     config: DistributedConfig,
     scheduler: ScanScheduler,
     aggregator: ResultAggregator,
+    api_keys: ApiKeys,
 }
 
 impl DistributedScanner {
     /// Create a new distributed scanner
     pub fn new(config: DistributedConfig) -> ScanResult<Self> {
         info!("Initializing distributed scanner");
-        
+        let api_keys = ApiKeys::new(config.api_keys.clone())?;
+
         let scheduler = ScanScheduler::new(
             config.max_agents,
             config.job_timeout_seconds,
-        );
+        )
+        .with_preemption(config.preemption);
         
         let aggregator = ResultAggregator::new(
             config.result_retention_hours,
@@ -65,16 +86,75 @@ impl DistributedScanner {
             config,
             scheduler,
             aggregator,
+            api_keys,
         })
     }
 
+    /// Refuse jobs, or hold them pending, during these windows
+    /// (`security.blackouts`)
+    pub fn with_blackouts(mut self, blackouts: BlackoutCalendar) -> Self {
+        self.scheduler = self.scheduler.with_blackouts(blackouts);
+        self
+    }
+
     /// Submit a scan job for distributed execution
     pub async fn submit_job(
         &mut self,
         targets: Vec<IpAddr>,
         ports: Vec<u16>,
     ) -> ScanResult<String> {
-        self.scheduler.submit_job(targets, ports).await
+        self.submit_job_with(targets, ports, JobOptions::default()).await
+    }
+
+    /// Submit a job with a priority, submitter and agent constraints
+    ///
+    /// Refused once API keys are configured; use [`Self::submit_job_as`].
+    pub async fn submit_job_with(
+        &mut self,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        options: JobOptions,
+    ) -> ScanResult<String> {
+        if self.api_keys.is_required() {
+            return Err(ScanError::permission_denied("job submission without an API key"));
+        }
+        self.scheduler.submit_job_with(targets, ports, options).await
+    }
+
+    /// Submit a job with an API key, for the key's tenant
+    ///
+    /// The targets must be in the key's allowed ranges and within its
+    /// quotas; the tenant becomes the job's submitter. Without configured
+    /// keys this is [`Self::submit_job_with`] and `api_key` is ignored.
+    pub async fn submit_job_as(
+        &mut self,
+        api_key: &str,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        options: JobOptions,
+    ) -> ScanResult<String> {
+        if !self.api_keys.is_required() {
+            return self.scheduler.submit_job_with(targets, ports, options).await;
+        }
+        let grant = self.api_keys.authorize(api_key, &targets, chrono::Utc::now())?;
+        let options = JobOptions {
+            submitter: Some(grant.tenant.clone()),
+            ..options
+        };
+        let job_id = self.scheduler.submit_job_with(targets, ports, options).await?;
+        self.api_keys.record(&grant, &job_id, chrono::Utc::now());
+        Ok(job_id)
+    }
+
+    /// Job, agent and queue statistics
+    pub async fn get_stats(&self) -> SchedulerStats {
+        self.scheduler.get_stats().await
+    }
+
+    /// Assign pending jobs to free agents, e.g. after a blackout window
+    /// closed; returns how many were assigned
+    pub async fn assign_pending_jobs(&self) -> ScanResult<usize> {
+        self.scheduler.assign_pending_jobs().await
     }
 
     /// Get job status
@@ -82,16 +162,164 @@ impl DistributedScanner {
         self.scheduler.get_job_status(job_id).await
     }
 
+    /// Get a job with its assignment and timestamps
+    pub async fn get_job(&self, job_id: &str) -> ScanResult<Option<ScanJob>> {
+        self.scheduler.get_job(job_id).await
+    }
+
+    /// Get all submitted jobs
+    pub async fn list_jobs(&self) -> ScanResult<Vec<ScanJob>> {
+        self.scheduler.list_jobs().await
+    }
+
     /// Get aggregated results for a job
     pub async fn get_results(&self, job_id: &str) -> ScanResult<Option<AggregatedResults>> {
         self.aggregator.get_results(job_id).await
     }
 
+    /// Record an agent's results for a job and mark the job completed
+    pub async fn complete_job(
+        &mut self,
+        job_id: &str,
+        agent_id: &str,
+        results: Vec<CompleteScanResult>,
+    ) -> ScanResult<()> {
+        self.aggregator
+            .store_results(job_id.to_string(), agent_id.to_string(), results)
+            .await?;
+        self.scheduler.mark_job_completed(job_id).await
+    }
+
+    /// Record a result chunk an agent delivered and mark its job completed
+    ///
+    /// A chunk delivered again is ignored; returns whether it was new. The
+    /// chunk only counts as delivered once its job is marked, so a chunk
+    /// whose delivery failed midway is taken again. Results older than the
+    /// retention period are dropped, along with their chunk IDs.
+    pub async fn complete_chunk(&mut self, chunk: ResultChunk) -> ScanResult<bool> {
+        let (job_id, chunk_id) = (chunk.job_id.clone(), chunk.chunk_id.clone());
+        if !self.aggregator.store_chunk(chunk).await? {
+            return Ok(false);
+        }
+        self.scheduler.mark_job_completed(&job_id).await?;
+        self.aggregator.confirm_chunk(&job_id, &chunk_id).await;
+        self.aggregator.cleanup_old_results().await?;
+        Ok(true)
+    }
+
+    /// Take the chunks an agent spooled but never got stored, e.g. left by
+    /// a previous run or by a failed delivery, retrying each with backoff
+    /// until it is stored or the agent shuts down; returns how many were
+    /// stored
+    pub async fn collect_spooled(&mut self, agent: &ScanAgent) -> ScanResult<usize> {
+        let this = tokio::sync::Mutex::new(self);
+        agent
+            .deliver_spooled(|chunk| {
+                let this = &this;
+                async move { this.lock().await.complete_chunk(chunk).await.map(drop) }
+            })
+            .await
+    }
+
+    /// Mark a job failed, freeing its agent
+    pub async fn fail_job(&mut self, job_id: &str, reason: &str) -> ScanResult<()> {
+        self.scheduler.mark_job_failed(job_id, reason).await
+    }
+
+    /// Mark a job running and return it for execution
+    pub async fn start_job(&mut self, job_id: &str) -> ScanResult<ScanJob> {
+        let job = self
+            .get_job(job_id)
+            .await?
+            .ok_or_else(|| ScanError::validation_error("job_id", format!("Unknown job {}", job_id)))?;
+        if let Some(active) = self.scheduler.blackouts().hold(&job.targets, chrono::Utc::now()) {
+            return Err(ScanError::validation_error(
+                "job_id",
+                format!(
+                    "Job {} is held by blackout window '{}' until {}",
+                    job_id,
+                    active.window.name,
+                    active.until.to_rfc3339()
+                ),
+            ));
+        }
+        self.scheduler.mark_job_running(job_id).await?;
+        Ok(job)
+    }
+
+    /// Execute a job on an agent in this process and aggregate its results
+    ///
+    /// The job must be assigned to `agent`. A failed scan marks the job
+    /// failed and is returned as the error.
+    pub async fn run_job(&mut self, job_id: &str, agent: &mut ScanAgent, scan_types: Vec<ScanType>) -> ScanResult<()> {
+        let assigned = self.get_job(job_id).await?.and_then(|job| job.assigned_agent);
+        match assigned {
+            Some(assigned) if assigned == agent.agent_id() => {}
+            Some(assigned) => {
+                return Err(ScanError::validation_error(
+                    "agent_id",
+                    format!("Job {} is assigned to agent {}", job_id, assigned),
+                ))
+            }
+            None => {
+                return Err(ScanError::validation_error(
+                    "job_id",
+                    format!("Job {} is not assigned to an agent", job_id),
+                ))
+            }
+        }
+
+        let unmet = self
+            .get_job(job_id)
+            .await?
+            .map(|job| job.constraints.unmet(Some(&agent.capabilities()), &agent.placement()))
+            .unwrap_or_default();
+        if !unmet.is_empty() {
+            return Err(ScanError::validation_error(
+                "agent_id",
+                format!("Agent {} does not meet job {}'s constraints: {}", agent.agent_id(), job_id, unmet.join(", ")),
+            ));
+        }
+
+        // Results the agent still holds from before go first
+        self.collect_spooled(agent).await?;
+
+        let job = self.start_job(job_id).await?;
+        match agent.execute_chunk(job.job_id, job.targets, job.ports, scan_types).await {
+            Ok(chunk) => {
+                let chunk_id = chunk.chunk_id.clone();
+                match self.complete_chunk(chunk).await {
+                    Ok(_) => agent.acknowledge_chunk(&chunk_id),
+                    // A spooled chunk is retried with backoff
+                    Err(e) => match self.collect_spooled(agent).await? {
+                        0 => Err(e),
+                        _ => Ok(()),
+                    },
+                }
+            }
+            Err(e) => {
+                self.fail_job(job_id, &e.to_string()).await?;
+                Err(e)
+            }
+        }
+    }
+
     /// Register a new agent
     pub async fn register_agent(&mut self, agent_id: String, address: String) -> ScanResult<()> {
         self.scheduler.register_agent(agent_id, address).await
     }
 
+    /// Record what a registered agent can do and where it runs
+    pub async fn advertise_agent(
+        &mut self,
+        agent_id: &str,
+        capabilities: Capabilities,
+        placement: AgentPlacement,
+    ) -> ScanResult<()> {
+        self.scheduler.advertise_capabilities(agent_id, capabilities).await?;
+        self.scheduler.advertise_placement(agent_id, placement).await
+    }
+
     /// Get distributed configuration
     pub fn config(&self) -> &DistributedConfig {
         &self.config
@@ -110,6 +338,97 @@ mod tests {
         assert!(!config.enable_distributed);
     }
 
+    #[tokio::test]
+    async fn test_run_job_aggregates_results() {
+        let mut distributed = DistributedScanner::new(DistributedConfig::default()).unwrap();
+        let config = AgentConfig {
+            agent_id: "agent-1".to_string(),
+            ..AgentConfig::default()
+        };
+        let mut agent = ScanAgent::new(config, crate::config::AppConfig::default().scanner).unwrap();
+        distributed
+            .register_agent("agent-1".to_string(), "in-process".to_string())
+            .await
+            .unwrap();
+
+        let job_id = distributed
+            .submit_job(vec!["127.0.0.1".parse().unwrap()], vec![1])
+            .await
+            .unwrap();
+        assert_eq!(distributed.get_job_status(&job_id).await.unwrap(), Some(JobStatus::Assigned));
+
+        distributed
+            .run_job(&job_id, &mut agent, vec![ScanType::TcpConnect])
+            .await
+            .unwrap();
+        assert_eq!(distributed.get_job_status(&job_id).await.unwrap(), Some(JobStatus::Completed));
+        let results = distributed.get_results(&job_id).await.unwrap().unwrap();
+        assert_eq!(results.total_targets, 1);
+        assert_eq!(results.agent_count, 1);
+        assert!(distributed.run_job("job_missing", &mut agent, vec![]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spooled_chunks_are_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = CompleteScanResult::unscanned(
+            "10.0.0.1".parse().unwrap(),
+            crate::scanner::host_discovery::HostStatus::Up,
+            Default::default(),
+        );
+        let chunk = ResultChunk::new("job-earlier".to_string(), "agent-1".to_string(), vec![result]);
+        ResultSpool::open(dir.path()).unwrap().store(&chunk).unwrap();
+
+        let config = AgentConfig {
+            agent_id: "agent-1".to_string(),
+            spool_dir: Some(dir.path().to_path_buf()),
+            ..AgentConfig::default()
+        };
+        let agent = ScanAgent::new(config, crate::config::AppConfig::default().scanner).unwrap();
+        let mut distributed = DistributedScanner::new(DistributedConfig::default()).unwrap();
+        assert_eq!(distributed.collect_spooled(&agent).await.unwrap(), 1);
+        assert_eq!(distributed.collect_spooled(&agent).await.unwrap(), 0);
+        assert_eq!(distributed.get_results("job-earlier").await.unwrap().unwrap().total_targets, 1);
+
+        // Stored again only before it counts as delivered, without doubling
+        let mut aggregator = ResultAggregator::new(24);
+        assert!(aggregator.store_chunk(chunk.clone()).await.unwrap());
+        assert!(aggregator.store_chunk(chunk.clone()).await.unwrap());
+        assert_eq!(aggregator.get_results("job-earlier").await.unwrap().unwrap().total_targets, 1);
+        aggregator.confirm_chunk("job-earlier", &chunk.chunk_id).await;
+        assert!(!aggregator.store_chunk(chunk).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_gate_submission() {
+        let config = DistributedConfig {
+            api_keys: vec![ApiKeyConfig {
+                id: "ci".to_string(),
+                tenant: "platform".to_string(),
+                sha256: hash_api_key("s3cret"),
+                allowed_targets: vec!["10.1.0.0/16".parse().unwrap()],
+                jobs_per_hour: 0,
+                targets_per_hour: 0,
+            }],
+            ..DistributedConfig::default()
+        };
+        let mut distributed = DistributedScanner::new(config).unwrap();
+        let inside: Vec<IpAddr> = vec!["10.1.0.1".parse().unwrap()];
+        assert!(distributed.submit_job(inside.clone(), vec![22]).await.is_err());
+        assert!(distributed
+            .submit_job_as("s3cret", vec!["10.2.0.1".parse().unwrap()], vec![22], JobOptions::default())
+            .await
+            .is_err());
+
+        let options = JobOptions {
+            submitter: Some("someone-else".to_string()),
+            ..JobOptions::default()
+        };
+        let job_id = distributed.submit_job_as("s3cret", inside, vec![22], options).await.unwrap();
+        let job = distributed.get_job(&job_id).await.unwrap().unwrap();
+        assert_eq!(job.submitter.as_deref(), Some("platform"));
+    }
+
     #[test]
     fn test_distributed_scanner_creation() {
         let config = DistributedConfig::default();
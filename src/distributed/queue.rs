@@ -0,0 +1,240 @@
+//! Job priorities and fair queueing
+//!
+//! An incident-response scan must not wait behind the monthly inventory. A
+//! job has a [`JobPriority`], and pending jobs are handed to free agents by
+//! priority first; among jobs of the same priority, the submitter with the
+//! fewest jobs currently assigned or running goes first, so one team's bulk
+//! submission does not starve everyone else, and then the oldest job. With
+//! [`PreemptionPolicy::Preempt`] a job that finds no free agent also takes
+//! the agent of an assigned job of lower priority that has not started yet;
+//! that job goes back to the queue. Running scans are never interrupted.
+
+use crate::error::{ScanError, ScanResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::fmt;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::str::FromStr;
+
+/// How urgent a job is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl JobPriority {
+    pub const ALL: [JobPriority; 4] = [JobPriority::Urgent, JobPriority::High, JobPriority::Normal, JobPriority::Low];
+}
+
+impl fmt::Display for JobPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JobPriority::Low => "low",
+            JobPriority::Normal => "normal",
+            JobPriority::High => "high",
+            JobPriority::Urgent => "urgent",
+        })
+    }
+}
+
+impl FromStr for JobPriority {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> ScanResult<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(JobPriority::Low),
+            "normal" => Ok(JobPriority::Normal),
+            "high" => Ok(JobPriority::High),
+            "urgent" => Ok(JobPriority::Urgent),
+            _ => Err(ScanError::validation_error(
+                "priority",
+                format!("Unknown priority '{}' (expected low, normal, high or urgent)", s),
+            )),
+        }
+    }
+}
+
+/// What a job does when no agent is free
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreemptionPolicy {
+    /// Wait in the queue, ahead of jobs of lower priority
+    #[default]
+    Queue,
+    /// Take the agent of a lower-priority job that has not started yet
+    Preempt,
+}
+
+impl fmt::Display for PreemptionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PreemptionPolicy::Queue => "queue",
+            PreemptionPolicy::Preempt => "preempt",
+        })
+    }
+}
+
+/// Queue position of a pending job: lower sorts first
+///
+/// `active` is how many jobs of the same submitter are assigned or running.
+pub(crate) fn queue_key(priority: JobPriority, active: usize, created_at: DateTime<Utc>) -> QueueKey {
+    (Reverse(priority), active, created_at)
+}
+
+type QueueKey = (Reverse<JobPriority>, usize, DateTime<Utc>);
+
+/// Jobs of one priority from one submitter, oldest first
+type Lane = (JobPriority, Option<String>);
+type LaneEntry = (DateTime<Utc>, String);
+
+/// Pending jobs in queue order
+///
+/// Jobs are kept in lanes by priority and submitter, each in submission
+/// order, so the head of the queue is the best of the lane heads. Walking
+/// the queue keeps those heads in a heap and costs O(log lanes) per job.
+#[derive(Debug, Default)]
+pub(crate) struct JobQueue {
+    lanes: HashMap<Lane, BTreeSet<LaneEntry>>,
+    /// Assigned or running jobs by submitter
+    active: HashMap<Option<String>, usize>,
+}
+
+/// A walk over the queue, resumable while jobs are assigned
+#[derive(Debug, Default)]
+pub(crate) struct QueueCursor {
+    heads: BinaryHeap<Reverse<(QueueKey, Lane, LaneEntry)>>,
+}
+
+impl JobQueue {
+    pub(crate) fn push(&mut self, priority: JobPriority, submitter: Option<String>, created_at: DateTime<Utc>, job_id: String) {
+        self.lanes.entry((priority, submitter)).or_default().insert((created_at, job_id));
+    }
+
+    pub(crate) fn remove(&mut self, priority: JobPriority, submitter: &Option<String>, created_at: DateTime<Utc>, job_id: &str) {
+        let lane = (priority, submitter.clone());
+        if let Some(jobs) = self.lanes.get_mut(&lane) {
+            jobs.remove(&(created_at, job_id.to_string()));
+            if jobs.is_empty() {
+                self.lanes.remove(&lane);
+            }
+        }
+    }
+
+    /// A job of `submitter` was assigned
+    pub(crate) fn activate(&mut self, submitter: &Option<String>) {
+        *self.active.entry(submitter.clone()).or_default() += 1;
+    }
+
+    /// A job of `submitter` finished or went back to the queue
+    pub(crate) fn deactivate(&mut self, submitter: &Option<String>) {
+        if let Some(active) = self.active.get_mut(submitter) {
+            *active = active.saturating_sub(1);
+            if *active == 0 {
+                self.active.remove(submitter);
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    fn key(&self, lane: &Lane, entry: &LaneEntry) -> QueueKey {
+        let active = self.active.get(&lane.1).copied().unwrap_or(0);
+        queue_key(lane.0, active, entry.0)
+    }
+
+    /// Start a walk at the head of the queue
+    pub(crate) fn cursor(&self) -> QueueCursor {
+        let heads = self
+            .lanes
+            .iter()
+            .filter_map(|(lane, jobs)| {
+                let head = jobs.first()?.clone();
+                Some(Reverse((self.key(lane, &head), lane.clone(), head)))
+            })
+            .collect();
+        QueueCursor { heads }
+    }
+
+    /// The next job of the walk; jobs assigned since are skipped, and a
+    /// lane whose submitter got busier since is queued further back
+    pub(crate) fn next(&self, cursor: &mut QueueCursor) -> Option<String> {
+        while let Some(Reverse((key, lane, position))) = cursor.heads.pop() {
+            let Some(jobs) = self.lanes.get(&lane) else { continue };
+            let Some(head) = jobs.range((Included(&position), Unbounded)).next() else {
+                continue;
+            };
+            let current = self.key(&lane, head);
+            if current != key {
+                cursor.heads.push(Reverse((current, lane, head.clone())));
+                continue;
+            }
+            if let Some(after) = jobs.range((Excluded(head), Unbounded)).next() {
+                cursor.heads.push(Reverse((self.key(&lane, after), lane.clone(), after.clone())));
+            }
+            return Some(head.1.clone());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_order() {
+        let now = chrono::Utc::now();
+        let earlier = now - chrono::Duration::minutes(5);
+        // Priority beats fair share, fair share beats age
+        assert!(queue_key(JobPriority::Urgent, 3, now) < queue_key(JobPriority::Normal, 0, earlier));
+        assert!(queue_key(JobPriority::Normal, 0, now) < queue_key(JobPriority::Normal, 2, earlier));
+        assert!(queue_key(JobPriority::Low, 1, earlier) < queue_key(JobPriority::Low, 1, now));
+
+        assert_eq!("URGENT".parse::<JobPriority>().unwrap(), JobPriority::Urgent);
+        assert!("asap".parse::<JobPriority>().is_err());
+        assert_eq!(serde_json::to_string(&PreemptionPolicy::Preempt).unwrap(), "\"preempt\"");
+    }
+
+    #[test]
+    fn test_job_queue_walk() {
+        let now = Utc::now();
+        let at = |minutes: i64| now - chrono::Duration::minutes(minutes);
+        let inventory = Some("inventory".to_string());
+        let ir = Some("ir".to_string());
+        let mut queue = JobQueue::default();
+        queue.push(JobPriority::Normal, inventory.clone(), at(30), "bulk-1".to_string());
+        queue.push(JobPriority::Normal, inventory.clone(), at(20), "bulk-2".to_string());
+        queue.push(JobPriority::Normal, ir.clone(), at(10), "ir-1".to_string());
+        queue.push(JobPriority::Urgent, None, at(0), "urgent".to_string());
+
+        let mut cursor = queue.cursor();
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("urgent"));
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("bulk-1"));
+        // Assigning bulk-1 sends the rest of inventory's jobs behind ir's
+        queue.remove(JobPriority::Normal, &inventory, at(30), "bulk-1");
+        queue.activate(&inventory);
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("ir-1"));
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("bulk-2"));
+        assert_eq!(queue.next(&mut cursor), None);
+
+        queue.deactivate(&inventory);
+        let mut cursor = queue.cursor();
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("urgent"));
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("bulk-2"));
+        assert_eq!(queue.next(&mut cursor).as_deref(), Some("ir-1"));
+
+        queue.remove(JobPriority::Urgent, &None, at(0), "urgent");
+        queue.remove(JobPriority::Normal, &inventory, at(20), "bulk-2");
+        queue.remove(JobPriority::Normal, &ir, at(10), "ir-1");
+        assert!(queue.is_empty());
+    }
+}
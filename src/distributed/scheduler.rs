@@ -3,11 +3,16 @@
 /// This module implements job scheduling, work distribution, and agent management
 /// for distributed scanning operations.
 
+use super::constraints::{AgentPlacement, JobConstraints};
+use super::queue::{JobPriority, JobQueue, PreemptionPolicy};
+use crate::capabilities::Capabilities;
 use crate::error::{ScanError, ScanResult};
+use crate::scanner::BlackoutCalendar;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
@@ -22,6 +27,22 @@ pub struct ScanJob {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub started_at: Option<chrono::DateTime<chrono::Utc>>,
     pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// What the agent that runs the job must offer
+    #[serde(default, skip_serializing_if = "JobConstraints::is_empty")]
+    pub constraints: JobConstraints,
+    #[serde(default)]
+    pub priority: JobPriority,
+    /// Team or user the job is queued for, for fair sharing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+}
+
+/// How a job is queued and where it may run
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+    pub constraints: JobConstraints,
+    pub priority: JobPriority,
+    pub submitter: Option<String>,
 }
 
 /// Job status
@@ -59,6 +80,10 @@ struct AgentInfo {
     current_job: Option<String>,
     last_heartbeat: chrono::DateTime<chrono::Utc>,
     jobs_completed: usize,
+    /// What the agent reported it can do, once advertised
+    capabilities: Option<Capabilities>,
+    /// Region, zone and rate cap the agent reported
+    placement: AgentPlacement,
 }
 
 /// Agent health status
@@ -72,11 +97,22 @@ enum AgentHealthStatus {
 }
 
 /// Scan scheduler
+///
+/// Clones share the same jobs, agents and queue.
+#[derive(Clone)]
 pub struct ScanScheduler {
     jobs: Arc<RwLock<HashMap<String, ScanJob>>>,
     agents: Arc<RwLock<HashMap<String, AgentInfo>>>,
     max_agents: usize,
     job_timeout_seconds: u64,
+    blackouts: BlackoutCalendar,
+    preemption: PreemptionPolicy,
+    /// Jobs sent back to the queue for a more urgent one
+    preemptions: Arc<AtomicUsize>,
+    /// Pending jobs in queue order, updated with every status change
+    queue: Arc<Mutex<JobQueue>>,
+    /// When blackout windows holding jobs close, to assign them then
+    wakeups: Arc<Mutex<BTreeSet<chrono::DateTime<chrono::Utc>>>>,
 }
 
 impl ScanScheduler {
@@ -96,9 +132,32 @@ impl ScanScheduler {
             agents: Arc::new(RwLock::new(HashMap::new())),
             max_agents,
             job_timeout_seconds,
+            blackouts: BlackoutCalendar::default(),
+            preemption: PreemptionPolicy::default(),
+            preemptions: Arc::new(AtomicUsize::new(0)),
+            queue: Arc::new(Mutex::new(JobQueue::default())),
+            wakeups: Arc::new(Mutex::new(BTreeSet::new())),
         }
     }
 
+    /// Whether urgent jobs only jump the queue or also take agents from
+    /// lower-priority jobs that have not started
+    pub fn with_preemption(mut self, preemption: PreemptionPolicy) -> Self {
+        self.preemption = preemption;
+        self
+    }
+
+    /// Refuse jobs, or hold them pending, during these windows
+    /// (`security.blackouts`)
+    pub fn with_blackouts(mut self, blackouts: BlackoutCalendar) -> Self {
+        self.blackouts = blackouts;
+        self
+    }
+
+    pub fn blackouts(&self) -> &BlackoutCalendar {
+        &self.blackouts
+    }
+
     /// Get job timeout in seconds
     pub fn job_timeout(&self) -> u64 {
         self.job_timeout_seconds
@@ -116,6 +175,17 @@ impl ScanScheduler {
         &mut self,
         targets: Vec<IpAddr>,
         ports: Vec<u16>,
+    ) -> ScanResult<String> {
+        self.submit_job_with(targets, ports, JobOptions::default()).await
+    }
+
+    /// Submit a scan job with a priority, a submitter to share agents
+    /// fairly with, and constraints on the agents that may run it
+    pub async fn submit_job_with(
+        &mut self,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        options: JobOptions,
     ) -> ScanResult<String> {
         if targets.is_empty() {
             return Err(ScanError::validation_error("targets", "No targets provided"));
@@ -125,6 +195,8 @@ impl ScanScheduler {
             return Err(ScanError::validation_error("ports", "No ports provided"));
         }
 
+        self.blackouts.check(&targets, chrono::Utc::now(), None)?;
+
         // Generate unique job ID
         let job_id = format!("job_{}", uuid::Uuid::new_v4());
         
@@ -137,17 +209,22 @@ impl ScanScheduler {
             created_at: chrono::Utc::now(),
             started_at: None,
             completed_at: None,
+            constraints: options.constraints,
+            priority: options.priority,
+            submitter: options.submitter,
         };
 
         let mut jobs = self.jobs.write().await;
         jobs.insert(job_id.clone(), job.clone());
+        self.queue().push(job.priority, job.submitter.clone(), job.created_at, job_id.clone());
         drop(jobs);
 
         info!(
-            "Job {} submitted: {} targets, {} ports",
+            "Job {} submitted: {} targets, {} ports, {} priority",
             job_id,
             job.targets.len(),
-            job.ports.len()
+            job.ports.len(),
+            job.priority
         );
 
         // Try to assign to an available agent
@@ -156,26 +233,62 @@ impl ScanScheduler {
         Ok(job_id)
     }
 
-    /// Try to assign a job to an available agent
-    async fn try_assign_job(&self, job_id: &str) -> ScanResult<()> {
+    fn queue(&self) -> std::sync::MutexGuard<'_, JobQueue> {
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Try to assign a pending job to an available agent; returns whether
+    /// it was assigned
+    async fn try_assign_job(&self, job_id: &str) -> ScanResult<bool> {
+        let (constraints, priority) = match self.jobs.read().await.get(job_id) {
+            Some(job) if job.status != JobStatus::Pending => return Ok(false),
+            // A job stays pending while a blackout window covers its targets
+            Some(job) => match self.blackouts.hold(&job.targets, chrono::Utc::now()) {
+                Some(active) => {
+                    debug!(
+                        "Job {} held by blackout window '{}' until {}",
+                        job_id,
+                        active.window.name,
+                        active.until.to_rfc3339()
+                    );
+                    self.schedule_wakeup(active.until);
+                    return Ok(false);
+                }
+                None => (job.constraints.clone(), job.priority),
+            },
+            None => return Ok(false),
+        };
+
         let agents = self.agents.read().await;
         
-        // Find an available agent
+        // Find an available agent that meets the job's constraints
         let available_agent = agents
             .iter()
             .find(|(_, agent)| {
-                agent.status == AgentHealthStatus::Healthy && agent.current_job.is_none()
+                agent.status == AgentHealthStatus::Healthy
+                    && agent.current_job.is_none()
+                    && constraints.is_satisfied_by(agent.capabilities.as_ref(), &agent.placement)
             })
             .map(|(id, _)| id.clone());
 
         drop(agents);
 
+        let available_agent = match available_agent {
+            None if self.preemption == PreemptionPolicy::Preempt => self.preempt_for(job_id, priority, &constraints).await,
+            agent => agent,
+        };
+
         if let Some(agent_id) = available_agent {
             let mut jobs = self.jobs.write().await;
             
             if let Some(job) = jobs.get_mut(job_id) {
                 job.status = JobStatus::Assigned;
                 job.assigned_agent = Some(agent_id.clone());
+                {
+                    let mut queue = self.queue();
+                    queue.remove(job.priority, &job.submitter, job.created_at, job_id);
+                    queue.activate(&job.submitter);
+                }
                 
                 info!("Job {} assigned to agent {}", job_id, agent_id);
                 
@@ -185,12 +298,103 @@ impl ScanScheduler {
                     agent.status = AgentHealthStatus::Busy;
                     agent.current_job = Some(job_id.to_string());
                 }
+                return Ok(true);
             }
-        } else {
+        } else if constraints.is_empty() {
             debug!("No available agents for job {}", job_id);
+        } else {
+            debug!("No available agent meets the constraints of job {} ({})", job_id, constraints);
         }
 
-        Ok(())
+        Ok(false)
+    }
+
+    /// Send the lowest-priority assigned job that has not started and
+    /// whose agent could run `job_id` back to the queue; returns the agent
+    /// it frees
+    async fn preempt_for(&self, job_id: &str, priority: JobPriority, constraints: &JobConstraints) -> Option<String> {
+        let mut jobs = self.jobs.write().await;
+        let agents = self.agents.read().await;
+        let victim = jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Assigned && job.priority < priority)
+            .filter_map(|job| {
+                let agent = agents.get(job.assigned_agent.as_deref()?)?;
+                constraints
+                    .is_satisfied_by(agent.capabilities.as_ref(), &agent.placement)
+                    .then_some(job)
+            })
+            // Lowest priority first, then the most recently submitted
+            .min_by_key(|job| (job.priority, std::cmp::Reverse(job.created_at)))
+            .map(|job| job.job_id.clone())?;
+        drop(agents);
+
+        let job = jobs.get_mut(&victim)?;
+        let agent_id = job.assigned_agent.take()?;
+        job.status = JobStatus::Pending;
+        {
+            let mut queue = self.queue();
+            queue.deactivate(&job.submitter);
+            queue.push(job.priority, job.submitter.clone(), job.created_at, victim.clone());
+        }
+        self.preemptions.fetch_add(1, Ordering::Relaxed);
+        info!(
+            "Job {} ({} priority) preempts job {} ({} priority) on agent {}",
+            job_id, priority, victim, job.priority, agent_id
+        );
+        // The agent is handed over by the caller, still busy
+        Some(agent_id)
+    }
+
+    /// Assign pending jobs to free agents in queue order: by priority, then
+    /// the submitter with the fewest active jobs, then the oldest; jobs held
+    /// by a blackout window that has closed since are assigned now
+    ///
+    /// Returns how many jobs were assigned.
+    pub async fn assign_pending_jobs(&self) -> ScanResult<usize> {
+        let mut cursor = self.queue().cursor();
+        let mut assigned = 0;
+        while self.can_assign().await {
+            let next = self.queue().next(&mut cursor);
+            let Some(job_id) = next else { break };
+            if self.try_assign_job(&job_id).await? {
+                assigned += 1;
+            }
+        }
+        Ok(assigned)
+    }
+
+    /// Assign the jobs a blackout window holds once it closes at `at`
+    fn schedule_wakeup(&self, at: chrono::DateTime<chrono::Utc>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+        if !self.wakeups.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(at) {
+            return;
+        }
+        let scheduler = self.clone();
+        runtime.spawn(async move {
+            let wait = (at - chrono::Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            scheduler.wakeups.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&at);
+            match scheduler.assign_pending_jobs().await {
+                Ok(assigned) => debug!("Blackout window closed at {}, {} held jobs assigned", at.to_rfc3339(), assigned),
+                Err(e) => warn!("Assigning jobs after blackout window closed failed: {}", e),
+            }
+        });
+    }
+
+    /// Whether a queued job could get an agent: one is free, or busy with
+    /// a job that may be preempted
+    async fn can_assign(&self) -> bool {
+        if self.queue().is_empty() {
+            return false;
+        }
+        self.preemption == PreemptionPolicy::Preempt
+            || self
+                .agents
+                .read()
+                .await
+                .values()
+                .any(|agent| agent.status == AgentHealthStatus::Healthy && agent.current_job.is_none())
     }
 
     /// Get job status
@@ -233,6 +437,8 @@ impl ScanScheduler {
             current_job: None,
             last_heartbeat: chrono::Utc::now(),
             jobs_completed: 0,
+            capabilities: None,
+            placement: AgentPlacement::default(),
         };
 
         agents.insert(agent_id.clone(), agent);
@@ -241,6 +447,47 @@ impl ScanScheduler {
         Ok(())
     }
 
+    /// Record the capabilities a registered agent advertises
+    pub async fn advertise_capabilities(&mut self, agent_id: &str, capabilities: Capabilities) -> ScanResult<()> {
+        let mut agents = self.agents.write().await;
+        let agent = agents
+            .get_mut(agent_id)
+            .ok_or_else(|| ScanError::validation_error("agent_id", "Agent not registered"))?;
+
+        debug!(
+            "Agent {} advertises scan types {:?} (raw_sockets={}, ipv6={})",
+            agent_id, capabilities.scan_types, capabilities.raw_sockets, capabilities.ipv6
+        );
+        agent.capabilities = Some(capabilities);
+        drop(agents);
+        // Jobs waiting for an agent like this one can go now
+        self.assign_pending_jobs().await?;
+        Ok(())
+    }
+
+    /// Record the region, zone and rate cap a registered agent reports
+    pub async fn advertise_placement(&mut self, agent_id: &str, placement: AgentPlacement) -> ScanResult<()> {
+        let mut agents = self.agents.write().await;
+        let agent = agents
+            .get_mut(agent_id)
+            .ok_or_else(|| ScanError::validation_error("agent_id", "Agent not registered"))?;
+
+        debug!(
+            "Agent {} advertises region {:?}, zone {:?}, max rate {:?}",
+            agent_id, placement.region, placement.zone, placement.max_rate
+        );
+        agent.placement = placement;
+        drop(agents);
+        self.assign_pending_jobs().await?;
+        Ok(())
+    }
+
+    /// Capabilities an agent advertised, if it has
+    pub async fn agent_capabilities(&self, agent_id: &str) -> ScanResult<Option<Capabilities>> {
+        let agents = self.agents.read().await;
+        Ok(agents.get(agent_id).and_then(|agent| agent.capabilities.clone()))
+    }
+
     /// Unregister an agent
     pub async fn unregister_agent(&mut self, agent_id: &str) -> ScanResult<()> {
         let mut agents = self.agents.write().await;
@@ -278,24 +525,46 @@ impl ScanScheduler {
     }
 
     /// Mark job as running
+    ///
+    /// Only a job assigned to an agent can start; a pending job, including
+    /// one that was preempted, waits for the scheduler to assign it.
     pub async fn mark_job_running(&mut self, job_id: &str) -> ScanResult<()> {
         let mut jobs = self.jobs.write().await;
-        
-        if let Some(job) = jobs.get_mut(job_id) {
-            job.status = JobStatus::Running;
-            job.started_at = Some(chrono::Utc::now());
-            info!("Job {} started", job_id);
+        let job = jobs
+            .get_mut(job_id)
+            .ok_or_else(|| ScanError::validation_error("job_id", format!("Unknown job {}", job_id)))?;
+        if job.status != JobStatus::Assigned {
+            return Err(ScanError::validation_error(
+                "job_id",
+                format!("Job {} is {}, not assigned to an agent", job_id, job.status),
+            ));
         }
+        job.status = JobStatus::Running;
+        job.started_at = Some(chrono::Utc::now());
+        info!("Job {} started", job_id);
 
         Ok(())
     }
 
+    /// Take a job that is finishing out of the queue, or out of its
+    /// submitter's active jobs
+    fn dequeue(&self, job: &ScanJob) {
+        let mut queue = self.queue();
+        match job.status {
+            JobStatus::Pending => queue.remove(job.priority, &job.submitter, job.created_at, &job.job_id),
+            JobStatus::Assigned | JobStatus::Running => queue.deactivate(&job.submitter),
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Timeout => {}
+        }
+    }
+
     /// Mark job as completed
     pub async fn mark_job_completed(&mut self, job_id: &str) -> ScanResult<()> {
         let agent_id_opt = {
             let mut jobs = self.jobs.write().await;
             
-            if let Some(job) = jobs.get_mut(job_id) {
+            // Completing a job again must not free the agent's next job
+            if let Some(job) = jobs.get_mut(job_id).filter(|job| job.status != JobStatus::Completed) {
+                self.dequeue(job);
                 job.status = JobStatus::Completed;
                 job.completed_at = Some(chrono::Utc::now());
                 job.assigned_agent.clone()
@@ -316,6 +585,8 @@ impl ScanScheduler {
             info!("Job {} completed", job_id);
         }
 
+        // The freed agent takes the next queued job
+        self.assign_pending_jobs().await?;
         Ok(())
     }
 
@@ -325,6 +596,7 @@ impl ScanScheduler {
             let mut jobs = self.jobs.write().await;
             
             if let Some(job) = jobs.get_mut(job_id) {
+                self.dequeue(job);
                 job.status = JobStatus::Failed;
                 job.completed_at = Some(chrono::Utc::now());
                 job.assigned_agent.clone()
@@ -344,6 +616,7 @@ impl ScanScheduler {
             warn!("Job {} failed", job_id);
         }
 
+        self.assign_pending_jobs().await?;
         Ok(())
     }
 
@@ -369,6 +642,10 @@ impl ScanScheduler {
         let running = jobs.values().filter(|j| j.status == JobStatus::Running).count();
         let completed = jobs.values().filter(|j| j.status == JobStatus::Completed).count();
         let failed = jobs.values().filter(|j| j.status == JobStatus::Failed).count();
+        let mut pending_by_priority: BTreeMap<JobPriority, usize> = BTreeMap::new();
+        for job in jobs.values().filter(|j| j.status == JobStatus::Pending) {
+            *pending_by_priority.entry(job.priority).or_default() += 1;
+        }
 
         SchedulerStats {
             total_jobs,
@@ -379,6 +656,9 @@ impl ScanScheduler {
             total_agents: agents.len(),
             healthy_agents: agents.values().filter(|a| a.status == AgentHealthStatus::Healthy).count(),
             busy_agents: agents.values().filter(|a| a.status == AgentHealthStatus::Busy).count(),
+            pending_by_priority,
+            preemption: self.preemption,
+            preemptions: self.preemptions.load(Ordering::Relaxed),
         }
     }
 }
@@ -394,6 +674,11 @@ pub struct SchedulerStats {
     pub total_agents: usize,
     pub healthy_agents: usize,
     pub busy_agents: usize,
+    /// Pending jobs by priority
+    pub pending_by_priority: BTreeMap<JobPriority, usize>,
+    pub preemption: PreemptionPolicy,
+    /// Jobs sent back to the queue for a more urgent one so far
+    pub preemptions: usize,
 }
 
 impl std::fmt::Display for SchedulerStats {
@@ -404,6 +689,13 @@ impl std::fmt::Display for SchedulerStats {
             self.completed_jobs, self.failed_jobs)?;
         writeln!(f, "  Agents: {} total ({} healthy, {} busy)", 
             self.total_agents, self.healthy_agents, self.busy_agents)?;
+        let queued: Vec<String> = JobPriority::ALL
+            .iter()
+            .filter_map(|p| self.pending_by_priority.get(p).map(|count| format!("{} {}", count, p)))
+            .collect();
+        writeln!(f, "  Queue: {} pending; preemption: {} ({} preempted)",
+            if queued.is_empty() { "none".to_string() } else { queued.join(", ") },
+            self.preemption, self.preemptions)?;
         Ok(())
     }
 }
@@ -451,6 +743,99 @@ mod tests {
         assert_eq!(agents.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_advertise_capabilities() {
+        let mut scheduler = ScanScheduler::new(10, 3600);
+        let capabilities = crate::capabilities().clone();
+        assert!(scheduler.advertise_capabilities("agent-1", capabilities.clone()).await.is_err());
+
+        scheduler.register_agent("agent-1".to_string(), "127.0.0.1:8081".to_string()).await.unwrap();
+        assert_eq!(scheduler.agent_capabilities("agent-1").await.unwrap(), None);
+        scheduler.advertise_capabilities("agent-1", capabilities.clone()).await.unwrap();
+        assert_eq!(scheduler.agent_capabilities("agent-1").await.unwrap(), Some(capabilities));
+    }
+
+    #[tokio::test]
+    async fn test_constrained_job_waits_for_matching_agent() {
+        let mut scheduler = ScanScheduler::new(10, 3600);
+        scheduler.register_agent("office".to_string(), "10.0.0.2:8081".to_string()).await.unwrap();
+
+        let targets = vec![IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5))];
+        let options = JobOptions {
+            constraints: "zone=dmz".parse().unwrap(),
+            ..Default::default()
+        };
+        let job_id = scheduler.submit_job_with(targets, vec![443], options).await.unwrap();
+        assert_eq!(scheduler.get_job_status(&job_id).await.unwrap(), Some(JobStatus::Pending));
+
+        scheduler.register_agent("dmz-1".to_string(), "192.0.2.10:8081".to_string()).await.unwrap();
+        let dmz = AgentPlacement {
+            zone: Some("dmz".to_string()),
+            ..Default::default()
+        };
+        scheduler.advertise_placement("dmz-1", dmz).await.unwrap();
+        let job = scheduler.get_job(&job_id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Assigned);
+        assert_eq!(job.assigned_agent.as_deref(), Some("dmz-1"));
+    }
+
+    #[tokio::test]
+    async fn test_priorities_and_preemption() {
+        let mut scheduler = ScanScheduler::new(10, 3600);
+        scheduler.register_agent("agent-1".to_string(), "10.0.0.2:8081".to_string()).await.unwrap();
+        let target = vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+        let submit = |priority: JobPriority, submitter: &str| JobOptions {
+            priority,
+            submitter: Some(submitter.to_string()),
+            ..Default::default()
+        };
+
+        let running = scheduler.submit_job_with(target.clone(), vec![22], submit(JobPriority::Low, "inventory")).await.unwrap();
+        scheduler.mark_job_running(&running).await.unwrap();
+        let bulk = scheduler.submit_job_with(target.clone(), vec![22], submit(JobPriority::Low, "inventory")).await.unwrap();
+        let urgent = scheduler.submit_job_with(target.clone(), vec![22], submit(JobPriority::Urgent, "ir")).await.unwrap();
+
+        // The urgent job jumps the queue when the agent is free again
+        scheduler.mark_job_completed(&running).await.unwrap();
+        assert_eq!(scheduler.get_job_status(&urgent).await.unwrap(), Some(JobStatus::Assigned));
+        assert_eq!(scheduler.get_job_status(&bulk).await.unwrap(), Some(JobStatus::Pending));
+
+        // With preemption an urgent job takes the agent of a queued low one
+        let mut scheduler = ScanScheduler::new(10, 3600).with_preemption(PreemptionPolicy::Preempt);
+        scheduler.register_agent("agent-1".to_string(), "10.0.0.2:8081".to_string()).await.unwrap();
+        let bulk = scheduler.submit_job_with(target.clone(), vec![22], submit(JobPriority::Low, "inventory")).await.unwrap();
+        let urgent = scheduler.submit_job_with(target, vec![22], submit(JobPriority::Urgent, "ir")).await.unwrap();
+        assert_eq!(scheduler.get_job_status(&urgent).await.unwrap(), Some(JobStatus::Assigned));
+        assert_eq!(scheduler.get_job_status(&bulk).await.unwrap(), Some(JobStatus::Pending));
+        // The preempted job cannot start on the agent it lost
+        assert!(scheduler.mark_job_running(&bulk).await.is_err());
+        let stats = scheduler.get_stats().await;
+        assert_eq!(stats.preemptions, 1);
+        assert_eq!(stats.pending_by_priority.get(&JobPriority::Low), Some(&1));
+        assert!(stats.to_string().contains("Queue: 1 low pending; preemption: preempt (1 preempted)"));
+    }
+
+    #[tokio::test]
+    async fn test_held_jobs_resume_when_blackout_closes() {
+        let now = chrono::Utc::now();
+        let spec = format!(
+            "name = \"change\"\ninterval = \"{}/{}\"",
+            (now - chrono::Duration::seconds(1)).to_rfc3339(),
+            (now + chrono::Duration::milliseconds(300)).to_rfc3339()
+        );
+        let window = toml::from_str(&spec).unwrap();
+        let mut scheduler = ScanScheduler::new(10, 3600).with_blackouts(BlackoutCalendar::new(vec![window]));
+        scheduler.register_agent("agent-1".to_string(), "10.0.0.2:8081".to_string()).await.unwrap();
+
+        let targets = vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+        let job_id = scheduler.submit_job(targets, vec![22]).await.unwrap();
+        assert_eq!(scheduler.get_job_status(&job_id).await.unwrap(), Some(JobStatus::Pending));
+
+        // Nobody calls assign_pending_jobs: the window closing does
+        tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+        assert_eq!(scheduler.get_job_status(&job_id).await.unwrap(), Some(JobStatus::Assigned));
+    }
+
     #[tokio::test]
     async fn test_job_status_transitions() {
         let mut scheduler = ScanScheduler::new(10, 3600);
@@ -459,7 +844,12 @@ mod tests {
         let ports = vec![80];
         
         let job_id = scheduler.submit_job(targets, ports).await.unwrap();
-        
+
+        // A job cannot start before it is assigned
+        assert!(scheduler.mark_job_running(&job_id).await.is_err());
+        scheduler.register_agent("agent-1".to_string(), "127.0.0.1:8081".to_string()).await.unwrap();
+        assert_eq!(scheduler.assign_pending_jobs().await.unwrap(), 1);
+
         // Mark as running
         scheduler.mark_job_running(&job_id).await.unwrap();
         let status = scheduler.get_job_status(&job_id).await.unwrap();
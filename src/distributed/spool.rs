@@ -0,0 +1,284 @@
+//! Agent-side result spooling
+//!
+//! An agent that finished a job must not lose the results because the
+//! scheduler is briefly unreachable. With a spool directory each completed
+//! job's results are written to disk as a [`ResultChunk`] before delivery is
+//! attempted, and the file is removed only once the scheduler acknowledged
+//! it. Delivery is at-least-once: an agent that crashes between delivery and
+//! acknowledgement sends the chunk again after restarting, and the
+//! aggregator drops chunks whose ID it has seen. While the scheduler stays
+//! unreachable, the agent retries with exponential backoff (1s doubling up
+//! to 5 minutes, with jitter so a fleet of agents does not reconnect in
+//! lockstep). A chunk the scheduler refuses outright, e.g. for an unknown
+//! job, is moved to `rejected/` in the spool so it does not hold back the
+//! chunks after it.
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::{CompleteScanResult, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// First reconnect delay
+const RECONNECT_BASE_DELAY_MS: u64 = 1000;
+
+/// Reconnect delays double from the base up to 5 minutes, +/-20%
+const RECONNECT_POLICY: RetryPolicy = RetryPolicy {
+    backoff_multiplier: 2.0,
+    max_delay_ms: 300_000,
+    jitter: 0.2,
+    max_elapsed_ms: None,
+    reprobe_filtered: false,
+};
+
+/// Results of one job as an agent delivers them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultChunk {
+    /// Unique per chunk; the aggregator deduplicates by it
+    pub chunk_id: String,
+    pub job_id: String,
+    pub agent_id: String,
+    pub results: Vec<CompleteScanResult>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ResultChunk {
+    pub fn new(job_id: String, agent_id: String, results: Vec<CompleteScanResult>) -> Self {
+        Self {
+            chunk_id: uuid::Uuid::new_v4().to_string(),
+            job_id,
+            agent_id,
+            results,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Directory of chunks not yet acknowledged by the scheduler
+#[derive(Debug, Clone)]
+pub struct ResultSpool {
+    dir: PathBuf,
+    /// First delay after a failed delivery
+    retry_delay_ms: u64,
+}
+
+impl ResultSpool {
+    /// Spool into `dir`, creating it if needed
+    pub fn open(dir: impl Into<PathBuf>) -> ScanResult<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to create result spool {}: {}", dir.display(), e))
+        })?;
+        Ok(Self {
+            dir,
+            retry_delay_ms: RECONNECT_BASE_DELAY_MS,
+        })
+    }
+
+    /// Start the reconnect backoff at `delay_ms` instead of one second
+    pub fn with_retry_delay_ms(mut self, delay_ms: u64) -> Self {
+        self.retry_delay_ms = delay_ms;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn chunk_path(&self, chunk_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", chunk_id))
+    }
+
+    /// Where refused chunks are kept for inspection
+    pub fn rejected_dir(&self) -> PathBuf {
+        self.dir.join("rejected")
+    }
+
+    /// Write `chunk` to the spool in one rename, so a crash never leaves it
+    /// half written
+    pub fn store(&self, chunk: &ResultChunk) -> ScanResult<()> {
+        let path = self.chunk_path(&chunk.chunk_id);
+        let json = serde_json::to_string(chunk)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to serialize result chunk: {}", e)))?;
+        let partial = path.with_extension("json.tmp");
+        std::fs::write(&partial, json)
+            .and_then(|_| std::fs::rename(&partial, &path))
+            .map_err(|e| ScanError::scanner_error(format!("Failed to spool {}: {}", path.display(), e)))?;
+        debug!("Spooled chunk {} of job {} ({} results)", chunk.chunk_id, chunk.job_id, chunk.results.len());
+        Ok(())
+    }
+
+    /// Chunks awaiting delivery, oldest first; unreadable files are skipped
+    /// with a warning and left for inspection
+    pub fn pending(&self) -> ScanResult<Vec<ResultChunk>> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to read result spool {}: {}", self.dir.display(), e)))?;
+        let mut chunks: Vec<ResultChunk> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let chunk = std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+                chunk.map_err(|e| warn!("Skipping spooled chunk {}: {}", path.display(), e)).ok()
+            })
+            .collect();
+        chunks.sort_by_key(|chunk: &ResultChunk| chunk.created_at);
+        Ok(chunks)
+    }
+
+    /// Remove a delivered chunk
+    pub fn acknowledge(&self, chunk_id: &str) -> ScanResult<()> {
+        let path = self.chunk_path(chunk_id);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(ScanError::scanner_error(format!("Failed to remove {}: {}", path.display(), e))),
+        }
+    }
+
+    /// Move a chunk the scheduler will never accept out of the spool
+    fn reject(&self, chunk_id: &str) -> ScanResult<()> {
+        let rejected = self.rejected_dir();
+        let from = self.chunk_path(chunk_id);
+        let to = rejected.join(format!("{}.json", chunk_id));
+        std::fs::create_dir_all(&rejected)
+            .and_then(|_| std::fs::rename(&from, &to))
+            .map_err(|e| ScanError::scanner_error(format!("Failed to move {} to {}: {}", from.display(), to.display(), e)))
+    }
+
+    /// Deliver every spooled chunk with `deliver`, oldest first, retrying a
+    /// failed delivery with backoff until it succeeds or `cancel` fires
+    ///
+    /// Only transient errors (network, timeouts, I/O) are retried; a chunk
+    /// failing with any other error is moved to [`ResultSpool::rejected_dir`]
+    /// and delivery goes on with the next one. Returns how many chunks were
+    /// delivered; chunks left when cancelled stay spooled for the next run.
+    pub async fn deliver<F, Fut>(&self, cancel: &CancellationToken, mut deliver: F) -> ScanResult<usize>
+    where
+        F: FnMut(ResultChunk) -> Fut,
+        Fut: Future<Output = ScanResult<()>>,
+    {
+        let mut delivered = 0;
+        for chunk in self.pending()? {
+            let chunk_id = chunk.chunk_id.clone();
+            let mut failures = 0;
+            let accepted = loop {
+                match deliver(chunk.clone()).await {
+                    Ok(()) => break true,
+                    Err(e) if !is_transient(&e) => {
+                        warn!("Scheduler rejected chunk {} of job {} ({}), moving it to rejected/", chunk_id, chunk.job_id, e);
+                        break false;
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        let delay = RECONNECT_POLICY.delay(self.retry_delay_ms, failures);
+                        warn!(
+                            "Delivering chunk {} failed ({}), retrying in {:.1}s",
+                            chunk_id,
+                            e,
+                            delay.as_secs_f64()
+                        );
+                        tokio::select! {
+                            _ = cancel.cancelled() => return Ok(delivered),
+                            _ = tokio::time::sleep(delay) => {}
+                        }
+                    }
+                }
+            };
+            if accepted {
+                self.acknowledge(&chunk_id)?;
+                delivered += 1;
+            } else {
+                self.reject(&chunk_id)?;
+            }
+        }
+        if delivered > 0 {
+            info!("Delivered {} spooled result chunks", delivered);
+        }
+        Ok(delivered)
+    }
+}
+
+/// Whether a failed delivery may succeed when tried again
+fn is_transient(error: &ScanError) -> bool {
+    error.is_retryable() || matches!(error, ScanError::Io(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distributed::ResultAggregator;
+    use crate::scanner::host_discovery::HostStatus;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_spooled_chunks_survive_failed_delivery() {
+        let dir = tempdir().unwrap();
+        let spool = ResultSpool::open(dir.path().join("spool")).unwrap().with_retry_delay_ms(1);
+        let result = CompleteScanResult::unscanned("10.0.0.1".parse().unwrap(), HostStatus::Up, Default::default());
+        let chunk = ResultChunk::new("job-1".to_string(), "agent-1".to_string(), vec![result]);
+        spool.store(&chunk).unwrap();
+
+        // The scheduler is unreachable twice, then accepts the chunk
+        let attempts = AtomicUsize::new(0);
+        let aggregator = tokio::sync::Mutex::new(ResultAggregator::new(24));
+        let delivered = spool
+            .deliver(&CancellationToken::new(), |chunk| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                let aggregator = &aggregator;
+                async move {
+                    if attempt < 2 {
+                        return Err(ScanError::network("connection refused"));
+                    }
+                    let mut aggregator = aggregator.lock().await;
+                    let (job_id, chunk_id) = (chunk.job_id.clone(), chunk.chunk_id.clone());
+                    aggregator.store_chunk(chunk).await?;
+                    aggregator.confirm_chunk(&job_id, &chunk_id).await;
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!((delivered, attempts.load(Ordering::SeqCst)), (1, 3));
+        assert!(spool.pending().unwrap().is_empty());
+
+        // A chunk delivered again after a lost acknowledgement is dropped
+        let mut aggregator = aggregator.into_inner();
+        assert!(!aggregator.store_chunk(chunk).await.unwrap());
+        assert_eq!(aggregator.get_results("job-1").await.unwrap().unwrap().total_targets, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_chunk_does_not_block_later_ones() {
+        let dir = tempdir().unwrap();
+        let spool = ResultSpool::open(dir.path().join("spool")).unwrap().with_retry_delay_ms(1);
+        let result = CompleteScanResult::unscanned("10.0.0.1".parse().unwrap(), HostStatus::Up, Default::default());
+        let mut refused = ResultChunk::new("gone".to_string(), "agent-1".to_string(), vec![result.clone()]);
+        refused.created_at -= chrono::Duration::seconds(1);
+        let accepted = ResultChunk::new("job-1".to_string(), "agent-1".to_string(), vec![result]);
+        spool.store(&refused).unwrap();
+        spool.store(&accepted).unwrap();
+
+        let attempts = AtomicUsize::new(0);
+        let delivered = spool
+            .deliver(&CancellationToken::new(), |chunk| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if chunk.job_id == "gone" {
+                        return Err(ScanError::validation_error("job_id", "Unknown job"));
+                    }
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        // The refused chunk is tried once, set aside, and the next one delivered
+        assert_eq!((delivered, attempts.load(Ordering::SeqCst)), (1, 2));
+        assert!(spool.pending().unwrap().is_empty());
+        assert!(spool.rejected_dir().join(format!("{}.json", refused.chunk_id)).exists());
+    }
+}
@@ -0,0 +1,104 @@
+//! Hostname-based host exclusion
+//!
+//! Exclusion rules match resolved PTR names (e.g. `*.voip.internal`) so that
+//! fragile device classes can be skipped by name even when their IPs change.
+
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Host skipped because its PTR name matched an exclusion rule
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExcludedHost {
+    pub target: IpAddr,
+    pub hostname: String,
+    pub rule: String,
+}
+
+/// Set of hostname exclusion patterns
+///
+/// Patterns are case-insensitive globs where `*` matches any run of
+/// characters, so `*.voip.internal` matches `phone-12.voip.internal`.
+#[derive(Debug, Clone, Default)]
+pub struct HostnameExclusion {
+    patterns: Vec<String>,
+}
+
+impl HostnameExclusion {
+    /// Create an exclusion set from patterns
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|p| p.trim().trim_end_matches('.').to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Returns true if no patterns are configured
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Return the first pattern matching a hostname, if any
+    pub fn matching_rule(&self, hostname: &str) -> Option<&str> {
+        let hostname = hostname.trim_end_matches('.').to_lowercase();
+        self.patterns
+            .iter()
+            .find(|p| glob_match(p, &hostname))
+            .map(|p| p.as_str())
+    }
+}
+
+/// Match a hostname against a glob containing `*` wildcards
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut remaining = &text[first.len()..text.len() - last.len()];
+
+    for part in &parts[1..parts.len() - 1] {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.voip.internal", "phone-12.voip.internal"));
+        assert!(!glob_match("*.voip.internal", "voip.internal"));
+        assert!(glob_match("printer-*", "printer-3f"));
+        assert!(glob_match("ups-*.dc*.example.com", "ups-01.dc2.example.com"));
+        assert!(!glob_match("ups-*.dc*.example.com", "ups-01.example.com"));
+        assert!(glob_match("exact.host", "exact.host"));
+        assert!(glob_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_matching_rule() {
+        let exclusion = HostnameExclusion::new(&[
+            "*.VOIP.internal.".to_string(),
+            "scada-*".to_string(),
+        ]);
+        assert_eq!(exclusion.matching_rule("Desk-Phone.voip.internal."), Some("*.voip.internal"));
+        assert_eq!(exclusion.matching_rule("scada-plc1.plant"), Some("scada-*"));
+        assert_eq!(exclusion.matching_rule("web01.example.com"), None);
+        assert!(HostnameExclusion::new(&[]).is_empty());
+    }
+}
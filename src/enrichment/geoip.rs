@@ -0,0 +1,186 @@
+//! GeoIP and ASN enrichment
+//!
+//! Looks up country, city, autonomous system number and organization for
+//! scanned targets using local databases in MaxMind DB (.mmdb) format.
+
+use crate::error::{ScanError, ScanResult};
+#[cfg(not(feature = "no-net"))]
+use maxminddb::{geoip2, Reader};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::Path;
+use tracing::{debug, info};
+
+/// Geolocation and network ownership data for a single address
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub as_org: Option<String>,
+}
+
+impl GeoInfo {
+    /// Returns true if no field was populated by the lookup
+    pub fn is_empty(&self) -> bool {
+        self.country.is_none()
+            && self.country_code.is_none()
+            && self.city.is_none()
+            && self.asn.is_none()
+            && self.as_org.is_none()
+    }
+
+    /// Human-readable location, e.g. "Berlin, Germany"
+    pub fn location(&self) -> Option<String> {
+        match (&self.city, &self.country) {
+            (Some(city), Some(country)) => Some(format!("{}, {}", city, country)),
+            (None, Some(country)) => Some(country.clone()),
+            (Some(city), None) => Some(city.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Human-readable ASN, e.g. "AS15169 Google LLC"
+    pub fn asn_label(&self) -> Option<String> {
+        match (self.asn, &self.as_org) {
+            (Some(asn), Some(org)) => Some(format!("AS{} {}", asn, org)),
+            (Some(asn), None) => Some(format!("AS{}", asn)),
+            (None, Some(org)) => Some(org.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GeoInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = self.location().unwrap_or_else(|| "Unknown".to_string());
+        match self.asn_label() {
+            Some(asn) => write!(f, "{} ({})", location, asn),
+            None => write!(f, "{}", location),
+        }
+    }
+}
+
+/// GeoIP/ASN enricher backed by local MaxMind DB files
+#[cfg(not(feature = "no-net"))]
+pub struct GeoIpEnricher {
+    city_db: Option<Reader<Vec<u8>>>,
+    asn_db: Option<Reader<Vec<u8>>>,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl GeoIpEnricher {
+    /// Open the configured databases
+    ///
+    /// # Arguments
+    /// * `city_db` - Optional path to a City or Country database
+    /// * `asn_db` - Optional path to an ASN database
+    ///
+    /// # Returns
+    /// * `ScanResult<Self>` - Enricher or error if a configured file cannot be read
+    pub fn open<P: AsRef<Path>>(city_db: Option<P>, asn_db: Option<P>) -> ScanResult<Self> {
+        let city_db = city_db.map(|p| Self::open_reader(p.as_ref())).transpose()?;
+        let asn_db = asn_db.map(|p| Self::open_reader(p.as_ref())).transpose()?;
+
+        if city_db.is_none() && asn_db.is_none() {
+            return Err(ScanError::validation_error(
+                "enrichment",
+                "GeoIP enabled but no database path configured",
+            ));
+        }
+
+        info!(
+            "GeoIP enrichment initialized (city: {}, asn: {})",
+            city_db.is_some(),
+            asn_db.is_some()
+        );
+
+        Ok(Self { city_db, asn_db })
+    }
+
+    fn open_reader(path: &Path) -> ScanResult<Reader<Vec<u8>>> {
+        debug!("Opening GeoIP database: {}", path.display());
+        Reader::open_readfile(path).map_err(|e| {
+            ScanError::scanner_error(format!(
+                "Failed to open GeoIP database {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Look up enrichment data for an address
+    ///
+    /// Returns `None` when the address is not present in any database
+    /// (e.g. private or loopback ranges).
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let mut info = GeoInfo::default();
+
+        if let Some(ref reader) = self.city_db {
+            match reader.lookup::<geoip2::City>(ip) {
+                Ok(city) => {
+                    if let Some(country) = city.country {
+                        info.country_code = country.iso_code.map(str::to_string);
+                        info.country = country
+                            .names
+                            .and_then(|names| names.get("en").map(|s| s.to_string()));
+                    }
+                    info.city = city
+                        .city
+                        .and_then(|c| c.names)
+                        .and_then(|names| names.get("en").map(|s| s.to_string()));
+                }
+                Err(e) => debug!("No city record for {}: {}", ip, e),
+            }
+        }
+
+        if let Some(ref reader) = self.asn_db {
+            match reader.lookup::<geoip2::Asn>(ip) {
+                Ok(asn) => {
+                    info.asn = asn.autonomous_system_number;
+                    info.as_org = asn.autonomous_system_organization.map(str::to_string);
+                }
+                Err(e) => debug!("No ASN record for {}: {}", ip, e),
+            }
+        }
+
+        if info.is_empty() {
+            None
+        } else {
+            Some(info)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "no-net"))]
+    fn test_open_without_databases() {
+        let result = GeoIpEnricher::open::<&str>(None, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-net"))]
+    fn test_open_missing_database() {
+        let result = GeoIpEnricher::open(Some("/nonexistent/GeoLite2-City.mmdb"), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_geo_info_display() {
+        let info = GeoInfo {
+            country: Some("Germany".to_string()),
+            country_code: Some("DE".to_string()),
+            city: Some("Berlin".to_string()),
+            asn: Some(3320),
+            as_org: Some("Deutsche Telekom AG".to_string()),
+        };
+        assert_eq!(info.to_string(), "Berlin, Germany (AS3320 Deutsche Telekom AG)");
+        assert!(GeoInfo::default().is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Target enrichment module for NrMAP
+//!
+//! This module attaches contextual information to scanned targets that is not
+//! obtained from the scan itself, such as geolocation, network ownership and
+//! reverse DNS names, plus hostnames observed in TLS certificates.
+
+pub mod exclusion;
+pub mod geoip;
+pub mod rdns;
+pub mod tls_names;
+
+pub use exclusion::{ExcludedHost, HostnameExclusion};
+pub use geoip::GeoInfo;
+#[cfg(not(feature = "no-net"))]
+pub use geoip::GeoIpEnricher;
+#[cfg(not(feature = "no-net"))]
+pub use rdns::ReverseDnsResolver;
+pub use tls_names::{DiscoveredHostname, HostnameSource, ObservedCertificate, TlsEvidence, VhostQueue, VhostTarget};
+#[cfg(not(feature = "no-net"))]
+pub use tls_names::TlsHostnameCollector;
+
+use serde::{Deserialize, Serialize};
+
+/// Enrichment configuration (the `[enrichment]` section of config.toml)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    /// Enable GeoIP/ASN lookups for scanned targets
+    pub geoip_enabled: bool,
+    /// Path to a City (or Country) database in MaxMind DB format
+    pub geoip_city_db: Option<String>,
+    /// Path to an ASN database in MaxMind DB format
+    pub geoip_asn_db: Option<String>,
+    /// Resolve PTR names for scanned targets
+    pub rdns_enabled: bool,
+    /// Reverse DNS lookup timeout (milliseconds)
+    pub rdns_timeout_ms: u64,
+    /// Skip hosts whose PTR name matches any of these patterns (e.g. "*.voip.internal")
+    ///
+    /// Non-empty patterns imply reverse DNS resolution.
+    pub exclude_hostnames: Vec<String>,
+    /// Record hostnames from certificates served on open TLS ports
+    pub tls_hostnames_enabled: bool,
+    /// Ports whose certificates are fetched when open
+    pub tls_ports: Vec<u16>,
+    /// Certificate fetch timeout (milliseconds)
+    pub tls_timeout_ms: u64,
+    /// Also queue discovered names for a follow-up virtual host scan
+    pub tls_vhost_queue: bool,
+}
+
+impl EnrichmentConfig {
+    /// Whether reverse DNS resolution should run
+    pub fn rdns_required(&self) -> bool {
+        self.rdns_enabled || !self.exclude_hostnames.is_empty()
+    }
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            geoip_enabled: false,
+            geoip_city_db: None,
+            geoip_asn_db: None,
+            rdns_enabled: false,
+            rdns_timeout_ms: 2000,
+            exclude_hostnames: Vec::new(),
+            tls_hostnames_enabled: false,
+            tls_ports: tls_names::DEFAULT_TLS_PORTS.to_vec(),
+            tls_timeout_ms: 3000,
+            tls_vhost_queue: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enrichment_config_default() {
+        let config = EnrichmentConfig::default();
+        assert!(!config.geoip_enabled);
+        assert!(config.geoip_city_db.is_none());
+        assert!(config.geoip_asn_db.is_none());
+        assert!(!config.rdns_required());
+    }
+
+    #[test]
+    fn test_exclusions_imply_rdns() {
+        let config = EnrichmentConfig {
+            exclude_hostnames: vec!["*.voip.internal".to_string()],
+            ..Default::default()
+        };
+        assert!(config.rdns_required());
+    }
+}
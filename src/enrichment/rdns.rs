@@ -0,0 +1,65 @@
+//! Reverse DNS enrichment
+//!
+//! Resolves PTR names for scanned targets using the system resolver.
+
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::debug;
+
+/// Reverse DNS (PTR) resolver with a per-lookup timeout
+#[derive(Debug, Clone)]
+#[cfg(not(feature = "no-net"))]
+pub struct ReverseDnsResolver {
+    timeout: Duration,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl ReverseDnsResolver {
+    /// Create a new resolver
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+
+    /// Resolve the PTR name for an address
+    ///
+    /// Returns `None` if there is no PTR record or the lookup times out.
+    pub async fn resolve(&self, ip: IpAddr) -> Option<String> {
+        let lookup = tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip));
+
+        match tokio::time::timeout(self.timeout, lookup).await {
+            Ok(Ok(Ok(name))) => {
+                let name = name.trim_end_matches('.').to_lowercase();
+                debug!("Reverse DNS for {}: {}", ip, name);
+                Some(name)
+            }
+            Ok(Ok(Err(e))) => {
+                debug!("No PTR record for {}: {}", ip, e);
+                None
+            }
+            Ok(Err(e)) => {
+                debug!("Reverse DNS task failed for {}: {}", ip, e);
+                None
+            }
+            Err(_) => {
+                debug!("Reverse DNS lookup for {} timed out", ip);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_resolve_does_not_panic() {
+        let resolver = ReverseDnsResolver::new(1000);
+        // Result depends on the environment's resolver configuration
+        let _ = resolver.resolve(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))).await;
+    }
+}
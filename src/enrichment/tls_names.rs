@@ -0,0 +1,229 @@
+//! Hostname discovery from TLS certificates
+//!
+//! Certificates served on open TLS ports often name other hosts through their
+//! subject alternative names. Collecting those names during the scan turns
+//! scan evidence into new assets, and optionally feeds them to a queue of
+//! virtual hosts to scan next. The certificates' expiry dates are kept too,
+//! for policies on expiring certificates.
+
+#[cfg(not(feature = "no-net"))]
+use crate::detection::tls::TlsCertificateGrabber;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Ports probed for certificates by default
+pub const DEFAULT_TLS_PORTS: [u16; 8] = [443, 465, 636, 993, 995, 8443, 9443, 10443];
+
+/// Where a discovered hostname came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostnameSource {
+    TlsSubjectAltName,
+    TlsCommonName,
+}
+
+/// A hostname observed while scanning a host
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DiscoveredHostname {
+    pub hostname: String,
+    pub port: u16,
+    pub source: HostnameSource,
+}
+
+/// A name-based virtual host to scan as a follow-up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VhostTarget {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub hostname: String,
+}
+
+/// Certificate served on one of a host's open ports
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ObservedCertificate {
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub common_name: Option<String>,
+    /// End of the validity period, if it could be parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+/// What certificates on a host's TLS ports revealed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsEvidence {
+    pub hostnames: Vec<DiscoveredHostname>,
+    pub certificates: Vec<ObservedCertificate>,
+}
+
+/// Shared queue of follow-up virtual host scans
+#[derive(Debug, Clone, Default)]
+pub struct VhostQueue {
+    inner: Arc<Mutex<VecDeque<VhostTarget>>>,
+}
+
+impl VhostQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a virtual host unless it is already waiting
+    pub fn push(&self, target: VhostTarget) {
+        let mut queue = self.inner.lock().unwrap();
+        if !queue.contains(&target) {
+            queue.push_back(target);
+        }
+    }
+
+    /// Take every queued virtual host
+    pub fn drain(&self) -> Vec<VhostTarget> {
+        self.inner.lock().unwrap().drain(..).collect()
+    }
+
+    /// Number of queued virtual hosts
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    /// Whether the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Collects hostnames from certificates on a host's open TLS ports
+#[derive(Debug, Clone)]
+#[cfg(not(feature = "no-net"))]
+pub struct TlsHostnameCollector {
+    grabber: TlsCertificateGrabber,
+    ports: Vec<u16>,
+    queue: Option<VhostQueue>,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl TlsHostnameCollector {
+    /// Create a collector probing the given ports
+    pub fn new(ports: Vec<u16>, timeout_ms: u64) -> Self {
+        Self {
+            grabber: TlsCertificateGrabber::new(timeout_ms),
+            ports,
+            queue: None,
+        }
+    }
+
+    /// Also queue every concrete (non-wildcard) name for a vhost scan
+    pub fn with_vhost_queue(mut self, queue: VhostQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Queue receiving follow-up virtual hosts, if enabled
+    pub fn vhost_queue(&self) -> Option<&VhostQueue> {
+        self.queue.as_ref()
+    }
+
+    /// Fetch certificates from the open ports that are TLS candidates
+    pub async fn collect(&self, target: IpAddr, open_ports: &[u16]) -> TlsEvidence {
+        let mut discovered: Vec<DiscoveredHostname> = Vec::new();
+        let mut certificates = Vec::new();
+
+        for &port in open_ports.iter().filter(|p| self.ports.contains(p)) {
+            let names = match self.grabber.grab(target, port, None).await {
+                Ok(Some(names)) => names,
+                Ok(None) => continue,
+                Err(e) => {
+                    debug!("No certificate from {}:{}: {}", target, port, e);
+                    continue;
+                }
+            };
+            certificates.push(ObservedCertificate {
+                port,
+                common_name: names.common_name.clone(),
+                not_after: names.not_after,
+            });
+
+            let common_name = names
+                .common_name
+                .map(|cn| cn.to_lowercase())
+                .filter(|cn| looks_like_hostname(cn) && !names.dns_names.contains(cn))
+                .map(|cn| (cn, HostnameSource::TlsCommonName));
+            let sans = names
+                .dns_names
+                .into_iter()
+                .map(|name| (name, HostnameSource::TlsSubjectAltName));
+
+            for (hostname, source) in sans.chain(common_name) {
+                if discovered.iter().any(|d| d.hostname == hostname) {
+                    continue;
+                }
+                if let Some(ref queue) = self.queue {
+                    if !hostname.starts_with("*.") {
+                        queue.push(VhostTarget {
+                            ip: target,
+                            port,
+                            hostname: hostname.clone(),
+                        });
+                    }
+                }
+                discovered.push(DiscoveredHostname { hostname, port, source });
+            }
+        }
+
+        TlsEvidence {
+            hostnames: discovered,
+            certificates,
+        }
+    }
+}
+
+/// CNs are free text; only keep ones shaped like a DNS name
+fn looks_like_hostname(name: &str) -> bool {
+    name.contains('.')
+        && name.parse::<IpAddr>().is_err()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '*' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_hostname() {
+        assert!(looks_like_hostname("www.example.com"));
+        assert!(looks_like_hostname("*.example.com"));
+        assert!(!looks_like_hostname("Acme Corp Internal CA"));
+        assert!(!looks_like_hostname("localhost"));
+        assert!(!looks_like_hostname("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_vhost_queue_dedup() {
+        let queue = VhostQueue::new();
+        let target = VhostTarget {
+            ip: "10.0.0.1".parse().unwrap(),
+            port: 443,
+            hostname: "portal.example.com".to_string(),
+        };
+        queue.push(target.clone());
+        queue.push(target.clone());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.drain(), vec![target]);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_collect_skips_non_tls_ports() {
+        let collector = TlsHostnameCollector::new(vec![443], 200);
+        // Port 22 is not a TLS candidate, so nothing is contacted
+        let found = collector.collect("127.0.0.1".parse().unwrap(), &[22]).await;
+        assert_eq!(found, TlsEvidence::default());
+    }
+}
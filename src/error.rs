@@ -94,6 +94,10 @@ pub enum ScanError {
     #[error("Validation error: {field} - {reason}")]
     ValidationError { field: String, reason: String },
 
+    /// Results that break a `--policy` rule or `--allowed-ports`
+    #[error("Policy violation: {message}")]
+    PolicyViolation { message: String },
+
     /// Generic scanner errors
     #[error("Scanner error: {message}")]
     ScannerError { message: String },
@@ -199,6 +203,13 @@ impl ScanError {
         }
     }
 
+    /// Create a policy violation error
+    pub fn policy_violation<S: Into<String>>(message: S) -> Self {
+        ScanError::PolicyViolation {
+            message: message.into(),
+        }
+    }
+
     /// Log this error with appropriate level and context
     pub fn log(&self) {
         match self {
@@ -0,0 +1,493 @@
+//! AWS EC2 inventory
+//!
+//! Lists running instances and the network interfaces not attached to an
+//! instance (load balancers, NAT gateways, Lambda functions in a VPC) through
+//! the EC2 query API. Requests are signed with Signature Version 4 using
+//! credentials from `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY` (and
+//! `AWS_SESSION_TOKEN`), or from a profile in `~/.aws/credentials`. Only
+//! `ec2:DescribeInstances` and `ec2:DescribeNetworkInterfaces` are needed.
+
+use super::{api_error, http_agent, AssetSource, InventoryAsset};
+use crate::error::{ScanError, ScanResult};
+use hmac::{Hmac, Mac};
+use roxmltree::{Document, Node};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tracing::debug;
+
+const EC2_API_VERSION: &str = "2016-11-15";
+
+/// Access key pair, with the session token of temporary credentials
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Credentials from the AWS_* environment variables
+    pub fn from_env() -> ScanResult<Self> {
+        Ok(Self {
+            access_key_id: super::env_var("AWS_ACCESS_KEY_ID", "AWS")?,
+            secret_access_key: super::env_var("AWS_SECRET_ACCESS_KEY", "AWS")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok().filter(|t| !t.is_empty()),
+        })
+    }
+
+    /// Credentials of `profile` in the shared credentials file
+    pub fn from_profile(profile: &str) -> ScanResult<Self> {
+        let path = std::env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| aws_dir().join("credentials"));
+        let section = ini_section(&path, profile)?.ok_or_else(|| {
+            ScanError::validation_error("source", format!("No AWS profile '{}' in {}", profile, path.display()))
+        })?;
+        let key = |name: &str| {
+            section.get(name).cloned().ok_or_else(|| {
+                ScanError::validation_error("source", format!("AWS profile '{}' has no {}", profile, name))
+            })
+        };
+        Ok(Self {
+            access_key_id: key("aws_access_key_id")?,
+            secret_access_key: key("aws_secret_access_key")?,
+            session_token: section.get("aws_session_token").cloned(),
+        })
+    }
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Running EC2 instances and unattached network interfaces in one region
+pub struct Ec2Source {
+    profile: Option<String>,
+    region: String,
+    credentials: AwsCredentials,
+    agent: ureq::Agent,
+}
+
+impl Ec2Source {
+    /// Use `profile` (or the AWS_* variables) in `region`
+    ///
+    /// Without a region, AWS_REGION, AWS_DEFAULT_REGION or the profile's
+    /// region in `~/.aws/config` is used.
+    pub fn from_env(profile: Option<&str>, region: Option<&str>) -> ScanResult<Self> {
+        let credentials = match profile {
+            Some(profile) => AwsCredentials::from_profile(profile)?,
+            None => AwsCredentials::from_env()?,
+        };
+        let region = match region {
+            Some(region) => region.to_string(),
+            None => default_region(profile)?,
+        };
+        Ok(Self {
+            profile: profile.map(str::to_string),
+            region,
+            credentials,
+            agent: http_agent(),
+        })
+    }
+
+    fn host(&self) -> String {
+        format!("ec2.{}.amazonaws.com", self.region)
+    }
+
+    /// Call an EC2 action, following `nextToken` pages
+    fn call_paged(&self, action: &str, filters: &[(&str, &str)]) -> ScanResult<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let mut params = vec![
+                ("Action".to_string(), action.to_string()),
+                ("Version".to_string(), EC2_API_VERSION.to_string()),
+            ];
+            for (index, (name, value)) in filters.iter().enumerate() {
+                params.push((format!("Filter.{}.Name", index + 1), name.to_string()));
+                params.push((format!("Filter.{}.Value.1", index + 1), value.to_string()));
+            }
+            if let Some(ref token) = next_token {
+                params.push(("NextToken".to_string(), token.clone()));
+            }
+            let body = params
+                .iter()
+                .map(|(k, v)| format!("{}={}", uri_encode(k), uri_encode(v)))
+                .collect::<Vec<_>>()
+                .join("&");
+
+            let page = self.post(&body)?;
+            next_token = Document::parse(&page)
+                .ok()
+                .and_then(|doc| text(doc.root_element(), "nextToken").map(str::to_string))
+                .filter(|token| !token.is_empty());
+            pages.push(page);
+            if next_token.is_none() {
+                return Ok(pages);
+            }
+        }
+    }
+
+    fn post(&self, body: &str) -> ScanResult<String> {
+        let host = self.host();
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut headers = vec![
+            ("content-type", "application/x-www-form-urlencoded; charset=utf-8".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(ref token) = self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let request = SigningRequest {
+            method: "POST",
+            path: "/",
+            query: "",
+            headers: &headers,
+            payload: body.as_bytes(),
+        };
+        let authorization = sign_v4(&self.credentials, &self.region, "ec2", &request, &amz_date);
+
+        let mut call = self.agent.post(&format!("https://{}/", host));
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            call = call.set(name, value);
+        }
+        call.set("authorization", &authorization)
+            .send_string(body)
+            .map_err(|e| api_error("AWS EC2", e))?
+            .into_string()
+            .map_err(|e| ScanError::network(format!("Failed to read AWS EC2 response: {}", e)))
+    }
+}
+
+impl AssetSource for Ec2Source {
+    fn describe(&self) -> String {
+        match self.profile {
+            Some(ref profile) => format!("aws:{}@{}", profile, self.region),
+            None => format!("aws@{}", self.region),
+        }
+    }
+
+    fn assets(&self) -> ScanResult<Vec<InventoryAsset>> {
+        let mut assets = Vec::new();
+        for page in self.call_paged("DescribeInstances", &[("instance-state-name", "running")])? {
+            assets.extend(parse_instances(&page)?);
+        }
+        for page in self.call_paged("DescribeNetworkInterfaces", &[("status", "in-use")])? {
+            assets.extend(parse_unattached_interfaces(&page)?);
+        }
+        debug!("{} lists {} addresses", self.describe(), assets.len());
+        Ok(assets)
+    }
+}
+
+/// Parts of an HTTP request covered by the signature
+pub struct SigningRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    /// Canonical (sorted, encoded) query string
+    pub query: &'a str,
+    /// Lowercase header names with their values; every one is signed
+    pub headers: &'a [(&'a str, String)],
+    pub payload: &'a [u8],
+}
+
+/// `Authorization` header value for a Signature Version 4 request made at
+/// `amz_date` (`YYYYMMDDTHHMMSSZ`)
+pub fn sign_v4(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    request: &SigningRequest<'_>,
+    amz_date: &str,
+) -> String {
+    let mut headers: Vec<(&str, &str)> = request.headers.iter().map(|(n, v)| (*n, v.trim())).collect();
+    headers.sort();
+    let canonical_headers: String = headers.iter().map(|(n, v)| format!("{}:{}\n", n, v)).collect();
+    let signed_headers = headers.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.path,
+        request.query,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(request.payload))
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [date, region, service, "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", credentials.secret_access_key).into_bytes(), |key, part| {
+            hmac_sha256(&key, part.as_bytes())
+        });
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id,
+        scope,
+        signed_headers,
+        hex(&hmac_sha256(&key, string_to_sign.as_bytes()))
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent-encode everything but the RFC 3986 unreserved characters
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Addresses of the instances in a DescribeInstances response
+pub fn parse_instances(xml: &str) -> ScanResult<Vec<InventoryAsset>> {
+    let doc = parse_xml(xml)?;
+    let mut assets = Vec::new();
+    let instances = items(doc.root_element(), "reservationSet").flat_map(|reservation| items(reservation, "instancesSet"));
+    for instance in instances {
+        let Some(id) = text(instance, "instanceId") else {
+            continue;
+        };
+        let tags = tags(instance);
+        let mut addresses = Vec::new();
+        for interface in items(instance, "networkInterfaceSet") {
+            addresses.extend(interface_addresses(interface));
+        }
+        if addresses.is_empty() {
+            addresses.extend(text(instance, "privateIpAddress").map(|ip| (ip, false)));
+            addresses.extend(text(instance, "ipAddress").map(|ip| (ip, true)));
+        }
+        assets.extend(assets_for(id, tags.get("Name").cloned(), &tags, addresses));
+    }
+    Ok(assets)
+}
+
+/// Addresses of the in-use interfaces of a DescribeNetworkInterfaces
+/// response that are not attached to an instance
+pub fn parse_unattached_interfaces(xml: &str) -> ScanResult<Vec<InventoryAsset>> {
+    let doc = parse_xml(xml)?;
+    let mut assets = Vec::new();
+    for interface in items(doc.root_element(), "networkInterfaceSet") {
+        let attached = child(interface, "attachment").and_then(|a| text(a, "instanceId")).is_some();
+        let Some(id) = text(interface, "networkInterfaceId").filter(|_| !attached) else {
+            continue;
+        };
+        let tags = tags(interface);
+        let name = tags.get("Name").cloned().or_else(|| text(interface, "description").map(str::to_string));
+        assets.extend(assets_for(id, name, &tags, interface_addresses(interface)));
+    }
+    Ok(assets)
+}
+
+fn assets_for(
+    id: &str,
+    name: Option<String>,
+    tags: &BTreeMap<String, String>,
+    addresses: Vec<(&str, bool)>,
+) -> Vec<InventoryAsset> {
+    addresses
+        .into_iter()
+        .filter_map(|(address, public)| {
+            Some(InventoryAsset {
+                address: address.parse().ok()?,
+                public,
+                resource_id: id.to_string(),
+                name: name.clone().filter(|n| !n.is_empty()),
+                tags: tags.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Private, public and IPv6 addresses of a network interface element;
+/// IPv6 addresses count as public
+fn interface_addresses<'a>(interface: Node<'a, 'a>) -> Vec<(&'a str, bool)> {
+    let mut addresses = Vec::new();
+    for private in items(interface, "privateIpAddressesSet") {
+        addresses.extend(text(private, "privateIpAddress").map(|ip| (ip, false)));
+        addresses.extend(child(private, "association").and_then(|a| text(a, "publicIp")).map(|ip| (ip, true)));
+    }
+    for ipv6 in items(interface, "ipv6AddressesSet") {
+        addresses.extend(text(ipv6, "ipv6Address").map(|ip| (ip, true)));
+    }
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+fn tags(node: Node<'_, '_>) -> BTreeMap<String, String> {
+    items(node, "tagSet")
+        .filter_map(|tag| Some((text(tag, "key")?.to_string(), text(tag, "value").unwrap_or("").to_string())))
+        .collect()
+}
+
+fn parse_xml(xml: &str) -> ScanResult<Document<'_>> {
+    Document::parse(xml).map_err(|e| ScanError::network(format!("Invalid AWS EC2 response: {}", e)))
+}
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn text<'a>(node: Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+/// `<item>` children of the `set` element
+fn items<'a>(node: Node<'a, 'a>, set: &str) -> impl Iterator<Item = Node<'a, 'a>> {
+    child(node, set)
+        .into_iter()
+        .flat_map(|set| set.children().filter(|n| n.tag_name().name() == "item"))
+}
+
+fn aws_dir() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default().join(".aws")
+}
+
+fn default_region(profile: Option<&str>) -> ScanResult<String> {
+    let from_env = ["AWS_REGION", "AWS_DEFAULT_REGION"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|r| !r.is_empty()));
+    if let Some(region) = from_env {
+        return Ok(region);
+    }
+    let config = std::env::var_os("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| aws_dir().join("config"));
+    let section = match profile {
+        Some(profile) if profile != "default" => format!("profile {}", profile),
+        _ => "default".to_string(),
+    };
+    ini_section(&config, &section)?
+        .and_then(|section| section.get("region").cloned())
+        .ok_or_else(|| ScanError::validation_error("source", "No AWS region given (use aws:PROFILE@REGION or AWS_REGION)"))
+}
+
+/// Keys of one `[section]` of an INI file; `None` if the file or section is
+/// missing
+fn ini_section(path: &std::path::Path, name: &str) -> ScanResult<Option<HashMap<String, String>>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(ScanError::scanner_error(format!("Failed to read {}: {}", path.display(), e))),
+    };
+    let mut section = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if section.is_some() {
+                break;
+            }
+            if header.trim() == name {
+                section = Some(HashMap::new());
+            }
+        } else if let (Some(keys), Some((key, value))) = (section.as_mut(), line.split_once('=')) {
+            keys.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    Ok(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_v4() {
+        // Example request from the AWS Signature Version 4 documentation
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let headers = [
+            ("content-type", "application/x-www-form-urlencoded; charset=utf-8".to_string()),
+            ("host", "iam.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+        let request = SigningRequest {
+            method: "GET",
+            path: "/",
+            query: "Action=ListUsers&Version=2010-05-08",
+            headers: &headers,
+            payload: b"",
+        };
+        assert_eq!(
+            sign_v4(&credentials, "us-east-1", "iam", &request, "20150830T123600Z"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+        assert_eq!(uri_encode("a b/c=d"), "a%20b%2Fc%3Dd");
+    }
+
+    #[test]
+    fn test_parse_ec2_responses() {
+        let instances = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DescribeInstancesResponse xmlns="http://ec2.amazonaws.com/doc/2016-11-15/">
+  <reservationSet><item><instancesSet><item>
+    <instanceId>i-0abc</instanceId>
+    <privateIpAddress>10.0.1.5</privateIpAddress>
+    <ipAddress>54.1.2.3</ipAddress>
+    <tagSet><item><key>Name</key><value>web-1</value></item><item><key>env</key><value>prod</value></item></tagSet>
+    <networkInterfaceSet><item>
+      <privateIpAddressesSet>
+        <item><privateIpAddress>10.0.1.5</privateIpAddress><association><publicIp>54.1.2.3</publicIp></association></item>
+        <item><privateIpAddress>10.0.1.6</privateIpAddress></item>
+      </privateIpAddressesSet>
+      <ipv6AddressesSet><item><ipv6Address>2600:1f18::5</ipv6Address></item></ipv6AddressesSet>
+    </item></networkInterfaceSet>
+  </item></instancesSet></item></reservationSet>
+</DescribeInstancesResponse>"#;
+        let assets = parse_instances(instances).unwrap();
+        let addresses: Vec<(String, bool)> = assets.iter().map(|a| (a.address.to_string(), a.public)).collect();
+        assert_eq!(
+            addresses,
+            vec![
+                ("10.0.1.5".to_string(), false),
+                ("10.0.1.6".to_string(), false),
+                ("2600:1f18::5".to_string(), true),
+                ("54.1.2.3".to_string(), true),
+            ]
+        );
+        assert_eq!(assets[0].name.as_deref(), Some("web-1"));
+        assert!(assets[0].has_tag("env", Some("prod")));
+
+        let interfaces = r#"<DescribeNetworkInterfacesResponse xmlns="http://ec2.amazonaws.com/doc/2016-11-15/">
+  <networkInterfaceSet>
+    <item><networkInterfaceId>eni-1</networkInterfaceId><description>ELB app/web</description>
+      <privateIpAddressesSet><item><privateIpAddress>10.0.2.9</privateIpAddress></item></privateIpAddressesSet></item>
+    <item><networkInterfaceId>eni-2</networkInterfaceId><attachment><instanceId>i-0abc</instanceId></attachment>
+      <privateIpAddressesSet><item><privateIpAddress>10.0.1.5</privateIpAddress></item></privateIpAddressesSet></item>
+  </networkInterfaceSet>
+</DescribeNetworkInterfacesResponse>"#;
+        let assets = parse_unattached_interfaces(interfaces).unwrap();
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].resource_id, "eni-1");
+        assert_eq!(assets[0].name.as_deref(), Some("ELB app/web"));
+    }
+}
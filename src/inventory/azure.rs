@@ -0,0 +1,189 @@
+//! Azure network inventory
+//!
+//! Lists the network interfaces of a subscription with their private
+//! addresses and the public IP resources associated with them, through the
+//! Azure Resource Manager API. The bearer token comes from
+//! `AZURE_ACCESS_TOKEN` (e.g. from `az account get-access-token`), or is
+//! requested for the service principal in `AZURE_TENANT_ID`,
+//! `AZURE_CLIENT_ID` and `AZURE_CLIENT_SECRET`. The Reader role is enough.
+
+use super::{api_error, env_var, http_agent, read_json, AssetSource, InventoryAsset};
+use crate::error::{ScanError, ScanResult};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use tracing::debug;
+
+const MANAGEMENT_URL: &str = "https://management.azure.com";
+const NETWORK_API_VERSION: &str = "2023-09-01";
+
+/// Network interfaces of one subscription
+pub struct NetworkSource {
+    subscription: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl NetworkSource {
+    /// List `subscription` with credentials from the environment
+    pub fn from_env(subscription: &str) -> ScanResult<Self> {
+        let agent = http_agent();
+        let token = match std::env::var("AZURE_ACCESS_TOKEN").ok().filter(|t| !t.is_empty()) {
+            Some(token) => token,
+            None => service_principal_token(&agent)?,
+        };
+        Ok(Self {
+            subscription: subscription.to_string(),
+            token,
+            agent,
+        })
+    }
+
+    /// Every item of a paged resource list
+    fn list(&self, resource: &str) -> ScanResult<Vec<Value>> {
+        let mut url = format!(
+            "{}/subscriptions/{}/providers/Microsoft.Network/{}?api-version={}",
+            MANAGEMENT_URL, self.subscription, resource, NETWORK_API_VERSION
+        );
+        let mut items = Vec::new();
+        loop {
+            let response = self
+                .agent
+                .get(&url)
+                .set("authorization", &format!("Bearer {}", self.token))
+                .call()
+                .map_err(|e| api_error("Azure", e))?;
+            let mut page = read_json("Azure", response)?;
+            if let Value::Array(values) = page["value"].take() {
+                items.extend(values);
+            }
+            match page["nextLink"].as_str().filter(|link| !link.is_empty()) {
+                Some(next) => url = next.to_string(),
+                None => return Ok(items),
+            }
+        }
+    }
+}
+
+impl AssetSource for NetworkSource {
+    fn describe(&self) -> String {
+        format!("azure:{}", self.subscription)
+    }
+
+    fn assets(&self) -> ScanResult<Vec<InventoryAsset>> {
+        let public_ips = self.list("publicIPAddresses")?;
+        let interfaces = self.list("networkInterfaces")?;
+        let assets = parse_interfaces(&interfaces, &public_ips);
+        debug!("{} lists {} addresses", self.describe(), assets.len());
+        Ok(assets)
+    }
+}
+
+/// Addresses of network interfaces, resolving their public IP references
+/// against `public_ips`
+pub fn parse_interfaces(interfaces: &[Value], public_ips: &[Value]) -> Vec<InventoryAsset> {
+    // Resource IDs are case-insensitive
+    let public: HashMap<String, &str> = public_ips
+        .iter()
+        .filter_map(|ip| Some((ip["id"].as_str()?.to_lowercase(), ip["properties"]["ipAddress"].as_str()?)))
+        .collect();
+
+    let mut assets = Vec::new();
+    for interface in interfaces {
+        let id = interface["id"].as_str().unwrap_or_default();
+        let properties = &interface["properties"];
+        // Name the interface after its VM when it has one
+        let name = properties["virtualMachine"]["id"]
+            .as_str()
+            .and_then(|vm| vm.rsplit('/').next())
+            .or(interface["name"].as_str())
+            .map(str::to_string);
+        let tags: BTreeMap<String, String> = interface["tags"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.as_str().unwrap_or_default().to_string()))
+            .collect();
+
+        for config in properties["ipConfigurations"].as_array().into_iter().flatten() {
+            let config = &config["properties"];
+            let mut addresses: Vec<(&str, bool)> = Vec::new();
+            addresses.extend(config["privateIPAddress"].as_str().map(|ip| (ip, false)));
+            let public_id = config["publicIPAddress"]["id"].as_str().map(str::to_lowercase);
+            addresses.extend(public_id.and_then(|id| public.get(&id)).map(|ip| (*ip, true)));
+            assets.extend(addresses.into_iter().filter_map(|(address, public)| {
+                Some(InventoryAsset {
+                    address: address.parse().ok()?,
+                    public,
+                    resource_id: id.to_string(),
+                    name: name.clone(),
+                    tags: tags.clone(),
+                })
+            }));
+        }
+    }
+    assets
+}
+
+/// Request a management token with the client credentials grant
+fn service_principal_token(agent: &ureq::Agent) -> ScanResult<String> {
+    let tenant = env_var("AZURE_TENANT_ID", "Azure").map_err(|_| {
+        ScanError::validation_error(
+            "source",
+            "Azure credentials need AZURE_ACCESS_TOKEN, or AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET",
+        )
+    })?;
+    let client_id = env_var("AZURE_CLIENT_ID", "Azure")?;
+    let client_secret = env_var("AZURE_CLIENT_SECRET", "Azure")?;
+
+    let response = agent
+        .post(&format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", tenant))
+        .send_form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+            ("scope", "https://management.azure.com/.default"),
+        ])
+        .map_err(|e| api_error("Azure login", e))?;
+    let response = read_json("Azure login", response)?;
+    response["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ScanError::network("Azure login response has no access_token"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interfaces() {
+        let public_ips = vec![serde_json::json!({
+            "id": "/subscriptions/s/resourceGroups/rg/providers/Microsoft.Network/publicIPAddresses/web-ip",
+            "properties": { "ipAddress": "20.1.2.3" }
+        })];
+        let interfaces = vec![serde_json::json!({
+            "id": "/subscriptions/s/resourceGroups/rg/providers/Microsoft.Network/networkInterfaces/web-nic",
+            "name": "web-nic",
+            "tags": { "env": "prod" },
+            "properties": {
+                "virtualMachine": { "id": "/subscriptions/s/resourceGroups/rg/providers/Microsoft.Compute/virtualMachines/web-vm" },
+                "ipConfigurations": [{
+                    "properties": {
+                        "privateIPAddress": "10.1.0.4",
+                        "publicIPAddress": {
+                            "id": "/subscriptions/s/resourceGroups/RG/providers/Microsoft.Network/publicIPAddresses/web-ip"
+                        }
+                    }
+                }]
+            }
+        })];
+        let assets = parse_interfaces(&interfaces, &public_ips);
+        let addresses: Vec<(String, bool)> = assets.iter().map(|a| (a.address.to_string(), a.public)).collect();
+        assert_eq!(
+            addresses,
+            vec![("10.1.0.4".to_string(), false), ("20.1.2.3".to_string(), true)]
+        );
+        assert_eq!(assets[0].name.as_deref(), Some("web-vm"));
+        assert!(assets[1].has_tag("env", None));
+    }
+}
@@ -0,0 +1,161 @@
+//! Google Compute Engine inventory
+//!
+//! Lists the running instances of a project in every zone through the
+//! Compute API's aggregated list. The OAuth access token comes from
+//! `GOOGLE_OAUTH_ACCESS_TOKEN` or `CLOUDSDK_AUTH_ACCESS_TOKEN` (e.g. from
+//! `gcloud auth print-access-token`); `compute.instances.list` is the only
+//! permission needed.
+
+use super::{api_error, http_agent, read_json, AssetSource, InventoryAsset};
+use crate::error::{ScanError, ScanResult};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+const TOKEN_VARIABLES: [&str; 2] = ["GOOGLE_OAUTH_ACCESS_TOKEN", "CLOUDSDK_AUTH_ACCESS_TOKEN"];
+
+/// Running instances of one project
+pub struct ComputeSource {
+    project: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl ComputeSource {
+    /// List `project` with the access token from the environment
+    pub fn from_env(project: &str) -> ScanResult<Self> {
+        let token = TOKEN_VARIABLES
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|t| !t.is_empty()))
+            .ok_or_else(|| {
+                ScanError::validation_error(
+                    "source",
+                    format!("GCP credentials need {} to be set", TOKEN_VARIABLES.join(" or ")),
+                )
+            })?;
+        Ok(Self {
+            project: project.to_string(),
+            token,
+            agent: http_agent(),
+        })
+    }
+}
+
+impl AssetSource for ComputeSource {
+    fn describe(&self) -> String {
+        format!("gcp:{}", self.project)
+    }
+
+    fn assets(&self) -> ScanResult<Vec<InventoryAsset>> {
+        let url = format!(
+            "https://compute.googleapis.com/compute/v1/projects/{}/aggregated/instances",
+            self.project
+        );
+        let mut assets = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = self
+                .agent
+                .get(&url)
+                .set("authorization", &format!("Bearer {}", self.token))
+                .query("filter", "status = RUNNING");
+            if let Some(ref token) = page_token {
+                request = request.query("pageToken", token);
+            }
+            let response = request.call().map_err(|e| api_error("GCP Compute", e))?;
+            let page = read_json("GCP Compute", response)?;
+            assets.extend(parse_aggregated_instances(&page));
+
+            page_token = page["nextPageToken"].as_str().filter(|t| !t.is_empty()).map(str::to_string);
+            if page_token.is_none() {
+                break;
+            }
+        }
+        debug!("{} lists {} addresses", self.describe(), assets.len());
+        Ok(assets)
+    }
+}
+
+/// Addresses of the running instances in an aggregated list page
+pub fn parse_aggregated_instances(page: &Value) -> Vec<InventoryAsset> {
+    let instances = page["items"]
+        .as_object()
+        .into_iter()
+        .flat_map(|zones| zones.values())
+        .filter_map(|zone| zone["instances"].as_array())
+        .flatten()
+        .filter(|instance| instance["status"].as_str().is_none_or(|status| status == "RUNNING"));
+
+    let mut assets = Vec::new();
+    for instance in instances {
+        let id = instance["id"].as_str().or(instance["name"].as_str()).unwrap_or_default();
+        let name = instance["name"].as_str().map(str::to_string);
+        let tags: BTreeMap<String, String> = instance["labels"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.as_str().unwrap_or_default().to_string()))
+            .collect();
+
+        for interface in instance["networkInterfaces"].as_array().into_iter().flatten() {
+            let mut addresses: Vec<(&str, bool)> = Vec::new();
+            addresses.extend(interface["networkIP"].as_str().map(|ip| (ip, false)));
+            addresses.extend(interface["ipv6Address"].as_str().map(|ip| (ip, false)));
+            for config in interface["accessConfigs"].as_array().into_iter().flatten() {
+                addresses.extend(config["natIP"].as_str().map(|ip| (ip, true)));
+            }
+            for config in interface["ipv6AccessConfigs"].as_array().into_iter().flatten() {
+                addresses.extend(config["externalIpv6"].as_str().map(|ip| (ip, true)));
+            }
+            assets.extend(addresses.into_iter().filter_map(|(address, public)| {
+                Some(InventoryAsset {
+                    address: address.parse().ok()?,
+                    public,
+                    resource_id: id.to_string(),
+                    name: name.clone(),
+                    tags: tags.clone(),
+                })
+            }));
+        }
+    }
+    assets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aggregated_instances() {
+        let page = serde_json::json!({
+            "items": {
+                "zones/us-central1-a": {
+                    "instances": [{
+                        "id": "123",
+                        "name": "api-1",
+                        "status": "RUNNING",
+                        "labels": { "env": "prod" },
+                        "networkInterfaces": [{
+                            "networkIP": "10.128.0.2",
+                            "accessConfigs": [{ "natIP": "34.1.2.3" }, {}]
+                        }]
+                    }, {
+                        "id": "124",
+                        "name": "stopped",
+                        "status": "TERMINATED",
+                        "networkInterfaces": [{ "networkIP": "10.128.0.3" }]
+                    }]
+                },
+                "zones/europe-west1-b": { "warning": { "code": "NO_RESULTS_ON_PAGE" } }
+            }
+        });
+        let assets = parse_aggregated_instances(&page);
+        let addresses: Vec<(String, bool)> = assets.iter().map(|a| (a.address.to_string(), a.public)).collect();
+        assert_eq!(
+            addresses,
+            vec![("10.128.0.2".to_string(), false), ("34.1.2.3".to_string(), true)]
+        );
+        assert_eq!(assets[0].name.as_deref(), Some("api-1"));
+        assert!(assets[1].has_tag("env", Some("prod")));
+    }
+}
@@ -0,0 +1,228 @@
+//! Cloud inventory target sources
+//!
+//! Instead of exporting address lists by hand, a scan can take its targets
+//! from a cloud account: `aws[:PROFILE][@REGION]`, `gcp:PROJECT` or
+//! `azure:SUBSCRIPTION`. Each provider lists the running instances and
+//! network interfaces with their private and public addresses and tags,
+//! using read-only credentials from the environment.
+//!
+//! The provider APIs are called with a blocking HTTP client; async callers
+//! should run [`AssetSource::assets`] on a blocking thread.
+
+pub mod aws;
+pub mod azure;
+pub mod gcp;
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Timeout of a single provider API call
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One address of a cloud resource
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryAsset {
+    pub address: IpAddr,
+    /// Reachable from the internet rather than only inside the network
+    pub public: bool,
+    /// Provider resource ID (instance or network interface)
+    pub resource_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Provider tags or labels
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl InventoryAsset {
+    /// Whether the asset carries `key` with `value` (any value when `None`)
+    pub fn has_tag(&self, key: &str, value: Option<&str>) -> bool {
+        self.tags.get(key).is_some_and(|v| value.is_none_or(|value| v == value))
+    }
+}
+
+/// A provider that can enumerate assets
+pub trait AssetSource {
+    /// Human-readable source name, e.g. `aws:prod@eu-west-1`
+    fn describe(&self) -> String;
+
+    /// List every address of every running resource
+    fn assets(&self) -> ScanResult<Vec<InventoryAsset>>;
+}
+
+/// Parsed `--source` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSpec {
+    /// `aws[:PROFILE][@REGION]`; without a profile the AWS_* variables are used
+    Aws { profile: Option<String>, region: Option<String> },
+    /// `gcp:PROJECT`
+    Gcp { project: String },
+    /// `azure:SUBSCRIPTION`
+    Azure { subscription: String },
+}
+
+impl SourceSpec {
+    /// Create the provider for this spec, reading credentials from the
+    /// environment
+    pub fn connect(&self) -> ScanResult<Box<dyn AssetSource>> {
+        Ok(match self {
+            SourceSpec::Aws { profile, region } => Box::new(aws::Ec2Source::from_env(profile.as_deref(), region.as_deref())?),
+            SourceSpec::Gcp { project } => Box::new(gcp::ComputeSource::from_env(project)?),
+            SourceSpec::Azure { subscription } => Box::new(azure::NetworkSource::from_env(subscription)?),
+        })
+    }
+}
+
+impl FromStr for SourceSpec {
+    type Err = ScanError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        let (provider, rest) = match spec.find([':', '@']) {
+            Some(at) => (&spec[..at], spec[at..].strip_prefix(':').unwrap_or(&spec[at..])),
+            None => (spec, ""),
+        };
+        let invalid = |reason: &str| ScanError::validation_error("source", format!("'{}': {}", spec, reason));
+        match provider.to_lowercase().as_str() {
+            "aws" => {
+                let (profile, region) = match rest.split_once('@') {
+                    Some((profile, region)) => (profile, Some(region)),
+                    None => (rest, None),
+                };
+                if region == Some("") {
+                    return Err(invalid("missing region after '@'"));
+                }
+                Ok(SourceSpec::Aws {
+                    profile: (!profile.is_empty()).then(|| profile.to_string()),
+                    region: region.map(str::to_string),
+                })
+            }
+            "gcp" | "gce" if !rest.is_empty() => Ok(SourceSpec::Gcp { project: rest.to_string() }),
+            "azure" if !rest.is_empty() => Ok(SourceSpec::Azure {
+                subscription: rest.to_string(),
+            }),
+            "gcp" | "gce" => Err(invalid("expected gcp:PROJECT")),
+            "azure" => Err(invalid("expected azure:SUBSCRIPTION")),
+            _ => Err(invalid("unknown provider (expected aws, gcp or azure)")),
+        }
+    }
+}
+
+impl fmt::Display for SourceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceSpec::Aws { profile, region } => {
+                write!(f, "aws")?;
+                if let Some(profile) = profile {
+                    write!(f, ":{}", profile)?;
+                }
+                if let Some(region) = region {
+                    write!(f, "@{}", region)?;
+                }
+                Ok(())
+            }
+            SourceSpec::Gcp { project } => write!(f, "gcp:{}", project),
+            SourceSpec::Azure { subscription } => write!(f, "azure:{}", subscription),
+        }
+    }
+}
+
+/// Keep assets matching every `KEY` or `KEY=VALUE` filter
+pub fn filter_by_tags(assets: Vec<InventoryAsset>, filters: &[String]) -> Vec<InventoryAsset> {
+    assets
+        .into_iter()
+        .filter(|asset| {
+            filters.iter().all(|filter| match filter.split_once('=') {
+                Some((key, value)) => asset.has_tag(key, Some(value)),
+                None => asset.has_tag(filter, None),
+            })
+        })
+        .collect()
+}
+
+/// Distinct addresses of a set of assets, in order
+pub fn target_addresses(assets: &[InventoryAsset]) -> Vec<IpAddr> {
+    let addresses: BTreeSet<IpAddr> = assets.iter().map(|asset| asset.address).collect();
+    addresses.into_iter().collect()
+}
+
+fn http_agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(API_TIMEOUT).build()
+}
+
+/// Describe a failed provider call, including the error body the API sent
+fn api_error(provider: &str, e: ureq::Error) -> ScanError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            ScanError::network(format!("{} API returned {}: {}", provider, status, body.trim()))
+        }
+        e => ScanError::network(format!("{} API request failed: {}", provider, e)),
+    }
+}
+
+/// Parse a provider response body as JSON
+fn read_json(provider: &str, response: ureq::Response) -> ScanResult<serde_json::Value> {
+    let body = response
+        .into_string()
+        .map_err(|e| ScanError::network(format!("Failed to read {} response: {}", provider, e)))?;
+    serde_json::from_str(&body).map_err(|e| ScanError::network(format!("Invalid {} response: {}", provider, e)))
+}
+
+/// Read a required environment variable
+fn env_var(name: &str, provider: &str) -> ScanResult<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| ScanError::validation_error("source", format!("{} credentials need {} to be set", provider, name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_spec() {
+        assert_eq!(
+            "aws:prod-account@eu-west-1".parse::<SourceSpec>().unwrap(),
+            SourceSpec::Aws {
+                profile: Some("prod-account".to_string()),
+                region: Some("eu-west-1".to_string())
+            }
+        );
+        assert_eq!(
+            "aws".parse::<SourceSpec>().unwrap(),
+            SourceSpec::Aws { profile: None, region: None }
+        );
+        for spec in ["aws:prod", "aws@us-east-2", "gcp:my-project", "azure:0000-1111"] {
+            assert_eq!(spec.parse::<SourceSpec>().unwrap().to_string(), spec);
+        }
+        assert!("gcp".parse::<SourceSpec>().is_err());
+        assert!("aws:prod@".parse::<SourceSpec>().is_err());
+        assert!("vmware:dc1".parse::<SourceSpec>().is_err());
+
+        let asset = |address: &str, tags: &[(&str, &str)]| InventoryAsset {
+            address: address.parse().unwrap(),
+            public: false,
+            resource_id: "i-1".to_string(),
+            name: None,
+            tags: tags.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        let assets = vec![
+            asset("10.0.0.2", &[("env", "prod"), ("team", "web")]),
+            asset("10.0.0.1", &[("env", "prod")]),
+            asset("10.0.0.2", &[("env", "prod"), ("team", "web")]),
+            asset("10.0.0.3", &[("env", "staging")]),
+        ];
+        let prod = filter_by_tags(assets, &["env=prod".to_string()]);
+        assert_eq!(prod.len(), 3);
+        let addresses: Vec<String> = target_addresses(&prod).iter().map(IpAddr::to_string).collect();
+        assert_eq!(addresses, vec!["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(filter_by_tags(prod, &["team".to_string()]).len(), 2);
+    }
+}
@@ -0,0 +1,133 @@
+//! LAN utilities: MAC addresses, vendor lookup and Wake-on-LAN
+//!
+//! MACs come from ARP warm-up of on-link targets. Reports show them with the
+//! vendor their OUI is registered to, and `nrmap wol` wakes sleeping assets
+//! before a maintenance-window scan.
+
+pub mod oui;
+pub mod wol;
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+pub use oui::{install_oui_database, mac_vendor, OuiDatabase};
+pub use wol::magic_packet;
+#[cfg(not(feature = "no-net"))]
+pub use wol::{wake, WOL_PORT};
+
+/// 48-bit Ethernet MAC address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    /// Organizationally unique identifier: the first three octets
+    pub fn oui(&self) -> [u8; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    /// Locally administered addresses (randomized Wi-Fi MACs, most VM
+    /// defaults) carry no vendor registration
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+}
+
+impl From<[u8; 6]> for MacAddress {
+    fn from(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", a, b, c, d, e, g)
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = ScanError;
+
+    /// Accepts `aa:bb:cc:dd:ee:ff`, `AA-BB-CC-DD-EE-FF`, Cisco style
+    /// `aabb.ccdd.eeff` and bare `aabbccddeeff`
+    fn from_str(s: &str) -> ScanResult<Self> {
+        let digits: String = s.trim().chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect();
+        let invalid = || ScanError::validation_error("mac", format!("Invalid MAC address '{}'", s));
+        if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let mut octets = [0u8; 6];
+        for (i, octet) in octets.iter_mut().enumerate() {
+            *octet = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(octets))
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// MAC address of a scanned host and the vendor registered for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostMac {
+    pub address: MacAddress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+}
+
+impl HostMac {
+    /// Host MAC with its vendor from the active OUI database
+    pub fn new(address: MacAddress) -> Self {
+        Self {
+            address,
+            vendor: mac_vendor(&address),
+        }
+    }
+}
+
+impl fmt::Display for HostMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vendor {
+            Some(ref vendor) => write!(f, "{} ({})", self.address, vendor),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mac_formats() {
+        let expected = MacAddress([0x00, 0x50, 0x56, 0xab, 0xcd, 0xef]);
+        for text in ["00:50:56:ab:cd:ef", "00-50-56-AB-CD-EF", "0050.56ab.cdef", "005056abcdef"] {
+            assert_eq!(text.parse::<MacAddress>().unwrap(), expected, "{}", text);
+        }
+        assert!("00:50:56:ab:cd".parse::<MacAddress>().is_err());
+        assert!("00:50:56:ab:cd:eg".parse::<MacAddress>().is_err());
+
+        let host = HostMac::new(expected);
+        assert_eq!(host.to_string(), "00:50:56:ab:cd:ef (VMware)");
+        let json = serde_json::to_string(&host).unwrap();
+        assert_eq!(json, r#"{"address":"00:50:56:ab:cd:ef","vendor":"VMware"}"#);
+        assert_eq!(serde_json::from_str::<HostMac>(&json).unwrap(), host);
+        assert!("02:00:00:00:00:01".parse::<MacAddress>().unwrap().is_locally_administered());
+    }
+}
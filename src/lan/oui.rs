@@ -0,0 +1,170 @@
+//! MAC vendor lookup by OUI
+//!
+//! A table of vendors common on scanned networks is embedded;
+//! `scanner.oui_file` merges the IEEE `oui.txt`, its CSV export, or a
+//! Wireshark `manuf` file over it.
+
+use super::MacAddress;
+use crate::error::{ScanError, ScanResult};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::debug;
+
+const EMBEDDED_TABLE: &str = include_str!("oui.txt");
+
+/// Vendor names by OUI
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OuiDatabase {
+    vendors: HashMap<[u8; 3], String>,
+}
+
+impl OuiDatabase {
+    /// Parse OUI data in any of the supported formats:
+    ///
+    /// - IEEE `oui.txt`: `00-00-0C   (hex)   Cisco Systems, Inc`
+    /// - IEEE CSV: `MA-L,00000C,"Cisco Systems, Inc",address`
+    /// - Wireshark `manuf`: `00:00:0C<TAB>Cisco<TAB>Cisco Systems, Inc`
+    ///
+    /// The first entry for a prefix names it. Lines that are none of these,
+    /// such as the address lines of `oui.txt` and the longer MA-M/MA-S
+    /// prefixes of `manuf`, are skipped.
+    pub fn parse(content: &str) -> Self {
+        let mut database = Self::default();
+        for line in content.lines() {
+            if let Some((oui, vendor)) = parse_line(line) {
+                database.vendors.entry(oui).or_insert(vendor);
+            }
+        }
+        database
+    }
+
+    /// The table shipped with nrmap
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_TABLE)
+    }
+
+    /// Add entries from an OUI file; its vendors replace existing ones
+    pub fn merge_file(&mut self, path: impl AsRef<Path>) -> ScanResult<()> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to read OUI file {}: {}", path.display(), e)))?;
+        let overrides = Self::parse(&content);
+        if overrides.vendors.is_empty() {
+            return Err(ScanError::validation_error(
+                "oui_file",
+                format!("{} holds no OUI entries", path.display()),
+            ));
+        }
+        self.vendors.extend(overrides.vendors);
+        Ok(())
+    }
+
+    /// Vendor registered for a MAC address; locally administered addresses
+    /// have none unless the table lists their prefix (as for QEMU)
+    pub fn lookup(&self, mac: &MacAddress) -> Option<&str> {
+        if mac.is_multicast() {
+            return None;
+        }
+        self.vendors.get(&mac.oui()).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+}
+
+fn parse_line(line: &str) -> Option<([u8; 3], String)> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    if let Some(record) = line.strip_prefix("MA-L,") {
+        let (prefix, rest) = record.split_once(',')?;
+        let vendor = if let Some(quoted) = rest.strip_prefix('"') {
+            quoted.split('"').next()?
+        } else {
+            rest.split(',').next()?
+        };
+        return Some((parse_prefix(prefix)?, vendor.trim().to_string()));
+    }
+
+    let (prefix, rest) = line.split_once(|c: char| c.is_whitespace())?;
+    let oui = parse_prefix(prefix)?;
+    let rest = rest.trim_start();
+    let rest = rest
+        .strip_prefix("(hex)")
+        .or_else(|| rest.strip_prefix("(base 16)"))
+        .unwrap_or(rest);
+    // manuf lines carry a short and a long name; the long one reads better
+    let vendor = rest.split('\t').map(str::trim).rfind(|s| !s.is_empty())?;
+    Some((oui, vendor.to_string()))
+}
+
+/// Three-octet prefix as `00:00:0C`, `00-00-0C` or `00000C`
+fn parse_prefix(prefix: &str) -> Option<[u8; 3]> {
+    let digits: String = prefix.chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect();
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut oui = [0u8; 3];
+    for (i, octet) in oui.iter_mut().enumerate() {
+        *octet = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(oui)
+}
+
+fn active_database() -> &'static RwLock<Arc<OuiDatabase>> {
+    static DATABASE: OnceLock<RwLock<Arc<OuiDatabase>>> = OnceLock::new();
+    DATABASE.get_or_init(|| RwLock::new(Arc::new(OuiDatabase::embedded())))
+}
+
+/// Replace the OUI database used for MAC vendor lookups
+pub fn install_oui_database(database: OuiDatabase) {
+    debug!("Installed OUI database: {} entries", database.len());
+    *active_database().write().unwrap() = Arc::new(database);
+}
+
+/// Vendor of a MAC address in the active OUI database
+pub fn mac_vendor(mac: &MacAddress) -> Option<String> {
+    active_database().read().unwrap().lookup(mac).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oui_formats() {
+        let mac = |s: &str| s.parse::<MacAddress>().unwrap();
+        let embedded = OuiDatabase::embedded();
+        assert_eq!(embedded.lookup(&mac("b8:27:eb:12:34:56")), Some("Raspberry Pi Foundation"));
+        assert_eq!(embedded.lookup(&mac("52:54:00:12:34:56")), Some("QEMU/KVM"));
+        assert_eq!(embedded.lookup(&mac("01:00:5e:00:00:fb")), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oui.txt");
+        std::fs::write(
+            &path,
+            "OUI/MA-L                                                    Organization\n\
+             00-00-0C   (hex)\t\tCisco Systems, Inc\n\
+             00000C     (base 16)\t\tCisco Systems, Inc\n\
+             \t\t\t\t170 WEST TASMAN DRIVE\n\
+             MA-L,A4C3F0,\"Intel Corporate, Inc\",Lot 8 Jalan Hi-Tech\n\
+             00:1B:C5:00:00/36\tConverg\tConverging Systems Inc.\n\
+             FC:FB:FB\tCisco\tCisco Systems, Inc\n",
+        )
+        .unwrap();
+        let mut database = embedded.clone();
+        database.merge_file(&path).unwrap();
+        assert_eq!(database.lookup(&mac("00:00:0c:07:ac:01")), Some("Cisco Systems, Inc"));
+        assert_eq!(database.lookup(&mac("a4:c3:f0:00:00:01")), Some("Intel Corporate, Inc"));
+        assert_eq!(database.lookup(&mac("fc:fb:fb:00:00:01")), Some("Cisco Systems, Inc"));
+        assert_eq!(database.lookup(&mac("00:50:56:00:00:01")), Some("VMware"));
+        assert_eq!(database.len(), embedded.len() + 2);
+
+        std::fs::write(&path, "not an oui file\n").unwrap();
+        assert!(database.merge_file(&path).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Wake-on-LAN
+//!
+//! Sends the magic packet (six `0xff` bytes, then the target MAC sixteen
+//! times) as a UDP broadcast, so sleeping assets are up before a scan
+//! scheduled for their maintenance window starts.
+
+use super::MacAddress;
+#[cfg(not(feature = "no-net"))]
+use {
+    crate::error::{ScanError, ScanResult},
+    std::net::SocketAddr,
+    std::time::Duration,
+    tokio::net::UdpSocket,
+    tracing::debug,
+};
+
+/// Conventional Wake-on-LAN port (discard); 7 is the other common choice
+#[cfg(not(feature = "no-net"))]
+pub const WOL_PORT: u16 = 9;
+
+/// Magic packet waking the NIC with address `mac`
+pub fn magic_packet(mac: &MacAddress) -> Vec<u8> {
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac.0);
+    }
+    packet
+}
+
+/// Send the magic packet for `mac` to `target`, usually a broadcast
+/// address, `count` times a short interval apart since it is fire and forget
+#[cfg(not(feature = "no-net"))]
+pub async fn wake(mac: &MacAddress, target: SocketAddr, count: u32) -> ScanResult<()> {
+    let bind: SocketAddr = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+    let socket = UdpSocket::bind(bind).await?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| ScanError::network(format!("Cannot enable broadcast: {}", e)))?;
+
+    let packet = magic_packet(mac);
+    for attempt in 0..count.max(1) {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        socket
+            .send_to(&packet, target)
+            .await
+            .map_err(|e| ScanError::network(format!("Wake-on-LAN to {} via {} failed: {}", mac, target, e)))?;
+    }
+    debug!("Sent {} Wake-on-LAN packet(s) for {} to {}", count.max(1), mac, target);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_packet() {
+        let mac = MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        let packet = magic_packet(&mac);
+        assert_eq!(packet.len(), 102);
+        assert_eq!(&packet[..6], &[0xff; 6]);
+        assert!(packet[6..].chunks(6).all(|chunk| chunk == mac.0));
+    }
+}
@@ -1,3 +1,6 @@
+// The no-net core leaves out the scanners, so some helpers go unused
+#![cfg_attr(feature = "no-net", allow(unused_imports, dead_code))]
+
 /// NrMAP - Network Scanner Library
 /// 
 /// A high-performance network scanner written in Rust with comprehensive
@@ -8,26 +11,58 @@ pub mod config;
 pub mod error;
 pub mod logging;
 pub mod scanner;
+#[cfg(not(feature = "no-net"))]
 pub mod packet;
 pub mod detection;
+#[cfg(not(feature = "no-net"))]
 pub mod distributed;
 pub mod cli;
 pub mod report;
 pub mod os_fingerprint;
+pub mod enrichment;
+pub mod lan;
+#[cfg(not(feature = "no-net"))]
+pub mod inventory;
+#[cfg(not(feature = "no-net"))]
+pub mod blocking;
+#[cfg(not(feature = "no-net"))]
+pub mod capabilities;
+pub mod policy;
+#[cfg(not(feature = "no-net"))]
+pub mod notify;
+#[cfg(not(feature = "no-net"))]
+pub mod schedule;
+#[cfg(not(feature = "no-net"))]
+pub mod monitor;
 
 #[cfg(feature = "python")]
 pub mod python;
 
+#[cfg(all(feature = "python", feature = "no-net"))]
+compile_error!("the python bindings need the network code; build without the no-net feature");
+
 // Re-export commonly used types
-pub use config::AppConfig;
+pub use config::{AppConfig, ConfigLayers};
 pub use error::{ScanError, ScanResult};
-pub use scanner::{Scanner, ScanType};
+#[cfg(not(feature = "no-net"))]
+pub use scanner::Scanner;
+pub use scanner::{ScanType, DiscoveryProbe, ConsensusPolicy, ThrottleStrategy};
+#[cfg(not(feature = "no-net"))]
 pub use packet::{PacketEngine, PacketBuilder};
-pub use detection::{DetectionEngine, ServiceBanner, ServiceFingerprint, OsMatch};
+#[cfg(not(feature = "no-net"))]
+pub use detection::{DetectionEngine, ServiceBanner};
+pub use detection::{ServiceFingerprint, OsMatch};
+#[cfg(not(feature = "no-net"))]
 pub use distributed::{DistributedScanner, ScanAgent, ScanScheduler};
 pub use cli::{Cli, ScanProfile, OutputFormatter, OutputFormat};
 pub use report::{ReportEngine, ReportBuilder, ScanReport, ReportFormat};
 pub use os_fingerprint::{OsFingerprintEngine, OsFingerprint, OsMatchResult};
+pub use enrichment::GeoInfo;
+#[cfg(not(feature = "no-net"))]
+pub use enrichment::GeoIpEnricher;
+#[cfg(not(feature = "no-net"))]
+pub use capabilities::{capabilities, Capabilities};
+pub use policy::{ComplianceReport, Policy};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -66,11 +101,10 @@ pub const NAME: &str = env!("CARGO_PKG_NAME");
 ///     Ok(())
 /// }
 /// ```
+#[cfg(not(feature = "no-net"))]
 pub async fn init_library<P: AsRef<std::path::Path>>(
     config_path: Option<P>,
 ) -> ScanResult<(Scanner, Option<tracing_appender::non_blocking::WorkerGuard>)> {
-    use tracing::info;
-
     // Load configuration
     let config = if let Some(path) = config_path {
         AppConfig::from_file(path)?
@@ -78,18 +112,103 @@ pub async fn init_library<P: AsRef<std::path::Path>>(
         AppConfig::default()
     };
 
+    init_library_with_config(config).await
+}
+
+/// Initialize the library with an already loaded configuration
+///
+/// Like [`init_library`], for configuration merged from several sources
+/// with [`ConfigLayers`](config::ConfigLayers) or built in code.
+#[cfg(not(feature = "no-net"))]
+pub async fn init_library_with_config(
+    config: AppConfig,
+) -> ScanResult<(Scanner, Option<tracing_appender::non_blocking::WorkerGuard>)> {
+    use tracing::{info, warn};
+
     // Initialize logging
     let guard = logging::init_logging(&config.logging)?;
 
+    // Make organization port presets resolvable by name
+    scanner::port_presets::register_presets(&config.presets)?;
+
+    // Rank ports for --top-ports from the configured frequency data
+    if let Some(ref path) = config.scanner.port_frequency_file {
+        let table = scanner::port_frequency::PortFrequencyTable::from_file(path)?;
+        scanner::port_frequency::install_port_frequencies(table);
+    }
+
+    // Merge local service names over the embedded table
+    if let Some(ref path) = config.scanner.services_file {
+        let mut names = scanner::service_names::ServiceNames::embedded();
+        names.merge_file(path)?;
+        scanner::service_names::install_service_names(names);
+    }
+
+    // Merge a full OUI registry over the embedded vendor table
+    if let Some(ref path) = config.scanner.oui_file {
+        let mut database = lan::OuiDatabase::embedded();
+        database.merge_file(path)?;
+        lan::install_oui_database(database);
+    }
+
     info!("{} v{} initialized", NAME, VERSION);
 
     // Create scanner
-    let scanner = Scanner::new(config.scanner);
+    let mut scanner = Scanner::new(config.scanner)
+        .with_throttling(config.throttling.clone())
+        .with_security(&config.security)
+        .with_operator(config.general.operator.clone());
+
+    // Attach GeoIP/ASN enrichment if configured
+    if config.enrichment.geoip_enabled {
+        match enrichment::GeoIpEnricher::open(
+            config.enrichment.geoip_city_db.as_deref(),
+            config.enrichment.geoip_asn_db.as_deref(),
+        ) {
+            Ok(enricher) => scanner = scanner.with_geoip(std::sync::Arc::new(enricher)),
+            Err(e) => warn!("GeoIP enrichment disabled: {}", e),
+        }
+    }
+
+    // Attach reverse DNS and hostname exclusion rules
+    if config.enrichment.rdns_required() {
+        scanner = scanner
+            .with_reverse_dns(enrichment::ReverseDnsResolver::new(config.enrichment.rdns_timeout_ms))
+            .with_hostname_exclusion(enrichment::HostnameExclusion::new(
+                &config.enrichment.exclude_hostnames,
+            ));
+    }
+
+    // Collect hostnames from TLS certificates
+    if config.enrichment.tls_hostnames_enabled {
+        let mut collector = enrichment::TlsHostnameCollector::new(
+            config.enrichment.tls_ports.clone(),
+            config.enrichment.tls_timeout_ms,
+        );
+        if config.enrichment.tls_vhost_queue {
+            collector = collector.with_vhost_queue(enrichment::VhostQueue::new());
+        }
+        scanner = scanner.with_tls_hostnames(collector);
+    }
+
+    // Fingerprint scanned hosts on a separate work queue
+    if scanner.config().os_detection.enabled {
+        let queue = scanner::os_detection::OsDetectionQueue::from_config(&scanner.config().os_detection);
+        scanner = scanner.with_os_detection(queue);
+    }
+
+    // Record scan traffic to pcapng if configured
+    if let Some(ref path) = config.output.pcap_file {
+        scanner = scanner.with_pcap_output(path);
+    }
 
     Ok((scanner, guard))
 }
 
-/// Parse a port range string (e.g., "1-1000", "80,443,8080")
+/// Parse a port range string (e.g., "1-1000", "80,443,8080", "ssh,http,https")
+///
+/// Service names resolve through the service names table, preferring the
+/// TCP assignment.
 /// 
 /// # Arguments
 /// * `port_str` - Port range string
@@ -112,8 +231,11 @@ pub fn parse_port_range(port_str: &str) -> ScanResult<Vec<u16>> {
 
     for part in port_str.split(',') {
         let part = part.trim();
-        
-        if part.contains('-') {
+
+        if let Some(port) = part.parse::<u16>().ok().or_else(|| scanner::service_names::service_port(part)) {
+            // Single port, by number or service name ("ssh", "ms-wbt-server")
+            ports.push(port);
+        } else if part.contains('-') {
             // Range like "1-100"
             let range_parts: Vec<&str> = part.split('-').collect();
             if range_parts.len() != 2 {
@@ -137,11 +259,10 @@ pub fn parse_port_range(port_str: &str) -> ScanResult<Vec<u16>> {
 
             ports.extend(start..=end);
         } else {
-            // Single port
-            let port: u16 = part.parse().map_err(|_| {
-                ScanError::validation_error("port", format!("Invalid port number: {}", part))
-            })?;
-            ports.push(port);
+            return Err(ScanError::validation_error(
+                "port",
+                format!("Invalid port number or unknown service: {}", part),
+            ));
         }
     }
 
@@ -161,26 +282,21 @@ pub fn parse_port_range(port_str: &str) -> ScanResult<Vec<u16>> {
 
 /// Parse common port presets
 /// 
+/// Organization presets from the `[presets]` config table resolve too once
+/// [`init_library`] has registered them.
+/// 
 /// # Arguments
 /// * `preset` - Preset name (e.g., "common", "all", "web")
 /// 
 /// # Returns
 /// * `ScanResult<Vec<u16>>` - Vector of port numbers
 pub fn parse_port_preset(preset: &str) -> ScanResult<Vec<u16>> {
-    match preset.to_lowercase().as_str() {
-        "common" | "top100" => Ok(vec![
-            21, 22, 23, 25, 53, 80, 110, 111, 135, 139,
-            143, 443, 445, 993, 995, 1723, 3306, 3389, 5900, 8080,
-        ]),
-        "web" => Ok(vec![80, 443, 8000, 8080, 8443, 8888]),
-        "mail" => Ok(vec![25, 110, 143, 465, 587, 993, 995]),
-        "database" => Ok(vec![1433, 3306, 5432, 27017, 6379]),
-        "all" => Ok((1..=65535).collect()),
-        _ => Err(ScanError::validation_error(
+    scanner::port_presets::resolve_preset(preset).ok_or_else(|| {
+        ScanError::validation_error(
             "preset",
             format!("Unknown port preset: {}", preset),
-        )),
-    }
+        )
+    })
 }
 
 #[cfg(test)]
@@ -217,6 +333,14 @@ mod tests {
         assert_eq!(ports, vec![80, 443]);
     }
 
+    #[test]
+    fn test_parse_port_range_service_names() {
+        let ports = parse_port_range("ssh,HTTP,https,8000-8001").unwrap();
+        assert_eq!(ports, vec![22, 80, 443, 8000, 8001]);
+        assert_eq!(parse_port_range("ms-wbt-server").unwrap(), vec![3389]);
+        assert!(parse_port_range("ssh,no-such-service").is_err());
+    }
+
     #[test]
     fn test_parse_port_range_invalid() {
         assert!(parse_port_range("invalid").is_err());
@@ -3,12 +3,20 @@
 /// This module initializes and configures the tracing-based logging system
 /// with support for console and file output, structured logging, and configurable levels.
 
+pub mod remote;
+pub mod scan_log;
+
 use crate::config::LoggingConfig;
 use crate::error::{ScanError, ScanResult};
 use std::path::Path;
+use std::sync::OnceLock;
 use tracing::{info, Level};
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
+
+/// Handle to swap the level filter of the installed subscriber
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 /// Initialize the logging system based on configuration
 /// 
@@ -24,6 +32,10 @@ pub fn init_logging(config: &LoggingConfig) -> ScanResult<Option<WorkerGuard>> {
     // Create environment filter
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(format!("nrmap={}", config.level)));
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+    let _ = FILTER_HANDLE.set(filter_handle);
+    let scan_log = config.scan_log_dir.as_ref().map(scan_log::ScanLogLayer::new).transpose()?;
+    let remote = remote::RemoteLogLayer::from_config(config)?;
 
     let guard = if config.file_logging {
         // Setup file logging
@@ -50,13 +62,15 @@ pub fn init_logging(config: &LoggingConfig) -> ScanResult<Option<WorkerGuard>> {
         if config.format == "json" {
             tracing_subscriber::registry()
                 .with(env_filter)
+                .with(scan_log)
+                .with(remote)
                 .with(
                     fmt::layer()
                         .json()
                         .with_target(true)
                         .with_thread_ids(true)
                         .with_thread_names(true)
-                        .with_writer(std::io::stdout),
+                        .with_writer(console_writer(config)),
                 )
                 .with(
                     fmt::layer()
@@ -70,12 +84,14 @@ pub fn init_logging(config: &LoggingConfig) -> ScanResult<Option<WorkerGuard>> {
         } else {
             tracing_subscriber::registry()
                 .with(env_filter)
+                .with(scan_log)
+                .with(remote)
                 .with(
                     fmt::layer()
                         .with_target(true)
                         .with_thread_ids(true)
                         .with_thread_names(true)
-                        .with_writer(std::io::stdout),
+                        .with_writer(console_writer(config)),
                 )
                 .with(
                     fmt::layer()
@@ -93,22 +109,28 @@ pub fn init_logging(config: &LoggingConfig) -> ScanResult<Option<WorkerGuard>> {
         if config.format == "json" {
             tracing_subscriber::registry()
                 .with(env_filter)
+                .with(scan_log)
+                .with(remote)
                 .with(
                     fmt::layer()
                         .json()
                         .with_target(true)
                         .with_thread_ids(true)
-                        .with_thread_names(true),
+                        .with_thread_names(true)
+                        .with_writer(console_writer(config)),
                 )
                 .init();
         } else {
             tracing_subscriber::registry()
                 .with(env_filter)
+                .with(scan_log)
+                .with(remote)
                 .with(
                     fmt::layer()
                         .with_target(true)
                         .with_thread_ids(true)
-                        .with_thread_names(true),
+                        .with_thread_names(true)
+                        .with_writer(console_writer(config)),
                 )
                 .init();
         }
@@ -124,6 +146,28 @@ pub fn init_logging(config: &LoggingConfig) -> ScanResult<Option<WorkerGuard>> {
     Ok(guard)
 }
 
+/// Change the log level of the running process, e.g. on config reload
+pub fn set_level(level: &str) -> ScanResult<()> {
+    parse_log_level(level)?;
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| ScanError::scanner_error("Logging is not initialized"))?;
+    handle
+        .reload(EnvFilter::new(format!("nrmap={}", level)))
+        .map_err(|e| ScanError::scanner_error(format!("Failed to change log level: {}", e)))?;
+    info!("Log level changed to {}", level);
+    Ok(())
+}
+
+/// Console log destination: stdout, or stderr when stdout carries a report
+fn console_writer(config: &LoggingConfig) -> BoxMakeWriter {
+    if config.stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    }
+}
+
 /// Parse log level string to tracing Level
 fn parse_log_level(level: &str) -> ScanResult<Level> {
     match level.to_lowercase().as_str() {
@@ -227,7 +271,6 @@ macro_rules! track_performance {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::LoggingConfig;
 
     #[test]
     fn test_parse_log_level() {
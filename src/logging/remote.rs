@@ -0,0 +1,607 @@
+//! Remote log shipping: syslog and HTTP collectors
+//!
+//! [`RemoteLogLayer`] forwards events to an RFC 5424 syslog server (UDP, or
+//! TCP with octet-counted framing) and/or POSTs them as NDJSON batches to an
+//! HTTP collector such as Vector's `http_server` source. Each sink has a
+//! bounded queue drained by its own thread, and the layer only ever
+//! `try_send`s: a slow or unreachable collector costs the scan hot path
+//! dropped log lines, never a stall. Events over a sink's `max_per_second`
+//! are dropped the same way. Drops are counted and reported to the sink as a
+//! warning once it accepts records again.
+
+use super::scan_log::{event_span_fields, record_new_span, record_span_values, JsonVisitor, SCAN_ID_FIELD};
+use crate::config::{HttpSinkConfig, LoggingConfig, SyslogSinkConfig, SyslogTransport};
+use crate::error::{ScanError, ScanResult};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Structured-data ID of event fields in syslog messages (RFC 5612 example PEN)
+const SD_ID: &str = "nrmap@32473";
+
+/// Connect, read and write timeout of TCP and HTTP sinks
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One log event as queued for the sinks
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: Level,
+    pub target: String,
+    /// Fields of the enclosing spans, e.g. `scan_id`
+    pub span: Map<String, Value>,
+    /// Fields of the event, including `message`
+    pub fields: Map<String, Value>,
+}
+
+impl LogRecord {
+    fn dropped_notice(count: u64) -> Self {
+        let mut fields = Map::new();
+        fields.insert("message".to_string(), Value::from(format!("{} log records dropped", count)));
+        fields.insert("dropped".to_string(), Value::from(count));
+        Self {
+            timestamp: Utc::now(),
+            level: Level::WARN,
+            target: module_path!().to_string(),
+            span: Map::new(),
+            fields,
+        }
+    }
+
+    /// The event's message, empty if it has none
+    pub fn message(&self) -> &str {
+        self.fields.get("message").and_then(Value::as_str).unwrap_or("")
+    }
+
+    /// The `scan_id` of the enclosing scan, if any
+    pub fn scan_id(&self) -> Option<&str> {
+        self.span.get(SCAN_ID_FIELD).and_then(Value::as_str)
+    }
+
+    /// JSON object in the shape of the per-scan NDJSON logs
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "timestamp": self.timestamp.to_rfc3339(),
+            "level": self.level.as_str(),
+            "target": self.target,
+            "scan_id": self.scan_id(),
+            "span": self.span,
+            "fields": self.fields,
+        })
+    }
+
+    /// RFC 5424 message; span and event fields go into structured data
+    pub fn to_syslog(&self, facility: u8, hostname: &str, app_name: &str) -> String {
+        let priority = u16::from(facility) * 8 + u16::from(severity(&self.level));
+        let params: Vec<String> = self
+            .span
+            .iter()
+            .chain(self.fields.iter().filter(|(name, _)| *name != "message"))
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                format!("{}=\"{}\"", header_field(name, 32), escape_param(&value))
+            })
+            .collect();
+        let structured_data = match params.is_empty() {
+            true => "-".to_string(),
+            false => format!("[{} {}]", SD_ID, params.join(" ")),
+        };
+        format!(
+            "<{}>1 {} {} {} {} {} {} {}",
+            priority,
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+            header_field(hostname, 255),
+            header_field(app_name, 48),
+            std::process::id(),
+            header_field(&self.target, 32),
+            structured_data,
+            self.message()
+        )
+    }
+}
+
+/// Syslog severity of a tracing level
+fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+/// Header fields are printable ASCII without spaces, `-` when empty
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Host, port and path of an `http://` URL
+pub fn parse_http_url(url: &str) -> ScanResult<(String, u16, String)> {
+    let invalid = |reason: &str| ScanError::validation_error("logging.http_sink.url", format!("{}: {}", url, reason));
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("only http:// URLs are supported"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse().map_err(|_| invalid("invalid port"))?),
+        _ => (authority, 80),
+    };
+    // IPv6 literals are bracketed in URLs only
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Fixed one-second window limiting records per second (0 for no limit)
+struct RateLimit {
+    max_per_second: u32,
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RateLimit {
+    fn new(max_per_second: u32) -> Self {
+        Self {
+            max_per_second,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    fn allow(&self) -> bool {
+        if self.max_per_second == 0 {
+            return true;
+        }
+        let Ok(mut window) = self.window.lock() else {
+            return false;
+        };
+        if window.0.elapsed() >= Duration::from_secs(1) {
+            *window = (Instant::now(), 0);
+        }
+        window.1 += 1;
+        window.1 <= self.max_per_second
+    }
+}
+
+/// Queue of one sink, drained by its delivery thread
+struct SinkQueue {
+    sender: SyncSender<LogRecord>,
+    limit: RateLimit,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SinkQueue {
+    /// Start a thread that passes batches of up to `batch_size` records to
+    /// `deliver`, sending partial batches after `flush_interval`
+    fn spawn<F>(
+        name: &str,
+        queue_size: usize,
+        max_per_second: u32,
+        batch_size: usize,
+        flush_interval: Duration,
+        deliver: F,
+    ) -> ScanResult<Self>
+    where
+        F: FnMut(&[LogRecord]) -> io::Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(queue_size.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let worker_dropped = Arc::clone(&dropped);
+        std::thread::Builder::new()
+            .name(format!("nrmap-log-{}", name))
+            .spawn(move || drain(receiver, batch_size.max(1), flush_interval, worker_dropped, deliver))
+            .map_err(|e| ScanError::scanner_error(format!("Failed to start {} log sink: {}", name, e)))?;
+        Ok(Self {
+            sender,
+            limit: RateLimit::new(max_per_second),
+            dropped,
+        })
+    }
+
+    fn push(&self, record: LogRecord) {
+        if !self.limit.allow() || self.sender.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn drain<F>(receiver: Receiver<LogRecord>, batch_size: usize, flush_interval: Duration, dropped: Arc<AtomicU64>, mut deliver: F)
+where
+    F: FnMut(&[LogRecord]) -> io::Result<()>,
+{
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut deadline: Option<Instant> = None;
+    let mut reported = 0;
+    loop {
+        let received = match deadline {
+            Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let closed = match received {
+            Ok(record) => {
+                batch.push(record);
+                deadline.get_or_insert_with(|| Instant::now() + flush_interval);
+                if batch.len() < batch_size {
+                    continue;
+                }
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+
+        if !batch.is_empty() {
+            let total = dropped.load(Ordering::Relaxed);
+            if total > reported {
+                batch.push(LogRecord::dropped_notice(total - reported));
+                reported = total;
+            }
+            // Failed deliveries are counted and reported with a later batch;
+            // logging about them here would feed back into this sink
+            if deliver(&batch).is_err() {
+                dropped.fetch_add(batch.len() as u64, Ordering::Relaxed);
+            }
+            batch.clear();
+        }
+        deadline = None;
+        if closed {
+            break;
+        }
+    }
+}
+
+/// Delivery of RFC 5424 messages over UDP or TCP
+struct SyslogClient {
+    config: SyslogSinkConfig,
+    hostname: String,
+    udp: Option<UdpSocket>,
+    tcp: Option<TcpStream>,
+}
+
+impl SyslogClient {
+    fn new(config: &SyslogSinkConfig) -> ScanResult<Self> {
+        let address = config
+            .address
+            .to_socket_addrs()
+            .map_err(|e| ScanError::validation_error("logging.syslog.address", format!("{}: {}", config.address, e)))?
+            .next()
+            .ok_or_else(|| ScanError::validation_error("logging.syslog.address", "no address found"))?;
+        let udp = match config.transport {
+            SyslogTransport::Udp => {
+                let bind = if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let socket = UdpSocket::bind(bind)?;
+                socket.connect(address)?;
+                Some(socket)
+            }
+            SyslogTransport::Tcp => None,
+        };
+        Ok(Self {
+            config: config.clone(),
+            hostname: crate::report::origin::local_hostname().unwrap_or_default(),
+            udp,
+            tcp: None,
+        })
+    }
+
+    fn send(&mut self, records: &[LogRecord]) -> io::Result<()> {
+        for record in records {
+            let message = record.to_syslog(self.config.facility, &self.hostname, &self.config.app_name);
+            if let Some(ref socket) = self.udp {
+                socket.send(message.as_bytes())?;
+                continue;
+            }
+            let framed = format!("{} {}", message.len(), message);
+            if let Err(e) = self.tcp_stream()?.write_all(framed.as_bytes()) {
+                // Reconnect with the next batch
+                self.tcp = None;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn tcp_stream(&mut self) -> io::Result<&mut TcpStream> {
+        if self.tcp.is_none() {
+            let address = self
+                .config
+                .address
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no syslog address"))?;
+            let stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)?;
+            stream.set_write_timeout(Some(IO_TIMEOUT))?;
+            self.tcp = Some(stream);
+        }
+        Ok(self.tcp.as_mut().expect("connected above"))
+    }
+}
+
+/// POST a batch of records as NDJSON
+fn post_ndjson(host: &str, port: u16, path: &str, records: &[LogRecord]) -> io::Result<()> {
+    let mut body = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut body, &record.to_json())?;
+        body.push(b'\n');
+    }
+
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no collector address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        host,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!("collector replied {}", status_line.trim()))),
+    }
+}
+
+/// Tracing layer forwarding events to remote log sinks
+#[derive(Default)]
+pub struct RemoteLogLayer {
+    sinks: Vec<SinkQueue>,
+}
+
+impl RemoteLogLayer {
+    /// A layer without sinks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sinks configured in `config`, or `None` when there are none
+    pub fn from_config(config: &LoggingConfig) -> ScanResult<Option<Self>> {
+        let mut layer = Self::new();
+        if let Some(ref syslog) = config.syslog {
+            layer = layer.with_syslog(syslog)?;
+        }
+        if let Some(ref http) = config.http_sink {
+            layer = layer.with_http(http)?;
+        }
+        Ok((!layer.sinks.is_empty()).then_some(layer))
+    }
+
+    /// Add a syslog sink
+    pub fn with_syslog(mut self, config: &SyslogSinkConfig) -> ScanResult<Self> {
+        let mut client = SyslogClient::new(config)?;
+        self.sinks.push(SinkQueue::spawn(
+            "syslog",
+            config.queue_size,
+            config.max_per_second,
+            1,
+            Duration::ZERO,
+            move |records| client.send(records),
+        )?);
+        Ok(self)
+    }
+
+    /// Add an NDJSON HTTP sink
+    pub fn with_http(mut self, config: &HttpSinkConfig) -> ScanResult<Self> {
+        let (host, port, path) = parse_http_url(&config.url)?;
+        self.sinks.push(SinkQueue::spawn(
+            "http",
+            config.queue_size,
+            config.max_per_second,
+            config.batch_size,
+            Duration::from_millis(config.flush_interval_ms),
+            move |records| post_ndjson(&host, port, &path, records),
+        )?);
+        Ok(self)
+    }
+
+    /// Records dropped so far across all sinks
+    pub fn dropped(&self) -> u64 {
+        self.sinks.iter().map(|sink| sink.dropped.load(Ordering::Relaxed)).sum()
+    }
+}
+
+impl<S> Layer<S> for RemoteLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        record_new_span(attrs, id, &ctx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        record_span_values(id, values, &ctx);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let record = LogRecord {
+            timestamp: Utc::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            span: event_span_fields(event, &ctx),
+            fields: visitor.0,
+        };
+        for sink in &self.sinks {
+            sink.push(record.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn record(message: &str) -> LogRecord {
+        let mut fields = Map::new();
+        fields.insert("message".to_string(), Value::from(message));
+        fields.insert("port".to_string(), Value::from(443));
+        let mut span = Map::new();
+        span.insert(SCAN_ID_FIELD.to_string(), Value::from("scan \"1\""));
+        LogRecord {
+            timestamp: "2024-05-01T12:00:00Z".parse().unwrap(),
+            level: Level::WARN,
+            target: "nrmap::scanner".to_string(),
+            span,
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_rfc5424_format() {
+        let line = record("probe failed").to_syslog(16, "scanner-01", "nrmap");
+        assert_eq!(
+            line,
+            format!(
+                "<132>1 2024-05-01T12:00:00.000000Z scanner-01 nrmap {} nrmap::scanner \
+                 [nrmap@32473 scan_id=\"scan \\\"1\\\"\" port=\"443\"] probe failed",
+                std::process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://vector.local:8686/logs").unwrap(),
+            ("vector.local".to_string(), 8686, "/logs".to_string())
+        );
+        assert_eq!(parse_http_url("http://collector").unwrap().1, 80);
+        assert!(parse_http_url("https://collector/logs").is_err());
+        assert!(parse_http_url("http://:80/").is_err());
+    }
+
+    #[test]
+    fn test_udp_syslog_sink() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = SyslogSinkConfig {
+            address: server.local_addr().unwrap().to_string(),
+            transport: SyslogTransport::Udp,
+            facility: 1,
+            app_name: "nrmap".to_string(),
+            queue_size: 16,
+            max_per_second: 0,
+        };
+        let layer = RemoteLogLayer::new().with_syslog(&config).unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            let scan = tracing::info_span!("scan", scan_id = "scan-1");
+            let _scan = scan.enter();
+            tracing::error!("connection refused");
+        });
+
+        let mut buffer = [0u8; 2048];
+        let len = server.recv(&mut buffer).unwrap();
+        let message = std::str::from_utf8(&buffer[..len]).unwrap();
+        assert!(message.starts_with("<11>1 "), "{}", message);
+        assert!(message.contains("[nrmap@32473 scan_id=\"scan-1\"]"));
+        assert!(message.ends_with(" connection refused"));
+    }
+
+    #[test]
+    fn test_http_sink_batches_ndjson() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = HttpSinkConfig {
+            url: format!("http://{}/ingest", listener.local_addr().unwrap()),
+            batch_size: 2,
+            flush_interval_ms: 60_000,
+            queue_size: 16,
+            max_per_second: 0,
+        };
+        let layer = RemoteLogLayer::new().with_http(&config).unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info!(port = 22u16, "first");
+            tracing::info!("second");
+        });
+
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while !String::from_utf8_lossy(&request).contains("\"second\"") {
+            let n = stream.read(&mut buffer).unwrap();
+            assert!(n > 0);
+            request.extend_from_slice(&buffer[..n]);
+        }
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST /ingest HTTP/1.1\r\n"));
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let lines: Vec<Value> = body.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["fields"]["port"], 22);
+        assert_eq!(lines[1]["fields"]["message"], "second");
+    }
+
+    #[test]
+    fn test_full_queue_drops_instead_of_blocking() {
+        let (release, gate) = mpsc::channel::<()>();
+        let sink = SinkQueue::spawn("test", 1, 0, 1, Duration::ZERO, move |_| {
+            let _ = gate.recv();
+            Ok(())
+        })
+        .unwrap();
+        let started = Instant::now();
+        for i in 0..100 {
+            sink.push(record(&i.to_string()));
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        // One record is being delivered and one waits in the queue
+        assert!(sink.dropped.load(Ordering::Relaxed) >= 98);
+        drop(release);
+
+        let limit = RateLimit::new(2);
+        assert!(limit.allow() && limit.allow());
+        assert!(!limit.allow());
+    }
+}
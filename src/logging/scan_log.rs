@@ -0,0 +1,253 @@
+//! Per-scan NDJSON log files
+//!
+//! Every scan runs inside a `scan` span carrying its `scan_id` (the report's
+//! scan ID), with a `host` span per target below it, so all events of a scan
+//! (probes, errors, OS detection) share the ID in any log format. With
+//! `logging.scan_log_dir` set, [`ScanLogLayer`] also writes each scan's events
+//! to `<dir>/<scan_id>.ndjson`, one JSON object per line, for SIEM ingestion:
+//!
+//! ```text
+//! {"timestamp":"...","level":"INFO","target":"nrmap::scanner","scan_id":"...",
+//!  "span":{"scan_id":"...","target":"192.0.2.1"},"fields":{"message":"..."}}
+//! ```
+
+use crate::error::{ScanError, ScanResult};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Name of the span every scan runs in
+pub const SCAN_SPAN: &str = "scan";
+
+/// Span field holding the correlation ID
+pub const SCAN_ID_FIELD: &str = "scan_id";
+
+/// Recorded fields of a span, kept in its extensions
+struct SpanFields(Map<String, Value>);
+
+/// Collects tracing fields as JSON values
+#[derive(Default)]
+pub(super) struct JsonVisitor(pub(super) Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+    }
+}
+
+/// Keep the fields of a new span for the events below it
+///
+/// Shared by every layer that needs span fields; the first one stores them.
+pub(super) fn record_new_span<S>(attrs: &Attributes<'_>, id: &Id, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let Some(span) = ctx.span(id) else {
+        return;
+    };
+    let mut extensions = span.extensions_mut();
+    if extensions.get_mut::<SpanFields>().is_none() {
+        let mut visitor = JsonVisitor::default();
+        attrs.record(&mut visitor);
+        extensions.insert(SpanFields(visitor.0));
+    }
+}
+
+/// Merge fields recorded on a span after it was created
+pub(super) fn record_span_values<S>(id: &Id, values: &Record<'_>, ctx: &Context<'_, S>)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let Some(span) = ctx.span(id) else {
+        return;
+    };
+    let mut extensions = span.extensions_mut();
+    if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+        let mut visitor = JsonVisitor::default();
+        values.record(&mut visitor);
+        fields.extend(visitor.0);
+    }
+}
+
+/// Fields of every span around `event`, inner spans overriding outer ones
+pub(super) fn event_span_fields<S>(event: &Event<'_>, ctx: &Context<'_, S>) -> Map<String, Value>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut span_fields = Map::new();
+    if let Some(scope) = ctx.event_scope(event) {
+        for span in scope.from_root() {
+            if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                span_fields.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+    }
+    span_fields
+}
+
+/// Writes the events of each scan to its own NDJSON file
+pub struct ScanLogLayer {
+    dir: PathBuf,
+    files: Mutex<HashMap<String, BufWriter<File>>>,
+}
+
+impl ScanLogLayer {
+    /// Write scan logs into `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> ScanResult<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to create scan log directory {}: {}", dir.display(), e))
+        })?;
+        Ok(Self {
+            dir,
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Path of the log file for `scan_id`
+    pub fn path(&self, scan_id: &str) -> PathBuf {
+        // Scan IDs are UUIDs, but keep caller-chosen ones inside the directory
+        let name: String = scan_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.ndjson", name))
+    }
+
+    fn write(&self, scan_id: &str, line: &Value) {
+        let Ok(mut files) = self.files.lock() else {
+            return;
+        };
+        if !files.contains_key(scan_id) {
+            let Ok(file) = OpenOptions::new().create(true).append(true).open(self.path(scan_id)) else {
+                return;
+            };
+            files.insert(scan_id.to_string(), BufWriter::new(file));
+        }
+        if let Some(writer) = files.get_mut(scan_id) {
+            // Logging must not fail the scan; a full disk loses log lines only
+            let _ = serde_json::to_writer(&mut *writer, line);
+            let _ = writer.write_all(b"\n");
+        }
+    }
+
+    /// Flush and close the file of `scan_id`
+    fn close(&self, scan_id: &str) {
+        if let Ok(mut files) = self.files.lock() {
+            if let Some(mut writer) = files.remove(scan_id) {
+                let _ = writer.flush();
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for ScanLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        record_new_span(attrs, id, &ctx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        record_span_values(id, values, &ctx);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let span_fields = event_span_fields(event, &ctx);
+        let Some(scan_id) = span_fields.get(SCAN_ID_FIELD).and_then(Value::as_str).map(String::from) else {
+            return;
+        };
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let line = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "scan_id": scan_id,
+            "span": span_fields,
+            "fields": visitor.0,
+        });
+        self.write(&scan_id, &line);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if span.name() != SCAN_SPAN {
+            return;
+        }
+        let scan_id = span
+            .extensions()
+            .get::<SpanFields>()
+            .and_then(|SpanFields(fields)| fields.get(SCAN_ID_FIELD)?.as_str().map(String::from));
+        if let Some(scan_id) = scan_id {
+            self.close(&scan_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_events_go_to_their_scan_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let layer = ScanLogLayer::new(dir.path()).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("outside any scan");
+            let scan = tracing::info_span!(SCAN_SPAN, scan_id = "scan-1");
+            let _scan = scan.enter();
+            let host = tracing::info_span!("host", target = "192.0.2.1");
+            let _host = host.enter();
+            tracing::warn!(port = 443u16, "probe failed");
+        });
+
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(dir.path().join("scan-1.ndjson")).unwrap();
+        let lines: Vec<Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["scan_id"], "scan-1");
+        assert_eq!(lines[0]["level"], "WARN");
+        assert_eq!(lines[0]["span"]["target"], "192.0.2.1");
+        assert_eq!(lines[0]["fields"]["port"], 443);
+        assert_eq!(lines[0]["fields"]["message"], "probe failed");
+    }
+}
@@ -3,10 +3,39 @@
 /// Main entry point for the command-line interface
 
 use clap::{Parser, Subcommand};
-use nrmap::{init_library, parse_port_preset, parse_port_range, ScanType};
+use nrmap::cli::{
+    confirm_scope, ExitCodePolicy, ExitOutcome, ProfileRegistry, ProgressBarDisplay, RunSummary, ScanProfile, SummaryLine,
+};
+use nrmap::enrichment::TlsHostnameCollector;
+use nrmap::policy::{ComplianceReport, Policy, PolicyEvaluator};
+use nrmap::report::junit::unexpected_open_ports;
+use nrmap::report::merge::load_report;
+use nrmap::report::{
+    AssetHistory, NmapRun, OutputSink, RedactScope, Redactor, ReportEngine, ResultSpool, ScanDiff, ScanParameters,
+    ReportFormat, SinkList, TemplateReportGenerator,
+};
+use nrmap::scanner::discovery_cache::DiscoveryCache;
+use nrmap::scanner::dns_discovery::{self, DnsEnumerator};
+use nrmap::scanner::lan_discovery::{LanDiscovery, LanProtocol};
+use nrmap::scanner::os_detection::OsDetectionQueue;
+use nrmap::scanner::targets::{parse_target_list, ScanScope};
+use nrmap::scanner::Confidence;
+use nrmap::schedule::{RecurringScans, ScanPlan, ScheduleFile};
+use nrmap::monitor::Monitor;
+use nrmap::inventory::{filter_by_tags, target_addresses, SourceSpec};
+use nrmap::notify::{Notifier, NotifyConfig};
+use nrmap::report::time::parse_duration;
+use nrmap::{init_library_with_config, parse_port_preset, parse_port_range, AppConfig, ConfigLayers, ReportBuilder, ScanType};
+use std::collections::BTreeSet;
+use std::io::IsTerminal;
 use std::net::IpAddr;
+use std::path::PathBuf;
 use std::process;
-use tracing::{error, info};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+/// Project configuration file used without --config
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
 
 #[derive(Parser)]
 #[command(name = "nrmap")]
@@ -16,13 +45,252 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.toml")]
-    config: String,
+    /// Path to the project configuration file (default: ./config.toml),
+    /// layered over the system and user config files
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// Override a configuration key, e.g. `--set scanner.max_concurrent_scans=200`;
+    /// repeatable, and applied over files and NRMAP_* environment variables
+    #[arg(long, global = true, value_name = "KEY=VALUE")]
+    set: Vec<String>,
 
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Print a single-line machine-parsable summary to stderr when done
+    #[arg(long, global = true)]
+    summary_line: bool,
+
+    /// Keep stdout free of human-readable text: no result listing, and log
+    /// messages go to stderr; --output reports are still written (scan,
+    /// scan-file, import and report merge)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print one JSON object with the run's outcome (counts, duration,
+    /// report files) to stdout when done, and nothing else, also when the
+    /// run fails; implies --quiet (scan, scan-file, import and report merge)
+    #[arg(long, global = true)]
+    summary_json: bool,
+
+    /// Exit codes per outcome over the defaults success=0, error=1,
+    /// policy=2 (--policy or --allowed-ports violated), unreachable=3
+    /// (targets down or without a result) and findings=4 (open ports),
+    /// e.g. `findings=0,unreachable=0`
+    #[arg(long, global = true, value_name = "OUTCOME=CODE,...")]
+    exit_code_policy: Option<ExitCodePolicy>,
+
+    /// Record scan traffic to a pcapng file (one file per scanned host)
+    #[arg(long, global = true, value_name = "FILE")]
+    pcap: Option<String>,
+
+    /// Write the report as FORMAT to PATH ("-" for stdout, also used when
+    /// PATH is left out), or to a PATH whose extension names the format;
+    /// repeatable, e.g. `--output json=scan.json -o table -o scan.html`
+    #[arg(short = 'o', long, global = true, value_name = "FORMAT=PATH")]
+    output: Vec<String>,
+
+    /// Write one JSON line per host result to PATH ("-" for stdout); the
+    /// same as `--output jsonl=PATH`
+    #[arg(long, global = true, value_name = "PATH")]
+    output_jsonl: Option<String>,
+
+    /// Write open ports in masscan's `-oL` list format to PATH ("-" for
+    /// stdout); the same as `--output list=PATH`
+    #[arg(long, global = true, value_name = "PATH")]
+    output_list: Option<String>,
+
+    /// Render the report through this Handlebars template, to stdout unless
+    /// a `--output template=PATH` names a file
+    #[arg(long, global = true, value_name = "FILE")]
+    template: Option<PathBuf>,
+
+    /// Share-safe --output reports: pseudonymize IPs and hostnames and drop
+    /// banners (comma-separated ips, hostnames, banners, or all)
+    #[arg(long, global = true, value_name = "WHAT")]
+    redact: Option<RedactScope>,
+
+    /// Secret key for --redact pseudonyms, created when missing; the same
+    /// key gives the same pseudonyms in every report (default: a new key
+    /// per run)
+    #[arg(long, global = true, value_name = "FILE", requires = "redact")]
+    redact_key: Option<PathBuf>,
+
+    /// Keep the mapping from --redact pseudonyms back to the originals in
+    /// this JSON file, for those allowed to see them
+    #[arg(long, global = true, value_name = "FILE", requires = "redact")]
+    redact_map: Option<PathBuf>,
+
+    /// Fingerprint the OS of every scanned host with an open port
+    #[arg(long, global = true)]
+    os_detect: bool,
+
+    /// Split crafted probe packets into 8-byte IP fragments
+    #[arg(long, global = true)]
+    fragment: bool,
+
+    /// Fragment crafted probe packets to this many payload bytes (multiple
+    /// of 8); implies --fragment
+    #[arg(long, global = true, value_name = "BYTES")]
+    mtu: Option<usize>,
+
+    /// Send probes with this IP TTL / IPv6 hop limit
+    #[arg(long, global = true, value_name = "TTL", value_parser = clap::value_parser!(u8).range(1..))]
+    ttl: Option<u8>,
+
+    /// Send crafted probes with invalid TCP/UDP checksums; answers come from
+    /// middleboxes, since end hosts drop them
+    #[arg(long, global = true)]
+    badsum: bool,
+
+    /// Re-check open ports with the other TCP scan type and flag
+    /// disagreements (e.g. fake SYN-ACKs from deception tools)
+    #[arg(long, global = true)]
+    verify_open: bool,
+
+    /// Check that open HTTP, SMTP, FTP and RDP services answer in their
+    /// protocol, recording latency and protocol errors
+    #[arg(long, global = true)]
+    deep_check: bool,
+
+    /// Calibrate probe timeouts and retries per host from measured RTTs
+    #[arg(long, global = true)]
+    adaptive_timeout: bool,
+
+    /// Never send more than this many probes per second across the whole run
+    #[arg(long, global = true, value_name = "PPS")]
+    max_rate: Option<u32>,
+
+    /// Keep the adaptive throttle from backing off below this many probes
+    /// per second
+    #[arg(long, global = true, value_name = "PPS")]
+    min_rate: Option<u32>,
+
+    /// Send probes at this many per second across the whole run, like
+    /// masscan's `--rate`: the same as equal `--min-rate` and `--max-rate`
+    #[arg(long, global = true, value_name = "PPS", conflicts_with_all = ["min_rate", "max_rate"])]
+    rate: Option<u32>,
+
+    /// Scan this many targets at the same time
+    #[arg(long, global = true, value_name = "N")]
+    max_concurrent_targets: Option<usize>,
+
+    /// Keep at most this many port probes in flight across all targets
+    #[arg(long, global = true, value_name = "N")]
+    max_concurrent_probes: Option<usize>,
+
+    /// What to do when the concurrency needs more sockets than the open
+    /// file limit (`ulimit -n`) allows: clamp it (default), warn only, or
+    /// off to skip the check
+    #[arg(long, global = true, value_name = "MODE")]
+    ulimit_check: Option<nrmap::scanner::UlimitCheck>,
+
+    /// Fail with a non-zero exit instead of degrading: no fallback to the
+    /// default config, and host discovery or scan type failures abort the scan
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Probe ports that came back filtered a second time to weed out
+    /// dropped packets
+    #[arg(long, global = true)]
+    reprobe_filtered: bool,
+
+    /// Probe each host's ports in random order
+    #[arg(long, global = true)]
+    randomize_ports: bool,
+
+    /// Scan targets in random order
+    #[arg(long, global = true)]
+    randomize_hosts: bool,
+
+    /// Seed for the randomized order, to reproduce a previous run; implies
+    /// --randomize-ports and --randomize-hosts unless one is given
+    #[arg(long, global = true, value_name = "SEED")]
+    randomize_seed: Option<u64>,
+
+    /// Do not draw a progress bar on stderr while scanning
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// Remember responsive hosts and open ports across runs in this JSON file
+    #[arg(long, global = true, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Report only hosts and ports the --history file has never seen
+    #[arg(long, global = true, requires = "history")]
+    only_new: bool,
+
+    /// Probe and fingerprint every host afresh instead of reusing cached
+    /// down hosts and OS detections
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Report hosts found down within the last MINUTES as down without
+    /// probing them again (cached next to --history when given)
+    #[arg(long, global = true, value_name = "MINUTES", conflicts_with = "no_cache")]
+    skip_down_for: Option<u64>,
+
+    /// Expect only these ports open on every host (e.g. "22,443"); other
+    /// open ports fail JUnit test cases and the run
+    #[arg(long, global = true, value_name = "PORTS")]
+    allowed_ports: Option<String>,
+
+    /// Check the results against the rules in this policy file (TOML or
+    /// YAML); any violated rule fails the run
+    #[arg(long, global = true, value_name = "FILE")]
+    policy: Option<PathBuf>,
+
+    /// Ignore open ports found with less confidence (0-100) in policy
+    /// checks and comparisons with earlier results; policy rules that set
+    /// their own `min_confidence` keep it
+    #[arg(long, global = true, value_name = "SCORE")]
+    min_confidence: Option<Confidence>,
+
+    /// Keep only open ports in the results; closed, filtered and failed
+    /// ports are reduced to per-host counts
+    #[arg(long, global = true)]
+    open_only: bool,
+
+    /// After the requested ports (e.g. --top-ports 100), scan the rest of
+    /// 1-65535 on hosts found up or with an open port
+    #[arg(long, global = true)]
+    expand_ports: bool,
+
+    /// Record why each port got its state (syn-ack, reset, conn-refused,
+    /// ICMP unreachable code, no-response after N retries) and list every
+    /// port with its reason in the table and HTML reports
+    #[arg(long, global = true)]
+    reason: bool,
+
+    /// Masscan-style SYN scanning (with -t syn): one probe per port,
+    /// replies validated by SipHash SYN cookies, no retries or timings
+    #[arg(long, global = true)]
+    stateless: bool,
+
+    /// Make TCP connects from this SSH bastion ([USER@]HOST[:PORT]) to reach
+    /// segments only it can route to
+    #[arg(long, global = true, value_name = "BASTION")]
+    ssh_bastion: Option<String>,
+
+    /// Private key for --ssh-bastion (default: the SSH agent)
+    #[arg(long, global = true, value_name = "FILE", requires = "ssh_bastion")]
+    ssh_identity: Option<PathBuf>,
+
+    /// Spill scan-file results to disk in this directory and stream the
+    /// report from there instead of holding every result in memory
+    #[arg(long, global = true, value_name = "DIR")]
+    spool_dir: Option<PathBuf>,
+
+    /// Scan large target sets without asking for confirmation
+    #[arg(short = 'y', long, global = true)]
+    yes: bool,
+
+    /// Scan targets outside the authorized scope (`security.scope`); the
+    /// override is recorded in the audit log
+    #[arg(long = "i-know-what-im-doing", global = true)]
+    override_scope: bool,
 }
 
 #[derive(Subcommand)]
@@ -30,31 +298,113 @@ enum Commands {
     /// Scan a target host
     Scan {
         /// Target IP address
-        #[arg(short, long)]
-        target: String,
+        #[arg(short, long, required_unless_present = "source", conflicts_with = "source")]
+        target: Option<String>,
 
-        /// Ports to scan (e.g., "80,443" or "1-1000")
+        /// Scan the running instances of a cloud account instead of one
+        /// target: aws[:PROFILE][@REGION], gcp:PROJECT or azure:SUBSCRIPTION
+        #[arg(long, value_name = "SOURCE")]
+        source: Option<String>,
+
+        /// Keep only cloud assets with this tag (KEY or KEY=VALUE, repeatable)
+        #[arg(long, value_name = "TAG", requires = "source")]
+        source_tag: Vec<String>,
+
+        /// Ports to scan (e.g., "80,443", "1-1000" or "ssh,http,https")
         #[arg(short, long)]
         ports: Option<String>,
 
-        /// Port preset (common, web, mail, database, all)
+        /// Port preset (common, top1000, web, mail, database, all)
         #[arg(long)]
         preset: Option<String>,
 
-        /// Scan type: tcp, syn, udp (can specify multiple)
-        #[arg(short = 't', long, default_value = "tcp")]
+        /// Scan the N most frequently open ports (TCP, UDP or both, following
+        /// the scan types)
+        #[arg(long, value_name = "N", conflicts_with_all = ["ports", "preset"])]
+        top_ports: Option<usize>,
+
+        /// Scan type: tcp, syn, udp (can specify multiple; default tcp)
+        #[arg(short = 't', long)]
         scan_type: Vec<String>,
 
-        /// Maximum concurrent scans
+        /// Ports probed at the same time
         #[arg(short, long)]
         concurrency: Option<usize>,
+
+        /// Built-in or user-defined scan profile; explicit options override it
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
     },
 
     /// Scan multiple targets from a file
     ScanFile {
-        /// File containing target IP addresses (one per line)
+        /// File of targets, one address, CIDR block or JSON record per line
+        /// ("-" for stdin)
+        #[arg(short, long, required_unless_present = "rescan_open_from")]
+        file: Option<String>,
+
+        /// Re-verify the open TCP ports of an Nmap XML file (`nmap -oX`)
+        /// instead, and log what changed since
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["file", "ports", "preset", "top_ports"]
+        )]
+        rescan_open_from: Option<PathBuf>,
+
+        /// Ports to scan
         #[arg(short, long)]
-        file: String,
+        ports: Option<String>,
+
+        /// Port preset
+        #[arg(long)]
+        preset: Option<String>,
+
+        /// Scan the N most frequently open ports (TCP, UDP or both, following
+        /// the scan types)
+        #[arg(long, value_name = "N", conflicts_with_all = ["ports", "preset"])]
+        top_ports: Option<usize>,
+
+        /// Scan type (default tcp)
+        #[arg(short = 't', long)]
+        scan_type: Vec<String>,
+
+        /// Built-in or user-defined scan profile; explicit options override it
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+
+    /// Import an Nmap XML file: record it in --history and render it with
+    /// --output (printed when no --output is given)
+    Import {
+        /// Nmap XML output (`nmap -oX`)
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+
+    /// Work with reports written by earlier runs
+    Report {
+        #[command(subcommand)]
+        action: ReportCommand,
+    },
+
+    /// Run the scans saved in a schedule file whenever they come due,
+    /// notifying about changes since each scan's previous run
+    Schedule {
+        /// Schedule file (TOML, or YAML by extension)
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Run every scan once now and exit instead of waiting for the schedule
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Re-scan targets at an interval and report changes that persist
+    Monitor {
+        /// File containing target IP addresses (one per line)
+        #[arg(short = 'f', long)]
+        targets: String,
 
         /// Ports to scan
         #[arg(short, long)]
@@ -64,105 +414,832 @@ enum Commands {
         #[arg(long)]
         preset: Option<String>,
 
-        /// Scan type
-        #[arg(short = 't', long, default_value = "tcp")]
+        /// Scan the N most frequently open ports (TCP, UDP or both, following
+        /// the scan types)
+        #[arg(long, value_name = "N", conflicts_with_all = ["ports", "preset"])]
+        top_ports: Option<usize>,
+
+        /// Scan type (default tcp)
+        #[arg(short = 't', long)]
         scan_type: Vec<String>,
+
+        /// Time between scan rounds (e.g. "90s", "10m", "1h")
+        #[arg(long, default_value = "10m")]
+        interval: String,
+
+        /// Rounds in a row a change must be seen before it is reported
+        #[arg(long, value_name = "ROUNDS", default_value_t = 2)]
+        confirm: u32,
+
+        /// POST every change as JSON to this URL (repeatable)
+        #[arg(long, value_name = "URL")]
+        webhook: Vec<String>,
+
+        /// Run this shell command for every change, with the JSON on stdin
+        #[arg(long, value_name = "CMD")]
+        notify_command: Option<String>,
+
+        /// Built-in or user-defined scan profile; explicit options override it
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+
+    /// Enumerate targets through DNS and print them as a target list
+    /// (JSON lines that `scan-file -f -` reads)
+    DnsEnum {
+        /// Resolve the PTR record of every address in this block (repeatable)
+        #[arg(long, value_name = "CIDR", required_unless_present_any = ["axfr", "brute"])]
+        ptr: Vec<String>,
+
+        /// Attempt a zone transfer of ZONE from NAMESERVER[:PORT] (repeatable)
+        #[arg(long, value_name = "ZONE@NAMESERVER")]
+        axfr: Vec<String>,
+
+        /// Resolve WORD.DOMAIN for every word of --wordlist (repeatable)
+        #[arg(long, value_name = "DOMAIN")]
+        brute: Vec<String>,
+
+        /// Words for --brute, one per line (default: a built-in list of
+        /// common host names)
+        #[arg(long, value_name = "FILE", requires = "brute")]
+        wordlist: Option<PathBuf>,
+
+        /// Lookups in flight at once
+        #[arg(long, default_value_t = 32)]
+        concurrency: usize,
+    },
+
+    /// Discover devices on the local network over mDNS, SSDP and
+    /// WS-Discovery and print them as a target list (JSON lines that
+    /// `scan-file -f -` reads)
+    LanDiscover {
+        /// Protocol to search with: mdns, ssdp or wsd (repeatable; default all)
+        #[arg(long, value_name = "PROTOCOL")]
+        protocol: Vec<String>,
+
+        /// Seconds to collect answers for
+        #[arg(long, value_name = "SECONDS", default_value_t = 3)]
+        duration: u64,
+
+        /// Also join the multicast groups and take in unsolicited
+        /// announcements
+        #[arg(long)]
+        listen: bool,
+
+        /// IPv4 address of the interface to search from
+        #[arg(long, value_name = "ADDR")]
+        interface: Option<std::net::Ipv4Addr>,
+    },
+
+    /// Wake sleeping hosts with Wake-on-LAN magic packets, e.g. before a
+    /// scan scheduled for their maintenance window
+    Wol {
+        /// MAC address to wake (aa:bb:cc:dd:ee:ff, aa-bb-..., aabb.ccdd.eeff)
+        #[arg(required = true, value_name = "MAC")]
+        mac: Vec<String>,
+
+        /// Broadcast address to send to; a directed broadcast such as
+        /// 10.1.2.255 reaches a routed subnet
+        #[arg(long, value_name = "ADDR", default_value = "255.255.255.255")]
+        broadcast: std::net::IpAddr,
+
+        /// UDP port (9, or 7 for some NICs)
+        #[arg(long, default_value_t = nrmap::lan::WOL_PORT)]
+        port: u16,
+
+        /// Packets sent per MAC
+        #[arg(long, default_value_t = 3)]
+        count: u32,
+
+        /// Seconds to wait afterwards for the hosts to boot
+        #[arg(long, value_name = "SECONDS", default_value_t = 0)]
+        wait: u64,
+    },
+
+    /// Discover IPv6 hosts in a prefix (requires host_discovery.ipv6 enabled)
+    Discover6 {
+        /// IPv6 prefix (e.g., "2001:db8::/64")
+        #[arg(short, long)]
+        prefix: String,
+    },
+
+    /// Passively fingerprint hosts from live traffic (requires root)
+    Passive {
+        /// Interface to listen on (e.g., "eth0")
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Seconds between fingerprint reports
+        #[arg(long, default_value = "30")]
+        interval: u64,
+    },
+
+    /// List and inspect scan profiles
+    Profiles {
+        #[command(subcommand)]
+        action: ProfilesCommand,
+    },
+
+    /// List port presets
+    Presets {
+        #[command(subcommand)]
+        action: PresetsCommand,
+    },
+
+    /// Manage custom OS signatures in the user database
+    OsDb {
+        #[command(subcommand)]
+        action: OsDbCommand,
+    },
+
+    /// Inspect the layered configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Check what this build and platform support
+    Doctor {
+        /// Print the support matrix as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show version information
     Version,
 }
 
+impl Commands {
+    /// Whether the command reports scan results through `--output`, and so
+    /// has a `--quiet` and `--summary-json` form
+    fn reports_scans(&self) -> bool {
+        matches!(
+            self,
+            Commands::Scan { .. } | Commands::ScanFile { .. } | Commands::Import { .. } | Commands::Report { .. }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum ReportCommand {
+    /// Merge JSON or YAML reports of several runs into one, rendered with
+    /// --output: hosts are combined, and a port scanned more than once keeps
+    /// the more confident or, when equal, the more recent result
+    Merge {
+        /// Reports written with `--output json=...` or `yaml=...`
+        #[arg(value_name = "REPORT", required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// List configuration sources in precedence order
+    Show {
+        /// Also print the merged configuration as TOML
+        #[arg(long)]
+        effective: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetsCommand {
+    /// List built-in and organization presets
+    List,
+}
+
+#[derive(Subcommand)]
+enum OsDbCommand {
+    /// Add signatures from a JSON/YAML database or signature file, or an
+    /// nmap-os-db file
+    Add {
+        /// File to read signatures from
+        file: PathBuf,
+
+        /// Replace user signatures of the same name
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Remove a user signature
+    Remove {
+        /// Signature (OS) name
+        name: String,
+    },
+
+    /// List signatures with their source
+    List {
+        /// Only list user signatures
+        #[arg(long)]
+        user: bool,
+    },
+
+    /// List fingerprints recorded in learning mode
+    Unknown,
+
+    /// Turn a recorded unknown fingerprint into a user signature
+    Label {
+        /// Fingerprint ID (or a unique prefix) from `os-db unknown`
+        id: String,
+
+        /// Signature (OS) name
+        #[arg(long)]
+        name: String,
+
+        /// OS family (linux, windows, macos, bsd, unix, cisco, embedded)
+        #[arg(long, default_value = "unknown")]
+        family: String,
+
+        /// OS version
+        #[arg(long)]
+        os_version: Option<String>,
+
+        /// Replace a user signature of the same name
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Export the merged (or only the user) database as JSON or YAML
+    Export {
+        /// Output file; .yaml/.yml writes YAML, anything else JSON
+        path: PathBuf,
+
+        /// Only export user signatures
+        #[arg(long)]
+        user: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfilesCommand {
+    /// List built-in and user-defined profiles
+    List,
+
+    /// Show the scan parameters a profile expands to
+    Show {
+        /// Profile name
+        name: String,
+    },
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    let run_end = RunEnd {
+        summary_json: cli.summary_json,
+        exit_codes: cli.exit_code_policy.unwrap_or_default(),
+        started: std::time::Instant::now(),
+    };
+    // Commands that print their own results have no quiet form
+    if (cli.quiet || cli.summary_json) && !cli.command.reports_scans() {
+        run_end.fail("--quiet and --summary-json apply to scan, scan-file, import and report merge".to_string());
+    }
+
+    // Merge system, user and project config, NRMAP_* variables and --set
+    let config_path = cli.config.as_deref().unwrap_or(DEFAULT_CONFIG_PATH);
+    let mut overrides = cli.set.clone();
+    if cli.no_cache {
+        overrides.push("scanner.os_detection.cache.enabled=false".to_string());
+        overrides.push("scanner.host_discovery.cache.enabled=false".to_string());
+    }
+    if let Some(minutes) = cli.skip_down_for {
+        overrides.push("scanner.host_discovery.cache.enabled=true".to_string());
+        overrides.push(format!("scanner.host_discovery.cache.ttl_secs={}", minutes.saturating_mul(60)));
+    }
+    let cli_outputs: Vec<String> = cli
+        .output
+        .iter()
+        .map(|spec| match spec.parse::<ReportFormat>() {
+            // A bare format goes to stdout; a bare path names its format
+            Ok(_) if !spec.contains('=') => format!("{}=-", spec),
+            _ => spec.clone(),
+        })
+        .chain(cli.output_jsonl.iter().map(|path| format!("jsonl={}", path)))
+        .chain(cli.output_list.iter().map(|path| format!("list={}", path)))
+        .collect();
+    // Keep stdout clean for a report piped into another tool
+    let template_to_stdout = cli.template.is_some() && !cli_outputs.iter().any(|spec| spec.starts_with("template="));
+    if matches!(cli.command, Commands::DnsEnum { .. } | Commands::LanDiscover { .. }) {
+        overrides.push("logging.stderr=true".to_string());
+    }
+    if template_to_stdout || cli_outputs.iter().any(|spec| spec.trim_end().ends_with("=-")) {
+        overrides.push("logging.stderr=true".to_string());
+    }
+    if cli.quiet || cli.summary_json {
+        overrides.push("logging.stderr=true".to_string());
+    }
+    let layers = match ConfigLayers::standard(config_path, cli.config.is_some())
+        .with_environment(std::env::vars())
+        .with_overrides(&overrides)
+    {
+        Ok(layers) => layers,
+        Err(e) => {
+            run_end.fail(format!("Invalid --set: {}", e));
+        }
+    };
+
+    if let Commands::Config { action } = cli.command {
+        if let Err(e) = handle_config(&layers, action) {
+            run_end.fail(format!("Error: {}", e));
+        }
+        return;
+    }
 
     // Initialize library
-    let (scanner, _guard) = match init_library(Some(&cli.config)).await {
+    let (config, (scanner, _guard)) = match load_and_init(&layers).await {
         Ok(result) => result,
+        Err(e) if cli.strict => {
+            run_end.fail(format!("Failed to initialize: {}", e));
+        }
         Err(e) => {
             eprintln!("Failed to initialize: {}", e);
-            eprintln!("Using default configuration...");
-            
-            match init_library::<&str>(None).await {
-                Ok(result) => result,
+            // Scope and blackout windows must hold even on defaults
+            let security = match layers.load_security() {
+                Ok(security) => security,
                 Err(e) => {
-                    eprintln!("Fatal error: {}", e);
-                    process::exit(1);
+                    run_end.fail(format!("Invalid [security] configuration: {}", e));
+                }
+            };
+            eprintln!("Using default configuration with the configured [security] section...");
+
+            let config = AppConfig { security, ..AppConfig::default() };
+            match init_library_with_config(config.clone()).await {
+                Ok(result) => (config, result),
+                Err(e) => {
+                    run_end.fail(format!("Fatal error: {}", e));
                 }
             }
         }
     };
 
-    // Execute command
-    let result = match cli.command {
-        Commands::Scan {
-            target,
-            ports,
-            preset,
-            scan_type,
-            concurrency,
-        } => {
-            handle_scan(scanner, target, ports, preset, scan_type, concurrency).await
+    let profile = match cli.command {
+        Commands::Scan { ref profile, .. }
+        | Commands::ScanFile { ref profile, .. }
+        | Commands::Monitor { ref profile, .. } => profile.as_deref(),
+        _ => None,
+    };
+    let profile = match profile.map(|name| load_profiles(&config, config_path)?.get(name)).transpose() {
+        Ok(profile) => profile,
+        Err(e) => {
+            run_end.fail(format!("Invalid --profile: {}", e));
         }
-        Commands::ScanFile {
-            file,
-            ports,
-            preset,
-            scan_type,
-        } => {
-            handle_scan_file(scanner, file, ports, preset, scan_type).await
+    };
+
+    // A profile's output format applies only when no --output is given
+    let mut output_specs = match profile.as_ref().and_then(|p| p.output_format) {
+        Some(format) if cli_outputs.is_empty() && cli.template.is_none() => vec![format!("{}=-", format)],
+        _ => cli_outputs,
+    };
+    let template = match cli.template.as_ref().map(TemplateReportGenerator::from_file).transpose() {
+        Ok(template) => template,
+        Err(e) => {
+            run_end.fail(format!("Invalid --template: {}", e));
         }
-        Commands::Version => {
-            handle_version();
-            Ok(())
+    };
+    if template.is_some() && !output_specs.iter().any(|spec| spec.starts_with("template=")) {
+        output_specs.push("template=-".to_string());
+    }
+    let allowed_ports = match cli.allowed_ports.as_deref().map(parse_port_range).transpose() {
+        Ok(ports) => ports,
+        Err(e) => {
+            run_end.fail(format!("Invalid --allowed-ports: {}", e));
         }
     };
-
-    if let Err(e) = result {
-        error!("Error: {}", e);
-        process::exit(1);
+    let policy = match cli.policy.as_ref().map(Policy::from_file).transpose() {
+        Ok(Some(policy)) if cli.min_confidence.is_some() => Some(Policy {
+            min_confidence: cli.min_confidence,
+            ..policy
+        }),
+        Ok(policy) => policy,
+        Err(e) => {
+            run_end.fail(format!("Invalid --policy: {}", e));
+        }
+    };
+    if cli.redact.is_some() && output_specs.is_empty() {
+        run_end.fail("--redact applies to --output reports; name at least one".to_string());
     }
-}
+    let redactor = match cli.redact.map(|scope| redactor(scope, cli.redact_key.as_deref(), cli.redact_map.clone())).transpose() {
+        Ok(redactor) => redactor,
+        Err(e) => {
+            run_end.fail(format!("Invalid --redact: {}", e));
+        }
+    };
+    let sinks = SinkList::from_specs_with_template(&output_specs, template.as_ref())
+        .map(|sinks| match redactor {
+            Some(redactor) => sinks.with_redactor(redactor),
+            None => sinks,
+        });
+    let sinks = sinks.and_then(|sinks| match cli.summary_json && sinks.writes_stdout() {
+        true => Err(nrmap::ScanError::validation_error(
+            "output",
+            "--summary-json keeps stdout for the summary; write reports to files",
+        )),
+        false => Ok(sinks),
+    });
+    let output = match sinks {
+        Ok(sinks) => ScanOutput {
+            summary_line: cli.summary_line,
+            quiet: cli.quiet || cli.summary_json,
+            summary: Arc::default(),
+            progress_bar: !cli.no_progress && std::io::stderr().is_terminal(),
+            history: cli.history.clone(),
+            only_new: cli.only_new,
+            sinks,
+            spool_dir: cli.spool_dir.clone().or_else(|| config.output.spool_dir.clone().map(PathBuf::from)),
+            spool_segment_results: config.output.spool_segment_results,
+            allowed_ports,
+            policy,
+            compare_with: None,
+            min_confidence: cli.min_confidence,
+        },
+        Err(e) => {
+            run_end.fail(format!("Invalid --output: {}", e));
+        }
+    };
 
-async fn handle_scan(
-    scanner: nrmap::Scanner,
-    target: String,
-    ports_str: Option<String>,
-    preset: Option<String>,
-    scan_types: Vec<String>,
-    _concurrency: Option<usize>,
-) -> nrmap::ScanResult<()> {
-    // Parse target IP
-    let target_ip: IpAddr = target
-        .parse()
-        .map_err(|_| nrmap::ScanError::invalid_target(target, "Invalid IP address"))?;
+    let scanner = match cli.pcap {
+        Some(ref path) => scanner.with_pcap_output(path),
+        None => scanner,
+    };
 
-    // Parse ports
-    let ports = if let Some(preset) = preset {
-        parse_port_preset(&preset)?
-    } else if let Some(ports_str) = ports_str {
-        parse_port_range(&ports_str)?
+    let scanner = match profile {
+        Some(ref profile) => scanner
+            .with_probe_timeout(profile.options.timeout_ms)
+            .with_max_concurrent(profile.options.max_concurrent)
+            .with_banner_grabbing(profile.options.enable_banner_grabbing),
+        None => scanner,
+    };
+
+    // tls_expiry rules check certificates, so collect them even when the
+    // configuration does not
+    let needs_certificates = output.policy.as_ref().is_some_and(Policy::needs_certificates);
+    let scanner = if needs_certificates && !config.enrichment.tls_hostnames_enabled {
+        scanner.with_tls_hostnames(TlsHostnameCollector::new(
+            config.enrichment.tls_ports.clone(),
+            config.enrichment.tls_timeout_ms,
+        ))
     } else {
-        // Default to common ports
-        parse_port_preset("common")?
+        scanner
     };
 
-    // Parse scan types
-    let scan_types: Vec<ScanType> = scan_types
-        .iter()
-        .map(|s| match s.to_lowercase().as_str() {
-            "tcp" | "connect" => Ok(ScanType::TcpConnect),
-            "syn" => Ok(ScanType::TcpSyn),
-            "udp" => Ok(ScanType::Udp),
-            _ => Err(nrmap::ScanError::validation_error(
-                "scan_type",
-                format!("Unknown scan type: {}", s),
-            )),
+    let profile_os_detect = profile.as_ref().is_some_and(|p| p.options.enable_os_detection);
+    let scanner = if cli.os_detect || profile_os_detect {
+        let queue = OsDetectionQueue::from_config(&scanner.config().os_detection);
+        scanner.with_os_detection(queue)
+    } else {
+        scanner
+    };
+
+    let fragment_size = cli.mtu.or(cli.fragment.then_some(8));
+    let scanner = match fragment_size {
+        Some(size) => {
+            if let Err(e) = nrmap::packet::crafting::validate_fragment_size(size) {
+                run_end.fail(format!("Invalid --mtu: {}", e));
+            }
+            scanner.with_fragmentation(size)
+        }
+        None => scanner,
+    };
+
+    let scanner = if cli.ttl.is_some() || cli.badsum {
+        let configured = scanner.config().evasion;
+        scanner.with_evasion(nrmap::scanner::ProbeEvasion {
+            ttl: cli.ttl.or(configured.ttl),
+            bad_checksum: cli.badsum || configured.bad_checksum,
         })
-        .collect::<Result<Vec<_>, _>>()?;
+    } else {
+        scanner
+    };
 
-    info!(
+    let scanner = if cli.adaptive_timeout && !scanner.config().adaptive_timeout.enabled {
+        let adaptive = scanner.config().adaptive_timeout.clone();
+        scanner.with_adaptive_timeouts(adaptive)
+    } else {
+        scanner
+    };
+
+    let profile_rate = profile.as_ref().and_then(|p| p.timing.max_rate());
+    let scanner = if cli.min_rate.is_some() || cli.max_rate.is_some() || cli.rate.is_some() || profile_rate.is_some() {
+        let min_rate = cli.rate.or(cli.min_rate).or(scanner.config().min_rate);
+        let max_rate = cli.rate.or(cli.max_rate).or(profile_rate).or(scanner.config().max_rate);
+        if let Err(e) = nrmap::scanner::throttle::validate_rate_limits(min_rate, max_rate) {
+            run_end.fail(format!("Invalid rate limits: {}", e));
+        }
+        scanner.with_rate_limits(min_rate, max_rate)
+    } else {
+        scanner
+    };
+
+    let scanner = match cli.max_concurrent_targets {
+        Some(targets) => scanner.with_max_concurrent_targets(targets),
+        None => scanner,
+    };
+
+    let scanner = match cli.max_concurrent_probes {
+        Some(total) => scanner.with_max_concurrent_probes_total(total),
+        None => scanner,
+    };
+
+    let scanner = match cli.ulimit_check {
+        Some(check) => scanner.with_ulimit_check(check),
+        None => scanner,
+    };
+
+    let scanner = if cli.open_only {
+        scanner.with_open_ports_only(true)
+    } else {
+        scanner
+    };
+    let scanner = if cli.expand_ports {
+        scanner.with_port_expansion(true)
+    } else {
+        scanner
+    };
+    let scanner = if cli.reason {
+        scanner.with_port_reasons(true)
+    } else {
+        scanner
+    };
+    let scanner = scanner.with_scope_override(cli.override_scope);
+
+    let scanner = match cli.ssh_bastion {
+        Some(ref bastion) => {
+            let mut tunnel = scanner.config().ssh_tunnel.clone().unwrap_or_default();
+            tunnel.bastion = bastion.clone();
+            if let Some(ref identity) = cli.ssh_identity {
+                tunnel.identity_file = Some(identity.clone());
+            }
+            scanner.with_ssh_tunnel(tunnel)
+        }
+        None => scanner,
+    };
+
+    let scanner = if cli.stateless {
+        scanner.with_stateless_syn(true)
+    } else {
+        scanner
+    };
+
+    let scanner = if cli.strict {
+        scanner.with_strict(true)
+    } else {
+        scanner
+    };
+
+    let scanner = if cli.randomize_ports || cli.randomize_hosts || cli.randomize_seed.is_some() {
+        let mut scan_order = scanner.config().scan_order;
+        if cli.randomize_ports || cli.randomize_hosts {
+            scan_order.randomize_ports |= cli.randomize_ports;
+            scan_order.randomize_targets |= cli.randomize_hosts;
+        } else if !scan_order.is_active() {
+            scan_order.randomize_ports = true;
+            scan_order.randomize_targets = true;
+        }
+        scan_order.seed = cli.randomize_seed.or(scan_order.seed);
+        scanner.with_scan_order(scan_order)
+    } else {
+        scanner
+    };
+
+    let scanner = if cli.reprobe_filtered {
+        let retry_policy = nrmap::scanner::RetryPolicy {
+            reprobe_filtered: true,
+            ..scanner.config().retry_policy
+        };
+        scanner.with_retry_policy(retry_policy)
+    } else {
+        scanner
+    };
+
+    let scanner = if cli.verify_open {
+        scanner.with_port_verification(true)
+    } else {
+        scanner
+    };
+
+    let scanner = if cli.deep_check {
+        scanner.with_deep_check(true)
+    } else {
+        scanner
+    };
+
+    // Down hosts are remembered beside the asset history unless a cache file is configured
+    let discovery_cache = &config.scanner.host_discovery.cache;
+    let scanner = match cli.history {
+        Some(ref history) if discovery_cache.enabled && discovery_cache.path.is_none() => scanner
+            .with_discovery_cache(Arc::new(DiscoveryCache::from_config(discovery_cache, Some(history)))),
+        _ => scanner,
+    };
+
+    // Kept for --summary-json, which prints them after the command ends
+    let scan_id = scanner.scan_id().to_string();
+    let report_files = output.sinks.files();
+    let summary = output.summary.clone();
+
+    // Execute command
+    let result = match cli.command {
+        Commands::Scan {
+            target,
+            source,
+            source_tag,
+            ports,
+            preset,
+            top_ports,
+            scan_type,
+            concurrency,
+            ..
+        } => match scan_plan(ports, preset, top_ports, scan_type, profile.as_ref()) {
+            Ok((ports, scan_types)) => match (target, source) {
+                (Some(target), _) => handle_scan(scanner, target, ports, scan_types, concurrency, output).await,
+                (None, source) => {
+                    let source = source.unwrap_or_default();
+                    handle_scan_source(scanner, source, source_tag, ports, scan_types, output, cli.yes).await
+                }
+            },
+            Err(e) => Err(e),
+        },
+        Commands::ScanFile {
+            file,
+            rescan_open_from,
+            ports,
+            preset,
+            top_ports,
+            scan_type,
+            ..
+        } => match scan_plan(ports, preset, top_ports, scan_type, profile.as_ref()) {
+            Ok((ports, scan_types)) => match (file, rescan_open_from) {
+                (Some(file), _) => handle_scan_file(scanner, file, ports, scan_types, output, cli.yes).await,
+                (None, nmap_file) => {
+                    let nmap_file = nmap_file.unwrap_or_default();
+                    handle_rescan_open(scanner, nmap_file, scan_types, output, cli.yes).await
+                }
+            },
+            Err(e) => Err(e),
+        },
+        Commands::Import { file } => handle_import(file, output),
+        Commands::Report { action: ReportCommand::Merge { files } } => handle_report_merge(files, output),
+        Commands::Schedule { file, once } => handle_schedule(scanner, file, once).await,
+        Commands::Monitor {
+            targets,
+            ports,
+            preset,
+            top_ports,
+            scan_type,
+            interval,
+            confirm,
+            webhook,
+            notify_command,
+            ..
+        } => match scan_plan(ports, preset, top_ports, scan_type, profile.as_ref()) {
+            Ok((ports, scan_types)) => {
+                let notify = NotifyConfig {
+                    webhooks: webhook,
+                    command: notify_command,
+                    ..Default::default()
+                };
+                handle_monitor(scanner, targets, ports, scan_types, interval, confirm, notify).await
+            }
+            Err(e) => Err(e),
+        },
+        Commands::DnsEnum {
+            ptr,
+            axfr,
+            brute,
+            wordlist,
+            concurrency,
+        } => {
+            let enumerator = DnsEnumerator::new(config.enrichment.rdns_timeout_ms, concurrency);
+            handle_dns_enum(enumerator, ptr, axfr, brute, wordlist).await
+        }
+        Commands::LanDiscover {
+            protocol,
+            duration,
+            listen,
+            interface,
+        } => handle_lan_discover(protocol, duration, listen, interface).await,
+        Commands::Wol {
+            mac,
+            broadcast,
+            port,
+            count,
+            wait,
+        } => handle_wol(mac, std::net::SocketAddr::new(broadcast, port), count, wait).await,
+        Commands::Discover6 { prefix } => handle_discover6(scanner, prefix).await,
+        Commands::Passive { interface, interval } => handle_passive(interface, interval).await,
+        Commands::Profiles { action } => handle_profiles(&config, config_path, action),
+        Commands::Presets { action } => {
+            handle_presets(action);
+            Ok(())
+        }
+        Commands::OsDb { action } => handle_os_db(&config, action),
+        Commands::Doctor { json } => handle_doctor(json),
+        Commands::Version => {
+            handle_version();
+            Ok(())
+        }
+        Commands::Config { .. } => unreachable!("handled before initialization"),
+    };
+
+    let counts = *summary.lock().unwrap_or_else(|e| e.into_inner());
+    if cli.summary_json {
+        let summary = RunSummary::new(scan_id, counts, run_end.started.elapsed(), report_files, result.as_ref().err());
+        println!("{}", summary.to_json());
+    }
+
+    if let Err(ref e) = result {
+        error!("Error: {}", e);
+    }
+    let outcome = ExitOutcome::of(result.as_ref().err(), counts.as_ref());
+    let code = run_end.exit_codes.code(outcome);
+    if code != 0 {
+        info!("Exiting with code {} ({})", code, outcome);
+        process::exit(code);
+    }
+}
+
+/// How a run reports its end
+struct RunEnd {
+    /// `--summary-json`: print the outcome as one JSON object on stdout
+    summary_json: bool,
+    exit_codes: ExitCodePolicy,
+    started: std::time::Instant,
+}
+
+impl RunEnd {
+    /// End a run that failed before its command ran, with the error on
+    /// stderr, for `--summary-json` an error summary on stdout, and the
+    /// `--exit-code-policy` code for errors
+    fn fail(&self, message: String) -> ! {
+        eprintln!("{}", message);
+        if self.summary_json {
+            println!("{}", RunSummary::setup_failed(message, self.started.elapsed()).to_json());
+        }
+        process::exit(self.exit_codes.code(ExitOutcome::Error));
+    }
+}
+
+/// What to emit once a scan finishes
+struct ScanOutput {
+    /// Print the machine-parsable summary line to stderr
+    summary_line: bool,
+    /// `--quiet`: print no result listing on stdout
+    quiet: bool,
+    /// Numbers of the finished scan, for `--summary-json` and the exit code
+    summary: Arc<Mutex<Option<SummaryLine>>>,
+    /// Draw a live progress bar on stderr while scanning
+    progress_bar: bool,
+    /// Asset history file updated with every scan
+    history: Option<PathBuf>,
+    /// Narrow results to assets the history has not seen
+    only_new: bool,
+    /// `--output` destinations; the plain-text listing is printed when empty
+    sinks: SinkList,
+    /// Spool `scan-file` results to this directory instead of memory
+    spool_dir: Option<PathBuf>,
+    /// Host results per spool segment file
+    spool_segment_results: usize,
+    /// `--allowed-ports`: other open ports fail the run
+    allowed_ports: Option<Vec<u16>>,
+    /// `--policy`: violated rules fail the run
+    policy: Option<Policy>,
+    /// Earlier results to log the changes against
+    compare_with: Option<Vec<nrmap::scanner::CompleteScanResult>>,
+    /// `--min-confidence`: port changes found with less are not logged
+    min_confidence: Option<Confidence>,
+}
+
+impl ScanOutput {
+    /// Print the summary line if asked to, and keep the numbers
+    fn finish_summary(&self, summary: SummaryLine) {
+        if self.summary_line {
+            eprintln!("{}", summary);
+        }
+        *self.summary.lock().unwrap_or_else(|e| e.into_inner()) = Some(summary);
+    }
+}
+
+async fn handle_scan(
+    scanner: nrmap::Scanner,
+    target: String,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    concurrency: Option<usize>,
+    output: ScanOutput,
+) -> nrmap::ScanResult<()> {
+    let scanner = match concurrency {
+        Some(concurrency) => scanner.with_max_concurrent(concurrency),
+        None => scanner,
+    };
+
+    // Parse target IP
+    let target_ip: IpAddr = target
+        .parse()
+        .map_err(|_| nrmap::ScanError::invalid_target(target, "Invalid IP address"))?;
+
+    // Fail early if a privileged scan type was requested without privileges
+    scanner.check_scan_types(&scan_types)?;
+
+    info!(
         "Starting scan: target={}, ports={}, scan_types={:?}",
         target_ip,
         ports.len(),
@@ -170,39 +1247,576 @@ async fn handle_scan(
     );
 
     // Perform scan
-    let results = scanner.scan(target_ip, ports, scan_types).await?;
+    let start = std::time::Instant::now();
+    let report = ReportBuilder::new(scanner.scan_id().to_string())
+        .with_parameters(scan_parameters(&scanner, vec![target_ip], &ports, &scan_types))
+        .with_origin(scanner.scan_origin(Some(target_ip)));
+    cancel_on_ctrl_c(&scanner);
+    pause_on_signals(&scanner);
+    let progress = output.progress_bar.then(|| ProgressBarDisplay::start(scanner.progress()));
+    let scan = scanner.scan(target_ip, ports, scan_types).await;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    let scanned = scan.as_ref().map(std::slice::from_ref).unwrap_or(&[]);
+    output.finish_summary(SummaryLine::from_results(scanned, 1, start.elapsed()));
+
+    let results = apply_history(&output, vec![scan?])?;
+    let compliance = output.policy.as_ref().map(|policy| policy.evaluate(&results, chrono::Utc::now()));
+    let expected = check_allowed_ports(&output, &results).and(policy_error(compliance.as_ref()));
+
+    if !output.sinks.is_empty() {
+        let report = report
+            .with_cancelled(scanner.is_cancelled())
+            .with_resources(scanner.resource_usage())
+            .with_new_assets_only(output.only_new);
+        let report = with_expectations(report, &output, compliance);
+        write_outputs(output.sinks, report, results)?;
+        expected?;
+        return cancelled_error(&scanner);
+    }
 
     // Display results
-    println!("\n{}", "=".repeat(80));
-    println!("{}", results);
-    println!("{}", "=".repeat(80));
+    if !output.quiet {
+        println!("\n{}", "=".repeat(80));
+        for result in &results {
+            println!("{}", result);
+        }
+        println!("{}", "=".repeat(80));
+        if let Some(ref compliance) = compliance {
+            print_compliance(compliance);
+        }
+    }
 
-    Ok(())
+    expected?;
+    cancelled_error(&scanner)
 }
 
 async fn handle_scan_file(
     scanner: nrmap::Scanner,
     file_path: String,
-    ports_str: Option<String>,
-    preset: Option<String>,
-    scan_types: Vec<String>,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    output: ScanOutput,
+    assume_yes: bool,
 ) -> nrmap::ScanResult<()> {
-    use std::fs;
+    let targets = read_targets(&file_path)?;
+    scan_targets(scanner, targets, ports, scan_types, output, assume_yes).await
+}
 
-    // Read targets from file
-    let content = fs::read_to_string(&file_path).map_err(|e| {
-        nrmap::ScanError::scanner_error(format!("Failed to read file {}: {}", file_path, e))
-    })?;
+/// Scan the addresses listed by a cloud inventory
+async fn handle_scan_source(
+    scanner: nrmap::Scanner,
+    source: String,
+    tags: Vec<String>,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    output: ScanOutput,
+    assume_yes: bool,
+) -> nrmap::ScanResult<()> {
+    let spec: SourceSpec = source.parse()?;
+    let (name, assets) = tokio::task::spawn_blocking(move || {
+        let source = spec.connect()?;
+        Ok::<_, nrmap::ScanError>((source.describe(), source.assets()?))
+    })
+    .await
+    .map_err(|e| nrmap::ScanError::scanner_error(format!("Inventory task failed: {}", e)))??;
+    let listed = assets.len();
+    let assets = filter_by_tags(assets, &tags);
+    info!(
+        "{} lists {} addresses, {} match the tag filters ({} public)",
+        name,
+        listed,
+        assets.len(),
+        assets.iter().filter(|asset| asset.public).count()
+    );
+
+    let targets = target_addresses(&assets);
+    if targets.is_empty() {
+        return Err(nrmap::ScanError::validation_error("source", format!("{} lists no matching addresses", name)));
+    }
+    scan_targets(scanner, targets, ports, scan_types, output, assume_yes).await
+}
+
+/// Re-verify the ports an Nmap scan found open
+///
+/// Every host with open ports is scanned on the union of those ports, so a
+/// port that opened on another host of the set is caught too.
+async fn handle_rescan_open(
+    scanner: nrmap::Scanner,
+    nmap_file: PathBuf,
+    scan_types: Vec<ScanType>,
+    mut output: ScanOutput,
+    assume_yes: bool,
+) -> nrmap::ScanResult<()> {
+    let run = NmapRun::from_file(&nmap_file)?;
+    let open = run.open_tcp_ports();
+    if open.is_empty() {
+        return Err(nrmap::ScanError::validation_error(
+            "rescan_open_from",
+            format!("{} lists no open TCP ports", nmap_file.display()),
+        ));
+    }
+    let ports: BTreeSet<u16> = open.values().flatten().copied().collect();
+    info!(
+        "{} lists {} hosts with open TCP ports ({} distinct ports)",
+        nmap_file.display(),
+        open.len(),
+        ports.len()
+    );
+
+    // The comparison needs every result at hand, and only covers what is
+    // rescanned
+    let ports: Vec<u16> = ports.into_iter().collect();
+    output.spool_dir = None;
+    output.compare_with = Some(run.rescan_baseline(&ports));
+    let targets = open.into_keys().collect();
+    scan_targets(scanner, targets, ports, scan_types, output, assume_yes).await
+}
+
+/// Record and render the results of an Nmap XML file
+fn handle_import(file: PathBuf, output: ScanOutput) -> nrmap::ScanResult<()> {
+    let run = NmapRun::from_file(&file)?;
+    info!("Imported {} hosts from {}", run.results.len(), file.display());
+    let report = run.report();
+    let results = apply_history(&output, run.results)?;
+
+    if !output.sinks.is_empty() {
+        return write_outputs(output.sinks, report, results);
+    }
+    if !output.quiet {
+        for result in &results {
+            println!("{}", result);
+            println!("{}", "-".repeat(80));
+        }
+    }
+    Ok(())
+}
+
+fn handle_report_merge(files: Vec<PathBuf>, output: ScanOutput) -> nrmap::ScanResult<()> {
+    let reports = files.iter().map(|file| load_report(file)).collect::<nrmap::ScanResult<Vec<_>>>()?;
+    let (report, results) = ReportEngine::merge_parts(reports)?;
+    info!("Merged {} reports: {} hosts", files.len(), results.len());
+    let report = with_expectations(report, &output, None);
+
+    if !output.sinks.is_empty() {
+        return write_outputs(output.sinks, report, results);
+    }
+    if !output.quiet {
+        for result in &results {
+            println!("{}", result);
+            println!("{}", "-".repeat(80));
+        }
+    }
+    Ok(())
+}
+
+async fn scan_targets(
+    scanner: nrmap::Scanner,
+    targets: Vec<IpAddr>,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    output: ScanOutput,
+    assume_yes: bool,
+) -> nrmap::ScanResult<()> {
+    // Fail early if a privileged scan type was requested without privileges
+    scanner.check_scan_types(&scan_types)?;
+
+    let scope = ScanScope::new(targets.len(), ports.len(), &scan_types, scanner.config());
+    confirm_scope(
+        &scope,
+        scanner.config().confirm_targets_above,
+        assume_yes,
+        std::io::stdin().is_terminal(),
+        &mut std::io::stdin().lock(),
+        &mut std::io::stderr(),
+    )?;
+
+    info!(
+        "Starting scan: {} targets, {} ports per target",
+        targets.len(),
+        ports.len()
+    );
 
-    let targets: Vec<IpAddr> = content
-        .lines()
-        .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
-        .map(|line| {
-            line.trim().parse().map_err(|_| {
-                nrmap::ScanError::invalid_target(line, "Invalid IP address in file")
-            })
+    // Perform scans
+    let start = std::time::Instant::now();
+    let target_count = targets.len();
+    let report = ReportBuilder::new(scanner.scan_id().to_string())
+        .with_parameters(scan_parameters(&scanner, targets.clone(), &ports, &scan_types))
+        .with_origin(scanner.scan_origin(targets.first().copied()));
+    cancel_on_ctrl_c(&scanner);
+    pause_on_signals(&scanner);
+    if let Some(dir) = output.spool_dir.clone() {
+        return scan_file_spooled(scanner, targets, ports, scan_types, report, output, dir).await;
+    }
+    let progress = output.progress_bar.then(|| ProgressBarDisplay::start(scanner.progress()));
+    let results = scanner.scan_multiple(targets, ports, scan_types).await;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    let results = results?;
+
+    output.finish_summary(SummaryLine::from_results(&results, target_count, start.elapsed()));
+    if let Some(ref previous) = output.compare_with {
+        let mut diff = ScanDiff::between(previous, &results);
+        if let Some(min) = output.min_confidence {
+            diff.retain_confident(&results, min);
+        }
+        info!("{} changes since the earlier scan", diff.changes.len());
+        for change in &diff.changes {
+            info!("  {}", change);
+        }
+    }
+
+    let results = apply_history(&output, results)?;
+    let compliance = output.policy.as_ref().map(|policy| policy.evaluate(&results, chrono::Utc::now()));
+    let expected = check_allowed_ports(&output, &results).and(policy_error(compliance.as_ref()));
+
+    if !output.sinks.is_empty() {
+        let report = report
+            .with_cancelled(scanner.is_cancelled())
+            .with_resources(scanner.resource_usage())
+            .with_new_assets_only(output.only_new);
+        let report = with_expectations(report, &output, compliance);
+        write_outputs(output.sinks, report, results)?;
+        expected?;
+        return cancelled_error(&scanner);
+    }
+
+    // Display results
+    if !output.quiet {
+        println!("\n{}", "=".repeat(80));
+        for result in &results {
+            println!("{}", result);
+            println!("{}", "-".repeat(80));
+        }
+        println!("{}", "=".repeat(80));
+        if let Some(ref compliance) = compliance {
+            print_compliance(compliance);
+        }
+    }
+
+    expected?;
+    cancelled_error(&scanner)
+}
+
+/// Per-result state of a spooled scan, updated on the delivery thread
+struct SpoolState {
+    summary: SummaryLine,
+    spool: ResultSpool,
+    history: Option<AssetHistory>,
+    /// Hosts with open ports outside `--allowed-ports`
+    unexpected_hosts: usize,
+}
+
+/// Scan with every host's result spooled to `dir` as soon as it is done
+///
+/// Memory stays bounded by the hosts in flight: the summary line and the
+/// history are updated per result, and the report is streamed back from the
+/// spool, which is removed afterwards.
+async fn scan_file_spooled(
+    scanner: nrmap::Scanner,
+    targets: Vec<IpAddr>,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    report: ReportBuilder,
+    mut output: ScanOutput,
+    dir: PathBuf,
+) -> nrmap::ScanResult<()> {
+    let start = std::time::Instant::now();
+    let recorded_at = chrono::Utc::now();
+    let only_new = output.only_new;
+    let allowed_ports = output.allowed_ports.clone();
+    let state = Arc::new(Mutex::new(SpoolState {
+        summary: SummaryLine::new(targets.len()),
+        spool: ResultSpool::create(dir.join(scanner.scan_id()), output.spool_segment_results)?,
+        history: output.history.as_ref().map(AssetHistory::load).transpose()?,
+        unexpected_hosts: 0,
+    }));
+
+    let progress = output.progress_bar.then(|| ProgressBarDisplay::start(scanner.progress()));
+    let recording = Arc::clone(&state);
+    let scanned = scanner
+        .scan_multiple_with(targets, ports, scan_types, move |result| {
+            let mut state = recording.lock().unwrap_or_else(|e| e.into_inner());
+            let SpoolState { summary, spool, history, unexpected_hosts } = &mut *state;
+            summary.record(&result);
+            if allowed_ports.as_ref().is_some_and(|ports| !unexpected_open_ports(&result, ports).is_empty()) {
+                *unexpected_hosts += 1;
+            }
+            // The --only-new filter compares against the history as it was
+            // before this host was recorded
+            let reported = match history {
+                Some(ref mut history) => {
+                    let reported = if only_new {
+                        history.retain_new(vec![result.clone()])
+                    } else {
+                        vec![result.clone()]
+                    };
+                    history.record(std::slice::from_ref(&result), recorded_at);
+                    reported
+                }
+                None => vec![result],
+            };
+            reported.iter().try_for_each(|result| spool.push(result))
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .await;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    scanned?;
+    // The delivery thread has finished and dropped its handle
+    let SpoolState { summary, spool, history, unexpected_hosts } = Arc::try_unwrap(state)
+        .map_err(|_| nrmap::ScanError::scanner_error("Result delivery still running"))?
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner());
+    let results = spool.finish()?;
+    info!("Spooled {} host results to {}", results.len(), dir.display());
+
+    // Checked against what is reported, like results held in memory
+    let compliance = match output.policy {
+        Some(ref policy) => {
+            let mut evaluator = PolicyEvaluator::new(policy, recorded_at);
+            for result in results.iter() {
+                evaluator.add(&result?);
+            }
+            Some(evaluator.finish())
+        }
+        None => None,
+    };
+
+    output.finish_summary(summary.with_duration(start.elapsed()));
+    if let (Some(history), Some(path)) = (history, output.history.as_ref()) {
+        history.save(path)?;
+    }
+
+    let written = if output.quiet && output.sinks.is_empty() {
+        Ok(())
+    } else if output.sinks.is_empty() {
+        println!("\n{}", "=".repeat(80));
+        let listed = results.iter().try_for_each(|result| {
+            println!("{}", result?);
+            println!("{}", "-".repeat(80));
+            Ok(())
+        });
+        println!("{}", "=".repeat(80));
+        if let Some(ref compliance) = compliance {
+            print_compliance(compliance);
+        }
+        listed
+    } else {
+        let report = report
+            .with_cancelled(scanner.is_cancelled())
+            .with_resources(scanner.resource_usage())
+            .with_new_assets_only(output.only_new);
+        let report = with_expectations(report, &output, compliance.clone());
+        let report = report.complete().build_spooled(results.clone())?;
+        info!("Writing report to {}", output.sinks.describe());
+        output.sinks.write_spooled(&report)
+    };
+    results.remove()?;
+    written?;
+    unexpected_ports_error(unexpected_hosts)?;
+    policy_error(compliance.as_ref())?;
+
+    cancelled_error(&scanner)
+}
+
+/// Cancel the scan on the first Ctrl-C and exit on the second
+///
+/// Cancelling lets in-flight probes finish so the partial results can still
+/// be written out.
+fn cancel_on_ctrl_c(scanner: &nrmap::Scanner) {
+    let token = scanner.cancellation_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!("Interrupted, finishing in-flight probes (press Ctrl-C again to abort)");
+        token.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::exit(130);
+        }
+    });
+}
+
+/// Pause or resume the scan on Ctrl-Z (SIGTSTP) and SIGUSR1
+///
+/// The process keeps running while paused, so sockets and collected results
+/// survive; the same key (or signal) resumes.
+#[cfg(unix)]
+fn pause_on_signals(scanner: &nrmap::Scanner) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let control = scanner.pause_control();
+    for kind in [SignalKind::from_raw(libc::SIGTSTP), SignalKind::user_defined1()] {
+        let Ok(mut signals) = signal(kind) else {
+            continue;
+        };
+        let control = control.clone();
+        tokio::spawn(async move {
+            while signals.recv().await.is_some() {
+                if control.toggle() {
+                    warn!("Scan paused (press Ctrl-Z or send SIGUSR1 to resume)");
+                } else {
+                    info!("Scan resumed");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn pause_on_signals(_scanner: &nrmap::Scanner) {}
+
+/// Fail the run when a host has open ports outside `--allowed-ports`
+fn check_allowed_ports(output: &ScanOutput, results: &[nrmap::scanner::CompleteScanResult]) -> nrmap::ScanResult<()> {
+    let Some(ref allowed) = output.allowed_ports else {
+        return Ok(());
+    };
+    let hosts = results
+        .iter()
+        .filter(|result| !unexpected_open_ports(result, allowed).is_empty())
+        .count();
+    unexpected_ports_error(hosts)
+}
+
+fn unexpected_ports_error(hosts: usize) -> nrmap::ScanResult<()> {
+    if hosts > 0 {
+        return Err(nrmap::ScanError::policy_violation(format!(
+            "{} host(s) have open ports outside --allowed-ports",
+            hosts
+        )));
+    }
+    Ok(())
+}
+
+/// Record `--allowed-ports` and `--policy` in the report, with the policy
+/// outcome when the run already evaluated it
+fn with_expectations(report: ReportBuilder, output: &ScanOutput, compliance: Option<ComplianceReport>) -> ReportBuilder {
+    let report = match output.allowed_ports {
+        Some(ref ports) => report.with_allowed_ports(ports.clone()),
+        None => report,
+    };
+    match (compliance, output.policy.as_ref()) {
+        (Some(compliance), _) => report.with_compliance(compliance),
+        (None, Some(policy)) => report.with_policy(policy.clone()),
+        (None, None) => report,
+    }
+}
+
+/// List policy results after the plain-text results
+fn print_compliance(compliance: &ComplianceReport) {
+    println!("Policy: {}", compliance);
+    for rule in &compliance.rules {
+        println!("  [{}] {}", if rule.passed() { "PASS" } else { "FAIL" }, rule.name);
+        if let Some(ref warning) = rule.warning {
+            println!("      warning: {}", warning);
+        }
+        for violation in &rule.violations {
+            println!("      {}", violation);
+        }
+    }
+}
+
+/// Fail the run when a `--policy` rule was violated
+fn policy_error(compliance: Option<&ComplianceReport>) -> nrmap::ScanResult<()> {
+    let Some(compliance) = compliance else {
+        return Ok(());
+    };
+    let failed: Vec<&str> = compliance.failed_rules().map(|rule| rule.name.as_str()).collect();
+    if !failed.is_empty() {
+        return Err(nrmap::ScanError::policy_violation(format!(
+            "{} policy rule(s) failed: {}",
+            failed.len(),
+            failed.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Fail the run after partial results of a cancelled scan were written
+fn cancelled_error(scanner: &nrmap::Scanner) -> nrmap::ScanResult<()> {
+    if scanner.is_cancelled() {
+        return Err(nrmap::ScanError::scanner_error("Scan cancelled, results are partial"));
+    }
+    Ok(())
+}
+
+fn scan_parameters(
+    scanner: &nrmap::Scanner,
+    targets: Vec<IpAddr>,
+    ports: &[u16],
+    scan_types: &[ScanType],
+) -> ScanParameters {
+    ScanParameters {
+        targets,
+        ports: ports.to_vec(),
+        scan_types: scan_types.iter().map(|t| format!("{:?}", t)).collect(),
+        timeout_ms: scanner.config().default_timeout_ms,
+        concurrent_scans: scanner.concurrency().ports,
+    }
+}
+
+/// Record the results in the `--history` file and apply `--only-new`
+///
+/// The filter compares against the history as it was before this scan.
+fn apply_history(
+    output: &ScanOutput,
+    results: Vec<nrmap::scanner::CompleteScanResult>,
+) -> nrmap::ScanResult<Vec<nrmap::scanner::CompleteScanResult>> {
+    let Some(ref path) = output.history else {
+        return Ok(results);
+    };
+    let mut history = AssetHistory::load(path)?;
+    let reported = if output.only_new {
+        let fresh = history.retain_new(results.clone());
+        info!("{} of {} hosts have assets not seen before", fresh.len(), results.len());
+        fresh
+    } else {
+        results.clone()
+    };
+    history.record(&results, chrono::Utc::now());
+    history.save(path)?;
+    Ok(reported)
+}
+
+/// Redactor for `--redact`, keyed by `--redact-key` and mapping into
+/// `--redact-map` when given
+fn redactor(scope: RedactScope, key: Option<&std::path::Path>, map: Option<PathBuf>) -> nrmap::ScanResult<Redactor> {
+    let redactor = match key {
+        Some(path) => Redactor::new(Redactor::load_key(path)?, scope),
+        None => Redactor::with_random_key(scope),
+    };
+    match map {
+        Some(path) => redactor.with_map_file(path),
+        None => Ok(redactor),
+    }
+}
+
+/// Render the finished scan to every `--output` destination
+fn write_outputs(
+    mut outputs: SinkList,
+    report: ReportBuilder,
+    results: Vec<nrmap::scanner::CompleteScanResult>,
+) -> nrmap::ScanResult<()> {
+    let report = report.add_results(results).complete().build()?;
+    info!("Writing report to {}", outputs.describe());
+    outputs.write_report(&report)
+}
+
+/// Read a target file ("-" for stdin): addresses, CIDR blocks or JSON
+/// records, one per line
+fn read_targets(file_path: &str) -> nrmap::ScanResult<Vec<IpAddr>> {
+    let content = if file_path == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(file_path)
+    }
+    .map_err(|e| nrmap::ScanError::scanner_error(format!("Failed to read file {}: {}", file_path, e)))?;
+
+    let targets = parse_target_list(&content)?;
 
     if targets.is_empty() {
         return Err(nrmap::ScanError::validation_error(
@@ -210,50 +1824,483 @@ async fn handle_scan_file(
             "No valid targets found in file",
         ));
     }
+    Ok(targets)
+}
 
-    // Parse ports
-    let ports = if let Some(preset) = preset {
-        parse_port_preset(&preset)?
-    } else if let Some(ports_str) = ports_str {
-        parse_port_range(&ports_str)?
-    } else {
-        parse_port_preset("common")?
+async fn handle_monitor(
+    scanner: nrmap::Scanner,
+    targets_file: String,
+    ports: Vec<u16>,
+    scan_types: Vec<ScanType>,
+    interval: String,
+    confirm: u32,
+    notify: NotifyConfig,
+) -> nrmap::ScanResult<()> {
+    let interval = parse_duration(&interval)?;
+    if interval.is_zero() {
+        return Err(nrmap::ScanError::validation_error("interval", "must be longer than zero"));
+    }
+    let plan = ScanPlan {
+        targets: read_targets(&targets_file)?,
+        ports,
+        scan_types,
     };
+    scanner.check_scan_types(&plan.scan_types)?;
+    info!(
+        "Monitoring {} targets, {} ports every {:?}; changes are reported after {} rounds",
+        plan.targets.len(),
+        plan.ports.len(),
+        interval,
+        confirm.max(1)
+    );
 
-    // Parse scan types
-    let scan_types: Vec<ScanType> = scan_types
-        .iter()
-        .map(|s| match s.to_lowercase().as_str() {
-            "tcp" | "connect" => Ok(ScanType::TcpConnect),
-            "syn" => Ok(ScanType::TcpSyn),
-            "udp" => Ok(ScanType::Udp),
-            _ => Err(nrmap::ScanError::validation_error(
-                "scan_type",
-                format!("Unknown scan type: {}", s),
-            )),
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    cancel_on_ctrl_c(&scanner);
+    Monitor::new(scanner, plan, interval, Notifier::new(notify))
+        .with_confirm_after(confirm)
+        .run()
+        .await
+}
 
+async fn handle_schedule(scanner: nrmap::Scanner, file: PathBuf, once: bool) -> nrmap::ScanResult<()> {
+    let schedule = ScheduleFile::from_file(&file)?;
+    for scan in &schedule.scans {
+        scanner.check_scan_types(&scan.plan()?.scan_types)?;
+    }
     info!(
-        "Starting scan: {} targets, {} ports per target",
+        "Loaded {} scheduled scans from {}, state in {}",
+        schedule.scans.len(),
+        file.display(),
+        schedule.state_dir.display()
+    );
+
+    cancel_on_ctrl_c(&scanner);
+    let scans = RecurringScans::new(schedule, scanner);
+    if !once {
+        return scans.run().await;
+    }
+    for scan in scans.scans() {
+        match scans.run_scan(scan).await? {
+            Some(diff) => info!("{}: {}", scan.name, diff),
+            None => info!("{}: baseline recorded", scan.name),
+        }
+    }
+    Ok(())
+}
+
+/// Print the names DNS enumeration finds as a target list on stdout
+///
+/// A failed zone transfer or sweep is logged and the others still run.
+async fn handle_dns_enum(
+    enumerator: DnsEnumerator,
+    ptr: Vec<String>,
+    axfr: Vec<String>,
+    brute: Vec<String>,
+    wordlist: Option<PathBuf>,
+) -> nrmap::ScanResult<()> {
+    let words: Vec<String> = match wordlist {
+        Some(path) => std::fs::read_to_string(&path)?
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && !word.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        None => dns_discovery::DEFAULT_WORDS.iter().map(|word| word.to_string()).collect(),
+    };
+    let zones = axfr.iter().map(|spec| dns_discovery::parse_axfr_spec(spec)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut found = Vec::new();
+    for block in &ptr {
+        found.extend(enumerator.ptr_sweep(block).await?);
+    }
+    for (zone, server) in &zones {
+        match enumerator.zone_transfer(zone, server).await {
+            Ok(targets) => found.extend(targets),
+            Err(e) => warn!("Zone transfer of {} from {} failed: {}", zone, server, e),
+        }
+    }
+    for domain in &brute {
+        found.extend(enumerator.brute_force(domain, &words).await?);
+    }
+
+    let targets = dns_discovery::merge_targets(found);
+    for target in &targets {
+        println!("{}", target.to_json_line());
+    }
+    info!(
+        "DNS enumeration found {} names with {} addresses",
         targets.len(),
-        ports.len()
+        targets.iter().map(|t| t.addresses().count()).sum::<usize>()
     );
+    Ok(())
+}
 
-    // Perform scans
-    let results = scanner.scan_multiple(targets, ports, scan_types).await?;
+/// Print the devices multicast discovery finds as a target list on stdout
+async fn handle_lan_discover(
+    protocols: Vec<String>,
+    duration: u64,
+    listen: bool,
+    interface: Option<std::net::Ipv4Addr>,
+) -> nrmap::ScanResult<()> {
+    let protocols = protocols.iter().map(|p| p.parse::<LanProtocol>()).collect::<Result<Vec<_>, _>>()?;
+    let mut discovery = LanDiscovery::new(std::time::Duration::from_secs(duration))
+        .with_protocols(protocols)
+        .with_listen(listen);
+    if let Some(interface) = interface {
+        discovery = discovery.with_interface(interface);
+    }
+
+    let devices = discovery.discover().await?;
+    for device in &devices {
+        println!("{}", device.to_json_line());
+    }
+    for device in &devices {
+        info!("{}", device);
+    }
+    info!("LAN discovery found {} devices", devices.len());
+    Ok(())
+}
+
+async fn handle_wol(macs: Vec<String>, target: std::net::SocketAddr, count: u32, wait: u64) -> nrmap::ScanResult<()> {
+    let macs = macs.iter().map(|mac| mac.parse::<nrmap::lan::MacAddress>()).collect::<Result<Vec<_>, _>>()?;
+    for mac in &macs {
+        nrmap::lan::wake(mac, target, count).await?;
+        println!("Sent Wake-on-LAN to {} via {}", nrmap::lan::HostMac::new(*mac), target);
+    }
+    if wait > 0 {
+        info!("Waiting {}s for hosts to wake", wait);
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+    }
+    Ok(())
+}
+
+async fn handle_discover6(scanner: nrmap::Scanner, prefix: String) -> nrmap::ScanResult<()> {
+    let prefix = nrmap::scanner::ipv6_discovery::Ipv6Prefix::parse(&prefix)?;
+
+    info!("Starting IPv6 discovery: prefix={}", prefix);
+
+    let results = scanner.discover_ipv6(&prefix).await?;
 
     // Display results
     println!("\n{}", "=".repeat(80));
-    for result in results {
+    for result in results.iter().filter(|r| r.status == nrmap::scanner::host_discovery::HostStatus::Up) {
         println!("{}", result);
-        println!("{}", "-".repeat(80));
     }
     println!("{}", "=".repeat(80));
 
     Ok(())
 }
 
+async fn handle_passive(interface: Option<String>, interval: u64) -> nrmap::ScanResult<()> {
+    use nrmap::os_fingerprint::PassiveCaptureService;
+
+    let mut service = PassiveCaptureService::new(nrmap::packet::CaptureConfig {
+        interface,
+        promiscuous: true,
+        ..Default::default()
+    });
+    service.start()?;
+
+    println!("Passive fingerprinting started (Ctrl-C to stop)");
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+
+        if !service.is_running() {
+            error!("Passive capture stopped unexpectedly");
+            break;
+        }
+
+        let results = service.results();
+        println!("\n{}", "=".repeat(80));
+        println!(
+            "{} frames, {} SYN/SYN-ACK observations, {} hosts fingerprinted",
+            service.frames_seen(),
+            service.observations(),
+            results.len()
+        );
+        for result in &results {
+            println!(
+                "{}: {} packets, confidence {:.0}%, {}",
+                result.target,
+                result.packets_observed,
+                result.confidence * 100.0,
+                result.os_hints.join("; ")
+            );
+        }
+        println!("{}", "=".repeat(80));
+    }
+
+    service.stop();
+    Ok(())
+}
+
+/// Ports and scan types of a scan command
+///
+/// Explicit `--ports`, `--preset`, `--top-ports` and `--scan-type` win over
+/// the profile; without either the common ports are scanned over TCP connect.
+fn scan_plan(
+    ports_str: Option<String>,
+    preset: Option<String>,
+    top_ports: Option<usize>,
+    scan_types: Vec<String>,
+    profile: Option<&ScanProfile>,
+) -> nrmap::ScanResult<(Vec<u16>, Vec<ScanType>)> {
+    let scan_types = if !scan_types.is_empty() {
+        scan_types.iter().map(|s| s.parse()).collect::<Result<Vec<_>, _>>()?
+    } else if let Some(profile) = profile {
+        profile.scan_types.clone()
+    } else {
+        vec![ScanType::TcpConnect]
+    };
+
+    let ports = if let Some(preset) = preset {
+        parse_port_preset(&preset)?
+    } else if let Some(ports_str) = ports_str {
+        parse_port_range(&ports_str)?
+    } else if let Some(count) = top_ports {
+        top_ports_for(&scan_types, count)?
+    } else if let Some(profile) = profile {
+        profile.ports.resolve()?
+    } else {
+        parse_port_preset("common")?
+    };
+
+    Ok((ports, scan_types))
+}
+
+/// The `count` most frequently open ports of each protocol the scan types use
+fn top_ports_for(scan_types: &[ScanType], count: usize) -> nrmap::ScanResult<Vec<u16>> {
+    use nrmap::scanner::port_frequency::{top_ports, TransportProtocol};
+
+    if count == 0 {
+        return Err(nrmap::ScanError::validation_error("top_ports", "must be greater than 0"));
+    }
+    let mut ports = Vec::new();
+    if scan_types.iter().any(|t| matches!(t, ScanType::TcpConnect | ScanType::TcpSyn)) {
+        ports.extend(top_ports(TransportProtocol::Tcp, count));
+    }
+    if scan_types.contains(&ScanType::Udp) {
+        ports.extend(top_ports(TransportProtocol::Udp, count));
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Merge the configuration layers and initialize the library with the result
+async fn load_and_init(
+    layers: &ConfigLayers,
+) -> nrmap::ScanResult<(AppConfig, (nrmap::Scanner, Option<tracing_appender::non_blocking::WorkerGuard>))> {
+    let config = layers.load()?;
+    let initialized = init_library_with_config(config.clone()).await?;
+    Ok((config, initialized))
+}
+
+fn handle_config(layers: &ConfigLayers, action: ConfigCommand) -> nrmap::ScanResult<()> {
+    match action {
+        ConfigCommand::Show { effective } => {
+            // Comments keep `--effective` output loadable as a config file
+            println!("# Configuration sources, later ones override earlier ones:");
+            for (index, layer) in layers.layers().iter().enumerate() {
+                println!("#   {}. {}", index + 1, layer);
+            }
+            if effective {
+                let config = layers.load()?;
+                let toml = toml::to_string_pretty(&config)
+                    .map_err(|e| nrmap::ScanError::scanner_error(format!("Failed to render configuration: {}", e)))?;
+                println!();
+                print!("{}", toml);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Built-in profiles plus those in the configuration and the project
+/// config's profile directory
+fn load_profiles(config: &AppConfig, config_path: &str) -> nrmap::ScanResult<ProfileRegistry> {
+    ProfileRegistry::load(&config.profiles, Some(&config.profiles_dir(config_path)))
+}
+
+fn handle_profiles(config: &AppConfig, config_path: &str, action: ProfilesCommand) -> nrmap::ScanResult<()> {
+    let registry = load_profiles(config, config_path)?;
+    match action {
+        ProfilesCommand::List => {
+            println!("{:<16} {:<12} DESCRIPTION", "NAME", "SOURCE");
+            for (profile, source) in registry.list() {
+                let source = match source {
+                    nrmap::cli::ProfileSource::File(_) => "file".to_string(),
+                    source => source.to_string(),
+                };
+                println!("{:<16} {:<12} {}", profile.name, source, profile.description);
+            }
+        }
+        ProfilesCommand::Show { name } => {
+            let profile = registry.get(&name)?;
+            print!("{}", profile);
+            if let Some(source) = registry.source(&name) {
+                println!("  Source: {}", source);
+            }
+            println!("  Port Count: {}", profile.ports.resolve()?.len());
+            if let Some(rate) = profile.timing.max_rate() {
+                println!("  Max Rate: {} pps", rate);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_presets(action: PresetsCommand) {
+    use nrmap::scanner::port_presets::{format_ports, list_presets};
+
+    match action {
+        PresetsCommand::List => {
+            println!("{:<20} {:<10} {:>6}  PORTS", "NAME", "SOURCE", "COUNT");
+            for preset in list_presets() {
+                println!(
+                    "{:<20} {:<10} {:>6}  {}",
+                    preset.name,
+                    preset.source,
+                    preset.ports.len(),
+                    format_ports(&preset.ports)
+                );
+            }
+        }
+    }
+}
+
+fn handle_os_db(config: &AppConfig, action: OsDbCommand) -> nrmap::ScanResult<()> {
+    use nrmap::os_fingerprint::{user_db, DatabaseIO, OsFingerprintDatabase, UserDatabase};
+
+    let path = config.scanner.os_detection.user_database.clone().ok_or_else(|| {
+        nrmap::ScanError::validation_error("os_detection.user_database", "no user database path (HOME is unset)")
+    })?;
+    let mut user = UserDatabase::load(&path)?;
+    let builtin = OsFingerprintDatabase::new();
+
+    match action {
+        OsDbCommand::Add { file, replace } => {
+            for signature in user_db::read_signatures(&file)? {
+                let name = signature.os_name.clone();
+                let duplicates = user.duplicates_of(&signature, &builtin);
+                user.add(signature, replace)?;
+                if builtin.get_signature(&name).is_some() {
+                    println!("Added '{}' (overrides the built-in signature)", name);
+                } else {
+                    println!("Added '{}'", name);
+                }
+                if !duplicates.is_empty() {
+                    println!("  warning: same TCP/ICMP evidence as {}", duplicates.join(", "));
+                }
+            }
+            user.save()?;
+        }
+        OsDbCommand::Remove { name } => {
+            user.remove(&name)?;
+            user.save()?;
+            if builtin.get_signature(&name).is_some() {
+                println!("Removed '{}' (the built-in signature applies again)", name);
+            } else {
+                println!("Removed '{}'", name);
+            }
+        }
+        OsDbCommand::List { user: user_only } => {
+            let (merged, report) = user.merge_over(&builtin);
+            let mut names: Vec<&String> = merged.signatures().keys().collect();
+            names.sort();
+            println!("{:<40} {:<10} SOURCE", "NAME", "FAMILY");
+            for name in names {
+                let source = if report.overridden.contains(name) {
+                    "user (overrides built-in)"
+                } else if report.added.contains(name) {
+                    "user"
+                } else if user_only {
+                    continue;
+                } else {
+                    "built-in"
+                };
+                println!("{:<40} {:<10} {}", name, merged.signatures()[name].os_family.to_string(), source);
+            }
+            println!("\nUser database: {}", user.path().display());
+        }
+        OsDbCommand::Unknown => {
+            let store = learning_store(config)?;
+            println!("{:<16} {:<25} {:<39} CLOSEST", "ID", "RECORDED", "TARGET");
+            for unknown in store.load()? {
+                let closest = match (unknown.closest_match, unknown.closest_score) {
+                    (Some(name), Some(score)) => format!("{} ({:.2})", name, score),
+                    _ => "-".to_string(),
+                };
+                println!("{:<16} {:<25} {:<39} {}", unknown.id, unknown.recorded_at, unknown.fingerprint.target, closest);
+            }
+            println!("\nUnknown fingerprints: {}", store.path().display());
+        }
+        OsDbCommand::Label { id, name, family, os_version, replace } => {
+            let store = learning_store(config)?;
+            let family = family.parse()?;
+            let unknown = store.take(&id)?;
+            let labeled = unknown
+                .to_signature(&name, family, os_version)
+                .and_then(|signature| user.add(signature, replace))
+                .and_then(|()| user.save());
+            if let Err(e) = labeled {
+                // Put the record back so it can be labeled again
+                store.record(&unknown)?;
+                return Err(e);
+            }
+            println!("Labeled {} as '{}' in {}", unknown.id, name, user.path().display());
+        }
+        OsDbCommand::Export { path: output, user: user_only } => {
+            let database = if user_only {
+                user.merge_over(&OsFingerprintDatabase::empty()).0
+            } else {
+                user.merge_over(&builtin).0
+            };
+            match output.extension().and_then(|ext| ext.to_str()) {
+                Some("yaml") | Some("yml") => DatabaseIO::export_to_yaml(&database, &output)?,
+                _ => DatabaseIO::export_to_json(&database, &output, true)?,
+            }
+            println!("Exported {} signatures to {}", database.signature_count(), output.display());
+        }
+    }
+    Ok(())
+}
+
+/// Learning mode store from the OS detection config
+fn learning_store(config: &AppConfig) -> nrmap::ScanResult<nrmap::os_fingerprint::LearningStore> {
+    let path = config.scanner.os_detection.unknown_fingerprints.clone().ok_or_else(|| {
+        nrmap::ScanError::validation_error("os_detection.unknown_fingerprints", "no path (HOME is unset)")
+    })?;
+    Ok(nrmap::os_fingerprint::LearningStore::new(path))
+}
+
+fn handle_doctor(json: bool) -> nrmap::ScanResult<()> {
+    let capabilities = nrmap::capabilities();
+    if json {
+        let json = serde_json::to_string_pretty(capabilities)
+            .map_err(|e| nrmap::ScanError::scanner_error(format!("Failed to serialize capabilities: {}", e)))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    println!("{}", capabilities);
+    if !capabilities.raw_sockets {
+        println!("\nSYN scans, ARP discovery and packet capture need root or CAP_NET_RAW");
+    }
+    if capabilities.raw_sockets && !capabilities.packet_capture {
+        println!("\nNo active network interface found for packet capture");
+    }
+    if !capabilities.unprivileged_icmp && !capabilities.raw_sockets {
+        println!("ICMP discovery falls back to TCP (see net.ipv4.ping_group_range)");
+    }
+    Ok(())
+}
+
 fn handle_version() {
     println!("{} version {}", nrmap::NAME, nrmap::VERSION);
     println!("High-performance network scanner written in Rust");
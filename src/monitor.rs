@@ -0,0 +1,241 @@
+//! Continuous monitoring
+//!
+//! `nrmap monitor` re-scans a fixed target set at an interval. It keeps a
+//! model of what each host is expected to expose and emits an event only
+//! when that changes. A change must be seen in several consecutive rounds
+//! before it is reported, so a port that flaps for one round is not
+//! reported at all.
+
+use crate::error::ScanResult;
+use crate::notify::Notifier;
+use crate::report::diff::{AssetChange, AssetSnapshot};
+use crate::scanner::{CompleteScanResult, Scanner};
+use crate::schedule::ScanPlan;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Expected state of the monitored hosts, with changes waiting for
+/// confirmation
+#[derive(Debug, Clone)]
+pub struct ChangeTracker {
+    /// Consecutive rounds a change must be observed in
+    confirm_after: u32,
+    expected: Option<AssetSnapshot>,
+    /// Changes seen in the latest rounds, by the number of rounds in a row
+    pending: HashMap<AssetChange, u32>,
+}
+
+impl ChangeTracker {
+    /// Report changes once they were observed `confirm_after` rounds in a row
+    /// (1 reports every change immediately)
+    pub fn new(confirm_after: u32) -> Self {
+        Self {
+            confirm_after: confirm_after.max(1),
+            expected: None,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// What the hosts are expected to expose; `None` before the first round
+    pub fn expected(&self) -> Option<&AssetSnapshot> {
+        self.expected.as_ref()
+    }
+
+    /// Changes observed but not yet confirmed, with their round counts
+    pub fn pending(&self) -> impl Iterator<Item = (&AssetChange, u32)> {
+        self.pending.iter().map(|(change, rounds)| (change, *rounds))
+    }
+
+    /// Record one round of results and return the changes it confirmed
+    ///
+    /// The first round sets the expected state and confirms nothing. A
+    /// pending change that is not seen again is dropped.
+    pub fn observe(&mut self, results: &[CompleteScanResult]) -> Vec<AssetChange> {
+        let observed = AssetSnapshot::from_results(results);
+        let Some(ref mut expected) = self.expected else {
+            self.expected = Some(observed);
+            return Vec::new();
+        };
+
+        let changes = expected.diff(&observed).changes;
+        let mut pending = HashMap::with_capacity(changes.len());
+        let mut confirmed = Vec::new();
+        for change in changes {
+            let rounds = self.pending.get(&change).copied().unwrap_or(0) + 1;
+            if rounds >= self.confirm_after {
+                confirmed.push(change);
+            } else {
+                debug!("Change pending ({}/{} rounds): {}", rounds, self.confirm_after, change);
+                pending.insert(change, rounds);
+            }
+        }
+        for change in &confirmed {
+            expected.apply(change);
+        }
+        self.pending = pending;
+        confirmed
+    }
+}
+
+/// Emitted for every confirmed change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorEvent {
+    /// Always `asset_changed`
+    pub event: String,
+    pub at: DateTime<Utc>,
+    /// Scan round that confirmed the change, counting from 1
+    pub round: u64,
+    #[serde(flatten)]
+    pub change: AssetChange,
+}
+
+/// Re-scans a target set and reports confirmed changes
+pub struct Monitor {
+    scanner: Scanner,
+    plan: ScanPlan,
+    interval: Duration,
+    tracker: ChangeTracker,
+    notifier: Notifier,
+}
+
+impl Monitor {
+    /// Scan `plan` every `interval`; cancelling the scanner stops it
+    pub fn new(scanner: Scanner, plan: ScanPlan, interval: Duration, notifier: Notifier) -> Self {
+        Self {
+            scanner,
+            plan,
+            interval,
+            tracker: ChangeTracker::new(2),
+            notifier,
+        }
+    }
+
+    /// Rounds a change must persist before it is reported (default 2)
+    pub fn with_confirm_after(mut self, rounds: u32) -> Self {
+        self.tracker = ChangeTracker::new(rounds);
+        self
+    }
+
+    /// Scan, report and wait, until cancelled
+    ///
+    /// A failed round is logged and skipped; it does not count towards or
+    /// against pending changes.
+    pub async fn run(&mut self) -> ScanResult<()> {
+        let token = self.scanner.cancellation_token();
+        let mut round = 0u64;
+        loop {
+            round += 1;
+            if let Err(e) = self.round(round).await {
+                error!("Monitoring round {} failed: {}", round, e);
+            }
+            tokio::select! {
+                _ = token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(self.interval) => {}
+            }
+        }
+    }
+
+    /// Run one round and send an event per confirmed change
+    pub async fn round(&mut self, round: u64) -> ScanResult<Vec<AssetChange>> {
+        let results = self
+            .scanner
+            .scan_multiple(self.plan.targets.clone(), self.plan.ports.clone(), self.plan.scan_types.clone())
+            .await?;
+        if self.scanner.is_cancelled() {
+            return Ok(Vec::new());
+        }
+
+        let baseline = self.tracker.expected().is_none();
+        let changes = self.tracker.observe(&results);
+        if baseline {
+            let expected = self.tracker.expected().map_or(0, |snapshot| snapshot.hosts().count());
+            info!("Monitoring baseline: {} of {} hosts responsive", expected, self.plan.targets.len());
+        } else {
+            info!(
+                "Monitoring round {}: {} changes confirmed, {} pending",
+                round,
+                changes.len(),
+                self.tracker.pending().count()
+            );
+        }
+
+        let at = Utc::now();
+        for change in &changes {
+            let event = MonitorEvent {
+                event: "asset_changed".to_string(),
+                at,
+                round,
+                change: change.clone(),
+            };
+            if let Err(e) = self.notifier.notify(&event).await {
+                warn!("{}", e);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use std::net::IpAddr;
+
+    fn round(open: &[u16]) -> Vec<CompleteScanResult> {
+        let target = IpAddr::from([10, 0, 0, 1]);
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, Duration::ZERO);
+        result.tcp_results = open
+            .iter()
+            .map(|&port| TcpConnectResult::open(target, port))
+            .collect();
+        vec![result]
+    }
+
+    #[test]
+    fn test_hysteresis_suppresses_flapping() {
+        let mut tracker = ChangeTracker::new(2);
+        assert!(tracker.observe(&round(&[22])).is_empty());
+
+        // 80 shows up for one round only
+        assert!(tracker.observe(&round(&[22, 80])).is_empty());
+        assert_eq!(tracker.pending().count(), 1);
+        assert!(tracker.observe(&round(&[22])).is_empty());
+        assert_eq!(tracker.pending().count(), 0);
+
+        // 443 stays open: confirmed on its second round, then reported once
+        assert!(tracker.observe(&round(&[22, 443])).is_empty());
+        let confirmed = tracker.observe(&round(&[22, 443]));
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].to_string(), "10.0.0.1 opened 443/tcp https");
+        assert!(tracker.observe(&round(&[22, 443])).is_empty());
+
+        let ports: Vec<u16> = tracker
+            .expected()
+            .unwrap()
+            .open_ports(IpAddr::from([10, 0, 0, 1]))
+            .map(|(port, _, _)| port)
+            .collect();
+        assert_eq!(ports, vec![22, 443]);
+    }
+
+    #[test]
+    fn test_event_format() {
+        let mut tracker = ChangeTracker::new(1);
+        tracker.observe(&round(&[22]));
+        let change = tracker.observe(&[]).remove(0);
+        let event = MonitorEvent {
+            event: "asset_changed".to_string(),
+            at: Utc::now(),
+            round: 2,
+            change,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["change"], "host_down");
+        assert_eq!(json["target"], "10.0.0.1");
+        assert_eq!(json["round"], 2);
+    }
+}
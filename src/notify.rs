@@ -0,0 +1,174 @@
+//! Change notifications
+//!
+//! Long-running modes report what changed between scans as JSON events. An
+//! event can go to any mix of channels: stdout (one JSON object per line),
+//! webhooks that receive it as an HTTP POST, and a command that reads it on
+//! stdin. A channel that fails is logged and does not stop the others.
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+/// Where change events are sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// Print every event as one line of JSON on stdout
+    pub stdout: bool,
+    /// URLs every event is POSTed to as JSON
+    pub webhooks: Vec<String>,
+    /// Shell command run for every event, with the event JSON on stdin
+    pub command: Option<String>,
+    /// Seconds a webhook may take to answer
+    pub timeout_secs: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            stdout: true,
+            webhooks: Vec::new(),
+            command: None,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// Sends events to the configured channels
+#[derive(Clone)]
+pub struct Notifier {
+    config: NotifyConfig,
+    agent: ureq::Agent,
+}
+
+impl Notifier {
+    /// Create a notifier for `config`
+    pub fn new(config: NotifyConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build();
+        Self { config, agent }
+    }
+
+    /// Channel configuration
+    pub fn config(&self) -> &NotifyConfig {
+        &self.config
+    }
+
+    /// Send `event` to every channel
+    ///
+    /// Every channel is tried; the error lists the ones that failed.
+    pub async fn notify<T: Serialize>(&self, event: &T) -> ScanResult<()> {
+        let json = serde_json::to_string(event)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to serialize event: {}", e)))?;
+        let mut failed = Vec::new();
+
+        if self.config.stdout {
+            println!("{}", json);
+        }
+        for url in &self.config.webhooks {
+            if let Err(e) = self.post(url, &json).await {
+                warn!("Webhook {} failed: {}", url, e);
+                failed.push(url.clone());
+            }
+        }
+        if let Some(ref command) = self.config.command {
+            if let Err(e) = run_command(command, &json).await {
+                warn!("Notification command failed: {}", e);
+                failed.push(command.clone());
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(ScanError::network(format!("Notification failed for {}", failed.join(", "))))
+        }
+    }
+
+    async fn post(&self, url: &str, json: &str) -> ScanResult<()> {
+        let request = self.agent.post(url).set("Content-Type", "application/json");
+        let body = json.to_string();
+        let status = tokio::task::spawn_blocking(move || {
+            request
+                .send_string(&body)
+                .map(|response| response.status())
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| ScanError::scanner_error(format!("Webhook task failed: {}", e)))?
+        .map_err(ScanError::network)?;
+        debug!("Webhook {} answered {}", url, status);
+        Ok(())
+    }
+}
+
+async fn run_command(command: &str, json: &str) -> ScanResult<()> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| ScanError::scanner_error(format!("Failed to run '{}': {}", command, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may close stdin early
+        let _ = stdin.write_all(json.as_bytes()).await;
+        let _ = stdin.write_all(b"\n").await;
+    }
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| ScanError::scanner_error(format!("Failed to wait for '{}': {}", command, e)))?;
+    if !status.success() {
+        return Err(ScanError::scanner_error(format!("'{}' exited with {}", command, status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[tokio::test]
+    async fn test_notify_webhook_and_command() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"changes\":1}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("event.json");
+        let notifier = Notifier::new(NotifyConfig {
+            stdout: false,
+            webhooks: vec![url],
+            command: Some(format!("cat > {}", out.display())),
+            timeout_secs: 5,
+        });
+        notifier.notify(&serde_json::json!({ "changes": 1 })).await.unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request.to_lowercase().contains("content-type: application/json"));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"changes\":1}\n");
+
+        let failing = Notifier::new(NotifyConfig {
+            stdout: false,
+            command: Some("exit 3".to_string()),
+            ..Default::default()
+        });
+        assert!(failing.notify(&serde_json::json!({})).await.is_err());
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! This module implements Nmap-style active probes for comprehensive OS detection.
 //! It includes TCP T1-T7 probes, UDP U1 probe, ICMP IE probe, and SEQ/ECN analysis.
+//!
+//! Probes can run under a [`ProbeBudget`], which orders them by how much they
+//! tell apart operating systems, skips probes whose outcome earlier replies
+//! already imply, and stops as soon as the best match is confident enough.
 
 use crate::error::ScanResult;
 use std::net::IpAddr;
@@ -138,6 +142,150 @@ pub struct ActiveProbeResults {
     pub seq_probes: Vec<SeqProbeResponse>,
     pub ecn_probe: Option<EcnProbeResponse>,
     pub total_time_ms: u64,
+    /// Probe packets sent
+    #[serde(default)]
+    pub packets_sent: usize,
+    /// Probes not sent because of the budget, redundancy or an early stop
+    #[serde(default)]
+    pub skipped_probes: Vec<ActiveProbe>,
+}
+
+impl ActiveProbeResults {
+    fn empty(target: IpAddr) -> Self {
+        Self {
+            target,
+            tcp_probes: Vec::new(),
+            udp_probe: None,
+            icmp_probe: None,
+            seq_probes: Vec::new(),
+            ecn_probe: None,
+            total_time_ms: 0,
+            packets_sent: 0,
+            skipped_probes: Vec::new(),
+        }
+    }
+
+    fn tcp_probe(&self, probe_type: TcpProbeType) -> Option<&TcpProbeResponse> {
+        self.tcp_probes.iter().find(|p| p.probe_type == probe_type)
+    }
+}
+
+/// A single entry of the active probe library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActiveProbe {
+    T1,
+    T2,
+    T3,
+    T4,
+    T5,
+    T6,
+    T7,
+    U1,
+    Ie,
+    Seq,
+    Ecn,
+}
+
+impl ActiveProbe {
+    /// Nmap's order, used when every probe is sent anyway
+    pub const NMAP_ORDER: [ActiveProbe; 11] = [
+        ActiveProbe::T1,
+        ActiveProbe::T2,
+        ActiveProbe::T3,
+        ActiveProbe::T4,
+        ActiveProbe::T5,
+        ActiveProbe::T6,
+        ActiveProbe::T7,
+        ActiveProbe::U1,
+        ActiveProbe::Ie,
+        ActiveProbe::Seq,
+        ActiveProbe::Ecn,
+    ];
+
+    /// Most discriminating probes first: the SYN-ACK (T1) carries the option
+    /// layout, closed-port RSTs and ICMP expose TTL/DF defaults, and the
+    /// remaining open-port probes mostly refine an existing guess
+    pub const DEFAULT_PRIORITY: [ActiveProbe; 11] = [
+        ActiveProbe::T1,
+        ActiveProbe::T5,
+        ActiveProbe::Ie,
+        ActiveProbe::Seq,
+        ActiveProbe::T7,
+        ActiveProbe::U1,
+        ActiveProbe::Ecn,
+        ActiveProbe::T2,
+        ActiveProbe::T3,
+        ActiveProbe::T4,
+        ActiveProbe::T6,
+    ];
+
+    /// Packets this probe sends
+    pub fn packet_cost(self) -> usize {
+        match self {
+            ActiveProbe::Seq => SEQ_PROBE_COUNT,
+            _ => 1,
+        }
+    }
+
+    /// Whether earlier replies already imply this probe's outcome
+    ///
+    /// If the open port ignored T1 the other open-port probes will be
+    /// ignored too, and likewise for the closed port after T5.
+    fn is_redundant(self, results: &ActiveProbeResults) -> bool {
+        let unanswered = |probe_type| results.tcp_probe(probe_type).is_some_and(|p| !p.responded);
+        match self {
+            ActiveProbe::T2 | ActiveProbe::T3 | ActiveProbe::T4 | ActiveProbe::Seq | ActiveProbe::Ecn => {
+                unanswered(TcpProbeType::T1)
+            }
+            ActiveProbe::T6 | ActiveProbe::T7 => unanswered(TcpProbeType::T5),
+            _ => false,
+        }
+    }
+}
+
+/// Number of SYNs sent by the SEQ probe
+const SEQ_PROBE_COUNT: usize = 6;
+
+/// Limits on active probing of a single host (see `OsFingerprintConfig`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProbeBudget {
+    /// Maximum probe packets per host (0 for no limit)
+    pub max_packets: usize,
+    /// Stop once the best OS match reaches this confidence
+    pub stop_confidence: Option<f64>,
+    /// Skip probes whose outcome earlier replies already imply
+    pub skip_redundant: bool,
+    /// Order in which probes are sent
+    pub priority: Vec<ActiveProbe>,
+}
+
+impl ProbeBudget {
+    /// Send every probe in Nmap's order
+    pub fn unlimited() -> Self {
+        Self {
+            max_packets: 0,
+            stop_confidence: None,
+            skip_redundant: false,
+            priority: ActiveProbe::NMAP_ORDER.to_vec(),
+        }
+    }
+
+    fn allows(&self, packets_sent: usize, probe: ActiveProbe) -> bool {
+        self.max_packets == 0 || packets_sent + probe.packet_cost() <= self.max_packets
+    }
+}
+
+impl Default for ProbeBudget {
+    fn default() -> Self {
+        Self {
+            max_packets: 0,
+            stop_confidence: Some(0.75),
+            skip_redundant: true,
+            priority: ActiveProbe::DEFAULT_PRIORITY.to_vec(),
+        }
+    }
 }
 
 /// Active probe library
@@ -164,41 +312,95 @@ impl ActiveProbeLibrary {
         open_port: u16,
         closed_port: u16,
     ) -> ScanResult<ActiveProbeResults> {
+        self.probe_with_budget(target, open_port, closed_port, &ProbeBudget::unlimited(), |_| 0.0)
+            .await
+    }
+
+    /// Perform active probes within a budget
+    ///
+    /// `confidence` scores the results gathered so far (typically the best
+    /// database match) and is consulted before each probe to decide whether
+    /// to stop early.
+    pub async fn probe_with_budget<F>(
+        &self,
+        target: IpAddr,
+        open_port: u16,
+        closed_port: u16,
+        budget: &ProbeBudget,
+        mut confidence: F,
+    ) -> ScanResult<ActiveProbeResults>
+    where
+        F: FnMut(&ActiveProbeResults) -> f64,
+    {
         info!("Starting active probe library scan on {}", target);
         let start_time = SystemTime::now();
+        let mut results = ActiveProbeResults::empty(target);
+        
+        for (index, &probe) in budget.priority.iter().enumerate() {
+            if let Some(threshold) = budget.stop_confidence {
+                let score = confidence(&results);
+                if score >= threshold {
+                    info!(
+                        "Match confidence {:.2} reached for {}, skipping {} remaining probes",
+                        score,
+                        target,
+                        budget.priority.len() - index
+                    );
+                    results.skipped_probes.extend_from_slice(&budget.priority[index..]);
+                    break;
+                }
+            }
+
+            if budget.skip_redundant && probe.is_redundant(&results) {
+                debug!("Skipping redundant {:?} probe to {}", probe, target);
+                results.skipped_probes.push(probe);
+                continue;
+            }
+            if !budget.allows(results.packets_sent, probe) {
+                debug!("Probe budget exhausted, skipping {:?} to {}", probe, target);
+                results.skipped_probes.push(probe);
+                continue;
+            }
+
+            self.run_probe(probe, target, open_port, closed_port, &mut results).await?;
+            results.packets_sent += probe.packet_cost();
+        }
         
-        // TCP T1-T7 probes
-        let tcp_probes = self.run_tcp_probes(target, open_port, closed_port).await?;
-        
-        // UDP U1 probe
-        let udp_probe = self.run_udp_probe(target, closed_port).await.ok();
-        
-        // ICMP IE probe
-        let icmp_probe = self.run_icmp_probe(target).await.ok();
-        
-        // SEQ probes (6 probes for ISN analysis)
-        let seq_probes = self.run_seq_probes(target, open_port, 6).await?;
-        
-        // ECN probe
-        let ecn_probe = self.run_ecn_probe(target, open_port).await.ok();
-        
-        let total_time_ms = start_time.elapsed()
+        results.total_time_ms = start_time.elapsed()
             .unwrap_or(Duration::from_secs(0))
             .as_millis() as u64;
-        
-        Ok(ActiveProbeResults {
-            target,
-            tcp_probes,
-            udp_probe,
-            icmp_probe,
-            seq_probes,
-            ecn_probe,
-            total_time_ms,
-        })
+        Ok(results)
+    }
+
+    /// Run one probe and store its response
+    async fn run_probe(
+        &self,
+        probe: ActiveProbe,
+        target: IpAddr,
+        open_port: u16,
+        closed_port: u16,
+        results: &mut ActiveProbeResults,
+    ) -> ScanResult<()> {
+        match probe {
+            ActiveProbe::T1 => results.tcp_probes.push(self.run_t1_probe(target, open_port).await?),
+            ActiveProbe::T2 => results.tcp_probes.push(self.run_t2_probe(target, open_port).await?),
+            ActiveProbe::T3 => results.tcp_probes.push(self.run_t3_probe(target, open_port).await?),
+            ActiveProbe::T4 => results.tcp_probes.push(self.run_t4_probe(target, open_port).await?),
+            ActiveProbe::T5 => results.tcp_probes.push(self.run_t5_probe(target, closed_port).await?),
+            ActiveProbe::T6 => results.tcp_probes.push(self.run_t6_probe(target, closed_port).await?),
+            ActiveProbe::T7 => results.tcp_probes.push(self.run_t7_probe(target, closed_port).await?),
+            ActiveProbe::U1 => results.udp_probe = self.run_udp_probe(target, closed_port).await.ok(),
+            ActiveProbe::Ie => results.icmp_probe = self.run_icmp_probe(target).await.ok(),
+            ActiveProbe::Seq => {
+                results.seq_probes = self.run_seq_probes(target, open_port, SEQ_PROBE_COUNT).await?
+            }
+            ActiveProbe::Ecn => results.ecn_probe = self.run_ecn_probe(target, open_port).await.ok(),
+        }
+        Ok(())
     }
 
     /// Run TCP T1-T7 probes
-    async fn run_tcp_probes(
+    pub async fn run_tcp_probes(
         &self,
         target: IpAddr,
         open_port: u16,
@@ -871,6 +1073,67 @@ mod tests {
         assert!(probe.ecn_supported);
     }
 
+    #[tokio::test]
+    async fn test_budget_stops_early() {
+        let library = ActiveProbeLibrary::new(3000);
+        let target: IpAddr = "127.0.0.1".parse().unwrap();
+
+        // Confident as soon as T1 and T5 have answered
+        let results = library
+            .probe_with_budget(target, 80, 81, &ProbeBudget::default(), |r| r.tcp_probes.len() as f64 * 0.4)
+            .await
+            .unwrap();
+        let sent: Vec<TcpProbeType> = results.tcp_probes.iter().map(|p| p.probe_type).collect();
+        assert_eq!(sent, vec![TcpProbeType::T1, TcpProbeType::T5]);
+        assert_eq!(results.packets_sent, 2);
+        assert_eq!(results.skipped_probes.len(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_budget_packet_limit() {
+        let library = ActiveProbeLibrary::new(3000);
+        let target: IpAddr = "127.0.0.1".parse().unwrap();
+        let budget = ProbeBudget {
+            max_packets: 4,
+            stop_confidence: None,
+            ..Default::default()
+        };
+
+        // T1, T5 and IE fit; SEQ costs 6 packets and is skipped, but the
+        // cheaper T7 after it still runs
+        let results = library.probe_with_budget(target, 80, 81, &budget, |_| 0.0).await.unwrap();
+        assert_eq!(results.packets_sent, 4);
+        assert!(results.seq_probes.is_empty());
+        assert!(results.skipped_probes.contains(&ActiveProbe::Seq));
+        assert!(results.tcp_probe(TcpProbeType::T7).is_some());
+        assert!(results.udp_probe.is_none());
+    }
+
+    #[test]
+    fn test_redundant_probes() {
+        let target: IpAddr = "127.0.0.1".parse().unwrap();
+        let mut results = ActiveProbeResults::empty(target);
+        assert!(!ActiveProbe::T2.is_redundant(&results));
+
+        results.tcp_probes.push(TcpProbeResponse {
+            probe_type: TcpProbeType::T1,
+            responded: false,
+            flags: None,
+            window_size: None,
+            seq: None,
+            ack: None,
+            options: vec![],
+            ttl: None,
+            ip_id: None,
+            df_flag: false,
+            response_time_us: 0,
+        });
+        assert!(ActiveProbe::T2.is_redundant(&results));
+        assert!(ActiveProbe::Seq.is_redundant(&results));
+        assert!(!ActiveProbe::T6.is_redundant(&results));
+        assert!(!ActiveProbe::Ie.is_redundant(&results));
+    }
+
     #[tokio::test]
     async fn test_probe_all() {
         let library = ActiveProbeLibrary::new(3000);
@@ -0,0 +1,200 @@
+//! OS detection result cache
+//!
+//! Fingerprinting a host costs dozens of probes, and repeated scans of the
+//! same estate mostly find the same operating systems. The cache keeps
+//! [`OsMatchResult`]s per target IP and evidence hash in memory and,
+//! optionally, in a JSON file shared between runs. The evidence hash covers
+//! what a result was derived from (probe ports and options, the signature
+//! database, matching settings), so changing any of them misses the cache
+//! instead of returning stale matches. Entries expire after a TTL.
+
+use super::learning::fnv1a_64;
+use super::matcher::OsMatchResult;
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// File name of the on-disk cache in the user config directory
+pub const OS_CACHE_FILE: &str = "os-cache.json";
+
+/// Default on-disk cache location, next to the user config file
+pub fn default_cache_path() -> Option<PathBuf> {
+    Some(crate::config::user_config_path()?.with_file_name(OS_CACHE_FILE))
+}
+
+/// Hash of the inputs an OS detection result was derived from
+pub fn evidence_hash(parts: &[&str]) -> u64 {
+    fnv1a_64(parts.join("\u{1f}").as_bytes())
+}
+
+/// One cached detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    target: IpAddr,
+    /// Evidence hash as hex, since JSON numbers lose 64-bit precision
+    evidence: String,
+    /// Unix time the result was stored
+    stored_at: u64,
+    matches: Vec<OsMatchResult>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Cache of OS matches keyed by target and evidence hash
+#[derive(Debug)]
+pub struct OsResultCache {
+    ttl: Duration,
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<(IpAddr, u64), CacheEntry>>,
+}
+
+impl OsResultCache {
+    /// In-memory cache
+    pub fn in_memory(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            path: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cache persisted to `path`, loading its unexpired entries
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration) -> ScanResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut cache = Self::in_memory(ttl);
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let stored: Vec<CacheEntry> = serde_json::from_str(&content).map_err(|e| ScanError::ScannerError {
+                message: format!("{}: {}", path.display(), e),
+            })?;
+            let live: HashMap<(IpAddr, u64), CacheEntry> = stored
+                .into_iter()
+                .filter(|entry| !cache.expired(entry))
+                .filter_map(|entry| {
+                    let evidence = u64::from_str_radix(&entry.evidence, 16).ok()?;
+                    Some(((entry.target, evidence), entry))
+                })
+                .collect();
+            debug!("Loaded {} cached OS detections from {:?}", live.len(), path);
+            cache.entries = Mutex::new(live);
+        }
+        cache.path = Some(path);
+        Ok(cache)
+    }
+
+    fn expired(&self, entry: &CacheEntry) -> bool {
+        now_secs() >= entry.stored_at.saturating_add(self.ttl.as_secs())
+    }
+
+    /// Unexpired matches for a target and evidence hash
+    pub fn get(&self, target: IpAddr, evidence: u64) -> Option<Vec<OsMatchResult>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.get(&(target, evidence))?;
+        if self.expired(entry) {
+            entries.remove(&(target, evidence));
+            return None;
+        }
+        Some(entry.matches.clone())
+    }
+
+    /// Store matches, writing through to the cache file if there is one
+    pub fn insert(&self, target: IpAddr, evidence: u64, matches: Vec<OsMatchResult>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| !self.expired(entry));
+        entries.insert(
+            (target, evidence),
+            CacheEntry {
+                target,
+                evidence: format!("{:016x}", evidence),
+                stored_at: now_secs(),
+                matches,
+            },
+        );
+        if let Some(ref path) = self.path {
+            if let Err(e) = Self::save(path, entries.values()) {
+                warn!("Failed to write OS detection cache {:?}: {}", path, e);
+            }
+        }
+    }
+
+    fn save<'a>(path: &Path, entries: impl Iterator<Item = &'a CacheEntry>) -> ScanResult<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let entries: Vec<&CacheEntry> = entries.collect();
+        let json = serde_json::to_string(&entries)
+            .map_err(|e| ScanError::ScannerError { message: format!("JSON serialization failed: {}", e) })?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Number of cached targets and evidence hashes, expired ones included
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os_fingerprint::fingerprint_db::OsFamily;
+    use crate::os_fingerprint::matcher::MatchConfidence;
+
+    fn linux() -> Vec<OsMatchResult> {
+        vec![OsMatchResult {
+            os_name: "Linux 2.6+".to_string(),
+            os_version: None,
+            os_family: OsFamily::Linux,
+            confidence: MatchConfidence::High,
+            confidence_score: 0.8,
+            matching_features: vec![],
+        }]
+    }
+
+    #[test]
+    fn test_keyed_by_target_and_evidence() {
+        let cache = OsResultCache::in_memory(Duration::from_secs(60));
+        let target: IpAddr = "10.0.0.1".parse().unwrap();
+        let evidence = evidence_hash(&["80", "81"]);
+        cache.insert(target, evidence, linux());
+
+        assert_eq!(cache.get(target, evidence).unwrap()[0].os_name, "Linux 2.6+");
+        assert!(cache.get(target, evidence_hash(&["80", "82"])).is_none());
+        assert!(cache.get("10.0.0.2".parse().unwrap(), evidence).is_none());
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let cache = OsResultCache::in_memory(Duration::ZERO);
+        let target: IpAddr = "10.0.0.1".parse().unwrap();
+        cache.insert(target, 1, linux());
+        assert!(cache.get(target, 1).is_none());
+    }
+
+    #[test]
+    fn test_persisted_between_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(OS_CACHE_FILE);
+        let target: IpAddr = "2001:db8::1".parse().unwrap();
+
+        let cache = OsResultCache::open(&path, Duration::from_secs(3600)).unwrap();
+        cache.insert(target, u64::MAX, linux());
+
+        let reopened = OsResultCache::open(&path, Duration::from_secs(3600)).unwrap();
+        assert_eq!(reopened.get(target, u64::MAX).unwrap().len(), 1);
+        let expired = OsResultCache::open(&path, Duration::ZERO).unwrap();
+        assert!(expired.is_empty());
+    }
+}
@@ -3,13 +3,34 @@
 //! This module implements TCP timestamp-based clock skew analysis for OS fingerprinting.
 //! Clock skew analysis examines the rate at which a remote system's TCP timestamp counter
 //! increments, which can reveal information about the underlying operating system and hardware.
+//!
+//! Timestamps are read from a plain TCP connection to an open port: the kernel
+//! makes the connection (advertising TCP timestamps by default) and keeps it
+//! alive, and the packet capture subsystem picks the target's SYN-ACK and
+//! keepalive ACKs off the wire, matched on the local port. Capturing requires
+//! raw socket privileges.
 
 use crate::error::{ScanResult, ScanError};
-use std::net::IpAddr;
+#[cfg(not(feature = "no-net"))]
+use crate::packet::{CaptureConfig, CaptureFilter, ConnectionKey, PacketCapture};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "no-net"))]
+use socket2::{SockRef, TcpKeepalive};
+#[cfg(not(feature = "no-net"))]
+use tokio::net::TcpSocket;
 use tracing::{debug, info, warn};
 
+/// Timestamp clock rates seen in the wild (Hz)
+///
+/// 1 MHz covers stacks with microsecond timestamps (e.g. Linux `tcp_ts_usec`).
+const NOMINAL_CLOCK_RATES_HZ: [f64; 7] = [2.0, 10.0, 64.0, 100.0, 250.0, 1000.0, 1_000_000.0];
+
+/// Relative distance from the nearest nominal rate beyond which a measured
+/// clock is considered implausible
+const NOMINAL_RATE_TOLERANCE: f64 = 0.1;
+
 /// Represents a TCP timestamp measurement
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimestampMeasurement {
@@ -38,6 +59,36 @@ pub struct ClockSkewAnalysis {
     pub os_hints: Vec<String>,
     /// Confidence score (0.0 to 1.0)
     pub confidence: f64,
+    /// Evidence that timestamps were rewritten on the path (NAT, load
+    /// balancer, scrubbing firewall)
+    #[serde(default)]
+    pub timestamp_anomaly: Option<TimestampAnomaly>,
+}
+
+/// Signs that the timestamps seen do not come from a single, untouched clock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimestampAnomaly {
+    /// Every reply carried the same value: the clock is frozen or replaced
+    Constant,
+    /// Values went backwards between replies, typical of several hosts (or
+    /// per-connection offsets) behind one address
+    NonMonotonic { backward_steps: usize },
+    /// The clock ticks at a rate no known stack uses
+    ImplausibleRate { clock_frequency_hz: f64 },
+}
+
+impl std::fmt::Display for TimestampAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimestampAnomaly::Constant => write!(f, "constant timestamps"),
+            TimestampAnomaly::NonMonotonic { backward_steps } => {
+                write!(f, "timestamps went backwards {} time(s)", backward_steps)
+            }
+            TimestampAnomaly::ImplausibleRate { clock_frequency_hz } => {
+                write!(f, "implausible clock rate of {:.1} Hz", clock_frequency_hz)
+            }
+        }
+    }
 }
 
 /// Clock skew analyzer
@@ -46,6 +97,13 @@ pub struct ClockSkewAnalyzer {
     min_samples: usize,
     /// Maximum time window for collecting samples (seconds)
     max_collection_time: u64,
+    /// Timeout for the connect and for each reply (milliseconds)
+    probe_timeout_ms: u64,
+    /// Keepalive interval between samples (whole seconds on Linux)
+    sample_interval: Duration,
+    /// Capture settings used to read the SYN-ACKs
+    #[cfg(not(feature = "no-net"))]
+    capture_config: CaptureConfig,
 }
 
 impl ClockSkewAnalyzer {
@@ -54,13 +112,41 @@ impl ClockSkewAnalyzer {
         Self {
             min_samples: 10,
             max_collection_time: 30,
+            probe_timeout_ms: 1000,
+            sample_interval: Duration::from_secs(1),
+            #[cfg(not(feature = "no-net"))]
+            capture_config: CaptureConfig::default(),
         }
     }
 
+    /// Use specific capture settings (e.g. a fixed interface)
+    #[cfg(not(feature = "no-net"))]
+    pub fn with_capture_config(mut self, config: CaptureConfig) -> Self {
+        self.capture_config = config;
+        self
+    }
+
+    /// Set the timeout for the connect and for each reply
+    pub fn with_probe_timeout(mut self, timeout_ms: u64) -> Self {
+        self.probe_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Set the interval between samples
+    ///
+    /// Longer collections give a more precise skew estimate.
+    pub fn with_sample_interval(mut self, interval: Duration) -> Self {
+        self.sample_interval = interval;
+        self
+    }
+
     /// Collects TCP timestamp deltas from a target
     ///
-    /// This function sends multiple probes to the target and collects TCP timestamp
-    /// values to build a dataset for skew analysis.
+    /// All samples come from a single connection, since current Linux (and
+    /// some middleboxes) randomize the timestamp offset per connection. The
+    /// first sample is the SYN-ACK; after that TCP keepalive probes are sent
+    /// every `sample_interval` and the timestamp of each ACK is recorded.
+    #[cfg(not(feature = "no-net"))]
     pub async fn collect_timestamps(
         &self,
         target: IpAddr,
@@ -68,9 +154,42 @@ impl ClockSkewAnalyzer {
         num_samples: usize,
     ) -> ScanResult<Vec<TimestampMeasurement>> {
         info!("Collecting TCP timestamps from {}:{}", target, port);
+
+        let mut capture = PacketCapture::new(self.capture_config.clone(), CaptureFilter::new([target]));
+        capture.start()?;
+
+        let (socket, unspecified) = match target {
+            IpAddr::V4(_) => (TcpSocket::new_v4()?, IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpAddr::V6(_) => (TcpSocket::new_v6()?, IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        };
+        // Bind first so the replies can be matched on the local port
+        socket.bind(SocketAddr::new(unspecified, 0))?;
+        let key = ConnectionKey::new(target, port, socket.local_addr()?.port());
+        let mut receiver = capture.register_connection(key);
+
+        let stream = match tokio::time::timeout(
+            Duration::from_millis(self.probe_timeout_ms),
+            socket.connect(SocketAddr::new(target, port)),
+        )
+        .await
+        {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                return Err(ScanError::network(format!(
+                    "Clock skew analysis needs an open port, {}:{} failed: {}",
+                    target, port, e
+                )))
+            }
+            Err(_) => return Err(ScanError::timeout(self.probe_timeout_ms)),
+        };
+        let keepalive = TcpKeepalive::new()
+            .with_time(self.sample_interval)
+            .with_interval(self.sample_interval);
+        SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
         
         let mut measurements = Vec::new();
         let start_time = SystemTime::now();
+        let wait_ms = self.sample_interval.as_millis() as u64 * 2 + self.probe_timeout_ms;
         
         for i in 0..num_samples {
             // Check if we've exceeded the maximum collection time
@@ -81,21 +200,43 @@ impl ClockSkewAnalyzer {
                 }
             }
             
-            // In a real implementation, this would send a TCP SYN packet
-            // and extract the timestamp from the SYN-ACK response
-            match self.probe_and_extract_timestamp(target, port, i as u32).await {
-                Ok(measurement) => {
-                    debug!("Collected timestamp: {:?}", measurement);
-                    measurements.push(measurement);
-                }
-                Err(e) => {
-                    warn!("Failed to collect timestamp: {}", e);
+            let Some(packet) = capture.wait_for_connection(key, receiver, wait_ms).await else {
+                warn!("No reply from {}:{} after {} samples", target, port, i);
+                break;
+            };
+            let Some(tcp) = packet.tcp_info.as_ref() else {
+                break;
+            };
+            let Some((remote_timestamp, _echo)) = tcp.timestamp() else {
+                if i == 0 {
+                    return Err(ScanError::scanner_error(format!(
+                        "{}:{} does not send TCP timestamps",
+                        target, port
+                    )));
                 }
+                break;
+            };
+
+            let measurement = TimestampMeasurement {
+                remote_timestamp,
+                local_time_us: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_micros() as u64,
+                sequence: i as u32,
+            };
+            debug!("Collected timestamp: {:?}", measurement);
+            measurements.push(measurement);
+
+            if tcp.flags.fin || tcp.flags.rst {
+                warn!("{}:{} closed the connection after {} samples", target, port, i + 1);
+                break;
             }
-            
-            // Add a small delay between probes
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            receiver = capture.register_connection(key);
         }
+
+        drop(stream);
+        capture.stop();
         
         if measurements.len() < self.min_samples {
             return Err(ScanError::InsufficientData {
@@ -108,34 +249,6 @@ impl ClockSkewAnalyzer {
         Ok(measurements)
     }
 
-    /// Probes a target and extracts the TCP timestamp
-    ///
-    /// This is a placeholder implementation. In a real system, this would:
-    /// 1. Send a TCP SYN packet with timestamp option
-    /// 2. Receive the SYN-ACK response
-    /// 3. Extract the timestamp value from the TCP options
-    async fn probe_and_extract_timestamp(
-        &self,
-        _target: IpAddr,
-        _port: u16,
-        sequence: u32,
-    ) -> ScanResult<TimestampMeasurement> {
-        // Simulate timestamp collection (in real implementation, would use raw sockets)
-        let local_time_us = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_micros() as u64;
-        
-        // Simulate a remote timestamp (in real implementation, extracted from packet)
-        let remote_timestamp = (local_time_us / 1000) as u32 + sequence * 100;
-        
-        Ok(TimestampMeasurement {
-            remote_timestamp,
-            local_time_us,
-            sequence,
-        })
-    }
-
     /// Estimates the clock skew from collected measurements
     ///
     /// This uses a linear regression approach to estimate the clock skew.
@@ -154,32 +267,26 @@ impl ClockSkewAnalyzer {
         
         debug!("Estimating clock skew from {} measurements", measurements.len());
         
-        // Perform linear regression: y = mx + b
-        // where y = remote_timestamp, x = local_time
-        let n = measurements.len() as f64;
+        // Perform linear regression: y = mx + b, where y = remote ticks and
+        // x = local microseconds, both relative to the first measurement so the
+        // 32-bit timestamp can be unwrapped and precision is not lost
+        let points = relative_points(measurements);
+        let n = points.len() as f64;
         
-        let sum_x: f64 = measurements.iter().map(|m| m.local_time_us as f64).sum();
-        let sum_y: f64 = measurements.iter().map(|m| m.remote_timestamp as f64).sum();
-        let sum_xy: f64 = measurements.iter()
-            .map(|m| (m.local_time_us as f64) * (m.remote_timestamp as f64))
-            .sum();
-        let sum_xx: f64 = measurements.iter()
-            .map(|m| (m.local_time_us as f64).powi(2))
-            .sum();
+        let sum_x: f64 = points.iter().map(|(x, _)| x).sum();
+        let sum_y: f64 = points.iter().map(|(_, y)| y).sum();
+        let sum_xy: f64 = points.iter().map(|(x, y)| x * y).sum();
+        let sum_xx: f64 = points.iter().map(|(x, _)| x.powi(2)).sum();
         
-        // Calculate slope (m) - this represents the clock frequency ratio
+        // Calculate slope (m) - remote ticks per local microsecond
         let slope = (n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x.powi(2));
         
         // Calculate intercept (b)
         let intercept = (sum_y - slope * sum_x) / n;
         
         // Calculate standard deviation of residuals
-        let residuals: Vec<f64> = measurements.iter()
-            .map(|m| {
-                let predicted = slope * (m.local_time_us as f64) + intercept;
-                let actual = m.remote_timestamp as f64;
-                actual - predicted
-            })
+        let residuals: Vec<f64> = points.iter()
+            .map(|(x, y)| y - (slope * x + intercept))
             .collect();
         
         let mean_residual = residuals.iter().sum::<f64>() / n;
@@ -188,13 +295,12 @@ impl ClockSkewAnalyzer {
             .sum::<f64>() / n;
         let std_dev = variance.sqrt();
         
-        // Convert slope to parts per million (ppm)
-        // Expected slope is 1.0 (perfect clock sync), deviation indicates skew
-        let skew_ppm = (slope - 1.0) * 1_000_000.0;
-        
-        // Estimate clock frequency (assuming microsecond timestamps)
         let clock_frequency_hz = slope * 1_000_000.0;
         
+        // Skew is the deviation from the nearest nominal clock rate, in ppm
+        let nominal_hz = nearest_nominal_rate(clock_frequency_hz);
+        let skew_ppm = (clock_frequency_hz / nominal_hz - 1.0) * 1_000_000.0;
+        
         info!(
             "Clock skew estimate: {:.2} ppm, frequency: {:.2} Hz, std_dev: {:.2}",
             skew_ppm, clock_frequency_hz, std_dev
@@ -247,7 +353,38 @@ impl ClockSkewAnalyzer {
         hints
     }
 
+    /// Looks for signs that timestamps were rewritten between us and the target
+    ///
+    /// A NAT or load balancer in front of several hosts interleaves unrelated
+    /// clocks, and scrubbing middleboxes replace or freeze the value; either
+    /// way the skew no longer describes a single remote host.
+    pub fn detect_timestamp_rewriting(&self, measurements: &[TimestampMeasurement]) -> Option<TimestampAnomaly> {
+        let first = measurements.first()?;
+        if measurements.len() > 1 && measurements.iter().all(|m| m.remote_timestamp == first.remote_timestamp) {
+            return Some(TimestampAnomaly::Constant);
+        }
+
+        let backward_steps = measurements
+            .windows(2)
+            .filter(|pair| (pair[1].remote_timestamp.wrapping_sub(pair[0].remote_timestamp) as i32) < 0)
+            .count();
+        if backward_steps > 0 {
+            return Some(TimestampAnomaly::NonMonotonic { backward_steps });
+        }
+
+        if measurements.len() >= self.min_samples {
+            let (_, clock_frequency_hz, _) = self.estimate_skew(measurements).ok()?;
+            let nominal = nearest_nominal_rate(clock_frequency_hz);
+            if (clock_frequency_hz / nominal - 1.0).abs() > NOMINAL_RATE_TOLERANCE {
+                return Some(TimestampAnomaly::ImplausibleRate { clock_frequency_hz });
+            }
+        }
+
+        None
+    }
+
     /// Performs a complete clock skew analysis
+    #[cfg(not(feature = "no-net"))]
     pub async fn analyze(
         &self,
         target: IpAddr,
@@ -263,10 +400,18 @@ impl ClockSkewAnalyzer {
         let (skew_ppm, clock_frequency_hz, std_dev) = self.estimate_skew(&measurements)?;
         
         // Classify OS based on clock behavior
-        let os_hints = self.classify_os_by_clock(skew_ppm, clock_frequency_hz, std_dev);
+        let mut os_hints = self.classify_os_by_clock(skew_ppm, clock_frequency_hz, std_dev);
         
         // Calculate confidence based on standard deviation and sample count
-        let confidence = self.calculate_confidence(std_dev, measurements.len());
+        let mut confidence = self.calculate_confidence(std_dev, measurements.len());
+
+        // Rewritten timestamps say nothing reliable about the host itself
+        let timestamp_anomaly = self.detect_timestamp_rewriting(&measurements);
+        if let Some(ref anomaly) = timestamp_anomaly {
+            warn!("Timestamps from {} look rewritten: {}", target, anomaly);
+            os_hints.push(format!("NAT or middlebox rewriting TCP timestamps ({})", anomaly));
+            confidence *= 0.25;
+        }
         
         Ok(ClockSkewAnalysis {
             target,
@@ -276,6 +421,7 @@ impl ClockSkewAnalyzer {
             skew_std_dev: Some(std_dev),
             os_hints,
             confidence,
+            timestamp_anomaly,
         })
     }
 
@@ -292,6 +438,39 @@ impl ClockSkewAnalyzer {
     }
 }
 
+/// Convert measurements to (local µs, remote ticks) offsets from the first one
+///
+/// Remote deltas are taken as signed 32-bit differences so a counter wrap
+/// between samples does not look like a huge jump.
+fn relative_points(measurements: &[TimestampMeasurement]) -> Vec<(f64, f64)> {
+    let Some(first) = measurements.first() else {
+        return Vec::new();
+    };
+
+    let mut ticks = 0i64;
+    let mut previous = first.remote_timestamp;
+    measurements
+        .iter()
+        .map(|m| {
+            ticks += m.remote_timestamp.wrapping_sub(previous) as i32 as i64;
+            previous = m.remote_timestamp;
+            (m.local_time_us.saturating_sub(first.local_time_us) as f64, ticks as f64)
+        })
+        .collect()
+}
+
+/// Nominal clock rate closest (by ratio) to a measured one
+fn nearest_nominal_rate(clock_frequency_hz: f64) -> f64 {
+    NOMINAL_CLOCK_RATES_HZ
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let distance = |rate: f64| (clock_frequency_hz / rate).ln().abs();
+            distance(*a).total_cmp(&distance(*b))
+        })
+        .unwrap_or(1000.0)
+}
+
 impl Default for ClockSkewAnalyzer {
     fn default() -> Self {
         Self::new()
@@ -302,16 +481,37 @@ impl Default for ClockSkewAnalyzer {
 mod tests {
     use super::*;
 
+    fn measurements_from(remote: impl IntoIterator<Item = u32>) -> Vec<TimestampMeasurement> {
+        remote
+            .into_iter()
+            .enumerate()
+            .map(|(i, remote_timestamp)| TimestampMeasurement {
+                remote_timestamp,
+                local_time_us: 1_000_000 + i as u64 * 100_000,
+                sequence: i as u32,
+            })
+            .collect()
+    }
+
     #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
     async fn test_clock_skew_collection() {
-        let analyzer = ClockSkewAnalyzer::new();
+        let mut analyzer = ClockSkewAnalyzer::new();
+        analyzer.min_samples = 3;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
         let target: IpAddr = "127.0.0.1".parse().unwrap();
         
-        let result = analyzer.collect_timestamps(target, 80, 15).await;
-        assert!(result.is_ok());
+        let result = analyzer.collect_timestamps(target, port, 4).await;
+        if let Err(ScanError::PermissionDenied { .. }) = result {
+            // Reading SYN-ACKs needs capture privileges
+            return;
+        }
         
         let measurements = result.unwrap();
-        assert!(measurements.len() >= 10);
+        assert!(measurements.len() >= 3);
+        // The local kernel's clock must never run backwards
+        assert_eq!(analyzer.detect_timestamp_rewriting(&measurements), None);
     }
 
     #[test]
@@ -340,6 +540,42 @@ mod tests {
         assert!((skew_ppm - 10.0).abs() < 5.0);
     }
 
+    #[test]
+    fn test_skew_across_counter_wrap() {
+        let analyzer = ClockSkewAnalyzer::new();
+
+        // Microsecond clock running 50 ppm fast, wrapping around u32::MAX
+        let measurements = measurements_from((0..20u32).map(|i| (u32::MAX - 500_000).wrapping_add(i * 100_005)));
+        let (skew_ppm, freq, _) = analyzer.estimate_skew(&measurements).unwrap();
+        assert!((freq - 1_000_050.0).abs() < 1.0);
+        assert!((skew_ppm - 50.0).abs() < 1.0);
+        assert_eq!(analyzer.detect_timestamp_rewriting(&measurements), None);
+    }
+
+    #[test]
+    fn test_detect_timestamp_rewriting() {
+        let analyzer = ClockSkewAnalyzer::new();
+
+        let frozen = measurements_from(vec![42; 12]);
+        assert_eq!(analyzer.detect_timestamp_rewriting(&frozen), Some(TimestampAnomaly::Constant));
+
+        // Two hosts behind one NAT address, replies alternating between them
+        let interleaved = measurements_from((0..12u32).map(|i| {
+            if i % 2 == 0 { 1_000 + i * 100 } else { 9_000_000 + i * 100 }
+        }));
+        assert!(matches!(
+            analyzer.detect_timestamp_rewriting(&interleaved),
+            Some(TimestampAnomaly::NonMonotonic { backward_steps: 5 })
+        ));
+
+        // 5000 ticks per second is not a rate any stack uses
+        let odd_rate = measurements_from((0..12u32).map(|i| i * 500));
+        assert!(matches!(
+            analyzer.detect_timestamp_rewriting(&odd_rate),
+            Some(TimestampAnomaly::ImplausibleRate { .. })
+        ));
+    }
+
     #[test]
     fn test_os_classification() {
         let analyzer = ClockSkewAnalyzer::new();
@@ -1,10 +1,12 @@
 //! OS Fingerprint Database I/O
 //!
-//! This module provides JSON/YAML import/export functionality for OS fingerprint databases.
+//! This module provides JSON/YAML import/export functionality for OS fingerprint databases,
+//! plus import of Nmap `nmap-os-db` files.
 //! Allows loading custom signatures and saving fingerprint collections.
 
 use crate::error::{ScanResult, ScanError};
 use super::fingerprint_db::{OsFingerprintDatabase, OsSignature};
+use super::nmap_os_db;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -135,6 +137,45 @@ impl DatabaseIO {
         Ok(())
     }
 
+    /// Import database from an Nmap `nmap-os-db` file
+    ///
+    /// Fingerprints without TCP or ICMP test data are skipped; repeated
+    /// fingerprint names get a ` (2)`, ` (3)`, ... suffix so none is lost.
+    pub fn import_from_nmap_os_db<P: AsRef<Path>>(path: P) -> ScanResult<OsFingerprintDatabase> {
+        info!("Importing fingerprint database from nmap-os-db: {:?}", path.as_ref());
+
+        let content = fs::read_to_string(path.as_ref())?;
+
+        Self::import_nmap_os_db_str(&content)
+    }
+
+    /// Import database from nmap-os-db formatted text
+    pub fn import_nmap_os_db_str(content: &str) -> ScanResult<OsFingerprintDatabase> {
+        let fingerprints = nmap_os_db::parse(content)?;
+        let mut database = OsFingerprintDatabase::empty();
+        let mut skipped = 0;
+
+        for fingerprint in &fingerprints {
+            let Some(mut signature) = fingerprint.to_signature() else {
+                skipped += 1;
+                continue;
+            };
+            let base = signature.os_name.clone();
+            let mut n = 1;
+            while database.signatures().contains_key(&signature.os_name) {
+                n += 1;
+                signature.os_name = format!("{} ({})", base, n);
+            }
+            database.add_signature(signature);
+        }
+
+        if skipped > 0 {
+            warn!("Skipped {} nmap-os-db fingerprints without TCP or ICMP tests", skipped);
+        }
+        info!("Successfully imported {} signatures from nmap-os-db", database.signature_count());
+        Ok(database)
+    }
+
     /// Import database from YAML file
     pub fn import_from_yaml<P: AsRef<Path>>(path: P) -> ScanResult<OsFingerprintDatabase> {
         info!("Importing fingerprint database from YAML: {:?}", path.as_ref());
@@ -162,6 +203,14 @@ impl DatabaseIO {
     pub fn import_auto<P: AsRef<Path>>(path: P) -> ScanResult<OsFingerprintDatabase> {
         let path_ref = path.as_ref();
         
+        let is_nmap_os_db = path_ref
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("nmap-os-db"));
+        if is_nmap_os_db {
+            return Self::import_from_nmap_os_db(path);
+        }
+        
         if let Some(ext) = path_ref.extension() {
             match ext.to_str() {
                 Some("json") => Self::import_from_json(path),
@@ -352,5 +401,41 @@ mod tests {
         let merged = DatabaseIO::merge_databases(vec![db1, db2]);
         assert!(merged.signature_count() > 0);
     }
+
+    #[test]
+    fn test_import_nmap_os_db() {
+        let content = "\
+Fingerprint Linux 4.15 - 5.8
+Class Linux | Linux | 4.X | general purpose
+SEQ(SP=100-10A%GCD=1-6%ISR=104-10E%TI=Z%CI=Z%II=I%TS=A)
+OPS(O1=M5B4ST11NW7)
+WIN(W1=FE88%W2=FE88%W3=FE88%W4=FE88%W5=FE88%W6=FE88)
+T1(R=Y%DF=Y%T=3B-45%TG=40%S=O%A=S+%F=AS%RD=0%Q=)
+
+Fingerprint Linux 4.15 - 5.8
+Class Linux | Linux | 5.X | general purpose
+OPS(O1=M5B4ST11NW7)
+WIN(W1=FAF0%W2=FAF0%W3=FAF0%W4=FAF0%W5=FAF0%W6=FAF0)
+T1(R=Y%DF=Y%T=3B-45%TG=40%S=O%A=S+%F=AS%RD=0%Q=)
+
+Fingerprint Silent device
+Class Generic | embedded | | specialized
+T1(R=N)
+IE(R=N)
+";
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nmap-os-db");
+        fs::write(&path, content).unwrap();
+
+        let db = DatabaseIO::import_auto(&path).unwrap();
+        assert_eq!(db.signature_count(), 2);
+        assert!(db.get_signature("Linux 4.15 - 5.8").is_some());
+        assert_eq!(
+            db.get_signature("Linux 4.15 - 5.8 (2)").unwrap().os_version.as_deref(),
+            Some("5.X")
+        );
+        assert!(db.get_signature("Silent device").is_none());
+        assert!(DatabaseIO::validate_database(&db).unwrap().is_valid());
+    }
 }
 
@@ -0,0 +1,594 @@
+//! Weighted evidence fusion across fingerprinting techniques
+//!
+//! Each technique (TCP, ICMP, UDP, protocol hints, clock skew, passive
+//! observation, active probes) scores how well its evidence fits a
+//! signature, between 0.0 and 1.0. Fusion turns the scores into a posterior
+//! probability per signature:
+//!
+//! * a score `s` becomes the likelihood `ε + (1 - 2ε)·s` of the evidence
+//!   under that OS, so one contradicting source lowers a match without
+//!   ruling it out
+//! * the configured weight of a source is the exponent of its likelihood:
+//!   1.0 counts the source as one independent observation, 0.0 ignores it
+//! * next to the signatures there is an "unknown OS" hypothesis for which
+//!   every source is a coin flip (likelihood 0.5); it keeps
+//!   `unknown_prior` of the prior mass, the signatures share the rest in
+//!   proportion to their confidence weight
+//! * signatures failing a must-match constraint are dropped before the
+//!   probabilities are normalized
+//!
+//! Probabilities of the matches and of the unknown hypothesis sum to 1.
+
+use super::fingerprint_db::{OsFamily, OsFingerprintDatabase, OsSignature};
+use super::matcher::{MatchConfidence, OsMatchResult, OsMatcher};
+use super::OsFingerprint;
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Likelihood floor (and one minus the ceiling) of a source
+const LIKELIHOOD_EPSILON: f64 = 0.05;
+
+/// A fingerprinting technique contributing evidence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceSource {
+    Tcp,
+    Icmp,
+    Udp,
+    ProtocolHints,
+    ClockSkew,
+    Passive,
+    ActiveProbes,
+}
+
+impl EvidenceSource {
+    pub const ALL: [EvidenceSource; 7] = [
+        Self::Tcp,
+        Self::Icmp,
+        Self::Udp,
+        Self::ProtocolHints,
+        Self::ClockSkew,
+        Self::Passive,
+        Self::ActiveProbes,
+    ];
+}
+
+impl fmt::Display for EvidenceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Tcp => "tcp",
+            Self::Icmp => "icmp",
+            Self::Udp => "udp",
+            Self::ProtocolHints => "protocol_hints",
+            Self::ClockSkew => "clock_skew",
+            Self::Passive => "passive",
+            Self::ActiveProbes => "active_probes",
+        };
+        f.pad(name)
+    }
+}
+
+/// Likelihood exponent per evidence source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceWeights {
+    pub tcp: f64,
+    pub icmp: f64,
+    pub udp: f64,
+    pub protocol_hints: f64,
+    pub clock_skew: f64,
+    pub passive: f64,
+    pub active_probes: f64,
+}
+
+impl Default for SourceWeights {
+    fn default() -> Self {
+        Self {
+            tcp: 3.0,
+            icmp: 2.0,
+            udp: 1.0,
+            protocol_hints: 1.5,
+            clock_skew: 0.5,
+            passive: 1.5,
+            active_probes: 3.0,
+        }
+    }
+}
+
+impl SourceWeights {
+    pub fn weight(&self, source: EvidenceSource) -> f64 {
+        match source {
+            EvidenceSource::Tcp => self.tcp,
+            EvidenceSource::Icmp => self.icmp,
+            EvidenceSource::Udp => self.udp,
+            EvidenceSource::ProtocolHints => self.protocol_hints,
+            EvidenceSource::ClockSkew => self.clock_skew,
+            EvidenceSource::Passive => self.passive,
+            EvidenceSource::ActiveProbes => self.active_probes,
+        }
+    }
+}
+
+/// Evidence fusion settings (part of `OsFingerprintConfig`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvidenceFusionConfig {
+    /// Report fused probabilities instead of the matcher's scores
+    pub enabled: bool,
+    pub weights: SourceWeights,
+    /// Sources a signature must agree with, when the fingerprint has their
+    /// evidence, to stay a candidate
+    pub must_match: Vec<EvidenceSource>,
+    /// Lowest source score counting as agreement for `must_match`
+    pub must_match_min_score: f64,
+    /// Prior probability that the host runs an OS not in the database
+    pub unknown_prior: f64,
+}
+
+impl Default for EvidenceFusionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weights: SourceWeights::default(),
+            must_match: Vec::new(),
+            must_match_min_score: 0.5,
+            unknown_prior: 0.2,
+        }
+    }
+}
+
+impl EvidenceFusionConfig {
+    pub fn validate(&self) -> ScanResult<()> {
+        for source in EvidenceSource::ALL {
+            let weight = self.weights.weight(source);
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(ScanError::validation_error(
+                    "evidence_fusion.weights",
+                    format!("{} weight must be a non-negative number, got {}", source, weight),
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.must_match_min_score) {
+            return Err(ScanError::validation_error(
+                "evidence_fusion.must_match_min_score",
+                "must be between 0.0 and 1.0",
+            ));
+        }
+        if !(self.unknown_prior > 0.0 && self.unknown_prior < 1.0) {
+            return Err(ScanError::validation_error(
+                "evidence_fusion.unknown_prior",
+                "must be between 0.0 and 1.0 (exclusive)",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Posterior probability of one signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusedMatch {
+    pub os_name: String,
+    pub os_version: Option<String>,
+    pub os_family: OsFamily,
+    pub probability: f64,
+    /// Score of every source with evidence for this signature
+    pub source_scores: BTreeMap<EvidenceSource, f64>,
+}
+
+/// Fused matches, most probable first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusionResult {
+    pub matches: Vec<FusedMatch>,
+    /// Probability that the OS is none of the signatures
+    pub unknown_probability: f64,
+    /// Signatures dropped by must-match constraints
+    pub excluded: Vec<String>,
+}
+
+impl FusionResult {
+    /// Matches as matcher results, the probability as confidence score
+    pub fn to_match_results(&self, limit: usize) -> Vec<OsMatchResult> {
+        self.matches
+            .iter()
+            .take(limit)
+            .map(|m| OsMatchResult {
+                os_name: m.os_name.clone(),
+                os_version: m.os_version.clone(),
+                os_family: m.os_family,
+                confidence: MatchConfidence::from(m.probability),
+                confidence_score: m.probability,
+                matching_features: m
+                    .source_scores
+                    .iter()
+                    .map(|(source, score)| format!("{} evidence: {:.2}", source, score))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Combines per-source scores into calibrated probabilities
+#[derive(Debug, Clone, Default)]
+pub struct EvidenceFusion {
+    config: EvidenceFusionConfig,
+}
+
+impl EvidenceFusion {
+    pub fn new(config: EvidenceFusionConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &EvidenceFusionConfig {
+        &self.config
+    }
+
+    /// Score every source with evidence for a signature
+    pub fn source_scores(fingerprint: &OsFingerprint, signature: &OsSignature) -> BTreeMap<EvidenceSource, f64> {
+        let mut scores = BTreeMap::new();
+        if let (Some(tcp), Some(sig)) = (&fingerprint.tcp_fingerprint, &signature.tcp_signature) {
+            scores.insert(EvidenceSource::Tcp, OsMatcher::match_tcp_fingerprint(tcp, sig));
+        }
+        if let (Some(icmp), Some(sig)) = (&fingerprint.icmp_fingerprint, &signature.icmp_signature) {
+            scores.insert(EvidenceSource::Icmp, OsMatcher::match_icmp_fingerprint(icmp, sig));
+        }
+        if let (Some(udp), Some(sig)) = (&fingerprint.udp_fingerprint, &signature.icmp_signature) {
+            // Closed UDP ports should answer with a port unreachable quoting
+            // as much data as the signature's unreachables do
+            let unreachable = &udp.port_unreachable_behavior;
+            let score = match unreachable.sends_icmp_unreachable {
+                false => 0.0,
+                true if udp.payload_echoing.bytes_echoed == sig.unreachable_data_length => 1.0,
+                true => 0.5,
+            };
+            scores.insert(EvidenceSource::Udp, score);
+        }
+        if let Some(ref hints) = fingerprint.protocol_hints {
+            let os_hints = [
+                hints.ssh_hints.as_ref().map(|h| &h.os_hints),
+                hints.smb_hints.as_ref().map(|h| &h.os_hints),
+                hints.http_hints.as_ref().map(|h| &h.os_hints),
+                hints.tls_hints.as_ref().map(|h| &h.os_hints),
+            ];
+            let os_hints = os_hints.into_iter().flatten().flatten();
+            if let Some(score) = hint_agreement(os_hints, signature.os_family) {
+                scores.insert(EvidenceSource::ProtocolHints, score);
+            }
+        }
+        if let Some(ref clock) = fingerprint.clock_skew {
+            let score = match clock.clock_frequency_hz {
+                Some(hz) => Some(clock_frequency_score(hz, signature.os_family)),
+                None => hint_agreement(&clock.os_hints, signature.os_family),
+            };
+            if let Some(score) = score {
+                scores.insert(EvidenceSource::ClockSkew, score);
+            }
+        }
+        if let Some(ref passive) = fingerprint.passive_fingerprint {
+            if let Some(score) = hint_agreement(&passive.os_hints, signature.os_family) {
+                scores.insert(EvidenceSource::Passive, score);
+            }
+        }
+        if let (Some(active), Some(sig)) = (&fingerprint.active_probes, &signature.tcp_signature) {
+            if let Some(score) = active_probe_score(active, sig) {
+                scores.insert(EvidenceSource::ActiveProbes, score);
+            }
+        }
+        scores
+    }
+
+    /// Posterior probabilities of every signature in the database
+    pub fn fuse(&self, fingerprint: &OsFingerprint, database: &OsFingerprintDatabase) -> FusionResult {
+        let weights = &self.config.weights;
+        let total_weight: f64 = database.signatures().values().map(|s| s.confidence_weight.max(0.0)).sum();
+
+        let mut candidates = Vec::new();
+        let mut excluded = Vec::new();
+        for signature in database.signatures().values() {
+            let source_scores = Self::source_scores(fingerprint, signature);
+            let violates = self.config.must_match.iter().any(|source| {
+                source_scores
+                    .get(source)
+                    .is_some_and(|&score| score < self.config.must_match_min_score)
+            });
+            if violates {
+                excluded.push(signature.os_name.clone());
+                continue;
+            }
+            if total_weight <= 0.0 || signature.confidence_weight <= 0.0 {
+                continue;
+            }
+
+            // Log-likelihood ratio against the unknown hypothesis; sources
+            // without evidence are 0.5 under both and cancel out
+            let log_ratio: f64 = source_scores
+                .iter()
+                .map(|(&source, &score)| {
+                    let likelihood = LIKELIHOOD_EPSILON + (1.0 - 2.0 * LIKELIHOOD_EPSILON) * score.clamp(0.0, 1.0);
+                    weights.weight(source) * (likelihood / 0.5).ln()
+                })
+                .sum();
+            let prior = (1.0 - self.config.unknown_prior) * signature.confidence_weight / total_weight;
+            candidates.push((signature, source_scores, prior.ln() + log_ratio));
+        }
+
+        // Normalize in log space against the unknown hypothesis
+        let unknown_log = self.config.unknown_prior.ln();
+        let max_log = candidates.iter().map(|c| c.2).fold(unknown_log, f64::max);
+        let normalizer = (unknown_log - max_log).exp() + candidates.iter().map(|c| (c.2 - max_log).exp()).sum::<f64>();
+
+        let mut matches: Vec<FusedMatch> = candidates
+            .into_iter()
+            .map(|(signature, source_scores, log_posterior)| FusedMatch {
+                os_name: signature.os_name.clone(),
+                os_version: signature.os_version.clone(),
+                os_family: signature.os_family,
+                probability: (log_posterior - max_log).exp() / normalizer,
+                source_scores,
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.probability
+                .partial_cmp(&a.probability)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.os_name.cmp(&b.os_name))
+        });
+        excluded.sort();
+
+        FusionResult {
+            matches,
+            unknown_probability: (unknown_log - max_log).exp() / normalizer,
+            excluded,
+        }
+    }
+}
+
+/// OS family a free-text hint names, if any
+fn hint_family(hint: &str) -> Option<OsFamily> {
+    let hint = hint.to_lowercase();
+    let any = |words: &[&str]| words.iter().any(|word| hint.contains(word));
+    if any(&["windows", "microsoft"]) {
+        Some(OsFamily::Windows)
+    } else if any(&["mac", "darwin", "os x", "apple"]) {
+        Some(OsFamily::MacOS)
+    } else if any(&["bsd"]) {
+        Some(OsFamily::BSD)
+    } else if any(&["cisco"]) {
+        Some(OsFamily::Cisco)
+    } else if any(&["linux", "ubuntu", "debian", "centos", "red hat", "fedora", "alpine", "android"]) {
+        Some(OsFamily::Linux)
+    } else if any(&["solaris", "aix", "hp-ux", "unix"]) {
+        Some(OsFamily::Unix)
+    } else {
+        None
+    }
+}
+
+/// Share of family-naming hints agreeing with a family
+///
+/// Embedded devices commonly run Linux, so Linux hints count half for them.
+fn hint_agreement<'a>(hints: impl IntoIterator<Item = &'a String>, family: OsFamily) -> Option<f64> {
+    let families: Vec<OsFamily> = hints.into_iter().filter_map(|hint| hint_family(hint)).collect();
+    if families.is_empty() {
+        return None;
+    }
+    let agreeing: f64 = families
+        .iter()
+        .map(|&hinted| match (hinted, family) {
+            (a, b) if a == b => 1.0,
+            (OsFamily::Linux, OsFamily::Embedded) => 0.5,
+            _ => 0.0,
+        })
+        .sum();
+    Some(agreeing / families.len() as f64)
+}
+
+/// Fit of a TCP timestamp clock frequency to a family's usual tick rates
+fn clock_frequency_score(hz: f64, family: OsFamily) -> f64 {
+    let near = |target: f64| (hz - target).abs() <= target * 0.05;
+    match family {
+        OsFamily::Linux if near(1000.0) || near(250.0) || near(100.0) => 1.0,
+        OsFamily::Windows if near(100.0) || near(1000.0) => 0.9,
+        OsFamily::MacOS if near(1000.0) => 1.0,
+        OsFamily::BSD if near(1000.0) => 0.9,
+        _ => 0.5,
+    }
+}
+
+/// Fit of the T1/T5-T7/ECN active probe answers to a TCP signature
+fn active_probe_score(
+    active: &super::active_probes::ActiveProbeResults,
+    signature: &super::fingerprint_db::TcpSignature,
+) -> Option<f64> {
+    use super::active_probes::TcpProbeType;
+    use super::tcp_fingerprint::{EcnBehavior, RstBehavior};
+
+    let mut checks = Vec::new();
+    if let Some(t1) = active.tcp_probes.iter().find(|p| p.probe_type == TcpProbeType::T1 && p.responded) {
+        if let Some(ttl) = t1.ttl {
+            let initial = [32u8, 64, 128, 255].into_iter().find(|&t| ttl <= t).unwrap_or(255);
+            checks.push(initial >= signature.ttl_range.0 && initial <= signature.ttl_range.1);
+        }
+        if let Some(window) = t1.window_size {
+            checks.push(window >= signature.window_size_range.0 && window <= signature.window_size_range.1);
+        }
+        checks.push(t1.df_flag == signature.df_flag);
+    }
+    let closed_probes: Vec<_> = active
+        .tcp_probes
+        .iter()
+        .filter(|p| matches!(p.probe_type, TcpProbeType::T5 | TcpProbeType::T6 | TcpProbeType::T7))
+        .collect();
+    if !closed_probes.is_empty() {
+        let answers_rst = closed_probes.iter().any(|p| p.responded);
+        checks.push(answers_rst == (signature.rst_behavior != RstBehavior::None));
+    }
+    if let Some(ref ecn) = active.ecn_probe {
+        checks.push(ecn.ecn_supported == signature.ecn_support);
+        if let Some(behavior) = signature.ecn_behavior {
+            checks.push(EcnBehavior::from_flags(ecn.ece_flag, ecn.cwr_flag) == behavior);
+        }
+    }
+    if checks.is_empty() {
+        return None;
+    }
+    Some(checks.iter().filter(|&&ok| ok).count() as f64 / checks.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os_fingerprint::icmp_fingerprint::*;
+    use crate::os_fingerprint::tcp_fingerprint::*;
+
+    /// A host answering exactly like the built-in signature
+    fn fingerprint_like(database: &OsFingerprintDatabase, name: &str) -> OsFingerprint {
+        let signature = database.get_signature(name).unwrap();
+        let tcp = signature.tcp_signature.clone().unwrap();
+        let icmp = signature.icmp_signature.clone().unwrap();
+        let target = "192.0.2.10".parse().unwrap();
+        OsFingerprint {
+            target,
+            tcp_fingerprint: Some(TcpFingerprint {
+                target,
+                initial_ttl: tcp.ttl_range.0,
+                window_size: tcp.window_size_range.0,
+                mss: tcp.typical_mss,
+                tcp_options: tcp.tcp_options_pattern.clone(),
+                df_flag: tcp.df_flag,
+                syn_ack_pattern: SynAckPattern {
+                    initial_sequence: 0,
+                    acknowledgment: 1,
+                    window_size: tcp.window_size_range.0,
+                    flags: TcpFlags {
+                        syn: true,
+                        ack: true,
+                        rst: false,
+                        fin: false,
+                        psh: false,
+                        urg: false,
+                        ece: false,
+                        cwr: false,
+                    },
+                    response_time_ms: 1,
+                },
+                rst_behavior: tcp.rst_behavior,
+                ip_id_pattern: tcp.ip_id_pattern,
+                ecn_support: tcp.ecn_support,
+                cwr_flag: false,
+                window_scale: tcp.window_scale,
+                ecn_behavior: tcp.ecn_behavior,
+                rst_data: tcp.rst_data,
+                tos: tcp.dscp.map(|dscp| dscp << 2),
+            }),
+            icmp_fingerprint: Some(IcmpFingerprint {
+                target,
+                echo_reply: Some(IcmpEchoReply {
+                    ttl: icmp.ttl_range.0,
+                    payload_echo: icmp.echoes_payload,
+                    code: 0,
+                    response_time_ms: 1,
+                    payload_size: 56,
+                    tos_value: 0,
+                }),
+                unreachable_behavior: IcmpUnreachableBehavior {
+                    port_unreachable_code: Some(3),
+                    host_unreachable_code: None,
+                    protocol_unreachable_code: None,
+                    echoes_data: true,
+                    data_length: icmp.unreachable_data_length,
+                },
+                timestamp_behavior: icmp.timestamp_behavior,
+                rate_limiting: IcmpRateLimiting {
+                    has_rate_limiting: false,
+                    limit_per_second: None,
+                    burst_size: None,
+                    pattern: icmp.rate_limit_pattern,
+                },
+            }),
+            udp_fingerprint: None,
+            protocol_hints: None,
+            clock_skew: None,
+            passive_fingerprint: None,
+            active_probes: None,
+            detection_time_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_probabilities_are_normalized() {
+        let database = OsFingerprintDatabase::new();
+        let fingerprint = fingerprint_like(&database, "Windows 10/11");
+        let result = EvidenceFusion::default().fuse(&fingerprint, &database);
+
+        let total: f64 = result.matches.iter().map(|m| m.probability).sum::<f64>() + result.unknown_probability;
+        assert!((total - 1.0).abs() < 1e-9);
+        assert_eq!(result.matches[0].os_name, "Windows 10/11");
+        assert!(result.matches[0].probability > 0.5);
+        assert_eq!(result.matches[0].source_scores[&EvidenceSource::Tcp], 1.0);
+    }
+
+    #[test]
+    fn test_without_evidence_the_prior_remains() {
+        let database = OsFingerprintDatabase::new();
+        let mut fingerprint = fingerprint_like(&database, "Windows 10/11");
+        fingerprint.tcp_fingerprint = None;
+        fingerprint.icmp_fingerprint = None;
+
+        let result = EvidenceFusion::default().fuse(&fingerprint, &database);
+        assert!((result.unknown_probability - 0.2).abs() < 1e-9);
+        assert!(result.matches.iter().all(|m| m.source_scores.is_empty()));
+    }
+
+    #[test]
+    fn test_weights_change_the_outcome() {
+        let database = OsFingerprintDatabase::new();
+        let fingerprint = fingerprint_like(&database, "Windows 10/11");
+        let confident = EvidenceFusion::default().fuse(&fingerprint, &database);
+
+        let mut config = EvidenceFusionConfig::default();
+        config.weights.tcp = 0.5;
+        config.weights.icmp = 0.5;
+        let hesitant = EvidenceFusion::new(config).fuse(&fingerprint, &database);
+        assert_eq!(hesitant.matches[0].os_name, "Windows 10/11");
+        assert!(hesitant.matches[0].probability < confident.matches[0].probability);
+    }
+
+    #[test]
+    fn test_must_match_excludes_disagreeing_signatures() {
+        let database = OsFingerprintDatabase::new();
+        let mut fingerprint = fingerprint_like(&database, "Windows 10/11");
+        fingerprint.protocol_hints = Some(crate::os_fingerprint::ProtocolHints {
+            target: fingerprint.target,
+            ssh_hints: None,
+            smb_hints: None,
+            http_hints: Some(crate::os_fingerprint::protocol_hints::HttpHints {
+                server_header: Some("Apache/2.4.41 (Ubuntu)".to_string()),
+                date_format: None,
+                custom_headers: Default::default(),
+                powered_by: None,
+                os_hints: vec!["Ubuntu Linux".to_string()],
+            }),
+            tls_hints: None,
+        });
+
+        let config = EvidenceFusionConfig {
+            must_match: vec![EvidenceSource::ProtocolHints],
+            ..EvidenceFusionConfig::default()
+        };
+        let result = EvidenceFusion::new(config).fuse(&fingerprint, &database);
+        assert!(result.excluded.contains(&"Windows 10/11".to_string()));
+        assert!(result.matches.iter().all(|m| m.os_family == OsFamily::Linux || m.os_family == OsFamily::Embedded));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(EvidenceFusionConfig::default().validate().is_ok());
+        let mut config = EvidenceFusionConfig::default();
+        config.weights.udp = -1.0;
+        assert!(config.validate().is_err());
+        let config = EvidenceFusionConfig { unknown_prior: 1.0, ..EvidenceFusionConfig::default() };
+        assert!(config.validate().is_err());
+    }
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::info;
 
-use super::tcp_fingerprint::{IpIdPattern, RstBehavior, TcpOption};
+use super::tcp_fingerprint::{options_order, EcnBehavior, IpIdPattern, RstBehavior, TcpOption};
 use super::icmp_fingerprint::{IcmpTimestampBehavior, RateLimitPattern};
 
 /// OS signature for matching
@@ -48,6 +48,27 @@ impl std::fmt::Display for OsFamily {
     }
 }
 
+impl std::str::FromStr for OsFamily {
+    type Err = crate::error::ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linux" => Ok(OsFamily::Linux),
+            "windows" => Ok(OsFamily::Windows),
+            "macos" | "mac" => Ok(OsFamily::MacOS),
+            "bsd" => Ok(OsFamily::BSD),
+            "unix" => Ok(OsFamily::Unix),
+            "cisco" => Ok(OsFamily::Cisco),
+            "embedded" => Ok(OsFamily::Embedded),
+            "unknown" => Ok(OsFamily::Unknown),
+            _ => Err(crate::error::ScanError::validation_error(
+                "family",
+                format!("Unknown OS family: {}", s),
+            )),
+        }
+    }
+}
+
 /// TCP signature patterns
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TcpSignature {
@@ -59,6 +80,33 @@ pub struct TcpSignature {
     pub rst_behavior: RstBehavior,
     pub ip_id_pattern: IpIdPattern,
     pub ecn_support: bool,
+    /// Option-order strings such as `MSTNW`, `|`-separated alternatives;
+    /// derived from `tcp_options_pattern` when unset
+    #[serde(default)]
+    pub options_order: Option<String>,
+    /// Window scale shift count of the SYN/ACK
+    #[serde(default)]
+    pub window_scale: Option<u8>,
+    /// Flags answered to an ECN-setup SYN
+    #[serde(default)]
+    pub ecn_behavior: Option<EcnBehavior>,
+    /// Whether RST segments carry data
+    #[serde(default)]
+    pub rst_data: Option<bool>,
+    /// DSCP (upper six TOS bits) of the responses
+    #[serde(default)]
+    pub dscp: Option<u8>,
+}
+
+impl TcpSignature {
+    /// Accepted option-order strings
+    pub fn options_orders(&self) -> Vec<String> {
+        match self.options_order {
+            Some(ref orders) => orders.split('|').map(str::to_string).collect(),
+            None if self.tcp_options_pattern.is_empty() => Vec::new(),
+            None => vec![options_order(&self.tcp_options_pattern)],
+        }
+    }
 }
 
 /// ICMP signature patterns
@@ -121,6 +169,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::Immediate,
                 ip_id_pattern: IpIdPattern::Incremental,
                 ecn_support: false,
+                options_order: None,
+                window_scale: Some(7),
+                ecn_behavior: Some(EcnBehavior::NoEcn),
+                rst_data: Some(false),
+                dscp: Some(0),
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (64, 64),
@@ -153,6 +206,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::Immediate,
                 ip_id_pattern: IpIdPattern::Incremental,
                 ecn_support: false,
+                options_order: None,
+                window_scale: Some(8),
+                ecn_behavior: Some(EcnBehavior::NoEcn),
+                rst_data: Some(false),
+                dscp: Some(0),
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (128, 128),
@@ -187,6 +245,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::Immediate,
                 ip_id_pattern: IpIdPattern::Random,
                 ecn_support: false,
+                options_order: None,
+                window_scale: Some(6),
+                ecn_behavior: None,
+                rst_data: Some(false),
+                dscp: Some(0),
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (64, 64),
@@ -218,6 +281,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::Immediate,
                 ip_id_pattern: IpIdPattern::Random,
                 ecn_support: false,
+                options_order: None,
+                window_scale: Some(6),
+                ecn_behavior: Some(EcnBehavior::NoEcn),
+                rst_data: Some(false),
+                dscp: Some(0),
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (64, 64),
@@ -243,6 +311,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::None,
                 ip_id_pattern: IpIdPattern::Zero,
                 ecn_support: false,
+                options_order: None,
+                window_scale: None,
+                ecn_behavior: Some(EcnBehavior::NoEcn),
+                rst_data: Some(false),
+                dscp: Some(0),
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (255, 255),
@@ -272,6 +345,11 @@ impl OsFingerprintDatabase {
                 rst_behavior: RstBehavior::Immediate,
                 ip_id_pattern: IpIdPattern::Incremental,
                 ecn_support: false,
+                options_order: None,
+                window_scale: Some(0),
+                ecn_behavior: None,
+                rst_data: Some(false),
+                dscp: None,
             }),
             icmp_signature: Some(IcmpSignature {
                 ttl_range: (64, 64),
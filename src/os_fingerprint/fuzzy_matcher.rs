@@ -5,9 +5,10 @@
 
 use crate::error::ScanResult;
 use super::fingerprint_db::{OsFingerprintDatabase, OsSignature, OsFamily};
+use super::learning::{LearningStore, UnknownFingerprint};
 use super::OsFingerprint;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Fuzzy matcher with advanced matching algorithms
 pub struct FuzzyMatcher {
@@ -17,6 +18,8 @@ pub struct FuzzyMatcher {
     /// Enable partial matching
     #[allow(dead_code)]
     enable_partial_match: bool,
+    /// Where fingerprints without a match above the threshold are recorded
+    learning: Option<LearningStore>,
 }
 
 impl FuzzyMatcher {
@@ -26,9 +29,16 @@ impl FuzzyMatcher {
             database,
             min_threshold,
             enable_partial_match: true,
+            learning: None,
         }
     }
 
+    /// Record fingerprints that match nothing above the threshold
+    pub fn with_learning(mut self, store: LearningStore) -> Self {
+        self.learning = Some(store);
+        self
+    }
+
     /// Match with detailed analysis
     pub fn match_with_details(
         &self,
@@ -36,20 +46,36 @@ impl FuzzyMatcher {
     ) -> ScanResult<DetailedMatchResult> {
         info!("Performing fuzzy matching for {}", fingerprint.target);
         
-        let mut all_scores = Vec::new();
-        
         // Calculate scores for all signatures
-        for signature in self.database.signatures().values() {
-            let score = self.calculate_fuzzy_score(fingerprint, signature);
-            
-            if score.total_score >= self.min_threshold {
-                all_scores.push(score);
-            }
-        }
+        let mut all_scores: Vec<FuzzyScore> = self.database.signatures().values()
+            .map(|signature| self.calculate_fuzzy_score(fingerprint, signature))
+            .collect();
         
         // Sort by total score (descending)
         all_scores.sort_by(|a, b| b.total_score.partial_cmp(&a.total_score).unwrap());
         
+        // Nothing above the threshold: keep the evidence for labeling
+        let closest = all_scores.first().cloned();
+        all_scores.retain(|score| score.total_score >= self.min_threshold);
+        let unknown_id = match self.learning {
+            Some(ref store) if all_scores.is_empty() => {
+                let unknown = UnknownFingerprint::new(fingerprint.clone(), closest.as_ref());
+                match store.record(&unknown) {
+                    Ok(new) => {
+                        info!("No OS match for {}; {} fingerprint {} in {:?}",
+                              fingerprint.target, if new { "recorded" } else { "already recorded" },
+                              unknown.id, store.path());
+                        Some(unknown.id)
+                    }
+                    Err(e) => {
+                        warn!("Failed to record unknown fingerprint of {}: {}", fingerprint.target, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        
         // Create detailed result
         let confidence_distribution = self.calculate_confidence_distribution(&all_scores);
         let best_match = all_scores.first().cloned();
@@ -64,6 +90,7 @@ impl FuzzyMatcher {
             match_scores: all_scores,
             confidence_distribution,
             feature_coverage: self.calculate_feature_coverage(fingerprint),
+            unknown_id,
         };
         
         debug!("Fuzzy matching complete: {} matches found", result.matches_found);
@@ -224,6 +251,67 @@ impl FuzzyMatcher {
             }
         }
         
+        // Option order (any of the signature's alternatives)
+        let orders = sig.options_orders();
+        let fp_order = super::tcp_fingerprint::options_order(&fp.tcp_options);
+        if !orders.is_empty() && !fp_order.is_empty() {
+            if orders.contains(&fp_order) {
+                score += 1.0;
+                matched.push(format!("Options: {}", fp_order));
+            } else {
+                mismatched.push(format!("Options: {} (expected {})", fp_order, orders.join("|")));
+            }
+            checks += 1;
+        }
+        
+        // Window scale (neighbouring shift counts get partial credit, since
+        // they follow the receive buffer size)
+        if let (Some(fp_scale), Some(sig_scale)) = (fp.window_scale, sig.window_scale) {
+            if fp_scale == sig_scale {
+                score += 1.0;
+                matched.push(format!("Window scale: {}", fp_scale));
+            } else if fp_scale.abs_diff(sig_scale) == 1 {
+                score += 0.5;
+                matched.push(format!("Window scale: {} (partial)", fp_scale));
+            } else {
+                mismatched.push(format!("Window scale: {} (expected {})", fp_scale, sig_scale));
+            }
+            checks += 1;
+        }
+        
+        // ECN negotiation
+        if let (Some(fp_ecn), Some(sig_ecn)) = (fp.ecn_behavior, sig.ecn_behavior) {
+            if fp_ecn == sig_ecn {
+                score += 1.0;
+                matched.push(format!("ECN: {:?}", fp_ecn));
+            } else {
+                mismatched.push(format!("ECN: {:?} (expected {:?})", fp_ecn, sig_ecn));
+            }
+            checks += 1;
+        }
+        
+        // RST data
+        if let (Some(fp_data), Some(sig_data)) = (fp.rst_data, sig.rst_data) {
+            if fp_data == sig_data {
+                score += 1.0;
+                matched.push(format!("RST data: {}", fp_data));
+            } else {
+                mismatched.push(format!("RST data: {} (expected {})", fp_data, sig_data));
+            }
+            checks += 1;
+        }
+        
+        // DSCP marking
+        if let (Some(tos), Some(sig_dscp)) = (fp.tos, sig.dscp) {
+            if tos >> 2 == sig_dscp {
+                score += 1.0;
+                matched.push(format!("DSCP: {}", sig_dscp));
+            } else {
+                mismatched.push(format!("DSCP: {} (expected {})", tos >> 2, sig_dscp));
+            }
+            checks += 1;
+        }
+        
         // Normalize score
         if checks > 0 {
             score / checks as f64
@@ -386,6 +474,9 @@ pub struct DetailedMatchResult {
     pub match_scores: Vec<FuzzyScore>,
     pub confidence_distribution: ConfidenceDistribution,
     pub feature_coverage: FeatureCoverage,
+    /// ID the fingerprint was recorded under in learning mode
+    #[serde(default)]
+    pub unknown_id: Option<String>,
 }
 
 /// Fuzzy score with detailed breakdown
@@ -444,5 +535,30 @@ mod tests {
         let matcher = FuzzyMatcher::new(db, 0.5);
         assert!(matcher.min_threshold == 0.5);
     }
+
+    #[test]
+    fn test_learning_records_unmatched_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LearningStore::new(dir.path().join("unknown.jsonl"));
+        let matcher = FuzzyMatcher::new(OsFingerprintDatabase::new(), 0.5).with_learning(store.clone());
+        let fingerprint = OsFingerprint {
+            target: "192.0.2.7".parse().unwrap(),
+            tcp_fingerprint: None,
+            icmp_fingerprint: None,
+            udp_fingerprint: None,
+            protocol_hints: None,
+            clock_skew: None,
+            passive_fingerprint: None,
+            active_probes: None,
+            detection_time_ms: 0,
+        };
+
+        let result = matcher.match_with_details(&fingerprint).unwrap();
+        assert!(result.best_match.is_none());
+        let recorded = store.load().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(result.unknown_id.as_deref(), Some(recorded[0].id.as_str()));
+        assert!(recorded[0].closest_match.is_some());
+    }
 }
 
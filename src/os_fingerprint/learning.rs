@@ -0,0 +1,341 @@
+//! Learning mode: recording fingerprints no signature matches
+//!
+//! When the fuzzy matcher finds nothing above its threshold, the complete
+//! [`OsFingerprint`] is appended to an unknown-fingerprints file (JSON Lines,
+//! one [`UnknownFingerprint`] per line). Each record carries a stable ID
+//! derived from the probe evidence alone, leaving out the target address and
+//! timings, so the same unknown stack seen on many hosts is recorded once.
+//! `nrmap os-db label` later turns a record into a signature in the user
+//! database.
+
+use super::fingerprint_db::{IcmpSignature, OsFamily, OsSignature, TcpSignature};
+use super::fuzzy_matcher::FuzzyScore;
+use super::OsFingerprint;
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// File name of the unknown-fingerprints file in the user config directory
+pub const UNKNOWN_FINGERPRINTS_FILE: &str = "unknown-fingerprints.jsonl";
+
+/// Default unknown-fingerprints location, next to the user config file
+pub fn default_unknown_fingerprints_path() -> Option<PathBuf> {
+    Some(crate::config::user_config_path()?.with_file_name(UNKNOWN_FINGERPRINTS_FILE))
+}
+
+/// Keys left out of the evidence an ID is computed from
+const NON_EVIDENCE_KEYS: &[&str] = &["target", "detection_time_ms", "response_time_ms"];
+
+/// Confidence weight of a signature labeled from a single observation
+const LABELED_CONFIDENCE_WEIGHT: f64 = 0.9;
+
+/// A fingerprint recorded for later labeling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownFingerprint {
+    /// Stable ID of the probe evidence
+    pub id: String,
+    /// RFC 3339 time of the first observation
+    pub recorded_at: String,
+    /// Closest signature, even though it scored below the threshold
+    pub closest_match: Option<String>,
+    pub closest_score: Option<f64>,
+    pub fingerprint: OsFingerprint,
+}
+
+impl UnknownFingerprint {
+    /// Record a fingerprint with its closest (sub-threshold) match
+    pub fn new(fingerprint: OsFingerprint, closest: Option<&FuzzyScore>) -> Self {
+        Self {
+            id: fingerprint_id(&fingerprint),
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            closest_match: closest.map(|score| score.signature_name.clone()),
+            closest_score: closest.map(|score| score.total_score),
+            fingerprint,
+        }
+    }
+
+    /// Turn the recorded evidence into a signature
+    ///
+    /// TCP values are taken exactly. The ICMP TTL range spans the observed
+    /// echo reply TTL up to the initial TTL it was decremented from, so
+    /// hosts at other hop distances match too.
+    pub fn to_signature(&self, name: &str, family: OsFamily, version: Option<String>) -> ScanResult<OsSignature> {
+        let tcp_signature = self.fingerprint.tcp_fingerprint.as_ref().map(|tcp| TcpSignature {
+            ttl_range: (tcp.initial_ttl, tcp.initial_ttl),
+            window_size_range: (tcp.window_size, tcp.window_size),
+            typical_mss: tcp.mss,
+            tcp_options_pattern: tcp.tcp_options.clone(),
+            df_flag: tcp.df_flag,
+            rst_behavior: tcp.rst_behavior,
+            ip_id_pattern: tcp.ip_id_pattern,
+            ecn_support: tcp.ecn_support,
+            options_order: None,
+            window_scale: tcp.window_scale,
+            ecn_behavior: tcp.ecn_behavior,
+            rst_data: tcp.rst_data,
+            dscp: tcp.tos.map(|tos| tos >> 2),
+        });
+        let icmp_signature = self.fingerprint.icmp_fingerprint.as_ref().and_then(|icmp| {
+            let echo = icmp.echo_reply.as_ref()?;
+            Some(IcmpSignature {
+                ttl_range: (echo.ttl, initial_ttl(echo.ttl)),
+                echoes_payload: echo.payload_echo,
+                timestamp_behavior: icmp.timestamp_behavior,
+                rate_limit_pattern: icmp.rate_limiting.pattern,
+                unreachable_data_length: icmp.unreachable_behavior.data_length,
+            })
+        });
+        if tcp_signature.is_none() && icmp_signature.is_none() {
+            return Err(ScanError::validation_error(
+                "fingerprint",
+                format!("{} has no TCP or ICMP echo evidence to build a signature from", self.id),
+            ));
+        }
+        Ok(OsSignature {
+            os_name: name.to_string(),
+            os_version: version,
+            os_family: family,
+            tcp_signature,
+            icmp_signature,
+            confidence_weight: LABELED_CONFIDENCE_WEIGHT,
+        })
+    }
+}
+
+/// Smallest common initial TTL at or above an observed TTL
+fn initial_ttl(observed: u8) -> u8 {
+    [32, 64, 128, 255].into_iter().find(|&ttl| observed <= ttl).unwrap_or(255)
+}
+
+/// Stable ID of a fingerprint's probe evidence (64-bit FNV-1a, hex)
+pub fn fingerprint_id(fingerprint: &OsFingerprint) -> String {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.retain(|key, _| !NON_EVIDENCE_KEYS.contains(&key.as_str()));
+                map.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+
+    let mut evidence = serde_json::to_value(fingerprint).unwrap_or_default();
+    strip(&mut evidence);
+    format!("{:016x}", fnv1a_64(evidence.to_string().as_bytes()))
+}
+
+/// 64-bit FNV-1a, stable across builds and platforms
+pub(super) fn fnv1a_64(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Append-only store of unknown fingerprints
+#[derive(Debug, Clone)]
+pub struct LearningStore {
+    path: PathBuf,
+    /// Serializes the duplicate check and append of concurrent detections
+    lock: Arc<Mutex<()>>,
+}
+
+impl LearningStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record unless its evidence is already recorded
+    ///
+    /// Returns whether the record was new.
+    pub fn record(&self, unknown: &UnknownFingerprint) -> ScanResult<bool> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if self.load()?.iter().any(|existing| existing.id == unknown.id) {
+            return Ok(false);
+        }
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(unknown)
+            .map_err(|e| ScanError::ScannerError { message: format!("JSON serialization failed: {}", e) })?;
+        line.push('\n');
+        OpenOptions::new().create(true).append(true).open(&self.path)?.write_all(line.as_bytes())?;
+        Ok(true)
+    }
+
+    /// All recorded fingerprints, oldest first (none if the file is missing)
+    pub fn load(&self) -> ScanResult<Vec<UnknownFingerprint>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| ScanError::ScannerError {
+                    message: format!("{}: line {}: {}", self.path.display(), index + 1, e),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove and return the record with this ID or unique ID prefix
+    pub fn take(&self, id: &str) -> ScanResult<UnknownFingerprint> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut records = self.load()?;
+        let matching: Vec<usize> = (0..records.len()).filter(|&i| records[i].id.starts_with(id)).collect();
+        let index = match matching[..] {
+            [index] => index,
+            [] => {
+                return Err(ScanError::validation_error("id", format!("no unknown fingerprint '{}'", id)));
+            }
+            _ => {
+                return Err(ScanError::validation_error("id", format!("'{}' matches several fingerprints", id)));
+            }
+        };
+        let taken = records.remove(index);
+
+        let mut content = String::new();
+        for record in &records {
+            let line = serde_json::to_string(record)
+                .map_err(|e| ScanError::ScannerError { message: format!("JSON serialization failed: {}", e) })?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        fs::write(&self.path, content)?;
+        Ok(taken)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os_fingerprint::icmp_fingerprint::*;
+    use crate::os_fingerprint::tcp_fingerprint::*;
+
+    fn fingerprint(target: &str, window_size: u16) -> OsFingerprint {
+        let target = target.parse().unwrap();
+        OsFingerprint {
+            target,
+            tcp_fingerprint: Some(TcpFingerprint {
+                target,
+                initial_ttl: 64,
+                window_size,
+                mss: Some(1380),
+                tcp_options: vec![TcpOption::Mss, TcpOption::Nop, TcpOption::WindowScale],
+                df_flag: false,
+                syn_ack_pattern: SynAckPattern {
+                    initial_sequence: 1,
+                    acknowledgment: 2,
+                    window_size,
+                    flags: TcpFlags {
+                        syn: true,
+                        ack: true,
+                        rst: false,
+                        fin: false,
+                        psh: false,
+                        urg: false,
+                        ece: false,
+                        cwr: false,
+                    },
+                    response_time_ms: 3,
+                },
+                rst_behavior: RstBehavior::Immediate,
+                ip_id_pattern: IpIdPattern::Zero,
+                ecn_support: false,
+                cwr_flag: false,
+                window_scale: Some(2),
+                ecn_behavior: Some(EcnBehavior::NoEcn),
+                rst_data: None,
+                tos: Some(0),
+            }),
+            icmp_fingerprint: Some(IcmpFingerprint {
+                target,
+                echo_reply: Some(IcmpEchoReply {
+                    ttl: 60,
+                    payload_echo: true,
+                    code: 0,
+                    response_time_ms: 4,
+                    payload_size: 56,
+                    tos_value: 0,
+                }),
+                unreachable_behavior: IcmpUnreachableBehavior {
+                    port_unreachable_code: Some(3),
+                    host_unreachable_code: None,
+                    protocol_unreachable_code: None,
+                    echoes_data: true,
+                    data_length: 8,
+                },
+                timestamp_behavior: IcmpTimestampBehavior::NoResponse,
+                rate_limiting: IcmpRateLimiting {
+                    has_rate_limiting: false,
+                    limit_per_second: None,
+                    burst_size: None,
+                    pattern: RateLimitPattern::None,
+                },
+            }),
+            udp_fingerprint: None,
+            protocol_hints: None,
+            clock_skew: None,
+            passive_fingerprint: None,
+            active_probes: None,
+            detection_time_ms: 1200,
+        }
+    }
+
+    #[test]
+    fn test_id_ignores_target_and_timing() {
+        let mut other_host = fingerprint("10.0.0.2", 5840);
+        other_host.detection_time_ms = 99;
+        assert_eq!(fingerprint_id(&fingerprint("10.0.0.1", 5840)), fingerprint_id(&other_host));
+        assert_ne!(
+            fingerprint_id(&fingerprint("10.0.0.1", 5840)),
+            fingerprint_id(&fingerprint("10.0.0.1", 8192))
+        );
+    }
+
+    #[test]
+    fn test_record_and_take() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LearningStore::new(dir.path().join(UNKNOWN_FINGERPRINTS_FILE));
+        assert!(store.load().unwrap().is_empty());
+
+        let first = UnknownFingerprint::new(fingerprint("10.0.0.1", 5840), None);
+        assert!(store.record(&first).unwrap());
+        assert!(!store.record(&UnknownFingerprint::new(fingerprint("10.0.0.2", 5840), None)).unwrap());
+        assert!(store.record(&UnknownFingerprint::new(fingerprint("10.0.0.3", 8192), None)).unwrap());
+        assert_eq!(store.load().unwrap().len(), 2);
+
+        let taken = store.take(&first.id[..8]).unwrap();
+        assert_eq!(taken.fingerprint.target.to_string(), "10.0.0.1");
+        assert_eq!(store.load().unwrap().len(), 1);
+        assert!(store.take(&first.id).is_err());
+    }
+
+    #[test]
+    fn test_label_to_signature() {
+        let unknown = UnknownFingerprint::new(fingerprint("10.0.0.1", 5840), None);
+        let signature = unknown.to_signature("Acme NAS", OsFamily::Embedded, Some("4.2".to_string())).unwrap();
+        let tcp = signature.tcp_signature.unwrap();
+        assert_eq!(tcp.window_size_range, (5840, 5840));
+        assert_eq!(tcp.typical_mss, Some(1380));
+        assert_eq!(tcp.ip_id_pattern, IpIdPattern::Zero);
+        assert_eq!(signature.icmp_signature.unwrap().ttl_range, (60, 64));
+
+        let mut empty = unknown.clone();
+        empty.fingerprint.tcp_fingerprint = None;
+        empty.fingerprint.icmp_fingerprint = None;
+        assert!(empty.to_signature("Nothing", OsFamily::Unknown, None).is_err());
+    }
+}
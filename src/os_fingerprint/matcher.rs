@@ -117,14 +117,14 @@ impl OsMatcher {
         
         // TCP fingerprint matching (70% weight)
         if let (Some(fp_tcp), Some(sig_tcp)) = (&fingerprint.tcp_fingerprint, &signature.tcp_signature) {
-            let tcp_score = self.match_tcp_fingerprint(fp_tcp, sig_tcp);
+            let tcp_score = Self::match_tcp_fingerprint(fp_tcp, sig_tcp);
             total_score += tcp_score * 0.7;
             total_weight += 0.7;
         }
         
         // ICMP fingerprint matching (30% weight)
         if let (Some(fp_icmp), Some(sig_icmp)) = (&fingerprint.icmp_fingerprint, &signature.icmp_signature) {
-            let icmp_score = self.match_icmp_fingerprint(fp_icmp, sig_icmp);
+            let icmp_score = Self::match_icmp_fingerprint(fp_icmp, sig_icmp);
             total_score += icmp_score * 0.3;
             total_weight += 0.3;
         }
@@ -137,9 +137,8 @@ impl OsMatcher {
         }
     }
 
-    /// Match TCP fingerprints
-    fn match_tcp_fingerprint(
-        &self,
+    /// Match TCP fingerprints (0.0 - 1.0)
+    pub(super) fn match_tcp_fingerprint(
         fingerprint: &super::tcp_fingerprint::TcpFingerprint,
         signature: &super::fingerprint_db::TcpSignature,
     ) -> f64 {
@@ -187,18 +186,47 @@ impl OsMatcher {
         }
         
         // TCP options matching (10% weight)
-        let options_similarity = self.match_tcp_options(
+        let options_similarity = Self::match_tcp_options(
             &fingerprint.tcp_options,
             &signature.tcp_options_pattern,
         );
         score += options_similarity * 0.10;
         
-        score
+        // Stack quirks weigh in on top of the base score, only when both
+        // the fingerprint and the signature have them
+        let mut quirk_score = 0.0;
+        let mut quirk_weight = 0.0;
+        let mut check = |weight: f64, matched: Option<bool>| {
+            if let Some(matched) = matched {
+                quirk_weight += weight;
+                if matched {
+                    quirk_score += weight;
+                }
+            }
+        };
+        
+        // Exact option order (15% weight)
+        let orders = signature.options_orders();
+        let fp_order = super::tcp_fingerprint::options_order(&fingerprint.tcp_options);
+        check(0.15, (!orders.is_empty() && !fp_order.is_empty()).then(|| orders.contains(&fp_order)));
+        
+        // Window scale (10% weight)
+        check(0.10, fingerprint.window_scale.zip(signature.window_scale).map(|(fp, sig)| fp == sig));
+        
+        // ECN negotiation (10% weight)
+        check(0.10, fingerprint.ecn_behavior.zip(signature.ecn_behavior).map(|(fp, sig)| fp == sig));
+        
+        // RST data (5% weight)
+        check(0.05, fingerprint.rst_data.zip(signature.rst_data).map(|(fp, sig)| fp == sig));
+        
+        // DSCP marking (5% weight)
+        check(0.05, fingerprint.tos.zip(signature.dscp).map(|(tos, dscp)| tos >> 2 == dscp));
+        
+        (score + quirk_score) / (1.0 + quirk_weight)
     }
 
-    /// Match ICMP fingerprints
-    fn match_icmp_fingerprint(
-        &self,
+    /// Match ICMP fingerprints (0.0 - 1.0)
+    pub(super) fn match_icmp_fingerprint(
         fingerprint: &super::icmp_fingerprint::IcmpFingerprint,
         signature: &super::fingerprint_db::IcmpSignature,
     ) -> f64 {
@@ -231,7 +259,6 @@ impl OsMatcher {
 
     /// Match TCP options ordering
     fn match_tcp_options(
-        &self,
         fingerprint_options: &[super::tcp_fingerprint::TcpOption],
         signature_options: &[super::tcp_fingerprint::TcpOption],
     ) -> f64 {
@@ -275,6 +302,27 @@ impl OsMatcher {
             if fp_tcp.ip_id_pattern == sig_tcp.ip_id_pattern {
                 features.push(format!("IP ID: {:?}", fp_tcp.ip_id_pattern));
             }
+            
+            let fp_order = super::tcp_fingerprint::options_order(&fp_tcp.tcp_options);
+            if !fp_order.is_empty() && sig_tcp.options_orders().contains(&fp_order) {
+                features.push(format!("Options: {}", fp_order));
+            }
+            
+            if let Some(scale) = fp_tcp.window_scale.filter(|&scale| Some(scale) == sig_tcp.window_scale) {
+                features.push(format!("Window Scale: {}", scale));
+            }
+            
+            if let Some(ecn) = fp_tcp.ecn_behavior.filter(|&ecn| Some(ecn) == sig_tcp.ecn_behavior) {
+                features.push(format!("ECN: {:?}", ecn));
+            }
+            
+            if let Some(true) = fp_tcp.rst_data.zip(sig_tcp.rst_data).map(|(fp, sig)| fp && sig) {
+                features.push("RST Data".to_string());
+            }
+            
+            if let Some(tos) = fp_tcp.tos.filter(|&tos| Some(tos >> 2) == sig_tcp.dscp) {
+                features.push(format!("DSCP: {}", tos >> 2));
+            }
         }
         
         if let (Some(fp_icmp), Some(sig_icmp)) = (&fingerprint.icmp_fingerprint, &signature.icmp_signature) {
@@ -350,5 +398,40 @@ mod tests {
         let result = matcher.match_fingerprint(&fingerprint);
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_stack_quirks_separate_equal_base_signatures() {
+        use super::super::tcp_fingerprint::{EcnBehavior, TcpFingerprintAnalyzer};
+
+        let linux = OsFingerprintDatabase::new().get_signature("Linux 2.6+").unwrap().clone();
+        let mut lookalike = linux.clone();
+        lookalike.os_name = "Lookalike".to_string();
+        let tcp = lookalike.tcp_signature.as_mut().unwrap();
+        tcp.options_order = Some("MNWST".to_string());
+        tcp.window_scale = Some(6);
+        tcp.ecn_behavior = Some(EcnBehavior::Ece);
+        let mut db = OsFingerprintDatabase::empty();
+        db.add_signature(linux);
+        db.add_signature(lookalike);
+
+        let target = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let fingerprint = OsFingerprint {
+            target,
+            tcp_fingerprint: Some(TcpFingerprintAnalyzer::new().analyze(target, 80).await.unwrap()),
+            icmp_fingerprint: None,
+            udp_fingerprint: None,
+            protocol_hints: None,
+            clock_skew: None,
+            passive_fingerprint: None,
+            active_probes: None,
+            detection_time_ms: 100,
+        };
+
+        let results = OsMatcher::new(db).match_fingerprint(&fingerprint).unwrap();
+        assert_eq!(results[0].os_name, "Linux 2.6+");
+        assert!(results[0].confidence_score > results[1].confidence_score);
+        assert!(results[0].matching_features.contains(&"Options: MSTNW".to_string()));
+        assert!(results[0].matching_features.contains(&"Window Scale: 7".to_string()));
+    }
 }
 
@@ -11,8 +11,15 @@ pub mod fingerprint_db;
 pub mod matcher;
 pub mod clock_skew;
 pub mod passive;
+#[cfg(not(feature = "no-net"))]
+pub mod passive_capture;
 pub mod active_probes;
 pub mod database_io;
+pub mod nmap_os_db;
+pub mod user_db;
+pub mod learning;
+pub mod evidence_fusion;
+pub mod cache;
 pub mod fuzzy_matcher;
 
 pub use tcp_fingerprint::{TcpFingerprint, TcpFingerprintAnalyzer};
@@ -21,16 +28,23 @@ pub use udp_fingerprint::{UdpFingerprint, UdpFingerprintAnalyzer};
 pub use protocol_hints::{ProtocolHints, ProtocolHintsAnalyzer};
 pub use fingerprint_db::{OsFingerprintDatabase, OsSignature};
 pub use matcher::{OsMatcher, OsMatchResult, MatchConfidence};
-pub use clock_skew::{ClockSkewAnalyzer, ClockSkewAnalysis};
+pub use clock_skew::{ClockSkewAnalyzer, ClockSkewAnalysis, TimestampAnomaly};
 pub use passive::{PassiveAnalyzer, PassiveFingerprintResult, PassiveObservation};
-pub use active_probes::{ActiveProbeLibrary, ActiveProbeResults, TcpProbeType, SeqAnalysis, SeqPredictability};
+#[cfg(not(feature = "no-net"))]
+pub use passive_capture::PassiveCaptureService;
+pub use active_probes::{ActiveProbe, ActiveProbeLibrary, ActiveProbeResults, ProbeBudget, TcpProbeType, SeqAnalysis, SeqPredictability};
 pub use database_io::{DatabaseIO, FingerprintDatabaseFile};
 pub use fuzzy_matcher::{FuzzyMatcher, DetailedMatchResult, FuzzyScore};
+pub use user_db::{OverlayReport, UserDatabase};
+pub use learning::{LearningStore, UnknownFingerprint};
+pub use evidence_fusion::{EvidenceFusion, EvidenceFusionConfig, EvidenceSource, FusionResult};
+pub use cache::OsResultCache;
 
 use crate::error::ScanResult;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
-use tracing::info;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
 /// Complete OS fingerprint combining multiple analysis techniques
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,8 +69,16 @@ pub struct OsFingerprintEngine {
     clock_skew_analyzer: ClockSkewAnalyzer,
     passive_analyzer: PassiveAnalyzer,
     active_probe_library: ActiveProbeLibrary,
+    probe_budget: ProbeBudget,
     database: OsFingerprintDatabase,
     matcher: OsMatcher,
+    /// Learning mode store and fuzzy match threshold
+    learning: Option<(LearningStore, f64)>,
+    fuzzy_matcher: Option<FuzzyMatcher>,
+    fusion: EvidenceFusion,
+    cache: Option<Arc<OsResultCache>>,
+    /// Hash of the signature database, part of the cache evidence hash
+    database_digest: u64,
 }
 
 impl OsFingerprintEngine {
@@ -74,11 +96,91 @@ impl OsFingerprintEngine {
             clock_skew_analyzer: ClockSkewAnalyzer::new(),
             passive_analyzer: PassiveAnalyzer::new(),
             active_probe_library: ActiveProbeLibrary::new(3000),
+            probe_budget: ProbeBudget::default(),
             database: database.clone(),
-            matcher: OsMatcher::new(database),
+            matcher: OsMatcher::new(database.clone()),
+            learning: None,
+            fuzzy_matcher: None,
+            fusion: EvidenceFusion::default(),
+            cache: None,
+            database_digest: Self::database_digest(&database),
         }
     }
 
+    /// Record fingerprints the fuzzy matcher scores below `threshold` for
+    /// every signature (see [`learning`])
+    pub fn with_learning(mut self, store: LearningStore, threshold: f64) -> Self {
+        info!("OS fingerprint learning mode: unknowns go to {:?}", store.path());
+        self.learning = Some((store, threshold));
+        self.rebuild_fuzzy_matcher();
+        self
+    }
+
+    /// Reuse detections from a result cache (see [`cache`])
+    pub fn with_cache(mut self, cache: Arc<OsResultCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn database_digest(database: &OsFingerprintDatabase) -> u64 {
+        let mut names: Vec<&String> = database.signatures().keys().collect();
+        names.sort();
+        let signatures: Vec<String> = names
+            .into_iter()
+            .map(|name| serde_json::to_string(&database.signatures()[name]).unwrap_or_default())
+            .collect();
+        cache::evidence_hash(&signatures.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Cache evidence hash of a detection with these probe settings
+    fn evidence_hash(&self, open_port: u16, closed_port: Option<u16>, use_active_probes: bool) -> u64 {
+        let fusion = serde_json::to_string(self.fusion.config()).unwrap_or_default();
+        cache::evidence_hash(&[
+            &open_port.to_string(),
+            &format!("{:?}", closed_port),
+            &use_active_probes.to_string(),
+            &format!("{:016x}", self.database_digest),
+            &fusion,
+        ])
+    }
+
+    fn rebuild_fuzzy_matcher(&mut self) {
+        self.fuzzy_matcher = self.learning.as_ref().map(|(store, threshold)| {
+            FuzzyMatcher::new(self.database.clone(), *threshold).with_learning(store.clone())
+        });
+    }
+
+    /// Merge a user signature database over the current one
+    ///
+    /// User signatures replace built-in ones of the same name (see
+    /// [`user_db`] for the precedence rules).
+    pub fn with_user_database(mut self, user: &UserDatabase) -> Self {
+        let (database, report) = user.merge_over(&self.database);
+        info!(
+            "Merged {} user OS signatures from {:?} ({} added, {} overriding built-in)",
+            user.len(),
+            user.path(),
+            report.added.len(),
+            report.overridden.len()
+        );
+        for name in &report.overridden {
+            debug!("User OS signature '{}' overrides the built-in one", name);
+        }
+        self.load_database(database);
+        self
+    }
+
+    /// Apply the active probe and evidence fusion settings from a configuration
+    pub fn with_config(mut self, config: &OsFingerprintConfig) -> Self {
+        self.active_probe_library = ActiveProbeLibrary::new(config.active_probes_timeout_ms);
+        self.probe_budget = config.active_probe_budget.clone();
+        match config.evidence_fusion.validate() {
+            Ok(()) => self.fusion = EvidenceFusion::new(config.evidence_fusion.clone()),
+            Err(e) => warn!("Ignoring evidence fusion settings: {}", e),
+        }
+        self
+    }
+
     /// Perform comprehensive OS fingerprinting on a target
     /// 
     /// # Arguments
@@ -86,6 +188,7 @@ impl OsFingerprintEngine {
     /// * `open_port` - Open port to use for TCP fingerprinting
     /// * `closed_port` - Closed port for some probes
     /// * `use_active_probes` - Whether to use active probe library (more intrusive)
+    #[cfg(not(feature = "no-net"))]
     pub async fn fingerprint(
         &self,
         target: IpAddr,
@@ -122,17 +225,7 @@ impl OsFingerprintEngine {
         // Passive fingerprinting (if observations are available)
         let passive_fingerprint = self.passive_analyzer.analyze(target).ok();
         
-        // Active probe library (most comprehensive but intrusive)
-        let active_probes = if use_active_probes {
-            let closed = closed_port.unwrap_or(open_port + 1);
-            self.active_probe_library.probe_all(target, open_port, closed).await.ok()
-        } else {
-            None
-        };
-        
-        let detection_time_ms = start_time.elapsed().as_millis() as u64;
-        
-        Ok(OsFingerprint {
+        let mut fingerprint = OsFingerprint {
             target,
             tcp_fingerprint,
             icmp_fingerprint,
@@ -140,26 +233,67 @@ impl OsFingerprintEngine {
             protocol_hints,
             clock_skew,
             passive_fingerprint,
-            active_probes,
-            detection_time_ms,
-        })
+            active_probes: None,
+            detection_time_ms: 0,
+        };
+        
+        // Active probe library (most comprehensive but intrusive), stopping
+        // once the evidence so far identifies the host well enough
+        if use_active_probes {
+            let closed = closed_port.unwrap_or(open_port + 1);
+            let mut partial = fingerprint.clone();
+            fingerprint.active_probes = self.active_probe_library
+                .probe_with_budget(target, open_port, closed, &self.probe_budget, |results| {
+                    partial.active_probes = Some(results.clone());
+                    self.best_confidence(&partial)
+                })
+                .await
+                .ok();
+        }
+        
+        fingerprint.detection_time_ms = start_time.elapsed().as_millis() as u64;
+        Ok(fingerprint)
+    }
+
+    /// Confidence of the best database match for a fingerprint
+    fn best_confidence(&self, fingerprint: &OsFingerprint) -> f64 {
+        self.matcher
+            .match_fingerprint(fingerprint)
+            .ok()
+            .and_then(|matches| matches.first().map(|m| m.confidence_score))
+            .unwrap_or(0.0)
     }
 
     /// Match a fingerprint against the database
     /// 
+    /// With evidence fusion enabled the confidence scores are the fused
+    /// probabilities.
+    /// 
     /// # Arguments
     /// * `fingerprint` - The OS fingerprint to match
     pub fn match_os(&self, fingerprint: &OsFingerprint) -> ScanResult<Vec<OsMatchResult>> {
+        if self.fusion.config().enabled {
+            return Ok(self.fuse(fingerprint).to_match_results(5));
+        }
         self.matcher.match_fingerprint(fingerprint)
     }
 
+    /// Fuse the evidence of all techniques into a probability per signature
+    pub fn fuse(&self, fingerprint: &OsFingerprint) -> FusionResult {
+        self.fusion.fuse(fingerprint, &self.database)
+    }
+
     /// Perform fingerprinting and matching in one call
     /// 
+    /// With a result cache, an unexpired detection of the target with the
+    /// same probe settings and database is returned without probing.
+    /// 
     /// # Arguments
     /// * `target` - Target IP address
     /// * `open_port` - Open port to use for TCP fingerprinting
     /// * `closed_port` - Optional closed port
     /// * `use_active_probes` - Whether to use active probes
+    #[cfg(not(feature = "no-net"))]
     pub async fn detect_os(
         &self,
         target: IpAddr,
@@ -167,8 +301,23 @@ impl OsFingerprintEngine {
         closed_port: Option<u16>,
         use_active_probes: bool,
     ) -> ScanResult<Vec<OsMatchResult>> {
+        let evidence = self.evidence_hash(open_port, closed_port, use_active_probes);
+        if let Some(matches) = self.cache.as_ref().and_then(|cache| cache.get(target, evidence)) {
+            debug!("Using cached OS detection for {}", target);
+            return Ok(matches);
+        }
+        
         let fingerprint = self.fingerprint(target, open_port, closed_port, use_active_probes).await?;
-        self.match_os(&fingerprint)
+        if let Some(ref fuzzy_matcher) = self.fuzzy_matcher {
+            fuzzy_matcher.match_with_details(&fingerprint)?;
+        }
+        let matches = self.match_os(&fingerprint)?;
+        if let Some(ref cache) = self.cache {
+            if !matches.is_empty() {
+                cache.insert(target, evidence, matches.clone());
+            }
+        }
+        Ok(matches)
     }
 
     /// Get the fingerprint database
@@ -179,8 +328,10 @@ impl OsFingerprintEngine {
     /// Load custom fingerprint database
     pub fn load_database(&mut self, database: OsFingerprintDatabase) {
         info!("Loading custom fingerprint database");
+        self.database_digest = Self::database_digest(&database);
         self.database = database.clone();
         self.matcher = OsMatcher::new(database);
+        self.rebuild_fuzzy_matcher();
     }
 
     /// Get analyzer references for custom configuration
@@ -211,6 +362,10 @@ impl OsFingerprintEngine {
     pub fn active_probe_library(&mut self) -> &mut ActiveProbeLibrary {
         &mut self.active_probe_library
     }
+
+    pub fn probe_budget(&mut self) -> &mut ProbeBudget {
+        &mut self.probe_budget
+    }
 }
 
 impl Default for OsFingerprintEngine {
@@ -239,6 +394,12 @@ pub struct OsFingerprintConfig {
     pub seq_probes_count: usize,
     pub max_retries: u8,
     pub confidence_threshold: f64,
+    /// Ordering, packet limit and early stop for active probes
+    #[serde(default)]
+    pub active_probe_budget: ProbeBudget,
+    /// Per-technique weights and constraints for fused match probabilities
+    #[serde(default)]
+    pub evidence_fusion: EvidenceFusionConfig,
 }
 
 impl Default for OsFingerprintConfig {
@@ -261,6 +422,8 @@ impl Default for OsFingerprintConfig {
             seq_probes_count: 6,
             max_retries: 2,
             confidence_threshold: 0.75,
+            active_probe_budget: ProbeBudget::default(),
+            evidence_fusion: EvidenceFusionConfig::default(),
         }
     }
 }
@@ -281,9 +444,63 @@ mod tests {
         assert!(config.enable_tcp_fingerprinting);
         assert!(config.enable_icmp_fingerprinting);
         assert_eq!(config.tcp_timeout_ms, 5000);
+        assert_eq!(config.active_probe_budget.stop_confidence, Some(0.75));
+    }
+
+    #[test]
+    fn test_config_probe_budget_from_json() {
+        let mut value = serde_json::to_value(OsFingerprintConfig::default()).unwrap();
+        value["active_probe_budget"] = serde_json::json!({
+            "max_packets": 8,
+            "priority": ["t1", "ie", "t5"]
+        });
+        let config: OsFingerprintConfig = serde_json::from_value(value).unwrap();
+        let budget = &config.active_probe_budget;
+        assert_eq!(budget.max_packets, 8);
+        assert_eq!(budget.priority, vec![ActiveProbe::T1, ActiveProbe::Ie, ActiveProbe::T5]);
+        assert!(budget.skip_redundant);
+
+        let mut value = serde_json::to_value(OsFingerprintConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("active_probe_budget");
+        let config: OsFingerprintConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.active_probe_budget, ProbeBudget::default());
+    }
+
+    #[test]
+    fn test_config_evidence_fusion_from_json() {
+        let mut value = serde_json::to_value(OsFingerprintConfig::default()).unwrap();
+        value["evidence_fusion"] = serde_json::json!({
+            "enabled": true,
+            "weights": { "tcp": 4.0 },
+            "must_match": ["tcp", "protocol_hints"]
+        });
+        let config: OsFingerprintConfig = serde_json::from_value(value).unwrap();
+        let fusion = &config.evidence_fusion;
+        assert!(fusion.enabled);
+        assert_eq!(fusion.weights.tcp, 4.0);
+        assert_eq!(fusion.weights.icmp, 2.0);
+        assert_eq!(fusion.must_match, vec![EvidenceSource::Tcp, EvidenceSource::ProtocolHints]);
+
+        // Fused probabilities replace the matcher's scores
+        let engine = OsFingerprintEngine::new().with_config(&config);
+        let fingerprint = OsFingerprint {
+            target: "192.0.2.1".parse().unwrap(),
+            tcp_fingerprint: None,
+            icmp_fingerprint: None,
+            udp_fingerprint: None,
+            protocol_hints: None,
+            clock_skew: None,
+            passive_fingerprint: None,
+            active_probes: None,
+            detection_time_ms: 0,
+        };
+        let matches = engine.match_os(&fingerprint).unwrap();
+        let total: f64 = matches.iter().map(|m| m.confidence_score).sum();
+        assert!(!matches.is_empty() && total <= 1.0);
     }
 
     #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
     async fn test_fingerprint_structure() {
         use std::net::Ipv4Addr;
         
@@ -0,0 +1,450 @@
+//! Import of Nmap `nmap-os-db` signatures
+//!
+//! Parses the Nmap OS detection database format: a `Fingerprint` line
+//! naming the OS, `Class` and `CPE` lines, and one line per test
+//! (`SEQ`, `OPS`, `WIN`, `ECN`, `T1`-`T7`, `U1`, `IE`) holding
+//! `%`-separated attributes whose values are hex numbers, `a-b` ranges,
+//! `>a`/`<a` bounds or `|`-separated alternatives. Each fingerprint is
+//! converted to an [`OsSignature`]:
+//!
+//! * TTL: the initial TTL guess `TG` of `T1` (TCP) and `IE` (ICMP)
+//! * window: the range covering `WIN` `W1`-`W6`
+//! * options, option order, MSS and window scale: `OPS` `O1`
+//! * DF: `T1` `DF`; RST behaviour: whether `T4`-`T7` answered
+//! * RST data: `RD` of the `T4`-`T7` answers
+//! * IP ID pattern: `SEQ` `TI`; ECN: `ECN` `CC`
+//! * quoted data length: `U1` `IPL`
+//!
+//! Tests the signature model has no room for (`SP`, `GCD`, `ISR`, the
+//! `T2`-`T7` flag details, ...) are kept in [`NmapFingerprint::tests`] but
+//! not used for matching.
+
+use super::fingerprint_db::{IcmpSignature, OsFamily, OsSignature, TcpSignature};
+use super::icmp_fingerprint::{IcmpTimestampBehavior, RateLimitPattern};
+use super::tcp_fingerprint::{options_order, EcnBehavior, IpIdPattern, RstBehavior, TcpOption};
+use crate::error::{ScanError, ScanResult};
+use std::collections::BTreeMap;
+
+/// `Class` line: vendor, OS family, generation and device type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NmapClass {
+    pub vendor: String,
+    pub os_family: String,
+    pub generation: String,
+    pub device_type: String,
+}
+
+/// One `Fingerprint` entry of an nmap-os-db file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NmapFingerprint {
+    pub name: String,
+    pub classes: Vec<NmapClass>,
+    pub cpe: Vec<String>,
+    /// Attributes by test name (`SEQ`, `T1`, ...) and attribute name
+    pub tests: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Parse the fingerprints of an nmap-os-db file
+///
+/// `MatchPoints`, comments and unknown lines are skipped; a malformed test
+/// line is an error naming its line number.
+pub fn parse(content: &str) -> ScanResult<Vec<NmapFingerprint>> {
+    let mut fingerprints = Vec::new();
+    let mut current: Option<NmapFingerprint> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("Fingerprint ") {
+            fingerprints.extend(current.take());
+            current = Some(NmapFingerprint {
+                name: name.trim().to_string(),
+                ..NmapFingerprint::default()
+            });
+            continue;
+        }
+        if line == "MatchPoints" {
+            fingerprints.extend(current.take());
+            continue;
+        }
+        // Test lines before the first fingerprint belong to MatchPoints
+        let Some(fingerprint) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(class) = line.strip_prefix("Class ") {
+            let fields: Vec<&str> = class.split('|').map(str::trim).collect();
+            let field = |i: usize| fields.get(i).copied().unwrap_or_default().to_string();
+            fingerprint.classes.push(NmapClass {
+                vendor: field(0),
+                os_family: field(1),
+                generation: field(2),
+                device_type: field(3),
+            });
+        } else if let Some(cpe) = line.strip_prefix("CPE ") {
+            let cpe = cpe.split_whitespace().next().unwrap_or_default();
+            fingerprint.cpe.push(cpe.to_string());
+        } else if let Some((test, attributes)) = parse_test(line) {
+            fingerprint.tests.insert(test, attributes);
+        } else {
+            return Err(ScanError::validation_error(
+                "nmap-os-db",
+                format!("line {}: malformed test line '{}'", index + 1, line),
+            ));
+        }
+    }
+    fingerprints.extend(current);
+    Ok(fingerprints)
+}
+
+/// `T1(R=Y%DF=Y)` as the test name and its attributes
+fn parse_test(line: &str) -> Option<(String, BTreeMap<String, String>)> {
+    let (name, rest) = line.split_once('(')?;
+    let body = rest.strip_suffix(')')?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let mut attributes = BTreeMap::new();
+    for attribute in body.split('%').filter(|a| !a.is_empty()) {
+        let (key, value) = attribute.split_once('=')?;
+        attributes.insert(key.to_string(), value.to_string());
+    }
+    Some((name.to_string(), attributes))
+}
+
+/// Ranges of a numeric attribute expression such as `F8-102|>4|0`
+fn value_ranges(expression: &str) -> Vec<(u32, u32)> {
+    expression
+        .split('|')
+        .filter_map(|alternative| {
+            let hex = |s: &str| u32::from_str_radix(s, 16).ok();
+            if let Some(bound) = alternative.strip_prefix('>') {
+                return Some((hex(bound)?.saturating_add(1), u32::MAX));
+            }
+            if let Some(bound) = alternative.strip_prefix('<') {
+                return Some((0, hex(bound)?.saturating_sub(1)));
+            }
+            match alternative.split_once('-') {
+                Some((low, high)) => Some((hex(low)?, hex(high)?)),
+                None => hex(alternative).map(|v| (v, v)),
+            }
+        })
+        .collect()
+}
+
+/// Smallest range covering every value of the given expressions
+fn covering_range<'a>(expressions: impl IntoIterator<Item = &'a String>) -> Option<(u32, u32)> {
+    expressions
+        .into_iter()
+        .flat_map(|expression| value_ranges(expression))
+        .fold(None, |range, (low, high)| match range {
+            None => Some((low, high)),
+            Some((l, h)) => Some((l.min(low), h.max(high))),
+        })
+}
+
+/// TCP options, MSS and window scale of an `O1`-style string such as
+/// `M5B4ST11NW7`
+fn parse_options(options: &str) -> (Vec<TcpOption>, Option<u16>, Option<u8>) {
+    let mut parsed = Vec::new();
+    let mut mss = None;
+    let mut window_scale = None;
+    let mut chars = options.chars().peekable();
+    while let Some(kind) = chars.next() {
+        // Option letters are never hex digits, so the value is the run of
+        // hex digits that follows
+        let mut value = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_hexdigit) {
+            value.push(c);
+        }
+        match kind {
+            'M' => {
+                mss = u16::from_str_radix(&value, 16).ok();
+                parsed.push(TcpOption::Mss);
+            }
+            'W' => {
+                window_scale = u8::from_str_radix(&value, 16).ok();
+                parsed.push(TcpOption::WindowScale);
+            }
+            'T' => parsed.push(TcpOption::Timestamp),
+            'S' => parsed.push(TcpOption::SackPermitted),
+            'N' => parsed.push(TcpOption::Nop),
+            'L' => parsed.push(TcpOption::EndOfOptions),
+            _ => {}
+        }
+    }
+    (parsed, mss, window_scale)
+}
+
+impl NmapFingerprint {
+    fn attribute(&self, test: &str, name: &str) -> Option<&String> {
+        self.tests.get(test)?.get(name)
+    }
+
+    fn responded(&self, test: &str) -> bool {
+        self.tests.contains_key(test) && self.attribute(test, "R").is_none_or(|r| r != "N")
+    }
+
+    /// TTL range from the initial TTL guess, else the observed TTL
+    fn ttl_range(&self, test: &str) -> Option<(u8, u8)> {
+        let (low, high) = self
+            .attribute(test, "TG")
+            .or_else(|| self.attribute(test, "T"))
+            .and_then(|expression| covering_range([expression]))?;
+        Some((low.min(255) as u8, high.min(255) as u8))
+    }
+
+    /// OS family from the first `Class` line
+    pub fn os_family(&self) -> OsFamily {
+        let Some(class) = self.classes.first() else {
+            return OsFamily::Unknown;
+        };
+        let family = class.os_family.to_ascii_lowercase();
+        match family.as_str() {
+            _ if class.vendor.eq_ignore_ascii_case("cisco") => OsFamily::Cisco,
+            "linux" | "android" => OsFamily::Linux,
+            "windows" => OsFamily::Windows,
+            "mac os x" | "macos" | "ios" | "ipados" if class.vendor.eq_ignore_ascii_case("apple") => {
+                OsFamily::MacOS
+            }
+            f if f.contains("bsd") => OsFamily::BSD,
+            "solaris" | "aix" | "hp-ux" | "irix" | "unix" => OsFamily::Unix,
+            _ if !class.device_type.eq_ignore_ascii_case("general purpose") => OsFamily::Embedded,
+            _ => OsFamily::Unknown,
+        }
+    }
+
+    fn tcp_signature(&self) -> Option<TcpSignature> {
+        if !self.responded("T1") && !self.tests.contains_key("WIN") {
+            return None;
+        }
+        let windows: Vec<&String> = (1..=6)
+            .filter_map(|i| self.attribute("WIN", &format!("W{}", i)))
+            .collect();
+        let (window_low, window_high) = covering_range(windows).unwrap_or((0, u32::from(u16::MAX)));
+        // Alternatives differ in option values or order; the first one
+        // gives the pattern, all of them the accepted orders
+        let alternatives: Vec<&str> = self
+            .attribute("OPS", "O1")
+            .map(|o| o.split('|').filter(|a| !a.is_empty()).collect())
+            .unwrap_or_default();
+        let (options, mss, window_scale) = alternatives.first().map(|o| parse_options(o)).unwrap_or_default();
+        let mut orders: Vec<String> = alternatives.iter().map(|o| options_order(&parse_options(o).0)).collect();
+        orders.dedup();
+        let rst_tests: Vec<&str> = ["T4", "T5", "T6", "T7"]
+            .into_iter()
+            .filter(|test| self.responded(test))
+            .collect();
+        let rst_answered = !rst_tests.is_empty();
+        let rst_data = rst_tests
+            .iter()
+            .filter_map(|test| self.attribute(test, "RD"))
+            .map(|rd| rd != "0")
+            .reduce(|a, b| a || b);
+        let ecn_behavior = match self.attribute("ECN", "CC").map(String::as_str) {
+            Some("N") => Some(EcnBehavior::NoEcn),
+            Some("Y") => Some(EcnBehavior::Ece),
+            Some("S") => Some(EcnBehavior::EceCwr),
+            Some("O") => Some(EcnBehavior::Other),
+            _ => None,
+        };
+        let ip_id_pattern = match self.attribute("SEQ", "TI").map(String::as_str) {
+            Some("Z") => IpIdPattern::Zero,
+            Some("RD") | Some("RI") => IpIdPattern::Random,
+            Some("I") | Some("BI") => IpIdPattern::Incremental,
+            Some(fixed) => u16::from_str_radix(fixed, 16).map_or(IpIdPattern::Unknown, IpIdPattern::Fixed),
+            None => IpIdPattern::Unknown,
+        };
+
+        Some(TcpSignature {
+            ttl_range: self.ttl_range("T1").or_else(|| self.ttl_range("ECN")).unwrap_or((0, 255)),
+            window_size_range: (window_low.min(65535) as u16, window_high.min(65535) as u16),
+            typical_mss: mss,
+            tcp_options_pattern: options,
+            df_flag: self.attribute("T1", "DF").is_some_and(|df| df == "Y"),
+            rst_behavior: if rst_answered { RstBehavior::Immediate } else { RstBehavior::None },
+            ip_id_pattern,
+            ecn_support: self.attribute("ECN", "CC").is_some_and(|cc| cc == "Y"),
+            options_order: (!orders.is_empty()).then(|| orders.join("|")),
+            window_scale,
+            ecn_behavior,
+            rst_data,
+            dscp: None,
+        })
+    }
+
+    fn icmp_signature(&self) -> Option<IcmpSignature> {
+        if !self.responded("IE") {
+            return None;
+        }
+        // IPL is the whole port unreachable; less the outer IP and ICMP
+        // headers and the quoted IP header, the rest is quoted data
+        let quoted = self
+            .attribute("U1", "IPL")
+            .and_then(|ipl| u32::from_str_radix(ipl, 16).ok())
+            .map_or(8, |ipl| ipl.saturating_sub(48) as usize);
+        Some(IcmpSignature {
+            ttl_range: self.ttl_range("IE").unwrap_or((0, 255)),
+            echoes_payload: true,
+            timestamp_behavior: IcmpTimestampBehavior::NoResponse,
+            rate_limit_pattern: RateLimitPattern::None,
+            unreachable_data_length: quoted,
+        })
+    }
+
+    /// Convert to a signature; `None` without any TCP or ICMP test data
+    pub fn to_signature(&self) -> Option<OsSignature> {
+        let tcp_signature = self.tcp_signature();
+        let icmp_signature = self.icmp_signature();
+        if tcp_signature.is_none() && icmp_signature.is_none() {
+            return None;
+        }
+        let os_version = self
+            .classes
+            .first()
+            .map(|class| class.generation.clone())
+            .filter(|generation| !generation.is_empty());
+        Some(OsSignature {
+            os_name: self.name.clone(),
+            os_version,
+            os_family: self.os_family(),
+            tcp_signature,
+            icmp_signature,
+            confidence_weight: 1.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Sample of nmap-os-db
+MatchPoints
+SEQ(SP=25%GCD=75%ISR=25%TI=100%CI=50%II=100%SS=80%TS=100)
+T1(R=100%DF=20%T=15%TG=15%S=20%A=20%F=30%RD=20%Q=20)
+
+Fingerprint Linux 2.6.32 - 3.10
+Class Linux | Linux | 2.6.X | general purpose
+Class Linux | Linux | 3.X | general purpose
+CPE cpe:/o:linux:linux_kernel:2.6 auto
+SEQ(SP=F8-102%GCD=1-6%ISR=FB-105%TI=Z%CI=Z%II=I%TS=8)
+OPS(O1=M5B4ST11NW7%O2=M5B4ST11NW7%O3=M5B4NNT11NW7%O4=M5B4ST11NW7%O5=M5B4ST11NW7%O6=M5B4ST11)
+WIN(W1=3890%W2=3890%W3=3890%W4=3890%W5=3890%W6=3890)
+ECN(R=Y%DF=Y%T=3B-45%TG=40%W=3908%O=M5B4NNSNW7%CC=N%Q=)
+T1(R=Y%DF=Y%T=3B-45%TG=40%S=O%A=S+%F=AS%RD=0%Q=)
+T2(R=N)
+T3(R=N)
+T4(R=Y%DF=Y%T=3B-45%TG=40%W=0%S=A%A=Z%F=R%O=%RD=0%Q=)
+T5(R=Y%DF=Y%T=3B-45%TG=40%W=0%S=Z%A=S+%F=AR%O=%RD=0%Q=)
+T6(R=Y%DF=Y%T=3B-45%TG=40%W=0%S=A%A=Z%F=R%O=%RD=0%Q=)
+T7(R=Y%DF=Y%T=3B-45%TG=40%W=0%S=Z%A=S+%F=AR%O=%RD=0%Q=)
+U1(R=Y%DF=N%T=3B-45%TG=40%IPL=164%UN=0%RIPL=G%RID=G%RIPCK=G%RUCK=G%RUD=G)
+IE(R=Y%DFI=N%T=3B-45%TG=40%CD=S)
+
+Fingerprint Microsoft Windows 10 1709 - 1909
+Class Microsoft | Windows | 10 | general purpose
+CPE cpe:/o:microsoft:windows_10
+SEQ(SP=FF-109%GCD=1-6%ISR=108-112%TI=I%CI=I%II=I%SS=S%TS=A)
+OPS(O1=M5B4NW8ST11%O2=M5B4NW8ST11%O3=M5B4NW8NNT11%O4=M5B4NW8ST11%O5=M5B4NW8ST11%O6=M5B4ST11)
+WIN(W1=FFFF%W2=FFFF%W3=FFFF%W4=FFFF%W5=FFFF%W6=FF70|FFDC)
+ECN(R=Y%DF=Y%T=7B-85%TG=80%W=FFFF%O=M5B4NW8NNS%CC=N%Q=)
+T1(R=Y%DF=Y%T=7B-85%TG=80%S=O%A=S+%F=AS%RD=0%Q=)
+T2(R=N)
+T3(R=N)
+T4(R=N)
+T5(R=N)
+T6(R=N)
+T7(R=N)
+U1(R=N)
+IE(R=N)
+
+Fingerprint Cisco IOS 12.4
+Class Cisco | IOS | 12.X | router
+SEQ(SP=0-5%GCD=FA7F|1F4FE%ISR=8E-98%TI=RD%CI=RI%II=RI%SS=S%TS=U)
+T1(R=Y%DF=N%T=FA-104%TG=FF%S=O%A=S+%F=AS%RD=0%Q=)
+IE(R=Y%DFI=S%T=FA-104%TG=FF%CD=S)
+";
+
+    #[test]
+    fn test_parse_sample() {
+        let fingerprints = parse(SAMPLE).unwrap();
+        assert_eq!(fingerprints.len(), 3);
+
+        let linux = &fingerprints[0];
+        assert_eq!(linux.name, "Linux 2.6.32 - 3.10");
+        assert_eq!(linux.classes.len(), 2);
+        assert_eq!(linux.cpe, vec!["cpe:/o:linux:linux_kernel:2.6"]);
+        assert_eq!(linux.tests.len(), 13);
+        assert_eq!(linux.tests["T4"]["F"], "R");
+        assert_eq!(linux.tests["ECN"]["Q"], "");
+    }
+
+    #[test]
+    fn test_convert_to_signatures() {
+        let fingerprints = parse(SAMPLE).unwrap();
+
+        let linux = fingerprints[0].to_signature().unwrap();
+        assert_eq!(linux.os_family, OsFamily::Linux);
+        assert_eq!(linux.os_version.as_deref(), Some("2.6.X"));
+        let tcp = linux.tcp_signature.unwrap();
+        assert_eq!(tcp.ttl_range, (64, 64));
+        assert_eq!(tcp.window_size_range, (0x3890, 0x3890));
+        assert_eq!(tcp.typical_mss, Some(1460));
+        assert_eq!(
+            tcp.tcp_options_pattern,
+            vec![
+                TcpOption::Mss,
+                TcpOption::SackPermitted,
+                TcpOption::Timestamp,
+                TcpOption::Nop,
+                TcpOption::WindowScale
+            ]
+        );
+        assert!(tcp.df_flag && !tcp.ecn_support);
+        assert_eq!(tcp.options_order.as_deref(), Some("MSTNW"));
+        assert_eq!(tcp.window_scale, Some(7));
+        assert_eq!(tcp.ecn_behavior, Some(EcnBehavior::NoEcn));
+        assert_eq!(tcp.rst_data, Some(false));
+        assert_eq!(tcp.rst_behavior, RstBehavior::Immediate);
+        assert_eq!(tcp.ip_id_pattern, IpIdPattern::Zero);
+        let icmp = linux.icmp_signature.unwrap();
+        assert_eq!(icmp.ttl_range, (64, 64));
+        assert_eq!(icmp.unreachable_data_length, 0x164 - 48);
+
+        let windows = fingerprints[1].to_signature().unwrap();
+        assert_eq!(windows.os_family, OsFamily::Windows);
+        let tcp = windows.tcp_signature.unwrap();
+        assert_eq!(tcp.window_size_range, (0xFF70, 0xFFFF));
+        assert_eq!(tcp.ttl_range, (128, 128));
+        assert_eq!(tcp.rst_behavior, RstBehavior::None);
+        assert_eq!(tcp.ip_id_pattern, IpIdPattern::Incremental);
+        assert_eq!(tcp.options_order.as_deref(), Some("MNWST"));
+        assert_eq!(tcp.window_scale, Some(8));
+        assert_eq!(tcp.rst_data, None);
+        assert!(windows.icmp_signature.is_none());
+
+        let cisco = fingerprints[2].to_signature().unwrap();
+        assert_eq!(cisco.os_family, OsFamily::Cisco);
+        assert_eq!(cisco.tcp_signature.unwrap().ip_id_pattern, IpIdPattern::Random);
+        assert_eq!(cisco.icmp_signature.unwrap().ttl_range, (255, 255));
+    }
+
+    #[test]
+    fn test_value_expressions() {
+        assert_eq!(value_ranges("F8-102"), vec![(0xF8, 0x102)]);
+        assert_eq!(value_ranges("FF70|FFDC"), vec![(0xFF70, 0xFF70), (0xFFDC, 0xFFDC)]);
+        assert_eq!(value_ranges(">4"), vec![(5, u32::MAX)]);
+        assert_eq!(value_ranges("<10"), vec![(0, 15)]);
+        assert!(value_ranges("Z").is_empty());
+    }
+
+    #[test]
+    fn test_malformed_test_line() {
+        let err = parse("Fingerprint Broken\nT1(R=Y%DF\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
@@ -35,6 +35,9 @@ pub struct PassiveObservation {
     pub timestamp_us: u64,
     /// Whether the DF (Don't Fragment) flag is set
     pub df_flag: bool,
+    /// TCP timestamp value (TSval), if the timestamp option was present
+    #[serde(default)]
+    pub tcp_timestamp: Option<u32>,
 }
 
 /// Represents the results of passive fingerprinting
@@ -443,6 +446,11 @@ impl PassiveAnalyzer {
         debug!("Cleared observations for {}", target);
     }
 
+    /// Hosts with at least one observation
+    pub fn targets(&self) -> Vec<IpAddr> {
+        self.observations.keys().copied().collect()
+    }
+
     /// Gets the number of observations for a target
     pub fn observation_count(&self, target: IpAddr) -> usize {
         self.observations.get(&target).map(|v| v.len()).unwrap_or(0)
@@ -476,6 +484,7 @@ mod tests {
                 .unwrap()
                 .as_micros() as u64,
             df_flag: true,
+            tcp_timestamp: None,
         }
     }
 
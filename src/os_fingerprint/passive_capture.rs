@@ -0,0 +1,267 @@
+//! Passive fingerprinting fed by live packet capture
+//!
+//! Attaches to an interface, parses SYN and SYN-ACK segments with
+//! `PacketParser` and feeds them to a `PassiveAnalyzer` as
+//! `PassiveObservation`s, without sending a single probe.
+
+use crate::error::{ScanError, ScanResult};
+use crate::os_fingerprint::passive::{PassiveAnalyzer, PassiveFingerprintResult, PassiveObservation};
+use crate::packet::capture::{ip_payload, open_receiver};
+use crate::packet::{CaptureConfig, PacketParser, PacketType};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, trace, warn};
+
+/// Counters for a running passive capture
+#[derive(Debug, Default)]
+struct PassiveCounters {
+    frames_seen: AtomicU64,
+    observations: AtomicU64,
+}
+
+/// Continuous passive OS fingerprinting from a live interface
+pub struct PassiveCaptureService {
+    config: CaptureConfig,
+    analyzer: Arc<Mutex<PassiveAnalyzer>>,
+    counters: Arc<PassiveCounters>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PassiveCaptureService {
+    /// Create a service for the given capture settings (not yet started)
+    pub fn new(config: CaptureConfig) -> Self {
+        Self {
+            config,
+            analyzer: Arc::new(Mutex::new(PassiveAnalyzer::new())),
+            counters: Arc::new(PassiveCounters::default()),
+            running: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    /// Open the interface and start collecting observations
+    ///
+    /// Requires raw socket privileges (root or CAP_NET_RAW).
+    pub fn start(&mut self) -> ScanResult<()> {
+        if self.running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let (interface, mut rx) = open_receiver(&self.config, false)?;
+        info!("Starting passive fingerprinting on {}", interface.name);
+        self.running.store(true, Ordering::SeqCst);
+
+        let running = Arc::clone(&self.running);
+        let analyzer = Arc::clone(&self.analyzer);
+        let counters = Arc::clone(&self.counters);
+
+        let handle = std::thread::Builder::new()
+            .name("nrmap-passive".to_string())
+            .spawn(move || {
+                let parser = PacketParser::new(false);
+                while running.load(Ordering::Relaxed) {
+                    match rx.next() {
+                        Ok(frame) => {
+                            counters.frames_seen.fetch_add(1, Ordering::Relaxed);
+                            let Some(observation) = ip_payload(frame)
+                                .and_then(|ip| observation_from_packet(ip, &parser))
+                            else {
+                                continue;
+                            };
+                            counters.observations.fetch_add(1, Ordering::Relaxed);
+                            if let Ok(mut analyzer) = analyzer.lock() {
+                                analyzer.add_observation(observation);
+                            }
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                        Err(e) => {
+                            warn!("Passive capture read failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                running.store(false, Ordering::SeqCst);
+                debug!("Passive capture thread stopped");
+            })
+            .map_err(ScanError::Io)?;
+
+        self.handle = Some(handle);
+        Ok(())
+    }
+
+    /// Stop the capture thread and wait for it to exit
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+            info!(
+                "Passive capture stopped: {} frames, {} observations",
+                self.frames_seen(),
+                self.observations()
+            );
+        }
+    }
+
+    /// Whether the capture thread is running
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Frames read from the interface so far
+    pub fn frames_seen(&self) -> u64 {
+        self.counters.frames_seen.load(Ordering::Relaxed)
+    }
+
+    /// SYN/SYN-ACK observations collected so far
+    pub fn observations(&self) -> u64 {
+        self.counters.observations.load(Ordering::Relaxed)
+    }
+
+    /// Shared analyzer holding the collected observations
+    pub fn analyzer(&self) -> Arc<Mutex<PassiveAnalyzer>> {
+        Arc::clone(&self.analyzer)
+    }
+
+    /// Fingerprint every host with enough observations
+    pub fn results(&self) -> Vec<PassiveFingerprintResult> {
+        let Ok(analyzer) = self.analyzer.lock() else {
+            return Vec::new();
+        };
+
+        let mut results: Vec<_> = analyzer
+            .targets()
+            .into_iter()
+            .filter_map(|target| analyzer.analyze(target).ok())
+            .collect();
+        results.sort_by_key(|r| r.target);
+        results
+    }
+}
+
+impl Drop for PassiveCaptureService {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Convert a raw IP packet into a passive observation
+///
+/// Only SYN and SYN-ACK segments carry the stack defaults (window, MSS,
+/// options ordering) that passive fingerprinting relies on; everything else
+/// yields None.
+pub fn observation_from_packet(ip: &[u8], parser: &PacketParser) -> Option<PassiveObservation> {
+    let packet = parser.parse(ip).ok()?;
+    if packet.packet_type != PacketType::Tcp {
+        return None;
+    }
+    let tcp = packet.tcp_info.as_ref()?;
+    if !tcp.flags.syn {
+        return None;
+    }
+
+    let df_flag = match packet.source_ip {
+        IpAddr::V4(_) => ip.get(6)? & 0x40 != 0,
+        // IPv6 never fragments in transit
+        IpAddr::V6(_) => true,
+    };
+    let mss = tcp.mss();
+
+    trace!(
+        "Passive observation: {} -> {} ttl={} win={} mss={:?}",
+        packet.source_ip,
+        packet.dest_ip,
+        packet.ttl,
+        tcp.window,
+        mss
+    );
+
+    Some(PassiveObservation {
+        src_ip: packet.source_ip,
+        dst_ip: packet.dest_ip,
+        src_port: tcp.source_port,
+        dst_port: tcp.dest_port,
+        ttl: initial_ttl(packet.ttl),
+        window_size: tcp.window,
+        mss,
+        tcp_options: tcp.options_bytes(),
+        tcp_flags: tcp.flags.to_u8(),
+        timestamp_us: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0),
+        df_flag,
+        tcp_timestamp: tcp.timestamp().map(|(value, _)| value),
+    })
+}
+
+/// Round an observed TTL up to the likely initial value (32, 64, 128 or 255)
+pub fn initial_ttl(observed: u8) -> u8 {
+    match observed {
+        0..=32 => 32,
+        33..=64 => 64,
+        65..=128 => 128,
+        _ => 255,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::crafting::{PacketBuilder, TcpFlags, TcpPacket};
+    use std::net::Ipv4Addr;
+
+    fn syn_packet(options: Vec<u8>) -> Vec<u8> {
+        let mut packet = PacketBuilder::new()
+            .source(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+            .destination(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+            .ttl(57)
+            .build_tcp(&TcpPacket {
+                source_port: 40000,
+                dest_port: 443,
+                sequence: 1,
+                acknowledgment: 0,
+                flags: TcpFlags::syn(),
+                window: 64240,
+                urgent_pointer: 0,
+                options: Vec::new(),
+                payload: Vec::new(),
+            })
+            .unwrap();
+
+        // Splice raw options in after the 20 byte TCP header
+        let data_offset = (20 + options.len()) / 4;
+        packet[20 + 12] = (data_offset as u8) << 4;
+        packet.splice(40..40, options);
+        let total_len = packet.len() as u16;
+        packet[2..4].copy_from_slice(&total_len.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_observation_from_syn() {
+        let options = vec![2, 4, 0x05, 0xb4, 1, 3, 3, 7, 8, 10, 0, 0, 0, 9, 0, 0, 0, 0, 1, 1];
+        let packet = syn_packet(options.clone());
+        let observation = observation_from_packet(&packet, &PacketParser::new(false)).unwrap();
+
+        assert_eq!(observation.src_ip, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        assert_eq!(observation.dst_port, 443);
+        assert_eq!(observation.ttl, 64);
+        assert_eq!(observation.window_size, 64240);
+        assert_eq!(observation.mss, Some(1460));
+        assert_eq!(observation.tcp_options, options);
+        assert_eq!(observation.tcp_flags & 0x02, 0x02);
+        assert_eq!(observation.tcp_timestamp, Some(9));
+    }
+
+    #[test]
+    fn test_initial_ttl() {
+        assert_eq!(initial_ttl(57), 64);
+        assert_eq!(initial_ttl(118), 128);
+        assert_eq!(initial_ttl(250), 255);
+        assert_eq!(initial_ttl(30), 32);
+    }
+}
@@ -9,8 +9,11 @@
 /// - RST packet behavior
 /// - IP ID increment patterns
 /// - ECN/CWR response analysis
+/// - Window scale, RST payload and TOS/DSCP quirks
 
 use crate::error::ScanResult;
+#[cfg(not(feature = "no-net"))]
+use crate::packet::parser::{ParsedTcpOption, ParsedTcpPacket};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use tracing::{debug, info};
@@ -29,6 +32,18 @@ pub struct TcpFingerprint {
     pub ip_id_pattern: IpIdPattern,
     pub ecn_support: bool,
     pub cwr_flag: bool,
+    /// Window scale shift count of the SYN/ACK
+    #[serde(default)]
+    pub window_scale: Option<u8>,
+    /// Flags answered to an ECN-setup SYN
+    #[serde(default)]
+    pub ecn_behavior: Option<EcnBehavior>,
+    /// Whether RST segments carry data (an HP-UX and embedded stack quirk)
+    #[serde(default)]
+    pub rst_data: Option<bool>,
+    /// IP TOS byte of the responses
+    #[serde(default)]
+    pub tos: Option<u8>,
 }
 
 /// TCP option types
@@ -44,6 +59,67 @@ pub enum TcpOption {
     Unknown(u8),
 }
 
+#[cfg(not(feature = "no-net"))]
+impl From<&ParsedTcpOption> for TcpOption {
+    fn from(option: &ParsedTcpOption) -> Self {
+        match option {
+            ParsedTcpOption::EndOfOptions => TcpOption::EndOfOptions,
+            ParsedTcpOption::Nop => TcpOption::Nop,
+            ParsedTcpOption::Mss(_) => TcpOption::Mss,
+            ParsedTcpOption::WindowScale(_) => TcpOption::WindowScale,
+            ParsedTcpOption::SackPermitted => TcpOption::SackPermitted,
+            ParsedTcpOption::Sack(_) => TcpOption::Sack,
+            ParsedTcpOption::Timestamp { .. } => TcpOption::Timestamp,
+            other => TcpOption::Unknown(other.kind()),
+        }
+    }
+}
+
+impl TcpOption {
+    /// Letter of the option in an option-order string, as Nmap writes them
+    pub fn order_letter(&self) -> char {
+        match self {
+            TcpOption::Mss => 'M',
+            TcpOption::WindowScale => 'W',
+            TcpOption::Timestamp => 'T',
+            TcpOption::SackPermitted => 'S',
+            TcpOption::Nop => 'N',
+            TcpOption::EndOfOptions => 'L',
+            TcpOption::Sack | TcpOption::Unknown(_) => '?',
+        }
+    }
+}
+
+/// Option-order string such as `MSTNW` for a list of options
+pub fn options_order(options: &[TcpOption]) -> String {
+    options.iter().map(TcpOption::order_letter).collect()
+}
+
+/// Answer to a SYN with ECE and CWR set (Nmap's `CC` test)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EcnBehavior {
+    /// Neither ECE nor CWR echoed: ECN not negotiated
+    NoEcn,
+    /// ECE only: ECN negotiated as RFC 3168 specifies
+    Ece,
+    /// Both ECE and CWR echoed back
+    EceCwr,
+    /// CWR only
+    Other,
+}
+
+impl EcnBehavior {
+    /// Classify the ECE and CWR flags of the SYN/ACK
+    pub fn from_flags(ece: bool, cwr: bool) -> Self {
+        match (ece, cwr) {
+            (false, false) => EcnBehavior::NoEcn,
+            (true, false) => EcnBehavior::Ece,
+            (true, true) => EcnBehavior::EceCwr,
+            (false, true) => EcnBehavior::Other,
+        }
+    }
+}
+
 /// SYN/ACK response pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SynAckPattern {
@@ -150,6 +226,10 @@ impl TcpFingerprintAnalyzer {
         let (ecn_support, cwr_flag) = self.analyze_ecn_cwr(target, port).await?;
         debug!("ECN support: {}, CWR: {}", ecn_support, cwr_flag);
         
+        // Analyze window scale
+        let window_scale = self.detect_window_scale(target, port).await?;
+        debug!("Window scale: {:?}", window_scale);
+        
         Ok(TcpFingerprint {
             target,
             initial_ttl,
@@ -162,6 +242,10 @@ impl TcpFingerprintAnalyzer {
             ip_id_pattern,
             ecn_support,
             cwr_flag,
+            window_scale,
+            ecn_behavior: Some(EcnBehavior::from_flags(ecn_support, cwr_flag)),
+            rst_data: None,
+            tos: None,
         })
     }
 
@@ -261,6 +345,16 @@ impl TcpFingerprintAnalyzer {
         Ok((false, false))
     }
 
+    /// Detect the window scale shift count of the SYN/ACK
+    async fn detect_window_scale(&self, _target: IpAddr, _port: u16) -> ScanResult<Option<u8>> {
+        // Framework implementation
+        // Common shift counts:
+        // - Linux: 7
+        // - Windows: 8
+        // - macOS/FreeBSD: 6
+        Ok(Some(7))
+    }
+
     /// Set timeout for operations
     pub fn set_timeout(&mut self, timeout_ms: u64) {
         self.timeout_ms = timeout_ms;
@@ -278,6 +372,12 @@ impl Default for TcpFingerprintAnalyzer {
     }
 }
 
+/// Extract MSS and option ordering from a captured SYN or SYN/ACK
+#[cfg(not(feature = "no-net"))]
+pub fn mss_and_options(packet: &ParsedTcpPacket) -> (Option<u16>, Vec<TcpOption>) {
+    (packet.mss(), packet.options.iter().map(TcpOption::from).collect())
+}
+
 /// Analyze TTL to determine likely OS family
 pub fn ttl_to_os_hint(ttl: u8) -> Vec<&'static str> {
     match ttl {
@@ -309,6 +409,59 @@ mod tests {
         assert!(matches!(opt, TcpOption::Mss));
     }
 
+    #[test]
+    #[cfg(not(feature = "no-net"))]
+    fn test_mss_and_options_from_packet() {
+        let packet = ParsedTcpPacket {
+            source_port: 80,
+            dest_port: 40000,
+            sequence: 0,
+            acknowledgment: 1,
+            flags: crate::packet::crafting::TcpFlags::syn_ack(),
+            window: 29200,
+            checksum: 0,
+            urgent_pointer: 0,
+            data_offset: 10,
+            options: vec![
+                ParsedTcpOption::Mss(1460),
+                ParsedTcpOption::SackPermitted,
+                ParsedTcpOption::Timestamp { value: 1, echo_reply: 0 },
+                ParsedTcpOption::Nop,
+                ParsedTcpOption::WindowScale(7),
+                ParsedTcpOption::Md5Signature(vec![0; 16]),
+            ],
+        };
+
+        let (mss, options) = mss_and_options(&packet);
+        assert_eq!(mss, Some(1460));
+        assert_eq!(
+            options,
+            vec![
+                TcpOption::Mss,
+                TcpOption::SackPermitted,
+                TcpOption::Timestamp,
+                TcpOption::Nop,
+                TcpOption::WindowScale,
+                TcpOption::Unknown(19),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_options_order_and_ecn_behavior() {
+        let options = [
+            TcpOption::Mss,
+            TcpOption::Nop,
+            TcpOption::WindowScale,
+            TcpOption::SackPermitted,
+            TcpOption::Timestamp,
+            TcpOption::Unknown(30),
+        ];
+        assert_eq!(options_order(&options), "MNWST?");
+        assert_eq!(EcnBehavior::from_flags(true, false), EcnBehavior::Ece);
+        assert_eq!(EcnBehavior::from_flags(false, false), EcnBehavior::NoEcn);
+    }
+
     #[test]
     fn test_rst_behavior() {
         let behavior = RstBehavior::Immediate;
@@ -0,0 +1,256 @@
+//! User OS signature database
+//!
+//! Custom signatures (e.g. for in-house appliances) live in a JSON file in
+//! the database export format, by default `~/.config/nrmap/os-db.json`.
+//! The fingerprinting engine merges it over the built-in database with
+//! these precedence rules:
+//!
+//! 1. a user signature with the name of a built-in one replaces it
+//! 2. every other user signature is added next to the built-in ones
+//! 3. names are unique within the user database; adding an existing name
+//!    fails unless replacing is asked for explicitly
+//!
+//! Signatures whose TCP and ICMP evidence is identical to another entry are
+//! reported as duplicates, since they can never be told apart by a match.
+
+use super::database_io::{DatabaseIO, DatabaseMetadata, FingerprintDatabaseFile};
+use super::fingerprint_db::{OsFingerprintDatabase, OsSignature};
+use crate::error::{ScanError, ScanResult};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// File name of the user database in the user config directory
+pub const USER_DATABASE_FILE: &str = "os-db.json";
+
+/// Default user database location, next to the user config file
+pub fn default_user_database_path() -> Option<PathBuf> {
+    Some(crate::config::user_config_path()?.with_file_name(USER_DATABASE_FILE))
+}
+
+/// Outcome of merging the user database over another database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlayReport {
+    /// User signatures added alongside the base ones
+    pub added: Vec<String>,
+    /// Base signatures replaced by a user signature of the same name
+    pub overridden: Vec<String>,
+}
+
+/// Custom signatures persisted to a user database file
+#[derive(Debug, Clone)]
+pub struct UserDatabase {
+    path: PathBuf,
+    signatures: BTreeMap<String, OsSignature>,
+}
+
+impl UserDatabase {
+    /// Load the user database, empty when the file does not exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> ScanResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut signatures = BTreeMap::new();
+        if path.exists() {
+            for signature in DatabaseIO::import_from_json(&path)?.signatures().values() {
+                signatures.insert(signature.os_name.clone(), signature.clone());
+            }
+            debug!("Loaded {} user OS signatures from {:?}", signatures.len(), path);
+        }
+        Ok(Self { path, signatures })
+    }
+
+    /// Write the database back to its file, creating parent directories
+    pub fn save(&self) -> ScanResult<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let now = chrono::Utc::now().to_rfc3339();
+        let file = FingerprintDatabaseFile {
+            metadata: DatabaseMetadata {
+                name: "NrMAP User OS Signatures".to_string(),
+                version: "1.0.0".to_string(),
+                created: now.clone(),
+                modified: now,
+                signature_count: self.signatures.len(),
+                description: Some("Custom signatures merged over the built-in database".to_string()),
+                author: None,
+            },
+            signatures: self.signatures.values().cloned().collect(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| ScanError::ScannerError { message: format!("JSON serialization failed: {}", e) })?;
+        fs::write(&self.path, json)?;
+        info!("Saved {} user OS signatures to {:?}", self.signatures.len(), self.path);
+        Ok(())
+    }
+
+    /// Path of the database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Signatures ordered by name
+    pub fn signatures(&self) -> impl Iterator<Item = &OsSignature> {
+        self.signatures.values()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OsSignature> {
+        self.signatures.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Add a signature; an existing name is only replaced with `replace`
+    pub fn add(&mut self, signature: OsSignature, replace: bool) -> ScanResult<()> {
+        let mut check = OsFingerprintDatabase::empty();
+        check.add_signature(signature.clone());
+        if let Some(issue) = DatabaseIO::validate_database(&check)?.issues.into_iter().next() {
+            return Err(ScanError::validation_error("signature", issue));
+        }
+        if !replace && self.signatures.contains_key(&signature.os_name) {
+            return Err(ScanError::validation_error(
+                "signature",
+                format!("'{}' is already in the user database (replace it explicitly)", signature.os_name),
+            ));
+        }
+        self.signatures.insert(signature.os_name.clone(), signature);
+        Ok(())
+    }
+
+    /// Remove a signature by name
+    pub fn remove(&mut self, name: &str) -> ScanResult<OsSignature> {
+        self.signatures
+            .remove(name)
+            .ok_or_else(|| ScanError::validation_error("signature", format!("'{}' is not in the user database", name)))
+    }
+
+    /// Names of signatures in `base` or this database, other than the
+    /// signature itself, with the same TCP and ICMP evidence
+    pub fn duplicates_of(&self, signature: &OsSignature, base: &OsFingerprintDatabase) -> Vec<String> {
+        let evidence = |s: &OsSignature| {
+            (
+                serde_json::to_value(&s.tcp_signature).ok(),
+                serde_json::to_value(&s.icmp_signature).ok(),
+            )
+        };
+        let wanted = evidence(signature);
+        let mut names: Vec<String> = base
+            .signatures()
+            .values()
+            .chain(self.signatures.values())
+            .filter(|other| other.os_name != signature.os_name && evidence(other) == wanted)
+            .map(|other| other.os_name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Merge the user signatures over `base`
+    pub fn merge_over(&self, base: &OsFingerprintDatabase) -> (OsFingerprintDatabase, OverlayReport) {
+        let mut merged = base.clone();
+        let mut report = OverlayReport::default();
+        for signature in self.signatures.values() {
+            if base.get_signature(&signature.os_name).is_some() {
+                report.overridden.push(signature.os_name.clone());
+            } else {
+                report.added.push(signature.os_name.clone());
+            }
+            merged.add_signature(signature.clone());
+        }
+        (merged, report)
+    }
+}
+
+/// Read signatures to add from a database file (JSON, YAML, nmap-os-db)
+/// or a single exported signature (JSON, YAML)
+pub fn read_signatures<P: AsRef<Path>>(path: P) -> ScanResult<Vec<OsSignature>> {
+    let path = path.as_ref();
+    match DatabaseIO::import_auto(path) {
+        Ok(database) => {
+            let mut signatures: Vec<OsSignature> = database.signatures().values().cloned().collect();
+            signatures.sort_by(|a, b| a.os_name.cmp(&b.os_name));
+            Ok(signatures)
+        }
+        Err(database_error) => {
+            let content = fs::read_to_string(path)?;
+            let single = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => DatabaseIO::import_signature_json(&content),
+                Some("yaml") | Some("yml") => DatabaseIO::import_signature_yaml(&content),
+                _ => return Err(database_error),
+            };
+            single.map(|signature| vec![signature]).map_err(|_| database_error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_signature(name: &str) -> OsSignature {
+        let mut signature = OsFingerprintDatabase::new().get_signature("Linux 2.6+").unwrap().clone();
+        signature.os_name = name.to_string();
+        if let Some(tcp) = signature.tcp_signature.as_mut() {
+            tcp.window_size_range = (4321, 4321);
+        }
+        signature
+    }
+
+    #[test]
+    fn test_add_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(USER_DATABASE_FILE);
+
+        let mut db = UserDatabase::load(&path).unwrap();
+        assert!(db.is_empty());
+        db.add(custom_signature("Acme Firewall 3"), false).unwrap();
+        assert!(db.add(custom_signature("Acme Firewall 3"), false).is_err());
+        db.add(custom_signature("Acme Firewall 3"), true).unwrap();
+        db.save().unwrap();
+
+        let mut reloaded = UserDatabase::load(&path).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded.get("Acme Firewall 3").is_some());
+        reloaded.remove("Acme Firewall 3").unwrap();
+        assert!(reloaded.remove("Acme Firewall 3").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_signature() {
+        let mut db = UserDatabase::load("/nonexistent/os-db.json").unwrap();
+        let mut signature = custom_signature("Broken");
+        signature.confidence_weight = 2.0;
+        assert!(db.add(signature, false).is_err());
+    }
+
+    #[test]
+    fn test_merge_precedence_and_duplicates() {
+        let base = OsFingerprintDatabase::new();
+        let mut db = UserDatabase::load("/nonexistent/os-db.json").unwrap();
+        let mut linux = custom_signature("Linux 2.6+");
+        linux.confidence_weight = 0.1;
+        db.add(linux, false).unwrap();
+        db.add(custom_signature("Acme Firewall 3"), false).unwrap();
+
+        let (merged, report) = db.merge_over(&base);
+        assert_eq!(report.overridden, vec!["Linux 2.6+"]);
+        assert_eq!(report.added, vec!["Acme Firewall 3"]);
+        assert_eq!(merged.signature_count(), base.signature_count() + 1);
+        assert_eq!(merged.get_signature("Linux 2.6+").unwrap().confidence_weight, 0.1);
+
+        let copy = custom_signature("Acme Firewall 3 copy");
+        assert_eq!(db.duplicates_of(&copy, &base), vec!["Acme Firewall 3", "Linux 2.6+"]);
+        let builtin_copy = OsSignature {
+            os_name: "Another Linux".to_string(),
+            ..base.get_signature("Linux 2.6+").unwrap().clone()
+        };
+        assert_eq!(db.duplicates_of(&builtin_copy, &base), vec!["Linux 2.6+"]);
+    }
+}
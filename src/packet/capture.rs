@@ -0,0 +1,822 @@
+//! Packet capture based response listener
+//!
+//! Captures frames from a network interface (pnet datalink), applies a
+//! BPF-style filter for the scan's targets and source ports, and demultiplexes
+//! responses to in-flight probes keyed by (ip, port, seq). This is what SYN
+//! scans and active OS probes use to observe replies that the kernel would
+//! otherwise swallow.
+
+use crate::error::{ScanError, ScanResult};
+use crate::packet::parser::{
+    PacketParser, PacketType, ParsedPacket, ParsedPacketRef, ParsedTcpPacket, ParsedUdpPacket,
+};
+use crate::packet::pcap::{self, PacketDirection, SharedPcapWriter};
+use crate::scanner::resources;
+use pnet::datalink::{self, Channel, DataLinkReceiver, NetworkInterface};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::{debug, info, trace, warn};
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+/// Packet capture configuration
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    /// Interface to capture on (first active non-loopback interface if None)
+    pub interface: Option<String>,
+    /// Receive buffer size in bytes
+    pub buffer_size: usize,
+    /// Read timeout used to poll the stop flag (milliseconds)
+    pub read_timeout_ms: u64,
+    /// Put the interface into promiscuous mode
+    pub promiscuous: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            buffer_size: 65535,
+            read_timeout_ms: 100,
+            promiscuous: false,
+        }
+    }
+}
+
+/// Filter selecting responses relevant to a scan
+#[derive(Debug, Clone, Default)]
+pub struct CaptureFilter {
+    /// Hosts being probed; empty means any host
+    pub targets: HashSet<IpAddr>,
+    /// Inclusive range of local source ports used by probes
+    pub source_ports: Option<(u16, u16)>,
+}
+
+impl CaptureFilter {
+    /// Create a filter for a set of targets
+    pub fn new(targets: impl IntoIterator<Item = IpAddr>) -> Self {
+        Self {
+            targets: targets.into_iter().collect(),
+            source_ports: None,
+        }
+    }
+
+    /// Restrict TCP/UDP responses to the given local port range
+    pub fn with_source_ports(mut self, first: u16, last: u16) -> Self {
+        self.source_ports = Some((first.min(last), first.max(last)));
+        self
+    }
+
+    /// Check whether a parsed packet passes the filter
+    ///
+    /// ICMP errors are kept when the datagram they quote was sent to a
+    /// target, whoever sent them: a router or firewall reports a target
+    /// unreachable from its own address. They carry the port information
+    /// inside that quoted datagram.
+    pub fn matches(&self, packet: &ParsedPacket) -> bool {
+        let dest_port = match (&packet.tcp_info, &packet.udp_info) {
+            (Some(tcp), _) => Some(tcp.dest_port),
+            (None, Some(udp)) => Some(udp.dest_port),
+            _ => None,
+        };
+        self.matches_fields(packet.source_ip, packet.packet_type, dest_port, &packet.payload)
+    }
+
+    /// [`matches`](Self::matches) for a packet parsed without copying
+    pub fn matches_ref(&self, packet: &ParsedPacketRef<'_>) -> bool {
+        let dest_port = match (&packet.tcp_info, &packet.udp_info) {
+            (Some(tcp), _) => Some(tcp.dest_port),
+            (None, Some(udp)) => Some(udp.dest_port),
+            _ => None,
+        };
+        self.matches_fields(packet.source_ip, packet.packet_type, dest_port, packet.payload)
+    }
+
+    fn matches_fields(&self, source_ip: IpAddr, packet_type: PacketType, dest_port: Option<u16>, payload: &[u8]) -> bool {
+        if !self.targets.is_empty() && !self.targets.contains(&source_ip) {
+            let icmp = dest_port.is_none() && matches!(packet_type, PacketType::Icmp | PacketType::Icmpv6);
+            let quoted = icmp.then(|| ProbeKey::from_icmp_quote(payload)).flatten();
+            if !quoted.is_some_and(|key| self.targets.contains(&key.ip)) {
+                return false;
+            }
+        }
+
+        match (self.source_ports, dest_port) {
+            (Some((first, last)), Some(port)) => (first..=last).contains(&port),
+            (_, None) => matches!(packet_type, PacketType::Icmp | PacketType::Icmpv6),
+            (None, Some(_)) => true,
+        }
+    }
+
+    /// Equivalent filter in libpcap/BPF syntax (for logging and external tools)
+    ///
+    /// ICMP is kept from any host, since BPF cannot read the quoted datagram.
+    pub fn to_bpf(&self) -> String {
+        let mut clauses = Vec::new();
+
+        if !self.targets.is_empty() {
+            let mut hosts: Vec<String> = self.targets.iter().map(|t| format!("src host {}", t)).collect();
+            hosts.sort();
+            clauses.push(format!("({})", hosts.join(" or ")));
+        }
+
+        match self.source_ports {
+            Some((first, last)) if first == last => clauses.push(format!("(tcp or udp) and dst port {}", first)),
+            Some((first, last)) => clauses.push(format!("(tcp or udp) and dst portrange {}-{}", first, last)),
+            None => clauses.push("(tcp or udp)".to_string()),
+        }
+
+        format!("({}) or icmp or icmp6", clauses.join(" and "))
+    }
+}
+
+/// Key identifying an in-flight probe
+///
+/// `ip` and `port` are the remote target; `seq` is the TCP sequence number of
+/// the probe (0 for UDP probes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProbeKey {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub seq: u32,
+}
+
+impl ProbeKey {
+    /// Key for a TCP probe sent with sequence number `seq`
+    pub fn tcp(ip: IpAddr, port: u16, seq: u32) -> Self {
+        Self { ip, port, seq }
+    }
+
+    /// Key for a UDP probe
+    pub fn udp(ip: IpAddr, port: u16) -> Self {
+        Self { ip, port, seq: 0 }
+    }
+
+    /// Derive the probe key a captured response belongs to
+    pub fn from_response(packet: &ParsedPacket) -> Option<Self> {
+        Self::from_fields(
+            packet.source_ip,
+            packet.tcp_info.as_ref(),
+            packet.udp_info.as_ref(),
+            packet.icmp_info.is_some(),
+            &packet.payload,
+        )
+    }
+
+    /// [`from_response`](Self::from_response) for a packet parsed without copying
+    pub fn from_response_ref(packet: &ParsedPacketRef<'_>) -> Option<Self> {
+        Self::from_fields(
+            packet.source_ip,
+            packet.tcp_info.as_ref(),
+            packet.udp_info.as_ref(),
+            packet.icmp_info.is_some(),
+            packet.payload,
+        )
+    }
+
+    fn from_fields(
+        source_ip: IpAddr,
+        tcp: Option<&ParsedTcpPacket>,
+        udp: Option<&ParsedUdpPacket>,
+        icmp: bool,
+        payload: &[u8],
+    ) -> Option<Self> {
+        if let Some(tcp) = tcp {
+            // SYN-ACK and RST replies acknowledge seq + 1
+            return Some(Self::tcp(source_ip, tcp.source_port, tcp.acknowledgment.wrapping_sub(1)));
+        }
+        if let Some(udp) = udp {
+            return Some(Self::udp(source_ip, udp.source_port));
+        }
+        if icmp {
+            return Self::from_icmp_quote(payload);
+        }
+        None
+    }
+
+    /// Extract the original probe from the datagram quoted in an ICMP error
+    ///
+    /// `quote` is the ICMP body after the 8-byte header: the original IP
+    /// header followed by at least 8 bytes of the transport header.
+    fn from_icmp_quote(quote: &[u8]) -> Option<Self> {
+        let version = quote.first()? >> 4;
+        let (dest, protocol, transport) = match version {
+            4 => {
+                let header_len = ((quote[0] & 0x0f) as usize) * 4;
+                let dest: [u8; 4] = quote.get(16..20)?.try_into().ok()?;
+                (IpAddr::V4(Ipv4Addr::from(dest)), *quote.get(9)?, quote.get(header_len..)?)
+            }
+            6 => {
+                let dest: [u8; 16] = quote.get(24..40)?.try_into().ok()?;
+                (IpAddr::V6(Ipv6Addr::from(dest)), *quote.get(6)?, quote.get(40..)?)
+            }
+            _ => return None,
+        };
+
+        let port = u16::from_be_bytes(transport.get(2..4)?.try_into().ok()?);
+        match protocol {
+            6 => {
+                let seq = u32::from_be_bytes(transport.get(4..8)?.try_into().ok()?);
+                Some(Self::tcp(dest, port, seq))
+            }
+            17 => Some(Self::udp(dest, port)),
+            _ => None,
+        }
+    }
+}
+
+/// Key identifying a kernel-initiated TCP connection
+///
+/// Used for probes sent through the regular socket API (e.g. a TCP connect),
+/// where the kernel picks the initial sequence number and responses can only
+/// be matched on the remote endpoint and the local port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionKey {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub local_port: u16,
+}
+
+impl ConnectionKey {
+    /// Key for a connection from `local_port` to `ip:port`
+    pub fn new(ip: IpAddr, port: u16, local_port: u16) -> Self {
+        Self { ip, port, local_port }
+    }
+
+    /// Derive the connection a captured TCP response belongs to
+    pub fn from_response(packet: &ParsedPacket) -> Option<Self> {
+        let tcp = packet.tcp_info.as_ref()?;
+        Some(Self::new(packet.source_ip, tcp.source_port, tcp.dest_port))
+    }
+}
+
+/// Capture statistics snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureStats {
+    pub packets_captured: u64,
+    pub packets_matched: u64,
+    pub parse_errors: u64,
+    pub probes_registered: u64,
+    pub responses_delivered: u64,
+    pub unmatched_responses: u64,
+    pub probes_timed_out: u64,
+}
+
+impl std::fmt::Display for CaptureStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Capture Stats: captured={}, matched={}, delivered={}, unmatched={}, timed_out={}, parse_errors={}",
+            self.packets_captured,
+            self.packets_matched,
+            self.responses_delivered,
+            self.unmatched_responses,
+            self.probes_timed_out,
+            self.parse_errors
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct CaptureCounters {
+    packets_captured: AtomicU64,
+    packets_matched: AtomicU64,
+    parse_errors: AtomicU64,
+    probes_registered: AtomicU64,
+    responses_delivered: AtomicU64,
+    unmatched_responses: AtomicU64,
+    probes_timed_out: AtomicU64,
+}
+
+impl CaptureCounters {
+    fn snapshot(&self) -> CaptureStats {
+        CaptureStats {
+            packets_captured: self.packets_captured.load(Ordering::Relaxed),
+            packets_matched: self.packets_matched.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            probes_registered: self.probes_registered.load(Ordering::Relaxed),
+            responses_delivered: self.responses_delivered.load(Ordering::Relaxed),
+            unmatched_responses: self.unmatched_responses.load(Ordering::Relaxed),
+            probes_timed_out: self.probes_timed_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Routes captured responses to the futures waiting on them
+#[derive(Debug, Default)]
+pub struct ResponseDemux {
+    pending: Mutex<HashMap<ProbeKey, oneshot::Sender<ParsedPacket>>>,
+    connections: Mutex<HashMap<ConnectionKey, oneshot::Sender<ParsedPacket>>>,
+    counters: CaptureCounters,
+}
+
+impl ResponseDemux {
+    /// Create an empty demultiplexer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an in-flight probe and get a receiver for its response
+    pub fn register(&self, key: ProbeKey) -> oneshot::Receiver<ParsedPacket> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(key, tx);
+        self.counters.probes_registered.fetch_add(1, Ordering::Relaxed);
+        rx
+    }
+
+    /// Stop waiting for a probe (e.g. after it timed out)
+    pub fn cancel(&self, key: &ProbeKey) {
+        if self.pending.lock().unwrap().remove(key).is_some() {
+            self.counters.probes_timed_out.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Register a kernel-initiated connection and get a receiver for the
+    /// first TCP segment the remote end sends on it
+    pub fn register_connection(&self, key: ConnectionKey) -> oneshot::Receiver<ParsedPacket> {
+        let (tx, rx) = oneshot::channel();
+        self.connections.lock().unwrap().insert(key, tx);
+        self.counters.probes_registered.fetch_add(1, Ordering::Relaxed);
+        rx
+    }
+
+    /// Stop waiting for a connection response
+    pub fn cancel_connection(&self, key: &ConnectionKey) {
+        if self.connections.lock().unwrap().remove(key).is_some() {
+            self.counters.probes_timed_out.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of probes currently awaiting a response
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len() + self.connections.lock().unwrap().len()
+    }
+
+    /// Deliver a captured packet to its probe, if one is registered
+    ///
+    /// # Returns
+    /// * `bool` - True if the packet was delivered
+    pub fn dispatch(&self, packet: ParsedPacket) -> bool {
+        let sender = ProbeKey::from_response(&packet)
+            .and_then(|key| self.pending.lock().unwrap().remove(&key))
+            .or_else(|| {
+                ConnectionKey::from_response(&packet)
+                    .and_then(|key| self.connections.lock().unwrap().remove(&key))
+            });
+
+        match sender {
+            Some(tx) => {
+                // The receiver may have been dropped by a cancelled probe
+                let delivered = tx.send(packet).is_ok();
+                if delivered {
+                    self.counters.responses_delivered.fetch_add(1, Ordering::Relaxed);
+                }
+                delivered
+            }
+            None => {
+                self.counters.unmatched_responses.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> CaptureStats {
+        self.counters.snapshot()
+    }
+}
+
+/// Live packet capture feeding a [`ResponseDemux`]
+pub struct PacketCapture {
+    config: CaptureConfig,
+    filter: Arc<CaptureFilter>,
+    demux: Arc<ResponseDemux>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    pcap: Option<SharedPcapWriter>,
+}
+
+impl PacketCapture {
+    /// Create a capture (not yet started)
+    pub fn new(config: CaptureConfig, filter: CaptureFilter) -> Self {
+        info!("Packet capture filter: {}", filter.to_bpf());
+        Self {
+            config,
+            filter: Arc::new(filter),
+            demux: Arc::new(ResponseDemux::new()),
+            running: Arc::new(AtomicBool::new(false)),
+            handle: None,
+            pcap: None,
+        }
+    }
+
+    /// Record traffic to and from the filter's targets to a pcap file
+    ///
+    /// Both directions are recorded, so kernel-generated probes (e.g. TCP
+    /// connect handshakes) end up in the file alongside the responses.
+    pub fn with_pcap_writer(mut self, writer: SharedPcapWriter) -> Self {
+        self.pcap = Some(writer);
+        self
+    }
+
+    /// Shared demultiplexer, for probes that register themselves directly
+    pub fn demux(&self) -> Arc<ResponseDemux> {
+        Arc::clone(&self.demux)
+    }
+
+    /// Open the interface and start the capture thread
+    ///
+    /// Requires raw socket privileges (root or CAP_NET_RAW).
+    pub fn start(&mut self) -> ScanResult<()> {
+        let demux = Arc::clone(&self.demux);
+        self.spawn(move |packet| {
+            demux.dispatch(packet.into_owned());
+        })
+    }
+
+    /// Start the capture thread, handing matching packets to `handler`
+    /// instead of the demultiplexer
+    ///
+    /// The handler runs on the capture thread with the packet still borrowed
+    /// from the receive buffer, and returns whether it claimed the packet.
+    /// Probes registered with [`register`](Self::register) are not answered.
+    pub fn start_with<F>(&mut self, handler: F) -> ScanResult<()>
+    where
+        F: Fn(&ParsedPacketRef<'_>) -> bool + Send + 'static,
+    {
+        let demux = Arc::clone(&self.demux);
+        self.spawn(move |packet| {
+            let counter = match handler(&packet) {
+                true => &demux.counters.responses_delivered,
+                false => &demux.counters.unmatched_responses,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    fn spawn<F>(&mut self, deliver: F) -> ScanResult<()>
+    where
+        F: Fn(ParsedPacketRef<'_>) + Send + 'static,
+    {
+        if self.running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let loopback_only = !self.filter.targets.is_empty()
+            && self.filter.targets.iter().all(|ip| ip.is_loopback());
+        let (interface, mut rx) = open_receiver(&self.config, loopback_only)?;
+
+        info!("Starting packet capture on {}", interface.name);
+        self.running.store(true, Ordering::SeqCst);
+
+        let running = Arc::clone(&self.running);
+        let filter = Arc::clone(&self.filter);
+        let demux = Arc::clone(&self.demux);
+        let pcap = self.pcap.clone();
+
+        let handle = std::thread::Builder::new()
+            .name("nrmap-capture".to_string())
+            .spawn(move || {
+                let parser = PacketParser::new(false);
+                while running.load(Ordering::Relaxed) {
+                    match rx.next() {
+                        Ok(frame) => {
+                            process_frame(frame, &parser, &filter, &demux.counters, pcap.as_ref(), &deliver)
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                        Err(e) => {
+                            warn!("Packet capture read failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                running.store(false, Ordering::SeqCst);
+                if let Some(Ok(mut writer)) = pcap.as_ref().map(|w| w.lock()) {
+                    let _ = writer.flush();
+                }
+                debug!("Packet capture thread stopped");
+            })
+            .map_err(ScanError::Io)?;
+
+        self.handle = Some(handle);
+        Ok(())
+    }
+
+    /// Stop the capture thread and wait for it to exit
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+            info!("Packet capture stopped: {}", self.stats());
+        }
+    }
+
+    /// Whether the capture thread is running
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Wait for the response to a probe
+    ///
+    /// Register before sending the probe so a fast reply is not missed.
+    ///
+    /// # Returns
+    /// * `Option<ParsedPacket>` - The response, or None on timeout
+    pub async fn wait_for(
+        &self,
+        key: ProbeKey,
+        receiver: oneshot::Receiver<ParsedPacket>,
+        timeout_ms: u64,
+    ) -> Option<ParsedPacket> {
+        match tokio::time::timeout(Duration::from_millis(timeout_ms), receiver).await {
+            Ok(Ok(packet)) => Some(packet),
+            _ => {
+                self.demux.cancel(&key);
+                None
+            }
+        }
+    }
+
+    /// Register a probe with the demultiplexer
+    pub fn register(&self, key: ProbeKey) -> oneshot::Receiver<ParsedPacket> {
+        self.demux.register(key)
+    }
+
+    /// Register a kernel-initiated connection with the demultiplexer
+    pub fn register_connection(&self, key: ConnectionKey) -> oneshot::Receiver<ParsedPacket> {
+        self.demux.register_connection(key)
+    }
+
+    /// Wait for the first segment of a registered connection
+    ///
+    /// # Returns
+    /// * `Option<ParsedPacket>` - The response, or None on timeout
+    pub async fn wait_for_connection(
+        &self,
+        key: ConnectionKey,
+        receiver: oneshot::Receiver<ParsedPacket>,
+        timeout_ms: u64,
+    ) -> Option<ParsedPacket> {
+        match tokio::time::timeout(Duration::from_millis(timeout_ms), receiver).await {
+            Ok(Ok(packet)) => Some(packet),
+            _ => {
+                self.demux.cancel_connection(&key);
+                None
+            }
+        }
+    }
+
+    /// Current capture statistics
+    pub fn stats(&self) -> CaptureStats {
+        self.demux.stats()
+    }
+}
+
+impl Drop for PacketCapture {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Open a datalink receiver on the configured (or first suitable) interface
+///
+/// With `loopback_only` and no configured interface, the loopback interface
+/// is chosen since loopback traffic is not visible anywhere else.
+pub(crate) fn open_receiver(
+    config: &CaptureConfig,
+    loopback_only: bool,
+) -> ScanResult<(NetworkInterface, Box<dyn DataLinkReceiver>)> {
+    let interface = select_interface(config.interface.as_deref(), loopback_only)?;
+    let channel_config = datalink::Config {
+        read_buffer_size: config.buffer_size,
+        read_timeout: Some(Duration::from_millis(config.read_timeout_ms)),
+        promiscuous: config.promiscuous,
+        ..Default::default()
+    };
+
+    match datalink::channel(&interface, channel_config) {
+        Ok(Channel::Ethernet(_tx, rx)) => Ok((interface, rx)),
+        Ok(_) => Err(ScanError::packet_error(format!(
+            "Unsupported datalink channel on {}",
+            interface.name
+        ))),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err(ScanError::permission_denied("Packet capture"))
+        }
+        Err(e) => Err(ScanError::Io(e)),
+    }
+}
+
+fn select_interface(name: Option<&str>, loopback_only: bool) -> ScanResult<NetworkInterface> {
+    let interfaces = datalink::interfaces();
+    let found = match name {
+        Some(name) => interfaces.into_iter().find(|i| i.name == name),
+        None if loopback_only => interfaces.into_iter().find(|i| i.is_up() && i.is_loopback()),
+        None => interfaces
+            .into_iter()
+            .find(|i| i.is_up() && !i.is_loopback() && !i.ips.is_empty()),
+    };
+
+    found.ok_or_else(|| {
+        ScanError::packet_error(format!(
+            "No capture interface found{}",
+            name.map(|n| format!(" named {}", n)).unwrap_or_default()
+        ))
+    })
+}
+
+/// Strip the Ethernet (and optional VLAN) header from a frame
+pub(crate) fn ip_payload(frame: &[u8]) -> Option<&[u8]> {
+    let mut ethertype = u16::from_be_bytes(frame.get(12..14)?.try_into().ok()?);
+    let mut offset = 14;
+    if ethertype == ETHERTYPE_VLAN {
+        ethertype = u16::from_be_bytes(frame.get(16..18)?.try_into().ok()?);
+        offset = 18;
+    }
+
+    match ethertype {
+        ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => frame.get(offset..),
+        _ => None,
+    }
+}
+
+fn process_frame(
+    frame: &[u8],
+    parser: &PacketParser,
+    filter: &CaptureFilter,
+    counters: &CaptureCounters,
+    pcap: Option<&SharedPcapWriter>,
+    deliver: &dyn Fn(ParsedPacketRef<'_>),
+) {
+    counters.packets_captured.fetch_add(1, Ordering::Relaxed);
+
+    let Some(ip) = ip_payload(frame) else {
+        return;
+    };
+
+    // Parse without copying; only matched packets are copied out
+    let packet = match parser.parse_ref(ip) {
+        Ok(packet) => packet,
+        Err(e) => {
+            trace!("Dropping unparseable frame: {}", e);
+            counters.parse_errors.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    };
+
+    if pcap.is_some() {
+        if filter.targets.contains(&packet.source_ip) {
+            pcap::record(pcap, ip, PacketDirection::Inbound);
+        } else if filter.targets.contains(&packet.dest_ip) {
+            pcap::record(pcap, ip, PacketDirection::Outbound);
+        }
+    }
+
+    if filter.matches_ref(&packet) {
+        counters.packets_matched.fetch_add(1, Ordering::Relaxed);
+        resources::packet_received(ip.len());
+        deliver(packet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::crafting::TcpFlags;
+    use crate::packet::parser::{ParsedIcmpPacket, ParsedTcpPacket};
+
+    fn target() -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10))
+    }
+
+    fn tcp_response(source_port: u16, dest_port: u16, ack: u32) -> ParsedPacket {
+        ParsedPacket {
+            packet_type: PacketType::Tcp,
+            source_ip: target(),
+            dest_ip: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2)),
+            ttl: 64,
+            protocol: 6,
+            payload: vec![],
+            tcp_info: Some(ParsedTcpPacket {
+                source_port,
+                dest_port,
+                sequence: 1000,
+                acknowledgment: ack,
+                flags: TcpFlags { syn: true, ack: true, ..Default::default() },
+                window: 65535,
+                checksum: 0,
+                urgent_pointer: 0,
+                data_offset: 5,
+                options: vec![],
+            }),
+            udp_info: None,
+            icmp_info: None,
+            fragment: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let filter = CaptureFilter::new([target()]).with_source_ports(40000, 40100);
+        assert!(filter.matches(&tcp_response(80, 40050, 1)));
+        assert!(!filter.matches(&tcp_response(80, 12345, 1)));
+
+        let mut other_host = tcp_response(80, 40050, 1);
+        other_host.source_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert!(!filter.matches(&other_host));
+    }
+
+    #[test]
+    fn test_filter_to_bpf() {
+        let filter = CaptureFilter::new([target()]).with_source_ports(40000, 40100);
+        assert_eq!(
+            filter.to_bpf(),
+            "((src host 192.168.1.10) and (tcp or udp) and dst portrange 40000-40100) or icmp or icmp6"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_demux_tcp_response() {
+        let demux = ResponseDemux::new();
+        let key = ProbeKey::tcp(target(), 80, 5000);
+        let rx = demux.register(key);
+
+        // Response for a different probe is not delivered
+        assert!(!demux.dispatch(tcp_response(80, 40000, 9999)));
+        assert!(demux.dispatch(tcp_response(80, 40000, 5001)));
+
+        let packet = rx.await.unwrap();
+        assert_eq!(packet.tcp_info.unwrap().acknowledgment, 5001);
+
+        let stats = demux.stats();
+        assert_eq!(stats.probes_registered, 1);
+        assert_eq!(stats.responses_delivered, 1);
+        assert_eq!(stats.unmatched_responses, 1);
+        assert_eq!(demux.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_demux_connection_response() {
+        let demux = ResponseDemux::new();
+        let rx = demux.register_connection(ConnectionKey::new(target(), 80, 40000));
+
+        // The kernel picked the ISN, so any acknowledgment number matches
+        assert!(!demux.dispatch(tcp_response(80, 40001, 77)));
+        assert!(demux.dispatch(tcp_response(80, 40000, 77)));
+        assert_eq!(rx.await.unwrap().tcp_info.unwrap().dest_port, 40000);
+        assert_eq!(demux.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_demux_icmp_unreachable() {
+        // Quoted original datagram: IPv4 header to 192.168.1.10, UDP dst port 53
+        let mut quote = vec![0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 192, 168, 1, 2, 192, 168, 1, 10];
+        quote.extend_from_slice(&[0x9c, 0x40, 0x00, 0x35, 0, 8, 0, 0]);
+
+        let packet = ParsedPacket {
+            packet_type: PacketType::Icmp,
+            source_ip: target(),
+            dest_ip: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2)),
+            ttl: 64,
+            protocol: 1,
+            payload: quote,
+            tcp_info: None,
+            udp_info: None,
+            icmp_info: Some(ParsedIcmpPacket {
+                icmp_type: 3,
+                code: 3,
+                checksum: 0,
+                identifier: None,
+                sequence: None,
+                rest_of_header: None,
+            }),
+            fragment: None,
+        };
+
+        assert_eq!(ProbeKey::from_response(&packet), Some(ProbeKey::udp(target(), 53)));
+
+        let demux = ResponseDemux::new();
+        let _rx = demux.register(ProbeKey::udp(target(), 53));
+        assert!(demux.dispatch(packet));
+    }
+
+    #[test]
+    fn test_ip_payload() {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.push(0x45);
+        assert_eq!(ip_payload(&frame), Some(&[0x45][..]));
+
+        let mut arp = vec![0u8; 12];
+        arp.extend_from_slice(&0x0806u16.to_be_bytes());
+        assert_eq!(ip_payload(&arp), None);
+    }
+}
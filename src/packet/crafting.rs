@@ -4,6 +4,7 @@
 /// with proper checksums and header fields using the pnet library.
 
 use crate::error::{ScanError, ScanResult};
+use crate::packet::buffer::{PacketBufferPool, PooledBuffer};
 use pnet::packet::tcp::{MutableTcpPacket, TcpOption};
 use pnet::packet::udp::MutableUdpPacket;
 use pnet::packet::icmp::{IcmpPacket as PnetIcmpPacket, MutableIcmpPacket};
@@ -196,12 +197,49 @@ impl IcmpPacket {
     }
 }
 
+/// IPv6 Fragment extension header number
+pub const IPV6_FRAGMENT_HEADER: u8 = 44;
+
+/// Check a fragment payload size (`--mtu`)
+///
+/// Fragment offsets count 8-byte units, so every fragment but the last must
+/// carry a multiple of 8 bytes.
+pub fn validate_fragment_size(size: usize) -> ScanResult<()> {
+    if size == 0 || !size.is_multiple_of(8) || size > 65528 {
+        return Err(ScanError::validation_error(
+            "mtu",
+            format!("Fragment size must be a non-zero multiple of 8 up to 65528, got {}", size),
+        ));
+    }
+    Ok(())
+}
+
+/// Length of the IP header preceding the transport header
+fn ip_header_len(ip: IpAddr) -> usize {
+    match ip {
+        IpAddr::V4(_) => 20,
+        IpAddr::V6(_) => 40,
+    }
+}
+
 /// Packet builder for constructing network packets using pnet
 pub struct PacketBuilder {
     source_ip: Option<IpAddr>,
     dest_ip: Option<IpAddr>,
     ttl: u8,
     identification: u16,
+    fragment_size: Option<usize>,
+    bad_checksum: bool,
+}
+
+/// Turn a valid transport checksum into a wrong one that is still non-zero
+///
+/// A zero UDP checksum means "no checksum", which receivers would accept.
+fn corrupt_checksum(checksum: u16) -> u16 {
+    match checksum.wrapping_add(1) {
+        0 => 1,
+        bad => bad,
+    }
 }
 
 impl PacketBuilder {
@@ -213,6 +251,8 @@ impl PacketBuilder {
             dest_ip: None,
             ttl: 64,
             identification: rand::random(),
+            fragment_size: None,
+            bad_checksum: false,
         }
     }
 
@@ -240,10 +280,110 @@ impl PacketBuilder {
         self
     }
 
-    /// Build a complete TCP/IP packet
-    pub fn build_tcp(&self, tcp: &TcpPacket) -> ScanResult<Vec<u8>> {
-        trace!("Building TCP packet with pnet: {:?}", tcp);
+    /// Write an invalid TCP/UDP checksum into built packets
+    ///
+    /// End hosts drop such segments, so replies come from middleboxes that do
+    /// not validate checksums.
+    pub fn bad_checksum(mut self, enabled: bool) -> Self {
+        self.bad_checksum = enabled;
+        self
+    }
+
+    /// Split packets passed to [`fragment`](Self::fragment) into IP fragments
+    /// carrying `size` bytes of payload each (see [`validate_fragment_size`])
+    pub fn fragment_size(mut self, size: usize) -> Self {
+        self.fragment_size = Some(size);
+        self
+    }
+
+    /// Split a built IP packet into fragments
+    ///
+    /// Returns the packet unchanged when fragmentation is off or it already
+    /// fits. IPv4 fragments clear Don't Fragment; IPv6 fragments gain a
+    /// Fragment extension header.
+    pub fn fragment(&self, packet: &[u8]) -> ScanResult<Vec<Vec<u8>>> {
+        let Some(size) = self.fragment_size else {
+            return Ok(vec![packet.to_vec()]);
+        };
+        validate_fragment_size(size)?;
+
+        match packet.first().map(|b| b >> 4) {
+            Some(4) => self.fragment_ipv4(packet, size),
+            Some(6) => self.fragment_ipv6(packet, size),
+            _ => Err(ScanError::packet_error("Cannot fragment a non-IP packet")),
+        }
+    }
+
+    /// Split an IPv4 packet into fragments
+    fn fragment_ipv4(&self, packet: &[u8], size: usize) -> ScanResult<Vec<Vec<u8>>> {
+        let header_len = packet.first().map_or(0, |b| (b & 0x0F) as usize * 4);
+        if header_len < 20 || packet.len() < header_len {
+            return Err(ScanError::packet_error("Truncated IPv4 header"));
+        }
+        let (header, payload) = packet.split_at(header_len);
+        if payload.len() <= size {
+            return Ok(vec![packet.to_vec()]);
+        }
+
+        let count = payload.len().div_ceil(size);
+        let mut fragments = Vec::with_capacity(count);
+        for (index, chunk) in payload.chunks(size).enumerate() {
+            let mut buffer = Vec::with_capacity(header_len + chunk.len());
+            buffer.extend_from_slice(header);
+            buffer.extend_from_slice(chunk);
+
+            let mut ip_packet = MutableIpv4Packet::new(&mut buffer)
+                .ok_or_else(|| ScanError::packet_error("Failed to create IPv4 fragment"))?;
+            ip_packet.set_total_length((header_len + chunk.len()) as u16);
+            ip_packet.set_flags(if index + 1 < count { Ipv4Flags::MoreFragments } else { 0 });
+            ip_packet.set_fragment_offset((index * size / 8) as u16);
+            ip_packet.set_checksum(0);
+            let checksum = pnet::packet::ipv4::checksum(&ip_packet.to_immutable());
+            ip_packet.set_checksum(checksum);
 
+            fragments.push(buffer);
+        }
+
+        debug!("Split {}-byte IPv4 packet into {} fragments of {} bytes", packet.len(), count, size);
+        Ok(fragments)
+    }
+
+    /// Split an IPv6 packet into fragments behind a Fragment extension header
+    fn fragment_ipv6(&self, packet: &[u8], size: usize) -> ScanResult<Vec<Vec<u8>>> {
+        if packet.len() < 40 {
+            return Err(ScanError::packet_error("Truncated IPv6 header"));
+        }
+        let (header, payload) = packet.split_at(40);
+        if payload.len() <= size {
+            return Ok(vec![packet.to_vec()]);
+        }
+
+        let next_header = header[6];
+        let identification = u32::from(self.identification).to_be_bytes();
+        let count = payload.len().div_ceil(size);
+        let mut fragments = Vec::with_capacity(count);
+        for (index, chunk) in payload.chunks(size).enumerate() {
+            let more = u16::from(index + 1 < count);
+            let offset_field = ((index * size) as u16) | more;
+
+            let mut buffer = Vec::with_capacity(48 + chunk.len());
+            buffer.extend_from_slice(header);
+            buffer[4..6].copy_from_slice(&((8 + chunk.len()) as u16).to_be_bytes());
+            buffer[6] = IPV6_FRAGMENT_HEADER;
+            buffer.extend_from_slice(&[next_header, 0]);
+            buffer.extend_from_slice(&offset_field.to_be_bytes());
+            buffer.extend_from_slice(&identification);
+            buffer.extend_from_slice(chunk);
+
+            fragments.push(buffer);
+        }
+
+        debug!("Split {}-byte IPv6 packet into {} fragments of {} bytes", packet.len(), count, size);
+        Ok(fragments)
+    }
+
+    /// Source and destination addresses, both required for TCP and UDP
+    fn addresses(&self) -> ScanResult<(IpAddr, IpAddr)> {
         let dest_ip = self.dest_ip.ok_or_else(|| {
             ScanError::packet_error("Destination IP not set")
         })?;
@@ -252,15 +392,53 @@ impl PacketBuilder {
             ScanError::packet_error("Source IP not set")
         })?;
 
+        Ok((source_ip, dest_ip))
+    }
+
+    /// Build a complete TCP/IP packet
+    pub fn build_tcp(&self, tcp: &TcpPacket) -> ScanResult<Vec<u8>> {
+        let mut buffer = vec![0u8; self.tcp_packet_len(tcp)?];
+        self.build_tcp_into(tcp, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Build a TCP/IP packet in a buffer taken from `pool`
+    pub fn build_tcp_pooled<'a>(&self, tcp: &TcpPacket, pool: &'a PacketBufferPool) -> ScanResult<PooledBuffer<'a>> {
+        let mut buffer = pool.acquire(self.tcp_packet_len(tcp)?);
+        self.build_tcp_into(tcp, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Length of the TCP/IP packet built for `tcp`
+    pub fn tcp_packet_len(&self, tcp: &TcpPacket) -> ScanResult<usize> {
+        let (source_ip, _) = self.addresses()?;
+        Ok(ip_header_len(source_ip) + 20 + tcp.payload.len())
+    }
+
+    /// Build a TCP/IP packet into the start of `buffer`, returning its length
+    ///
+    /// Nothing is allocated, so senders can reuse one buffer per probe.
+    pub fn build_tcp_into(&self, tcp: &TcpPacket, buffer: &mut [u8]) -> ScanResult<usize> {
+        trace!("Building TCP packet with pnet: {:?}", tcp);
+
+        let len = self.tcp_packet_len(tcp)?;
+        let available = buffer.len();
+        let buffer = buffer.get_mut(..len).ok_or_else(|| {
+            ScanError::packet_error(format!("{}-byte buffer too small for {}-byte packet", available, len))
+        })?;
+        buffer.fill(0);
+
+        let (source_ip, dest_ip) = self.addresses()?;
         match (source_ip, dest_ip) {
-            (IpAddr::V4(src), IpAddr::V4(dst)) => self.build_tcp_ipv4(tcp, src, dst),
-            (IpAddr::V6(src), IpAddr::V6(dst)) => self.build_tcp_ipv6(tcp, src, dst),
-            _ => Err(ScanError::packet_error("Source and destination IP versions must match")),
+            (IpAddr::V4(src), IpAddr::V4(dst)) => self.write_tcp_ipv4(tcp, src, dst, buffer)?,
+            (IpAddr::V6(src), IpAddr::V6(dst)) => self.write_tcp_ipv6(tcp, src, dst, buffer)?,
+            _ => return Err(ScanError::packet_error("Source and destination IP versions must match")),
         }
+        Ok(len)
     }
 
-    /// Build a TCP/IPv4 packet
-    fn build_tcp_ipv4(&self, tcp: &TcpPacket, src: Ipv4Addr, dst: Ipv4Addr) -> ScanResult<Vec<u8>> {
+    /// Write a TCP/IPv4 packet into `buffer`, sized to fit it exactly
+    fn write_tcp_ipv4(&self, tcp: &TcpPacket, src: Ipv4Addr, dst: Ipv4Addr, buffer: &mut [u8]) -> ScanResult<()> {
         // Calculate sizes
         // For TCP options, we'll just use empty options for now
         // In production, serialize the options properly
@@ -270,9 +448,6 @@ impl PacketBuilder {
         let tcp_total_len = tcp_header_len + tcp.payload.len();
         let ip_total_len = 20 + tcp_total_len;
 
-        // Allocate buffer
-        let mut buffer = vec![0u8; ip_total_len];
-
         // Build IP header
         {
             let mut ip_packet = MutableIpv4Packet::new(&mut buffer[..20])
@@ -324,7 +499,7 @@ impl PacketBuilder {
                 &src,
                 &dst
             );
-            tcp_packet.set_checksum(checksum);
+            tcp_packet.set_checksum(if self.bad_checksum { corrupt_checksum(checksum) } else { checksum });
         }
 
         debug!(
@@ -333,20 +508,16 @@ impl PacketBuilder {
             tcp.flags, buffer.len()
         );
 
-        Ok(buffer)
+        Ok(())
     }
 
-    /// Build a TCP/IPv6 packet
-    fn build_tcp_ipv6(&self, tcp: &TcpPacket, src: Ipv6Addr, dst: Ipv6Addr) -> ScanResult<Vec<u8>> {
+    /// Write a TCP/IPv6 packet into `buffer`, sized to fit it exactly
+    fn write_tcp_ipv6(&self, tcp: &TcpPacket, src: Ipv6Addr, dst: Ipv6Addr, buffer: &mut [u8]) -> ScanResult<()> {
         // Calculate sizes
         let tcp_options_len = 0; // Simplified: no options support yet
         let _ = &tcp.options; // Suppress unused warning
         let tcp_header_len = 20 + tcp_options_len;
         let tcp_total_len = tcp_header_len + tcp.payload.len();
-        let ip_total_len = 40 + tcp_total_len;
-
-        // Allocate buffer
-        let mut buffer = vec![0u8; ip_total_len];
 
         // Build IPv6 header
         {
@@ -391,7 +562,7 @@ impl PacketBuilder {
                 &src,
                 &dst
             );
-            tcp_packet.set_checksum(checksum);
+            tcp_packet.set_checksum(if self.bad_checksum { corrupt_checksum(checksum) } else { checksum });
         }
 
         debug!(
@@ -400,35 +571,56 @@ impl PacketBuilder {
             tcp.flags, buffer.len()
         );
 
-        Ok(buffer)
+        Ok(())
     }
 
     /// Build a complete UDP/IP packet
     pub fn build_udp(&self, udp: &UdpPacket) -> ScanResult<Vec<u8>> {
-        trace!("Building UDP packet with pnet: {:?}", udp);
+        let mut buffer = vec![0u8; self.udp_packet_len(udp)?];
+        self.build_udp_into(udp, &mut buffer)?;
+        Ok(buffer)
+    }
 
-        let dest_ip = self.dest_ip.ok_or_else(|| {
-            ScanError::packet_error("Destination IP not set")
-        })?;
+    /// Build a UDP/IP packet in a buffer taken from `pool`
+    pub fn build_udp_pooled<'a>(&self, udp: &UdpPacket, pool: &'a PacketBufferPool) -> ScanResult<PooledBuffer<'a>> {
+        let mut buffer = pool.acquire(self.udp_packet_len(udp)?);
+        self.build_udp_into(udp, &mut buffer)?;
+        Ok(buffer)
+    }
 
-        let source_ip = self.source_ip.ok_or_else(|| {
-            ScanError::packet_error("Source IP not set")
+    /// Length of the UDP/IP packet built for `udp`
+    pub fn udp_packet_len(&self, udp: &UdpPacket) -> ScanResult<usize> {
+        let (source_ip, _) = self.addresses()?;
+        Ok(ip_header_len(source_ip) + 8 + udp.payload.len())
+    }
+
+    /// Build a UDP/IP packet into the start of `buffer`, returning its length
+    ///
+    /// Nothing is allocated, so senders can reuse one buffer per probe.
+    pub fn build_udp_into(&self, udp: &UdpPacket, buffer: &mut [u8]) -> ScanResult<usize> {
+        trace!("Building UDP packet with pnet: {:?}", udp);
+
+        let len = self.udp_packet_len(udp)?;
+        let available = buffer.len();
+        let buffer = buffer.get_mut(..len).ok_or_else(|| {
+            ScanError::packet_error(format!("{}-byte buffer too small for {}-byte packet", available, len))
         })?;
+        buffer.fill(0);
 
+        let (source_ip, dest_ip) = self.addresses()?;
         match (source_ip, dest_ip) {
-            (IpAddr::V4(src), IpAddr::V4(dst)) => self.build_udp_ipv4(udp, src, dst),
-            (IpAddr::V6(src), IpAddr::V6(dst)) => self.build_udp_ipv6(udp, src, dst),
-            _ => Err(ScanError::packet_error("Source and destination IP versions must match")),
+            (IpAddr::V4(src), IpAddr::V4(dst)) => self.write_udp_ipv4(udp, src, dst, buffer)?,
+            (IpAddr::V6(src), IpAddr::V6(dst)) => self.write_udp_ipv6(udp, src, dst, buffer)?,
+            _ => return Err(ScanError::packet_error("Source and destination IP versions must match")),
         }
+        Ok(len)
     }
 
-    /// Build a UDP/IPv4 packet
-    fn build_udp_ipv4(&self, udp: &UdpPacket, src: Ipv4Addr, dst: Ipv4Addr) -> ScanResult<Vec<u8>> {
+    /// Write a UDP/IPv4 packet into `buffer`, sized to fit it exactly
+    fn write_udp_ipv4(&self, udp: &UdpPacket, src: Ipv4Addr, dst: Ipv4Addr, buffer: &mut [u8]) -> ScanResult<()> {
         let udp_total_len = 8 + udp.payload.len();
         let ip_total_len = 20 + udp_total_len;
 
-        let mut buffer = vec![0u8; ip_total_len];
-
         // Build IP header
         {
             let mut ip_packet = MutableIpv4Packet::new(&mut buffer[..20])
@@ -468,7 +660,7 @@ impl PacketBuilder {
                 &src,
                 &dst
             );
-            udp_packet.set_checksum(checksum);
+            udp_packet.set_checksum(if self.bad_checksum { corrupt_checksum(checksum) } else { checksum });
         }
 
         debug!(
@@ -476,15 +668,12 @@ impl PacketBuilder {
             src, udp.source_port, dst, udp.dest_port, buffer.len()
         );
 
-        Ok(buffer)
+        Ok(())
     }
 
-    /// Build a UDP/IPv6 packet
-    fn build_udp_ipv6(&self, udp: &UdpPacket, src: Ipv6Addr, dst: Ipv6Addr) -> ScanResult<Vec<u8>> {
+    /// Write a UDP/IPv6 packet into `buffer`, sized to fit it exactly
+    fn write_udp_ipv6(&self, udp: &UdpPacket, src: Ipv6Addr, dst: Ipv6Addr, buffer: &mut [u8]) -> ScanResult<()> {
         let udp_total_len = 8 + udp.payload.len();
-        let ip_total_len = 40 + udp_total_len;
-
-        let mut buffer = vec![0u8; ip_total_len];
 
         // Build IPv6 header
         {
@@ -519,7 +708,7 @@ impl PacketBuilder {
                 &src,
                 &dst
             );
-            udp_packet.set_checksum(checksum);
+            udp_packet.set_checksum(if self.bad_checksum { corrupt_checksum(checksum) } else { checksum });
         }
 
         debug!(
@@ -527,7 +716,7 @@ impl PacketBuilder {
             src, udp.source_port, dst, udp.dest_port, buffer.len()
         );
 
-        Ok(buffer)
+        Ok(())
     }
 
     /// Build an ICMP packet (returns only ICMP payload, caller adds IP header)
@@ -613,6 +802,142 @@ impl Default for PacketBuilder {
 mod tests {
     use super::*;
 
+    fn syn_packet(source: IpAddr, dest: IpAddr, fragment_size: Option<usize>) -> (PacketBuilder, Vec<u8>) {
+        let mut builder = PacketBuilder::new().source(source).destination(dest).identification(0x1234);
+        if let Some(size) = fragment_size {
+            builder = builder.fragment_size(size);
+        }
+        let packet = builder
+            .build_tcp(&TcpPacket {
+                source_port: 40000,
+                dest_port: 80,
+                sequence: 1,
+                acknowledgment: 0,
+                flags: TcpFlags::syn(),
+                window: 1024,
+                urgent_pointer: 0,
+                options: Vec::new(),
+                payload: Vec::new(),
+            })
+            .unwrap();
+        (builder, packet)
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let src = Ipv4Addr::new(10, 0, 0, 1);
+        let dst = Ipv4Addr::new(10, 0, 0, 2);
+        let builder = PacketBuilder::new().source(IpAddr::V4(src)).destination(IpAddr::V4(dst));
+
+        let udp = UdpPacket {
+            source_port: 40000,
+            dest_port: 53,
+            payload: vec![1, 2, 3],
+        };
+        let good = builder.build_udp(&udp).unwrap();
+        let bad = PacketBuilder::new()
+            .source(IpAddr::V4(src))
+            .destination(IpAddr::V4(dst))
+            .bad_checksum(true)
+            .build_udp(&udp)
+            .unwrap();
+
+        let good_udp = pnet::packet::udp::UdpPacket::new(&good[20..]).unwrap();
+        let bad_udp = pnet::packet::udp::UdpPacket::new(&bad[20..]).unwrap();
+        let valid = pnet::packet::udp::ipv4_checksum(&bad_udp, &src, &dst);
+        assert_eq!(good_udp.get_checksum(), valid);
+        assert_ne!(bad_udp.get_checksum(), valid);
+        assert_ne!(bad_udp.get_checksum(), 0);
+        assert_eq!(corrupt_checksum(0xffff), 1);
+    }
+
+    #[test]
+    fn test_build_into_reused_buffers() {
+        let source = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let dest = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+        let (builder, expected) = syn_packet(source, dest, None);
+        let syn = TcpPacket {
+            source_port: 40000,
+            dest_port: 80,
+            sequence: 1,
+            acknowledgment: 0,
+            flags: TcpFlags::syn(),
+            window: 1024,
+            urgent_pointer: 0,
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+
+        // Leftovers from a longer packet must not leak into the next one
+        let mut buffer = [0xAAu8; 128];
+        let len = builder.build_tcp_into(&syn, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], &expected[..]);
+        assert!(builder.build_tcp_into(&syn, &mut [0u8; 39]).is_err());
+
+        let pool = PacketBufferPool::new(128, 4);
+        for _ in 0..3 {
+            assert_eq!(&builder.build_tcp_pooled(&syn, &pool).unwrap()[..], &expected[..]);
+        }
+        assert_eq!(pool.idle(), 1);
+
+        let udp = UdpPacket { source_port: 12345, dest_port: 53, payload: vec![1, 2, 3, 4] };
+        let pooled = builder.build_udp_pooled(&udp, &pool).unwrap();
+        assert_eq!(&pooled[..], &builder.build_udp(&udp).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_validate_fragment_size() {
+        assert!(validate_fragment_size(8).is_ok());
+        assert!(validate_fragment_size(1480).is_ok());
+        assert!(validate_fragment_size(0).is_err());
+        assert!(validate_fragment_size(12).is_err());
+    }
+
+    #[test]
+    fn test_fragment_ipv4() {
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let dst = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        // Unfragmented unless asked
+        let (builder, packet) = syn_packet(src, dst, None);
+        assert_eq!(builder.fragment(&packet).unwrap(), vec![packet]);
+
+        // A 20-byte TCP header becomes 8 + 8 + 4 bytes
+        let (builder, packet) = syn_packet(src, dst, Some(8));
+        let fragments = builder.fragment(&packet).unwrap();
+        assert_eq!(fragments.len(), 3);
+        for (index, fragment) in fragments.iter().enumerate() {
+            let ip = pnet::packet::ipv4::Ipv4Packet::new(fragment).unwrap();
+            assert_eq!(ip.get_identification(), 0x1234);
+            assert_eq!(ip.get_fragment_offset(), index as u16);
+            assert_eq!(ip.get_total_length() as usize, fragment.len());
+            let more = if index < 2 { Ipv4Flags::MoreFragments } else { 0 };
+            assert_eq!(ip.get_flags(), more);
+            assert_eq!(ip.get_checksum(), pnet::packet::ipv4::checksum(&ip));
+        }
+        let payload: Vec<u8> = fragments.iter().flat_map(|f| f[20..].to_vec()).collect();
+        assert_eq!(payload, packet[20..]);
+
+        // Packets that already fit are left whole
+        let (builder, packet) = syn_packet(src, dst, Some(24));
+        assert_eq!(builder.fragment(&packet).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_fragment_ipv6() {
+        let (builder, packet) = syn_packet("fe80::1".parse().unwrap(), "fe80::2".parse().unwrap(), Some(16));
+        let fragments = builder.fragment(&packet).unwrap();
+        assert_eq!(fragments.len(), 2);
+
+        let first = &fragments[0];
+        assert_eq!(first[6], IPV6_FRAGMENT_HEADER);
+        assert_eq!(first[40], 6); // TCP follows the fragment header
+        assert_eq!(u16::from_be_bytes([first[42], first[43]]), 1); // offset 0, M set
+        assert_eq!(u16::from_be_bytes([first[4], first[5]]) as usize, first.len() - 40);
+        assert_eq!(u16::from_be_bytes([fragments[1][42], fragments[1][43]]), 16);
+        assert_eq!(&first[48..], &packet[40..56]);
+    }
+
     #[test]
     fn test_tcp_flags() {
         let syn = TcpFlags::syn();
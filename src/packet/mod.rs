@@ -1,15 +1,25 @@
 /// Packet engine module for NrMAP
 /// 
 /// This module provides low-level packet manipulation capabilities including
-/// raw socket abstraction, packet crafting, and packet parsing.
+/// raw socket abstraction, packet crafting, packet parsing, and response capture.
 
 pub mod raw_socket;
+pub mod buffer;
 pub mod crafting;
 pub mod parser;
+pub mod capture;
+pub mod pcap;
 
 pub use raw_socket::{RawSocket, RawSocketType};
+pub use buffer::{PacketBufferPool, PooledBuffer};
 pub use crafting::{PacketBuilder, TcpPacket, UdpPacket, IcmpPacket};
-pub use parser::{PacketParser, ParsedPacket, PacketType};
+pub use parser::{
+    FragmentReassembler, IpFragment, PacketParser, ParsedPacket, ParsedPacketRef, ParsedTcpOption, PacketType,
+};
+pub use capture::{
+    CaptureConfig, CaptureFilter, CaptureStats, ConnectionKey, PacketCapture, ProbeKey, ResponseDemux,
+};
+pub use pcap::{PacketDirection, PcapWriter, SharedPcapWriter};
 
 use crate::error::ScanResult;
 use std::net::IpAddr;
@@ -21,6 +31,10 @@ pub struct PacketEngineConfig {
     pub default_ttl: u8,
     pub default_buffer_size: usize,
     pub enable_checksum_validation: bool,
+    /// Fragment payload size applied to every builder (`--fragment`/`--mtu`)
+    pub fragment_size: Option<usize>,
+    /// Corrupt TCP/UDP checksums of built packets (`--badsum`)
+    pub bad_checksum: bool,
 }
 
 impl Default for PacketEngineConfig {
@@ -30,6 +44,8 @@ impl Default for PacketEngineConfig {
             default_ttl: 64,
             default_buffer_size: 65535,
             enable_checksum_validation: true,
+            fragment_size: None,
+            bad_checksum: false,
         }
     }
 }
@@ -37,13 +53,22 @@ impl Default for PacketEngineConfig {
 /// Packet engine facade providing high-level API
 pub struct PacketEngine {
     config: PacketEngineConfig,
+    pcap: Option<SharedPcapWriter>,
 }
 
 impl PacketEngine {
     /// Create a new packet engine
     pub fn new(config: PacketEngineConfig) -> Self {
         tracing::info!("Initializing packet engine");
-        Self { config }
+        Self { config, pcap: None }
+    }
+
+    /// Record crafted packets and captured responses to a pcap file
+    ///
+    /// Applies to sockets and captures created after this call.
+    pub fn with_pcap_writer(mut self, writer: SharedPcapWriter) -> Self {
+        self.pcap = Some(writer);
+        self
     }
 
     /// Create a raw socket for the specified protocol
@@ -53,13 +78,36 @@ impl PacketEngine {
                 "Raw sockets are disabled in configuration"
             ));
         }
-        RawSocket::new(socket_type)
+        let mut socket = RawSocket::new(socket_type)?;
+        if let Some(ref writer) = self.pcap {
+            socket.set_pcap_writer(writer.clone());
+        }
+        Ok(socket)
+    }
+
+    /// Create a response capture for a set of probes (not yet started)
+    pub fn create_capture(&self, config: CaptureConfig, filter: CaptureFilter) -> ScanResult<PacketCapture> {
+        if !self.config.enable_raw_sockets {
+            return Err(crate::error::ScanError::scanner_error(
+                "Raw sockets are disabled in configuration"
+            ));
+        }
+        let capture = PacketCapture::new(config, filter);
+        Ok(match self.pcap {
+            Some(ref writer) => capture.with_pcap_writer(writer.clone()),
+            None => capture,
+        })
     }
 
     /// Create a packet builder
     pub fn builder(&self) -> PacketBuilder {
-        PacketBuilder::new()
+        let builder = PacketBuilder::new()
             .ttl(self.config.default_ttl)
+            .bad_checksum(self.config.bad_checksum);
+        match self.config.fragment_size {
+            Some(size) => builder.fragment_size(size),
+            None => builder,
+        }
     }
 
     /// Create a packet parser
@@ -4,17 +4,17 @@
 /// with validation and field extraction using the pnet library.
 
 use crate::error::{ScanError, ScanResult};
-use crate::packet::crafting::TcpFlags;
-use pnet::packet::Packet;
-use pnet::packet::ipv4::Ipv4Packet;
+use crate::packet::crafting::{TcpFlags, IPV6_FRAGMENT_HEADER};
+use pnet::packet::ipv4::{Ipv4Flags, Ipv4Packet};
 use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::tcp::TcpPacket as PnetTcpPacket;
-use pnet::packet::tcp::TcpOption;
 use pnet::packet::udp::UdpPacket as PnetUdpPacket;
 use pnet::packet::icmp::IcmpPacket as PnetIcmpPacket;
 use pnet::packet::icmp::echo_request::EchoRequestPacket;
 use pnet::packet::icmp::echo_reply::EchoReplyPacket;
+use std::collections::{BTreeMap, HashMap};
 use std::net::IpAddr;
+use std::time::{Duration, Instant};
 use tracing::{debug, trace, warn};
 
 /// Type of parsed packet
@@ -40,6 +40,71 @@ pub struct ParsedPacket {
     pub tcp_info: Option<ParsedTcpPacket>,
     pub udp_info: Option<ParsedUdpPacket>,
     pub icmp_info: Option<ParsedIcmpPacket>,
+    /// Set when the packet is an IP fragment; transport headers are then
+    /// left unparsed until the datagram is reassembled
+    pub fragment: Option<IpFragment>,
+}
+
+/// Parsed packet borrowing its payload from the raw bytes
+///
+/// Returned by [`PacketParser::parse_ref`] so hot paths can inspect and
+/// filter packets without copying them; convert the few worth keeping with
+/// [`ParsedPacketRef::into_owned`].
+#[derive(Debug, Clone)]
+pub struct ParsedPacketRef<'a> {
+    pub packet_type: PacketType,
+    pub source_ip: IpAddr,
+    pub dest_ip: IpAddr,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub payload: &'a [u8],
+    pub tcp_info: Option<ParsedTcpPacket>,
+    pub udp_info: Option<ParsedUdpPacket>,
+    pub icmp_info: Option<ParsedIcmpPacket>,
+    pub fragment: Option<IpFragment>,
+}
+
+impl ParsedPacketRef<'_> {
+    /// Copy the payload into an owned [`ParsedPacket`]
+    pub fn into_owned(self) -> ParsedPacket {
+        ParsedPacket {
+            packet_type: self.packet_type,
+            source_ip: self.source_ip,
+            dest_ip: self.dest_ip,
+            ttl: self.ttl,
+            protocol: self.protocol,
+            payload: self.payload.to_vec(),
+            tcp_info: self.tcp_info,
+            udp_info: self.udp_info,
+            icmp_info: self.icmp_info,
+            fragment: self.fragment,
+        }
+    }
+}
+
+/// Bytes `start..start + len` of `data`, clamped to what was captured
+fn payload_slice(data: &[u8], start: usize, len: usize) -> &[u8] {
+    let start = start.min(data.len());
+    let end = start.saturating_add(len).min(data.len());
+    &data[start..end]
+}
+
+/// Transport-layer fields parsed out of an IP payload
+type TransportFields<'a> = (
+    PacketType,
+    Option<ParsedTcpPacket>,
+    Option<ParsedUdpPacket>,
+    Option<ParsedIcmpPacket>,
+    &'a [u8],
+);
+
+/// Position of an IP fragment within its datagram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpFragment {
+    pub identification: u32,
+    /// Offset of this fragment's data in bytes
+    pub offset: usize,
+    pub more_fragments: bool,
 }
 
 /// Parsed TCP packet
@@ -54,7 +119,174 @@ pub struct ParsedTcpPacket {
     pub checksum: u16,
     pub urgent_pointer: u16,
     pub data_offset: u8,
-    pub options: Vec<TcpOption>,
+    pub options: Vec<ParsedTcpOption>,
+}
+
+impl ParsedTcpPacket {
+    /// Maximum segment size option, if present
+    pub fn mss(&self) -> Option<u16> {
+        self.options.iter().find_map(|o| match o {
+            ParsedTcpOption::Mss(mss) => Some(*mss),
+            _ => None,
+        })
+    }
+
+    /// Window scale shift count, if present
+    pub fn window_scale(&self) -> Option<u8> {
+        self.options.iter().find_map(|o| match o {
+            ParsedTcpOption::WindowScale(shift) => Some(*shift),
+            _ => None,
+        })
+    }
+
+    /// TCP timestamp option as (TSval, TSecr), if present
+    pub fn timestamp(&self) -> Option<(u32, u32)> {
+        self.options.iter().find_map(|o| match o {
+            ParsedTcpOption::Timestamp { value, echo_reply } => Some((*value, *echo_reply)),
+            _ => None,
+        })
+    }
+
+    /// Whether the SACK-permitted option is present
+    pub fn sack_permitted(&self) -> bool {
+        self.options.contains(&ParsedTcpOption::SackPermitted)
+    }
+
+    /// Options re-encoded in wire format (original order preserved)
+    pub fn options_bytes(&self) -> Vec<u8> {
+        self.options.iter().flat_map(|o| o.to_bytes()).collect()
+    }
+}
+
+/// A decoded TCP option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsedTcpOption {
+    /// End of option list (kind 0)
+    EndOfOptions,
+    /// No-operation padding (kind 1)
+    Nop,
+    /// Maximum segment size (kind 2)
+    Mss(u16),
+    /// Window scale shift count (kind 3)
+    WindowScale(u8),
+    /// SACK permitted (kind 4)
+    SackPermitted,
+    /// Selective acknowledgment blocks (kind 5)
+    Sack(Vec<(u32, u32)>),
+    /// Timestamps (kind 8)
+    Timestamp { value: u32, echo_reply: u32 },
+    /// TCP MD5 signature (kind 19)
+    Md5Signature(Vec<u8>),
+    /// Any other option, passed through undecoded
+    Unknown { kind: u8, data: Vec<u8> },
+}
+
+impl ParsedTcpOption {
+    /// Option kind number
+    pub fn kind(&self) -> u8 {
+        match self {
+            ParsedTcpOption::EndOfOptions => 0,
+            ParsedTcpOption::Nop => 1,
+            ParsedTcpOption::Mss(_) => 2,
+            ParsedTcpOption::WindowScale(_) => 3,
+            ParsedTcpOption::SackPermitted => 4,
+            ParsedTcpOption::Sack(_) => 5,
+            ParsedTcpOption::Timestamp { .. } => 8,
+            ParsedTcpOption::Md5Signature(_) => 19,
+            ParsedTcpOption::Unknown { kind, .. } => *kind,
+        }
+    }
+
+    /// Encode the option in wire format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = match self {
+            ParsedTcpOption::EndOfOptions => return vec![0],
+            ParsedTcpOption::Nop => return vec![1],
+            ParsedTcpOption::Mss(mss) => mss.to_be_bytes().to_vec(),
+            ParsedTcpOption::WindowScale(shift) => vec![*shift],
+            ParsedTcpOption::SackPermitted => Vec::new(),
+            ParsedTcpOption::Sack(blocks) => blocks
+                .iter()
+                .flat_map(|(left, right)| left.to_be_bytes().into_iter().chain(right.to_be_bytes()))
+                .collect(),
+            ParsedTcpOption::Timestamp { value, echo_reply } => {
+                value.to_be_bytes().into_iter().chain(echo_reply.to_be_bytes()).collect()
+            }
+            ParsedTcpOption::Md5Signature(digest) => digest.clone(),
+            ParsedTcpOption::Unknown { data, .. } => data.clone(),
+        };
+
+        let mut bytes = Vec::with_capacity(data.len() + 2);
+        bytes.push(self.kind());
+        bytes.push((data.len() + 2) as u8);
+        bytes.extend(data);
+        bytes
+    }
+}
+
+/// Decode the options area of a TCP header
+///
+/// Parsing stops at end-of-options or at the first malformed option, so a
+/// truncated or corrupted options area yields the options decoded so far.
+pub fn parse_tcp_options(bytes: &[u8]) -> Vec<ParsedTcpOption> {
+    let mut options = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let kind = bytes[i];
+        match kind {
+            0 => {
+                options.push(ParsedTcpOption::EndOfOptions);
+                break;
+            }
+            1 => {
+                options.push(ParsedTcpOption::Nop);
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(&len) = bytes.get(i + 1) else {
+            trace!("Truncated TCP option kind {}", kind);
+            break;
+        };
+        let len = len as usize;
+        if len < 2 || i + len > bytes.len() {
+            trace!("Malformed TCP option kind {} length {}", kind, len);
+            break;
+        }
+        let data = &bytes[i + 2..i + len];
+
+        let option = match (kind, data.len()) {
+            (2, 2) => ParsedTcpOption::Mss(u16::from_be_bytes([data[0], data[1]])),
+            (3, 1) => ParsedTcpOption::WindowScale(data[0]),
+            (4, 0) => ParsedTcpOption::SackPermitted,
+            (5, n) if n % 8 == 0 => ParsedTcpOption::Sack(
+                data.chunks_exact(8)
+                    .map(|c| {
+                        (
+                            u32::from_be_bytes([c[0], c[1], c[2], c[3]]),
+                            u32::from_be_bytes([c[4], c[5], c[6], c[7]]),
+                        )
+                    })
+                    .collect(),
+            ),
+            (8, 8) => ParsedTcpOption::Timestamp {
+                value: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+                echo_reply: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            },
+            (19, 16) => ParsedTcpOption::Md5Signature(data.to_vec()),
+            _ => ParsedTcpOption::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+        };
+        options.push(option);
+        i += len;
+    }
+
+    options
 }
 
 /// Parsed UDP packet
@@ -100,6 +332,11 @@ impl PacketParser {
     /// # Returns
     /// * `ScanResult<ParsedPacket>` - Parsed packet information
     pub fn parse(&self, data: &[u8]) -> ScanResult<ParsedPacket> {
+        self.parse_ref(data).map(ParsedPacketRef::into_owned)
+    }
+
+    /// Parse a raw packet without copying its payload
+    pub fn parse_ref<'a>(&self, data: &'a [u8]) -> ScanResult<ParsedPacketRef<'a>> {
         trace!("Parsing packet of {} bytes with pnet", data.len());
 
         if data.len() < 20 {
@@ -120,7 +357,7 @@ impl PacketParser {
     }
 
     /// Parse an IPv4 packet using pnet
-    fn parse_ipv4(&self, data: &[u8]) -> ScanResult<ParsedPacket> {
+    fn parse_ipv4<'a>(&self, data: &'a [u8]) -> ScanResult<ParsedPacketRef<'a>> {
         let ip_packet = Ipv4Packet::new(data)
             .ok_or_else(|| ScanError::packet_error("Failed to parse IPv4 packet"))?;
 
@@ -145,12 +382,26 @@ impl PacketParser {
             source_ip, dest_ip, protocol, ttl
         );
 
+        let more_fragments = ip_packet.get_flags() & Ipv4Flags::MoreFragments != 0;
+        let fragment = (more_fragments || ip_packet.get_fragment_offset() > 0).then(|| IpFragment {
+            identification: u32::from(ip_packet.get_identification()),
+            offset: ip_packet.get_fragment_offset() as usize * 8,
+            more_fragments,
+        });
+
         // Parse transport layer
-        let transport_data = ip_packet.payload();
-        let (packet_type, tcp_info, udp_info, icmp_info, payload) =
-            self.parse_transport_layer(protocol, transport_data, source_ip, dest_ip)?;
+        let header_len = ip_packet.get_header_length() as usize * 4;
+        let transport_data = payload_slice(
+            data,
+            header_len,
+            (ip_packet.get_total_length() as usize).saturating_sub(header_len),
+        );
+        let (packet_type, tcp_info, udp_info, icmp_info, payload) = match fragment {
+            Some(_) => Self::unparsed_fragment(protocol, transport_data),
+            None => self.parse_transport_layer(protocol, transport_data, source_ip, dest_ip)?,
+        };
 
-        Ok(ParsedPacket {
+        Ok(ParsedPacketRef {
             packet_type,
             source_ip,
             dest_ip,
@@ -160,18 +411,38 @@ impl PacketParser {
             tcp_info,
             udp_info,
             icmp_info,
+            fragment,
         })
     }
 
     /// Parse an IPv6 packet using pnet
-    fn parse_ipv6(&self, data: &[u8]) -> ScanResult<ParsedPacket> {
+    fn parse_ipv6<'a>(&self, data: &'a [u8]) -> ScanResult<ParsedPacketRef<'a>> {
         let ip_packet = Ipv6Packet::new(data)
             .ok_or_else(|| ScanError::packet_error("Failed to parse IPv6 packet"))?;
 
         let source_ip = IpAddr::V6(ip_packet.get_source());
         let dest_ip = IpAddr::V6(ip_packet.get_destination());
         let ttl = ip_packet.get_hop_limit();
-        let protocol = ip_packet.get_next_header().0;
+        let mut protocol = ip_packet.get_next_header().0;
+        let mut transport_data = payload_slice(data, 40, ip_packet.get_payload_length() as usize);
+
+        // A Fragment extension header names the real upper-layer protocol
+        let mut fragment = None;
+        if protocol == IPV6_FRAGMENT_HEADER {
+            if transport_data.len() < 8 {
+                return Err(ScanError::packet_error("Truncated IPv6 fragment header"));
+            }
+            let offset_field = u16::from_be_bytes([transport_data[2], transport_data[3]]);
+            fragment = Some(IpFragment {
+                identification: u32::from_be_bytes([
+                    transport_data[4], transport_data[5], transport_data[6], transport_data[7],
+                ]),
+                offset: (offset_field & 0xFFF8) as usize,
+                more_fragments: offset_field & 1 != 0,
+            });
+            protocol = transport_data[0];
+            transport_data = &transport_data[8..];
+        }
 
         debug!(
             "Parsed IPv6: {} -> {}, protocol={}, hop_limit={}",
@@ -179,11 +450,12 @@ impl PacketParser {
         );
 
         // Parse transport layer
-        let transport_data = ip_packet.payload();
-        let (packet_type, tcp_info, udp_info, icmp_info, payload) =
-            self.parse_transport_layer(protocol, transport_data, source_ip, dest_ip)?;
+        let (packet_type, tcp_info, udp_info, icmp_info, payload) = match fragment {
+            Some(_) => Self::unparsed_fragment(protocol, transport_data),
+            None => self.parse_transport_layer(protocol, transport_data, source_ip, dest_ip)?,
+        };
 
-        Ok(ParsedPacket {
+        Ok(ParsedPacketRef {
             packet_type,
             source_ip,
             dest_ip,
@@ -193,71 +465,63 @@ impl PacketParser {
             tcp_info,
             udp_info,
             icmp_info,
+            fragment,
         })
     }
 
+    /// Classify a fragment without parsing its (possibly partial) transport header
+    fn unparsed_fragment(protocol: u8, data: &[u8]) -> TransportFields<'_> {
+        let packet_type = match protocol {
+            6 => PacketType::Tcp,
+            17 => PacketType::Udp,
+            1 => PacketType::Icmp,
+            58 => PacketType::Icmpv6,
+            2 => PacketType::Igmp,
+            other => PacketType::Other(other),
+        };
+        (packet_type, None, None, None, data)
+    }
+
     /// Parse transport layer (TCP/UDP/ICMP) using pnet
-    fn parse_transport_layer(
+    fn parse_transport_layer<'a>(
         &self,
         protocol: u8,
-        data: &[u8],
+        data: &'a [u8],
         source_ip: IpAddr,
         dest_ip: IpAddr,
-    ) -> ScanResult<(
-        PacketType,
-        Option<ParsedTcpPacket>,
-        Option<ParsedUdpPacket>,
-        Option<ParsedIcmpPacket>,
-        Vec<u8>,
-    )> {
+    ) -> ScanResult<TransportFields<'a>> {
         match protocol {
             6 => {
                 // TCP
                 let tcp_info = self.parse_tcp(data, source_ip, dest_ip)?;
                 let payload_offset = tcp_info.data_offset as usize * 4;
-                let payload = if payload_offset < data.len() {
-                    data[payload_offset..].to_vec()
-                } else {
-                    vec![]
-                };
+                let payload = data.get(payload_offset..).unwrap_or_default();
                 Ok((PacketType::Tcp, Some(tcp_info), None, None, payload))
             }
             17 => {
                 // UDP
                 let udp_info = self.parse_udp(data, source_ip, dest_ip)?;
-                let payload = if data.len() > 8 {
-                    data[8..].to_vec()
-                } else {
-                    vec![]
-                };
+                let payload = data.get(8..).unwrap_or_default();
                 Ok((PacketType::Udp, None, Some(udp_info), None, payload))
             }
             1 => {
                 // ICMP (IPv4)
                 let icmp_info = self.parse_icmp(data)?;
-                let payload = if data.len() > 8 {
-                    data[8..].to_vec()
-                } else {
-                    vec![]
-                };
+                let payload = data.get(8..).unwrap_or_default();
                 Ok((PacketType::Icmp, None, None, Some(icmp_info), payload))
             }
             58 => {
                 // ICMPv6
                 let icmp_info = self.parse_icmp(data)?;
-                let payload = if data.len() > 8 {
-                    data[8..].to_vec()
-                } else {
-                    vec![]
-                };
+                let payload = data.get(8..).unwrap_or_default();
                 Ok((PacketType::Icmpv6, None, None, Some(icmp_info), payload))
             }
             2 => {
                 // IGMP
-                Ok((PacketType::Igmp, None, None, None, data.to_vec()))
+                Ok((PacketType::Igmp, None, None, None, data))
             }
             _ => {
-                Ok((PacketType::Other(protocol), None, None, None, data.to_vec()))
+                Ok((PacketType::Other(protocol), None, None, None, data))
             }
         }
     }
@@ -291,9 +555,11 @@ impl PacketParser {
         }
 
         let flags = TcpFlags::from_u8(tcp_packet.get_flags());
-        // For now, we'll skip parsing TCP options due to pnet API complexities
-        // In production, you'd need to manually extract and construct TcpOptions
-        let options: Vec<TcpOption> = vec![];
+        let header_len = tcp_packet.get_data_offset() as usize * 4;
+        let options = data
+            .get(20..header_len)
+            .map(parse_tcp_options)
+            .unwrap_or_default();
 
         debug!(
             "Parsed TCP: {}:{} -> {}:{}, seq={}, ack={}, flags={:?}",
@@ -418,6 +684,141 @@ impl PacketParser {
     }
 }
 
+/// Identifies the datagram a fragment belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FragmentKey {
+    source_ip: IpAddr,
+    dest_ip: IpAddr,
+    protocol: u8,
+    identification: u32,
+}
+
+/// Fragments received so far for one datagram
+struct PendingDatagram {
+    /// Header of the first fragment, once seen
+    header: Option<Vec<u8>>,
+    pieces: BTreeMap<usize, Vec<u8>>,
+    total_len: Option<usize>,
+    first_seen: Instant,
+}
+
+impl PendingDatagram {
+    /// Concatenate the pieces if they cover the whole datagram
+    fn assemble(&self) -> Option<Vec<u8>> {
+        let total_len = self.total_len?;
+        let mut data = vec![0u8; total_len];
+        let mut covered = 0;
+        for (&offset, piece) in &self.pieces {
+            if offset > covered {
+                return None;
+            }
+            let end = (offset + piece.len()).min(total_len);
+            if end > offset {
+                data[offset..end].copy_from_slice(&piece[..end - offset]);
+            }
+            covered = covered.max(end);
+        }
+        (covered == total_len).then_some(data)
+    }
+}
+
+/// Reassembles IP fragments into whole packets
+///
+/// Used to check fragmented probes (`--fragment`/`--mtu`) end to end: feed it
+/// raw packets and parse whatever complete datagrams come out.
+pub struct FragmentReassembler {
+    parser: PacketParser,
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    timeout: Duration,
+}
+
+impl FragmentReassembler {
+    /// Create a reassembler that drops incomplete datagrams after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            parser: PacketParser::new(false),
+            pending: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Add a raw packet
+    ///
+    /// Returns unfragmented packets as-is, and the reassembled packet once the
+    /// last missing fragment of a datagram arrives.
+    pub fn push(&mut self, data: &[u8]) -> ScanResult<Option<Vec<u8>>> {
+        let parsed = self.parser.parse_ref(data)?;
+        let Some(fragment) = parsed.fragment else {
+            return Ok(Some(data.to_vec()));
+        };
+
+        let timeout = self.timeout;
+        self.pending.retain(|_, d| d.first_seen.elapsed() < timeout);
+
+        let key = FragmentKey {
+            source_ip: parsed.source_ip,
+            dest_ip: parsed.dest_ip,
+            protocol: parsed.protocol,
+            identification: fragment.identification,
+        };
+        let pending = self.pending.entry(key).or_insert_with(|| PendingDatagram {
+            header: None,
+            pieces: BTreeMap::new(),
+            total_len: None,
+            first_seen: Instant::now(),
+        });
+
+        let header_len = data.len() - parsed.payload.len();
+        if fragment.offset == 0 {
+            pending.header = Some(data[..header_len].to_vec());
+        }
+        if !fragment.more_fragments {
+            pending.total_len = Some(fragment.offset + parsed.payload.len());
+        }
+        pending.pieces.insert(fragment.offset, parsed.payload.to_vec());
+
+        let Some(payload) = pending.assemble() else {
+            return Ok(None);
+        };
+        let Some(header) = pending.header.take() else {
+            return Ok(None);
+        };
+        self.pending.remove(&key);
+
+        trace!("Reassembled {}-byte datagram {:#x}", payload.len(), fragment.identification);
+        Ok(Some(Self::rebuild(header, parsed.protocol, &payload)))
+    }
+
+    /// Number of datagrams still missing fragments
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Turn the first fragment's header into an unfragmented packet header
+    fn rebuild(mut header: Vec<u8>, protocol: u8, payload: &[u8]) -> Vec<u8> {
+        if header[0] >> 4 == 4 {
+            let total_len = (header.len() + payload.len()) as u16;
+            header[2..4].copy_from_slice(&total_len.to_be_bytes());
+            header[6] &= 0xC0; // keep DF/reserved, clear MF and the offset
+            header[7] = 0;
+            header[10..12].copy_from_slice(&[0, 0]);
+            header.extend_from_slice(payload);
+            if let Some(ip) = Ipv4Packet::new(&header) {
+                let checksum = pnet::packet::ipv4::checksum(&ip);
+                header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            }
+            header
+        } else {
+            // Drop the Fragment extension header
+            header.truncate(40);
+            header[4..6].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+            header[6] = protocol;
+            header.extend_from_slice(payload);
+            header
+        }
+    }
+}
+
 impl Default for PacketParser {
     fn default() -> Self {
         Self::new(true)
@@ -468,6 +869,158 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_tcp_options() {
+        let bytes = [
+            2, 4, 0x05, 0xb4, // MSS 1460
+            4, 2, // SACK permitted
+            8, 10, 0, 0, 0x10, 0, 0, 0, 0, 1, // Timestamp 4096/1
+            1, // NOP
+            3, 3, 7, // Window scale 7
+            5, 10, 0, 0, 0, 1, 0, 0, 0, 2, // SACK block 1-2
+            19, 18, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, // MD5
+            30, 3, 0xaa, // Unknown kind 30
+            0, // EOL
+        ];
+        let options = parse_tcp_options(&bytes);
+        assert_eq!(
+            options,
+            vec![
+                ParsedTcpOption::Mss(1460),
+                ParsedTcpOption::SackPermitted,
+                ParsedTcpOption::Timestamp { value: 4096, echo_reply: 1 },
+                ParsedTcpOption::Nop,
+                ParsedTcpOption::WindowScale(7),
+                ParsedTcpOption::Sack(vec![(1, 2)]),
+                ParsedTcpOption::Md5Signature((0..16).collect()),
+                ParsedTcpOption::Unknown { kind: 30, data: vec![0xaa] },
+                ParsedTcpOption::EndOfOptions,
+            ]
+        );
+
+        let encoded: Vec<u8> = options.iter().flat_map(|o| o.to_bytes()).collect();
+        assert_eq!(encoded, bytes);
+
+        // Malformed length stops parsing without panicking
+        assert_eq!(parse_tcp_options(&[2, 4, 0x05, 0xb4, 8, 40, 0]), vec![ParsedTcpOption::Mss(1460)]);
+        assert_eq!(parse_tcp_options(&[3]), vec![]);
+    }
+
+    #[test]
+    fn test_parse_tcp_packet_options() {
+        use crate::packet::crafting::{PacketBuilder, TcpPacket};
+        use std::net::Ipv4Addr;
+
+        let mut packet = PacketBuilder::new()
+            .source(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+            .destination(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+            .build_tcp(&TcpPacket {
+                source_port: 443,
+                dest_port: 40000,
+                sequence: 1,
+                acknowledgment: 2,
+                flags: TcpFlags::syn_ack(),
+                window: 65160,
+                urgent_pointer: 0,
+                options: Vec::new(),
+                payload: Vec::new(),
+            })
+            .unwrap();
+        let options = [2, 4, 0x05, 0xb4, 1, 1, 8, 10, 0, 0, 0, 42, 0, 0, 0, 7, 1, 3, 3, 7];
+        packet[32] = (((20 + options.len()) / 4) as u8) << 4;
+        packet.splice(40..40, options);
+        let total_len = packet.len() as u16;
+        packet[2..4].copy_from_slice(&total_len.to_be_bytes());
+
+        let parsed = PacketParser::new(false).parse(&packet).unwrap();
+        let tcp = parsed.tcp_info.unwrap();
+        assert_eq!(tcp.mss(), Some(1460));
+        assert_eq!(tcp.timestamp(), Some((42, 7)));
+        assert_eq!(tcp.window_scale(), Some(7));
+        assert!(!tcp.sack_permitted());
+        assert_eq!(tcp.options_bytes(), options);
+    }
+
+    #[test]
+    fn test_parse_ref_borrows_payload() {
+        use crate::packet::crafting::{PacketBuilder, UdpPacket};
+        use std::net::Ipv4Addr;
+
+        let mut packet = PacketBuilder::new()
+            .source(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+            .destination(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+            .build_udp(&UdpPacket { source_port: 53, dest_port: 40000, payload: b"answer".to_vec() })
+            .unwrap();
+        // Ethernet padding past the IP total length is not payload
+        packet.extend_from_slice(&[0; 6]);
+
+        let parser = PacketParser::new(false);
+        let parsed = parser.parse_ref(&packet).unwrap();
+        assert_eq!(parsed.payload, b"answer");
+        assert_eq!(parsed.payload.as_ptr(), packet[28..].as_ptr());
+        assert_eq!(parsed.udp_info.as_ref().unwrap().dest_port, 40000);
+        assert_eq!(parsed.into_owned().payload, parser.parse(&packet).unwrap().payload);
+    }
+
+    #[test]
+    fn test_reassemble_fragmented_probe() {
+        use crate::packet::crafting::{PacketBuilder, TcpPacket};
+
+        for (source, dest) in [("10.0.0.1", "10.0.0.2"), ("fe80::1", "fe80::2")] {
+            let builder = PacketBuilder::new()
+                .source(source.parse().unwrap())
+                .destination(dest.parse().unwrap())
+                .fragment_size(8);
+            let packet = builder
+                .build_tcp(&TcpPacket {
+                    source_port: 40000,
+                    dest_port: 443,
+                    sequence: 99,
+                    acknowledgment: 0,
+                    flags: TcpFlags::syn(),
+                    window: 1024,
+                    urgent_pointer: 0,
+                    options: Vec::new(),
+                    payload: b"probe".to_vec(),
+                })
+                .unwrap();
+            let mut fragments = builder.fragment(&packet).unwrap();
+            assert_eq!(fragments.len(), 4);
+
+            // Fragments parse without touching the split TCP header
+            let parsed = PacketParser::new(true).parse(&fragments[1]).unwrap();
+            assert_eq!(parsed.packet_type, PacketType::Tcp);
+            assert!(parsed.tcp_info.is_none());
+            assert_eq!(parsed.fragment.unwrap().offset, 8);
+
+            // Out-of-order delivery still reassembles
+            fragments.swap(0, 3);
+            let mut reassembler = FragmentReassembler::new(Duration::from_secs(30));
+            let mut whole = None;
+            for fragment in &fragments {
+                whole = reassembler.push(fragment).unwrap();
+            }
+            let whole = whole.unwrap();
+            assert_eq!(reassembler.pending_count(), 0);
+
+            let parsed = PacketParser::new(true).parse(&whole).unwrap();
+            assert!(parsed.fragment.is_none());
+            let tcp = parsed.tcp_info.unwrap();
+            assert_eq!((tcp.source_port, tcp.dest_port, tcp.sequence), (40000, 443, 99));
+            assert_eq!(parsed.payload, b"probe");
+        }
+    }
+
+    #[test]
+    fn test_reassembler_passes_whole_packets() {
+        let mut reassembler = FragmentReassembler::new(Duration::from_secs(30));
+        let mut packet = vec![0u8; 20];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&20u16.to_be_bytes());
+        packet[9] = 89;
+        assert_eq!(reassembler.push(&packet).unwrap(), Some(packet));
+    }
+
     #[test]
     fn test_parse_invalid_version() {
         let parser = PacketParser::new(false);
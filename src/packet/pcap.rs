@@ -0,0 +1,245 @@
+//! PCAP-NG export of scan traffic
+//!
+//! Writes crafted probes and captured responses to a standard pcapng file so
+//! that security teams have packet evidence of what the scanner actually sent.
+//! Packets are stored as raw IP (LINKTYPE_RAW) with the inbound/outbound
+//! direction recorded in the `epb_flags` option.
+
+use crate::error::{ScanError, ScanResult};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const LINKTYPE_RAW: u16 = 101;
+const SNAPLEN: u32 = 65535;
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_EPB_FLAGS: u16 = 2;
+
+/// Direction of a recorded packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketDirection {
+    Inbound,
+    Outbound,
+}
+
+impl PacketDirection {
+    fn epb_flags(self) -> u32 {
+        match self {
+            PacketDirection::Inbound => 0b01,
+            PacketDirection::Outbound => 0b10,
+        }
+    }
+}
+
+/// Writer for a single pcapng file
+pub struct PcapWriter {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    packets_written: u64,
+}
+
+/// Writer shared between the sender and capture threads
+pub type SharedPcapWriter = Arc<Mutex<PcapWriter>>;
+
+impl PcapWriter {
+    /// Create a new pcapng file and write its section and interface headers
+    pub fn create<P: AsRef<Path>>(path: P) -> ScanResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(&path).map_err(|e| {
+            ScanError::OutputError {
+                message: format!("Failed to create pcap file {}: {}", path.display(), e),
+            }
+        })?;
+
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+            path,
+            packets_written: 0,
+        };
+        writer.write_section_header()?;
+        writer.write_interface_description()?;
+
+        info!("Recording scan traffic to {}", writer.path.display());
+        Ok(writer)
+    }
+
+    /// Create a writer wrapped for sharing between threads
+    pub fn create_shared<P: AsRef<Path>>(path: P) -> ScanResult<SharedPcapWriter> {
+        Ok(Arc::new(Mutex::new(Self::create(path)?)))
+    }
+
+    /// Path of the file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of packets written so far
+    pub fn packets_written(&self) -> u64 {
+        self.packets_written
+    }
+
+    /// Append a raw IP packet
+    ///
+    /// # Arguments
+    /// * `data` - IP packet bytes (starting with the IPv4/IPv6 header)
+    /// * `direction` - Whether the packet was sent or received
+    /// * `timestamp` - Time the packet was sent or captured
+    pub fn write_packet(&mut self, data: &[u8], direction: PacketDirection, timestamp: SystemTime) -> ScanResult<()> {
+        let captured = &data[..data.len().min(SNAPLEN as usize)];
+        let padded_len = padded(captured.len());
+        // header(28) + data + options(flags 8 + end 4) + trailer(4)
+        let total_len = (28 + padded_len + 12 + 4) as u32;
+
+        let micros = timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+
+        let mut block = Vec::with_capacity(total_len as usize);
+        block.extend_from_slice(&ENHANCED_PACKET_BLOCK.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes()); // interface id
+        block.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        block.extend_from_slice(&(micros as u32).to_le_bytes());
+        block.extend_from_slice(&(captured.len() as u32).to_le_bytes());
+        block.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        block.extend_from_slice(captured);
+        block.resize(block.len() + padded_len - captured.len(), 0);
+        block.extend_from_slice(&OPT_EPB_FLAGS.to_le_bytes());
+        block.extend_from_slice(&4u16.to_le_bytes());
+        block.extend_from_slice(&direction.epb_flags().to_le_bytes());
+        block.extend_from_slice(&OPT_ENDOFOPT.to_le_bytes());
+        block.extend_from_slice(&0u16.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+
+        self.writer.write_all(&block)?;
+        self.packets_written += 1;
+        Ok(())
+    }
+
+    /// Flush buffered packets to disk
+    pub fn flush(&mut self) -> ScanResult<()> {
+        self.writer.flush()?;
+        debug!("Flushed {} packets to {}", self.packets_written, self.path.display());
+        Ok(())
+    }
+
+    fn write_section_header(&mut self) -> ScanResult<()> {
+        let total_len: u32 = 28;
+        let mut block = Vec::with_capacity(total_len as usize);
+        block.extend_from_slice(&SECTION_HEADER_BLOCK.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        block.extend_from_slice(&1u16.to_le_bytes()); // major version
+        block.extend_from_slice(&0u16.to_le_bytes()); // minor version
+        block.extend_from_slice(&(-1i64).to_le_bytes()); // section length unknown
+        block.extend_from_slice(&total_len.to_le_bytes());
+        self.writer.write_all(&block)?;
+        Ok(())
+    }
+
+    fn write_interface_description(&mut self) -> ScanResult<()> {
+        let total_len: u32 = 20;
+        let mut block = Vec::with_capacity(total_len as usize);
+        block.extend_from_slice(&INTERFACE_DESCRIPTION_BLOCK.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        block.extend_from_slice(&0u16.to_le_bytes()); // reserved
+        block.extend_from_slice(&SNAPLEN.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        self.writer.write_all(&block)?;
+        Ok(())
+    }
+}
+
+impl Drop for PcapWriter {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Record a packet into an optional shared writer, logging failures
+pub fn record(writer: Option<&SharedPcapWriter>, data: &[u8], direction: PacketDirection) {
+    if let Some(writer) = writer {
+        if let Ok(mut writer) = writer.lock() {
+            if let Err(e) = writer.write_packet(data, direction, SystemTime::now()) {
+                debug!("Failed to record packet to pcap: {}", e);
+            }
+        }
+    }
+}
+
+/// Derive the per-scan file name for a configured base path
+///
+/// `results/scan.pcapng` with label `10.0.0.1` becomes
+/// `results/scan-10.0.0.1-20240301T120000.pcapng`.
+pub fn rotated_path<P: AsRef<Path>>(base: P, label: &str) -> PathBuf {
+    let base = base.as_ref();
+    let stem = base
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "nrmap".to_string());
+    let label: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%3f");
+
+    base.with_file_name(format!("{}-{}-{}.pcapng", stem, label, timestamp))
+}
+
+fn padded(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_pcapng() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.pcapng");
+
+        let mut writer = PcapWriter::create(&path).unwrap();
+        let packet = [0x45u8, 0, 0, 21, 0, 0, 0, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 0xff];
+        writer.write_packet(&packet, PacketDirection::Outbound, SystemTime::now()).unwrap();
+        writer.write_packet(&packet, PacketDirection::Inbound, SystemTime::now()).unwrap();
+        assert_eq!(writer.packets_written(), 2);
+        drop(writer);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], &SECTION_HEADER_BLOCK.to_le_bytes());
+        assert_eq!(&bytes[8..12], &BYTE_ORDER_MAGIC.to_le_bytes());
+        assert_eq!(&bytes[28..32], &INTERFACE_DESCRIPTION_BLOCK.to_le_bytes());
+
+        // First EPB follows SHB (28) and IDB (20); 21 data bytes pad to 24
+        let epb = &bytes[48..];
+        assert_eq!(&epb[0..4], &ENHANCED_PACKET_BLOCK.to_le_bytes());
+        let total_len = u32::from_le_bytes(epb[4..8].try_into().unwrap()) as usize;
+        assert_eq!(total_len, 28 + 24 + 12 + 4);
+        assert_eq!(&epb[total_len - 4..total_len], &(total_len as u32).to_le_bytes());
+        assert_eq!(bytes.len(), 48 + total_len * 2);
+    }
+
+    #[test]
+    fn test_rotated_path() {
+        let path = rotated_path("results/scan.pcapng", "fe80::1");
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(path.starts_with("results"));
+        assert!(name.starts_with("scan-fe80__1-"));
+        assert!(name.ends_with(".pcapng"));
+    }
+}
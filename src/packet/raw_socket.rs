@@ -4,6 +4,7 @@
 /// network packets at the IP layer.
 
 use crate::error::{ScanError, ScanResult};
+use crate::packet::pcap::{self, PacketDirection, SharedPcapWriter};
 use std::net::{IpAddr, Ipv4Addr};
 use std::time::Duration;
 use tokio::time::timeout;
@@ -30,6 +31,7 @@ pub struct RawSocket {
     #[allow(dead_code)]
     socket: Option<socket2::Socket>,
     buffer_size: usize,
+    pcap: Option<SharedPcapWriter>,
 }
 
 impl RawSocket {
@@ -65,6 +67,7 @@ impl RawSocket {
             socket_type,
             socket: None,
             buffer_size: 65535,
+            pcap: None,
         })
     }
 
@@ -95,6 +98,11 @@ impl RawSocket {
         Ok(())
     }
 
+    /// Record every packet sent through this socket to a pcap file
+    pub fn set_pcap_writer(&mut self, writer: SharedPcapWriter) {
+        self.pcap = Some(writer);
+    }
+
     /// Send data to a destination
     /// 
     /// # Arguments
@@ -129,6 +137,8 @@ impl RawSocket {
             }
         }
 
+        pcap::record(self.pcap.as_ref(), data, PacketDirection::Outbound);
+
         // Framework: return success for now
         Ok(data.len())
     }
@@ -0,0 +1,506 @@
+//! Scan policies: pass/fail rules evaluated against scan results
+//!
+//! A policy file lists rules such as "telnet must not be open anywhere",
+//! "only 80 and 443 may be open in the DMZ" or "TLS certificates must stay
+//! valid for 30 more days". Each rule can be scoped to address blocks.
+//! Evaluating a policy yields a [`ComplianceReport`]. That report is attached
+//! to the scan report, and the CLI exits non-zero when any rule fails.
+//!
+//! ```toml
+//! [[rules]]
+//! name = "no-telnet"
+//! check = "forbidden_services"
+//! services = ["telnet"]
+//!
+//! [[rules]]
+//! name = "dmz-web-only"
+//! description = "The DMZ only serves HTTP(S)"
+//! check = "allowed_ports"
+//! scope = ["203.0.113.0/24"]
+//! ports = [80, 443]
+//!
+//! [[rules]]
+//! name = "certificates-not-expiring"
+//! check = "tls_expiry"
+//! min_days = 30
+//! ```
+//!
+//! The same structure can be written as YAML (`.yaml` or `.yml` files).
+//!
+//! `min_confidence` (0-100), at the top or on a rule, ignores open ports
+//! whose evidence scored lower, e.g. a lone SYN-ACK that needed retries.
+//!
+//! `tls_expiry` rules need certificates collected during the scan. A rule
+//! that saw none passes with a warning rather than silently.
+
+use crate::error::{ScanError, ScanResult};
+use crate::report::junit::unexpected_open_ports;
+use crate::report::port_view::{port_view, PortEvidence};
+use crate::scanner::targets::IpNetwork;
+use crate::scanner::{CompleteScanResult, Confidence};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// A set of rules every scan is checked against
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    /// Confidence below which open ports are ignored, for rules that set none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<Confidence>,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// One named rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Address blocks the rule applies to; every host when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scope: Vec<IpNetwork>,
+    /// Ignore open ports found with less confidence than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<Confidence>,
+    #[serde(flatten)]
+    pub check: PolicyCheck,
+}
+
+/// What a rule asserts about each host in its scope
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum PolicyCheck {
+    /// None of these ports may be open
+    ForbiddenPorts { ports: Vec<u16> },
+    /// No port outside this set may be open
+    AllowedPorts { ports: Vec<u16> },
+    /// No open port may run one of these services (e.g. `telnet`)
+    ForbiddenServices { services: Vec<String> },
+    /// Every observed TLS certificate must stay valid this many more days
+    TlsExpiry { min_days: i64 },
+}
+
+impl fmt::Display for PolicyCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |ports: &[u16]| ports.iter().map(u16::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            PolicyCheck::ForbiddenPorts { ports } => write!(f, "ports {} must not be open", join(ports)),
+            PolicyCheck::AllowedPorts { ports } => write!(f, "only ports {} may be open", join(ports)),
+            PolicyCheck::ForbiddenServices { services } => {
+                write!(f, "services {} must not be exposed", services.join(", "))
+            }
+            PolicyCheck::TlsExpiry { min_days } => {
+                write!(f, "TLS certificates must be valid for at least {} days", min_days)
+            }
+        }
+    }
+}
+
+impl Policy {
+    /// Load a policy from a TOML or YAML file, chosen by extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ScanError::validation_error("policy", format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let invalid = |e: String| ScanError::validation_error("policy", format!("Invalid policy {}: {}", path.display(), e));
+
+        let policy: Policy = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            _ => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+        };
+        policy.validate().map_err(invalid)?;
+
+        info!("Loaded {} policy rules from {}", policy.rules.len(), path.display());
+        Ok(policy)
+    }
+
+    /// Check that rule names are unique and every check has something to test
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() {
+                return Err("every rule needs a name".to_string());
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(format!("duplicate rule name '{}'", rule.name));
+            }
+            let empty = match rule.check {
+                PolicyCheck::ForbiddenPorts { ref ports } => ports.is_empty(),
+                PolicyCheck::ForbiddenServices { ref services } => services.is_empty(),
+                PolicyCheck::AllowedPorts { .. } => false,
+                PolicyCheck::TlsExpiry { min_days } if min_days < 0 => {
+                    return Err(format!("rule '{}': min_days must not be negative", rule.name));
+                }
+                PolicyCheck::TlsExpiry { .. } => false,
+            };
+            if empty {
+                return Err(format!("rule '{}' lists nothing to check", rule.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the policy against a complete result set
+    ///
+    /// # Arguments
+    /// * `results` - Scan results to check
+    /// * `now` - Reference time for certificate expiry
+    pub fn evaluate(&self, results: &[CompleteScanResult], now: DateTime<Utc>) -> ComplianceReport {
+        let mut evaluator = PolicyEvaluator::new(self, now);
+        for result in results {
+            evaluator.add(result);
+        }
+        evaluator.finish()
+    }
+
+    /// Whether any rule checks TLS certificates, which the scan must then
+    /// collect
+    pub fn needs_certificates(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| matches!(rule.check, PolicyCheck::TlsExpiry { .. }))
+    }
+}
+
+impl PolicyRule {
+    /// Whether the rule applies to `target`
+    pub fn applies_to(&self, target: IpAddr) -> bool {
+        self.scope.is_empty() || self.scope.iter().any(|block| block.contains(target))
+    }
+
+    fn violations(
+        &self,
+        result: &CompleteScanResult,
+        now: DateTime<Utc>,
+        min_confidence: Option<Confidence>,
+    ) -> Vec<PolicyViolation> {
+        let violation = |port: Option<u16>, message: String| PolicyViolation {
+            target: result.target,
+            port,
+            message,
+        };
+        let open_ports = |evidence: Vec<PortEvidence>| -> Vec<PolicyViolation> {
+            evidence
+                .iter()
+                .filter(|p| p.meets_confidence(self.min_confidence.or(min_confidence)))
+                .filter_map(|p| p.open_label().map(|label| violation(Some(p.port), format!("{} is open", label))))
+                .collect()
+        };
+
+        match self.check {
+            PolicyCheck::ForbiddenPorts { ref ports } => open_ports(
+                port_view(result)
+                    .into_iter()
+                    .filter(|p| ports.contains(&p.port))
+                    .collect(),
+            ),
+            PolicyCheck::AllowedPorts { ref ports } => open_ports(unexpected_open_ports(result, ports)),
+            PolicyCheck::ForbiddenServices { ref services } => open_ports(
+                port_view(result)
+                    .into_iter()
+                    .filter(|p| {
+                        p.service
+                            .as_deref()
+                            .is_some_and(|service| services.iter().any(|s| s.eq_ignore_ascii_case(service)))
+                    })
+                    .collect(),
+            ),
+            PolicyCheck::TlsExpiry { min_days } => result
+                .tls_certificates
+                .iter()
+                .filter_map(|cert| {
+                    let not_after = cert.not_after?;
+                    let days_left = (not_after - now).num_days();
+                    if days_left >= min_days {
+                        return None;
+                    }
+                    let subject = cert.common_name.as_deref().map_or(String::new(), |cn| format!(" ({})", cn));
+                    let message = if not_after <= now {
+                        format!("certificate{} expired on {}", subject, not_after.format("%Y-%m-%d"))
+                    } else {
+                        format!(
+                            "certificate{} expires on {} ({} days left)",
+                            subject,
+                            not_after.format("%Y-%m-%d"),
+                            days_left
+                        )
+                    };
+                    Some(violation(Some(cert.port), message))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Checks results against a policy one at a time
+///
+/// Results are added as they are read, so spooled reports can be checked
+/// without holding every result in memory.
+pub struct PolicyEvaluator<'a> {
+    policy: &'a Policy,
+    now: DateTime<Utc>,
+    outcomes: Vec<RuleOutcome>,
+    /// Certificates seen in each rule's scope
+    certificates: Vec<usize>,
+}
+
+impl<'a> PolicyEvaluator<'a> {
+    /// Start evaluating `policy`, taking `now` as the time of the scan
+    pub fn new(policy: &'a Policy, now: DateTime<Utc>) -> Self {
+        let outcomes = policy
+            .rules
+            .iter()
+            .map(|rule| RuleOutcome {
+                name: rule.name.clone(),
+                description: rule.description.clone(),
+                requirement: rule.check.to_string(),
+                hosts_checked: 0,
+                violations: Vec::new(),
+                warning: None,
+            })
+            .collect();
+        Self {
+            policy,
+            now,
+            outcomes,
+            certificates: vec![0; policy.rules.len()],
+        }
+    }
+
+    /// Check one host against every rule in scope
+    pub fn add(&mut self, result: &CompleteScanResult) {
+        let rules = self.policy.rules.iter().zip(self.outcomes.iter_mut());
+        for ((rule, outcome), certificates) in rules.zip(self.certificates.iter_mut()) {
+            if rule.applies_to(result.target) {
+                outcome.hosts_checked += 1;
+                *certificates += result.tls_certificates.len();
+                outcome
+                    .violations
+                    .extend(rule.violations(result, self.now, self.policy.min_confidence));
+            }
+        }
+    }
+
+    /// Outcome of every rule
+    pub fn finish(mut self) -> ComplianceReport {
+        let rules = self.policy.rules.iter().zip(self.outcomes.iter_mut());
+        for ((rule, outcome), certificates) in rules.zip(self.certificates) {
+            if matches!(rule.check, PolicyCheck::TlsExpiry { .. }) && outcome.hosts_checked > 0 && certificates == 0 {
+                warn!("Policy rule '{}' saw no TLS certificates to check", rule.name);
+                outcome.warning = Some("no TLS certificates were observed".to_string());
+            }
+        }
+        ComplianceReport { rules: self.outcomes }
+    }
+}
+
+/// How a scan fared against a policy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub rules: Vec<RuleOutcome>,
+}
+
+impl ComplianceReport {
+    /// Whether every rule passed
+    pub fn passed(&self) -> bool {
+        self.rules.iter().all(RuleOutcome::passed)
+    }
+
+    /// Rules with at least one violation
+    pub fn failed_rules(&self) -> impl Iterator<Item = &RuleOutcome> {
+        self.rules.iter().filter(|rule| !rule.passed())
+    }
+
+    /// Violations across all rules
+    pub fn violation_count(&self) -> usize {
+        self.rules.iter().map(|rule| rule.violations.len()).sum()
+    }
+}
+
+impl fmt::Display for ComplianceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let passed = self.rules.iter().filter(|rule| rule.passed()).count();
+        write!(f, "{} of {} policy rules passed", passed, self.rules.len())?;
+        if !self.passed() {
+            write!(f, " ({} violations)", self.violation_count())?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of one rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The rule's check in words, e.g. "only ports 80, 443 may be open"
+    pub requirement: String,
+    /// Hosts in the rule's scope
+    pub hosts_checked: usize,
+    #[serde(default)]
+    pub violations: Vec<PolicyViolation>,
+    /// Why a passing rule may not have checked anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl RuleOutcome {
+    /// Whether no host violated the rule
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// One host breaking a rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub target: IpAddr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.target, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment::ObservedCertificate;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::port_frequency::TransportProtocol;
+    use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+    use chrono::TimeZone;
+    use std::net::Ipv4Addr;
+
+    const POLICY: &str = r#"
+        [[rules]]
+        name = "no-telnet"
+        check = "forbidden_services"
+        services = ["telnet"]
+
+        [[rules]]
+        name = "dmz-web-only"
+        check = "allowed_ports"
+        scope = ["203.0.113.0/24"]
+        ports = [80, 443]
+
+        [[rules]]
+        name = "certificates"
+        description = "Renew a month ahead"
+        check = "tls_expiry"
+        min_days = 30
+    "#;
+
+    fn host(ip: [u8; 4], open_ports: &[u16]) -> CompleteScanResult {
+        CompleteScanResult::with_open_tcp(IpAddr::V4(Ipv4Addr::from(ip)), HostStatus::Up, open_ports)
+    }
+
+    #[test]
+    fn test_evaluate_policy() {
+        let policy: Policy = toml::from_str(POLICY).unwrap();
+        policy.validate().unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+
+        let mut web = host([203, 0, 113, 10], &[80, 443, 8080]);
+        web.tls_certificates = vec![ObservedCertificate {
+            port: 443,
+            common_name: Some("www.example.com".to_string()),
+            not_after: Some(Utc.with_ymd_and_hms(2026, 3, 11, 0, 0, 0).unwrap()),
+        }];
+        let legacy = host([10, 0, 0, 5], &[22, 23, 8080]);
+
+        let compliance = policy.evaluate(&[web, legacy], now);
+        assert!(!compliance.passed());
+        assert_eq!(compliance.to_string(), "0 of 3 policy rules passed (3 violations)");
+
+        let telnet = &compliance.rules[0];
+        assert_eq!(telnet.hosts_checked, 2);
+        assert_eq!(telnet.violations[0].to_string(), "10.0.0.5: 23/tcp telnet is open");
+
+        // Scoped to the DMZ: the legacy host's 8080 is out of scope
+        let dmz = &compliance.rules[1];
+        assert_eq!(dmz.requirement, "only ports 80, 443 may be open");
+        assert_eq!(dmz.hosts_checked, 1);
+        assert_eq!(dmz.violations.len(), 1);
+        assert_eq!(dmz.violations[0].port, Some(8080));
+
+        let certs = &compliance.rules[2];
+        assert_eq!(
+            certs.violations[0].message,
+            "certificate (www.example.com) expires on 2026-03-11 (10 days left)"
+        );
+        assert_eq!(certs.warning, None);
+        assert!(policy.needs_certificates());
+    }
+
+    #[test]
+    fn test_tls_expiry_without_certificates_warns() {
+        let policy: Policy = toml::from_str(POLICY).unwrap();
+        let compliance = policy.evaluate(&[host([10, 0, 0, 5], &[22])], Utc::now());
+        let certs = &compliance.rules[2];
+        assert!(certs.passed());
+        assert_eq!(certs.warning.as_deref(), Some("no TLS certificates were observed"));
+        assert_eq!(compliance.rules[0].warning, None);
+    }
+
+    #[test]
+    fn test_min_confidence_ignores_weak_findings() {
+        let policy: Policy = toml::from_str(
+            "min_confidence = 80\n\
+             [[rules]]\nname = \"no-telnet\"\ncheck = \"forbidden_ports\"\nports = [23]\n\
+             [[rules]]\nname = \"no-telnet-any\"\ncheck = \"forbidden_ports\"\nports = [23]\nmin_confidence = 0\n",
+        )
+        .unwrap();
+        let mut legacy = host([10, 0, 0, 5], &[23]);
+        legacy.tcp_results[0].attempts = 3;
+        legacy.assess_confidence();
+        let confidence = legacy.port_confidence(23, TransportProtocol::Tcp, Some(PortStatus::Open));
+        assert_eq!(confidence, Some(Confidence::new(70).unwrap()));
+
+        let compliance = policy.evaluate(&[legacy], Utc::now());
+        assert!(compliance.rules[0].passed());
+        assert_eq!(compliance.rules[1].violations.len(), 1);
+    }
+
+    #[test]
+    fn test_load_yaml_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.yml");
+        std::fs::write(
+            &path,
+            "rules:\n  - name: no-telnet\n    check: forbidden_ports\n    ports: [23]\n",
+        )
+        .unwrap();
+
+        let policy = Policy::from_file(&path).unwrap();
+        assert_eq!(policy.rules[0].check, PolicyCheck::ForbiddenPorts { ports: vec![23] });
+        let compliance = policy.evaluate(&[host([10, 0, 0, 1], &[22])], Utc::now());
+        assert!(compliance.passed());
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        let duplicate = "[[rules]]\nname = \"a\"\ncheck = \"forbidden_ports\"\nports = [23]\n\
+                         [[rules]]\nname = \"a\"\ncheck = \"tls_expiry\"\nmin_days = 1\n";
+        let policy: Policy = toml::from_str(duplicate).unwrap();
+        assert!(policy.validate().unwrap_err().contains("duplicate rule name"));
+
+        let empty: Policy = toml::from_str("[[rules]]\nname = \"a\"\ncheck = \"forbidden_ports\"\nports = []\n").unwrap();
+        assert!(empty.validate().is_err());
+
+        assert!(toml::from_str::<Policy>("[[rules]]\nname = \"a\"\ncheck = \"no_such_check\"\n").is_err());
+        assert!(toml::from_str::<Policy>("[[rules]]\nname = \"a\"\ncheck = \"allowed_ports\"\nports = [1]\nscope = [\"10.0.0.0/99\"]\n").is_err());
+    }
+}
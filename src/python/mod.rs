@@ -15,6 +15,9 @@ pub mod phase3_os_fingerprint;
 #[cfg(feature = "python")]
 pub mod phase4_reporting;
 
+#[cfg(feature = "python")]
+pub mod phase5_distributed;
+
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 
@@ -26,6 +29,7 @@ fn _nrmap_rs(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<phase1_scanner::PyScanner>()?;
     m.add_class::<phase1_scanner::PyHostStatus>()?;
     m.add_class::<phase1_scanner::PyScanResult>()?;
+    m.add_class::<phase1_scanner::PyScanStream>()?;
     
     // Phase 2: Detection Engine
     m.add_class::<phase2_detection::PyDetectionEngine>()?;
@@ -39,6 +43,11 @@ fn _nrmap_rs(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<phase4_reporting::PyReportEngine>()?;
     m.add_class::<phase4_reporting::PyReportFormat>()?;
     
+    // Phase 5: Distributed Scanning and Policies
+    m.add_class::<phase5_distributed::PyDistributedScanner>()?;
+    m.add_class::<phase5_distributed::PyScanAgent>()?;
+    m.add_class::<phase5_distributed::PyPolicy>()?;
+    
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     
     Ok(())
@@ -6,15 +6,18 @@
 //! - TCP SYN scan
 //! - UDP scan
 //! - Adaptive throttling
+//! - Multi-target scans streamed per host, with cancellation
 
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use pyo3_asyncio::tokio::future_into_py;
 use std::net::IpAddr;
 use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
 
 use crate::config::AppConfig;
-use crate::scanner::{Scanner, ScanType};
+use crate::error::ScanError;
+use crate::scanner::{CompleteScanResult, Scanner, ScanType};
 use crate::scanner::host_discovery::HostStatus;
 
 /// Python wrapper for Scanner
@@ -43,7 +46,10 @@ impl PyScanner {
             AppConfig::default()
         };
 
-        let scanner = Scanner::new(app_config.scanner);
+        let scanner = Scanner::new(app_config.scanner)
+            .with_throttling(app_config.throttling.clone())
+            .with_security(&app_config.security)
+            .with_operator(app_config.general.operator.clone());
         
         Ok(PyScanner { 
             scanner: Arc::new(scanner),
@@ -69,70 +75,120 @@ impl PyScanner {
         let target_ip: IpAddr = target.parse()
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid IP: {}", e)))?;
         
-        let scan_types_vec = if let Some(types) = scan_types {
-            types.iter().map(|t| match t.as_str() {
-                "tcp" => ScanType::TcpConnect,
-                "syn" => ScanType::TcpSyn,
-                "udp" => ScanType::Udp,
-                _ => ScanType::TcpConnect,
-            }).collect()
-        } else {
-            vec![ScanType::TcpConnect]
-        };
+        let scan_types_vec = parse_scan_types(scan_types);
 
         future_into_py(py, async move {
             let result = scanner.scan(target_ip, ports, scan_types_vec).await
                 .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Scan failed: {}", e)))?;
-            
-            Python::with_gil(|py| {
-                let dict = PyDict::new(py);
-                dict.set_item("target", result.target.to_string())?;
-                dict.set_item("host_status", format!("{:?}", result.host_status))?;
-                dict.set_item("scan_duration_ms", result.scan_duration_ms)?;
-                
-                // TCP results
-                let tcp_list = PyList::empty(py);
-                for tcp_result in &result.tcp_results {
-                    let tcp_dict = PyDict::new(py);
-                    tcp_dict.set_item("port", tcp_result.port)?;
-                    let is_open = matches!(tcp_result.status, crate::scanner::tcp_connect::PortStatus::Open);
-                    tcp_dict.set_item("open", is_open)?;
-                    tcp_dict.set_item("response_time_ms", tcp_result.response_time_ms)?;
-                    tcp_list.append(tcp_dict)?;
-                }
-                dict.set_item("tcp_results", tcp_list)?;
-                
-                // SYN results
-                let syn_list = PyList::empty(py);
-                for syn_result in &result.syn_results {
-                    let syn_dict = PyDict::new(py);
-                    syn_dict.set_item("port", syn_result.port)?;
-                    // Check status via string comparison
-                    let is_open = format!("{:?}", syn_result.status).contains("Open");
-                    syn_dict.set_item("open", is_open)?;
-                    syn_dict.set_item("response_time_ms", syn_result.response_time_ms)?;
-                    syn_list.append(syn_dict)?;
-                }
-                dict.set_item("syn_results", syn_list)?;
-                
-                // UDP results
-                let udp_list = PyList::empty(py);
-                for udp_result in &result.udp_results {
-                    let udp_dict = PyDict::new(py);
-                    udp_dict.set_item("port", udp_result.port)?;
-                    // Check status via string comparison for now
-                    let is_open = format!("{:?}", udp_result.status).contains("Open");
-                    udp_dict.set_item("open", is_open)?;
-                    udp_dict.set_item("response_received", udp_result.response_data.is_some())?;
-                    udp_list.append(udp_dict)?;
-                }
-                dict.set_item("udp_results", udp_list)?;
-                
-                Ok::<Py<PyDict>, PyErr>(dict.into())
-            })
+            Python::with_gil(|py| result_dict(py, &result))
+        })
+    }
+
+    /// Scan several targets, optionally calling back as each host finishes
+    /// 
+    /// Args:
+    ///     targets (list[str]): Target IP addresses
+    ///     ports (list[int]): List of ports to scan
+    ///     scan_types (list[str], optional): Scan types ["tcp", "syn", "udp"]
+    ///     on_result (callable, optional): Called with each host's result
+    ///         dict as soon as the host is done; an exception stops the scan
+    /// 
+    /// Returns:
+    ///     list[dict]: Results of every host, in completion order
+    /// 
+    /// Example:
+    ///     >>> results = await scanner.scan_many(["10.0.0.1", "10.0.0.2"], [22, 443],
+    ///     ...                                   on_result=lambda r: print(r["target"]))
+    #[pyo3(signature = (targets, ports, scan_types=None, on_result=None))]
+    fn scan_many<'a>(
+        &self,
+        py: Python<'a>,
+        targets: Vec<String>,
+        ports: Vec<u16>,
+        scan_types: Option<Vec<String>>,
+        on_result: Option<PyObject>,
+    ) -> PyResult<&'a PyAny> {
+        let scanner = Arc::clone(&self.scanner);
+        let target_ips = parse_targets(&targets)?;
+        let scan_types_vec = parse_scan_types(scan_types);
+
+        future_into_py(py, async move {
+            let (results_tx, results) = std::sync::mpsc::channel::<Py<PyDict>>();
+            scanner
+                .scan_multiple_with(target_ips, ports, scan_types_vec, move |result| {
+                    Python::with_gil(|py| {
+                        let dict = result_dict(py, &result)?;
+                        if let Some(ref callback) = on_result {
+                            callback.call1(py, (dict.clone_ref(py),))?;
+                        }
+                        let _ = results_tx.send(dict);
+                        Ok::<(), PyErr>(())
+                    })
+                    .map_err(|e| ScanError::scanner_error(format!("on_result callback failed: {}", e)))
+                })
+                .await
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Scan failed: {}", e)))?;
+            Ok(results.try_iter().collect::<Vec<_>>())
+        })
+    }
+
+    /// Scan several targets and iterate over the host results as they finish
+    /// 
+    /// The scan starts right away in the background. Calling `cancel()` on
+    /// the stream (e.g. after breaking out of the loop) stops the scan when
+    /// the next host finishes.
+    /// 
+    /// Args:
+    ///     targets (list[str]): Target IP addresses
+    ///     ports (list[int]): List of ports to scan
+    ///     scan_types (list[str], optional): Scan types ["tcp", "syn", "udp"]
+    /// 
+    /// Returns:
+    ///     ScanStream: Async iterator of per-host result dicts
+    /// 
+    /// Example:
+    ///     >>> async for result in scanner.stream(["10.0.0.1", "10.0.0.2"], [22, 443]):
+    ///     ...     print(result["target"], result["host_status"])
+    #[pyo3(signature = (targets, ports, scan_types=None))]
+    fn stream(&self, targets: Vec<String>, ports: Vec<u16>, scan_types: Option<Vec<String>>) -> PyResult<PyScanStream> {
+        let scanner = Arc::clone(&self.scanner);
+        let target_ips = parse_targets(&targets)?;
+        let scan_types_vec = parse_scan_types(scan_types);
+
+        let (results_tx, results) = mpsc::unbounded_channel();
+        pyo3_asyncio::tokio::get_runtime().spawn(async move {
+            let errors_tx = results_tx.clone();
+            let outcome = scanner
+                .scan_multiple_with(target_ips, ports, scan_types_vec, move |result| {
+                    results_tx
+                        .send(Ok(result))
+                        .map_err(|_| ScanError::scanner_error("Result stream closed"))
+                })
+                .await;
+            // A closed stream is how the consumer stops the scan
+            if let Err(e) = outcome {
+                let _ = errors_tx.send(Err(e));
+            }
+        });
+
+        Ok(PyScanStream {
+            results: Arc::new(Mutex::new(results)),
         })
     }
 
+    /// Cancel every scan running on this scanner
+    /// 
+    /// Scans in flight return what they gathered so far. The scanner stays
+    /// cancelled; create a new one for further scans.
+    fn cancel(&self) {
+        self.scanner.cancel();
+    }
+
+    /// Whether `cancel()` was called
+    fn is_cancelled(&self) -> bool {
+        self.scanner.is_cancelled()
+    }
+
     /// Quick TCP scan (convenience method)
     /// 
     /// Args:
@@ -225,6 +281,113 @@ impl PyScanner {
     }
 }
 
+/// Async iterator over the host results of [`PyScanner::stream`]
+#[pyclass]
+pub struct PyScanStream {
+    results: Arc<Mutex<mpsc::UnboundedReceiver<crate::error::ScanResult<CompleteScanResult>>>>,
+}
+
+#[pymethods]
+impl PyScanStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'a>(&self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        let results = Arc::clone(&self.results);
+        let next = future_into_py(py, async move {
+            match results.lock().await.recv().await {
+                Some(Ok(result)) => Python::with_gil(|py| result_dict(py, &result)),
+                Some(Err(e)) => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Scan failed: {}", e))),
+                None => Err(PyErr::new::<pyo3::exceptions::PyStopAsyncIteration, _>(())),
+            }
+        })?;
+        Ok(Some(next))
+    }
+
+    /// Stop the scan; results already received can still be read
+    fn cancel<'a>(&self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let results = Arc::clone(&self.results);
+        future_into_py(py, async move {
+            results.lock().await.close();
+            Ok(())
+        })
+    }
+
+    /// Same as `cancel()`, for `contextlib.aclosing`
+    fn aclose<'a>(&self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        self.cancel(py)
+    }
+}
+
+/// Scan types from their Python names; unknown names fall back to TCP connect
+pub(crate) fn parse_scan_types(scan_types: Option<Vec<String>>) -> Vec<ScanType> {
+    match scan_types {
+        Some(types) => types.iter().map(|t| match t.as_str() {
+            "tcp" => ScanType::TcpConnect,
+            "syn" => ScanType::TcpSyn,
+            "udp" => ScanType::Udp,
+            _ => ScanType::TcpConnect,
+        }).collect(),
+        None => vec![ScanType::TcpConnect],
+    }
+}
+
+fn parse_targets(targets: &[String]) -> PyResult<Vec<IpAddr>> {
+    targets
+        .iter()
+        .map(|t| t.parse().map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid IP {}: {}", t, e))))
+        .collect()
+}
+
+/// Host result as the dict returned by `scan()`
+fn result_dict(py: Python<'_>, result: &CompleteScanResult) -> PyResult<Py<PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("target", result.target.to_string())?;
+    dict.set_item("host_status", format!("{:?}", result.host_status))?;
+    dict.set_item("scan_duration_ms", result.scan_duration_ms)?;
+
+    // TCP results
+    let tcp_list = PyList::empty(py);
+    for tcp_result in &result.tcp_results {
+        let tcp_dict = PyDict::new(py);
+        tcp_dict.set_item("port", tcp_result.port)?;
+        let is_open = matches!(tcp_result.status, crate::scanner::tcp_connect::PortStatus::Open);
+        tcp_dict.set_item("open", is_open)?;
+        tcp_dict.set_item("response_time_ms", tcp_result.response_time_ms)?;
+        tcp_list.append(tcp_dict)?;
+    }
+    dict.set_item("tcp_results", tcp_list)?;
+
+    // SYN results
+    let syn_list = PyList::empty(py);
+    for syn_result in &result.syn_results {
+        let syn_dict = PyDict::new(py);
+        syn_dict.set_item("port", syn_result.port)?;
+        // Check status via string comparison
+        let is_open = format!("{:?}", syn_result.status).contains("Open");
+        syn_dict.set_item("open", is_open)?;
+        syn_dict.set_item("response_time_ms", syn_result.response_time_ms)?;
+        syn_list.append(syn_dict)?;
+    }
+    dict.set_item("syn_results", syn_list)?;
+
+    // UDP results
+    let udp_list = PyList::empty(py);
+    for udp_result in &result.udp_results {
+        let udp_dict = PyDict::new(py);
+        udp_dict.set_item("port", udp_result.port)?;
+        // Check status via string comparison for now
+        let is_open = format!("{:?}", udp_result.status).contains("Open");
+        udp_dict.set_item("open", is_open)?;
+        udp_dict.set_item("response_received", udp_result.response_data.is_some())?;
+        udp_list.append(udp_dict)?;
+    }
+    dict.set_item("udp_results", udp_list)?;
+
+    Ok(dict.into())
+}
+
 /// Python wrapper for HostStatus
 #[pyclass]
 #[derive(Clone)]
@@ -51,11 +51,16 @@ impl PyReportEngine {
         let _output_format = match format.as_str() {
             "json" => ReportFormat::Json,
             "json_pretty" => ReportFormat::JsonPretty,
+            "jsonl" => ReportFormat::Jsonl,
+            "list" => ReportFormat::List,
             "yaml" => ReportFormat::Yaml,
             "html" => ReportFormat::Html,
             "table" => ReportFormat::Table,
+            "markdown" => ReportFormat::Markdown,
+            "sarif" => ReportFormat::Sarif,
+            "junit" => ReportFormat::Junit,
             _ => return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                format!("Invalid format: {}. Use: json, yaml, html, or table", format)
+                format!("Invalid format: {}. Use: json, jsonl, list, yaml, html, markdown, sarif, junit, or table", format)
             )),
         };
 
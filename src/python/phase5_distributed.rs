@@ -0,0 +1,510 @@
+//! Phase 5: Distributed Scanning and Policy Python Bindings
+//!
+//! This module provides Python bindings for:
+//! - Distributed job submission, status polling and aggregated results
+//! - In-process scan agents that execute jobs
+//! - Policy evaluation of scan results
+
+use pyo3::prelude::*;
+use pyo3_asyncio::tokio::future_into_py;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::config::{AppConfig, ConfigLayers, ConfigWatcher};
+use crate::distributed::{
+    AgentConfig, DistributedConfig, DistributedScanner, JobConstraints, JobOptions, JobPriority, PreemptionPolicy,
+    ScanAgent,
+};
+use crate::error::ScanError;
+use crate::policy::Policy;
+use crate::scanner::CompleteScanResult;
+
+use super::phase1_scanner::parse_scan_types;
+
+/// Python wrapper for DistributedScanner
+#[pyclass]
+pub struct PyDistributedScanner {
+    inner: Arc<Mutex<DistributedScanner>>,
+    /// In-process agents by ID, to run the jobs assigned to them
+    agents: Arc<Mutex<HashMap<String, Arc<Mutex<ScanAgent>>>>>,
+}
+
+#[pymethods]
+impl PyDistributedScanner {
+    /// Create a distributed scan coordinator
+    ///
+    /// Settings not given are read from the `[distributed]` section of the
+    /// config file, if one is given; its `security.blackouts` windows hold
+    /// jobs until they close.
+    ///
+    /// Args:
+    ///     max_agents (int, optional): Most agents that can register
+    ///     job_timeout_seconds (int, optional): Job timeout
+    ///     result_retention_hours (int, optional): How long results are kept
+    ///     preemption (str, optional): "queue" (urgent jobs only jump the
+    ///         queue) or "preempt" (they also take agents from lower-priority
+    ///         jobs that have not started)
+    ///     config_path (str, optional): Path to config.toml
+    ///
+    /// Example:
+    ///     >>> distributed = DistributedScanner(max_agents=4, preemption="preempt")
+    #[new]
+    #[pyo3(signature = (max_agents=None, job_timeout_seconds=None, result_retention_hours=None, preemption=None, config_path=None))]
+    fn new(
+        max_agents: Option<usize>,
+        job_timeout_seconds: Option<u64>,
+        result_retention_hours: Option<u64>,
+        preemption: Option<String>,
+        config_path: Option<String>,
+    ) -> PyResult<Self> {
+        let app_config = match config_path {
+            Some(path) => AppConfig::from_file(&path)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Config error: {}", e)))?,
+            None => AppConfig::default(),
+        };
+        let defaults = app_config.distributed;
+        let preemption = match preemption.as_deref().map(str::trim) {
+            Some("queue") => PreemptionPolicy::Queue,
+            Some("preempt") => PreemptionPolicy::Preempt,
+            Some(other) => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown preemption '{}' (expected queue or preempt)",
+                    other
+                )))
+            }
+            None => defaults.preemption,
+        };
+        let config = DistributedConfig {
+            enable_distributed: true,
+            max_agents: max_agents.unwrap_or(defaults.max_agents),
+            job_timeout_seconds: job_timeout_seconds.unwrap_or(defaults.job_timeout_seconds),
+            result_retention_hours: result_retention_hours.unwrap_or(defaults.result_retention_hours),
+            preemption,
+            ..defaults
+        };
+        let blackouts = crate::scanner::BlackoutCalendar::new(app_config.security.blackouts.clone());
+        let inner = DistributedScanner::new(config)
+            .map_err(runtime_error)?
+            .with_blackouts(blackouts);
+        Ok(PyDistributedScanner {
+            inner: Arc::new(Mutex::new(inner)),
+            agents: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Register an agent; jobs submitted afterwards are assigned to free agents
+    /// that meet their constraints. An in-process agent also advertises its
+    /// capabilities, region, zone and rate cap
+    ///
+    /// Args:
+    ///     agent (ScanAgent | str): In-process agent, or the ID of a remote one
+    ///     address (str, optional): Network address of a remote agent
+    ///
+    /// Example:
+    ///     >>> await distributed.register_agent(ScanAgent("agent-1"))
+    #[pyo3(signature = (agent, address=None))]
+    fn register_agent<'a>(&self, py: Python<'a>, agent: &PyAny, address: Option<String>) -> PyResult<&'a PyAny> {
+        let (agent_id, local) = match agent.extract::<PyRef<'_, PyScanAgent>>() {
+            Ok(agent) => (agent.agent_id.clone(), Some(Arc::clone(&agent.inner))),
+            Err(_) => (agent.extract::<String>()?, None),
+        };
+        let address = address.unwrap_or_else(|| "in-process".to_string());
+        let inner = Arc::clone(&self.inner);
+        let agents = Arc::clone(&self.agents);
+        future_into_py(py, async move {
+            let mut inner = inner.lock().await;
+            inner.register_agent(agent_id.clone(), address).await.map_err(runtime_error)?;
+            if let Some(local) = local {
+                let (capabilities, placement) = {
+                    let mut agent = local.lock().await;
+                    agent.watch_config();
+                    (agent.capabilities(), agent.placement())
+                };
+                inner
+                    .advertise_agent(&agent_id, capabilities, placement)
+                    .await
+                    .map_err(runtime_error)?;
+                // Results the agent spooled in an earlier run
+                inner.collect_spooled(&*local.lock().await).await.map_err(runtime_error)?;
+                agents.lock().await.insert(agent_id, local);
+            }
+            Ok(())
+        })
+    }
+
+    /// Submit a scan job
+    ///
+    /// Args:
+    ///     targets (list[str]): Target IP addresses
+    ///     ports (list[int]): Ports to scan on each target
+    ///     constraints (str, optional): What the agent must offer, e.g.
+    ///         "zone=dmz,raw_sockets,ipv6,scan_type=syn,region=eu-west,min_rate=5000"
+    ///     priority (str, optional): "low", "normal" (default), "high" or "urgent"
+    ///     submitter (str, optional): Team or user, to share agents fairly
+    ///     api_key (str, optional): Required once `distributed.api_keys` are
+    ///         configured; the job runs for the key's tenant, which replaces
+    ///         `submitter`
+    ///
+    /// Returns:
+    ///     str: Job ID
+    ///
+    /// Example:
+    ///     >>> job_id = await distributed.submit_job(["10.0.0.1", "10.0.0.2"], [22, 443])
+    ///     >>> job_id = await distributed.submit_job(["203.0.113.5"], [443], constraints="zone=dmz")
+    ///     >>> job_id = await distributed.submit_job(["10.0.9.7"], [22], priority="urgent", submitter="ir")
+    ///     >>> job_id = await distributed.submit_job(["10.1.0.7"], [22], api_key=os.environ["NRMAP_API_KEY"])
+    #[pyo3(signature = (targets, ports, constraints=None, priority=None, submitter=None, api_key=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn submit_job<'a>(
+        &self,
+        py: Python<'a>,
+        targets: Vec<String>,
+        ports: Vec<u16>,
+        constraints: Option<String>,
+        priority: Option<String>,
+        submitter: Option<String>,
+        api_key: Option<String>,
+    ) -> PyResult<&'a PyAny> {
+        let targets = targets
+            .iter()
+            .map(|t| t.parse().map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid IP {}: {}", t, e))))
+            .collect::<PyResult<Vec<_>>>()?;
+        let constraints: JobConstraints = match constraints {
+            Some(constraints) => constraints
+                .parse()
+                .map_err(|e: ScanError| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?,
+            None => JobConstraints::default(),
+        };
+        let priority: JobPriority = match priority {
+            Some(priority) => priority
+                .parse()
+                .map_err(|e: ScanError| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?,
+            None => JobPriority::default(),
+        };
+        let options = JobOptions {
+            constraints,
+            priority,
+            submitter,
+        };
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let mut inner = inner.lock().await;
+            let submitted = match api_key {
+                Some(api_key) => inner.submit_job_as(&api_key, targets, ports, options).await,
+                None => inner.submit_job_with(targets, ports, options).await,
+            };
+            submitted.map_err(runtime_error)
+        })
+    }
+
+    /// Job, agent and queue statistics as a dict, including pending jobs
+    /// by priority and how many jobs were preempted
+    fn stats<'a>(&self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let stats = inner.lock().await.get_stats().await;
+            Python::with_gil(|py| to_python(py, &stats))
+        })
+    }
+
+    /// Status of a job: "Pending", "Assigned", "Running", "Completed",
+    /// "Failed" or "Timeout"; None for an unknown job
+    fn job_status<'a>(&self, py: Python<'a>, job_id: String) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let status = inner.lock().await.get_job_status(&job_id).await.map_err(runtime_error)?;
+            Ok(status.map(|status| status.to_string()))
+        })
+    }
+
+    /// Job details (targets, ports, status, assigned agent, timestamps) as a
+    /// dict, or None for an unknown job
+    fn get_job<'a>(&self, py: Python<'a>, job_id: String) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let job = inner.lock().await.get_job(&job_id).await.map_err(runtime_error)?;
+            Python::with_gil(|py| to_python(py, &job))
+        })
+    }
+
+    /// All submitted jobs as dicts
+    fn list_jobs<'a>(&self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let jobs = inner.lock().await.list_jobs().await.map_err(runtime_error)?;
+            Python::with_gil(|py| to_python(py, &jobs))
+        })
+    }
+
+    /// Execute a job on the in-process agent it is assigned to and
+    /// aggregate its results
+    ///
+    /// Jobs assigned to different agents run concurrently.
+    ///
+    /// Args:
+    ///     job_id (str): Job to run
+    ///     scan_types (list[str], optional): Scan types ["tcp", "syn", "udp"]
+    ///
+    /// Example:
+    ///     >>> await asyncio.gather(*(distributed.run_job(job) for job in jobs))
+    ///     >>> results = await distributed.get_results(jobs[0])
+    #[pyo3(signature = (job_id, scan_types=None))]
+    fn run_job<'a>(&self, py: Python<'a>, job_id: String, scan_types: Option<Vec<String>>) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        let agents = Arc::clone(&self.agents);
+        let scan_types = parse_scan_types(scan_types);
+        future_into_py(py, async move {
+            let job = inner.lock().await.get_job(&job_id).await.map_err(runtime_error)?;
+            let job = job.ok_or_else(|| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Unknown job {}", job_id)))?;
+            let agent_id = job.assigned_agent.ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Job {} is not assigned to an agent", job_id))
+            })?;
+            let agent = agents.lock().await.get(&agent_id).cloned().ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("Agent {} does not run in this process", agent_id))
+            })?;
+
+            // Only the agent is held while scanning, so other jobs proceed
+            let job = inner.lock().await.start_job(&job_id).await.map_err(runtime_error)?;
+            let outcome = agent
+                .lock()
+                .await
+                .execute_chunk(job.job_id, job.targets, job.ports, scan_types)
+                .await;
+            let mut inner = inner.lock().await;
+            match outcome {
+                Ok(chunk) => {
+                    let chunk_id = chunk.chunk_id.clone();
+                    let agent = agent.lock().await;
+                    match inner.complete_chunk(chunk).await {
+                        Ok(_) => agent.acknowledge_chunk(&chunk_id).map_err(runtime_error),
+                        // A spooled chunk is retried with backoff
+                        Err(e) => match inner.collect_spooled(&agent).await.map_err(runtime_error)? {
+                            0 => Err(runtime_error(e)),
+                            _ => Ok(()),
+                        },
+                    }
+                }
+                Err(e) => {
+                    inner.fail_job(&job_id, &e.to_string()).await.map_err(runtime_error)?;
+                    Err(runtime_error(e))
+                }
+            }
+        })
+    }
+
+    /// Record results an agent produced elsewhere and complete the job
+    ///
+    /// Args:
+    ///     job_id (str): Job the results belong to
+    ///     agent_id (str): Agent that scanned
+    ///     results (str | list[dict]): Host results as nrmap writes them in
+    ///         JSON reports, or a whole JSON report
+    fn complete_job<'a>(&self, py: Python<'a>, job_id: String, agent_id: String, results: &PyAny) -> PyResult<&'a PyAny> {
+        let results = results_from_python(py, results)?;
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            inner
+                .lock()
+                .await
+                .complete_job(&job_id, &agent_id, results)
+                .await
+                .map_err(runtime_error)
+        })
+    }
+
+    /// Mark a job failed, freeing its agent
+    fn fail_job<'a>(&self, py: Python<'a>, job_id: String, reason: String) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            inner.lock().await.fail_job(&job_id, &reason).await.map_err(runtime_error)
+        })
+    }
+
+    /// Aggregated results of a job as a dict (host results, totals, agent
+    /// count), or None before any agent reported
+    fn get_results<'a>(&self, py: Python<'a>, job_id: String) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        future_into_py(py, async move {
+            let results = inner.lock().await.get_results(&job_id).await.map_err(runtime_error)?;
+            Python::with_gil(|py| to_python(py, &results))
+        })
+    }
+
+    /// Check a job's aggregated results against a policy
+    ///
+    /// Returns:
+    ///     dict: Compliance report with the outcome of every rule, or None
+    ///     before any agent reported
+    ///
+    /// Example:
+    ///     >>> report = await distributed.check_policy(job_id, Policy("policy.toml"))
+    ///     >>> print(report["passed"])
+    fn check_policy<'a>(&self, py: Python<'a>, job_id: String, policy: PyRef<'_, PyPolicy>) -> PyResult<&'a PyAny> {
+        let inner = Arc::clone(&self.inner);
+        let policy = policy.policy.clone();
+        future_into_py(py, async move {
+            let results = inner.lock().await.get_results(&job_id).await.map_err(runtime_error)?;
+            Python::with_gil(|py| match results {
+                Some(results) => compliance_dict(py, &policy, &results.results),
+                None => Ok(py.None()),
+            })
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        "DistributedScanner()".to_string()
+    }
+}
+
+/// Python wrapper for ScanAgent
+#[pyclass]
+pub struct PyScanAgent {
+    agent_id: String,
+    inner: Arc<Mutex<ScanAgent>>,
+}
+
+#[pymethods]
+impl PyScanAgent {
+    /// Create an in-process scan agent
+    ///
+    /// Args:
+    ///     agent_id (str, optional): Agent ID (generated when omitted)
+    ///     config_path (str, optional): Path to config.toml for its scanner;
+    ///         edits to its hot-reloadable settings apply from the next job
+    ///     region (str, optional): Geographic region, for job constraints
+    ///     zone (str, optional): Network zone such as "dmz", for job constraints
+    ///     spool_dir (str, optional): Keep results on disk until delivered
+    #[new]
+    #[pyo3(signature = (agent_id=None, config_path=None, region=None, zone=None, spool_dir=None))]
+    fn new(
+        agent_id: Option<String>,
+        config_path: Option<String>,
+        region: Option<String>,
+        zone: Option<String>,
+        spool_dir: Option<String>,
+    ) -> PyResult<Self> {
+        let app_config = match config_path {
+            Some(ref path) => AppConfig::from_file(path)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Config error: {}", e)))?,
+            None => AppConfig::default(),
+        };
+        // Edits to the config file are taken up while the agent runs
+        let watcher = config_path
+            .map(|path| ConfigWatcher::new(ConfigLayers::new().with_file(path, true), app_config.clone()));
+        let defaults = AgentConfig::default();
+        let config = AgentConfig {
+            agent_id: agent_id.unwrap_or(defaults.agent_id.clone()),
+            region,
+            zone,
+            spool_dir: spool_dir.map(Into::into),
+            ..defaults
+        };
+        let agent_id = config.agent_id.clone();
+        let scope = crate::scanner::AuthorizedScope::new(app_config.security.scope.clone());
+        let blackouts = crate::scanner::BlackoutCalendar::new(app_config.security.blackouts.clone());
+        let mut agent = ScanAgent::new(config, app_config.scanner)
+            .map_err(runtime_error)?
+            .with_authorized_scope(scope)
+            .with_blackouts(blackouts)
+            .with_throttling(app_config.throttling.clone());
+        if let Some(watcher) = watcher {
+            agent = agent.with_config_watcher(watcher);
+        }
+        Ok(PyScanAgent {
+            agent_id,
+            inner: Arc::new(Mutex::new(agent)),
+        })
+    }
+
+    #[getter]
+    fn agent_id(&self) -> String {
+        self.agent_id.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ScanAgent({})", self.agent_id)
+    }
+}
+
+/// Python wrapper for a scan Policy
+#[pyclass]
+pub struct PyPolicy {
+    policy: Policy,
+}
+
+#[pymethods]
+impl PyPolicy {
+    /// Load a policy file (TOML, or YAML by extension)
+    ///
+    /// Example:
+    ///     >>> policy = Policy("policy.toml")
+    #[new]
+    fn new(path: String) -> PyResult<Self> {
+        let policy = Policy::from_file(&path)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        Ok(PyPolicy { policy })
+    }
+
+    /// Names of the policy's rules
+    #[getter]
+    fn rules(&self) -> Vec<String> {
+        self.policy.rules.iter().map(|rule| rule.name.clone()).collect()
+    }
+
+    /// Check results against the policy
+    ///
+    /// Args:
+    ///     results (str | list[dict]): Host results as nrmap writes them in
+    ///         JSON reports, or a whole JSON report
+    ///
+    /// Returns:
+    ///     dict: Compliance report with "passed", "violations" and the
+    ///     outcome of every rule
+    fn evaluate(&self, py: Python<'_>, results: &PyAny) -> PyResult<PyObject> {
+        let results = results_from_python(py, results)?;
+        compliance_dict(py, &self.policy, &results)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Policy(rules={})", self.policy.rules.len())
+    }
+}
+
+fn runtime_error(e: ScanError) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
+}
+
+/// Convert a serializable value into Python objects through JSON
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Serialization failed: {}", e)))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/// Host results from a JSON string or Python objects: a list of results or
+/// a report holding them under "results"
+fn results_from_python(py: Python<'_>, results: &PyAny) -> PyResult<Vec<CompleteScanResult>> {
+    let json: String = match results.extract::<String>() {
+        Ok(json) => json,
+        Err(_) => py.import("json")?.call_method1("dumps", (results,))?.extract()?,
+    };
+    let mut value: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid results: {}", e)))?;
+    if let Some(results) = value.get_mut("results") {
+        value = results.take();
+    }
+    serde_json::from_value(value)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid results: {}", e)))
+}
+
+/// Compliance report as a dict, with the overall verdict added
+fn compliance_dict(py: Python<'_>, policy: &Policy, results: &[CompleteScanResult]) -> PyResult<PyObject> {
+    let report = policy.evaluate(results, chrono::Utc::now());
+    let dict = to_python(py, &report)?;
+    let dict = dict.as_ref(py);
+    dict.set_item("passed", report.passed())?;
+    dict.set_item("violations", report.violation_count())?;
+    Ok(dict.into())
+}
@@ -0,0 +1,327 @@
+//! Differences between two scans of the same targets
+//!
+//! [`ScanDiff::between`] compares a previous and a current result set: hosts
+//! that came up or went down, ports that opened or closed, and services that
+//! changed on a port that stayed open. Recurring scans use it to notify only
+//! when something changed, optionally ignoring port changes the current scan
+//! is not confident of.
+
+use crate::report::history::is_responsive;
+use crate::report::port_view::port_view;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::{CompleteScanResult, Confidence};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+
+/// One difference between two scans
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum AssetChange {
+    /// A host answered that did not before
+    HostUp { target: IpAddr },
+    /// A host that answered before no longer does
+    HostDown { target: IpAddr },
+    PortOpened {
+        target: IpAddr,
+        port: u16,
+        protocol: TransportProtocol,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        service: Option<String>,
+    },
+    PortClosed {
+        target: IpAddr,
+        port: u16,
+        protocol: TransportProtocol,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        service: Option<String>,
+    },
+    /// A port stayed open but now runs something else
+    ServiceChanged {
+        target: IpAddr,
+        port: u16,
+        protocol: TransportProtocol,
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+impl AssetChange {
+    /// Host the change is about
+    pub fn target(&self) -> IpAddr {
+        match *self {
+            AssetChange::HostUp { target }
+            | AssetChange::HostDown { target }
+            | AssetChange::PortOpened { target, .. }
+            | AssetChange::PortClosed { target, .. }
+            | AssetChange::ServiceChanged { target, .. } => target,
+        }
+    }
+}
+
+impl fmt::Display for AssetChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let service = |service: &Option<String>| service.as_deref().map_or(String::new(), |s| format!(" {}", s));
+        match self {
+            AssetChange::HostUp { target } => write!(f, "{} is up", target),
+            AssetChange::HostDown { target } => write!(f, "{} is down", target),
+            AssetChange::PortOpened { target, port, protocol, service: s } => {
+                write!(f, "{} opened {}/{}{}", target, port, protocol, service(s))
+            }
+            AssetChange::PortClosed { target, port, protocol, service: s } => {
+                write!(f, "{} closed {}/{}{}", target, port, protocol, service(s))
+            }
+            AssetChange::ServiceChanged { target, port, protocol, before, after } => write!(
+                f,
+                "{} {}/{} changed from {} to {}",
+                target,
+                port,
+                protocol,
+                before.as_deref().unwrap_or("unknown"),
+                after.as_deref().unwrap_or("unknown")
+            ),
+        }
+    }
+}
+
+/// Changes between two scans, by host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanDiff {
+    pub changes: Vec<AssetChange>,
+}
+
+/// Open ports of a host with their services
+type OpenPorts = BTreeMap<(u16, TransportProtocol), Option<String>>;
+
+impl ScanDiff {
+    /// Compare `current` results against `previous` ones
+    ///
+    /// A host that went down only reports [`AssetChange::HostDown`]; its
+    /// ports were not observed, so they are not listed as closed. Hosts
+    /// missing from either set count as down there.
+    pub fn between(previous: &[CompleteScanResult], current: &[CompleteScanResult]) -> Self {
+        AssetSnapshot::from_results(previous).diff(&AssetSnapshot::from_results(current))
+    }
+
+    /// Whether the scans found the same thing
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Drop port changes whose evidence in `current` scored below `min`,
+    /// so a port answering only now and then does not flap between runs
+    ///
+    /// An opened port or changed service counts the results that found the
+    /// port open; a closed port counts what the current scan found instead.
+    /// Changes without scored evidence are kept.
+    pub fn retain_confident(&mut self, current: &[CompleteScanResult], min: Confidence) {
+        let results: HashMap<IpAddr, &CompleteScanResult> = current.iter().map(|r| (r.target, r)).collect();
+        self.changes.retain(|change| {
+            let (port, protocol, status) = match *change {
+                AssetChange::PortOpened { port, protocol, .. } | AssetChange::ServiceChanged { port, protocol, .. } => {
+                    (port, protocol, Some(PortStatus::Open))
+                }
+                AssetChange::PortClosed { port, protocol, .. } => (port, protocol, None),
+                AssetChange::HostUp { .. } | AssetChange::HostDown { .. } => return true,
+            };
+            results
+                .get(&change.target())
+                .and_then(|result| result.port_confidence(port, protocol, status))
+                .is_none_or(|confidence| confidence >= min)
+        });
+    }
+}
+
+impl fmt::Display for ScanDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return write!(f, "no changes");
+        }
+        let changes: Vec<String> = self.changes.iter().map(AssetChange::to_string).collect();
+        write!(f, "{}", changes.join("; "))
+    }
+}
+
+/// Responsive hosts with their open ports and services
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetSnapshot {
+    hosts: BTreeMap<IpAddr, OpenPorts>,
+}
+
+impl AssetSnapshot {
+    /// What a set of results found
+    pub fn from_results(results: &[CompleteScanResult]) -> Self {
+        let hosts = results
+            .iter()
+            .filter(|result| is_responsive(result))
+            .map(|result| {
+                let mut ports = OpenPorts::new();
+                for evidence in port_view(result) {
+                    let statuses = [(TransportProtocol::Tcp, evidence.tcp()), (TransportProtocol::Udp, evidence.udp.clone())];
+                    for (protocol, status) in statuses {
+                        if status == Some(PortStatus::Open) {
+                            ports.insert((evidence.port, protocol), evidence.service.clone());
+                        }
+                    }
+                }
+                (result.target, ports)
+            })
+            .collect();
+        Self { hosts }
+    }
+
+    /// Responsive hosts
+    pub fn hosts(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.hosts.keys().copied()
+    }
+
+    /// Open ports of `target` with their services
+    pub fn open_ports(&self, target: IpAddr) -> impl Iterator<Item = (u16, TransportProtocol, Option<&str>)> + '_ {
+        self.hosts
+            .get(&target)
+            .into_iter()
+            .flatten()
+            .map(|(&(port, protocol), service)| (port, protocol, service.as_deref()))
+    }
+
+    /// Changes from this snapshot to `current`
+    pub fn diff(&self, current: &AssetSnapshot) -> ScanDiff {
+        let targets: BTreeSet<IpAddr> = self.hosts.keys().chain(current.hosts.keys()).copied().collect();
+
+        let mut changes = Vec::new();
+        for target in targets {
+            match (self.hosts.get(&target), current.hosts.get(&target)) {
+                (Some(_), None) => changes.push(AssetChange::HostDown { target }),
+                (None, Some(ports)) => {
+                    changes.push(AssetChange::HostUp { target });
+                    changes.extend(port_changes(target, &OpenPorts::new(), ports));
+                }
+                (Some(old), Some(new)) => changes.extend(port_changes(target, old, new)),
+                (None, None) => {}
+            }
+        }
+        ScanDiff { changes }
+    }
+
+    /// Update the snapshot with one change
+    pub fn apply(&mut self, change: &AssetChange) {
+        match change {
+            AssetChange::HostUp { target } => {
+                self.hosts.entry(*target).or_default();
+            }
+            AssetChange::HostDown { target } => {
+                self.hosts.remove(target);
+            }
+            AssetChange::PortOpened { target, port, protocol, service } => {
+                self.hosts.entry(*target).or_default().insert((*port, *protocol), service.clone());
+            }
+            AssetChange::ServiceChanged { target, port, protocol, after, .. } => {
+                self.hosts.entry(*target).or_default().insert((*port, *protocol), after.clone());
+            }
+            AssetChange::PortClosed { target, port, protocol, .. } => {
+                if let Some(ports) = self.hosts.get_mut(target) {
+                    ports.remove(&(*port, *protocol));
+                }
+            }
+        }
+    }
+}
+
+fn port_changes(target: IpAddr, before: &OpenPorts, after: &OpenPorts) -> Vec<AssetChange> {
+    let mut changes = Vec::new();
+    for (&(port, protocol), service) in after {
+        match before.get(&(port, protocol)) {
+            None => changes.push(AssetChange::PortOpened {
+                target,
+                port,
+                protocol,
+                service: service.clone(),
+            }),
+            Some(previous) if previous != service => changes.push(AssetChange::ServiceChanged {
+                target,
+                port,
+                protocol,
+                before: previous.clone(),
+                after: service.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (&(port, protocol), service) in before {
+        if !after.contains_key(&(port, protocol)) {
+            changes.push(AssetChange::PortClosed {
+                target,
+                port,
+                protocol,
+                service: service.clone(),
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+
+    fn host(last: u8, status: HostStatus, open: &[(u16, &str)]) -> CompleteScanResult {
+        let ports: Vec<u16> = open.iter().map(|&(port, _)| port).collect();
+        let mut result = CompleteScanResult::with_open_tcp(IpAddr::from([10, 0, 0, last]), status, &ports);
+        for (port, &(_, service)) in result.tcp_results.iter_mut().zip(open) {
+            port.service = Some(service.to_string());
+        }
+        result
+    }
+
+    #[test]
+    fn test_diff_between_scans() {
+        let previous = vec![
+            host(1, HostStatus::Up, &[(22, "ssh"), (80, "http"), (8080, "http-proxy")]),
+            host(2, HostStatus::Up, &[(443, "https")]),
+            host(3, HostStatus::Down, &[]),
+        ];
+        let current = vec![
+            host(1, HostStatus::Up, &[(22, "ssh"), (80, "nginx"), (3306, "mysql")]),
+            host(2, HostStatus::Down, &[]),
+            host(3, HostStatus::Up, &[(23, "telnet")]),
+        ];
+
+        let diff = ScanDiff::between(&previous, &current);
+        let changes: Vec<String> = diff.changes.iter().map(AssetChange::to_string).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "10.0.0.1 80/tcp changed from http to nginx",
+                "10.0.0.1 opened 3306/tcp mysql",
+                "10.0.0.1 closed 8080/tcp http-proxy",
+                "10.0.0.2 is down",
+                "10.0.0.3 is up",
+                "10.0.0.3 opened 23/tcp telnet",
+            ]
+        );
+
+        let json = serde_json::to_value(&diff.changes[1]).unwrap();
+        assert_eq!(json["change"], "port_opened");
+        assert_eq!(json["protocol"], "tcp");
+        assert!(ScanDiff::between(&current, &current).is_empty());
+
+        // Applying the changes to the old snapshot yields the new one
+        let mut snapshot = AssetSnapshot::from_results(&previous);
+        for change in &diff.changes {
+            snapshot.apply(change);
+        }
+        assert_eq!(snapshot, AssetSnapshot::from_results(&current));
+
+        // 3306 only answered on the fourth try
+        let mut current = current;
+        current[0].tcp_results[2].attempts = 4;
+        current.iter_mut().for_each(CompleteScanResult::assess_confidence);
+        let mut confident = ScanDiff::between(&previous, &current);
+        confident.retain_confident(&current, Confidence::new(80).unwrap());
+        assert_eq!(confident.changes.len(), diff.changes.len() - 1);
+        assert!(!confident.to_string().contains("3306"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Asset history across scans
+//!
+//! A small JSON store remembers every responsive host and open port any
+//! earlier scan found, with when it was first and last seen. Runs with
+//! `--only-new` filter their results against it, so the report lists only
+//! hosts and ports never seen before instead of having to be diffed against
+//! every prior scan.
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::host_discovery::HostStatus;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::CompleteScanResult;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// First and last sighting of an asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sighting {
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Sighting {
+    fn new(at: DateTime<Utc>) -> Self {
+        Self {
+            first_seen: at,
+            last_seen: at,
+        }
+    }
+}
+
+/// Everything seen of one host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostHistory {
+    #[serde(flatten)]
+    pub seen: Sighting,
+    #[serde(default)]
+    pub tcp: BTreeMap<u16, Sighting>,
+    #[serde(default)]
+    pub udp: BTreeMap<u16, Sighting>,
+}
+
+/// Hosts and open ports seen by earlier scans
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetHistory {
+    #[serde(default)]
+    pub hosts: BTreeMap<IpAddr, HostHistory>,
+}
+
+impl AssetHistory {
+    /// Load the store at `path`; a missing file is an empty history
+    pub fn load(path: impl AsRef<Path>) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(ScanError::scanner_error(format!(
+                    "Failed to read asset history {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        serde_json::from_str(&content).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to parse asset history {}: {}", path.display(), e))
+        })
+    }
+
+    /// Write the store to `path`, replacing it atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> ScanResult<()> {
+        let path = path.as_ref();
+        let io_error =
+            |e: std::io::Error| ScanError::scanner_error(format!("Failed to write asset history {}: {}", path.display(), e));
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to serialize asset history: {}", e)))?;
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, content).map_err(io_error)?;
+        std::fs::rename(&temporary, path).map_err(io_error)
+    }
+
+    /// Whether `target` was seen responsive before
+    pub fn knows_host(&self, target: IpAddr) -> bool {
+        self.hosts.contains_key(&target)
+    }
+
+    /// Whether `port` (TCP, or UDP when `udp`) was seen open on `target`
+    pub fn knows_port(&self, target: IpAddr, port: u16, udp: bool) -> bool {
+        self.hosts.get(&target).is_some_and(|host| {
+            let ports = if udp { &host.udp } else { &host.tcp };
+            ports.contains_key(&port)
+        })
+    }
+
+    /// Add the responsive hosts and open ports of `results`
+    pub fn record(&mut self, results: &[CompleteScanResult], at: DateTime<Utc>) {
+        for result in results.iter().filter(|r| is_responsive(r)) {
+            let host = self.hosts.entry(result.target).or_insert_with(|| HostHistory {
+                seen: Sighting::new(at),
+                tcp: BTreeMap::new(),
+                udp: BTreeMap::new(),
+            });
+            host.seen.last_seen = at;
+
+            let (tcp, udp) = open_ports(result);
+            for (ports, seen) in [(tcp, &mut host.tcp), (udp, &mut host.udp)] {
+                for port in ports {
+                    seen.entry(port).or_insert_with(|| Sighting::new(at)).last_seen = at;
+                }
+            }
+        }
+    }
+
+    /// Narrow `results` to assets this history has not seen
+    ///
+    /// Hosts keep only their open ports that are new; a known host without
+    /// new ports, and every unresponsive host, is dropped.
+    pub fn retain_new(&self, results: Vec<CompleteScanResult>) -> Vec<CompleteScanResult> {
+        results
+            .into_iter()
+            .filter(is_responsive)
+            .filter_map(|mut result| {
+                let target = result.target;
+                result
+                    .tcp_results
+                    .retain(|r| r.status == PortStatus::Open && !self.knows_port(target, r.port, false));
+                result
+                    .syn_results
+                    .retain(|r| r.status == PortStatus::Open && !self.knows_port(target, r.port, false));
+                result
+                    .udp_results
+                    .retain(|r| r.status == PortStatus::Open && !self.knows_port(target, r.port, true));
+
+                let has_new_ports =
+                    !result.tcp_results.is_empty() || !result.syn_results.is_empty() || !result.udp_results.is_empty();
+                (has_new_ports || !self.knows_host(target)).then_some(result)
+            })
+            .collect()
+    }
+}
+
+/// Open TCP (connect or SYN) and UDP ports of a host
+fn open_ports(result: &CompleteScanResult) -> (Vec<u16>, Vec<u16>) {
+    let tcp = result
+        .tcp_results
+        .iter()
+        .filter(|r| r.status == PortStatus::Open)
+        .map(|r| r.port)
+        .chain(result.syn_results.iter().filter(|r| r.status == PortStatus::Open).map(|r| r.port))
+        .collect();
+    let udp = result
+        .udp_results
+        .iter()
+        .filter(|r| r.status == PortStatus::Open)
+        .map(|r| r.port)
+        .collect();
+    (tcp, udp)
+}
+
+/// A host counts as an asset when it was up or had an open port
+pub(crate) fn is_responsive(result: &CompleteScanResult) -> bool {
+    if result.host_status == HostStatus::Up {
+        return true;
+    }
+    let (tcp, udp) = open_ports(result);
+    !tcp.is_empty() || !udp.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(target: &str, status: HostStatus, open: &[u16]) -> CompleteScanResult {
+        CompleteScanResult::with_open_tcp(target.parse().unwrap(), status, open)
+    }
+
+    #[test]
+    fn test_only_new_assets_remain() {
+        let mut history = AssetHistory::default();
+        history.record(
+            &[
+                host("192.0.2.1", HostStatus::Up, &[22, 80]),
+                host("192.0.2.9", HostStatus::Down, &[]),
+            ],
+            Utc::now(),
+        );
+        assert!(history.knows_host("192.0.2.1".parse().unwrap()));
+        assert!(!history.knows_host("192.0.2.9".parse().unwrap()));
+
+        let fresh = history.retain_new(vec![
+            host("192.0.2.1", HostStatus::Up, &[22, 80]),
+            host("192.0.2.1", HostStatus::Up, &[22, 443]),
+            host("192.0.2.2", HostStatus::Up, &[]),
+            host("192.0.2.3", HostStatus::Down, &[]),
+        ]);
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(fresh[0].tcp_results.iter().map(|r| r.port).collect::<Vec<_>>(), vec![443]);
+        assert_eq!(fresh[1].target, "192.0.2.2".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        assert_eq!(AssetHistory::load(&path).unwrap(), AssetHistory::default());
+
+        let first = "2026-01-01T00:00:00Z".parse().unwrap();
+        let later = "2026-02-01T00:00:00Z".parse().unwrap();
+        let mut history = AssetHistory::default();
+        history.record(&[host("192.0.2.1", HostStatus::Up, &[22])], first);
+        history.record(&[host("192.0.2.1", HostStatus::Up, &[22])], later);
+        history.save(&path).unwrap();
+
+        let loaded = AssetHistory::load(&path).unwrap();
+        assert_eq!(loaded, history);
+        let port = loaded.hosts[&"192.0.2.1".parse().unwrap()].tcp[&22];
+        assert_eq!((port.first_seen, port.last_seen), (first, later));
+    }
+}
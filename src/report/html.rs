@@ -3,6 +3,9 @@
 /// This module generates HTML format reports for scan results with styling.
 
 use crate::error::ScanResult;
+use crate::policy::ComplianceReport;
+use crate::report::port_view::{open_port_labels, port_view, PortEvidence};
+use crate::report::time::{format_duration_ms, format_duration_secs, format_timestamp};
 use crate::report::ScanReport;
 use crate::scanner::host_discovery::HostStatus;
 use crate::scanner::tcp_connect::PortStatus;
@@ -40,9 +43,34 @@ impl HtmlReportGenerator {
         
         // Results table
         html.push_str(&self.generate_results_table(&report));
+
+        // Open ports with every scan type's verdict side by side
+        if report.results.iter().any(|r| port_view(r).iter().any(PortEvidence::is_open)) {
+            html.push_str(&self.generate_ports_table(report));
+        }
+
+        // Per-phase timing, when hosts recorded it
+        if report.results.iter().any(|r| r.phase_timings.is_some()) {
+            html.push_str(&self.generate_timing_table(report));
+        }
+
+        // Why each port got its state (`--reason`)
+        if report.results.iter().any(|r| r.has_reasons()) {
+            html.push_str(&self.generate_reasons_table(report));
+        }
+
+        // Hosts skipped by exclusion rules
+        if !report.metadata.excluded_hosts.is_empty() {
+            html.push_str(&self.generate_excluded_table(report));
+        }
+
+        // Policy rules the scan was checked against
+        if let Some(ref compliance) = report.compliance {
+            html.push_str(&self.generate_compliance_table(compliance));
+        }
         
         // HTML footer
-        html.push_str(&self.generate_footer());
+        html.push_str(&self.generate_footer(report));
         
         Ok(html)
     }
@@ -196,21 +224,26 @@ impl HtmlReportGenerator {
                 </div>
                 <div class="metadata-item">
                     <div class="metadata-label">Duration</div>
-                    <div class="metadata-value">{:.2} seconds</div>
+                    <div class="metadata-value">{}</div>
                 </div>
                 <div class="metadata-item">
                     <div class="metadata-label">Targets</div>
                     <div class="metadata-value">{}</div>
                 </div>
+                <div class="metadata-item">
+                    <div class="metadata-label">Origin</div>
+                    <div class="metadata-value">{}</div>
+                </div>
             </div>
         </div>
 "#,
             report.metadata.scan_id,
             report.metadata.scanner_version,
-            report.metadata.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            report.metadata.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            report.metadata.duration_seconds,
-            report.metadata.scan_parameters.targets.len()
+            format_timestamp(&report.metadata.start_time),
+            format_timestamp(&report.metadata.end_time),
+            format_duration_secs(report.metadata.duration_seconds),
+            report.metadata.scan_parameters.targets.len(),
+            report.metadata.origin.as_ref().map_or_else(|| "-".to_string(), |o| o.to_string())
         )
     }
 
@@ -261,15 +294,15 @@ impl HtmlReportGenerator {
         <div class="statistics">
             <div class="stats-grid">
                 <div class="stat-card">
-                    <div class="stat-value">{:.2}ms</div>
+                    <div class="stat-value">{}</div>
                     <div class="stat-label">Average Scan Time</div>
                 </div>
                 <div class="stat-card">
-                    <div class="stat-value">{}ms</div>
+                    <div class="stat-value">{}</div>
                     <div class="stat-label">Fastest Scan</div>
                 </div>
                 <div class="stat-card">
-                    <div class="stat-value">{}ms</div>
+                    <div class="stat-value">{}</div>
                     <div class="stat-label">Slowest Scan</div>
                 </div>
                 <div class="stat-card">
@@ -284,15 +317,26 @@ impl HtmlReportGenerator {
                     <div class="stat-value">{}</div>
                     <div class="stat-label">Packets Received</div>
                 </div>
+                <div class="stat-card">
+                    <div class="stat-value">{}</div>
+                    <div class="stat-label">RTT avg / p95 (jitter)</div>
+                </div>
+                <div class="stat-card">
+                    <div class="stat-value">{}</div>
+                    <div class="stat-label">Probe Errors</div>
+                </div>
             </div>
         </div>
 "#,
-            report.statistics.average_scan_time_ms,
-            report.statistics.fastest_scan_ms,
-            report.statistics.slowest_scan_ms,
+            format_duration_ms(report.statistics.average_scan_time_ms.round() as u64),
+            format_duration_ms(report.statistics.fastest_scan_ms),
+            format_duration_ms(report.statistics.slowest_scan_ms),
             report.statistics.success_rate,
             report.statistics.packets_sent,
-            report.statistics.packets_received
+            report.statistics.packets_received,
+            report.statistics.latency.as_ref()
+                .map_or("N/A".to_string(), |l| format!("{:.0} / {}ms ({:.1}ms)", l.avg_ms, l.p95_ms, l.jitter_ms)),
+            report.statistics.error_summary().unwrap_or_else(|| "none".to_string())
         )
     }
 
@@ -305,7 +349,10 @@ impl HtmlReportGenerator {
                     <th>Target</th>
                     <th>Host Status</th>
                     <th>Open Ports</th>
-                    <th>Scan Time (ms)</th>
+                    <th>Location</th>
+                    <th>ASN</th>
+                    <th>Deception</th>
+                    <th>Scan Time</th>
                 </tr>
             </thead>
             <tbody>
@@ -318,10 +365,7 @@ impl HtmlReportGenerator {
                 _ => "",
             };
 
-            let open_ports: Vec<String> = result.tcp_results.iter()
-                .filter(|r| r.status == PortStatus::Open)
-                .map(|r| r.port.to_string())
-                .collect();
+            let open_ports = open_port_labels(result);
 
             let open_ports_str = if open_ports.is_empty() {
                 "None".to_string()
@@ -329,19 +373,273 @@ impl HtmlReportGenerator {
                 open_ports.join(", ")
             };
 
+            let location = result.geo.as_ref()
+                .and_then(|g| g.location())
+                .map_or_else(|| "-".to_string(), |location| escape(&location));
+            let asn = result.geo.as_ref()
+                .and_then(|g| g.asn_label())
+                .map_or_else(|| "-".to_string(), |asn| escape(&asn));
+            let target = match result.mac {
+                Some(ref mac) => format!("{}<br><small>{}</small>", result.target, escape(&mac.to_string())),
+                None => result.target.to_string(),
+            };
+            let deception = match result.deception {
+                Some(ref d) if d.is_suspected() => format!("suspected ({:.2})", d.score),
+                Some(ref d) => format!("{:.2}", d.score),
+                None => "-".to_string(),
+            };
+
             table.push_str(&format!(r#"
                 <tr>
                     <td>{}</td>
                     <td class="{}">{:?}</td>
                     <td class="port-open">{}</td>
                     <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
                 </tr>
 "#,
-                result.target,
+                target,
                 host_status_class,
                 result.host_status,
                 open_ports_str,
-                result.scan_duration_ms
+                location,
+                asn,
+                deception,
+                format_duration_ms(result.scan_duration_ms)
+            ));
+        }
+
+        table.push_str(r#"
+            </tbody>
+        </table>
+"#);
+
+        table
+    }
+
+    fn generate_ports_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(r#"
+        <h2>Open Ports by Protocol</h2>
+        <table>
+            <thead>
+                <tr>
+                    <th>Target</th>
+                    <th>Port</th>
+                    <th>Service</th>
+                    <th>Connect</th>
+                    <th>SYN</th>
+                    <th>UDP</th>
+                    <th>Confidence</th>
+                </tr>
+            </thead>
+            <tbody>
+"#);
+
+        let cell = |status: &Option<PortStatus>| match status {
+            Some(PortStatus::Open) => r#"<td class="port-open">open</td>"#.to_string(),
+            Some(status) => format!("<td>{}</td>", status),
+            None => "<td>-</td>".to_string(),
+        };
+        for result in &report.results {
+            for evidence in port_view(result).iter().filter(|p| p.is_open()) {
+                table.push_str(&format!(r#"
+                <tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    {}
+                    {}
+                    {}
+                    <td>{}</td>
+                </tr>
+"#,
+                    result.target,
+                    evidence.port,
+                    match evidence.detail {
+                        Some(ref detail) => format!("{} <small>{}</small>", evidence.service.as_deref().unwrap_or("-"), escape(detail)),
+                        None => evidence.service.as_deref().unwrap_or("-").to_string(),
+                    },
+                    cell(&evidence.connect),
+                    cell(&evidence.syn),
+                    cell(&evidence.udp),
+                    evidence.confidence.map_or("-".to_string(), |c| c.to_string())
+                ));
+            }
+        }
+
+        table.push_str(r#"
+            </tbody>
+        </table>
+"#);
+
+        table
+    }
+
+    fn generate_timing_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(r#"
+        <h2>Host Timing</h2>
+        <table>
+            <thead>
+                <tr>
+                    <th>Target</th>
+                    <th>Discovery</th>
+                    <th>TCP</th>
+                    <th>UDP</th>
+                    <th>Detection</th>
+                    <th>OS</th>
+                    <th>Slowest Phase</th>
+                </tr>
+            </thead>
+            <tbody>
+"#);
+
+        for result in &report.results {
+            let Some(timings) = result.phase_timings else {
+                continue;
+            };
+            table.push_str(&format!(r#"
+                <tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>
+"#,
+                result.target,
+                format_duration_ms(timings.discovery_ms),
+                format_duration_ms(timings.tcp_ms),
+                format_duration_ms(timings.udp_ms),
+                format_duration_ms(timings.detection_ms),
+                format_duration_ms(timings.os_ms),
+                timings.dominant().map_or("-", |(phase, _)| phase)
+            ));
+        }
+
+        table.push_str(r#"
+            </tbody>
+        </table>
+"#);
+
+        table
+    }
+
+    fn generate_reasons_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(r#"
+        <h2>Port Reasons</h2>
+        <table>
+            <thead>
+                <tr>
+                    <th>Target</th>
+                    <th>Port</th>
+                    <th>Scan</th>
+                    <th>State</th>
+                    <th>Reason</th>
+                </tr>
+            </thead>
+            <tbody>
+"#);
+
+        for result in &report.results {
+            for (port, scan_type, status, reason) in result.port_reasons() {
+                table.push_str(&format!(r#"
+                <tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>
+"#,
+                    result.target, port, scan_type, status, reason
+                ));
+            }
+        }
+
+        table.push_str(r#"
+            </tbody>
+        </table>
+"#);
+
+        table
+    }
+
+    fn generate_excluded_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(r#"
+        <h2>Excluded Hosts</h2>
+        <table>
+            <thead>
+                <tr>
+                    <th>Target</th>
+                    <th>Hostname</th>
+                    <th>Exclusion Rule</th>
+                </tr>
+            </thead>
+            <tbody>
+"#);
+
+        for excluded in &report.metadata.excluded_hosts {
+            table.push_str(&format!(r#"
+                <tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>
+"#,
+                excluded.target,
+                escape(&excluded.hostname),
+                escape(&excluded.rule)
+            ));
+        }
+
+        table.push_str(r#"
+            </tbody>
+        </table>
+"#);
+
+        table
+    }
+
+    fn generate_compliance_table(&self, compliance: &ComplianceReport) -> String {
+        let mut table = format!(r#"
+        <h2>Compliance</h2>
+        <p>{}</p>
+        <table>
+            <thead>
+                <tr>
+                    <th>Rule</th>
+                    <th>Requirement</th>
+                    <th>Result</th>
+                    <th>Hosts Checked</th>
+                    <th>Violations</th>
+                </tr>
+            </thead>
+            <tbody>
+"#,
+            compliance
+        );
+
+        for rule in &compliance.rules {
+            let violations: Vec<String> = rule.violations.iter().map(|v| escape(&v.to_string())).collect();
+            table.push_str(&format!(r#"
+                <tr>
+                    <td>{}</td>
+                    <td>{}</td>
+                    <td class="{}">{}</td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>
+"#,
+                escape(&rule.name),
+                escape(rule.description.as_deref().unwrap_or(&rule.requirement)),
+                if rule.passed() { "status-up" } else { "status-down" },
+                if rule.passed() { "Pass" } else { "Fail" },
+                rule.hosts_checked,
+                if violations.is_empty() { "-".to_string() } else { violations.join("<br>") }
             ));
         }
 
@@ -353,7 +651,7 @@ impl HtmlReportGenerator {
         table
     }
 
-    fn generate_footer(&self) -> String {
+    fn generate_footer(&self, report: &ScanReport) -> String {
         format!(r#"
         <div class="footer">
             <p>Generated by NrMAP Scanner v{}</p>
@@ -364,11 +662,19 @@ impl HtmlReportGenerator {
 </html>
 "#,
             env!("CARGO_PKG_VERSION"),
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+            format_timestamp(&report.metadata.timezone.now())
         )
     }
 }
 
+/// Escape text from policy files and certificates for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 impl Default for HtmlReportGenerator {
     fn default() -> Self {
         Self::new()
@@ -419,5 +725,47 @@ mod tests {
         assert!(html.contains("font-family"));
         assert!(html.contains("color"));
     }
+
+    #[test]
+    fn test_excluded_hosts_are_escaped() {
+        let report = ReportBuilder::new("test-excluded".to_string())
+            .with_excluded_hosts(vec![crate::enrichment::ExcludedHost {
+                target: "10.0.0.2".parse().unwrap(),
+                hostname: "<script>alert(1)</script>.lan".to_string(),
+                rule: "*.lan".to_string(),
+            }])
+            .complete()
+            .build()
+            .unwrap();
+
+        let html = HtmlReportGenerator::new().generate(&report).unwrap();
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;.lan"));
+        assert!(!html.contains("<script>alert(1)"));
+    }
+
+    #[test]
+    fn test_geo_cells_are_escaped() {
+        use crate::enrichment::geoip::GeoInfo;
+        use crate::scanner::host_discovery::HostStatus;
+        use crate::scanner::CompleteScanResult;
+
+        let mut result = CompleteScanResult::unscanned("10.0.0.3".parse().unwrap(), HostStatus::Up, std::time::Duration::ZERO);
+        result.geo = Some(GeoInfo {
+            city: Some("<b>Dallas</b>".to_string()),
+            asn: Some(7018),
+            as_org: Some("AT&T".to_string()),
+            ..GeoInfo::default()
+        });
+        let report = ReportBuilder::new("test-geo".to_string())
+            .add_results(vec![result])
+            .complete()
+            .build()
+            .unwrap();
+
+        let html = HtmlReportGenerator::new().generate(&report).unwrap();
+        assert!(html.contains("AT&amp;T"));
+        assert!(html.contains("&lt;b&gt;Dallas&lt;/b&gt;"));
+        assert!(!html.contains("AT&T"));
+    }
 }
 
@@ -0,0 +1,253 @@
+//! JUnit XML report generator
+//!
+//! Turns expectations about a scan into JUnit test cases so CI jobs show
+//! failures with readable test output. With allowed ports set on the report
+//! (`--allowed-ports 22,443`), every scanned host is a test case that fails
+//! when a port outside the set is open; without expectations each host is a
+//! passing case listing its open ports. Reports checked against a policy
+//! add one case per policy rule.
+
+use crate::error::ScanResult;
+use crate::policy::RuleOutcome;
+use crate::report::port_view::{port_view, PortEvidence};
+use crate::report::ScanReport;
+use crate::scanner::CompleteScanResult;
+use tracing::debug;
+
+/// One JUnit test case
+#[derive(Debug, Clone, PartialEq)]
+pub struct JunitCase {
+    /// Grouping shown by CI, e.g. `nrmap.allowed_ports`
+    pub classname: String,
+    pub name: String,
+    pub time_secs: f64,
+    /// Failure message; `None` for a passing case
+    pub failure: Option<String>,
+    /// Extra detail shown with the case
+    pub output: Option<String>,
+}
+
+/// Open ports of `result` that are not in `allowed`
+pub fn unexpected_open_ports(result: &CompleteScanResult, allowed: &[u16]) -> Vec<PortEvidence> {
+    port_view(result)
+        .into_iter()
+        .filter(|p| p.is_open() && !allowed.contains(&p.port))
+        .collect()
+}
+
+/// JUnit report generator
+pub struct JunitReportGenerator;
+
+impl JunitReportGenerator {
+    /// Create a new JUnit report generator
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Test cases for a report's expectations
+    pub fn cases(&self, report: &ScanReport) -> Vec<JunitCase> {
+        let mut cases: Vec<JunitCase> = report.results.iter().map(|result| self.host_case(report, result)).collect();
+        if let Some(ref compliance) = report.compliance {
+            cases.extend(compliance.rules.iter().map(policy_case));
+        }
+        cases
+    }
+
+    fn host_case(&self, report: &ScanReport, result: &CompleteScanResult) -> JunitCase {
+        let open: Vec<String> = port_view(result).iter().filter_map(PortEvidence::open_label).collect();
+        let output = (!open.is_empty()).then(|| format!("open: {}", open.join(", ")));
+        let time_secs = result.scan_duration_ms as f64 / 1000.0;
+
+        match report.metadata.allowed_ports {
+            Some(ref allowed) => {
+                let unexpected: Vec<String> = unexpected_open_ports(result, allowed)
+                    .iter()
+                    .filter_map(PortEvidence::open_label)
+                    .collect();
+                JunitCase {
+                    classname: "nrmap.allowed_ports".to_string(),
+                    name: format!("{} opens only allowed ports", result.target),
+                    time_secs,
+                    failure: (!unexpected.is_empty()).then(|| format!("unexpected open ports: {}", unexpected.join(", "))),
+                    output,
+                }
+            }
+            None => JunitCase {
+                classname: "nrmap.hosts".to_string(),
+                name: format!("{} scanned", result.target),
+                time_secs,
+                failure: None,
+                output,
+            },
+        }
+    }
+
+    /// Generate a JUnit XML report
+    ///
+    /// # Arguments
+    /// * `report` - The scan report to format
+    pub fn generate(&self, report: &ScanReport) -> ScanResult<String> {
+        debug!("Generating JUnit report");
+        Ok(self.render(report, &self.cases(report)))
+    }
+
+    /// Render test cases as one JUnit test suite for the scan
+    pub fn render(&self, report: &ScanReport, cases: &[JunitCase]) -> String {
+        let failures = cases.iter().filter(|c| c.failure.is_some()).count();
+        let suite = format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\"",
+            cases.len(),
+            failures,
+            report.metadata.duration_seconds
+        );
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!("<testsuites name=\"nrmap\" {}>\n", suite));
+        xml.push_str(&format!(
+            "  <testsuite name=\"nrmap scan {}\" {} timestamp=\"{}\">\n",
+            escape(&report.metadata.scan_id),
+            suite,
+            report.metadata.start_time.format("%Y-%m-%dT%H:%M:%S")
+        ));
+        for case in cases {
+            xml.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                escape(&case.classname),
+                escape(&case.name),
+                case.time_secs
+            ));
+            if case.failure.is_none() && case.output.is_none() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            if let Some(ref failure) = case.failure {
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    escape(failure),
+                    escape(failure)
+                ));
+            }
+            if let Some(ref output) = case.output {
+                xml.push_str(&format!("      <system-out>{}</system-out>\n", escape(output)));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+impl Default for JunitReportGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One case per policy rule, failing with its violations
+fn policy_case(rule: &RuleOutcome) -> JunitCase {
+    let violations: Vec<String> = rule.violations.iter().map(|v| v.to_string()).collect();
+    JunitCase {
+        classname: "nrmap.policy".to_string(),
+        name: rule.name.clone(),
+        time_secs: 0.0,
+        failure: (!violations.is_empty()).then(|| format!("{} violated: {}", rule.requirement, violations.join("; "))),
+        output: Some(match rule.warning {
+            Some(ref warning) => format!("{} host(s) checked, {}", rule.hosts_checked, warning),
+            None => format!("{} host(s) checked", rule.hosts_checked),
+        }),
+    }
+}
+
+/// Escape text for XML attributes and content, dropping characters XML 1.0
+/// cannot carry
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportBuilder;
+    use crate::scanner::host_discovery::HostStatus;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn host(last: u8, open_ports: &[u16]) -> CompleteScanResult {
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+        CompleteScanResult {
+            scan_duration_ms: 250,
+            ..CompleteScanResult::with_open_tcp(target, HostStatus::Up, open_ports)
+        }
+    }
+
+    #[test]
+    fn test_allowed_ports_cases() {
+        let report = ReportBuilder::new("test-junit".to_string())
+            .with_allowed_ports(vec![22, 443])
+            .add_results(vec![host(1, &[22, 443]), host(2, &[22, 23])])
+            .complete()
+            .build()
+            .unwrap();
+
+        let xml = JunitReportGenerator::new().generate(&report).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"nrmap\" tests=\"2\" failures=\"1\""));
+        assert!(xml.contains(
+            "<testcase classname=\"nrmap.allowed_ports\" name=\"10.0.0.1 opens only allowed ports\" time=\"0.250\">\n      \
+             <system-out>open: 22/tcp ssh, 443/tcp https</system-out>"
+        ));
+        assert!(xml.contains("<failure message=\"unexpected open ports: 23/tcp telnet\">"));
+    }
+
+    #[test]
+    fn test_policy_rules_become_cases() {
+        let policy: crate::policy::Policy = toml::from_str(
+            "[[rules]]\nname = \"no-telnet\"\ncheck = \"forbidden_ports\"\nports = [23]\n\
+             [[rules]]\nname = \"no-ftp\"\ncheck = \"forbidden_ports\"\nports = [21]\n",
+        )
+        .unwrap();
+        let report = ReportBuilder::new("test-policy".to_string())
+            .with_policy(policy)
+            .add_results(vec![host(1, &[22]), host(2, &[23])])
+            .complete()
+            .build()
+            .unwrap();
+
+        let cases = JunitReportGenerator::new().cases(&report);
+        assert_eq!(cases.len(), 4);
+        assert_eq!(cases[2].classname, "nrmap.policy");
+        assert_eq!(
+            cases[2].failure.as_deref(),
+            Some("ports 23 must not be open violated: 10.0.0.2: 23/tcp telnet is open")
+        );
+        assert!(cases[3].failure.is_none());
+    }
+
+    #[test]
+    fn test_cases_without_expectations_pass() {
+        let report = ReportBuilder::new("test-<junit>".to_string())
+            .add_results(vec![host(1, &[]), host(2, &[23])])
+            .complete()
+            .build()
+            .unwrap();
+
+        let cases = JunitReportGenerator::new().cases(&report);
+        assert!(cases.iter().all(|c| c.failure.is_none()));
+        assert_eq!(cases[1].name, "10.0.0.2 scanned");
+        let xml = JunitReportGenerator::new().generate(&report).unwrap();
+        assert!(xml.contains("name=\"nrmap scan test-&lt;junit&gt;\""));
+        assert!(xml.contains("name=\"10.0.0.1 scanned\" time=\"0.250\"/>"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Markdown report generator
+//!
+//! This module generates GitHub-flavored Markdown reports for scan results,
+//! for pasting into issues and wikis.
+
+use crate::error::ScanResult;
+use crate::policy::ComplianceReport;
+use crate::report::port_view::{port_view, PortEvidence};
+use crate::report::time::{format_duration_ms, format_duration_secs, format_timestamp};
+use crate::report::ScanReport;
+use crate::scanner::host_discovery::HostStatus;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::CompleteScanResult;
+use tracing::debug;
+
+/// Longest banner shown in a port table, in characters
+const MAX_BANNER_CHARS: usize = 60;
+
+/// Markdown report generator
+pub struct MarkdownReportGenerator;
+
+impl MarkdownReportGenerator {
+    /// Create a new Markdown report generator
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Generate a Markdown report
+    ///
+    /// # Arguments
+    /// * `report` - The scan report to format
+    pub fn generate(&self, report: &ScanReport) -> ScanResult<String> {
+        debug!("Generating Markdown report");
+
+        let mut markdown = String::from("# NrMAP Scan Report\n\n");
+        markdown.push_str(&self.generate_metadata(report));
+        markdown.push_str(&self.generate_summary(report));
+        if let Some(ref compliance) = report.compliance {
+            markdown.push_str(&self.generate_compliance(compliance));
+        }
+        markdown.push_str(&self.generate_hosts(report));
+        Ok(markdown)
+    }
+
+    fn generate_metadata(&self, report: &ScanReport) -> String {
+        let metadata = &report.metadata;
+        let mut rows = vec![
+            ("Scan ID", format!("`{}`", metadata.scan_id)),
+            ("Scanner version", metadata.scanner_version.clone()),
+            ("Started", format_timestamp(&metadata.start_time)),
+            ("Duration", format_duration_secs(metadata.duration_seconds)),
+            ("Scan types", escape(&metadata.scan_parameters.scan_types.join(", "))),
+        ];
+        if let Some(ref origin) = metadata.origin {
+            rows.push(("Origin", escape(&origin.to_string())));
+        }
+        if !metadata.excluded_hosts.is_empty() {
+            rows.push(("Excluded hosts", metadata.excluded_hosts.len().to_string()));
+        }
+        if metadata.cancelled {
+            rows.push(("Cancelled", "yes, results are partial".to_string()));
+        }
+
+        let mut table = String::from("| | |\n|---|---|\n");
+        for (label, value) in rows {
+            table.push_str(&format!("| **{}** | {} |\n", label, value));
+        }
+        table.push('\n');
+        table
+    }
+
+    fn generate_summary(&self, report: &ScanReport) -> String {
+        let summary = &report.summary;
+        let mut section = format!(
+            "## Summary\n\n\
+             | Targets | Up | Down | Ports scanned | Open | Closed | Filtered |\n\
+             |---:|---:|---:|---:|---:|---:|---:|\n\
+             | {} | {} | {} | {} | {} | {} | {} |\n\n",
+            summary.total_targets,
+            summary.targets_up,
+            summary.targets_down,
+            summary.total_ports_scanned,
+            summary.total_open_ports,
+            summary.total_closed_ports,
+            summary.total_filtered_ports,
+        );
+        if summary.suspected_decoys > 0 {
+            section.push_str(&format!("> **Note:** {} host(s) look like decoys or honeypots.\n\n", summary.suspected_decoys));
+        }
+        if summary.expanded_hosts > 0 {
+            section.push_str(&format!(
+                "Port expansion: {} host(s) found up or open were scanned over all 65535 ports.\n\n",
+                summary.expanded_hosts
+            ));
+        }
+        if let Some(errors) = report.statistics.error_summary() {
+            section.push_str(&format!("Probe errors: {}\n\n", errors));
+        }
+        section
+    }
+
+    fn generate_compliance(&self, compliance: &ComplianceReport) -> String {
+        let mut section = format!(
+            "## Compliance\n\n{}.\n\n| Rule | Requirement | Result | Hosts | Violations |\n|---|---|---|---:|---:|\n",
+            compliance
+        );
+        for rule in &compliance.rules {
+            section.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                escape(&rule.name),
+                escape(rule.description.as_deref().unwrap_or(&rule.requirement)),
+                if rule.passed() { "✅ pass" } else { "❌ fail" },
+                rule.hosts_checked,
+                rule.violations.len()
+            ));
+        }
+        section.push('\n');
+
+        for rule in compliance.failed_rules() {
+            section.push_str(&format!("**{}**\n\n", escape(&rule.name)));
+            for violation in &rule.violations {
+                section.push_str(&format!("- {}\n", escape(&violation.to_string())));
+            }
+            section.push('\n');
+        }
+        section
+    }
+
+    /// One section per responsive host, then a list of hosts that were down
+    fn generate_hosts(&self, report: &ScanReport) -> String {
+        let mut section = String::from("## Hosts\n\n");
+        if report.results.is_empty() {
+            section.push_str("_No scan results available._\n");
+            return section;
+        }
+
+        let (down, hosts): (Vec<_>, Vec<_>) = report
+            .results
+            .iter()
+            .partition(|r| r.host_status == HostStatus::Down);
+        for result in hosts {
+            section.push_str(&self.generate_host(result));
+        }
+
+        if !down.is_empty() {
+            section.push_str(&format!("### Down ({})\n\n", down.len()));
+            for result in down {
+                match result.down_reason {
+                    Some(ref reason) => section.push_str(&format!("- {} ({})\n", host_title(result), escape(reason))),
+                    None => section.push_str(&format!("- {}\n", host_title(result))),
+                }
+            }
+            section.push('\n');
+        }
+        section
+    }
+
+    fn generate_host(&self, result: &CompleteScanResult) -> String {
+        let status = match result.host_status {
+            HostStatus::Up => "up",
+            HostStatus::Down => "down",
+            HostStatus::Unknown => "status unknown",
+        };
+        let mut section = format!(
+            "### {} — {}\n\nScanned in {}.",
+            host_title(result),
+            status,
+            format_duration_ms(result.scan_duration_ms)
+        );
+        if let Some(ref mac) = result.mac {
+            section.push_str(&format!(" MAC: `{}`", mac.address));
+            if let Some(ref vendor) = mac.vendor {
+                section.push_str(&format!(" ({})", escape(vendor)));
+            }
+            section.push('.');
+        }
+        if let Some(ref limit) = result.icmp_rate_limit {
+            section.push_str(&format!(
+                " ICMP unreachables rate-limited to ~{:.1}/s; {} UDP ports re-probed paced.",
+                limit.per_second, limit.reprobed_ports
+            ));
+        }
+        if let Some(best) = result.os_matches.first() {
+            let version = best.os_version.as_deref().map_or(String::new(), |v| format!(" {}", v));
+            section.push_str(&format!(
+                " OS guess: **{}{}** ({:.0}% confidence).",
+                escape(&best.os_name),
+                escape(&version),
+                best.confidence_score * 100.0
+            ));
+        }
+        section.push_str("\n\n");
+
+        let open: Vec<PortEvidence> = port_view(result).into_iter().filter(PortEvidence::is_open).collect();
+        if open.is_empty() {
+            section.push_str("_No open ports._\n\n");
+            return section;
+        }
+
+        section.push_str("| Port | Protocol | Service | Confidence | Banner |\n|---:|---|---|---:|---|\n");
+        for evidence in &open {
+            let protocols: Vec<&str> = [("tcp", evidence.tcp()), ("udp", evidence.udp.clone())]
+                .into_iter()
+                .filter(|(_, status)| *status == Some(PortStatus::Open))
+                .map(|(protocol, _)| protocol)
+                .collect();
+            let banner = result
+                .tcp_results
+                .iter()
+                .find(|r| r.port == evidence.port)
+                .and_then(|r| r.banner.as_deref())
+                .or(evidence.detail.as_deref())
+                .map_or(String::new(), |banner| {
+                    format!("`{}`", truncate(banner, MAX_BANNER_CHARS).replace('`', "'"))
+                });
+            section.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                evidence.port,
+                protocols.join("+"),
+                evidence.service.as_deref().map_or("-".to_string(), escape),
+                evidence.confidence.map_or("-".to_string(), |c| c.to_string()),
+                banner
+            ));
+        }
+        section.push('\n');
+        section
+    }
+}
+
+impl Default for MarkdownReportGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Target with its hostname, e.g. "10.0.0.5 (web1.example.com)"
+fn host_title(result: &CompleteScanResult) -> String {
+    match result.hostname {
+        Some(ref hostname) => format!("{} ({})", result.target, escape(hostname)),
+        None => result.target.to_string(),
+    }
+}
+
+/// Escape text so it renders literally, on one line, inside a table cell
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// First line of `text`, cut to `max` characters, with table pipes escaped
+fn truncate(text: &str, max: usize) -> String {
+    let line: String = text
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let mut cut: String = line.chars().take(max).collect();
+    if line.chars().count() > max {
+        cut.push('…');
+    }
+    cut.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{ReportBuilder, ScanParameters};
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use crate::scanner::ProbeError;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_generate_markdown() {
+        let generator = MarkdownReportGenerator::new();
+        let params = ScanParameters {
+            targets: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))],
+            ports: vec![80, 443],
+            scan_types: vec!["TcpConnect".to_string()],
+            timeout_ms: 5000,
+            concurrent_scans: 100,
+        };
+        let report = ReportBuilder::new("test-markdown-1".to_string())
+            .with_parameters(params)
+            .complete()
+            .build()
+            .unwrap();
+
+        let markdown = generator.generate(&report).unwrap();
+        assert!(markdown.starts_with("# NrMAP Scan Report\n"));
+        assert!(markdown.contains("| **Scan ID** | `test-markdown-1` |"));
+        assert!(markdown.contains("## Summary"));
+        assert!(markdown.contains("_No scan results available._"));
+    }
+
+    #[test]
+    fn test_host_sections_list_open_ports() {
+        let up = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let down = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let mut result = CompleteScanResult::unscanned(up, HostStatus::Up, std::time::Duration::from_millis(1200));
+        result.hostname = Some("web_1.example.com".to_string());
+        result.mac = Some(crate::lan::HostMac::new("b8:27:eb:00:00:01".parse().unwrap()));
+        let mut open = TcpConnectResult::failed(up, 22, ProbeError::Timeout);
+        open.status = PortStatus::Open;
+        open.error = None;
+        open.banner = Some("SSH-2.0-OpenSSH_9.6 | `x`\r\n".to_string());
+        result.tcp_results = vec![open, TcpConnectResult::failed(up, 23, ProbeError::Refused)];
+        let mut unreachable = CompleteScanResult::unscanned(down, HostStatus::Down, std::time::Duration::ZERO);
+        unreachable.down_reason = Some("no ARP reply".to_string());
+
+        let report = ReportBuilder::new("test-hosts".to_string())
+            .add_results(vec![result, unreachable])
+            .complete()
+            .build()
+            .unwrap();
+        let markdown = MarkdownReportGenerator::new().generate(&report).unwrap();
+
+        assert!(markdown.contains("### 10.0.0.1 (web\\_1.example.com) — up\n\nScanned in 1.2s. MAC: `b8:27:eb:00:00:01` (Raspberry Pi Foundation)."));
+        assert!(markdown.contains("| 22 | tcp | ssh | - | `SSH-2.0-OpenSSH_9.6 \\| 'x'` |\n"));
+        assert!(!markdown.contains("| 23 |"));
+        assert!(markdown.contains("### Down (1)\n\n- 10.0.0.2 (no ARP reply)\n"));
+    }
+}
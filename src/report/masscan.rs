@@ -0,0 +1,124 @@
+//! Masscan list output
+//!
+//! Writes results in the line format of `masscan -oL`, so scripts and
+//! pipelines built around masscan can read nrmap scans unchanged:
+//!
+//! ```text
+//! #masscan
+//! open tcp 22 192.0.2.10 1700000000
+//! banner tcp 22 192.0.2.10 1700000000 ssh SSH-2.0-OpenSSH_9.6
+//! # end
+//! ```
+//!
+//! Like masscan, only open ports are listed. Every line carries the time
+//! the scan finished, since nrmap does not time-stamp individual probes.
+
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::CompleteScanResult;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// First line of a list file
+pub const LIST_HEADER: &str = "#masscan\n";
+
+/// Last line of a list file
+pub const LIST_FOOTER: &str = "# end\n";
+
+/// List lines of one host: its open ports, then the banners read from them
+pub fn list_lines(result: &CompleteScanResult, timestamp: i64) -> String {
+    let open_tcp: BTreeSet<u16> = result
+        .tcp_results
+        .iter()
+        .filter(|port| port.status == PortStatus::Open)
+        .map(|port| port.port)
+        .chain(
+            result
+                .syn_results
+                .iter()
+                .filter(|port| port.status == PortStatus::Open)
+                .map(|port| port.port),
+        )
+        .collect();
+    let open_udp: BTreeSet<u16> = result
+        .udp_results
+        .iter()
+        .filter(|port| port.status == PortStatus::Open)
+        .map(|port| port.port)
+        .collect();
+
+    let mut lines = String::new();
+    let ports = open_tcp.iter().map(|port| ("tcp", port)).chain(open_udp.iter().map(|port| ("udp", port)));
+    for (protocol, port) in ports {
+        let _ = writeln!(lines, "open {} {} {} {}", protocol, port, result.target, timestamp);
+    }
+    for port in &result.tcp_results {
+        let Some(ref banner) = port.banner else { continue };
+        if port.status != PortStatus::Open || banner.is_empty() {
+            continue;
+        }
+        let _ = writeln!(
+            lines,
+            "banner tcp {} {} {} {} {}",
+            port.port,
+            result.target,
+            timestamp,
+            port.service.as_deref().unwrap_or("unknown"),
+            escape_banner(banner)
+        );
+    }
+    lines
+}
+
+/// Keep a banner on one line, escaping control characters as masscan does
+fn escape_banner(banner: &str) -> String {
+    let mut escaped = String::with_capacity(banner.len());
+    for c in banner.chars() {
+        if c.is_control() || c == '\\' {
+            let mut buf = [0u8; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                let _ = write!(escaped, "\\x{:02x}", byte);
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use std::time::Duration;
+
+    #[test]
+    fn test_list_lines() {
+        let target = "192.0.2.10".parse().unwrap();
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, Duration::ZERO);
+        let tcp = |port: u16, status: PortStatus, banner: Option<&str>| TcpConnectResult {
+            target,
+            port,
+            status,
+            response_time_ms: Some(1),
+            banner: banner.map(str::to_string),
+            evasion: None,
+            attempts: 1,
+            error: None,
+            service: Some("ssh".to_string()),
+            error_class: None,
+            confidence: None,
+            reason: None,
+        };
+        result.tcp_results = vec![
+            tcp(80, PortStatus::Closed, None),
+            tcp(22, PortStatus::Open, Some("SSH-2.0-OpenSSH_9.6\r\n")),
+        ];
+
+        assert_eq!(
+            list_lines(&result, 1700000000),
+            "open tcp 22 192.0.2.10 1700000000\n\
+             banner tcp 22 192.0.2.10 1700000000 ssh SSH-2.0-OpenSSH_9.6\\x0d\\x0a\n"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Merging the reports of several scan runs
+//!
+//! Teams that scan different segments, or one team that scans in batches,
+//! end up with several reports. [`ReportEngine::merge`] unions them into one:
+//! hosts are combined by address, and a port scanned by more than one run
+//! keeps the result with the higher confidence, or the more recent one when
+//! the confidence is equal or unscored. A host the latest run found down
+//! stays down, without the ports earlier runs saw. Summary and statistics
+//! are computed afresh from the merged results.
+
+use super::{ExcludedHost, ReportBuilder, ReportEngine, ScanParameters, ScanReport};
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::host_discovery::HostStatus;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::{CompleteScanResult, Confidence, ResourceUsage};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::path::Path;
+
+impl ReportEngine {
+    /// Merge the reports of several runs into one
+    pub fn merge(reports: Vec<ScanReport>) -> ScanResult<ScanReport> {
+        let (report, results) = Self::merge_parts(reports)?;
+        report.add_results(results).complete().build()
+    }
+
+    /// Builder and results of the merged report, for callers that add
+    /// expectations such as a policy before building it
+    ///
+    /// The builder carries the earliest start and latest end time, the
+    /// union of the scan parameters and excluded hosts, and the summed
+    /// resource usage; the scanning origin only when every run shares it.
+    pub fn merge_parts(mut reports: Vec<ScanReport>) -> ScanResult<(ReportBuilder, Vec<CompleteScanResult>)> {
+        if reports.is_empty() {
+            return Err(ScanError::validation_error("reports", "No reports to merge"));
+        }
+        // Oldest first, so later runs take over ports of equal confidence
+        reports.sort_by_key(|report| report.metadata.end_time);
+
+        let mut results: Vec<CompleteScanResult> = Vec::new();
+        let mut index: HashMap<IpAddr, usize> = HashMap::new();
+        let mut excluded: Vec<ExcludedHost> = Vec::new();
+        let mut targets: BTreeSet<IpAddr> = BTreeSet::new();
+        let mut ports: BTreeSet<u16> = BTreeSet::new();
+        let mut parameters = ScanParameters {
+            targets: Vec::new(),
+            ports: Vec::new(),
+            scan_types: Vec::new(),
+            timeout_ms: 0,
+            concurrent_scans: 0,
+        };
+        let mut resources: Option<ResourceUsage> = None;
+        let start = reports.iter().map(|r| r.metadata.start_time).min().expect("reports is not empty");
+        let end = reports.iter().map(|r| r.metadata.end_time).max().expect("reports is not empty");
+        let newest = &reports[reports.len() - 1].metadata;
+        let mut report = ReportBuilder::new(uuid::Uuid::new_v4().to_string())
+            .with_timezone(newest.timezone)
+            .with_times(start.to_utc(), end.to_utc())
+            .with_cancelled(reports.iter().any(|r| r.metadata.cancelled))
+            .with_new_assets_only(reports.iter().all(|r| r.metadata.new_assets_only));
+        if let Some(ref ports) = newest.allowed_ports {
+            report = report.with_allowed_ports(ports.clone());
+        }
+        if let Some(ref origin) = newest.origin {
+            if reports.iter().all(|r| r.metadata.origin.as_ref() == Some(origin)) {
+                report = report.with_origin(origin.clone());
+            }
+        }
+
+        for scan in reports {
+            let metadata = scan.metadata;
+            targets.extend(metadata.scan_parameters.targets);
+            ports.extend(metadata.scan_parameters.ports);
+            union(&mut parameters.scan_types, metadata.scan_parameters.scan_types);
+            parameters.timeout_ms = parameters.timeout_ms.max(metadata.scan_parameters.timeout_ms);
+            parameters.concurrent_scans = parameters.concurrent_scans.max(metadata.scan_parameters.concurrent_scans);
+            union(&mut excluded, metadata.excluded_hosts);
+            if let Some(usage) = scan.statistics.resources {
+                resources.get_or_insert_with(Default::default).add(&usage);
+            }
+
+            for result in scan.results {
+                match index.get(&result.target) {
+                    Some(&i) => {
+                        let older = std::mem::replace(&mut results[i], result);
+                        merge_host(&mut results[i], older);
+                    }
+                    None => {
+                        index.insert(result.target, results.len());
+                        results.push(result);
+                    }
+                }
+            }
+        }
+        parameters.targets = targets.into_iter().collect();
+        parameters.ports = ports.into_iter().collect();
+        // A host excluded by one run but scanned by another has results
+        excluded.retain(|host| !index.contains_key(&host.target));
+
+        let report = report
+            .with_parameters(parameters)
+            .with_excluded_hosts(excluded)
+            .with_resources(resources);
+        Ok((report, results))
+    }
+}
+
+/// Load a JSON or YAML (by extension) report written by `--output`
+pub fn load_report(path: &Path) -> ScanResult<ScanReport> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ScanError::scanner_error(format!("Failed to read report {}: {}", path.display(), e)))?;
+    let yaml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let report = if yaml {
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    };
+    report.map_err(|e| ScanError::scanner_error(format!("Invalid report {}: {}", path.display(), e)))
+}
+
+/// Fold an older run's result for the same host into `merged`, the newer one
+///
+/// The scan duration stays the newer run's. When the newer run found the
+/// host down, the older run's ports and certificates are stale and dropped.
+fn merge_host(merged: &mut CompleteScanResult, older: CompleteScanResult) {
+    merged.hostname = merged.hostname.take().or(older.hostname);
+    merged.geo = merged.geo.take().or(older.geo);
+    merged.mac = merged.mac.take().or(older.mac);
+    if merged.host_status == HostStatus::Down {
+        return;
+    }
+
+    merge_ports(&mut merged.tcp_results, older.tcp_results, |r| (r.port, r.confidence));
+    merge_ports(&mut merged.syn_results, older.syn_results, |r| (r.port, r.confidence));
+    merge_ports(&mut merged.udp_results, older.udp_results, |r| (r.port, r.confidence));
+    if merged.os_matches.is_empty() {
+        merged.os_matches = older.os_matches;
+    }
+    union(&mut merged.discovered_hostnames, older.discovered_hostnames);
+    union(&mut merged.tls_certificates, older.tls_certificates);
+
+    let open = |status: &PortStatus| *status == PortStatus::Open;
+    let any_open = merged.tcp_results.iter().any(|r| open(&r.status))
+        || merged.syn_results.iter().any(|r| open(&r.status))
+        || merged.udp_results.iter().any(|r| open(&r.status));
+    if merged.host_status != HostStatus::Up && any_open {
+        merged.host_status = HostStatus::Up;
+        merged.down_reason = None;
+    }
+}
+
+/// Combine port results by port: the older result stays only where its
+/// confidence is higher than the newer one's
+fn merge_ports<T>(merged: &mut Vec<T>, older: Vec<T>, key: impl Fn(&T) -> (u16, Option<Confidence>)) {
+    let positions: HashMap<u16, usize> = merged.iter().enumerate().map(|(i, r)| (key(r).0, i)).collect();
+    for result in older {
+        let (port, confidence) = key(&result);
+        match positions.get(&port) {
+            Some(&i) => {
+                let newer = key(&merged[i]).1;
+                if matches!((confidence, newer), (Some(old), Some(new)) if old > new) {
+                    merged[i] = result;
+                }
+            }
+            None => merged.push(result),
+        }
+    }
+    merged.sort_by_key(|r| key(r).0);
+}
+
+/// Append the items of `more` that `items` does not hold yet
+fn union<T: Hash + Eq + Clone>(items: &mut Vec<T>, more: Vec<T>) {
+    let mut seen: HashSet<T> = items.iter().cloned().collect();
+    items.extend(more.into_iter().filter(|item| seen.insert(item.clone())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use crate::scanner::ProbeError;
+
+    fn report(end: &str, ports: &[(u16, PortStatus, u8)]) -> ScanReport {
+        let target: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, Default::default());
+        result.tcp_results = ports
+            .iter()
+            .map(|&(port, ref status, confidence)| {
+                let mut r = TcpConnectResult::failed(target, port, ProbeError::Refused);
+                r.status = status.clone();
+                r.confidence = Some(Confidence::new(confidence).unwrap());
+                r
+            })
+            .collect();
+        let end = chrono::DateTime::parse_from_rfc3339(end).unwrap().to_utc();
+        ReportBuilder::new(end.to_string())
+            .with_times(end - chrono::Duration::minutes(5), end)
+            .add_results(vec![result])
+            .complete()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merge_reconciles_ports() {
+        let older = report("2026-01-01T10:00:00Z", &[(22, PortStatus::Open, 100), (80, PortStatus::Open, 90), (443, PortStatus::Closed, 90)]);
+        let mut newer = report("2026-01-02T10:00:00Z", &[(22, PortStatus::Filtered, 30), (80, PortStatus::Closed, 90)]);
+        newer.results[0].scan_duration_ms = 40;
+        let other: IpAddr = "10.0.1.1".parse().unwrap();
+        newer.results.push(CompleteScanResult::unscanned(other, HostStatus::Down, Default::default()));
+
+        let merged = ReportEngine::merge(vec![newer, older]).unwrap();
+        assert_eq!(merged.results.len(), 2);
+        let ports: Vec<_> = merged.results[0].tcp_results.iter().map(|r| (r.port, r.status.clone())).collect();
+        // Port 22 keeps the confident SYN-ACK, port 80 the newer answer
+        assert_eq!(ports, vec![(22, PortStatus::Open), (80, PortStatus::Closed), (443, PortStatus::Closed)]);
+        assert_eq!(merged.results[0].scan_duration_ms, 40);
+        assert_eq!(merged.summary.total_targets, 2);
+        assert_eq!(merged.summary.total_open_ports, 1);
+        assert_eq!(merged.metadata.start_time.to_rfc3339(), "2026-01-01T09:55:00+00:00");
+        assert!(ReportEngine::merge(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_merge_keeps_newer_down() {
+        let older = report("2026-01-01T10:00:00Z", &[(22, PortStatus::Open, 100)]);
+        let mut newer = report("2026-01-02T10:00:00Z", &[]);
+        newer.results[0].host_status = HostStatus::Down;
+
+        let merged = ReportEngine::merge(vec![older, newer]).unwrap();
+        assert_eq!(merged.results[0].host_status, HostStatus::Down);
+        assert!(merged.results[0].tcp_results.is_empty());
+        assert_eq!(merged.summary.total_open_ports, 0);
+
+        // A host down before and up now keeps what it has open now
+        let mut older = report("2026-01-01T10:00:00Z", &[]);
+        older.results[0].host_status = HostStatus::Down;
+        let newer = report("2026-01-02T10:00:00Z", &[(22, PortStatus::Open, 100)]);
+        let merged = ReportEngine::merge(vec![newer, older]).unwrap();
+        assert_eq!(merged.results[0].host_status, HostStatus::Up);
+        assert_eq!(merged.summary.total_open_ports, 1);
+    }
+}
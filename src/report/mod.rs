@@ -1,21 +1,56 @@
 /// Report engine for NrMAP
 /// 
 /// This module provides comprehensive reporting capabilities for scan results
-/// including JSON, YAML, HTML, and CLI table formats.
+/// including JSON, YAML, HTML, Markdown, SARIF, JUnit, CLI table and
+/// user-templated formats, and imports Nmap XML results.
 
 pub mod json;
+pub mod diff;
 pub mod yaml;
+pub mod history;
 pub mod html;
+pub mod junit;
+pub mod markdown;
+pub mod masscan;
+pub mod merge;
+pub mod nmap_xml;
+pub mod origin;
+pub mod port_view;
+pub mod redact;
+pub mod sarif;
 pub mod table;
+pub mod time;
+pub mod stream;
+pub mod sink;
+pub mod spool;
+pub mod template;
 
 pub use json::JsonReportGenerator;
+pub use diff::{AssetChange, AssetSnapshot, ScanDiff};
 pub use yaml::YamlReportGenerator;
+pub use history::AssetHistory;
 pub use html::HtmlReportGenerator;
+pub use junit::{JunitCase, JunitReportGenerator};
+pub use markdown::MarkdownReportGenerator;
+pub use nmap_xml::NmapRun;
+pub use origin::ScanOrigin;
+pub use port_view::{port_view, PortEvidence};
+pub use redact::{RedactScope, RedactionMap, Redactor};
+pub use sarif::{SarifLevel, SarifReportGenerator, SarifRule};
 pub use table::TableReportGenerator;
+pub use time::DisplayTimezone;
+pub use stream::ReportChunks;
+pub use sink::{FormatSink, OutputDestination, OutputSink, SinkList, TemplateSink};
+pub use spool::{ResultSpool, SpooledReport, SpooledResults};
+pub use template::TemplateReportGenerator;
 
+use crate::enrichment::ExcludedHost;
 use crate::error::ScanResult;
-use crate::scanner::CompleteScanResult;
+use crate::policy::{ComplianceReport, Policy, PolicyEvaluator};
+use crate::scanner::latency::LatencyStats;
+use crate::scanner::{CompleteScanResult, ProbeError, ResourceUsage};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::net::IpAddr;
 use tracing::info;
 
@@ -24,9 +59,21 @@ use tracing::info;
 pub enum ReportFormat {
     Json,
     JsonPretty,
+    /// One JSON object per host result and line, for shell pipelines
+    Jsonl,
+    /// Open ports one per line, as `masscan -oL` writes them
+    List,
     Yaml,
     Html,
     Table,
+    /// GitHub-flavored Markdown
+    Markdown,
+    /// SARIF 2.1.0 findings for CI security dashboards
+    Sarif,
+    /// JUnit XML test cases for the report's expectations
+    Junit,
+    /// Rendered through a user-provided template
+    Template,
 }
 
 impl std::fmt::Display for ReportFormat {
@@ -34,9 +81,15 @@ impl std::fmt::Display for ReportFormat {
         match self {
             ReportFormat::Json => write!(f, "json"),
             ReportFormat::JsonPretty => write!(f, "json-pretty"),
+            ReportFormat::Jsonl => write!(f, "jsonl"),
+            ReportFormat::List => write!(f, "list"),
             ReportFormat::Yaml => write!(f, "yaml"),
             ReportFormat::Html => write!(f, "html"),
             ReportFormat::Table => write!(f, "table"),
+            ReportFormat::Markdown => write!(f, "markdown"),
+            ReportFormat::Sarif => write!(f, "sarif"),
+            ReportFormat::Junit => write!(f, "junit"),
+            ReportFormat::Template => write!(f, "template"),
         }
     }
 }
@@ -48,9 +101,15 @@ impl std::str::FromStr for ReportFormat {
         match s.to_lowercase().as_str() {
             "json" => Ok(ReportFormat::Json),
             "json-pretty" | "pretty" => Ok(ReportFormat::JsonPretty),
+            "jsonl" | "ndjson" => Ok(ReportFormat::Jsonl),
+            "list" | "masscan" => Ok(ReportFormat::List),
             "yaml" | "yml" => Ok(ReportFormat::Yaml),
             "html" | "htm" => Ok(ReportFormat::Html),
             "table" | "tbl" => Ok(ReportFormat::Table),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "sarif" => Ok(ReportFormat::Sarif),
+            "junit" => Ok(ReportFormat::Junit),
+            "template" => Ok(ReportFormat::Template),
             _ => Err(crate::error::ScanError::validation_error(
                 "format",
                 format!("Unknown report format: {}", s),
@@ -66,6 +125,9 @@ pub struct ScanReport {
     pub summary: ReportSummary,
     pub results: Vec<CompleteScanResult>,
     pub statistics: ReportStatistics,
+    /// Outcome of the policy the scan was checked against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compliance: Option<ComplianceReport>,
 }
 
 /// Report metadata
@@ -73,10 +135,29 @@ pub struct ScanReport {
 pub struct ReportMetadata {
     pub scan_id: String,
     pub scanner_version: String,
-    pub start_time: chrono::DateTime<chrono::Utc>,
-    pub end_time: chrono::DateTime<chrono::Utc>,
+    /// Start time in the display timezone (serialized as ISO 8601 with offset)
+    pub start_time: chrono::DateTime<chrono::FixedOffset>,
+    /// End time in the display timezone (serialized as ISO 8601 with offset)
+    pub end_time: chrono::DateTime<chrono::FixedOffset>,
+    #[serde(default)]
+    pub timezone: DisplayTimezone,
     pub duration_seconds: f64,
     pub scan_parameters: ScanParameters,
+    /// Hosts skipped by hostname exclusion rules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_hosts: Vec<ExcludedHost>,
+    /// Scanning host and operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ScanOrigin>,
+    /// The scan was stopped early; results cover only the hosts and ports probed
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Results were narrowed to hosts and ports no earlier scan had seen
+    #[serde(default)]
+    pub new_assets_only: bool,
+    /// Ports allowed to be open; any other open port fails the expectation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_ports: Option<Vec<u16>>,
 }
 
 /// Scan parameters
@@ -99,6 +180,12 @@ pub struct ReportSummary {
     pub total_open_ports: usize,
     pub total_closed_ports: usize,
     pub total_filtered_ports: usize,
+    /// Hosts whose deception suspicion score reached the threshold
+    #[serde(default)]
+    pub suspected_decoys: usize,
+    /// Hosts that got the full-range second pass (`expand_ports`)
+    #[serde(default)]
+    pub expanded_hosts: usize,
 }
 
 /// Report statistics
@@ -110,6 +197,28 @@ pub struct ReportStatistics {
     pub success_rate: f64,
     pub packets_sent: usize,
     pub packets_received: usize,
+    /// Round-trip times across every answered probe in the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyStats>,
+    /// Failed probes by error class, across every port in the report
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub error_classes: BTreeMap<ProbeError, usize>,
+    /// Sockets, packets, bytes, file descriptors and memory the scan used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+}
+
+impl ReportStatistics {
+    /// Error classes with counts, most frequent first, e.g. "timeout 12, refused 3"
+    pub fn error_summary(&self) -> Option<String> {
+        if self.error_classes.is_empty() {
+            return None;
+        }
+        let mut classes: Vec<_> = self.error_classes.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let parts: Vec<String> = classes.iter().map(|(class, count)| format!("{} {}", class, count)).collect();
+        Some(parts.join(", "))
+    }
 }
 
 /// Report engine for generating reports in multiple formats
@@ -118,6 +227,10 @@ pub struct ReportEngine {
     yaml_generator: YamlReportGenerator,
     html_generator: HtmlReportGenerator,
     table_generator: TableReportGenerator,
+    markdown_generator: MarkdownReportGenerator,
+    sarif_generator: SarifReportGenerator,
+    junit_generator: JunitReportGenerator,
+    template_generator: Option<TemplateReportGenerator>,
 }
 
 impl ReportEngine {
@@ -130,9 +243,19 @@ impl ReportEngine {
             yaml_generator: YamlReportGenerator::new(),
             html_generator: HtmlReportGenerator::new(),
             table_generator: TableReportGenerator::new(),
+            markdown_generator: MarkdownReportGenerator::new(),
+            sarif_generator: SarifReportGenerator::new(),
+            junit_generator: JunitReportGenerator::new(),
+            template_generator: None,
         }
     }
 
+    /// Render [`ReportFormat::Template`] reports through this template
+    pub fn with_template(mut self, template: TemplateReportGenerator) -> Self {
+        self.template_generator = Some(template);
+        self
+    }
+
     /// Generate a report in the specified format
     /// 
     /// # Arguments
@@ -144,10 +267,62 @@ impl ReportEngine {
         match format {
             ReportFormat::Json => self.json_generator.generate(report, false),
             ReportFormat::JsonPretty => self.json_generator.generate(report, true),
+            ReportFormat::Jsonl | ReportFormat::List => {
+                let mut lines = Vec::new();
+                stream::write_report(report, format, &mut lines)?;
+                Ok(String::from_utf8_lossy(&lines).into_owned())
+            }
             ReportFormat::Yaml => self.yaml_generator.generate(report),
             ReportFormat::Html => self.html_generator.generate(report),
             ReportFormat::Table => self.table_generator.generate(report),
+            ReportFormat::Markdown => self.markdown_generator.generate(report),
+            ReportFormat::Sarif => self.sarif_generator.generate(report),
+            ReportFormat::Junit => self.junit_generator.generate(report),
+            ReportFormat::Template => match self.template_generator {
+                Some(ref template) => template.generate(report),
+                None => Err(stream::missing_template()),
+            },
+        }
+    }
+
+    /// Serialize a report incrementally, one chunk per host result
+    ///
+    /// Use this instead of [`ReportEngine::generate`] for large result sets
+    /// or streaming consumers (chunked HTTP bodies, sockets).
+    pub fn stream<'a>(&self, report: &'a ScanReport, format: ReportFormat) -> ReportChunks<'a> {
+        info!("Streaming report in {} format", format);
+        ReportChunks::new(report, format)
+    }
+
+    /// Write a report incrementally to a blocking writer
+    ///
+    /// Template reports are rendered in one piece.
+    pub fn write_to<W: std::io::Write>(
+        &self,
+        report: &ScanReport,
+        format: ReportFormat,
+        mut writer: W,
+    ) -> ScanResult<()> {
+        if format == ReportFormat::Template {
+            let text = self.generate(report, format)?;
+            return writer.write_all(text.as_bytes()).map_err(stream::write_error);
+        }
+        stream::write_report(report, format, writer)
+    }
+
+    /// Write a report incrementally to an async writer, awaiting each chunk
+    pub async fn write_to_async<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        report: &ScanReport,
+        format: ReportFormat,
+        writer: &mut W,
+    ) -> ScanResult<()> {
+        if format == ReportFormat::Template {
+            use tokio::io::AsyncWriteExt;
+            let text = self.generate(report, format)?;
+            return writer.write_all(text.as_bytes()).await.map_err(stream::write_error);
         }
+        stream::write_report_async(report, format, writer).await
     }
 
     /// Generate and save a report to a file
@@ -162,12 +337,12 @@ impl ReportEngine {
         format: ReportFormat,
         output_path: &str,
     ) -> ScanResult<()> {
-        let content = self.generate(report, format)?;
-        std::fs::write(output_path, content).map_err(|e| {
+        let file = std::fs::File::create(output_path).map_err(|e| {
             crate::error::ScanError::OutputError {
                 message: format!("Failed to write report to {}: {}", output_path, e),
             }
         })?;
+        self.write_to(report, format, std::io::BufWriter::new(file))?;
         
         info!("Report saved to: {}", output_path);
         Ok(())
@@ -187,6 +362,15 @@ pub struct ReportBuilder {
     end_time: Option<chrono::DateTime<chrono::Utc>>,
     results: Vec<CompleteScanResult>,
     scan_parameters: Option<ScanParameters>,
+    timezone: DisplayTimezone,
+    origin: Option<ScanOrigin>,
+    cancelled: bool,
+    new_assets_only: bool,
+    allowed_ports: Option<Vec<u16>>,
+    policy: Option<Policy>,
+    compliance: Option<ComplianceReport>,
+    resources: Option<ResourceUsage>,
+    excluded_hosts: Vec<ExcludedHost>,
 }
 
 impl ReportBuilder {
@@ -198,44 +382,189 @@ impl ReportBuilder {
             end_time: None,
             results: Vec::new(),
             scan_parameters: None,
+            timezone: DisplayTimezone::Utc,
+            origin: None,
+            cancelled: false,
+            new_assets_only: false,
+            allowed_ports: None,
+            policy: None,
+            compliance: None,
+            resources: None,
+            excluded_hosts: Vec::new(),
         }
     }
 
+    /// Set the timezone used for report timestamps
+    pub fn with_timezone(mut self, timezone: DisplayTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
     /// Set scan parameters
     pub fn with_parameters(mut self, params: ScanParameters) -> Self {
         self.scan_parameters = Some(params);
         self
     }
 
+    /// Set when the scan started and finished, e.g. for imported results
+    pub fn with_times(mut self, start: chrono::DateTime<chrono::Utc>, end: chrono::DateTime<chrono::Utc>) -> Self {
+        self.start_time = start;
+        self.end_time = Some(end);
+        self
+    }
+
+    /// Record where the scan was run from
+    pub fn with_origin(mut self, origin: ScanOrigin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Record whether the scan was cancelled before finishing
+    pub fn with_cancelled(mut self, cancelled: bool) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    /// Record whether the results were narrowed to newly seen assets
+    pub fn with_new_assets_only(mut self, new_assets_only: bool) -> Self {
+        self.new_assets_only = new_assets_only;
+        self
+    }
+
+    /// Expect only these ports to be open on every host
+    pub fn with_allowed_ports(mut self, ports: Vec<u16>) -> Self {
+        self.allowed_ports = Some(ports);
+        self
+    }
+
+    /// Check the results against a policy and include the outcome
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Include a policy outcome already evaluated while scanning, instead of
+    /// checking the results again against [`ReportBuilder::with_policy`]
+    pub fn with_compliance(mut self, compliance: ComplianceReport) -> Self {
+        self.compliance = Some(compliance);
+        self
+    }
+
+    /// Include the resources the scan used in the statistics
+    pub fn with_resources(mut self, resources: Option<ResourceUsage>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Record hosts skipped by exclusion rules, besides those among the
+    /// results, e.g. from merged reports
+    pub fn with_excluded_hosts(mut self, hosts: Vec<ExcludedHost>) -> Self {
+        self.excluded_hosts = hosts;
+        self
+    }
+
     /// Add scan results
     pub fn add_results(mut self, results: Vec<CompleteScanResult>) -> Self {
         self.results = results;
         self
     }
 
-    /// Mark scan as complete
+    /// Mark scan as complete, now unless an end time was set with
+    /// [`ReportBuilder::with_times`]
     pub fn complete(mut self) -> Self {
-        self.end_time = Some(chrono::Utc::now());
+        self.end_time.get_or_insert_with(chrono::Utc::now);
         self
     }
 
     /// Build the final report
-    pub fn build(self) -> ScanResult<ScanReport> {
+    ///
+    /// Results for hosts skipped by an exclusion rule are moved out of the
+    /// result list and recorded in the report metadata instead.
+    pub fn build(mut self) -> ScanResult<ScanReport> {
+        let (excluded, results): (Vec<_>, Vec<_>) = std::mem::take(&mut self.results)
+            .into_iter()
+            .partition(|r| r.excluded_by.is_some());
+
+        let mut totals = ReportTotals::default();
+        let now = self.end_time.unwrap_or_else(chrono::Utc::now);
+        let mut evaluator = self.evaluator(now);
+        for result in &results {
+            totals.add(result);
+            if let Some(ref mut evaluator) = evaluator {
+                evaluator.add(result);
+            }
+        }
+        let compliance = evaluator.map(PolicyEvaluator::finish).or_else(|| self.compliance.clone());
+        let excluded_hosts = excluded.iter().map(excluded_host).collect();
+        let (summary, mut statistics) = totals.finish();
+        statistics.resources = self.resources;
+
+        Ok(ScanReport {
+            metadata: self.metadata(excluded_hosts),
+            summary,
+            results,
+            statistics,
+            compliance,
+        })
+    }
+
+    /// Build the report for results spooled to disk
+    ///
+    /// The spool is read once to compute the summary and statistics; the
+    /// results themselves stay on disk and are streamed again when the
+    /// report is written. Results added with [`ReportBuilder::add_results`]
+    /// are ignored.
+    pub fn build_spooled(self, results: SpooledResults) -> ScanResult<SpooledReport> {
+        let mut totals = ReportTotals::default();
+        let now = self.end_time.unwrap_or_else(chrono::Utc::now);
+        let mut evaluator = self.evaluator(now);
+        let mut excluded_hosts = Vec::new();
+        for result in results.iter() {
+            let result = result?;
+            if result.excluded_by.is_some() {
+                excluded_hosts.push(excluded_host(&result));
+            } else {
+                totals.add(&result);
+                if let Some(ref mut evaluator) = evaluator {
+                    evaluator.add(&result);
+                }
+            }
+        }
+        let compliance = evaluator.map(PolicyEvaluator::finish).or_else(|| self.compliance.clone());
+        let (summary, mut statistics) = totals.finish();
+        statistics.resources = self.resources;
+
+        Ok(SpooledReport {
+            report: ScanReport {
+                metadata: self.metadata(excluded_hosts),
+                summary,
+                results: Vec::new(),
+                statistics,
+                compliance,
+            },
+            results,
+        })
+    }
+
+    /// Evaluator for the policy, unless its outcome is already known
+    fn evaluator(&self, now: chrono::DateTime<chrono::Utc>) -> Option<PolicyEvaluator<'_>> {
+        match self.compliance {
+            Some(_) => None,
+            None => self.policy.as_ref().map(|policy| PolicyEvaluator::new(policy, now)),
+        }
+    }
+
+    fn metadata(self, mut excluded_hosts: Vec<ExcludedHost>) -> ReportMetadata {
+        excluded_hosts.splice(0..0, self.excluded_hosts);
         let end_time = self.end_time.unwrap_or_else(chrono::Utc::now);
         let duration_seconds = (end_time - self.start_time).num_milliseconds() as f64 / 1000.0;
 
-        // Calculate summary
-        let summary = self.calculate_summary();
-        
-        // Calculate statistics
-        let statistics = self.calculate_statistics();
-
-        // Build metadata
-        let metadata = ReportMetadata {
+        ReportMetadata {
             scan_id: self.scan_id,
             scanner_version: env!("CARGO_PKG_VERSION").to_string(),
-            start_time: self.start_time,
-            end_time,
+            start_time: self.timezone.convert(self.start_time),
+            end_time: self.timezone.convert(end_time),
+            timezone: self.timezone,
             duration_seconds,
             scan_parameters: self.scan_parameters.unwrap_or_else(|| ScanParameters {
                 targets: Vec::new(),
@@ -244,96 +573,132 @@ impl ReportBuilder {
                 timeout_ms: 0,
                 concurrent_scans: 0,
             }),
-        };
+            excluded_hosts,
+            origin: self.origin,
+            cancelled: self.cancelled,
+            new_assets_only: self.new_assets_only,
+            allowed_ports: self.allowed_ports,
+        }
+    }
+}
 
-        Ok(ScanReport {
-            metadata,
-            summary,
-            results: self.results.clone(),
-            statistics,
-        })
+fn excluded_host(result: &CompleteScanResult) -> ExcludedHost {
+    ExcludedHost {
+        target: result.target,
+        hostname: result.hostname.clone().unwrap_or_default(),
+        rule: result.excluded_by.clone().unwrap_or_default(),
     }
+}
 
-    fn calculate_summary(&self) -> ReportSummary {
+/// Running totals for a report's summary and statistics
+///
+/// Results are added one at a time, so spooled reports never need the
+/// whole result set in memory. Ports dropped by `--open-only` count
+/// through their per-host counters.
+#[derive(Default)]
+struct ReportTotals {
+    targets: usize,
+    targets_up: usize,
+    ports_scanned: usize,
+    open_ports: usize,
+    closed_ports: usize,
+    filtered_ports: usize,
+    suspected_decoys: usize,
+    expanded_hosts: usize,
+    scan_time_total_ms: u64,
+    fastest_scan_ms: Option<u64>,
+    slowest_scan_ms: u64,
+    packets_sent: usize,
+    packets_received: usize,
+    rtt_samples: Vec<u64>,
+    error_classes: BTreeMap<ProbeError, usize>,
+}
+
+impl ReportTotals {
+    fn add(&mut self, result: &CompleteScanResult) {
         use crate::scanner::host_discovery::HostStatus;
         use crate::scanner::tcp_connect::PortStatus;
 
-        let total_targets = self.results.len();
-        let targets_up = self.results.iter()
-            .filter(|r| r.host_status == HostStatus::Up)
-            .count();
-        let targets_down = total_targets - targets_up;
-
-        let mut total_open_ports = 0;
-        let mut total_closed_ports = 0;
-        let mut total_filtered_ports = 0;
-        let mut total_ports_scanned = 0;
-
-        for result in &self.results {
-            total_ports_scanned += result.tcp_results.len() 
-                + result.syn_results.len() 
-                + result.udp_results.len();
-
-            for tcp_result in &result.tcp_results {
-                match tcp_result.status {
-                    PortStatus::Open => total_open_ports += 1,
-                    PortStatus::Closed => total_closed_ports += 1,
-                    PortStatus::Filtered => total_filtered_ports += 1,
-                    _ => {}
-                }
+        self.targets += 1;
+        if result.host_status == HostStatus::Up {
+            self.targets_up += 1;
+        }
+        if result.deception.as_ref().is_some_and(|d| d.is_suspected()) {
+            self.suspected_decoys += 1;
+        }
+        if result.expansion.is_some() {
+            self.expanded_hosts += 1;
+        }
+
+        self.ports_scanned += result.tcp_results.len() + result.syn_results.len() + result.udp_results.len();
+        for tcp_result in &result.tcp_results {
+            match tcp_result.status {
+                PortStatus::Open => self.open_ports += 1,
+                PortStatus::Closed => self.closed_ports += 1,
+                PortStatus::Filtered => self.filtered_ports += 1,
+                _ => {}
+            }
+        }
+        for error in result.probe_errors() {
+            *self.error_classes.entry(error).or_insert(0) += 1;
+        }
+        if let Some(ref dropped) = result.dropped_ports {
+            self.ports_scanned += dropped.total();
+            self.closed_ports += dropped.closed;
+            self.filtered_ports += dropped.filtered;
+            for (error, count) in &dropped.errors {
+                *self.error_classes.entry(*error).or_insert(0) += count;
             }
         }
 
-        ReportSummary {
-            total_targets,
-            targets_up,
-            targets_down,
-            total_ports_scanned,
-            total_open_ports,
-            total_closed_ports,
-            total_filtered_ports,
+        self.scan_time_total_ms += result.scan_duration_ms;
+        self.fastest_scan_ms = Some(self.fastest_scan_ms.map_or(result.scan_duration_ms, |fastest| {
+            fastest.min(result.scan_duration_ms)
+        }));
+        self.slowest_scan_ms = self.slowest_scan_ms.max(result.scan_duration_ms);
+
+        // Aggregate throttle stats if available
+        if let Some(ref stats) = result.throttle_stats {
+            self.packets_sent += stats.total_requests;
+            self.packets_received += stats.total_successes;
         }
+        self.rtt_samples.extend(result.rtt_samples());
     }
 
-    fn calculate_statistics(&self) -> ReportStatistics {
-        let scan_times: Vec<u64> = self.results.iter()
-            .map(|r| r.scan_duration_ms)
-            .collect();
-
-        let average_scan_time_ms = if !scan_times.is_empty() {
-            scan_times.iter().sum::<u64>() as f64 / scan_times.len() as f64
-        } else {
-            0.0
+    fn finish(self) -> (ReportSummary, ReportStatistics) {
+        let summary = ReportSummary {
+            total_targets: self.targets,
+            targets_up: self.targets_up,
+            targets_down: self.targets - self.targets_up,
+            total_ports_scanned: self.ports_scanned,
+            total_open_ports: self.open_ports,
+            total_closed_ports: self.closed_ports,
+            total_filtered_ports: self.filtered_ports,
+            suspected_decoys: self.suspected_decoys,
+            expanded_hosts: self.expanded_hosts,
         };
 
-        let fastest_scan_ms = scan_times.iter().min().copied().unwrap_or(0);
-        let slowest_scan_ms = scan_times.iter().max().copied().unwrap_or(0);
-
-        let success_rate = if !self.results.is_empty() {
-            use crate::scanner::host_discovery::HostStatus;
-            let successful = self.results.iter()
-                .filter(|r| r.host_status == HostStatus::Up)
-                .count();
-            (successful as f64 / self.results.len() as f64) * 100.0
+        let (average_scan_time_ms, success_rate) = if self.targets > 0 {
+            (
+                self.scan_time_total_ms as f64 / self.targets as f64,
+                (self.targets_up as f64 / self.targets as f64) * 100.0,
+            )
         } else {
-            0.0
+            (0.0, 0.0)
         };
-
-        // Aggregate throttle stats if available
-        let (packets_sent, packets_received) = self.results.iter()
-            .filter_map(|r| r.throttle_stats.as_ref())
-            .fold((0, 0), |(sent, received), stats| {
-                (sent + stats.total_requests, received + stats.total_successes)
-            });
-
-        ReportStatistics {
+        let statistics = ReportStatistics {
             average_scan_time_ms,
-            fastest_scan_ms,
-            slowest_scan_ms,
+            fastest_scan_ms: self.fastest_scan_ms.unwrap_or(0),
+            slowest_scan_ms: self.slowest_scan_ms,
             success_rate,
-            packets_sent,
-            packets_received,
-        }
+            packets_sent: self.packets_sent,
+            packets_received: self.packets_received,
+            latency: LatencyStats::from_samples(&self.rtt_samples),
+            error_classes: self.error_classes,
+            resources: None,
+        };
+
+        (summary, statistics)
     }
 }
 
@@ -348,6 +713,12 @@ mod tests {
         assert_eq!("yaml".parse::<ReportFormat>().unwrap(), ReportFormat::Yaml);
         assert_eq!("html".parse::<ReportFormat>().unwrap(), ReportFormat::Html);
         assert_eq!("table".parse::<ReportFormat>().unwrap(), ReportFormat::Table);
+        assert_eq!("template".parse::<ReportFormat>().unwrap(), ReportFormat::Template);
+        assert_eq!("md".parse::<ReportFormat>().unwrap(), ReportFormat::Markdown);
+        assert_eq!("sarif".parse::<ReportFormat>().unwrap(), ReportFormat::Sarif);
+        assert_eq!("junit".parse::<ReportFormat>().unwrap(), ReportFormat::Junit);
+        assert_eq!("ndjson".parse::<ReportFormat>().unwrap(), ReportFormat::Jsonl);
+        assert_eq!("masscan".parse::<ReportFormat>().unwrap(), ReportFormat::List);
     }
 
     #[test]
@@ -365,6 +736,190 @@ mod tests {
         let report = builder.build().unwrap();
         assert_eq!(report.metadata.scan_id, "test-scan-1");
         assert_eq!(report.results.len(), 0);
+        assert!(report.statistics.latency.is_none());
+    }
+
+    #[test]
+    fn test_report_builder_excluded_hosts() {
+        use crate::scanner::host_discovery::HostStatus;
+
+        let result = |last: u8, excluded_by: Option<&str>| CompleteScanResult {
+            target: IpAddr::V4(Ipv4Addr::new(10, 0, 0, last)),
+            host_status: HostStatus::Up,
+            tcp_results: vec![],
+            syn_results: vec![],
+            udp_results: vec![],
+            scan_duration_ms: 10,
+            throttle_stats: None,
+            geo: None,
+            hostname: Some(format!("host{}.voip.internal", last)),
+            excluded_by: excluded_by.map(str::to_string),
+            discovered_hostnames: Vec::new(),
+            tls_certificates: Vec::new(),
+            os_matches: Vec::new(),
+            down_reason: None,
+            port_verifications: Vec::new(),
+            liveness: Vec::new(),
+            deception: None,
+            latency: None,
+            phase_timings: None,
+            dropped_ports: None,
+            expansion: None,
+            mac: None,
+            icmp_rate_limit: None,
+        };
+
+        let report = ReportBuilder::new("test-exclusion".to_string())
+            .add_results(vec![result(1, None), result(2, Some("*.voip.internal"))])
+            .complete()
+            .build()
+            .unwrap();
+
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.summary.total_targets, 1);
+        assert_eq!(report.metadata.excluded_hosts.len(), 1);
+        assert_eq!(report.metadata.excluded_hosts[0].rule, "*.voip.internal");
+        assert_eq!(report.metadata.excluded_hosts[0].hostname, "host2.voip.internal");
+    }
+
+    #[test]
+    fn test_error_class_statistics() {
+        use crate::scanner::host_discovery::HostStatus;
+        use crate::scanner::tcp_connect::TcpConnectResult;
+        use crate::scanner::udp_scan::UdpScanResult;
+
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let result = CompleteScanResult {
+            target,
+            host_status: HostStatus::Up,
+            tcp_results: vec![
+                TcpConnectResult::failed(target, 22, ProbeError::Timeout),
+                TcpConnectResult::failed(target, 23, ProbeError::Timeout),
+                TcpConnectResult::failed(target, 80, ProbeError::ResourceExhausted),
+            ],
+            syn_results: vec![],
+            udp_results: vec![UdpScanResult::failed(target, 53, ProbeError::AdminProhibited)],
+            scan_duration_ms: 10,
+            throttle_stats: None,
+            geo: None,
+            hostname: None,
+            excluded_by: None,
+            discovered_hostnames: Vec::new(),
+            tls_certificates: Vec::new(),
+            os_matches: Vec::new(),
+            down_reason: None,
+            port_verifications: Vec::new(),
+            liveness: Vec::new(),
+            deception: None,
+            latency: None,
+            phase_timings: None,
+            dropped_ports: None,
+            expansion: None,
+            mac: None,
+            icmp_rate_limit: None,
+        };
+
+        let report = ReportBuilder::new("test-errors".to_string())
+            .add_results(vec![result])
+            .complete()
+            .build()
+            .unwrap();
+        let classes = &report.statistics.error_classes;
+        assert_eq!(classes[&ProbeError::Timeout], 2);
+        assert_eq!(classes[&ProbeError::AdminProhibited], 1);
+        assert_eq!(
+            report.statistics.error_summary().unwrap(),
+            "timeout 2, admin-prohibited 1, resource exhaustion 1"
+        );
+        let json = serde_json::to_value(&report.statistics).unwrap();
+        assert_eq!(json["error_classes"]["resource_exhausted"], 1);
+    }
+
+    #[test]
+    fn test_dropped_ports_keep_summary() {
+        use crate::scanner::host_discovery::HostStatus;
+        use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+        let mut open = TcpConnectResult::failed(target, 443, ProbeError::Timeout);
+        open.status = PortStatus::Open;
+        open.error = None;
+        let mut closed = TcpConnectResult::failed(target, 80, ProbeError::Timeout);
+        closed.status = PortStatus::Closed;
+        closed.error = None;
+        result.tcp_results = vec![open, closed, TcpConnectResult::failed(target, 22, ProbeError::Timeout)];
+
+        let build = |result: CompleteScanResult| {
+            ReportBuilder::new("test-dropped".to_string())
+                .add_results(vec![result])
+                .complete()
+                .build()
+                .unwrap()
+        };
+        let full = build(result.clone());
+        result.drop_unopened_ports();
+        let open_only = build(result);
+
+        assert_eq!(open_only.results[0].tcp_results.len(), 1);
+        assert_eq!(open_only.summary.total_ports_scanned, full.summary.total_ports_scanned);
+        assert_eq!(open_only.summary.total_open_ports, 1);
+        assert_eq!(open_only.summary.total_closed_ports, full.summary.total_closed_ports);
+        assert_eq!(open_only.summary.total_filtered_ports, full.summary.total_filtered_ports);
+        assert_eq!(open_only.statistics.error_classes, full.statistics.error_classes);
+    }
+
+    #[test]
+    fn test_policy_compliance() {
+        use crate::scanner::host_discovery::HostStatus;
+        use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+        let mut telnet = TcpConnectResult::failed(target, 23, ProbeError::Timeout);
+        telnet.status = PortStatus::Open;
+        telnet.error = None;
+        result.tcp_results = vec![telnet];
+        let policy: Policy = toml::from_str(
+            "[[rules]]\nname = \"no-telnet\"\ncheck = \"forbidden_ports\"\nports = [23]\n",
+        )
+        .unwrap();
+
+        let report = ReportBuilder::new("test-policy".to_string())
+            .with_policy(policy.clone())
+            .add_results(vec![result.clone()])
+            .complete()
+            .build()
+            .unwrap();
+        let compliance = report.compliance.as_ref().unwrap();
+        assert!(!compliance.passed());
+        assert_eq!(compliance.rules[0].violations[0].port, Some(23));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["compliance"]["rules"][0]["name"], "no-telnet");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = ResultSpool::create(dir.path(), 1).unwrap();
+        spool.push(&result).unwrap();
+        let spooled = ReportBuilder::new("test-policy".to_string())
+            .with_policy(policy.clone())
+            .complete()
+            .build_spooled(spool.finish().unwrap())
+            .unwrap();
+        assert_eq!(spooled.report.compliance.as_ref(), Some(compliance));
+
+        // An outcome evaluated while scanning is used as is
+        let evaluated = policy.evaluate(&[], chrono::Utc::now());
+        let report = ReportBuilder::new("test-policy".to_string())
+            .with_policy(policy)
+            .with_compliance(evaluated.clone())
+            .add_results(vec![result])
+            .complete()
+            .build()
+            .unwrap();
+        assert_eq!(report.compliance, Some(evaluated));
+
+        let unchecked = ReportBuilder::new("test-policy".to_string()).complete().build().unwrap();
+        assert!(serde_json::to_value(&unchecked).unwrap().get("compliance").is_none());
     }
 
     #[test]
@@ -0,0 +1,369 @@
+//! Nmap XML import
+//!
+//! Reads the XML that `nmap -oX` writes into nrmap's result types, so earlier
+//! Nmap scans can be diffed against nrmap scans, recorded in the asset
+//! history, re-rendered in any report format, or used to re-verify only the
+//! ports they found open.
+//!
+//! Ports Nmap folded into `<extraports>` are kept as counts in
+//! [`CompleteScanResult::dropped_ports`]. Nmap's SYN and connect results both
+//! become TCP results; `open|filtered` becomes filtered.
+
+use crate::detection::ServiceFingerprint;
+use crate::error::{ScanError, ScanResult};
+use crate::lan::HostMac;
+use crate::report::{ReportBuilder, ScanParameters};
+use crate::scanner::host_discovery::HostStatus;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::service_names::service_name;
+use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+use crate::scanner::udp_scan::UdpScanResult;
+use crate::scanner::{CompleteScanResult, DroppedPorts, ScanType};
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+/// One `<nmaprun>`: the hosts it scanned and what it was asked to do
+#[derive(Debug, Clone)]
+pub struct NmapRun {
+    /// Command line Nmap was run with
+    pub args: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Ports requested per `<scaninfo>`
+    pub ports: Vec<u16>,
+    pub scan_types: Vec<ScanType>,
+    pub results: Vec<CompleteScanResult>,
+}
+
+impl NmapRun {
+    /// Read an Nmap XML file
+    pub fn from_file(path: impl AsRef<Path>) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let xml = std::fs::read_to_string(path)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::parse(&xml).map_err(|e| ScanError::validation_error("nmap_xml", format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parse Nmap XML output
+    pub fn parse(xml: &str) -> ScanResult<Self> {
+        // Nmap writes a `<!DOCTYPE nmaprun>` line
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        };
+        let doc = Document::parse_with_options(xml, options)
+            .map_err(|e| ScanError::validation_error("nmap_xml", e.to_string()))?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "nmaprun" {
+            return Err(ScanError::validation_error(
+                "nmap_xml",
+                format!("Expected <nmaprun>, found <{}>", root.tag_name().name()),
+            ));
+        }
+
+        let mut ports = BTreeSet::new();
+        let mut scan_types = Vec::new();
+        for info in children(root, "scaninfo") {
+            if let Some(services) = info.attribute("services").filter(|s| !s.is_empty()) {
+                ports.extend(crate::parse_port_range(services)?);
+            }
+            let scan_type = match (info.attribute("type"), info.attribute("protocol")) {
+                (_, Some("udp")) => ScanType::Udp,
+                (Some("syn"), _) => ScanType::TcpSyn,
+                _ => ScanType::TcpConnect,
+            };
+            if !scan_types.contains(&scan_type) {
+                scan_types.push(scan_type);
+            }
+        }
+
+        let results = children(root, "host").filter_map(parse_host).collect();
+        let end_time = children(root, "runstats")
+            .flat_map(|stats| children(stats, "finished"))
+            .find_map(|finished| epoch(finished.attribute("time")));
+        Ok(Self {
+            args: root.attribute("args").map(str::to_string),
+            start_time: epoch(root.attribute("start")),
+            end_time,
+            ports: ports.into_iter().collect(),
+            scan_types,
+            results,
+        })
+    }
+
+    /// TCP ports found open on each host, for re-verification
+    pub fn open_tcp_ports(&self) -> BTreeMap<IpAddr, Vec<u16>> {
+        self.results
+            .iter()
+            .filter_map(|result| {
+                let open: Vec<u16> = result
+                    .tcp_results
+                    .iter()
+                    .filter(|port| port.status == PortStatus::Open)
+                    .map(|port| port.port)
+                    .collect();
+                (!open.is_empty()).then_some((result.target, open))
+            })
+            .collect()
+    }
+
+    /// What the run found on the hosts and ports a re-verification scans:
+    /// hosts with open TCP ports, with only their TCP results on `ports`
+    ///
+    /// Comparing a rescan against the whole run would report every host
+    /// and UDP port that was not rescanned as gone.
+    pub fn rescan_baseline(&self, ports: &[u16]) -> Vec<CompleteScanResult> {
+        let open = self.open_tcp_ports();
+        self.results
+            .iter()
+            .filter(|result| open.contains_key(&result.target))
+            .map(|result| {
+                let mut baseline = result.clone();
+                baseline.tcp_results.retain(|port| ports.contains(&port.port));
+                baseline.syn_results.retain(|port| ports.contains(&port.port));
+                baseline.udp_results.clear();
+                baseline
+            })
+            .collect()
+    }
+
+    /// Report builder carrying the run's parameters and times; add the
+    /// results to build the report
+    pub fn report(&self) -> ReportBuilder {
+        let scan_id = match self.start_time {
+            Some(start) => format!("nmap-{}", start.timestamp()),
+            None => "nmap-import".to_string(),
+        };
+        let parameters = ScanParameters {
+            targets: self.results.iter().map(|result| result.target).collect(),
+            ports: self.ports.clone(),
+            scan_types: self.scan_types.iter().map(|t| format!("{:?}", t)).collect(),
+            timeout_ms: 0,
+            concurrent_scans: 0,
+        };
+        let report = ReportBuilder::new(scan_id).with_parameters(parameters);
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) => report.with_times(start, end),
+            _ => report,
+        }
+    }
+}
+
+fn parse_host(host: Node<'_, '_>) -> Option<CompleteScanResult> {
+    // A host is listed by its IP address; MAC addresses come alongside
+    let target: IpAddr = children(host, "address")
+        .filter(|address| matches!(address.attribute("addrtype"), Some("ipv4") | Some("ipv6")))
+        .find_map(|address| address.attribute("addr")?.parse().ok())?;
+    let status = match child(host, "status").and_then(|status| status.attribute("state")) {
+        Some("up") => HostStatus::Up,
+        Some("down") => HostStatus::Down,
+        _ => HostStatus::Unknown,
+    };
+    let duration = match (epoch(host.attribute("starttime")), epoch(host.attribute("endtime"))) {
+        (Some(start), Some(end)) => (end - start).to_std().unwrap_or_default(),
+        _ => Duration::ZERO,
+    };
+
+    let mut result = CompleteScanResult::unscanned(target, status, duration);
+    result.hostname = child(host, "hostnames")
+        .and_then(|names| child(names, "hostname"))
+        .and_then(|name| name.attribute("name"))
+        .map(str::to_string);
+    // Nmap's vendor name is kept; ours fills in when it has none
+    result.mac = children(host, "address")
+        .filter(|address| address.attribute("addrtype") == Some("mac"))
+        .find_map(|address| {
+            let mut mac = HostMac::new(address.attribute("addr")?.parse().ok()?);
+            if let Some(vendor) = address.attribute("vendor") {
+                mac.vendor = Some(vendor.to_string());
+            }
+            Some(mac)
+        });
+
+    let Some(ports) = child(host, "ports") else {
+        return Some(result);
+    };
+    for port in children(ports, "port") {
+        let Some(number) = port.attribute("portid").and_then(|p| p.parse::<u16>().ok()) else {
+            continue;
+        };
+        let status = match child(port, "state").and_then(|state| state.attribute("state")) {
+            Some("open") => PortStatus::Open,
+            Some("closed") => PortStatus::Closed,
+            Some(state) if state.contains("filtered") => PortStatus::Filtered,
+            _ => PortStatus::Unknown,
+        };
+        let service = child(port, "service");
+        let udp = port.attribute("protocol") == Some("udp");
+        let protocol = if udp { TransportProtocol::Udp } else { TransportProtocol::Tcp };
+        let name = service
+            .and_then(|service| service.attribute("name"))
+            .map(str::to_string)
+            .or_else(|| service_name(number, protocol));
+
+        if udp {
+            result.udp_results.push(UdpScanResult {
+                target,
+                port: number,
+                status,
+                response_time_ms: None,
+                response_data: None,
+                evasion: None,
+                attempts: 1,
+                error: None,
+                icmp: None,
+                fingerprint: service.and_then(|service| service_fingerprint(service, name.as_deref())),
+                service: name,
+                confidence: None,
+                reason: None,
+            });
+        } else {
+            result.tcp_results.push(TcpConnectResult {
+                target,
+                port: number,
+                status,
+                response_time_ms: None,
+                banner: service.and_then(service_banner),
+                evasion: None,
+                attempts: 1,
+                error_class: None,
+                service: name,
+                error: None,
+                confidence: None,
+                reason: None,
+            });
+        }
+    }
+
+    let mut dropped = DroppedPorts::default();
+    for extra in children(ports, "extraports") {
+        let count: usize = extra.attribute("count").and_then(|c| c.parse().ok()).unwrap_or(0);
+        match extra.attribute("state") {
+            Some("closed") => dropped.closed += count,
+            Some(state) if state.contains("filtered") => dropped.filtered += count,
+            _ => dropped.unknown += count,
+        }
+    }
+    if dropped != DroppedPorts::default() {
+        result.dropped_ports = Some(dropped);
+    }
+    Some(result)
+}
+
+/// Product, version and extra info of a `<service>`, e.g. "OpenSSH 8.9p1"
+fn service_banner(service: Node<'_, '_>) -> Option<String> {
+    let parts: Vec<&str> = ["product", "version", "extrainfo"]
+        .iter()
+        .filter_map(|name| service.attribute(*name))
+        .filter(|value| !value.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// A probed UDP `<service>` with product details, as a fingerprint
+fn service_fingerprint(service: Node<'_, '_>, name: Option<&str>) -> Option<ServiceFingerprint> {
+    let attribute = |key| service.attribute(key).filter(|value| !value.is_empty()).map(str::to_string);
+    let (product, version, extra_info) = (attribute("product"), attribute("version"), attribute("extrainfo"));
+    if product.is_none() && version.is_none() && extra_info.is_none() {
+        return None;
+    }
+    Some(ServiceFingerprint {
+        service_name: name.unwrap_or("unknown").to_string(),
+        product,
+        version,
+        os_info: attribute("ostype"),
+        cpe: child(service, "cpe").and_then(|cpe| cpe.text()).map(str::to_string),
+        extra_info,
+        // Nmap rates its confidence 0-10
+        confidence: attribute("conf").and_then(|conf| conf.parse::<f32>().ok()).map_or(0.5, |conf| conf / 10.0),
+    })
+}
+
+fn epoch(seconds: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(seconds?.parse().ok()?, 0)
+}
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn children<'a>(node: Node<'a, 'a>, name: &'a str) -> impl Iterator<Item = Node<'a, 'a>> + 'a {
+    node.children().filter(move |n| n.tag_name().name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCAN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE nmaprun>
+<nmaprun scanner="nmap" args="nmap -sS -sU -p T:22,80,443,U:53 -oX scan.xml 192.0.2.0/30" start="1700000000" version="7.94">
+  <scaninfo type="syn" protocol="tcp" numservices="3" services="22,80,443"/>
+  <scaninfo type="udp" protocol="udp" numservices="1" services="53"/>
+  <host starttime="1700000001" endtime="1700000004">
+    <status state="up" reason="echo-reply"/>
+    <address addr="192.0.2.1" addrtype="ipv4"/>
+    <address addr="00:11:22:33:44:55" addrtype="mac" vendor="Acme"/>
+    <hostnames><hostname name="gw.example.com" type="PTR"/></hostnames>
+    <ports>
+      <extraports state="closed" count="1"><extrareasons reason="reset" count="1"/></extraports>
+      <port protocol="tcp" portid="22"><state state="open" reason="syn-ack"/>
+        <service name="ssh" product="OpenSSH" version="8.9p1" method="probed" conf="10"/></port>
+      <port protocol="tcp" portid="443"><state state="filtered" reason="no-response"/></port>
+      <port protocol="udp" portid="53"><state state="open|filtered" reason="no-response"/>
+        <service name="domain" method="table" conf="3"/></port>
+      <port protocol="udp" portid="123"><state state="open" reason="udp-response"/>
+        <service name="ntp" product="NTP" version="4" extrainfo="stratum 2" method="probed" conf="10"/></port>
+    </ports>
+  </host>
+  <host><status state="down" reason="no-response"/><address addr="192.0.2.2" addrtype="ipv4"/></host>
+  <runstats><finished time="1700000010" elapsed="10.00"/><hosts up="1" down="1" total="2"/></runstats>
+</nmaprun>"#;
+
+    #[test]
+    fn test_parse_nmap_xml() {
+        let run = NmapRun::parse(SCAN).unwrap();
+        assert_eq!(run.ports, vec![22, 53, 80, 443]);
+        assert_eq!(run.scan_types, vec![ScanType::TcpSyn, ScanType::Udp]);
+        assert_eq!(run.results.len(), 2);
+
+        let host = &run.results[0];
+        assert_eq!(host.target.to_string(), "192.0.2.1");
+        assert_eq!(host.host_status, HostStatus::Up);
+        assert_eq!(host.hostname.as_deref(), Some("gw.example.com"));
+        assert_eq!(host.mac.as_ref().map(|mac| mac.to_string()).as_deref(), Some("00:11:22:33:44:55 (Acme)"));
+        assert_eq!(host.scan_duration_ms, 3000);
+        assert_eq!(host.tcp_results[0].banner.as_deref(), Some("OpenSSH 8.9p1"));
+        assert_eq!(host.tcp_results[1].status, PortStatus::Filtered);
+        assert_eq!(host.udp_results[0].status, PortStatus::Filtered);
+        assert_eq!(host.udp_results[0].service.as_deref(), Some("domain"));
+        assert!(host.udp_results[0].fingerprint.is_none());
+        assert_eq!(
+            host.udp_results[1].fingerprint.as_ref().map(|fp| fp.to_string()).as_deref(),
+            Some("ntp (NTP) v4 - stratum 2 [100% confidence]")
+        );
+        assert_eq!(host.dropped_ports.as_ref().unwrap().closed, 1);
+        assert_eq!(run.results[1].host_status, HostStatus::Down);
+
+        let open = run.open_tcp_ports();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[&host.target], vec![22]);
+        let baseline = run.rescan_baseline(&[22]);
+        assert_eq!(baseline.len(), 1);
+        assert_eq!(baseline[0].tcp_results.len(), 1);
+        assert!(baseline[0].udp_results.is_empty());
+
+        let report = run.report().add_results(run.results.clone()).complete().build().unwrap();
+        assert_eq!(report.metadata.scan_id, "nmap-1700000000");
+        assert_eq!(report.metadata.duration_seconds, 10.0);
+        assert_eq!(report.summary.targets_up, 1);
+        assert_eq!(report.summary.total_open_ports, 1);
+
+        assert!(NmapRun::parse("<nmaprun><host>").is_err());
+        assert!(NmapRun::parse("<report/>").is_err());
+    }
+}
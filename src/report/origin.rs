@@ -0,0 +1,139 @@
+//! Where a scan was run from
+//!
+//! Reports record the scanning machine (hostname, the local address and
+//! interface traffic left through, OS, nrmap version, privileges) and the
+//! operator, so consumers aggregating reports from many places can tell where
+//! and by whom each scan was made.
+
+use crate::scanner::privileges::PrivilegeStatus;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+/// Environment variable naming the operator when the config does not
+pub const OPERATOR_ENV: &str = "NRMAP_OPERATOR";
+
+/// Identity of the scanning host and operator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanOrigin {
+    pub hostname: Option<String>,
+    /// Local address probes to the first target leave from
+    pub source_ip: Option<IpAddr>,
+    /// Interface owning `source_ip`
+    pub interface: Option<String>,
+    pub nrmap_version: String,
+    /// Operating system and architecture, e.g. "linux x86_64"
+    pub os: String,
+    /// "raw-sockets", "icmp-only" or "unprivileged"
+    pub privilege_level: String,
+    pub operator: Option<String>,
+}
+
+impl ScanOrigin {
+    /// Describe the current host
+    ///
+    /// `operator` comes from configuration; without it `NRMAP_OPERATOR` and
+    /// then the login name are used. `route_target` picks the outgoing
+    /// interface; without one the default route is assumed.
+    #[cfg(not(feature = "no-net"))]
+    pub fn detect(operator: Option<&str>, route_target: Option<IpAddr>, privileges: &PrivilegeStatus) -> Self {
+        let source_ip = source_address(route_target);
+        Self {
+            hostname: local_hostname(),
+            source_ip,
+            interface: source_ip.and_then(interface_for),
+            nrmap_version: crate::VERSION.to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            privilege_level: privileges.level().to_string(),
+            operator: operator.map(String::from).or_else(operator_from_env),
+        }
+    }
+}
+
+impl fmt::Display for ScanOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}@{}",
+            self.operator.as_deref().unwrap_or("unknown"),
+            self.hostname.as_deref().unwrap_or("unknown")
+        )?;
+        if let Some(ip) = self.source_ip {
+            write!(f, " ({}", ip)?;
+            if let Some(ref interface) = self.interface {
+                write!(f, " on {}", interface)?;
+            }
+            write!(f, ")")?;
+        }
+        write!(f, ", {}, nrmap {}, {}", self.os, self.nrmap_version, self.privilege_level)
+    }
+}
+
+fn operator_from_env() -> Option<String> {
+    [OPERATOR_ENV, "USER", "USERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// Local address the kernel would route traffic for `target` through
+///
+/// Connecting a UDP socket only selects the route; nothing is sent.
+#[cfg(not(feature = "no-net"))]
+pub(crate) fn source_address(target: Option<IpAddr>) -> Option<IpAddr> {
+    let (bind, destination) = match target {
+        Some(IpAddr::V6(v6)) => (IpAddr::V6(Ipv6Addr::UNSPECIFIED), IpAddr::V6(v6)),
+        Some(IpAddr::V4(v4)) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), IpAddr::V4(v4)),
+        // Any off-link address selects the default route
+        None => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(bind, 0)).ok()?;
+    socket.connect(SocketAddr::new(destination, 9)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+#[cfg(not(feature = "no-net"))]
+pub(crate) fn interface_for(ip: IpAddr) -> Option<String> {
+    pnet::datalink::interfaces()
+        .into_iter()
+        .find(|interface| interface.ips.iter().any(|network| network.ip() == ip))
+        .map(|interface| interface.name)
+}
+
+#[cfg(unix)]
+pub(crate) fn local_hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    let rc = unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+    if rc != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8(buffer[..len].to_vec()).ok().filter(|name| !name.is_empty())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn local_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "no-net"))]
+    fn test_detect_origin() {
+        let privileges = PrivilegeStatus {
+            raw_sockets: false,
+            unprivileged_icmp: true,
+        };
+        let origin = ScanOrigin::detect(Some("alice"), Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), &privileges);
+
+        assert_eq!(origin.operator.as_deref(), Some("alice"));
+        assert_eq!(origin.source_ip, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert_eq!(origin.nrmap_version, crate::VERSION);
+        assert_eq!(origin.privilege_level, "icmp-only");
+        assert!(origin.os.starts_with(std::env::consts::OS));
+        assert!(origin.to_string().starts_with("alice@"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Unified per-port view across scan types
+//!
+//! A host scanned with several scan types has separate connect, SYN and UDP
+//! result lists that repeat the same port numbers. [`port_view`] merges them
+//! into one [`PortEvidence`] row per port with a status column per scan type,
+//! so `53` reads as "open/udp, filtered/tcp" instead of appearing twice. The
+//! table and HTML reports render it, with the port's service name and any
+//! details fingerprinted from a UDP response.
+
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::service_names::service_name;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::{CompleteScanResult, Confidence};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What every scan type found on one port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortEvidence {
+    pub port: u16,
+    pub connect: Option<PortStatus>,
+    pub syn: Option<PortStatus>,
+    pub udp: Option<PortStatus>,
+    /// Service name recorded by the scan, or looked up for the port
+    pub service: Option<String>,
+    /// Product and device details fingerprinted from a UDP response
+    pub detail: Option<String>,
+    /// Best confidence among the results that found the port open
+    pub confidence: Option<Confidence>,
+}
+
+impl PortEvidence {
+    fn new(port: u16) -> Self {
+        Self {
+            port,
+            connect: None,
+            syn: None,
+            udp: None,
+            service: None,
+            detail: None,
+            confidence: None,
+        }
+    }
+
+    /// Count an open finding of `confidence` towards the port's
+    fn found_open(&mut self, status: &PortStatus, confidence: Option<Confidence>) {
+        if *status == PortStatus::Open {
+            self.confidence = self.confidence.max(confidence);
+        }
+    }
+
+    /// Whether the port's open findings are at least `min` confident;
+    /// unscored findings, e.g. from imported reports, always are
+    pub fn meets_confidence(&self, min: Option<Confidence>) -> bool {
+        match (self.confidence, min) {
+            (Some(confidence), Some(min)) => confidence >= min,
+            _ => true,
+        }
+    }
+
+    /// TCP status from connect and SYN results; the more conclusive wins
+    pub fn tcp(&self) -> Option<PortStatus> {
+        match (&self.connect, &self.syn) {
+            (Some(connect), Some(syn)) if conclusiveness(syn) > conclusiveness(connect) => Some(syn.clone()),
+            (Some(connect), _) => Some(connect.clone()),
+            (None, syn) => syn.clone(),
+        }
+    }
+
+    /// Whether any scan type found the port open
+    pub fn is_open(&self) -> bool {
+        self.tcp() == Some(PortStatus::Open) || self.udp == Some(PortStatus::Open)
+    }
+
+    /// Protocols the port is open on and its service, e.g. "22/tcp ssh" or
+    /// "53/tcp+udp domain"
+    pub fn open_label(&self) -> Option<String> {
+        let protocols: Vec<&str> = [("tcp", self.tcp()), ("udp", self.udp.clone())]
+            .into_iter()
+            .filter(|(_, status)| *status == Some(PortStatus::Open))
+            .map(|(protocol, _)| protocol)
+            .collect();
+        (!protocols.is_empty()).then(|| match self.service {
+            Some(ref service) => format!("{}/{} {}", self.port, protocols.join("+"), service),
+            None => format!("{}/{}", self.port, protocols.join("+")),
+        })
+    }
+}
+
+/// "53 open/udp, filtered/tcp": open protocols first
+impl fmt::Display for PortEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut statuses: Vec<(PortStatus, &str)> = [(self.tcp(), "tcp"), (self.udp.clone(), "udp")]
+            .into_iter()
+            .filter_map(|(status, protocol)| status.map(|s| (s, protocol)))
+            .collect();
+        statuses.sort_by_key(|(status, _)| *status != PortStatus::Open);
+        let statuses: Vec<String> = statuses
+            .iter()
+            .map(|(status, protocol)| format!("{}/{}", status, protocol))
+            .collect();
+        write!(f, "{} {}", self.port, statuses.join(", "))
+    }
+}
+
+/// Ranks how much a status tells about a port, for merging TCP evidence
+fn conclusiveness(status: &PortStatus) -> u8 {
+    match status {
+        PortStatus::Open => 3,
+        PortStatus::Closed => 2,
+        PortStatus::Filtered => 1,
+        PortStatus::Unknown => 0,
+    }
+}
+
+/// One row per scanned port of `result`, by port number
+pub fn port_view(result: &CompleteScanResult) -> Vec<PortEvidence> {
+    fn row(ports: &mut BTreeMap<u16, PortEvidence>, port: u16) -> &mut PortEvidence {
+        ports.entry(port).or_insert_with(|| PortEvidence::new(port))
+    }
+
+    let mut ports = BTreeMap::new();
+    for r in &result.tcp_results {
+        let row = row(&mut ports, r.port);
+        row.connect = Some(r.status.clone());
+        row.found_open(&r.status, r.confidence);
+        row.service = row.service.take().or_else(|| r.service.clone());
+    }
+    for r in &result.syn_results {
+        let row = row(&mut ports, r.port);
+        row.syn = Some(r.status.clone());
+        row.found_open(&r.status, r.confidence);
+        row.service = row.service.take().or_else(|| r.service.clone());
+    }
+    for r in &result.udp_results {
+        let row = row(&mut ports, r.port);
+        row.udp = Some(r.status.clone());
+        row.found_open(&r.status, r.confidence);
+        row.service = row.service.take().or_else(|| r.service.clone());
+        row.detail = r.fingerprint.as_ref().and_then(|fp| fp.details());
+    }
+
+    // Results loaded from older reports carry no service name
+    for evidence in ports.values_mut().filter(|p| p.service.is_none()) {
+        let protocol = match evidence.tcp() {
+            Some(_) => TransportProtocol::Tcp,
+            None => TransportProtocol::Udp,
+        };
+        evidence.service = service_name(evidence.port, protocol);
+    }
+    ports.into_values().collect()
+}
+
+/// Ports of `result` open on any protocol, labelled like "53/tcp+udp domain"
+pub fn open_port_labels(result: &CompleteScanResult) -> Vec<String> {
+    port_view(result).iter().filter_map(PortEvidence::open_label).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use crate::scanner::tcp_syn::TcpSynResult;
+    use crate::scanner::udp_scan::UdpScanResult;
+    use std::net::IpAddr;
+
+    #[test]
+    fn test_merges_scan_types_per_port() {
+        let target: IpAddr = "192.0.2.1".parse().unwrap();
+        let connect = |port, status| TcpConnectResult {
+            target,
+            port,
+            status,
+            response_time_ms: None,
+            banner: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            service: None,
+            error_class: None,
+            confidence: None,
+            reason: None,
+        };
+        let syn = |port, status| TcpSynResult {
+            target,
+            port,
+            status,
+            response_time_ms: None,
+            flags: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            icmp: None,
+            service: None,
+            confidence: None,
+            reason: None,
+        };
+        let udp = |port, status| UdpScanResult {
+            target,
+            port,
+            status,
+            response_time_ms: None,
+            response_data: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            icmp: None,
+            service: None,
+            fingerprint: None,
+            confidence: None,
+            reason: None,
+        };
+        let result = CompleteScanResult {
+            tcp_results: vec![connect(22, PortStatus::Open), connect(53, PortStatus::Filtered)],
+            syn_results: vec![syn(53, PortStatus::Filtered), syn(80, PortStatus::Closed), syn(22, PortStatus::Filtered)],
+            udp_results: vec![udp(53, PortStatus::Open)],
+            ..CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO)
+        };
+
+        let view = port_view(&result);
+        assert_eq!(view.iter().map(|p| p.port).collect::<Vec<_>>(), vec![22, 53, 80]);
+        assert_eq!(view[0].tcp(), Some(PortStatus::Open));
+        assert_eq!(view[1].to_string(), "53 open/udp, filtered/tcp");
+        assert_eq!(view[2].udp, None);
+        assert!(!view[2].is_open());
+        assert_eq!(view[2].service.as_deref(), Some("http"));
+        assert_eq!(open_port_labels(&result), vec!["22/tcp ssh", "53/udp domain"]);
+
+        let mut result = result;
+        result.udp_results[0].fingerprint = Some(crate::detection::ServiceFingerprint {
+            service_name: "domain".to_string(),
+            product: Some("dnsmasq".to_string()),
+            version: Some("2.85".to_string()),
+            os_info: None,
+            cpe: None,
+            extra_info: None,
+            confidence: 0.9,
+        });
+        assert_eq!(port_view(&result)[1].detail.as_deref(), Some("dnsmasq 2.85"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Redacted reports for sharing
+//!
+//! With `--redact` every `--output` report is sanitized before it is
+//! rendered, so it can go to a vendor or into a ticket without leaking
+//! internal addressing. IP addresses are replaced by pseudonyms derived
+//! with HMAC-SHA256 from a secret key: IPv4 addresses map into the reserved
+//! 240.0.0.0/4 block and IPv6 addresses into the 2001:db8::/32 documentation
+//! prefix. The same address gets the same pseudonym in every report made
+//! with the same key (`--redact-key`), so results stay comparable across
+//! runs. Hostnames (reverse DNS, certificate names, the scanning host) can
+//! be pseudonymized the same way, and banners and service responses dropped.
+//!
+//! Pseudonyms are replaced wherever they appear, also inside free text such
+//! as policy violations. In free text, a dotted quad that reads as a version
+//! number (`nginx/1.2.3.4`, `version 1.2.3.4`) is left alone unless the
+//! report knows it as an address. Hostnames include the names hosts announce
+//! over NetBIOS and mDNS. The reverse mapping from pseudonym to original is
+//! kept in a separate file (`--redact-map`) for whoever may see it.
+
+use crate::detection::udp_fingerprint::announced_names;
+use crate::error::{ScanError, ScanResult};
+use crate::report::ScanReport;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Placeholder for redacted banners and responses
+pub const REDACTED: &str = "[redacted]";
+
+lazy_static::lazy_static! {
+    /// Candidate addresses inside free text; each match is parsed to confirm
+    static ref ADDRESS_PATTERN: Regex =
+        Regex::new(r"\b(?:\d{1,3}(?:\.\d{1,3}){3}\b|[0-9A-Fa-f]{0,4}(?::[0-9A-Fa-f]{0,4}){2,7})").unwrap();
+}
+
+/// What a redacted report hides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RedactScope {
+    /// Pseudonymize IP addresses
+    pub ips: bool,
+    /// Pseudonymize hostnames
+    pub hostnames: bool,
+    /// Drop banners, service responses and fingerprint details
+    pub banners: bool,
+}
+
+impl RedactScope {
+    pub const ALL: RedactScope = RedactScope { ips: true, hostnames: true, banners: true };
+
+    pub fn is_empty(&self) -> bool {
+        !(self.ips || self.hostnames || self.banners)
+    }
+}
+
+impl FromStr for RedactScope {
+    type Err = ScanError;
+
+    /// Comma-separated `ips`, `hostnames` and `banners`, or `all`
+    fn from_str(s: &str) -> ScanResult<Self> {
+        let mut scope = RedactScope::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.to_lowercase().as_str() {
+                "ips" | "ip" => scope.ips = true,
+                "hostnames" | "hostname" | "hosts" => scope.hostnames = true,
+                "banners" | "banner" => scope.banners = true,
+                "all" => scope = RedactScope::ALL,
+                _ => {
+                    return Err(ScanError::validation_error(
+                        "redact",
+                        format!("Unknown redaction '{}' (expected ips, hostnames, banners or all)", part),
+                    ))
+                }
+            }
+        }
+        if scope.is_empty() {
+            return Err(ScanError::validation_error("redact", "Nothing to redact"));
+        }
+        Ok(scope)
+    }
+}
+
+impl fmt::Display for RedactScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<&str> = [(self.ips, "ips"), (self.hostnames, "hostnames"), (self.banners, "banners")]
+            .into_iter()
+            .filter_map(|(on, name)| on.then_some(name))
+            .collect();
+        f.write_str(&parts.join(","))
+    }
+}
+
+/// Pseudonyms and the originals they stand for
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionMap {
+    pub entries: BTreeMap<String, String>,
+}
+
+impl RedactionMap {
+    /// Load a mapping file; a missing file is an empty mapping
+    pub fn load(path: &Path) -> ScanResult<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                ScanError::scanner_error(format!("Invalid redaction map {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ScanError::scanner_error(format!(
+                "Failed to read redaction map {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Write the mapping, readable by the owner only
+    pub fn save(&self, path: &Path) -> ScanResult<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to serialize redaction map: {}", e)))?;
+        write_private(path, content.as_bytes())
+    }
+
+    /// Original behind a pseudonym
+    pub fn reveal(&self, pseudonym: &str) -> Option<&str> {
+        self.entries.get(pseudonym).map(String::as_str)
+    }
+}
+
+/// Sanitizes reports with keyed pseudonyms
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    key: Vec<u8>,
+    scope: RedactScope,
+    map_file: Option<PathBuf>,
+    ips: HashMap<IpAddr, IpAddr>,
+    pseudonyms: HashSet<IpAddr>,
+    map: RedactionMap,
+}
+
+impl Redactor {
+    pub fn new(key: Vec<u8>, scope: RedactScope) -> Self {
+        Self {
+            key,
+            scope,
+            map_file: None,
+            ips: HashMap::new(),
+            pseudonyms: HashSet::new(),
+            map: RedactionMap::default(),
+        }
+    }
+
+    /// Redactor with a fresh random key: pseudonyms are consistent within
+    /// this run's reports only
+    pub fn with_random_key(scope: RedactScope) -> Self {
+        Self::new(rand::random::<[u8; 32]>().to_vec(), scope)
+    }
+
+    /// Keep the reverse mapping in this file, adding to what it holds
+    pub fn with_map_file(mut self, path: PathBuf) -> ScanResult<Self> {
+        self.map = RedactionMap::load(&path)?;
+        self.map_file = Some(path);
+        Ok(self)
+    }
+
+    pub fn scope(&self) -> RedactScope {
+        self.scope
+    }
+
+    pub fn map(&self) -> &RedactionMap {
+        &self.map
+    }
+
+    /// Read the hex key in `path`, creating it with a random key when missing
+    pub fn load_key(path: &Path) -> ScanResult<Vec<u8>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => parse_hex(content.trim()).filter(|key| key.len() >= 16).ok_or_else(|| {
+                ScanError::validation_error(
+                    "redact_key",
+                    format!("{} must hold a hex key of at least 16 bytes", path.display()),
+                )
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = rand::random::<[u8; 32]>().to_vec();
+                write_private(path, format!("{}\n", hex(&key)).as_bytes())?;
+                tracing::info!("Created redaction key {}", path.display());
+                Ok(key)
+            }
+            Err(e) => Err(ScanError::scanner_error(format!(
+                "Failed to read redaction key {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Redacted copy of `report`
+    pub fn redact(&mut self, report: &ScanReport) -> ScanResult<ScanReport> {
+        let mut report = report.clone();
+        // Announced names are read from the raw answers the banner scope drops
+        let hostnames = if self.scope.hostnames { self.hostname_pseudonyms(&report) } else { Vec::new() };
+        if self.scope.banners {
+            strip_banners(&mut report);
+        }
+        if self.scope.hostnames {
+            strip_announced_names(&mut report);
+        }
+
+        let mut value = serde_json::to_value(&report)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to serialize report: {}", e)))?;
+        let mut addresses = HashSet::new();
+        collect_addresses(&value, &mut addresses);
+        map_strings(&mut value, &mut |text| self.redact_text(text, &hostnames, &addresses));
+        let report = serde_json::from_value(value)
+            .map_err(|e| ScanError::scanner_error(format!("Failed to rebuild redacted report: {}", e)))?;
+
+        if let Some(ref path) = self.map_file {
+            self.map.save(path)?;
+        }
+        Ok(report)
+    }
+
+    /// Pseudonym of an address, the same for the same key
+    pub fn pseudonymize_ip(&mut self, ip: IpAddr) -> IpAddr {
+        if self.pseudonyms.contains(&ip) {
+            return ip;
+        }
+        if let Some(&pseudonym) = self.ips.get(&ip) {
+            return pseudonym;
+        }
+        let octets = match ip {
+            IpAddr::V4(v4) => v4.octets().to_vec(),
+            IpAddr::V6(v6) => v6.octets().to_vec(),
+        };
+        // A pseudonym already taken by another address is derived again
+        let pseudonym = (0u32..)
+            .map(|round| {
+                let digest = self.digest(b"ip", &[&octets[..], &round.to_be_bytes()].concat());
+                pseudonym_address(ip, &digest)
+            })
+            .find(|candidate| !self.pseudonyms.contains(candidate) && !self.ips.contains_key(candidate))
+            .expect("pseudonym rounds are unbounded");
+        self.ips.insert(ip, pseudonym);
+        self.pseudonyms.insert(pseudonym);
+        self.map.entries.insert(pseudonym.to_string(), ip.to_string());
+        pseudonym
+    }
+
+    /// Pseudonym of a hostname, the same for the same key
+    pub fn pseudonymize_hostname(&mut self, hostname: &str) -> String {
+        let name = hostname.trim_end_matches('.').to_lowercase();
+        let digest = self.digest(b"host", name.as_bytes());
+        let pseudonym = format!("host-{}.redacted", hex(&digest[..4]));
+        self.map.entries.insert(pseudonym.clone(), hostname.to_string());
+        pseudonym
+    }
+
+    fn digest(&self, domain: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(domain);
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Hostnames the report names, longest first so that a name is
+    /// replaced before any name it ends with
+    fn hostname_pseudonyms(&mut self, report: &ScanReport) -> Vec<(String, String)> {
+        let mut names: Vec<String> = report
+            .results
+            .iter()
+            .flat_map(|result| {
+                result
+                    .hostname
+                    .iter()
+                    .cloned()
+                    .chain(result.discovered_hostnames.iter().map(|h| h.hostname.clone()))
+                    .chain(result.tls_certificates.iter().filter_map(|c| c.common_name.clone()))
+                    .chain(result.udp_results.iter().flat_map(|udp| {
+                        udp.response_data.as_deref().map_or_else(Vec::new, |data| announced_names(udp.port, data))
+                    }))
+            })
+            .chain(report.metadata.excluded_hosts.iter().map(|h| h.hostname.clone()))
+            .chain(report.metadata.origin.iter().filter_map(|o| o.hostname.clone()))
+            .filter(|name| !name.is_empty() && name.parse::<IpAddr>().is_err())
+            .collect();
+        names.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let pseudonym = self.pseudonymize_hostname(&name);
+                (name, pseudonym)
+            })
+            .collect()
+    }
+
+    /// `text` with hostnames and addresses replaced; `addresses` are those
+    /// the report holds as such, which are replaced even where they could
+    /// be a version number
+    fn redact_text(
+        &mut self,
+        text: &str,
+        hostnames: &[(String, String)],
+        addresses: &HashSet<IpAddr>,
+    ) -> Option<String> {
+        let mut redacted = text.to_string();
+        for (name, pseudonym) in hostnames {
+            if redacted.contains(name.as_str()) {
+                redacted = replace_name(&redacted, name, pseudonym);
+            }
+        }
+        if self.scope.ips {
+            if let Ok(ip) = redacted.parse::<IpAddr>() {
+                redacted = self.pseudonymize_ip(ip).to_string();
+            } else if ADDRESS_PATTERN.is_match(&redacted) {
+                let haystack = redacted.clone();
+                redacted = ADDRESS_PATTERN
+                    .replace_all(&haystack, |captures: &regex::Captures| {
+                        let candidate = captures.get(0).expect("group 0 is the match");
+                        match candidate.as_str().parse::<IpAddr>() {
+                            Ok(ip) if addresses.contains(&ip) || !is_version(&haystack, candidate.start(), candidate.end()) => {
+                                self.pseudonymize_ip(ip).to_string()
+                            }
+                            _ => candidate.as_str().to_string(),
+                        }
+                    })
+                    .into_owned();
+            }
+        }
+        (redacted != text).then_some(redacted)
+    }
+}
+
+/// Whether the dotted quad at `start..end` of `text` reads as a version
+/// number: attached to a product name (`nginx/`, `-`, `_`), after a word
+/// such as "version", or followed by a fifth part
+fn is_version(text: &str, start: usize, end: usize) -> bool {
+    let before = &text[..start];
+    let fifth_part = text[end..].strip_prefix('.').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+    if before.ends_with(['/', '-', '_']) || fifth_part {
+        return true;
+    }
+    let word = before.trim_end_matches([' ', ':', '=']);
+    let word = &word[word.rfind(|c: char| !c.is_ascii_alphabetic()).map_or(0, |i| i + 1)..];
+    ["version", "ver", "release", "build", "rev", "revision"].iter().any(|w| word.eq_ignore_ascii_case(w))
+}
+
+/// Address in the pseudonym range of `ip`'s family, from a digest
+fn pseudonym_address(ip: IpAddr, digest: &[u8]) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => {
+            let mut octets = [digest[0], digest[1], digest[2], digest[3]];
+            octets[0] = 0xf0 | (octets[0] & 0x0f);
+            // Keep clear of the limited broadcast address
+            if octets == [255; 4] {
+                octets[3] = 254;
+            }
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        IpAddr::V6(_) => {
+            let mut octets = [0u8; 16];
+            octets[..4].copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8]);
+            octets[4..].copy_from_slice(&digest[..12]);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+}
+
+/// Replace `name` in `text` where it stands alone, not where it is part of
+/// a longer name or word
+fn replace_name(text: &str, name: &str, pseudonym: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(name) {
+        let end = at + name.len();
+        let before = rest[..at].chars().next_back();
+        let after = rest[end..].chars().next();
+        replaced.push_str(&rest[..at]);
+        // A following dot is part of the name only when a label follows it
+        let longer = before.is_some_and(|c| is_name_char(c) || c == '.')
+            || after.is_some_and(is_name_char)
+            || (after == Some('.') && rest[end + 1..].starts_with(is_name_char));
+        replaced.push_str(if longer { name } else { pseudonym });
+        rest = &rest[end..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// Drop banners, service responses and free-form fingerprint details
+fn strip_banners(report: &mut ScanReport) {
+    for result in &mut report.results {
+        for tcp in &mut result.tcp_results {
+            if tcp.banner.is_some() {
+                tcp.banner = Some(REDACTED.to_string());
+            }
+        }
+        for udp in &mut result.udp_results {
+            udp.response_data = None;
+            if let Some(ref mut fingerprint) = udp.fingerprint {
+                fingerprint.extra_info = None;
+            }
+        }
+        for check in &mut result.liveness {
+            if check.response.is_some() {
+                check.response = Some(REDACTED.to_string());
+            }
+        }
+    }
+}
+
+/// Drop the raw NetBIOS and mDNS answers, which carry the names hosts
+/// announce, and the details of those whose names could not be read back
+fn strip_announced_names(report: &mut ScanReport) {
+    for udp in report.results.iter_mut().flat_map(|result| result.udp_results.iter_mut()) {
+        if !matches!(udp.port, 137 | 5353) {
+            continue;
+        }
+        let read = udp.response_data.take().is_some_and(|data| !announced_names(udp.port, &data).is_empty());
+        if let (false, Some(ref mut fingerprint)) = (read, udp.fingerprint.as_mut()) {
+            if fingerprint.extra_info.is_some() {
+                fingerprint.extra_info = Some(REDACTED.to_string());
+            }
+        }
+    }
+}
+
+/// Every string in `value` that is an address as a whole
+fn collect_addresses(value: &Value, addresses: &mut HashSet<IpAddr>) {
+    match value {
+        Value::String(text) => addresses.extend(text.parse::<IpAddr>()),
+        Value::Array(items) => items.iter().for_each(|item| collect_addresses(item, addresses)),
+        Value::Object(fields) => fields.iter().for_each(|(key, item)| {
+            addresses.extend(key.parse::<IpAddr>());
+            collect_addresses(item, addresses);
+        }),
+        _ => {}
+    }
+}
+
+/// Apply `redact` to every string in `value`, replacing those it changes
+fn map_strings(value: &mut Value, redact: &mut impl FnMut(&str) -> Option<String>) {
+    match value {
+        Value::String(text) => {
+            if let Some(redacted) = redact(text) {
+                *text = redacted;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| map_strings(item, redact)),
+        Value::Object(fields) => {
+            // Keys can hold addresses too, e.g. maps keyed by target
+            let keys: Vec<String> = fields.keys().cloned().collect();
+            for key in keys {
+                let mut item = fields.remove(&key).expect("key was just listed");
+                map_strings(&mut item, redact);
+                fields.insert(redact(&key).unwrap_or(key), item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Write a file only its owner can read
+fn write_private(path: &Path, content: &[u8]) -> ScanResult<()> {
+    let write = || -> std::io::Result<()> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(path)?, content)
+    };
+    write().map_err(|e| ScanError::scanner_error(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportBuilder;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::CompleteScanResult;
+    use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+
+    #[test]
+    fn test_redacted_report() {
+        let target: IpAddr = "10.1.2.3".parse().unwrap();
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, Default::default());
+        result.hostname = Some("db1.corp.example".to_string());
+        let mut open = TcpConnectResult::failed(target, 22, crate::scanner::ProbeError::Refused);
+        open.status = PortStatus::Open;
+        open.banner = Some("SSH-2.0-OpenSSH_9.6 db1.corp.example".to_string());
+        result.tcp_results = vec![open];
+        let report = ReportBuilder::new("redact-test".to_string()).add_results(vec![result]).complete().build().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let map_path = dir.path().join("map.json");
+        let mut redactor = Redactor::new(b"0123456789abcdef".to_vec(), "ips,hostnames".parse().unwrap())
+            .with_map_file(map_path.clone())
+            .unwrap();
+        let redacted = redactor.redact(&report).unwrap();
+
+        let pseudonym = redacted.results[0].target;
+        assert_ne!(pseudonym, target);
+        assert_eq!(redacted.results[0].tcp_results[0].target, pseudonym);
+        assert!(matches!(pseudonym, IpAddr::V4(v4) if v4.octets()[0] >= 240));
+        let banner = redacted.results[0].tcp_results[0].banner.clone().unwrap();
+        assert!(banner.starts_with("SSH-2.0-OpenSSH_9.6 host-") && !banner.contains("corp.example"), "{}", banner);
+        let json = serde_json::to_string(&redacted).unwrap();
+        assert!(!json.contains("10.1.2.3") && !json.contains("db1.corp"));
+
+        // Same key, same pseudonyms; the map reverses them
+        let mut again = Redactor::new(b"0123456789abcdef".to_vec(), RedactScope::ALL);
+        assert_eq!(again.pseudonymize_ip(target), pseudonym);
+        let map = RedactionMap::load(&map_path).unwrap();
+        assert_eq!(map.reveal(&pseudonym.to_string()), Some("10.1.2.3"));
+        assert_eq!(again.redact(&report).unwrap().results[0].tcp_results[0].banner.as_deref(), Some(REDACTED));
+
+        let mut other = Redactor::new(b"another key 1234".to_vec(), RedactScope::ALL);
+        assert!(other.pseudonymize_ip("fd00::1".parse().unwrap()).to_string().starts_with("2001:db8:"));
+        assert_ne!(other.pseudonymize_ip(target), pseudonym);
+        assert_eq!(replace_name("mongodb on db", "db", "host-x"), "mongodb on host-x");
+        assert!("ips,passwords".parse::<RedactScope>().is_err());
+
+        // Version numbers stay, unless they are one of the report's addresses
+        let mut versions = Redactor::new(b"0123456789abcdef".to_vec(), "ips".parse().unwrap());
+        let known: HashSet<IpAddr> = [target].into();
+        for banner in ["nginx/1.2.3.4", "build 1.2.3.4", "1.2.3.4.5 release"] {
+            assert_eq!(versions.redact_text(banner, &[], &known), None, "{}", banner);
+        }
+        let leaked = versions.redact_text("proxied for 192.168.7.1 and 10.1.2.3", &[], &known).unwrap();
+        assert!(!leaked.contains("192.168.7.1") && !leaked.contains("10.1.2.3"), "{}", leaked);
+        assert!(versions.redact_text("agent/10.1.2.3", &[], &known).is_some());
+        assert_eq!(parse_hex("00ff10"), Some(vec![0, 255, 16]));
+    }
+}
@@ -0,0 +1,434 @@
+//! SARIF 2.1.0 report generator
+//!
+//! Maps scan findings to SARIF results so scans can be uploaded to GitHub
+//! code scanning and other CI security dashboards. Each [`SarifRule`] matches
+//! open ports, banners, or both:
+//!
+//! - risky exposed services, e.g. Telnet, SMB or databases on an open port
+//! - weak services recognized from their banner, e.g. SSH protocol 1
+//! - known-vulnerable versions (CVE hits) recognized from their banner
+//!
+//! Versions come from banners only, so fixes backported by distributions
+//! are not visible; CVE results say the banner *suggests* the issue.
+//! Every result is located at the scanned host (`hosts/<ip>`) and carries a
+//! fingerprint of rule, host, port and protocol so dashboards can track it
+//! across scans.
+
+use crate::error::{ScanError, ScanResult};
+use crate::report::port_view::{port_view, PortEvidence};
+use crate::report::ScanReport;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::CompleteScanResult;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::fmt;
+use tracing::debug;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/deepskilling/RUSTSCAN";
+
+/// SARIF result level of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SarifLevel {
+    Note,
+    Warning,
+    Error,
+}
+
+impl SarifLevel {
+    /// GitHub's `security-severity` score: high, medium and low
+    fn security_severity(&self) -> &'static str {
+        match self {
+            SarifLevel::Error => "8.0",
+            SarifLevel::Warning => "5.0",
+            SarifLevel::Note => "2.0",
+        }
+    }
+}
+
+impl fmt::Display for SarifLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SarifLevel::Note => write!(f, "note"),
+            SarifLevel::Warning => write!(f, "warning"),
+            SarifLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Transport protocol a port rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleProtocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for RuleProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleProtocol::Tcp => write!(f, "tcp"),
+            RuleProtocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// A finding type and what triggers it
+#[derive(Debug, Clone)]
+pub struct SarifRule {
+    /// Stable rule ID, e.g. `NRMAP001` or a CVE ID
+    pub id: String,
+    /// Short PascalCase name, e.g. `CleartextTelnet`
+    pub name: String,
+    /// One-line description
+    pub description: String,
+    /// Remediation advice
+    pub help: String,
+    pub level: SarifLevel,
+    /// Open ports that trigger the rule; empty matches any open port
+    pub ports: Vec<(u16, RuleProtocol)>,
+    /// Pattern the whole banner must also match, for weak services and CVEs
+    pub banner: Option<Regex>,
+}
+
+impl SarifRule {
+    /// Rule triggered by any of `ports` being open
+    pub fn port(id: &str, name: &str, description: &str, help: &str, level: SarifLevel, ports: &[(u16, RuleProtocol)]) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            help: help.to_string(),
+            level,
+            ports: ports.to_vec(),
+            banner: None,
+        }
+    }
+
+    /// Rule triggered by a TCP banner matching `pattern`
+    pub fn banner(id: &str, name: &str, description: &str, help: &str, level: SarifLevel, pattern: &str) -> ScanResult<Self> {
+        let banner = Regex::new(pattern).map_err(|e| ScanError::validation_error("banner", e.to_string()))?;
+        Ok(Self {
+            banner: Some(banner),
+            ..Self::port(id, name, description, help, level, &[])
+        })
+    }
+
+    /// Why the rule fires for this port, if it does
+    fn matches(&self, evidence: &PortEvidence, banner: Option<&str>) -> Option<String> {
+        let protocol = self.ports.iter().find_map(|&(port, protocol)| {
+            let status = match protocol {
+                RuleProtocol::Tcp => evidence.tcp(),
+                RuleProtocol::Udp => evidence.udp.clone(),
+            };
+            (port == evidence.port && status == Some(PortStatus::Open)).then_some(protocol)
+        });
+        if !self.ports.is_empty() && protocol.is_none() {
+            return None;
+        }
+
+        match self.banner {
+            Some(ref pattern) => {
+                let banner = banner?;
+                let first_line = banner.lines().next().unwrap_or_default().trim();
+                pattern
+                    .is_match(banner)
+                    .then(|| format!("banner '{}' suggests {}", first_line, self.description))
+            }
+            None => Some(format!("{} ({}/{} open)", self.description, evidence.port, protocol?)),
+        }
+    }
+}
+
+/// Built-in rules: risky exposed services, weak services and CVE hits
+pub fn default_rules() -> Vec<SarifRule> {
+    use RuleProtocol::{Tcp, Udp};
+    use SarifLevel::{Error, Note, Warning};
+
+    let mut rules = vec![
+        SarifRule::port(
+            "NRMAP001",
+            "CleartextTelnet",
+            "Telnet exposes credentials in cleartext",
+            "Disable Telnet and use SSH instead.",
+            Error,
+            &[(23, Tcp)],
+        ),
+        SarifRule::port(
+            "NRMAP002",
+            "CleartextFtp",
+            "FTP exposes credentials in cleartext",
+            "Replace FTP with SFTP or FTPS, or restrict it to trusted networks.",
+            Warning,
+            &[(21, Tcp)],
+        ),
+        SarifRule::port(
+            "NRMAP003",
+            "ExposedSmb",
+            "SMB/NetBIOS file sharing is reachable",
+            "Block SMB (139, 445) at the network edge.",
+            Error,
+            &[(139, Tcp), (445, Tcp)],
+        ),
+        SarifRule::port(
+            "NRMAP004",
+            "ExposedRemoteDesktop",
+            "A remote desktop service (RDP or VNC) is reachable",
+            "Put remote desktop access behind a VPN or bastion.",
+            Warning,
+            &[(3389, Tcp), (5900, Tcp)],
+        ),
+        SarifRule::port(
+            "NRMAP005",
+            "ExposedDatabase",
+            "A database or cache service is reachable",
+            "Bind databases to private interfaces and firewall them from untrusted networks.",
+            Error,
+            &[(1433, Tcp), (1521, Tcp), (3306, Tcp), (5432, Tcp), (6379, Tcp), (9200, Tcp), (11211, Tcp), (27017, Tcp)],
+        ),
+        SarifRule::port(
+            "NRMAP006",
+            "ExposedSnmp",
+            "SNMP is reachable and may allow community-string guessing",
+            "Restrict SNMP to management networks and use SNMPv3.",
+            Warning,
+            &[(161, Udp)],
+        ),
+    ];
+
+    let banner_rules = [
+        (
+            "NRMAP101",
+            "SshProtocolVersion1",
+            "the server accepts the broken SSH protocol 1",
+            "Disable SSH protocol 1 on the server.",
+            Error,
+            r"^SSH-1\.(?:[0-8]|99)",
+        ),
+        (
+            "NRMAP102",
+            "ServiceVersionDisclosure",
+            "the service discloses its product version",
+            "Hide version details in service banners.",
+            Note,
+            r"(?m)^(?:SSH-[\d.]+-\S+?[_/]\d[\d.]*|Server: \S+/\d[\d.]*)",
+        ),
+        (
+            "CVE-2011-2523",
+            "VsftpdBackdoor",
+            "vsftpd 2.3.4 with a backdoor (CVE-2011-2523)",
+            "Reinstall vsftpd from a trusted source.",
+            Error,
+            r"vsFTPd 2\.3\.4\b",
+        ),
+        (
+            "CVE-2024-6387",
+            "OpenSshRegreSshion",
+            "an OpenSSH version affected by regreSSHion (CVE-2024-6387)",
+            "Upgrade to OpenSSH 9.8p1 or later, or confirm the distribution backported the fix.",
+            Warning,
+            r"OpenSSH_(?:8\.[5-9]|9\.[0-7])p1\b",
+        ),
+    ];
+    for (id, name, description, help, level, pattern) in banner_rules {
+        rules.push(SarifRule::banner(id, name, description, help, level, pattern).expect("built-in pattern"));
+    }
+    rules
+}
+
+/// SARIF report generator
+pub struct SarifReportGenerator {
+    rules: Vec<SarifRule>,
+}
+
+impl SarifReportGenerator {
+    /// Create a SARIF generator with the built-in rules
+    pub fn new() -> Self {
+        Self { rules: default_rules() }
+    }
+
+    /// Add a rule, e.g. a site-specific CVE check
+    pub fn with_rule(mut self, rule: SarifRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Rules results are reported against
+    pub fn rules(&self) -> &[SarifRule] {
+        &self.rules
+    }
+
+    /// Generate a SARIF log
+    ///
+    /// # Arguments
+    /// * `report` - The scan report to format
+    pub fn generate(&self, report: &ScanReport) -> ScanResult<String> {
+        debug!("Generating SARIF report");
+
+        let results: Vec<Value> = report.results.iter().flat_map(|r| self.host_results(r)).collect();
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.id,
+                    "name": rule.name,
+                    "shortDescription": { "text": rule.description },
+                    "help": { "text": rule.help },
+                    "defaultConfiguration": { "level": rule.level.to_string() },
+                    "properties": {
+                        "tags": ["security", "network"],
+                        "security-severity": rule.level.security_severity(),
+                    },
+                })
+            })
+            .collect();
+
+        let metadata = &report.metadata;
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "nrmap",
+                        "version": metadata.scanner_version,
+                        "informationUri": INFORMATION_URI,
+                        "rules": rules,
+                    }
+                },
+                "automationDetails": { "id": format!("nrmap/{}", metadata.scan_id) },
+                "invocations": [{
+                    "executionSuccessful": !metadata.cancelled,
+                    "startTimeUtc": metadata.start_time.with_timezone(&chrono::Utc).to_rfc3339(),
+                    "endTimeUtc": metadata.end_time.with_timezone(&chrono::Utc).to_rfc3339(),
+                }],
+                "results": results,
+            }]
+        });
+
+        serde_json::to_string_pretty(&log).map_err(|e| ScanError::OutputError {
+            message: format!("Failed to serialize report to SARIF: {}", e),
+        })
+    }
+
+    fn host_results(&self, result: &CompleteScanResult) -> Vec<Value> {
+        let mut results = Vec::new();
+        for evidence in port_view(result).iter().filter(|p| p.is_open()) {
+            let banner = result
+                .tcp_results
+                .iter()
+                .find(|r| r.port == evidence.port)
+                .and_then(|r| r.banner.as_deref());
+
+            for (index, rule) in self.rules.iter().enumerate() {
+                let Some(message) = rule.matches(evidence, banner) else {
+                    continue;
+                };
+                let protocol = if evidence.tcp() == Some(PortStatus::Open) { "tcp" } else { "udp" };
+                let location = format!("{}:{}/{}", result.target, evidence.port, protocol);
+                results.push(json!({
+                    "ruleId": rule.id,
+                    "ruleIndex": index,
+                    "level": rule.level.to_string(),
+                    "message": { "text": format!("{}: {}", location, message) },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": format!("hosts/{}", result.target) }
+                        },
+                        "logicalLocations": [{ "name": location, "kind": "resource" }],
+                    }],
+                    "partialFingerprints": { "nrmapFinding/v1": format!("{}|{}", rule.id, location) },
+                }));
+            }
+        }
+        results
+    }
+}
+
+impl Default for SarifReportGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportBuilder;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use crate::scanner::ProbeError;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn report(ports: &[(u16, Option<&str>)]) -> ScanReport {
+        let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+        result.tcp_results = ports
+            .iter()
+            .map(|&(port, banner)| {
+                let mut open = TcpConnectResult::open(target, port);
+                open.banner = banner.map(str::to_string);
+                open
+            })
+            .collect();
+        result.tcp_results.push(TcpConnectResult::failed(target, 445, ProbeError::Refused));
+
+        ReportBuilder::new("test-sarif".to_string())
+            .add_results(vec![result])
+            .complete()
+            .build()
+            .unwrap()
+    }
+
+    fn rule_ids(log: &Value) -> Vec<&str> {
+        log["runs"][0]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["ruleId"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_sarif_log() {
+        let sarif = SarifReportGenerator::new().generate(&report(&[(23, None)])).unwrap();
+        let log: Value = serde_json::from_str(&sarif).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "nrmap");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), default_rules().len());
+        assert_eq!(rule_ids(&log), vec!["NRMAP001"]);
+
+        let result = &run["results"][0];
+        assert_eq!(result["level"], "error");
+        assert_eq!(run["tool"]["driver"]["rules"][result["ruleIndex"].as_u64().unwrap() as usize]["id"], "NRMAP001");
+        assert_eq!(result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "hosts/10.0.0.1");
+        assert_eq!(result["partialFingerprints"]["nrmapFinding/v1"], "NRMAP001|10.0.0.1:23/tcp");
+    }
+
+    #[test]
+    fn test_banner_rules() {
+        let report = report(&[
+            (22, Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n")),
+            (2222, Some("SSH-1.5-OldSSH\r\n")),
+            (21, Some("220 (vsFTPd 2.3.4)\r\n")),
+            (8080, Some("HTTP/1.1 200 OK\r\nServer: nginx/1.24.0\r\n")),
+        ]);
+        let log: Value = serde_json::from_str(&SarifReportGenerator::new().generate(&report).unwrap()).unwrap();
+        assert_eq!(
+            rule_ids(&log),
+            vec!["NRMAP002", "CVE-2011-2523", "NRMAP102", "CVE-2024-6387", "NRMAP101", "NRMAP102"]
+        );
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        let rule = SarifRule::banner("SITE-1", "LegacyApp", "legacy app build", "Retire it.", SarifLevel::Warning, "^AppServer/1\\.")
+            .unwrap();
+        let generator = SarifReportGenerator::new().with_rule(rule);
+        let log: Value = serde_json::from_str(&generator.generate(&report(&[(9000, Some("AppServer/1.2"))])).unwrap()).unwrap();
+        assert_eq!(rule_ids(&log), vec!["SITE-1"]);
+        assert!(SarifRule::banner("X", "X", "x", "x", SarifLevel::Note, "(").is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Report output sinks
+//!
+//! An [`OutputSink`] receives a finished report and renders it somewhere: a
+//! file, stdout, or several places at once through a [`SinkList`]. The CLI's
+//! repeatable `--output FORMAT=PATH` flag builds one sink per destination so a
+//! single scan can be rendered as JSON, HTML and a terminal table together;
+//! `template=PATH` outputs render through the `--template` file. A list with
+//! a [`Redactor`] (`--redact`) renders only the redacted report.
+
+use crate::error::{ScanError, ScanResult};
+use crate::report::{stream, Redactor, ReportFormat, ScanReport, SpooledReport, TemplateReportGenerator};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Destination that renders finished reports
+pub trait OutputSink: Send {
+    /// Render the report to this sink
+    fn write_report(&mut self, report: &ScanReport) -> ScanResult<()>;
+
+    /// Render a report whose results are spooled to disk
+    ///
+    /// The default loads every result into memory; sinks that can stream
+    /// should read them from the spool instead.
+    fn write_spooled(&mut self, report: &SpooledReport) -> ScanResult<()> {
+        self.write_report(&report.load()?)
+    }
+
+    /// Human-readable description, used in logs and errors
+    fn describe(&self) -> String;
+
+    /// Where the report goes, for sinks writing to a single destination
+    fn destination(&self) -> Option<&OutputDestination> {
+        None
+    }
+}
+
+/// Where a [`FormatSink`] writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputDestination {
+    Stdout,
+    File(PathBuf),
+}
+
+impl OutputDestination {
+    fn write_with<F>(&self, format: ReportFormat, write: F) -> ScanResult<()>
+    where
+        F: FnOnce(&mut dyn std::io::Write) -> ScanResult<()>,
+    {
+        match self {
+            OutputDestination::Stdout => {
+                write(&mut std::io::stdout().lock())?;
+                // Line formats already end every line
+                if !matches!(format, ReportFormat::Jsonl | ReportFormat::List) {
+                    println!();
+                }
+            }
+            OutputDestination::File(path) => {
+                let file = std::fs::File::create(path).map_err(|e| ScanError::OutputError {
+                    message: format!("Failed to write report to {}: {}", path.display(), e),
+                })?;
+                write(&mut std::io::BufWriter::new(file))?;
+                info!("Report saved to: {}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for OutputDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputDestination::Stdout => write!(f, "-"),
+            OutputDestination::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Sink rendering one report format to stdout or a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSink {
+    pub format: ReportFormat,
+    pub destination: OutputDestination,
+}
+
+impl FormatSink {
+    /// Create a sink for a format and destination
+    pub fn new(format: ReportFormat, destination: OutputDestination) -> Self {
+        Self { format, destination }
+    }
+}
+
+/// Parses `FORMAT=PATH`, where a PATH of `-` means stdout, or a bare PATH
+/// whose extension names the format (e.g. `merged.html`)
+impl FromStr for FormatSink {
+    type Err = ScanError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let Some((format, path)) = spec.split_once('=') else {
+            let path = PathBuf::from(spec.trim());
+            return path
+                .extension()
+                .and_then(|ext| ext.to_str()?.parse().ok())
+                .map(|format| Self::new(format, OutputDestination::File(path.clone())))
+                .ok_or_else(|| {
+                    ScanError::validation_error("output", format!("Expected FORMAT=PATH, got '{}'", spec))
+                });
+        };
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(ScanError::validation_error(
+                "output",
+                format!("Missing path in '{}' (use '-' for stdout)", spec),
+            ));
+        }
+
+        let destination = match path {
+            "-" => OutputDestination::Stdout,
+            path => OutputDestination::File(PathBuf::from(path)),
+        };
+        Ok(Self::new(format.trim().parse()?, destination))
+    }
+}
+
+impl OutputSink for FormatSink {
+    fn write_report(&mut self, report: &ScanReport) -> ScanResult<()> {
+        self.destination
+            .write_with(self.format, |writer| stream::write_report(report, self.format, writer))
+    }
+
+    fn write_spooled(&mut self, report: &SpooledReport) -> ScanResult<()> {
+        self.destination
+            .write_with(self.format, |writer| stream::write_spooled(report, self.format, writer))
+    }
+
+    fn describe(&self) -> String {
+        format!("{}={}", self.format, self.destination)
+    }
+
+    fn destination(&self) -> Option<&OutputDestination> {
+        Some(&self.destination)
+    }
+}
+
+/// Sink rendering a user template to stdout or a file
+///
+/// Templates see the whole report, so spooled results are loaded first.
+#[derive(Debug, Clone)]
+pub struct TemplateSink {
+    pub template: TemplateReportGenerator,
+    pub destination: OutputDestination,
+}
+
+impl TemplateSink {
+    /// Create a sink for a template and destination
+    pub fn new(template: TemplateReportGenerator, destination: OutputDestination) -> Self {
+        Self { template, destination }
+    }
+}
+
+impl OutputSink for TemplateSink {
+    fn write_report(&mut self, report: &ScanReport) -> ScanResult<()> {
+        let text = self.template.generate(report)?;
+        self.destination
+            .write_with(ReportFormat::Template, |writer| writer.write_all(text.as_bytes()).map_err(stream::write_error))
+    }
+
+    fn describe(&self) -> String {
+        format!("{}={}", ReportFormat::Template, self.destination)
+    }
+
+    fn destination(&self) -> Option<&OutputDestination> {
+        Some(&self.destination)
+    }
+}
+
+/// Fans a report out to several sinks
+///
+/// Every sink is attempted even if an earlier one fails, so a bad output path
+/// does not cost the other formats; the first error is returned.
+#[derive(Default)]
+pub struct SinkList {
+    sinks: Vec<Box<dyn OutputSink>>,
+    redactor: Option<Redactor>,
+}
+
+impl SinkList {
+    /// Create an empty sink list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a sink list from `FORMAT=PATH` specs
+    ///
+    /// At most one sink may write to stdout, since interleaved formats would
+    /// be unreadable.
+    pub fn from_specs<S: AsRef<str>>(specs: &[S]) -> ScanResult<Self> {
+        Self::from_specs_with_template(specs, None)
+    }
+
+    /// Build a sink list from `FORMAT=PATH` specs, rendering `template`
+    /// outputs through `template`
+    pub fn from_specs_with_template<S: AsRef<str>>(
+        specs: &[S],
+        template: Option<&TemplateReportGenerator>,
+    ) -> ScanResult<Self> {
+        let sinks: Vec<FormatSink> = specs.iter().map(|s| s.as_ref().parse()).collect::<ScanResult<_>>()?;
+        if sinks.iter().filter(|s| s.destination == OutputDestination::Stdout).count() > 1 {
+            return Err(ScanError::validation_error("output", "Only one output may write to stdout"));
+        }
+
+        sinks.into_iter().try_fold(Self::new(), |list, sink| match (sink.format, template) {
+            (ReportFormat::Template, Some(template)) => {
+                Ok(list.with_sink(TemplateSink::new(template.clone(), sink.destination)))
+            }
+            (ReportFormat::Template, None) => Err(stream::missing_template()),
+            _ => Ok(list.with_sink(sink)),
+        })
+    }
+
+    /// Add a sink
+    pub fn with_sink(mut self, sink: impl OutputSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Redact reports before any sink renders them
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Number of sinks
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    /// Whether the list has no sinks
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Whether any sink writes to stdout
+    pub fn writes_stdout(&self) -> bool {
+        self.sinks.iter().any(|sink| sink.destination() == Some(&OutputDestination::Stdout))
+    }
+
+    /// Files the report is written to
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.sinks
+            .iter()
+            .filter_map(|sink| match sink.destination() {
+                Some(OutputDestination::File(path)) => Some(path.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl OutputSink for SinkList {
+    fn write_report(&mut self, report: &ScanReport) -> ScanResult<()> {
+        let redacted = match self.redactor {
+            Some(ref mut redactor) => Some(redactor.redact(report)?),
+            None => None,
+        };
+        let report = redacted.as_ref().unwrap_or(report);
+        let mut first_error = None;
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.write_report(report) {
+                warn!("Output {} failed: {}", sink.describe(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn write_spooled(&mut self, report: &SpooledReport) -> ScanResult<()> {
+        // Redaction needs the whole report in memory
+        if self.redactor.is_some() {
+            return self.write_report(&report.load()?);
+        }
+        let mut first_error = None;
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.write_spooled(report) {
+                warn!("Output {} failed: {}", sink.describe(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn describe(&self) -> String {
+        self.sinks.iter().map(|s| s.describe()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportBuilder;
+
+    fn report() -> ScanReport {
+        ReportBuilder::new("sink-test".to_string()).complete().build().unwrap()
+    }
+
+    #[test]
+    fn test_parse_format_sink() {
+        let sink: FormatSink = "json=scan.json".parse().unwrap();
+        assert_eq!(sink.format, ReportFormat::Json);
+        assert_eq!(sink.destination, OutputDestination::File(PathBuf::from("scan.json")));
+
+        let sink: FormatSink = "table=-".parse().unwrap();
+        assert_eq!(sink.destination, OutputDestination::Stdout);
+        assert_eq!(sink.describe(), "table=-");
+
+        let sink: FormatSink = "merged.html".parse().unwrap();
+        assert_eq!(sink.format, ReportFormat::Html);
+        assert_eq!(sink.destination, OutputDestination::File(PathBuf::from("merged.html")));
+
+        assert!("json".parse::<FormatSink>().is_err());
+        assert!("scan.xml".parse::<FormatSink>().is_err());
+        assert!("json=".parse::<FormatSink>().is_err());
+        assert!("xml=out.xml".parse::<FormatSink>().is_err());
+    }
+
+    #[test]
+    fn test_sink_list_writes_every_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("scan.json");
+        let html = dir.path().join("scan.html");
+        let specs = [format!("json={}", json.display()), format!("html={}", html.display())];
+
+        let mut sinks = SinkList::from_specs(&specs).unwrap();
+        assert_eq!(sinks.len(), 2);
+        assert_eq!(sinks.files(), vec![json.clone(), html.clone()]);
+        assert!(!sinks.writes_stdout());
+        sinks.write_report(&report()).unwrap();
+
+        let parsed: ScanReport = serde_json::from_slice(&std::fs::read(&json).unwrap()).unwrap();
+        assert_eq!(parsed.metadata.scan_id, "sink-test");
+        assert!(std::fs::read_to_string(&html).unwrap().contains("<html"));
+    }
+
+    #[test]
+    fn test_sink_list_continues_after_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("scan.yaml");
+        let specs = [
+            format!("json={}", dir.path().join("missing/scan.json").display()),
+            format!("yaml={}", good.display()),
+        ];
+
+        let mut sinks = SinkList::from_specs(&specs).unwrap();
+        assert!(sinks.write_report(&report()).is_err());
+        assert!(good.exists());
+    }
+
+    #[test]
+    fn test_template_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ticket.md");
+        let specs = [format!("template={}", path.display())];
+        assert!(SinkList::from_specs(&specs).is_err());
+
+        let template = TemplateReportGenerator::new("Scan {{metadata.scan_id}}", false).unwrap();
+        let mut sinks = SinkList::from_specs_with_template(&specs, Some(&template)).unwrap();
+        assert_eq!(sinks.describe(), specs[0]);
+        sinks.write_report(&report()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Scan sink-test");
+    }
+
+    #[test]
+    fn test_single_stdout_sink() {
+        assert!(SinkList::from_specs(&["table=-", "json=-"]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! On-disk result spooling for huge scans
+//!
+//! Holding every host's result in memory until the report is built does not
+//! scale to scans of millions of addresses. A [`ResultSpool`] appends each
+//! result as one JSON line to segment files in a spool directory as soon as
+//! the host is done; the report summary is then computed and the report
+//! written by reading the segments back one result at a time.
+
+use crate::error::{ScanError, ScanResult};
+use crate::report::ScanReport;
+use crate::scanner::CompleteScanResult;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Writes results to JSON Lines segment files
+pub struct ResultSpool {
+    dir: PathBuf,
+    segment_results: usize,
+    segments: Vec<PathBuf>,
+    writer: Option<BufWriter<File>>,
+    in_segment: usize,
+    len: usize,
+}
+
+impl ResultSpool {
+    /// Spool into `dir`, starting a new segment every `segment_results` results
+    pub fn create<P: AsRef<Path>>(dir: P, segment_results: usize) -> ScanResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            segment_results: segment_results.max(1),
+            segments: Vec::new(),
+            writer: None,
+            in_segment: 0,
+            len: 0,
+        })
+    }
+
+    /// Append a result
+    pub fn push(&mut self, result: &CompleteScanResult) -> ScanResult<()> {
+        let writer = match self.writer {
+            Some(ref mut writer) if self.in_segment < self.segment_results => writer,
+            _ => self.rotate()?,
+        };
+        serde_json::to_writer(&mut *writer, result).map_err(|e| ScanError::OutputError {
+            message: format!("Failed to spool result for {}: {}", result.target, e),
+        })?;
+        writer.write_all(b"\n")?;
+        self.in_segment += 1;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> ScanResult<&mut BufWriter<File>> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        let path = self.dir.join(format!("results-{:05}.jsonl", self.segments.len()));
+        debug!("Spooling results to {:?}", path);
+        let file = File::create(&path)?;
+        self.segments.push(path);
+        self.in_segment = 0;
+        Ok(self.writer.insert(BufWriter::new(file)))
+    }
+
+    /// Number of spooled results
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing was spooled yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Flush the last segment and hand over the spooled results
+    pub fn finish(mut self) -> ScanResult<SpooledResults> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(SpooledResults {
+            dir: self.dir,
+            segments: self.segments,
+            len: self.len,
+        })
+    }
+}
+
+/// Results written by a [`ResultSpool`], read back lazily
+#[derive(Debug, Clone)]
+pub struct SpooledResults {
+    dir: PathBuf,
+    segments: Vec<PathBuf>,
+    len: usize,
+}
+
+impl SpooledResults {
+    /// Number of spooled results
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the spool is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Segment files in write order
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Read the results back in spooling order, one at a time
+    pub fn iter(&self) -> impl Iterator<Item = ScanResult<CompleteScanResult>> + '_ {
+        self.segments.iter().flat_map(|path| read_segment(path))
+    }
+
+    /// Delete the segment files, and the spool directory if that leaves it empty
+    pub fn remove(self) -> ScanResult<()> {
+        for segment in &self.segments {
+            fs::remove_file(segment)?;
+        }
+        // Another run may still be using the directory
+        let _ = fs::remove_dir(&self.dir);
+        Ok(())
+    }
+}
+
+fn read_segment(path: &Path) -> Box<dyn Iterator<Item = ScanResult<CompleteScanResult>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Box::new(std::iter::once(Err(e.into()))),
+    };
+    let path = path.to_path_buf();
+    Box::new(BufReader::new(file).lines().map(move |line| {
+        serde_json::from_str(&line?).map_err(|e| ScanError::OutputError {
+            message: format!("Corrupt spool segment {}: {}", path.display(), e),
+        })
+    }))
+}
+
+/// A report whose results stay in the spool
+///
+/// `report` carries the metadata, summary and statistics computed while
+/// reading the spool; its result list is empty.
+#[derive(Debug, Clone)]
+pub struct SpooledReport {
+    pub report: ScanReport,
+    pub results: SpooledResults,
+}
+
+impl SpooledReport {
+    /// The report's results, without hosts skipped by exclusion rules
+    pub fn results(&self) -> impl Iterator<Item = ScanResult<CompleteScanResult>> + '_ {
+        self.results
+            .iter()
+            .filter(|result| !matches!(result, Ok(result) if result.excluded_by.is_some()))
+    }
+
+    /// Read every result into an in-memory report
+    pub fn load(&self) -> ScanResult<ScanReport> {
+        let mut report = self.report.clone();
+        report.results = self.results().collect::<ScanResult<_>>()?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+
+    fn result(last: u8) -> CompleteScanResult {
+        let target = std::net::IpAddr::from([10, 0, 0, last]);
+        CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO)
+    }
+
+    #[test]
+    fn test_round_trip_across_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool_dir = dir.path().join("scan");
+        let mut spool = ResultSpool::create(&spool_dir, 2).unwrap();
+        for last in 1..=5 {
+            spool.push(&result(last)).unwrap();
+        }
+
+        let spooled = spool.finish().unwrap();
+        assert_eq!(spooled.len(), 5);
+        assert_eq!(spooled.segments().len(), 3);
+        let targets: Vec<_> = spooled.iter().map(|r| r.unwrap().target.to_string()).collect();
+        assert_eq!(targets, ["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4", "10.0.0.5"]);
+
+        spooled.remove().unwrap();
+        assert!(!spool_dir.exists());
+    }
+
+    #[test]
+    fn test_corrupt_segment_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = ResultSpool::create(dir.path(), 10).unwrap();
+        spool.push(&result(1)).unwrap();
+        let spooled = spool.finish().unwrap();
+        fs::write(&spooled.segments()[0], "{not json\n").unwrap();
+        assert!(spooled.iter().next().unwrap().is_err());
+    }
+}
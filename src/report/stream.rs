@@ -0,0 +1,439 @@
+//! Incremental report serialization
+//!
+//! Produces a report as a sequence of chunks (header, one chunk per host
+//! result, footer) so large result sets can be written to sockets, chunked
+//! HTTP bodies or files without first building the whole document in memory.
+//! Chunks are generated lazily, so a slow consumer naturally applies
+//! backpressure to serialization.
+//!
+//! JSON Lines output has no header or footer: every chunk is one host
+//! result on its own line, so it can be piped into other tools as hosts
+//! finish.
+//!
+//! Template reports need the user's template, so they are rendered by
+//! [`crate::report::TemplateReportGenerator`] rather than streamed.
+
+use crate::error::{ScanError, ScanResult};
+use crate::policy::ComplianceReport;
+use crate::report::{
+    masscan, HtmlReportGenerator, JunitReportGenerator, MarkdownReportGenerator, ReportFormat, ReportMetadata,
+    ReportStatistics, ReportSummary, SarifReportGenerator, ScanReport, SpooledReport, TableReportGenerator,
+};
+use crate::scanner::CompleteScanResult;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::iter::Peekable;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Everything in a report except the results
+#[derive(Serialize)]
+struct ReportHeader<'a> {
+    metadata: &'a ReportMetadata,
+    summary: &'a ReportSummary,
+    statistics: &'a ReportStatistics,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compliance: Option<&'a ComplianceReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Header,
+    Results(usize),
+    /// Footer after this many results
+    Footer(usize),
+    Done,
+}
+
+type ResultIter<'a> = Box<dyn Iterator<Item = ScanResult<Cow<'a, CompleteScanResult>>> + 'a>;
+
+/// Lazy iterator over the serialized chunks of a report
+///
+/// JSON and YAML are streamed per result; other formats are rendered in one
+/// piece and yielded as a single chunk.
+pub struct ReportChunks<'a> {
+    report: &'a ScanReport,
+    format: ReportFormat,
+    stage: Stage,
+    results: Peekable<ResultIter<'a>>,
+}
+
+impl<'a> ReportChunks<'a> {
+    /// Create a chunk iterator for a report
+    pub fn new(report: &'a ScanReport, format: ReportFormat) -> Self {
+        let results: ResultIter<'a> = Box::new(report.results.iter().map(|result| Ok(Cow::Borrowed(result))));
+        Self {
+            report,
+            format,
+            stage: Stage::Header,
+            results: results.peekable(),
+        }
+    }
+
+    /// Create a chunk iterator whose results come from `results` instead of
+    /// the report's result list, e.g. read back from a spool
+    ///
+    /// HTML and table output are still rendered from `report` alone, so they
+    /// need its result list filled in.
+    pub fn with_results<I>(report: &'a ScanReport, format: ReportFormat, results: I) -> Self
+    where
+        I: Iterator<Item = ScanResult<CompleteScanResult>> + 'a,
+    {
+        let results: ResultIter<'a> = Box::new(results.map(|result| result.map(Cow::Owned)));
+        Self {
+            report,
+            format,
+            stage: Stage::Header,
+            results: results.peekable(),
+        }
+    }
+
+    fn header(&mut self) -> ScanResult<Vec<u8>> {
+        let header = ReportHeader {
+            metadata: &self.report.metadata,
+            summary: &self.report.summary,
+            statistics: &self.report.statistics,
+            compliance: self.report.compliance.as_ref(),
+        };
+
+        match self.format {
+            ReportFormat::Jsonl => Ok(Vec::new()),
+            ReportFormat::List => Ok(masscan::LIST_HEADER.as_bytes().to_vec()),
+            ReportFormat::Json | ReportFormat::JsonPretty => {
+                let pretty = self.format == ReportFormat::JsonPretty;
+                let mut json = if pretty {
+                    serde_json::to_string_pretty(&header)
+                } else {
+                    serde_json::to_string(&header)
+                }
+                .map_err(json_error)?;
+
+                // Reopen the object to append the results array
+                json.truncate(json.trim_end().len() - 1);
+                let json = json.trim_end().to_string();
+                Ok(if pretty {
+                    format!("{},\n  \"results\": [", json).into_bytes()
+                } else {
+                    format!("{},\"results\":[", json).into_bytes()
+                })
+            }
+            ReportFormat::Yaml => {
+                let mut yaml = serde_yaml::to_string(&header).map_err(yaml_error)?;
+                yaml.push_str(if self.results.peek().is_none() {
+                    "results: []\n"
+                } else {
+                    "results:\n"
+                });
+                Ok(yaml.into_bytes())
+            }
+            ReportFormat::Html => Ok(HtmlReportGenerator::new().generate(self.report)?.into_bytes()),
+            ReportFormat::Table => Ok(TableReportGenerator::new().generate(self.report)?.into_bytes()),
+            ReportFormat::Markdown => Ok(MarkdownReportGenerator::new().generate(self.report)?.into_bytes()),
+            ReportFormat::Sarif => Ok(SarifReportGenerator::new().generate(self.report)?.into_bytes()),
+            ReportFormat::Junit => Ok(JunitReportGenerator::new().generate(self.report)?.into_bytes()),
+            ReportFormat::Template => Err(missing_template()),
+        }
+    }
+
+    fn result(&self, index: usize, result: &CompleteScanResult) -> ScanResult<Vec<u8>> {
+        match self.format {
+            ReportFormat::Json => {
+                let mut chunk = if index > 0 { vec![b','] } else { Vec::new() };
+                serde_json::to_writer(&mut chunk, result).map_err(json_error)?;
+                Ok(chunk)
+            }
+            ReportFormat::JsonPretty => {
+                let json = serde_json::to_string_pretty(result).map_err(json_error)?;
+                let separator = if index > 0 { "," } else { "" };
+                Ok(format!("{}\n{}", separator, indent(&json, "    ")).into_bytes())
+            }
+            ReportFormat::Jsonl => {
+                let mut line = serde_json::to_vec(result).map_err(json_error)?;
+                line.push(b'\n');
+                Ok(line)
+            }
+            ReportFormat::List => {
+                let finished = self.report.metadata.end_time.timestamp();
+                Ok(masscan::list_lines(result, finished).into_bytes())
+            }
+            ReportFormat::Yaml => {
+                let yaml = serde_yaml::to_string(std::slice::from_ref(result)).map_err(yaml_error)?;
+                Ok(yaml.into_bytes())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn footer(&self, results: usize) -> Vec<u8> {
+        match self.format {
+            ReportFormat::Json => b"]}".to_vec(),
+            ReportFormat::JsonPretty if results == 0 => b"]\n}".to_vec(),
+            ReportFormat::JsonPretty => b"\n  ]\n}".to_vec(),
+            ReportFormat::List => masscan::LIST_FOOTER.as_bytes().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Iterator for ReportChunks<'_> {
+    type Item = ScanResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let templated = rendered_whole(self.format);
+
+        match self.stage {
+            Stage::Header => {
+                self.stage = if templated { Stage::Done } else { Stage::Results(0) };
+                Some(self.header())
+            }
+            Stage::Results(index) => match self.results.next() {
+                Some(Ok(result)) => {
+                    self.stage = Stage::Results(index + 1);
+                    Some(self.result(index, &result))
+                }
+                Some(Err(e)) => {
+                    self.stage = Stage::Done;
+                    Some(Err(e))
+                }
+                None => {
+                    self.stage = Stage::Footer(index);
+                    self.next()
+                }
+            },
+            Stage::Footer(results) => {
+                self.stage = Stage::Done;
+                let footer = self.footer(results);
+                if footer.is_empty() {
+                    None
+                } else {
+                    Some(Ok(footer))
+                }
+            }
+            Stage::Done => None,
+        }
+    }
+}
+
+/// Write a report chunk by chunk to a blocking writer
+pub fn write_report<W: std::io::Write>(report: &ScanReport, format: ReportFormat, mut writer: W) -> ScanResult<()> {
+    for chunk in ReportChunks::new(report, format) {
+        writer.write_all(&chunk?).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Write a spooled report chunk by chunk, reading results back from the spool
+///
+/// Formats other than JSON and YAML are rendered from the whole result set,
+/// so for those the spool is loaded into memory first.
+pub fn write_spooled<W: std::io::Write>(report: &SpooledReport, format: ReportFormat, mut writer: W) -> ScanResult<()> {
+    if rendered_whole(format) {
+        return write_report(&report.load()?, format, writer);
+    }
+    for chunk in ReportChunks::with_results(&report.report, format, report.results()) {
+        writer.write_all(&chunk?).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Write a report chunk by chunk to an async writer
+///
+/// Each chunk is serialized only after the previous one has been accepted by
+/// the writer, so memory use stays bounded by the largest single result.
+pub async fn write_report_async<W: AsyncWrite + Unpin>(
+    report: &ScanReport,
+    format: ReportFormat,
+    writer: &mut W,
+) -> ScanResult<()> {
+    for chunk in ReportChunks::new(report, format) {
+        writer.write_all(&chunk?).await.map_err(write_error)?;
+    }
+    writer.flush().await.map_err(write_error)
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", prefix, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn json_error(e: serde_json::Error) -> ScanError {
+    ScanError::OutputError {
+        message: format!("Failed to serialize report to JSON: {}", e),
+    }
+}
+
+fn yaml_error(e: serde_yaml::Error) -> ScanError {
+    ScanError::OutputError {
+        message: format!("Failed to serialize report to YAML: {}", e),
+    }
+}
+
+/// Whether a format is rendered from the whole report rather than per result
+fn rendered_whole(format: ReportFormat) -> bool {
+    !matches!(
+        format,
+        ReportFormat::Json | ReportFormat::JsonPretty | ReportFormat::Jsonl | ReportFormat::List | ReportFormat::Yaml
+    )
+}
+
+pub(crate) fn missing_template() -> ScanError {
+    ScanError::validation_error("format", "Template reports need a template file (--template)")
+}
+
+pub(crate) fn write_error(e: std::io::Error) -> ScanError {
+    ScanError::OutputError {
+        message: format!("Failed to write report: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportBuilder;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn report(hosts: u8) -> ScanReport {
+        let results = (1..=hosts)
+            .map(|last| {
+                let target = IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+                CompleteScanResult {
+                    target,
+                    host_status: HostStatus::Up,
+                    tcp_results: vec![TcpConnectResult {
+                        target,
+                        port: 22,
+                        status: PortStatus::Open,
+                        response_time_ms: Some(3),
+                        banner: None,
+                        evasion: None,
+                        attempts: 1,
+                        error: None,
+                        service: None,
+                        error_class: None,
+                        confidence: None,
+                        reason: None,
+                    }],
+                    syn_results: vec![],
+                    udp_results: vec![],
+                    scan_duration_ms: 10,
+                    throttle_stats: None,
+                    geo: None,
+                    hostname: None,
+                    excluded_by: None,
+                    discovered_hostnames: Vec::new(),
+                    tls_certificates: Vec::new(),
+                    os_matches: Vec::new(),
+                    down_reason: None,
+                    port_verifications: Vec::new(),
+                    liveness: Vec::new(),
+                    deception: None,
+                    latency: None,
+                    phase_timings: None,
+                    dropped_ports: None,
+                    expansion: None,
+                    mac: None,
+                    icmp_rate_limit: None,
+                }
+            })
+            .collect();
+
+        ReportBuilder::new("stream-test".to_string())
+            .add_results(results)
+            .complete()
+            .build()
+            .unwrap()
+    }
+
+    fn collect(report: &ScanReport, format: ReportFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_report(report, format, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_json_stream_matches_report() {
+        for hosts in [0, 1, 3] {
+            let report = report(hosts);
+            let expected = serde_json::to_value(&report).unwrap();
+            for format in [ReportFormat::Json, ReportFormat::JsonPretty] {
+                let streamed: serde_json::Value = serde_json::from_slice(&collect(&report, format)).unwrap();
+                assert_eq!(streamed, expected, "{} with {} hosts", format, hosts);
+            }
+        }
+    }
+
+    #[test]
+    fn test_yaml_stream_round_trips() {
+        for hosts in [0, 2] {
+            let report = report(hosts);
+            let parsed: ScanReport = serde_yaml::from_slice(&collect(&report, ReportFormat::Yaml)).unwrap();
+            assert_eq!(parsed.results.len(), hosts as usize);
+            assert_eq!(parsed.metadata.scan_id, "stream-test");
+        }
+    }
+
+    #[test]
+    fn test_jsonl_one_line_per_result() {
+        let report = report(3);
+        let out = String::from_utf8(collect(&report, ReportFormat::Jsonl)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (line, expected) in lines.iter().zip(&report.results) {
+            let parsed: CompleteScanResult = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed.target, expected.target);
+        }
+        assert!(collect(&self::report(0), ReportFormat::Jsonl).is_empty());
+    }
+
+    #[test]
+    fn test_masscan_list() {
+        let out = String::from_utf8(collect(&report(2), ReportFormat::List)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "#masscan");
+        assert!(lines[1].starts_with("open tcp 22 10.0.0.1 "));
+        assert_eq!(lines[3], "# end");
+    }
+
+    #[test]
+    fn test_one_chunk_per_result() {
+        let report = report(5);
+        assert_eq!(ReportChunks::new(&report, ReportFormat::Json).count(), 7);
+        assert_eq!(ReportChunks::new(&report, ReportFormat::Table).count(), 1);
+    }
+
+    #[test]
+    fn test_spooled_stream_matches_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = report(3);
+        let mut spool = crate::report::ResultSpool::create(dir.path(), 2).unwrap();
+        for result in &report.results {
+            spool.push(result).unwrap();
+        }
+        let spooled = ReportBuilder::new("stream-test".to_string())
+            .complete()
+            .build_spooled(spool.finish().unwrap())
+            .unwrap();
+        assert!(spooled.report.results.is_empty());
+        assert_eq!(spooled.report.summary.total_open_ports, 3);
+
+        for format in [ReportFormat::Json, ReportFormat::JsonPretty, ReportFormat::Yaml] {
+            let mut out = Vec::new();
+            write_spooled(&spooled, format, &mut out).unwrap();
+            let parsed: ScanReport = serde_yaml::from_slice(&out).unwrap();
+            assert_eq!(parsed.results.len(), 3, "{}", format);
+            assert_eq!(parsed.summary.total_targets, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_report_async() {
+        let report = report(2);
+        let mut out = Vec::new();
+        write_report_async(&report, ReportFormat::Json, &mut out).await.unwrap();
+        assert_eq!(out, collect(&report, ReportFormat::Json));
+    }
+}
@@ -3,6 +3,9 @@
 /// This module generates formatted ASCII table reports for scan results.
 
 use crate::error::ScanResult;
+use crate::policy::ComplianceReport;
+use crate::report::port_view::{open_port_labels, port_view, PortEvidence};
+use crate::report::time::{format_duration_ms, format_duration_secs, format_timestamp};
 use crate::report::ScanReport;
 use crate::scanner::host_discovery::HostStatus;
 use crate::scanner::tcp_connect::PortStatus;
@@ -40,6 +43,26 @@ impl TableReportGenerator {
         
         // Results table
         output.push_str(&self.generate_results_table(&report));
+
+        // Open ports with every scan type's verdict side by side
+        if report.results.iter().any(|r| port_view(r).iter().any(PortEvidence::is_open)) {
+            output.push_str(&self.generate_ports_table(report));
+        }
+
+        // Per-phase timing, when hosts recorded it
+        if report.results.iter().any(|r| r.phase_timings.is_some()) {
+            output.push_str(&self.generate_timing_table(report));
+        }
+
+        // Why each port got its state (`--reason`)
+        if report.results.iter().any(|r| r.has_reasons()) {
+            output.push_str(&self.generate_reasons_table(report));
+        }
+
+        // Policy rules the scan was checked against
+        if let Some(ref compliance) = report.compliance {
+            output.push_str(&self.generate_compliance_table(compliance));
+        }
         
         Ok(output)
     }
@@ -60,22 +83,26 @@ r#"METADATA
 │ Scanner Version:    {}
 │ Start Time:         {}
 │ End Time:           {}
-│ Duration:           {:.2} seconds
+│ Duration:           {}
 │ Targets:            {}
 │ Ports:              {}
 │ Scan Types:         {}
+│ Excluded Hosts:     {}
+│ Origin:             {}
 {}
 
 "#,
             "┌───────────────────────────────────────────────────────────────────────┐",
             report.metadata.scan_id,
             report.metadata.scanner_version,
-            report.metadata.start_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            report.metadata.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
-            report.metadata.duration_seconds,
+            format_timestamp(&report.metadata.start_time),
+            format_timestamp(&report.metadata.end_time),
+            format_duration_secs(report.metadata.duration_seconds),
             report.metadata.scan_parameters.targets.len(),
             report.metadata.scan_parameters.ports.len(),
             report.metadata.scan_parameters.scan_types.join(", "),
+            report.metadata.excluded_hosts.len(),
+            report.metadata.origin.as_ref().map_or_else(|| "-".to_string(), |o| o.to_string()),
             "└───────────────────────────────────────────────────────────────────────┘"
         )
     }
@@ -92,6 +119,7 @@ r#"SUMMARY
 │  Open Ports:             {:>10}  (✓)                                   │
 │  Closed Ports:           {:>10}                                       │
 │  Filtered Ports:         {:>10}                                       │
+│  Suspected Decoys:       {:>10}                                       │
 │                                                                           │
 {}
 
@@ -104,6 +132,7 @@ r#"SUMMARY
             report.summary.total_open_ports,
             report.summary.total_closed_ports,
             report.summary.total_filtered_ports,
+            report.summary.suspected_decoys,
             "└───────────────────────────────────────────────────────────────────────┘"
         )
     }
@@ -113,27 +142,67 @@ r#"SUMMARY
 r#"STATISTICS
 {}
 │                                                                           │
-│  Average Scan Time:      {:>10.2} ms                                   │
-│  Fastest Scan:           {:>10} ms                                     │
-│  Slowest Scan:           {:>10} ms                                     │
+│  Average Scan Time:      {:>10}                                       │
+│  Fastest Scan:           {:>10}                                       │
+│  Slowest Scan:           {:>10}                                       │
 │  Success Rate:           {:>10.1} %                                     │
 │  Packets Sent:           {:>10}                                       │
 │  Packets Received:       {:>10}                                       │
-│                                                                           │
+│  RTT avg / p95:          {:>10}                                       │
+│  RTT Jitter:             {:>10}                                       │
+{}{}│                                                                           │
 {}
 
 "#,
             "┌───────────────────────────────────────────────────────────────────────┐",
-            report.statistics.average_scan_time_ms,
-            report.statistics.fastest_scan_ms,
-            report.statistics.slowest_scan_ms,
+            format_duration_ms(report.statistics.average_scan_time_ms.round() as u64),
+            format_duration_ms(report.statistics.fastest_scan_ms),
+            format_duration_ms(report.statistics.slowest_scan_ms),
             report.statistics.success_rate,
             report.statistics.packets_sent,
             report.statistics.packets_received,
+            report.statistics.latency.as_ref()
+                .map_or("N/A".to_string(), |l| format!("{:.0} / {}ms", l.avg_ms, l.p95_ms)),
+            report.statistics.latency.as_ref()
+                .map_or("N/A".to_string(), |l| format!("{:.1}ms", l.jitter_ms)),
+            self.error_rows(report),
+            self.resource_rows(report),
             "└───────────────────────────────────────────────────────────────────────┘"
         )
     }
 
+    /// One statistics row per probe error class, under a total
+    fn error_rows(&self, report: &ScanReport) -> String {
+        let classes = &report.statistics.error_classes;
+        if classes.is_empty() {
+            return String::new();
+        }
+        let row = |label: String, count: usize| format!("│  {:<24}{:>10}{:39}│\n", label, count, "");
+        let mut rows = row("Probe Errors:".to_string(), classes.values().sum());
+        for (class, count) in classes {
+            rows.push_str(&row(format!("  {}:", class), *count));
+        }
+        rows
+    }
+
+    /// Resources the scan used, when they were measured
+    fn resource_rows(&self, report: &ScanReport) -> String {
+        let Some(ref resources) = report.statistics.resources else {
+            return String::new();
+        };
+        let row = |label: &str, value: String| format!("│  {:<24}{:>10}{:39}│\n", label, value, "");
+        let mut rows = row("Sockets Opened:", resources.sockets_opened.to_string());
+        rows.push_str(&row("Bytes Sent:", resources.bytes_sent.to_string()));
+        rows.push_str(&row("Bytes Received:", resources.bytes_received.to_string()));
+        if let Some(fds) = resources.peak_open_fds {
+            rows.push_str(&row("Peak Open FDs:", fds.to_string()));
+        }
+        if let Some(kb) = resources.peak_memory_kb {
+            rows.push_str(&row("Peak Memory:", format!("{} MiB", kb / 1024)));
+        }
+        rows
+    }
+
     fn generate_results_table(&self, report: &ScanReport) -> String {
         if report.results.is_empty() {
             return String::from("DETAILED RESULTS\nNo scan results available.\n\n");
@@ -153,10 +222,7 @@ r#"DETAILED RESULTS
                 HostStatus::Unknown => "UNKNOWN",
             };
 
-            let open_ports: Vec<String> = result.tcp_results.iter()
-                .filter(|r| r.status == PortStatus::Open)
-                .map(|r| r.port.to_string())
-                .collect();
+            let open_ports = open_port_labels(result);
 
             let open_ports_str = if open_ports.is_empty() {
                 "None".to_string()
@@ -167,11 +233,11 @@ r#"DETAILED RESULTS
             };
 
             table.push_str(&format!(
-                "│ {:<17} │ {:<10} │ {:<23} │ {:>10} ms │\n",
+                "│ {:<17} │ {:<10} │ {:<23} │ {:>12} │\n",
                 format!("{}", result.target).chars().take(17).collect::<String>(),
                 status_str,
                 open_ports_str.chars().take(23).collect::<String>(),
-                result.scan_duration_ms
+                format_duration_ms(result.scan_duration_ms)
             ));
         }
 
@@ -179,14 +245,123 @@ r#"DETAILED RESULTS
         table
     }
 
+    fn generate_ports_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(
+r#"OPEN PORTS BY PROTOCOL
+┌───────────────────┬─────────┬─────────────────┬────────────┬────────────┬────────────┬────────────┐
+│ Target            │ Port    │ Service         │ Connect    │ SYN        │ UDP        │ Confidence │
+├───────────────────┼─────────┼─────────────────┼────────────┼────────────┼────────────┼────────────┤
+"#);
+
+        let status = |status: &Option<PortStatus>| status.as_ref().map_or("-".to_string(), |s| s.to_string());
+        for result in &report.results {
+            for evidence in port_view(result).iter().filter(|p| p.is_open()) {
+                table.push_str(&format!(
+                    "│ {:<17} │ {:<7} │ {:<15} │ {:<10} │ {:<10} │ {:<10} │ {:>10} │\n",
+                    format!("{}", result.target).chars().take(17).collect::<String>(),
+                    evidence.port,
+                    evidence.service.as_deref().unwrap_or("-").chars().take(15).collect::<String>(),
+                    status(&evidence.connect),
+                    status(&evidence.syn),
+                    status(&evidence.udp),
+                    evidence.confidence.map_or("-".to_string(), |c| c.to_string())
+                ));
+            }
+        }
+
+        table.push_str("└───────────────────┴─────────┴─────────────────┴────────────┴────────────┴────────────┴────────────┘\n\n");
+        table
+    }
+
+    fn generate_timing_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(
+r#"HOST TIMING
+┌───────────────────┬───────────┬──────────┬──────────┬───────────┬──────────┬───────────┐
+│ Target            │ Discovery │ TCP      │ UDP      │ Detection │ OS       │ Slowest   │
+├───────────────────┼───────────┼──────────┼──────────┼───────────┼──────────┼───────────┤
+"#);
+
+        for result in &report.results {
+            let Some(timings) = result.phase_timings else {
+                continue;
+            };
+            table.push_str(&format!(
+                "│ {:<17} │ {:>9} │ {:>8} │ {:>8} │ {:>9} │ {:>8} │ {:<9} │\n",
+                format!("{}", result.target).chars().take(17).collect::<String>(),
+                format_duration_ms(timings.discovery_ms),
+                format_duration_ms(timings.tcp_ms),
+                format_duration_ms(timings.udp_ms),
+                format_duration_ms(timings.detection_ms),
+                format_duration_ms(timings.os_ms),
+                timings.dominant().map_or("-", |(phase, _)| phase)
+            ));
+        }
+
+        table.push_str("└───────────────────┴───────────┴──────────┴──────────┴───────────┴──────────┴───────────┘\n\n");
+        table
+    }
+
+    fn generate_reasons_table(&self, report: &ScanReport) -> String {
+        let mut table = String::from(
+r#"PORT REASONS
+┌───────────────────┬───────┬─────────┬──────────┬──────────────────────────────────┐
+│ Target            │ Port  │ Scan    │ State    │ Reason                           │
+├───────────────────┼───────┼─────────┼──────────┼──────────────────────────────────┤
+"#);
+
+        for result in &report.results {
+            for (port, scan_type, status, reason) in result.port_reasons() {
+                table.push_str(&format!(
+                    "│ {:<17} │ {:>5} │ {:<7} │ {:<8} │ {:<32} │\n",
+                    format!("{}", result.target).chars().take(17).collect::<String>(),
+                    port,
+                    scan_type,
+                    status.to_string(),
+                    reason.to_string().chars().take(32).collect::<String>()
+                ));
+            }
+        }
+
+        table.push_str("└───────────────────┴───────┴─────────┴──────────┴──────────────────────────────────┘\n\n");
+        table
+    }
+
+    fn generate_compliance_table(&self, compliance: &ComplianceReport) -> String {
+        let mut table = format!(
+r#"COMPLIANCE: {}
+┌──────────────────────────┬────────┬─────────┬────────────┐
+│ Rule                     │ Result │ Hosts   │ Violations │
+├──────────────────────────┼────────┼─────────┼────────────┤
+"#, compliance);
+
+        for rule in &compliance.rules {
+            table.push_str(&format!(
+                "│ {:<24} │ {:<6} │ {:>7} │ {:>10} │\n",
+                rule.name.chars().take(24).collect::<String>(),
+                if rule.passed() { "PASS" } else { "FAIL" },
+                rule.hosts_checked,
+                rule.violations.len()
+            ));
+        }
+        table.push_str("└──────────────────────────┴────────┴─────────┴────────────┘\n");
+
+        for rule in compliance.failed_rules() {
+            for violation in &rule.violations {
+                table.push_str(&format!("  {}: {}\n", rule.name, violation));
+            }
+        }
+        table.push('\n');
+        table
+    }
+
     /// Generate a simple summary table (for quick viewing)
     pub fn generate_summary_only(&self, report: &ScanReport) -> ScanResult<String> {
         let mut output = String::new();
         
         output.push_str(&format!("\n{}\n", "═".repeat(70)));
-        output.push_str(&format!("  Scan: {} (Duration: {:.2}s)\n", 
+        output.push_str(&format!("  Scan: {} (Duration: {})\n", 
             report.metadata.scan_id,
-            report.metadata.duration_seconds
+            format_duration_secs(report.metadata.duration_seconds)
         ));
         output.push_str(&format!("{}\n", "═".repeat(70)));
         
@@ -219,6 +394,7 @@ impl Default for TableReportGenerator {
 mod tests {
     use super::*;
     use crate::report::{ReportBuilder, ScanParameters};
+    use crate::scanner::{CompleteScanResult, PhaseTimings};
     use std::net::{IpAddr, Ipv4Addr};
 
     #[test]
@@ -256,6 +432,35 @@ mod tests {
         assert!(table_str.contains("STATISTICS"));
     }
 
+    #[test]
+    fn test_timing_section_lists_phases() {
+        let generator = TableReportGenerator::new();
+        let target = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+
+        let without = ReportBuilder::new("no-timing".to_string())
+            .add_results(vec![result.clone()])
+            .build()
+            .unwrap();
+        assert!(!generator.generate(&without).unwrap().contains("HOST TIMING"));
+
+        result.phase_timings = Some(PhaseTimings {
+            discovery_ms: 40,
+            tcp_ms: 2500,
+            udp_ms: 0,
+            detection_ms: 10,
+            os_ms: 0,
+        });
+        let report = ReportBuilder::new("timing".to_string())
+            .add_results(vec![result])
+            .build()
+            .unwrap();
+        let table = generator.generate(&report).unwrap();
+        assert!(table.contains("HOST TIMING"));
+        assert!(table.contains("2.5s"));
+        assert!(table.contains("│ tcp       │"));
+    }
+
     #[test]
     fn test_generate_summary_only() {
         let generator = TableReportGenerator::new();
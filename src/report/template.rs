@@ -0,0 +1,132 @@
+//! User-provided report templates
+//!
+//! Renders a [`ScanReport`] through a Handlebars template read at run time,
+//! so custom Markdown summaries, ticket bodies or corporate layouts need no
+//! code changes. The template sees the report exactly as it is serialized to
+//! JSON: `metadata`, `summary`, `results` and `statistics` at the top level.
+//!
+//! ```handlebars
+//! # Scan {{metadata.scan_id}}
+//! {{#each results}}
+//! ## {{target}} ({{host_status}})
+//! {{#each tcp_results}}{{#if (eq status "Open")}}- {{port}}/tcp
+//! {{/if}}{{/each}}
+//! {{/each}}
+//! ```
+//!
+//! Values are inserted as-is unless the template is for HTML (its file name
+//! contains `.html` or `.htm`), where they are HTML-escaped.
+
+use crate::error::{ScanError, ScanResult};
+use crate::report::ScanReport;
+use handlebars::Handlebars;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+
+const TEMPLATE_NAME: &str = "report";
+
+/// Report generator rendering a user template
+#[derive(Clone)]
+pub struct TemplateReportGenerator {
+    registry: Arc<Handlebars<'static>>,
+}
+
+impl std::fmt::Debug for TemplateReportGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TemplateReportGenerator").finish_non_exhaustive()
+    }
+}
+
+impl TemplateReportGenerator {
+    /// Compile a template from its source
+    ///
+    /// # Arguments
+    /// * `source` - Handlebars template text
+    /// * `escape_html` - HTML-escape inserted values
+    pub fn new(source: &str, escape_html: bool) -> ScanResult<Self> {
+        let mut registry = Handlebars::new();
+        if !escape_html {
+            registry.register_escape_fn(handlebars::no_escape);
+        }
+        registry
+            .register_template_string(TEMPLATE_NAME, source)
+            .map_err(|e| ScanError::validation_error("template", e.to_string()))?;
+
+        Ok(Self {
+            registry: Arc::new(registry),
+        })
+    }
+
+    /// Read and compile a template file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            ScanError::validation_error("template", format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        Self::new(&source, file_name.contains(".htm"))
+    }
+
+    /// Render a report through the template
+    ///
+    /// # Arguments
+    /// * `report` - The scan report to format
+    pub fn generate(&self, report: &ScanReport) -> ScanResult<String> {
+        debug!("Generating templated report");
+
+        self.registry.render(TEMPLATE_NAME, report).map_err(|e| ScanError::OutputError {
+            message: format!("Failed to render report template: {}", e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{ReportBuilder, ScanParameters};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn report() -> ScanReport {
+        ReportBuilder::new("test-template-1".to_string())
+            .with_parameters(ScanParameters {
+                targets: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))],
+                ports: vec![80, 443],
+                scan_types: vec!["TcpConnect".to_string()],
+                timeout_ms: 5000,
+                concurrent_scans: 100,
+            })
+            .complete()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_report_context() {
+        let source = "# {{metadata.scan_id}}\nports: {{#each metadata.scan_parameters.ports}}{{this}} {{/each}}\n\
+                      up: {{summary.targets_up}}/{{summary.total_targets}} <{{metadata.scan_parameters.scan_types.[0]}}>";
+        let text = TemplateReportGenerator::new(source, false).unwrap().generate(&report()).unwrap();
+        assert_eq!(text, "# test-template-1\nports: 80 443 \nup: 0/0 <TcpConnect>");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(TemplateReportGenerator::new("{{#each results}}", false).is_err());
+        assert!(TemplateReportGenerator::from_file("/nonexistent/report.hbs").is_err());
+    }
+
+    #[test]
+    fn test_html_template_escapes_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let html = dir.path().join("report.html.hbs");
+        let markdown = dir.path().join("report.md.hbs");
+        std::fs::write(&html, "{{metadata.scan_id}}").unwrap();
+        std::fs::write(&markdown, "{{metadata.scan_id}}").unwrap();
+
+        let mut report = report();
+        report.metadata.scan_id = "a<b>&c".to_string();
+        let render = |path| TemplateReportGenerator::from_file(path).unwrap().generate(&report).unwrap();
+        assert_eq!(render(&html), "a&lt;b&gt;&amp;c");
+        assert_eq!(render(&markdown), "a<b>&c");
+    }
+}
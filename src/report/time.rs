@@ -0,0 +1,213 @@
+//! Report time formatting
+//!
+//! Display timezone handling and human-readable durations for reports, and
+//! durations given on the command line.
+
+use crate::error::{ScanError, ScanResult};
+use chrono::{DateTime, FixedOffset, Local, Offset, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Timezone used to display report timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DisplayTimezone {
+    /// Coordinated Universal Time
+    #[default]
+    Utc,
+    /// The local timezone of the machine generating the report
+    Local,
+    /// A fixed UTC offset (e.g. "+05:30")
+    Fixed(FixedOffset),
+}
+
+impl DisplayTimezone {
+    /// Convert a UTC timestamp into this timezone
+    pub fn convert(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            DisplayTimezone::Utc => time.with_timezone(&Utc.fix()),
+            DisplayTimezone::Local => time.with_timezone(&Local).fixed_offset(),
+            DisplayTimezone::Fixed(offset) => time.with_timezone(offset),
+        }
+    }
+
+    /// Current time in this timezone
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.convert(Utc::now())
+    }
+}
+
+impl std::str::FromStr for DisplayTimezone {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        match value.to_lowercase().as_str() {
+            "utc" | "z" | "" => return Ok(DisplayTimezone::Utc),
+            "local" => return Ok(DisplayTimezone::Local),
+            _ => {}
+        }
+
+        // Accept "+05:30", "-0800", "+2" and the same prefixed with "UTC"
+        let offset = value
+            .strip_prefix("UTC")
+            .or_else(|| value.strip_prefix("utc"))
+            .unwrap_or(value);
+        let invalid = || {
+            ScanError::validation_error(
+                "timezone",
+                format!("Unknown timezone: {} (use UTC, local, or an offset like +05:30)", s),
+            )
+        };
+
+        let (sign, digits) = match offset.chars().next() {
+            Some('+') => (1, &offset[1..]),
+            Some('-') => (-1, &offset[1..]),
+            _ => return Err(invalid()),
+        };
+        let digits = digits.replace(':', "");
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<i32>().map_err(|_| invalid())?, 0),
+            4 => (
+                digits[..2].parse::<i32>().map_err(|_| invalid())?,
+                digits[2..].parse::<i32>().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+        if hours > 23 || minutes > 59 {
+            return Err(invalid());
+        }
+
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(DisplayTimezone::Fixed)
+            .ok_or_else(invalid)
+    }
+}
+
+impl std::fmt::Display for DisplayTimezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisplayTimezone::Utc => write!(f, "UTC"),
+            DisplayTimezone::Local => write!(f, "local"),
+            DisplayTimezone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl TryFrom<String> for DisplayTimezone {
+    type Error = ScanError;
+
+    fn try_from(value: String) -> ScanResult<Self> {
+        value.parse()
+    }
+}
+
+impl From<DisplayTimezone> for String {
+    fn from(value: DisplayTimezone) -> Self {
+        value.to_string()
+    }
+}
+
+/// Format a timestamp for display, including its UTC offset
+pub fn format_timestamp(time: &DateTime<FixedOffset>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S %:z").to_string()
+}
+
+/// Format a duration in milliseconds in human form ("450ms", "4.2s", "2m 13s")
+pub fn format_duration_ms(ms: u64) -> String {
+    if ms < 1000 {
+        return format!("{}ms", ms);
+    }
+    if ms < 60_000 {
+        return format!("{:.1}s", ms as f64 / 1000.0);
+    }
+
+    let total_seconds = ms / 1000;
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else {
+        format!("{}m {}s", minutes, seconds)
+    }
+}
+
+/// Format a duration in seconds in human form
+pub fn format_duration_secs(seconds: f64) -> String {
+    format_duration_ms((seconds.max(0.0) * 1000.0).round() as u64)
+}
+
+/// Parse a duration such as "90", "30s", "10m", "2h" or "1d" (bare numbers
+/// are seconds)
+pub fn parse_duration(spec: &str) -> ScanResult<Duration> {
+    let spec = spec.trim();
+    let split = spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+    let (number, unit) = spec.split_at(split);
+    let multiplier = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(value) if multiplier > 0 => Ok(Duration::from_secs(value.saturating_mul(multiplier))),
+        _ => Err(ScanError::validation_error(
+            "duration",
+            format!("Invalid duration '{}' (expected e.g. 30s, 10m, 2h)", spec),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!("UTC".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Utc);
+        assert_eq!("local".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Local);
+        assert_eq!(
+            "+05:30".parse::<DisplayTimezone>().unwrap(),
+            DisplayTimezone::Fixed(FixedOffset::east_opt(5 * 3600 + 1800).unwrap())
+        );
+        assert_eq!(
+            "UTC-8".parse::<DisplayTimezone>().unwrap(),
+            DisplayTimezone::Fixed(FixedOffset::west_opt(8 * 3600).unwrap())
+        );
+        assert!("Mars/Olympus".parse::<DisplayTimezone>().is_err());
+        assert!("+25:00".parse::<DisplayTimezone>().is_err());
+    }
+
+    #[test]
+    fn test_convert_and_format() {
+        let time = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let tz: DisplayTimezone = "+02:00".parse().unwrap();
+        let converted = tz.convert(time);
+        assert_eq!(format_timestamp(&converted), "2024-03-01 14:00:00 +02:00");
+        assert_eq!(converted.to_rfc3339(), "2024-03-01T14:00:00+02:00");
+        assert_eq!(format_timestamp(&DisplayTimezone::Utc.convert(time)), "2024-03-01 12:00:00 +00:00");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration_ms(450), "450ms");
+        assert_eq!(format_duration_ms(4200), "4.2s");
+        assert_eq!(format_duration_ms(133_000), "2m 13s");
+        assert_eq!(format_duration_ms(3_723_000), "1h 2m 3s");
+        assert_eq!(format_duration_secs(133.4), "2m 13s");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration(" 2h ").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86_400));
+        assert!(parse_duration("10 minutes").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+}
@@ -0,0 +1,281 @@
+//! ARP warm-up before scanning on-link IPv4 targets
+//!
+//! The first probe to an on-link host waits for the kernel to resolve its MAC
+//! address, so on a LAN the first-probe timeouts end up measuring ARP rather
+//! than the host. The warm-up phase nudges the kernel into resolving every
+//! on-link target up front (a single UDP datagram to the discard port is
+//! enough) and then reads the neighbour table: hosts that never answered ARP
+//! are recorded as down instead of being probed until their ports time out.
+
+use crate::error::{ScanError, ScanResult};
+use crate::lan::{HostMac, MacAddress};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Port the resolution-triggering datagram is sent to (discard)
+const TRIGGER_PORT: u16 = 9;
+
+/// ARP flag set on resolved entries (`ATF_COM`)
+const ATF_COM: u32 = 0x2;
+
+/// How often the neighbour table is re-read while waiting
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Entry from the kernel ARP table (`/proc/net/arp`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArpEntry {
+    pub ip: Ipv4Addr,
+    pub mac: Option<[u8; 6]>,
+    pub interface: String,
+    /// Resolution finished with a MAC address
+    pub complete: bool,
+}
+
+/// Directly connected IPv4 network from the routing table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnLinkRoute {
+    pub interface: String,
+    pub network: Ipv4Addr,
+    pub mask: Ipv4Addr,
+}
+
+impl OnLinkRoute {
+    /// Whether an address is inside this network
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        u32::from(ip) & u32::from(self.mask) == u32::from(self.network)
+    }
+}
+
+/// Outcome of the warm-up phase
+#[derive(Debug, Clone, Default)]
+pub struct ArpWarmupReport {
+    /// Targets whose MAC address was resolved
+    pub resolved: HashMap<Ipv4Addr, [u8; 6]>,
+    /// On-link targets that never answered ARP
+    pub failed: HashSet<Ipv4Addr>,
+}
+
+impl ArpWarmupReport {
+    /// Whether ARP resolution failed for a target
+    pub fn is_unresolved(&self, target: IpAddr) -> bool {
+        match target {
+            IpAddr::V4(ip) => self.failed.contains(&ip),
+            IpAddr::V6(_) => false,
+        }
+    }
+
+    /// MAC address a target resolved to, with its vendor
+    pub fn host_mac(&self, target: IpAddr) -> Option<HostMac> {
+        match target {
+            IpAddr::V4(ip) => self.resolved.get(&ip).map(|&mac| HostMac::new(MacAddress(mac))),
+            IpAddr::V6(_) => None,
+        }
+    }
+}
+
+/// Parse `/proc/net/arp`
+pub fn parse_arp_table(contents: &str) -> Vec<ArpEntry> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            let ip = fields[0].parse().ok()?;
+            let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16).ok()?;
+            let mac = parse_mac(fields[3]).filter(|mac| *mac != [0; 6]);
+            Some(ArpEntry {
+                ip,
+                mac,
+                interface: fields[5].to_string(),
+                complete: flags & ATF_COM != 0 && mac.is_some(),
+            })
+        })
+        .collect()
+}
+
+/// Parse `/proc/net/route`, keeping only directly connected networks
+pub fn parse_route_table(contents: &str) -> Vec<OnLinkRoute> {
+    let hex_addr = |s: &str| u32::from_str_radix(s, 16).ok().map(|v| Ipv4Addr::from(u32::from_be(v)));
+
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 8 {
+                return None;
+            }
+            let network = hex_addr(fields[1])?;
+            let gateway = hex_addr(fields[2])?;
+            let mask = hex_addr(fields[7])?;
+            // Routes via a gateway and the default route are not on-link
+            if !gateway.is_unspecified() || mask.is_unspecified() || fields[0] == "lo" {
+                return None;
+            }
+            Some(OnLinkRoute {
+                interface: fields[0].to_string(),
+                network,
+                mask,
+            })
+        })
+        .collect()
+}
+
+fn parse_mac(s: &str) -> Option<[u8; 6]> {
+    let mut mac = [0u8; 6];
+    let mut parts = s.split(':');
+    for byte in &mut mac {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    parts.next().is_none().then_some(mac)
+}
+
+fn read_proc(path: &str) -> ScanResult<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| ScanError::scanner_error(format!("Failed to read {}: {}", path, e)))
+}
+
+/// Pre-resolves ARP for on-link IPv4 targets
+#[derive(Debug, Clone)]
+pub struct ArpWarmup {
+    timeout: Duration,
+}
+
+impl ArpWarmup {
+    /// Create a warm-up phase waiting up to `timeout_ms` for resolution
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+
+    /// Resolve every on-link target and report which never answered
+    ///
+    /// Targets that are off-link, IPv6 or local addresses are left out of the
+    /// report entirely. Platforms without `/proc/net` get an empty report.
+    pub async fn run(&self, targets: &[IpAddr]) -> ScanResult<ArpWarmupReport> {
+        let routes = match read_proc("/proc/net/route") {
+            Ok(contents) => parse_route_table(&contents),
+            Err(e) => {
+                warn!("ARP warm-up skipped: {}", e);
+                return Ok(ArpWarmupReport::default());
+            }
+        };
+
+        let mut pending: HashSet<Ipv4Addr> = targets
+            .iter()
+            .filter_map(|t| match t {
+                IpAddr::V4(ip) => Some(*ip),
+                IpAddr::V6(_) => None,
+            })
+            .filter(|ip| routes.iter().any(|r| r.contains(*ip)))
+            .filter(|ip| !ip.is_broadcast() && !ip.is_multicast())
+            .collect();
+        if pending.is_empty() {
+            return Ok(ArpWarmupReport::default());
+        }
+
+        info!("ARP warm-up for {} on-link targets", pending.len());
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        pending.retain(|ip| trigger_resolution(&socket, *ip));
+
+        let mut report = ArpWarmupReport::default();
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            for entry in parse_arp_table(&read_proc("/proc/net/arp")?) {
+                if let (true, Some(mac)) = (entry.complete, entry.mac) {
+                    if pending.remove(&entry.ip) {
+                        report.resolved.insert(entry.ip, mac);
+                    }
+                }
+            }
+            if pending.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        for ip in &pending {
+            debug!("No ARP reply from {}", ip);
+        }
+        report.failed = pending;
+        info!(
+            "ARP warm-up complete: {} resolved, {} unresolved",
+            report.resolved.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+}
+
+/// Send one datagram so the kernel starts resolving `ip`
+///
+/// Returns false for the host's own addresses, which never get an ARP entry.
+fn trigger_resolution(socket: &UdpSocket, ip: Ipv4Addr) -> bool {
+    let local = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|probe| probe.connect((ip, TRIGGER_PORT)).and_then(|_| probe.local_addr()));
+    if matches!(local, Ok(addr) if addr.ip() == IpAddr::V4(ip)) {
+        return false;
+    }
+
+    if let Err(e) = socket.send_to(&[0], SocketAddr::from((ip, TRIGGER_PORT))) {
+        // EHOSTUNREACH here means an earlier resolution attempt already failed
+        debug!("ARP trigger to {} failed: {}", ip, e);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_arp_table() {
+        let contents = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         02:fc:00:00:00:05     *        eth0
+192.168.1.77     0x1         0x0         00:00:00:00:00:00     *        eth0
+";
+        let entries = parse_arp_table(contents);
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].complete);
+        assert_eq!(entries[0].mac, Some([0x02, 0xfc, 0, 0, 0, 5]));
+        assert_eq!(entries[1].ip, Ipv4Addr::new(192, 168, 1, 77));
+        assert!(!entries[1].complete);
+        assert_eq!(entries[1].mac, None);
+    }
+
+    #[test]
+    fn test_parse_route_table() {
+        let contents = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t010200C0\t0003\t0\t0\t0\t00000000\t0\t0\t0
+eth0\t000200C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+";
+        let routes = parse_route_table(contents);
+        assert_eq!(
+            routes,
+            vec![OnLinkRoute {
+                interface: "eth0".to_string(),
+                network: Ipv4Addr::new(192, 0, 2, 0),
+                mask: Ipv4Addr::new(255, 255, 255, 0),
+            }]
+        );
+        assert!(routes[0].contains(Ipv4Addr::new(192, 0, 2, 40)));
+        assert!(!routes[0].contains(Ipv4Addr::new(198, 51, 100, 1)));
+    }
+
+    #[tokio::test]
+    async fn test_warmup_ignores_off_link_targets() {
+        let targets = ["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()];
+        let report = ArpWarmup::new(100).run(&targets).await.unwrap();
+        assert!(report.resolved.is_empty());
+        assert!(report.failed.is_empty());
+        assert!(!report.is_unresolved(targets[0]));
+    }
+}
@@ -0,0 +1,118 @@
+//! Authorized scan scope
+//!
+//! An installation may only be allowed to scan certain networks: a corporate
+//! CI runner that scans the internal ranges should never reach the public
+//! internet because of a typo in a target list. `security.scope` lists the
+//! address blocks the installation is authorized to scan; a scan with any
+//! target outside them is refused before a probe is sent. An operator who
+//! really means it can override the check (`--i-know-what-im-doing`), which
+//! is then recorded in the audit log. An empty scope authorizes everything.
+
+use crate::config::reload::AUDIT_TARGET;
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::targets::IpNetwork;
+use std::net::IpAddr;
+use tracing::warn;
+
+/// Out-of-scope targets named in an error before the rest are counted
+const LISTED_TARGETS: usize = 5;
+
+/// Address blocks scans are authorized for
+#[derive(Debug, Clone, Default)]
+pub struct AuthorizedScope {
+    blocks: Vec<IpNetwork>,
+    overridden: bool,
+}
+
+impl AuthorizedScope {
+    /// Scope of `blocks`; empty authorizes every target
+    pub fn new(blocks: Vec<IpNetwork>) -> Self {
+        Self { blocks, overridden: false }
+    }
+
+    /// Scan out-of-scope targets anyway, with an audit entry
+    pub fn with_override(mut self, overridden: bool) -> Self {
+        self.overridden = overridden;
+        self
+    }
+
+    pub fn blocks(&self) -> &[IpNetwork] {
+        &self.blocks
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        !self.blocks.is_empty()
+    }
+
+    /// Whether `target` falls inside the scope
+    pub fn permits(&self, target: IpAddr) -> bool {
+        !self.is_restricted() || self.blocks.iter().any(|block| block.contains(target))
+    }
+
+    /// Refuse `targets` unless every one is in scope, or the scope is
+    /// overridden; an override is audited, naming `operator`
+    pub fn check(&self, targets: &[IpAddr], operator: Option<&str>) -> ScanResult<()> {
+        let outside: Vec<IpAddr> = targets.iter().copied().filter(|&t| !self.permits(t)).collect();
+        if outside.is_empty() {
+            return Ok(());
+        }
+        let listed = list_targets(&outside);
+        if self.overridden {
+            warn!(
+                target: AUDIT_TARGET,
+                outcome = "override",
+                operator = operator.unwrap_or("unknown"),
+                targets = outside.len(),
+                "Scanning {} outside the authorized scope",
+                listed
+            );
+            return Ok(());
+        }
+        warn!(
+            target: AUDIT_TARGET,
+            outcome = "refused",
+            operator = operator.unwrap_or("unknown"),
+            targets = outside.len(),
+            "Refused to scan {} outside the authorized scope",
+            listed
+        );
+        let scope: Vec<String> = self.blocks.iter().map(ToString::to_string).collect();
+        Err(ScanError::validation_error(
+            "targets",
+            format!(
+                "{} outside the authorized scope ({}); fix the targets or security.scope, \
+                 or pass --i-know-what-im-doing",
+                listed,
+                scope.join(", ")
+            ),
+        ))
+    }
+}
+
+/// "10.0.0.1, 10.0.0.2 and 3 more"
+fn list_targets(targets: &[IpAddr]) -> String {
+    let named: Vec<String> = targets.iter().take(LISTED_TARGETS).map(ToString::to_string).collect();
+    match targets.len().saturating_sub(LISTED_TARGETS) {
+        0 => named.join(", "),
+        more => format!("{} and {} more", named.join(", "), more),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_check() {
+        let scope = AuthorizedScope::new(vec!["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()]);
+        let inside: Vec<IpAddr> = vec!["10.1.2.3".parse().unwrap(), "fd00::1".parse().unwrap()];
+        assert!(scope.check(&inside, None).is_ok());
+
+        let outside: Vec<IpAddr> = (1..=7).map(|i| IpAddr::from([8, 8, 8, i])).collect();
+        let error = scope.check(&outside, Some("alice")).unwrap_err().to_string();
+        assert!(error.contains("8.8.8.5 and 2 more"), "{}", error);
+        assert!(scope.clone().with_override(true).check(&outside, None).is_ok());
+
+        assert!(AuthorizedScope::default().check(&outside, None).is_ok());
+    }
+}
@@ -0,0 +1,368 @@
+//! Blackout windows
+//!
+//! Production networks have times when nobody should scan them: a change
+//! freeze, the nightly backup of a database segment, a trading session.
+//! `security.blackouts` lists such windows, each for all targets or for
+//! some address blocks, either recurring (a cron expression and a duration)
+//! or as a single ISO 8601 interval (`start/end`):
+//!
+//! ```toml
+//! [[security.blackouts]]
+//! name = "year-end-freeze"
+//! interval = "2026-12-20T00:00:00Z/2027-01-04T00:00:00Z"
+//! action = "refuse"
+//!
+//! [[security.blackouts]]
+//! name = "db-backups"
+//! targets = ["10.20.0.0/24"]
+//! cron = "0 2 * * *"
+//! duration = "2h"
+//! ```
+//!
+//! A scan with a target in an active `refuse` window is refused, with an
+//! audit entry. In any active window a running scan starts no new host it
+//! covers; the host waits until the window closes and is scanned then
+//! (`pause`, the default, lets the scan start and wait the same way). Hosts
+//! already being probed when a window opens finish. The distributed
+//! scheduler holds jobs the same way, so agents never receive work inside a
+//! window. Cron expressions are evaluated in local time, as for schedules.
+
+use crate::config::reload::AUDIT_TARGET;
+use crate::error::{ScanError, ScanResult};
+use crate::report::time::parse_duration;
+use crate::scanner::targets::IpNetwork;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// When something happens, as a cron expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    cron: croner::Cron,
+}
+
+impl CronSchedule {
+    /// First time the schedule fires strictly after `after`
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.cron.find_next_occurrence(after, false).ok()
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = ScanError;
+
+    fn from_str(expression: &str) -> ScanResult<Self> {
+        let expression = expression.trim();
+        let cron = croner::Cron::new(expression)
+            .parse()
+            .map_err(|e| ScanError::validation_error("cron", format!("Invalid cron expression '{}': {}", expression, e)))?;
+        Ok(Self {
+            expression: expression.to_string(),
+            cron,
+        })
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = ScanError;
+
+    fn try_from(expression: String) -> ScanResult<Self> {
+        expression.parse()
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// What happens to a scan that meets an active window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlackoutAction {
+    /// Refuse scans that start inside the window
+    Refuse,
+    /// Hold the covered hosts until the window closes
+    #[default]
+    Pause,
+}
+
+/// When a window is active
+#[derive(Debug, Clone)]
+pub enum BlackoutTimes {
+    /// For `duration` from every time `cron` fires
+    Recurring { cron: Box<CronSchedule>, duration: Duration },
+    /// Once, from `start` until `end`
+    Interval { start: DateTime<Utc>, end: DateTime<Utc> },
+}
+
+impl BlackoutTimes {
+    /// End of the occurrence active at `now`, if one is
+    fn active_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            BlackoutTimes::Recurring { cron, duration } => {
+                let duration = chrono::Duration::from_std(*duration).ok()?;
+                // The earliest occurrence that may still cover `now`
+                let start = cron.next_after(&(now - duration).with_timezone(&Local))?.to_utc();
+                (start <= now).then_some(start + duration)
+            }
+            BlackoutTimes::Interval { start, end } => (*start <= now && now < *end).then_some(*end),
+        }
+    }
+}
+
+/// A window during which some or all targets must not be scanned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "WindowSpec", into = "WindowSpec")]
+pub struct BlackoutWindow {
+    pub name: String,
+    /// Address blocks the window covers; empty covers every target
+    pub targets: Vec<IpNetwork>,
+    pub times: BlackoutTimes,
+    pub action: BlackoutAction,
+}
+
+impl BlackoutWindow {
+    pub fn covers(&self, target: IpAddr) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|block| block.contains(target))
+    }
+}
+
+/// A window as written in the configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WindowSpec {
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<IpNetwork>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cron: Option<CronSchedule>,
+    /// How long each cron occurrence lasts, e.g. "2h"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<String>,
+    /// ISO 8601 interval, "start/end" in RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    #[serde(default)]
+    action: BlackoutAction,
+}
+
+impl TryFrom<WindowSpec> for BlackoutWindow {
+    type Error = ScanError;
+
+    fn try_from(spec: WindowSpec) -> ScanResult<Self> {
+        let invalid = |message: String| ScanError::validation_error("blackouts", format!("Window '{}': {}", spec.name, message));
+        let times = match (spec.cron.clone(), spec.duration.as_deref(), spec.interval.as_deref()) {
+            (Some(cron), Some(duration), None) => {
+                let duration = parse_duration(duration).map_err(|e| invalid(e.to_string()))?;
+                if duration.is_zero() {
+                    return Err(invalid("duration must not be zero".to_string()));
+                }
+                BlackoutTimes::Recurring { cron: Box::new(cron), duration }
+            }
+            (None, None, Some(interval)) => parse_interval(interval).map_err(invalid)?,
+            _ => return Err(invalid("give either cron and duration, or interval".to_string())),
+        };
+        Ok(Self {
+            name: spec.name,
+            targets: spec.targets,
+            times,
+            action: spec.action,
+        })
+    }
+}
+
+impl From<BlackoutWindow> for WindowSpec {
+    fn from(window: BlackoutWindow) -> Self {
+        let (cron, duration, interval) = match window.times {
+            BlackoutTimes::Recurring { cron, duration } => (Some(*cron), Some(format!("{}s", duration.as_secs())), None),
+            BlackoutTimes::Interval { start, end } => (None, None, Some(format!("{}/{}", start.to_rfc3339(), end.to_rfc3339()))),
+        };
+        Self {
+            name: window.name,
+            targets: window.targets,
+            cron,
+            duration,
+            interval,
+            action: window.action,
+        }
+    }
+}
+
+/// "2026-12-20T00:00:00Z/2027-01-04T00:00:00Z"
+fn parse_interval(interval: &str) -> Result<BlackoutTimes, String> {
+    let (start, end) = interval
+        .split_once('/')
+        .ok_or_else(|| format!("invalid interval '{}' (expected start/end)", interval))?;
+    let parse = |time: &str| {
+        DateTime::parse_from_rfc3339(time.trim())
+            .map(|time| time.to_utc())
+            .map_err(|e| format!("invalid time '{}' in interval: {}", time.trim(), e))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if end <= start {
+        return Err(format!("interval '{}' ends before it starts", interval));
+    }
+    Ok(BlackoutTimes::Interval { start, end })
+}
+
+/// A window active right now, and when it closes
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveBlackout<'a> {
+    pub window: &'a BlackoutWindow,
+    pub until: DateTime<Utc>,
+}
+
+/// The configured blackout windows
+#[derive(Debug, Clone, Default)]
+pub struct BlackoutCalendar {
+    windows: Vec<BlackoutWindow>,
+}
+
+impl BlackoutCalendar {
+    pub fn new(windows: Vec<BlackoutWindow>) -> Self {
+        Self { windows }
+    }
+
+    pub fn windows(&self) -> &[BlackoutWindow] {
+        &self.windows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// The active window covering `target` at `now` that closes last
+    pub fn active(&self, target: IpAddr, now: DateTime<Utc>) -> Option<ActiveBlackout<'_>> {
+        self.windows
+            .iter()
+            .filter(|window| window.covers(target))
+            .filter_map(|window| window.times.active_until(now).map(|until| ActiveBlackout { window, until }))
+            .max_by_key(|active| active.until)
+    }
+
+    /// The active window holding back any of `targets` at `now`
+    pub fn hold(&self, targets: &[IpAddr], now: DateTime<Utc>) -> Option<ActiveBlackout<'_>> {
+        targets
+            .iter()
+            .filter_map(|&target| self.active(target, now))
+            .max_by_key(|active| active.until)
+    }
+
+    /// Refuse `targets` when an active `refuse` window covers any of them,
+    /// with an audit entry naming `operator`
+    pub fn check(&self, targets: &[IpAddr], now: DateTime<Utc>, operator: Option<&str>) -> ScanResult<()> {
+        let refusing = self
+            .windows
+            .iter()
+            .filter(|window| window.action == BlackoutAction::Refuse)
+            .filter_map(|window| window.times.active_until(now).map(|until| (window, until)))
+            .find_map(|(window, until)| {
+                let covered = targets.iter().filter(|&&target| window.covers(target)).count();
+                (covered > 0).then_some((window, until, covered))
+            });
+        let Some((window, until, covered)) = refusing else {
+            return Ok(());
+        };
+        warn!(
+            target: AUDIT_TARGET,
+            outcome = "refused",
+            operator = operator.unwrap_or("unknown"),
+            targets = covered,
+            window = %window.name,
+            "Refused to scan {} targets during blackout window '{}'",
+            covered,
+            window.name
+        );
+        Err(ScanError::validation_error(
+            "targets",
+            format!(
+                "{} targets are in blackout window '{}' until {}",
+                covered,
+                window.name,
+                until.to_rfc3339()
+            ),
+        ))
+    }
+
+    /// Wait until no window covers `target`
+    ///
+    /// Returns `false` when `cancel` fired while waiting.
+    pub async fn wait_clear(&self, target: IpAddr, cancel: &CancellationToken) -> bool {
+        while let Some(active) = self.active(target, Utc::now()) {
+            info!(
+                "{} is in blackout window '{}', waiting until {}",
+                target,
+                active.window.name,
+                active.until.to_rfc3339()
+            );
+            let wait = (active.until - Utc::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = cancel.cancelled() => return false,
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blackout_windows() {
+        let windows: Vec<BlackoutWindow> = toml::from_str::<toml::Table>(
+            r#"
+            [[w]]
+            name = "freeze"
+            interval = "2026-12-20T00:00:00Z/2027-01-04T00:00:00Z"
+            action = "refuse"
+
+            [[w]]
+            name = "backups"
+            targets = ["10.20.0.0/24"]
+            cron = "0 2 * * *"
+            duration = "2h"
+            "#,
+        )
+        .unwrap()["w"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let calendar = BlackoutCalendar::new(windows);
+        let db: IpAddr = "10.20.0.5".parse().unwrap();
+        let web: IpAddr = "10.30.0.5".parse().unwrap();
+
+        let at_local = |h| Local.with_ymd_and_hms(2026, 3, 10, h, 30, 0).unwrap().to_utc();
+        let active = calendar.active(db, at_local(3)).unwrap();
+        assert_eq!(active.window.name, "backups");
+        assert_eq!(active.until, Local.with_ymd_and_hms(2026, 3, 10, 4, 0, 0).unwrap().to_utc());
+        assert!(calendar.active(db, at_local(4)).is_none());
+        assert!(calendar.active(web, at_local(3)).is_none());
+        // Pause windows hold but do not refuse
+        assert!(calendar.hold(&[web, db], at_local(3)).is_some());
+        assert!(calendar.check(&[db], at_local(3), None).is_ok());
+
+        let freeze = "2026-12-24T12:00:00Z".parse().unwrap();
+        let error = calendar.check(&[web, db], freeze, Some("alice")).unwrap_err().to_string();
+        assert!(error.contains("2 targets are in blackout window 'freeze'"), "{}", error);
+
+        let invalid = |spec: &str| toml::from_str::<BlackoutWindow>(spec).is_err();
+        assert!(invalid("name = \"x\"\ncron = \"0 2 * * *\""));
+        assert!(invalid("name = \"x\"\ninterval = \"2026-01-02T00:00:00Z/2026-01-01T00:00:00Z\""));
+    }
+}
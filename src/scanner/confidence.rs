@@ -0,0 +1,212 @@
+//! Per-port confidence scores
+//!
+//! A port's state is only as good as the evidence behind it. A SYN-ACK or a
+//! completed handshake is the port itself answering; an ICMP unreachable
+//! comes from the host or a device on the path; `filtered` after a timeout
+//! is an inference from silence. Every port result gets a 0-100 score from
+//! the kind of evidence, how many retries it took, and whether a banner or
+//! UDP fingerprint corroborates it. Reports show it, and policies and diffs
+//! can ignore findings below a minimum (`min_confidence`).
+
+use super::probe_error::ProbeError;
+use super::tcp_connect::{PortStatus, TcpConnectResult};
+use super::tcp_syn::TcpSynResult;
+use super::udp_scan::UdpScanResult;
+use super::port_frequency::TransportProtocol;
+use super::CompleteScanResult;
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How well a port's state is supported by evidence, from 0 to 100
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Confidence(u8);
+
+/// What a port's state rests on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evidence {
+    /// The port itself answered: handshake, SYN-ACK, RST or a UDP reply
+    Direct,
+    /// An ICMP unreachable from the host or a device on the path
+    Icmp,
+    /// Nothing came back; the state is inferred from the timeout
+    Silence,
+    /// The probe failed on the scanning host and says nothing
+    Nothing,
+}
+
+impl Confidence {
+    pub const MAX: Confidence = Confidence(100);
+
+    pub fn new(score: u8) -> ScanResult<Self> {
+        if score > 100 {
+            return Err(ScanError::validation_error(
+                "confidence",
+                format!("Confidence {} is out of range (0-100)", score),
+            ));
+        }
+        Ok(Self(score))
+    }
+
+    pub fn score(&self) -> u8 {
+        self.0
+    }
+
+    /// Score evidence that took `attempts` probes, `corroborated` by a
+    /// banner or fingerprint
+    ///
+    /// Answers lose 10 points per retry they needed, down to 50, since a
+    /// port that answers only sometimes is flaky. Silence gains 10 per
+    /// retry that went unanswered too, up to 60: it stays an inference.
+    pub fn assess(evidence: Evidence, attempts: u32, corroborated: bool) -> Self {
+        let retries = attempts.saturating_sub(1).min(10) as u8;
+        Self(match evidence {
+            Evidence::Direct => (if corroborated { 100u8 } else { 90 }).saturating_sub(10 * retries).max(50),
+            Evidence::Icmp => 80u8.saturating_sub(10 * retries).max(50),
+            Evidence::Silence => (30 + 10 * retries).min(60),
+            Evidence::Nothing => 0,
+        })
+    }
+}
+
+impl TryFrom<u8> for Confidence {
+    type Error = ScanError;
+
+    fn try_from(score: u8) -> ScanResult<Self> {
+        Self::new(score)
+    }
+}
+
+impl From<Confidence> for u8 {
+    fn from(confidence: Confidence) -> u8 {
+        confidence.0
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl FromStr for Confidence {
+    type Err = ScanError;
+
+    /// Accepts `80` and `80%`
+    fn from_str(s: &str) -> ScanResult<Self> {
+        let score = s.trim().trim_end_matches('%').parse::<u8>().map_err(|_| {
+            ScanError::validation_error("confidence", format!("Invalid confidence '{}' (expected 0-100)", s))
+        })?;
+        Self::new(score)
+    }
+}
+
+/// Evidence behind a port `status`; `icmp` is set when an ICMP unreachable
+/// settled it
+fn evidence(status: &PortStatus, error: Option<ProbeError>, icmp: bool) -> Evidence {
+    match (status, error) {
+        (PortStatus::Unknown, _) => Evidence::Nothing,
+        _ if icmp => Evidence::Icmp,
+        (PortStatus::Open, _) | (PortStatus::Closed, _) => Evidence::Direct,
+        (PortStatus::Filtered, Some(ProbeError::AdminProhibited | ProbeError::Unreachable)) => Evidence::Icmp,
+        (PortStatus::Filtered, _) => Evidence::Silence,
+    }
+}
+
+impl TcpConnectResult {
+    /// Confidence in this result's status; a banner corroborates an open port
+    pub fn assess_confidence(&self) -> Confidence {
+        let evidence = evidence(&self.status, self.error, false);
+        Confidence::assess(evidence, self.attempts, self.banner.is_some())
+    }
+}
+
+impl TcpSynResult {
+    /// Confidence in this result's status
+    pub fn assess_confidence(&self) -> Confidence {
+        let evidence = evidence(&self.status, self.error, self.icmp.is_some());
+        Confidence::assess(evidence, self.attempts, false)
+    }
+}
+
+impl UdpScanResult {
+    /// Confidence in this result's status; a fingerprinted reply corroborates
+    /// an open port, and ICMP port unreachable is what closes one
+    pub fn assess_confidence(&self) -> Confidence {
+        let icmp = self.icmp.is_some() || self.error == Some(ProbeError::Refused);
+        let evidence = evidence(&self.status, self.error, icmp);
+        Confidence::assess(evidence, self.attempts, self.fingerprint.is_some())
+    }
+}
+
+impl CompleteScanResult {
+    /// Score every port result by the evidence behind it
+    pub fn assess_confidence(&mut self) {
+        for result in &mut self.tcp_results {
+            result.confidence = Some(result.assess_confidence());
+        }
+        for result in &mut self.syn_results {
+            result.confidence = Some(result.assess_confidence());
+        }
+        for result in &mut self.udp_results {
+            result.confidence = Some(result.assess_confidence());
+        }
+    }
+
+    /// Status and confidence of every result for `port`, by protocol
+    fn port_results(&self, port: u16) -> impl Iterator<Item = (TransportProtocol, &PortStatus, Option<Confidence>)> {
+        let tcp = self.tcp_results.iter().map(|r| (r.port, &r.status, r.confidence));
+        let syn = self.syn_results.iter().map(|r| (r.port, &r.status, r.confidence));
+        let udp = self.udp_results.iter().map(|r| (r.port, &r.status, r.confidence));
+        tcp.chain(syn)
+            .map(|(p, status, confidence)| (p, TransportProtocol::Tcp, status, confidence))
+            .chain(udp.map(|(p, status, confidence)| (p, TransportProtocol::Udp, status, confidence)))
+            .filter(move |(p, ..)| *p == port)
+            .map(|(_, protocol, status, confidence)| (protocol, status, confidence))
+    }
+
+    /// Best confidence among the results for `port` over `protocol`, only
+    /// those that found `status` when given; `None` when no such result
+    /// was scored (e.g. imported results)
+    pub fn port_confidence(
+        &self,
+        port: u16,
+        protocol: TransportProtocol,
+        status: Option<PortStatus>,
+    ) -> Option<Confidence> {
+        self.port_results(port)
+            .filter(|(p, s, _)| *p == protocol && status.as_ref().is_none_or(|status| *s == status))
+            .filter_map(|(.., confidence)| confidence)
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confidence_from_evidence() {
+        let score = |evidence, attempts, corroborated| Confidence::assess(evidence, attempts, corroborated).score();
+        assert_eq!(score(Evidence::Direct, 1, true), 100);
+        assert_eq!(score(Evidence::Direct, 1, false), 90);
+        assert_eq!(score(Evidence::Direct, 3, false), 70);
+        assert_eq!(score(Evidence::Direct, 9, false), 50);
+        assert_eq!(score(Evidence::Icmp, 1, false), 80);
+        assert_eq!(score(Evidence::Silence, 1, false), 30);
+        assert_eq!(score(Evidence::Silence, 4, false), 60);
+        assert_eq!(score(Evidence::Nothing, 1, false), 0);
+
+        assert_eq!(evidence(&PortStatus::Filtered, Some(ProbeError::Timeout), false), Evidence::Silence);
+        assert_eq!(evidence(&PortStatus::Filtered, Some(ProbeError::AdminProhibited), false), Evidence::Icmp);
+        assert_eq!(evidence(&PortStatus::Closed, Some(ProbeError::Refused), true), Evidence::Icmp);
+        assert_eq!(evidence(&PortStatus::Unknown, Some(ProbeError::ResourceExhausted), false), Evidence::Nothing);
+
+        assert_eq!("75%".parse::<Confidence>().unwrap().score(), 75);
+        assert!("101".parse::<Confidence>().is_err());
+        assert!(serde_json::from_str::<Confidence>("120").is_err());
+        assert_eq!(serde_json::to_string(&Confidence::MAX).unwrap(), "100");
+    }
+}
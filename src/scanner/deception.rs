@@ -0,0 +1,242 @@
+//! Deception and honeypot heuristics
+//!
+//! Honeypots and deception grids tend to look "too good": every port open,
+//! the same canned banner everywhere, connections accepted instantly by a
+//! user-space responder. Each heuristic that fires adds to a per-host
+//! suspicion score between 0 and 1.
+
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::CompleteScanResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Score at or above which a host is reported as a likely honeypot
+pub const SUSPICION_THRESHOLD: f64 = 0.5;
+
+/// Open ports above which a host is implausibly exposed on its own
+const MANY_OPEN_PORTS: usize = 100;
+
+/// Minimum probed ports before the open ratio is meaningful
+const MIN_PORTS_FOR_RATIO: usize = 20;
+
+/// Share of probed ports open that counts as implausible
+const OPEN_RATIO: f64 = 0.5;
+
+/// Minimum open ports before banner or RTT uniformity is meaningful
+const MIN_UNIFORM_PORTS: usize = 3;
+
+/// One heuristic that fired for a host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeceptionIndicator {
+    /// Far more open ports than a real service host exposes
+    ManyOpenPorts { open: usize, probed: usize },
+    /// Every open port returned the same banner
+    IdenticalBanners { ports: usize },
+    /// Every open port accepted in under a millisecond, faster than the
+    /// host's other answers show its round trip to be
+    ZeroRttAccepts { ports: usize, baseline_ms: u64 },
+    /// Ports reported open that a second scan type could not confirm
+    UnconfirmedOpenPorts { ports: usize },
+}
+
+impl DeceptionIndicator {
+    fn weight(&self) -> f64 {
+        match self {
+            DeceptionIndicator::ManyOpenPorts { .. } => 0.4,
+            DeceptionIndicator::IdenticalBanners { .. } => 0.3,
+            DeceptionIndicator::ZeroRttAccepts { .. } => 0.2,
+            DeceptionIndicator::UnconfirmedOpenPorts { .. } => 0.3,
+        }
+    }
+}
+
+impl fmt::Display for DeceptionIndicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeceptionIndicator::ManyOpenPorts { open, probed } => {
+                write!(f, "{}/{} probed ports open", open, probed)
+            }
+            DeceptionIndicator::IdenticalBanners { ports } => {
+                write!(f, "identical banner on {} ports", ports)
+            }
+            DeceptionIndicator::ZeroRttAccepts { ports, baseline_ms } => {
+                write!(f, "zero-RTT accepts on {} ports (host RTT {}ms)", ports, baseline_ms)
+            }
+            DeceptionIndicator::UnconfirmedOpenPorts { ports } => {
+                write!(f, "{} open ports not confirmed by verification", ports)
+            }
+        }
+    }
+}
+
+/// Per-host deception suspicion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeceptionAssessment {
+    /// Suspicion score from 0 (nothing unusual) to 1
+    pub score: f64,
+    pub indicators: Vec<DeceptionIndicator>,
+}
+
+impl DeceptionAssessment {
+    /// Evaluate a scanned host; `None` when no heuristic fires
+    pub fn assess(result: &CompleteScanResult) -> Option<Self> {
+        // A port scanned by both connect and SYN counts once, open if either saw it open
+        let mut ports: BTreeMap<u16, bool> = BTreeMap::new();
+        let statuses = result
+            .tcp_results
+            .iter()
+            .map(|r| (r.port, &r.status))
+            .chain(result.syn_results.iter().map(|r| (r.port, &r.status)));
+        for (port, status) in statuses {
+            *ports.entry(port).or_default() |= *status == PortStatus::Open;
+        }
+        let open_tcp: Vec<_> = result.tcp_results.iter().filter(|r| r.status == PortStatus::Open).collect();
+
+        let mut indicators = Vec::new();
+
+        let probed = ports.len();
+        let open = ports.values().filter(|open| **open).count();
+        let ratio_high = probed >= MIN_PORTS_FOR_RATIO && open as f64 / probed as f64 >= OPEN_RATIO;
+        if open >= MANY_OPEN_PORTS || ratio_high {
+            indicators.push(DeceptionIndicator::ManyOpenPorts { open, probed });
+        }
+
+        let banners: Vec<&str> = open_tcp.iter().filter_map(|r| r.banner.as_deref()).collect();
+        if banners.len() >= MIN_UNIFORM_PORTS && banners.len() == open_tcp.len() && banners.iter().all(|b| *b == banners[0]) {
+            indicators.push(DeceptionIndicator::IdenticalBanners { ports: banners.len() });
+        }
+
+        // Accepts are timed to the millisecond, so 0ms only stands out on a
+        // host whose closed ports and SYN probes show a longer round trip;
+        // a LAN or loopback host answers everything in 0ms
+        if let Some(baseline_ms) = rtt_baseline(result).filter(|&rtt| rtt > 0) {
+            if open_tcp.len() >= MIN_UNIFORM_PORTS && open_tcp.iter().all(|r| r.response_time_ms == Some(0)) {
+                indicators.push(DeceptionIndicator::ZeroRttAccepts { ports: open_tcp.len(), baseline_ms });
+            }
+        }
+
+        let unconfirmed = result.disagreements().count();
+        if unconfirmed > 0 {
+            indicators.push(DeceptionIndicator::UnconfirmedOpenPorts { ports: unconfirmed });
+        }
+
+        if indicators.is_empty() {
+            return None;
+        }
+        let score = indicators.iter().map(DeceptionIndicator::weight).sum::<f64>().min(1.0);
+        Some(Self { score, indicators })
+    }
+
+    /// Whether the host is likely a honeypot or deception decoy
+    pub fn is_suspected(&self) -> bool {
+        self.score >= SUSPICION_THRESHOLD
+    }
+}
+
+/// Fastest answer of the host other than an open connect accept: closed
+/// connect ports and SYN probes, which the host's own stack answers
+fn rtt_baseline(result: &CompleteScanResult) -> Option<u64> {
+    result
+        .tcp_results
+        .iter()
+        .filter(|r| r.status != PortStatus::Open)
+        .filter_map(|r| r.response_time_ms)
+        .chain(result.syn_results.iter().filter_map(|r| r.response_time_ms))
+        .min()
+}
+
+impl fmt::Display for DeceptionAssessment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indicators: Vec<String> = self.indicators.iter().map(|i| i.to_string()).collect();
+        write!(f, "{:.2} ({})", self.score, indicators.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::host_discovery::HostStatus;
+    use crate::scanner::tcp_connect::TcpConnectResult;
+    use crate::scanner::tcp_syn::TcpSynResult;
+    use crate::scanner::ProbeError;
+    use std::net::IpAddr;
+
+    fn host(ports: &[(u16, PortStatus, Option<&str>, u64)]) -> CompleteScanResult {
+        let target: IpAddr = "198.51.100.7".parse().unwrap();
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+        result.tcp_results = ports
+            .iter()
+            .map(|(port, status, banner, rtt)| TcpConnectResult {
+                target,
+                port: *port,
+                status: status.clone(),
+                response_time_ms: Some(*rtt),
+                banner: banner.map(String::from),
+                evasion: None,
+                attempts: 1,
+                error: None,
+                service: None,
+                error_class: None,
+                confidence: None,
+                reason: None,
+            })
+            .collect();
+        result
+    }
+
+    #[test]
+    fn test_ordinary_host_not_flagged() {
+        let result = host(&[
+            (22, PortStatus::Open, Some("SSH-2.0-OpenSSH_9.6"), 4),
+            (80, PortStatus::Open, None, 3),
+            (443, PortStatus::Closed, None, 0),
+        ]);
+        assert!(DeceptionAssessment::assess(&result).is_none());
+    }
+
+    #[test]
+    fn test_honeypot_flagged() {
+        let mut ports: Vec<(u16, PortStatus, Option<&str>, u64)> =
+            (1..=30).map(|port| (port, PortStatus::Open, Some("220 Service ready"), 0)).collect();
+        ports.push((31, PortStatus::Closed, None, 12));
+        let assessment = DeceptionAssessment::assess(&host(&ports)).unwrap();
+
+        assert_eq!(assessment.indicators.len(), 3);
+        assert!(assessment.indicators.contains(&DeceptionIndicator::ManyOpenPorts { open: 30, probed: 31 }));
+        assert!(assessment.indicators.contains(&DeceptionIndicator::ZeroRttAccepts { ports: 30, baseline_ms: 12 }));
+        assert!((assessment.score - 0.9).abs() < 1e-9);
+        assert!(assessment.is_suspected());
+    }
+
+    #[test]
+    fn test_lan_host_and_double_scan_not_flagged() {
+        // Sub-millisecond LAN host: its closed ports answer in 0ms too
+        let mut result = host(&[
+            (22, PortStatus::Open, None, 0),
+            (80, PortStatus::Open, None, 0),
+            (443, PortStatus::Open, None, 0),
+            (8080, PortStatus::Closed, None, 0),
+        ]);
+        assert!(DeceptionAssessment::assess(&result).is_none());
+
+        // Connect and SYN over the same ports count each port once: 60 open
+        // of 160, not 120 open
+        let mut ports: Vec<(u16, PortStatus, Option<&str>, u64)> =
+            (1..=60).map(|port| (port, PortStatus::Open, None, 2)).collect();
+        ports.extend((61..=160).map(|port| (port, PortStatus::Closed, None, 2)));
+        result = host(&ports);
+        result.syn_results = result
+            .tcp_results
+            .iter()
+            .map(|r| {
+                let mut syn = TcpSynResult::failed(r.target, r.port, ProbeError::Timeout);
+                syn.status = r.status.clone();
+                syn.error = None;
+                syn
+            })
+            .collect();
+        assert!(DeceptionAssessment::assess(&result).is_none());
+    }
+}
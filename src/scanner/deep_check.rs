@@ -0,0 +1,403 @@
+//! Protocol liveness checks ("deep check") for open ports
+//!
+//! An open TCP port only says something accepted the connection. For
+//! monitoring, a deep check goes one step further and completes a small
+//! exchange in the port's protocol:
+//!
+//! - HTTP: `GET /` and the status line (5xx counts as not alive)
+//! - SMTP: the 220 greeting and a 250 answer to `EHLO`
+//! - FTP: the 220 greeting and an answer to `USER anonymous`
+//! - RDP: an X.224 Connection Confirm to a connection request
+//!
+//! Each check records how long the exchange took and, when it failed, the
+//! protocol error.
+
+use crate::config::DeepCheckConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+#[cfg(not(feature = "no-net"))]
+use {
+    super::tcp_connect::{PortStatus, TcpConnectResult},
+    super::tcp_syn::TcpSynResult,
+    futures::stream::{self, StreamExt},
+    std::collections::BTreeSet,
+    std::net::IpAddr,
+    std::time::{Duration, Instant},
+    tokio::net::TcpStream,
+    tracing::debug,
+};
+
+/// Longest reply line read from a service
+const MAX_LINE: u64 = 1024;
+
+/// Lines read from one multi-line SMTP/FTP reply before giving up
+const MAX_REPLY_LINES: usize = 64;
+
+/// X.224 Connection Request carrying an RDP negotiation request for TLS
+/// and CredSSP
+const RDP_CONNECTION_REQUEST: [u8; 19] = [
+    0x03, 0x00, 0x00, 0x13, // TPKT, 19 bytes
+    0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, // X.224 Connection Request
+    0x01, 0x00, 0x08, 0x00, 0x03, 0x00, 0x00, 0x00, // RDP_NEG_REQ
+];
+
+/// Protocol spoken in a deep check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LivenessProtocol {
+    Http,
+    Smtp,
+    Ftp,
+    Rdp,
+}
+
+impl LivenessProtocol {
+    /// Protocol to check on a port, from its service name or well-known port
+    pub fn for_port(port: u16, service: Option<&str>) -> Option<Self> {
+        let by_service = match service {
+            Some("http" | "http-alt" | "http-proxy" | "www" | "www-http") => Some(Self::Http),
+            Some("smtp" | "submission") => Some(Self::Smtp),
+            Some("ftp") => Some(Self::Ftp),
+            Some("ms-wbt-server" | "rdp") => Some(Self::Rdp),
+            _ => None,
+        };
+        by_service.or(match port {
+            80 | 8000 | 8008 | 8080 | 8888 => Some(Self::Http),
+            25 | 587 | 2525 => Some(Self::Smtp),
+            21 => Some(Self::Ftp),
+            3389 => Some(Self::Rdp),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for LivenessProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Http => "http",
+            Self::Smtp => "smtp",
+            Self::Ftp => "ftp",
+            Self::Rdp => "rdp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Outcome of a deep check on one open port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivenessCheck {
+    pub port: u16,
+    pub protocol: LivenessProtocol,
+    /// The service completed the exchange
+    pub alive: bool,
+    /// Time from connecting until the exchange completed or failed
+    pub latency_ms: u64,
+    /// Significant line of the answer, e.g. the HTTP status line or the
+    /// SMTP greeting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Why the exchange failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl fmt::Display for LivenessCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.alive { "alive" } else { "not alive" };
+        write!(f, "{}/tcp {} {} ({}ms)", self.port, self.protocol, state, self.latency_ms)?;
+        if let Some(ref error) = self.error {
+            write!(f, ": {}", error)?;
+        }
+        if let Some(ref response) = self.response {
+            write!(f, " [{}]", response)?;
+        }
+        Ok(())
+    }
+}
+
+/// A failed exchange: the last answer received, if any, and the error
+#[derive(Debug)]
+struct Failure {
+    response: Option<String>,
+    error: String,
+}
+
+impl Failure {
+    fn new(error: impl Into<String>) -> Self {
+        Self { response: None, error: error.into() }
+    }
+
+    fn after(response: &str, error: impl Into<String>) -> Self {
+        Self {
+            response: Some(response.to_string()),
+            error: error.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(e.to_string())
+    }
+}
+
+/// Run the exchange for `protocol` over a connected stream
+///
+/// `host` is sent as the HTTP `Host` header. On success the significant
+/// answer line is returned.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(protocol: LivenessProtocol, host: &str, stream: S) -> Result<String, Failure> {
+    let mut stream = BufReader::new(stream);
+    match protocol {
+        LivenessProtocol::Http => http(&mut stream, host).await,
+        LivenessProtocol::Smtp => smtp(&mut stream).await,
+        LivenessProtocol::Ftp => ftp(&mut stream).await,
+        LivenessProtocol::Rdp => rdp(&mut stream).await,
+    }
+}
+
+async fn http<S: AsyncBufRead + AsyncWrite + Unpin>(stream: &mut S, host: &str) -> Result<String, Failure> {
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: {}\r\nUser-Agent: nrmap/{}\r\nConnection: close\r\n\r\n",
+        host,
+        crate::VERSION
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let status_line = read_line(stream).await?;
+    let code = status_line
+        .strip_prefix("HTTP/")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| Failure::after(&status_line, "not an HTTP response"))?;
+    if code >= 500 {
+        return Err(Failure::after(&status_line, format!("server error {}", code)));
+    }
+    Ok(status_line)
+}
+
+async fn smtp<S: AsyncBufRead + AsyncWrite + Unpin>(stream: &mut S) -> Result<String, Failure> {
+    let (code, greeting) = read_reply(stream).await?;
+    if code != 220 {
+        return Err(Failure::after(&greeting, format!("service not ready ({})", code)));
+    }
+    let failed = |e: Failure| Failure::after(&greeting, e.error);
+    stream.write_all(b"EHLO nrmap.invalid\r\n").await.map_err(|e| failed(e.into()))?;
+    let (code, reply) = read_reply(stream).await.map_err(failed)?;
+    if code != 250 {
+        return Err(Failure::after(&greeting, format!("EHLO rejected: {}", reply)));
+    }
+    let _ = stream.write_all(b"QUIT\r\n").await;
+    Ok(greeting)
+}
+
+async fn ftp<S: AsyncBufRead + AsyncWrite + Unpin>(stream: &mut S) -> Result<String, Failure> {
+    let (code, greeting) = read_reply(stream).await?;
+    if code != 220 {
+        return Err(Failure::after(&greeting, format!("service not ready ({})", code)));
+    }
+    let failed = |e: Failure| Failure::after(&greeting, e.error);
+    stream.write_all(b"USER anonymous\r\n").await.map_err(|e| failed(e.into()))?;
+    let (code, reply) = read_reply(stream).await.map_err(failed)?;
+    // A refused anonymous login (530) still shows a working command loop
+    if !matches!(code, 200..=399 | 530) {
+        return Err(Failure::after(&greeting, format!("USER failed: {}", reply)));
+    }
+    let _ = stream.write_all(b"QUIT\r\n").await;
+    Ok(greeting)
+}
+
+async fn rdp<S: AsyncBufRead + AsyncWrite + Unpin>(stream: &mut S) -> Result<String, Failure> {
+    stream.write_all(&RDP_CONNECTION_REQUEST).await?;
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+    if header[0] != 0x03 || !(7..=512).contains(&length) {
+        return Err(Failure::new("not a TPKT response"));
+    }
+    let mut body = vec![0u8; length - 4];
+    stream.read_exact(&mut body).await?;
+    if body[1] & 0xf0 != 0xd0 {
+        return Err(Failure::new(format!("X.224 answer 0x{:02x} instead of Connection Confirm", body[1])));
+    }
+    // Optional RDP_NEG_RSP (0x02) or RDP_NEG_FAILURE (0x03) after the header
+    let negotiated = match body.get(7..15) {
+        Some(&[0x02, _, _, _, protocol, ..]) => match protocol {
+            0 => "standard RDP security",
+            1 => "TLS",
+            2 | 8 | 10 => "CredSSP",
+            _ => "other security protocol",
+        },
+        Some(&[0x03, ..]) => "security negotiation refused",
+        _ => "no negotiation",
+    };
+    Ok(format!("X.224 Connection Confirm, {}", negotiated))
+}
+
+/// One line, without the line ending
+async fn read_line<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<String, Failure> {
+    let mut line = String::new();
+    if (&mut *stream).take(MAX_LINE).read_line(&mut line).await? == 0 {
+        return Err(Failure::new("connection closed without an answer"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Code and first line of an SMTP/FTP reply, reading past `NNN-`
+/// continuation lines
+async fn read_reply<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<(u16, String), Failure> {
+    let first = read_line(stream).await?;
+    let code = first
+        .get(..3)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| Failure::after(&first, "not a reply line"))?;
+    if first.as_bytes().get(3) != Some(&b'-') {
+        return Ok((code, first));
+    }
+    let last = format!("{} ", code);
+    for _ in 0..MAX_REPLY_LINES {
+        let line = read_line(stream).await?;
+        if line.starts_with(&last) || line == last.trim_end() {
+            return Ok((code, first));
+        }
+    }
+    Err(Failure::after(&first, "reply too long"))
+}
+
+/// Deep check one port: connect, run the exchange and time it
+#[cfg(not(feature = "no-net"))]
+pub async fn check(target: IpAddr, port: u16, protocol: LivenessProtocol, timeout_ms: u64) -> LivenessCheck {
+    let start = Instant::now();
+    let host = match target {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => format!("[{}]", v6),
+    };
+    let attempt = async {
+        let stream = TcpStream::connect((target, port)).await?;
+        exchange(protocol, &host, stream).await
+    };
+    let outcome = tokio::time::timeout(Duration::from_millis(timeout_ms), attempt)
+        .await
+        .unwrap_or_else(|_| Err(Failure::new(format!("no answer within {}ms", timeout_ms))));
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    let check = match outcome {
+        Ok(response) => LivenessCheck {
+            port,
+            protocol,
+            alive: true,
+            latency_ms,
+            response: Some(response),
+            error: None,
+        },
+        Err(failure) => LivenessCheck {
+            port,
+            protocol,
+            alive: false,
+            latency_ms,
+            response: failure.response,
+            error: Some(failure.error),
+        },
+    };
+    debug!("Deep check of {}: {}", target, check);
+    check
+}
+
+/// Deep check every open TCP port of a host that speaks a known protocol
+#[cfg(not(feature = "no-net"))]
+pub async fn check_open_ports(
+    target: IpAddr,
+    tcp_results: &[TcpConnectResult],
+    syn_results: &[TcpSynResult],
+    config: &DeepCheckConfig,
+) -> Vec<LivenessCheck> {
+    let open: BTreeSet<(u16, LivenessProtocol)> = tcp_results
+        .iter()
+        .filter(|r| r.status == PortStatus::Open)
+        .map(|r| (r.port, r.service.as_deref()))
+        .chain(
+            syn_results
+                .iter()
+                .filter(|r| r.status == PortStatus::Open)
+                .map(|r| (r.port, r.service.as_deref())),
+        )
+        .filter_map(|(port, service)| Some((port, LivenessProtocol::for_port(port, service)?)))
+        .collect();
+
+    let mut checks: Vec<LivenessCheck> = stream::iter(open)
+        .map(|(port, protocol)| check(target, port, protocol, config.timeout_ms))
+        .buffer_unordered(config.max_concurrent.max(1))
+        .collect()
+        .await;
+    checks.sort_by_key(|check| check.port);
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    /// Run `protocol` against a scripted server: for each expected client
+    /// line (`None` for none), send the canned answer
+    async fn converse(protocol: LivenessProtocol, script: &'static [(Option<&'static str>, &'static [u8])]) -> Result<String, Failure> {
+        let (client, server) = duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            for (expected, answer) in script {
+                if let Some(expected) = expected {
+                    let mut line = String::new();
+                    server.read_line(&mut line).await.unwrap();
+                    assert!(line.starts_with(expected), "client sent {:?}", line);
+                }
+                server.write_all(answer).await.unwrap();
+            }
+        });
+        let outcome = exchange(protocol, "192.0.2.1", client).await;
+        server.await.unwrap();
+        outcome
+    }
+
+    #[tokio::test]
+    async fn test_protocol_exchanges() {
+        let ok = converse(LivenessProtocol::Http, &[(Some("GET / HTTP/1.1"), b"HTTP/1.1 200 OK\r\n\r\n")]).await;
+        assert_eq!(ok.unwrap(), "HTTP/1.1 200 OK");
+        let down = converse(LivenessProtocol::Http, &[(Some("GET"), b"HTTP/1.0 503 Service Unavailable\r\n")]).await;
+        assert_eq!(down.unwrap_err().error, "server error 503");
+
+        let smtp = converse(
+            LivenessProtocol::Smtp,
+            &[
+                (None, b"220 mail.example ESMTP\r\n"),
+                (Some("EHLO"), b"250-mail.example\r\n250-PIPELINING\r\n250 SIZE 10240000\r\n"),
+            ],
+        )
+        .await;
+        assert_eq!(smtp.unwrap(), "220 mail.example ESMTP");
+        let busy = converse(LivenessProtocol::Smtp, &[(None, b"421 too busy\r\n")]).await.unwrap_err();
+        assert_eq!(busy.response.as_deref(), Some("421 too busy"));
+
+        let ftp = converse(
+            LivenessProtocol::Ftp,
+            &[(None, b"220-Welcome\r\n220 FTP ready\r\n"), (Some("USER anonymous"), b"530 Login incorrect\r\n")],
+        )
+        .await;
+        assert_eq!(ftp.unwrap(), "220-Welcome");
+
+        let confirm: &[u8] = &[
+            0x03, 0x00, 0x00, 0x13, 0x0e, 0xd0, 0x00, 0x00, 0x12, 0x34, 0x00, 0x02, 0x00, 0x08, 0x00, 0x02, 0x00, 0x00, 0x00,
+        ];
+        let (client, mut server) = duplex(4096);
+        let rdp = tokio::spawn(async move { exchange(LivenessProtocol::Rdp, "192.0.2.1", client).await });
+        let mut request = [0u8; 19];
+        server.read_exact(&mut request).await.unwrap();
+        assert_eq!(request, RDP_CONNECTION_REQUEST);
+        server.write_all(confirm).await.unwrap();
+        assert_eq!(rdp.await.unwrap().unwrap(), "X.224 Connection Confirm, CredSSP");
+
+        let garbage = converse(LivenessProtocol::Http, &[(Some("GET"), b"SSH-2.0-OpenSSH_9.6\r\n")]).await;
+        assert_eq!(garbage.unwrap_err().error, "not an HTTP response");
+        assert_eq!(LivenessProtocol::for_port(8443, Some("http")), Some(LivenessProtocol::Http));
+        assert_eq!(LivenessProtocol::for_port(3389, None), Some(LivenessProtocol::Rdp));
+        assert_eq!(LivenessProtocol::for_port(22, Some("ssh")), None);
+    }
+}
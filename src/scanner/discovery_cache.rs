@@ -0,0 +1,245 @@
+//! Host liveness cache across scan invocations
+//!
+//! Repeated sweeps of the same network (monitor mode, cron jobs) spend most
+//! of their time waiting for probe timeouts on hosts that were down the last
+//! time too. With the cache enabled, a host found down and without open
+//! ports is remembered for a TTL, and scans within that window report it
+//! down straight away instead of probing it again. A host found up, or
+//! showing any open port, drops out of the cache.
+//!
+//! The cache is a JSON file next to the `--history` store, or in the user
+//! config directory when no history is kept. Changes are collected in memory
+//! and written once per scan run: merged into the file as it is then, so
+//! concurrent runs keep each other's entries, and replaced atomically. A
+//! file that does not parse is logged and started afresh.
+
+use super::host_discovery::HostStatus;
+use super::CompleteScanResult;
+use crate::config::DiscoveryCacheConfig;
+use crate::error::{ScanError, ScanResult};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// File name of the on-disk cache in the user config directory
+pub const DISCOVERY_CACHE_FILE: &str = "discovery-cache.json";
+
+/// Cache file kept next to an asset history file: `assets.json` gets
+/// `assets.discovery.json`
+pub fn path_beside_history(history: &Path) -> PathBuf {
+    let stem = history.file_stem().map_or_else(|| "history".into(), |stem| stem.to_string_lossy());
+    history.with_file_name(format!("{}.discovery.json", stem))
+}
+
+/// Default on-disk cache location, next to the user config file
+pub fn default_cache_path() -> Option<PathBuf> {
+    Some(crate::config::user_config_path()?.with_file_name(DISCOVERY_CACHE_FILE))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Hosts recently found down, with the Unix time they were last probed
+#[derive(Debug)]
+pub struct DiscoveryCache {
+    ttl: Duration,
+    path: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    down: BTreeMap<IpAddr, u64>,
+    /// Entries set (`Some`) or removed (`None`) since the last flush
+    changed: BTreeMap<IpAddr, Option<u64>>,
+}
+
+impl DiscoveryCache {
+    /// In-memory cache, shared by the scans of one process
+    pub fn in_memory(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            path: None,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Cache persisted to `path`, loading its unexpired entries
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration) -> ScanResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut cache = Self::in_memory(ttl);
+        let down = cache.load(&path)?;
+        debug!("Loaded {} cached down hosts from {:?}", down.len(), path);
+        cache.state.get_mut().unwrap_or_else(|e| e.into_inner()).down = down;
+        cache.path = Some(path);
+        Ok(cache)
+    }
+
+    /// Unexpired entries of the file at `path`; none when it is missing or
+    /// does not parse
+    fn load(&self, path: &Path) -> ScanResult<BTreeMap<IpAddr, u64>> {
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(path)?;
+        match serde_json::from_str::<BTreeMap<IpAddr, u64>>(&content) {
+            Ok(stored) => Ok(stored.into_iter().filter(|&(_, at)| !self.expired(at)).collect()),
+            Err(e) => {
+                warn!("Discovery cache {:?} is corrupt, starting afresh: {}", path, e);
+                Ok(BTreeMap::new())
+            }
+        }
+    }
+
+    /// Cache for the configured settings; `history` is the `--history` file,
+    /// if any. A file that cannot be read leaves an in-memory cache.
+    pub fn from_config(config: &DiscoveryCacheConfig, history: Option<&Path>) -> Self {
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let path = config
+            .path
+            .clone()
+            .or_else(|| history.map(path_beside_history))
+            .or_else(default_cache_path);
+        match path {
+            Some(path) => Self::open(&path, ttl).unwrap_or_else(|e| {
+                warn!("Discovery cache {:?} unusable, keeping it in memory: {}", path, e);
+                Self::in_memory(ttl)
+            }),
+            None => Self::in_memory(ttl),
+        }
+    }
+
+    fn expired(&self, at: u64) -> bool {
+        now_secs() >= at.saturating_add(self.ttl.as_secs())
+    }
+
+    /// How long ago `target` was found down, if that is within the TTL
+    pub fn down_since(&self, target: IpAddr) -> Option<Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let at = *state.down.get(&target)?;
+        (!self.expired(at)).then(|| Duration::from_secs(now_secs().saturating_sub(at)))
+    }
+
+    /// Remember a host that was down with no open port, or forget one that
+    /// answered; [`DiscoveryCache::flush`] writes the change to the file
+    pub fn record(&self, result: &CompleteScanResult) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = (result.host_status == HostStatus::Down && result.open_port_count() == 0).then(now_secs);
+        if state.down.get(&result.target).copied() == entry {
+            return;
+        }
+        match entry {
+            Some(at) => state.down.insert(result.target, at),
+            None => state.down.remove(&result.target),
+        };
+        state.changed.insert(result.target, entry);
+    }
+
+    /// Merge the changes recorded since the last flush into the cache file,
+    /// if there is one
+    pub fn flush(&self) -> ScanResult<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.changed.is_empty() {
+            return Ok(());
+        }
+        let mut down = self.load(path)?;
+        for (&target, &entry) in &state.changed {
+            match entry {
+                Some(at) => down.insert(target, at),
+                None => down.remove(&target),
+            };
+        }
+        down.retain(|_, at| !self.expired(*at));
+        Self::save(path, &down)?;
+        state.down = down;
+        state.changed.clear();
+        Ok(())
+    }
+
+    /// Write through a temporary file, so readers never see half a cache
+    fn save(path: &Path, down: &BTreeMap<IpAddr, u64>) -> ScanResult<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(down)
+            .map_err(|e| ScanError::ScannerError { message: format!("JSON serialization failed: {}", e) })?;
+        let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&temp, json)?;
+        if let Err(e) = fs::rename(&temp, path) {
+            let _ = fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Number of cached down hosts, expired ones included
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).down.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_down_hosts_cached_between_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join("assets.json");
+        let path = path_beside_history(&history);
+        assert_eq!(path, dir.path().join("assets.discovery.json"));
+
+        let down: IpAddr = "10.0.0.1".parse().unwrap();
+        let up: IpAddr = "10.0.0.2".parse().unwrap();
+        let cache = DiscoveryCache::open(&path, Duration::from_secs(600)).unwrap();
+        cache.record(&CompleteScanResult::unscanned(down, HostStatus::Down, Duration::ZERO));
+        cache.record(&CompleteScanResult::unscanned(up, HostStatus::Up, Duration::ZERO));
+        assert!(!path.exists());
+        cache.flush().unwrap();
+
+        let config = DiscoveryCacheConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let reopened = DiscoveryCache::from_config(&config, Some(&history));
+        assert!(reopened.down_since(down).is_some());
+        assert!(reopened.down_since(up).is_none());
+
+        // A host that answers again drops out
+        reopened.record(&CompleteScanResult::unscanned(down, HostStatus::Up, Duration::ZERO));
+        reopened.flush().unwrap();
+        assert!(DiscoveryCache::open(&path, Duration::from_secs(600)).unwrap().is_empty());
+
+        // Runs sharing the file keep each other's entries
+        let other: IpAddr = "10.0.0.3".parse().unwrap();
+        cache.record(&CompleteScanResult::unscanned(other, HostStatus::Down, Duration::ZERO));
+        reopened.record(&CompleteScanResult::unscanned(up, HostStatus::Down, Duration::ZERO));
+        cache.flush().unwrap();
+        reopened.flush().unwrap();
+        assert_eq!(DiscoveryCache::open(&path, Duration::from_secs(600)).unwrap().len(), 2);
+
+        // A corrupt file is replaced rather than disabling the cache
+        std::fs::write(&path, "{not json").unwrap();
+        let recovered = DiscoveryCache::from_config(&config, Some(&history));
+        assert!(recovered.is_empty());
+        recovered.record(&CompleteScanResult::unscanned(down, HostStatus::Down, Duration::ZERO));
+        recovered.flush().unwrap();
+        assert!(DiscoveryCache::open(&path, Duration::from_secs(600)).unwrap().down_since(down).is_some());
+
+        let expired = DiscoveryCache::in_memory(Duration::ZERO);
+        expired.record(&CompleteScanResult::unscanned(down, HostStatus::Down, Duration::ZERO));
+        assert!(expired.down_since(down).is_none());
+    }
+}
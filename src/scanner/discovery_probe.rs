@@ -0,0 +1,130 @@
+//! Pluggable host discovery probes
+//!
+//! Library users can implement [`DiscoveryProbe`] to add their own liveness
+//! checks (an internal CMDB, a cloud provider API, ...). Registered probes vote
+//! alongside the built-in ICMP/TCP/UDP/ARP method and a [`ConsensusPolicy`]
+//! combines the votes into the final host status.
+
+use crate::error::ScanResult;
+use crate::scanner::host_discovery::HostStatus;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Custom host discovery logic
+///
+/// Return `HostStatus::Unknown` when the probe has no opinion about a target;
+/// unknown votes and errors abstain from the consensus.
+#[async_trait]
+pub trait DiscoveryProbe: Send + Sync {
+    /// Short name recorded in discovery results (e.g. "cmdb")
+    fn name(&self) -> &str;
+
+    /// Decide whether a target is up
+    async fn probe(&self, target: IpAddr) -> ScanResult<HostStatus>;
+}
+
+/// A single probe's verdict for a target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeVote {
+    pub probe: String,
+    pub status: HostStatus,
+}
+
+impl ProbeVote {
+    pub fn new(probe: impl Into<String>, status: HostStatus) -> Self {
+        Self {
+            probe: probe.into(),
+            status,
+        }
+    }
+}
+
+/// How votes from several discovery probes are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusPolicy {
+    /// Up if any probe says up
+    #[default]
+    Any,
+    /// Up only if every deciding probe says up
+    All,
+    /// Up if more than half of the deciding probes say up
+    Majority,
+    /// Up if at least this many probes say up
+    AtLeast(usize),
+}
+
+impl ConsensusPolicy {
+    /// Combine probe votes into a host status
+    ///
+    /// Probes voting `Unknown` abstain; with no deciding votes the result is
+    /// `Unknown`.
+    pub fn decide(&self, votes: &[ProbeVote]) -> HostStatus {
+        let up = votes.iter().filter(|v| v.status == HostStatus::Up).count();
+        let deciding = votes.iter().filter(|v| v.status != HostStatus::Unknown).count();
+
+        if deciding == 0 {
+            return HostStatus::Unknown;
+        }
+
+        let is_up = match *self {
+            ConsensusPolicy::Any => up >= 1,
+            ConsensusPolicy::All => up == deciding,
+            ConsensusPolicy::Majority => up * 2 > deciding,
+            ConsensusPolicy::AtLeast(required) => up >= required.max(1),
+        };
+
+        if is_up {
+            HostStatus::Up
+        } else {
+            HostStatus::Down
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(statuses: &[HostStatus]) -> Vec<ProbeVote> {
+        statuses
+            .iter()
+            .enumerate()
+            .map(|(i, s)| ProbeVote::new(format!("probe{}", i), s.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_consensus_policies() {
+        let mixed = votes(&[HostStatus::Up, HostStatus::Down, HostStatus::Down]);
+        assert_eq!(ConsensusPolicy::Any.decide(&mixed), HostStatus::Up);
+        assert_eq!(ConsensusPolicy::All.decide(&mixed), HostStatus::Down);
+        assert_eq!(ConsensusPolicy::Majority.decide(&mixed), HostStatus::Down);
+        assert_eq!(ConsensusPolicy::AtLeast(1).decide(&mixed), HostStatus::Up);
+        assert_eq!(ConsensusPolicy::AtLeast(2).decide(&mixed), HostStatus::Down);
+    }
+
+    #[test]
+    fn test_unknown_votes_abstain() {
+        let abstaining = votes(&[HostStatus::Up, HostStatus::Unknown]);
+        assert_eq!(ConsensusPolicy::All.decide(&abstaining), HostStatus::Up);
+        assert_eq!(ConsensusPolicy::Majority.decide(&abstaining), HostStatus::Up);
+
+        let no_opinion = votes(&[HostStatus::Unknown]);
+        assert_eq!(ConsensusPolicy::Any.decide(&no_opinion), HostStatus::Unknown);
+    }
+
+    #[test]
+    fn test_consensus_policy_serde() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            consensus: ConsensusPolicy,
+        }
+
+        let parsed: Wrapper = toml::from_str("consensus = \"majority\"").unwrap();
+        assert_eq!(parsed.consensus, ConsensusPolicy::Majority);
+        let parsed: Wrapper = toml::from_str("consensus = { at_least = 2 }").unwrap();
+        assert_eq!(parsed.consensus, ConsensusPolicy::AtLeast(2));
+    }
+}
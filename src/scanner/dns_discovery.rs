@@ -0,0 +1,473 @@
+//! DNS target enumeration
+//!
+//! Candidate targets often have names long before anyone writes down their
+//! addresses. Three sources are supported:
+//!
+//! - PTR sweeps over a CIDR block, through the system resolver
+//! - zone transfers (AXFR) from a named nameserver, which many internal
+//!   servers still allow
+//! - forward brute force of `WORD.DOMAIN` from a wordlist, with wildcard
+//!   records detected and filtered out
+//!
+//! Every name found becomes a [`DnsTarget`]. Its JSON line is a record that
+//! [`parse_target_list`](super::targets::parse_target_list) reads, so
+//! `nrmap dns-enum ... | nrmap scan-file -f -` scans the result directly.
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[cfg(not(feature = "no-net"))]
+use {
+    crate::enrichment::ReverseDnsResolver,
+    futures::stream::{self, StreamExt},
+    std::time::Duration,
+    tokio::io::{AsyncReadExt, AsyncWriteExt},
+    tokio::net::TcpStream,
+    tracing::{debug, info, warn},
+};
+
+/// Record types the enumeration asks for or reads
+const TYPE_A: u16 = 1;
+const TYPE_SOA: u16 = 6;
+const TYPE_AAAA: u16 = 28;
+const TYPE_AXFR: u16 = 252;
+const CLASS_IN: u16 = 1;
+
+/// Labels tried when no wordlist is given
+pub const DEFAULT_WORDS: &[&str] = &[
+    "www", "mail", "smtp", "imap", "pop", "mx", "ns1", "ns2", "dns", "vpn", "remote", "gw", "gateway", "fw",
+    "proxy", "web", "api", "app", "portal", "intranet", "admin", "dev", "test", "staging", "git", "ci", "jenkins",
+    "jira", "wiki", "db", "sql", "mysql", "backup", "files", "ftp", "nas", "storage", "ldap", "ad", "dc", "exchange",
+    "owa", "autodiscover", "monitor", "grafana", "kibana", "log", "syslog", "printer", "voip",
+];
+
+/// How a name was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsSource {
+    Ptr,
+    Axfr,
+    Brute,
+}
+
+impl fmt::Display for DnsSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DnsSource::Ptr => "ptr",
+            DnsSource::Axfr => "axfr",
+            DnsSource::Brute => "brute",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A name and the addresses it resolves to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsTarget {
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub a: Vec<Ipv4Addr>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aaaa: Vec<Ipv6Addr>,
+    pub source: DnsSource,
+}
+
+impl DnsTarget {
+    fn new(host: &str, addresses: impl IntoIterator<Item = IpAddr>, source: DnsSource) -> Self {
+        let mut target = Self {
+            host: host.trim_end_matches('.').to_lowercase(),
+            a: Vec::new(),
+            aaaa: Vec::new(),
+            source,
+        };
+        target.add(addresses);
+        target
+    }
+
+    fn add(&mut self, addresses: impl IntoIterator<Item = IpAddr>) {
+        for address in addresses {
+            match address {
+                IpAddr::V4(v4) if !self.a.contains(&v4) => self.a.push(v4),
+                IpAddr::V6(v6) if !self.aaaa.contains(&v6) => self.aaaa.push(v6),
+                _ => {}
+            }
+        }
+    }
+
+    /// Every address of the name
+    pub fn addresses(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.a.iter().map(|a| IpAddr::V4(*a)).chain(self.aaaa.iter().map(|a| IpAddr::V6(*a)))
+    }
+
+    /// One line of a target list
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Combine findings per name, keeping the source that found it first;
+/// names without any address are dropped
+pub fn merge_targets(targets: impl IntoIterator<Item = DnsTarget>) -> Vec<DnsTarget> {
+    let mut merged: BTreeMap<String, DnsTarget> = BTreeMap::new();
+    for target in targets {
+        match merged.get_mut(&target.host) {
+            Some(known) => known.add(target.addresses().collect::<Vec<_>>()),
+            None => {
+                merged.insert(target.host.clone(), target);
+            }
+        }
+    }
+    merged.into_values().filter(|t| t.addresses().next().is_some()).collect()
+}
+
+/// `ZONE@NAMESERVER[:PORT]` of `--axfr`
+pub fn parse_axfr_spec(spec: &str) -> ScanResult<(String, String)> {
+    match spec.split_once('@') {
+        Some((zone, server)) if !zone.is_empty() && !server.is_empty() => {
+            let server = match server.parse::<IpAddr>() {
+                Ok(IpAddr::V6(v6)) => format!("[{}]:53", v6),
+                Ok(IpAddr::V4(v4)) => format!("{}:53", v4),
+                Err(_) if server.contains(':') => server.to_string(),
+                Err(_) => format!("{}:53", server),
+            };
+            Ok((zone.trim_end_matches('.').to_string(), server))
+        }
+        _ => Err(ScanError::validation_error("axfr", format!("expected ZONE@NAMESERVER, got {:?}", spec))),
+    }
+}
+
+/// A DNS query for `name`; recursion is asked for except on zone transfers
+fn encode_query(id: u16, name: &str, qtype: u16) -> ScanResult<Vec<u8>> {
+    let flags: u16 = if qtype == TYPE_AXFR { 0 } else { 0x0100 };
+    let mut packet = Vec::with_capacity(18 + name.len());
+    for field in [id, flags, 1, 0, 0, 0] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+    for label in name.trim_end_matches('.').split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            return Err(ScanError::validation_error("name", format!("label {:?} is longer than 63 bytes", label)));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(packet)
+}
+
+/// A resource record of interest in a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Record {
+    Address(String, IpAddr),
+    Soa,
+    Other,
+}
+
+fn malformed(what: &str) -> ScanError {
+    ScanError::network(format!("Malformed DNS response: {}", what))
+}
+
+/// Read a possibly compressed name at `pos`, returning it and the offset
+/// after it
+pub(crate) fn read_name(message: &[u8], mut pos: usize) -> ScanResult<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    for _ in 0..128 {
+        let len = *message.get(pos).ok_or_else(|| malformed("truncated name"))? as usize;
+        match len {
+            0 => {
+                return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *message.get(pos + 1).ok_or_else(|| malformed("truncated pointer"))? as usize;
+                end.get_or_insert(pos + 2);
+                pos = ((len & 0x3f) << 8) | low;
+            }
+            len => {
+                let label = message.get(pos + 1..pos + 1 + len).ok_or_else(|| malformed("truncated label"))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+        }
+    }
+    Err(malformed("name compression loop"))
+}
+
+fn read_u16(message: &[u8], pos: usize) -> ScanResult<u16> {
+    message
+        .get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| malformed("truncated record"))
+}
+
+/// Answer records of one response message
+fn parse_answers(message: &[u8]) -> ScanResult<Vec<Record>> {
+    let flags = read_u16(message, 2)?;
+    match flags & 0x000f {
+        0 => {}
+        5 => return Err(ScanError::network("transfer refused by the nameserver")),
+        3 => return Err(ScanError::network("name does not exist")),
+        rcode => return Err(ScanError::network(format!("DNS error response (rcode {})", rcode))),
+    }
+    let questions = read_u16(message, 4)?;
+    let answers = read_u16(message, 6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(message, pos)?.1 + 4;
+    }
+    let mut records = Vec::with_capacity(answers as usize);
+    for _ in 0..answers {
+        let (name, after) = read_name(message, pos)?;
+        let rtype = read_u16(message, after)?;
+        let rdlength = read_u16(message, after + 8)? as usize;
+        let data = message
+            .get(after + 10..after + 10 + rdlength)
+            .ok_or_else(|| malformed("truncated record data"))?;
+        records.push(match (rtype, data.len()) {
+            (TYPE_A, 4) => Record::Address(name, IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))),
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = data.try_into().unwrap_or_default();
+                Record::Address(name, IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            (TYPE_SOA, _) => Record::Soa,
+            _ => Record::Other,
+        });
+        pos = after + 10 + rdlength;
+    }
+    Ok(records)
+}
+
+/// Enumerates names with a per-lookup timeout and bounded concurrency
+#[cfg(not(feature = "no-net"))]
+#[derive(Debug, Clone)]
+pub struct DnsEnumerator {
+    timeout: Duration,
+    concurrency: usize,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl DnsEnumerator {
+    pub fn new(timeout_ms: u64, concurrency: usize) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// PTR names of every address in a block (`10.0.0.0/24`)
+    pub async fn ptr_sweep(&self, block: &str) -> ScanResult<Vec<DnsTarget>> {
+        let addresses = super::targets::expand_target(block)?;
+        info!("PTR sweep over {} addresses of {}", addresses.len(), block);
+        let resolver = ReverseDnsResolver::new(self.timeout.as_millis() as u64);
+        let found: Vec<DnsTarget> = stream::iter(addresses)
+            .map(|address| {
+                let resolver = resolver.clone();
+                async move {
+                    let name = resolver.resolve(address).await?;
+                    Some(DnsTarget::new(&name, [address], DnsSource::Ptr))
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .filter_map(std::future::ready)
+            .collect()
+            .await;
+        info!("{} of {} have PTR records", found.len(), block);
+        Ok(found)
+    }
+
+    /// Address records of a zone transferred from `server` (`host:port`)
+    pub async fn zone_transfer(&self, zone: &str, server: &str) -> ScanResult<Vec<DnsTarget>> {
+        info!("Requesting a zone transfer of {} from {}", zone, server);
+        let mut stream = tokio::time::timeout(self.timeout, TcpStream::connect(server))
+            .await
+            .map_err(|_| ScanError::timeout(self.timeout.as_millis() as u64))?
+            .map_err(|e| ScanError::network(format!("{}: {}", server, e)))?;
+
+        let query = encode_query(rand::random(), zone, TYPE_AXFR)?;
+        let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(&query);
+        stream.write_all(&framed).await?;
+
+        // The transfer opens and closes with the zone's SOA record
+        let mut targets: BTreeMap<String, DnsTarget> = BTreeMap::new();
+        let mut soa_seen = 0;
+        while soa_seen < 2 {
+            let mut len = [0u8; 2];
+            match tokio::time::timeout(self.timeout, stream.read_exact(&mut len)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof && soa_seen > 0 => break,
+                Ok(Err(e)) => return Err(ScanError::network(format!("{}: {}", server, e))),
+                Err(_) => return Err(ScanError::timeout(self.timeout.as_millis() as u64)),
+            }
+            let mut message = vec![0u8; u16::from_be_bytes(len) as usize];
+            tokio::time::timeout(self.timeout, stream.read_exact(&mut message))
+                .await
+                .map_err(|_| ScanError::timeout(self.timeout.as_millis() as u64))??;
+
+            let records = parse_answers(&message).map_err(|e| ScanError::network(format!("{} from {}: {}", zone, server, e)))?;
+            if records.is_empty() && soa_seen == 0 {
+                return Err(ScanError::network(format!("{} returned an empty transfer of {}", server, zone)));
+            }
+            for record in records {
+                match record {
+                    Record::Soa => soa_seen += 1,
+                    Record::Address(name, address) => targets
+                        .entry(name.to_lowercase())
+                        .or_insert_with(|| DnsTarget::new(&name, [], DnsSource::Axfr))
+                        .add([address]),
+                    Record::Other => {}
+                }
+            }
+        }
+        info!("Zone transfer of {} listed {} names with addresses", zone, targets.len());
+        Ok(targets.into_values().collect())
+    }
+
+    /// Names `WORD.domain` that resolve, minus wildcard answers
+    pub async fn brute_force(&self, domain: &str, words: &[String]) -> ScanResult<Vec<DnsTarget>> {
+        let domain = domain.trim_end_matches('.');
+        // A random label resolving means every label does; drop its addresses
+        let probe = format!("nrmap-{:016x}.{}", rand::random::<u64>(), domain);
+        let wildcard = self.lookup(probe).await;
+        if !wildcard.is_empty() {
+            warn!("{} has a wildcard record ({:?}); ignoring names resolving only to it", domain, wildcard);
+        }
+
+        info!("Trying {} names under {}", words.len(), domain);
+        let wildcard = &wildcard;
+        let found: Vec<DnsTarget> = stream::iter(words)
+            .map(|word| {
+                let name = format!("{}.{}", word.trim().trim_end_matches('.'), domain);
+                async move {
+                    let addresses = self.lookup(name.clone()).await;
+                    (!addresses.is_empty() && addresses.iter().any(|a| !wildcard.contains(a)))
+                        .then(|| DnsTarget::new(&name, addresses, DnsSource::Brute))
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .filter_map(std::future::ready)
+            .collect()
+            .await;
+        info!("{} names under {} resolve", found.len(), domain);
+        Ok(found)
+    }
+
+    /// Forward lookup through the system resolver; nothing on failure
+    async fn lookup(&self, name: String) -> Vec<IpAddr> {
+        let task = tokio::task::spawn_blocking({
+            let name = name.clone();
+            move || dns_lookup::lookup_host(&name)
+        });
+        match tokio::time::timeout(self.timeout, task).await {
+            Ok(Ok(Ok(addresses))) => addresses,
+            Ok(Ok(Err(e))) => {
+                debug!("{} does not resolve: {}", name, e);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response to an AXFR query carrying the given answer records
+    fn response(id: u16, zone: &str, answers: &[(&str, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut message = encode_query(id, zone, TYPE_AXFR).unwrap();
+        message[2] = 0x84;
+        message[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (name, rtype, data) in answers {
+            match *name {
+                // Pointer to the question name
+                "@" => message.extend_from_slice(&[0xc0, 12]),
+                name => message.extend_from_slice(&encode_query(0, name, 0).unwrap()[12..][..name.len() + 2]),
+            }
+            message.extend_from_slice(&rtype.to_be_bytes());
+            message.extend_from_slice(&CLASS_IN.to_be_bytes());
+            message.extend_from_slice(&300u32.to_be_bytes());
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+        message
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        let message = response(
+            7,
+            "corp.example",
+            &[
+                ("@", TYPE_SOA, vec![0; 22]),
+                ("db.corp.example", TYPE_A, vec![10, 0, 0, 5]),
+                ("db.corp.example", TYPE_AAAA, "2001:db8::5".parse::<Ipv6Addr>().unwrap().octets().to_vec()),
+            ],
+        );
+        let records = parse_answers(&message).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], Record::Soa);
+        assert_eq!(records[1], Record::Address("db.corp.example".into(), "10.0.0.5".parse().unwrap()));
+
+        let mut refused = message.clone();
+        refused[3] = 5;
+        assert!(parse_answers(&refused).unwrap_err().to_string().contains("refused"));
+        assert!(parse_answers(&message[..40]).is_err());
+
+        assert_eq!(
+            parse_axfr_spec("corp.example.@192.0.2.53").unwrap(),
+            ("corp.example".to_string(), "192.0.2.53:53".to_string())
+        );
+        assert!(parse_axfr_spec("corp.example").is_err());
+    }
+
+    #[test]
+    fn test_target_lines_feed_the_scanner() {
+        let targets = merge_targets([
+            DnsTarget::new("DB.corp.example.", ["10.0.0.5".parse().unwrap()], DnsSource::Axfr),
+            DnsTarget::new("db.corp.example", ["10.0.0.5".parse().unwrap(), "10.0.0.6".parse().unwrap()], DnsSource::Ptr),
+            DnsTarget::new("gone.corp.example", [], DnsSource::Axfr),
+        ]);
+        assert_eq!(targets.len(), 1);
+        let line = targets[0].to_json_line();
+        assert_eq!(line, r#"{"host":"db.corp.example","a":["10.0.0.5","10.0.0.6"],"source":"axfr"}"#);
+        assert_eq!(
+            super::super::targets::parse_target_list(&line).unwrap(),
+            vec!["10.0.0.5".parse::<IpAddr>().unwrap(), "10.0.0.6".parse().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_zone_transfer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut len = [0u8; 2];
+            socket.read_exact(&mut len).await.unwrap();
+            let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+            socket.read_exact(&mut query).await.unwrap();
+            let id = u16::from_be_bytes([query[0], query[1]]);
+            // The transfer spans two messages
+            for answers in [
+                vec![("@", TYPE_SOA, vec![0; 22]), ("web.corp.example", TYPE_A, vec![10, 0, 0, 80])],
+                vec![("vpn.corp.example", TYPE_A, vec![10, 0, 0, 1]), ("@", TYPE_SOA, vec![0; 22])],
+            ] {
+                let message = response(id, "corp.example", &answers);
+                socket.write_all(&(message.len() as u16).to_be_bytes()).await.unwrap();
+                socket.write_all(&message).await.unwrap();
+            }
+        });
+
+        let targets = DnsEnumerator::new(2000, 4).zone_transfer("corp.example", &server).await.unwrap();
+        let hosts: Vec<&str> = targets.iter().map(|t| t.host.as_str()).collect();
+        assert_eq!(hosts, ["vpn.corp.example", "web.corp.example"]);
+        assert!(targets.iter().all(|t| t.source == DnsSource::Axfr));
+    }
+}
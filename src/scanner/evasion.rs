@@ -0,0 +1,141 @@
+//! Firewall-auditing probe modes
+//!
+//! Probes can be sent with a chosen IP TTL (`--ttl`) or a deliberately wrong
+//! TCP/UDP checksum (`--badsum`). End hosts silently drop segments with a bad
+//! checksum, so any answer to such a probe comes from a device on the path
+//! that does not verify checksums: a firewall, IDS or load balancer. Results
+//! record the mode used and who is believed to have answered.
+
+#[cfg(not(feature = "no-net"))]
+use crate::packet::PacketBuilder;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::warn;
+
+/// How probes are altered for evasion and rule auditing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProbeEvasion {
+    /// IP TTL / IPv6 hop limit for probes
+    pub ttl: Option<u8>,
+    /// Send probes with an invalid TCP/UDP checksum
+    pub bad_checksum: bool,
+}
+
+impl ProbeEvasion {
+    /// Whether any evasion option is set
+    pub fn is_active(&self) -> bool {
+        self.ttl.is_some() || self.bad_checksum
+    }
+
+    /// Apply the options to a packet builder for crafted probes
+    #[cfg(not(feature = "no-net"))]
+    pub fn apply(&self, builder: PacketBuilder) -> PacketBuilder {
+        let builder = builder.bad_checksum(self.bad_checksum);
+        match self.ttl {
+            Some(ttl) => builder.ttl(ttl),
+            None => builder,
+        }
+    }
+
+    /// The subset usable by a scanner relying on kernel sockets
+    ///
+    /// The kernel computes checksums itself, so `bad_checksum` only applies to
+    /// crafted (raw) probes and is dropped here with a warning.
+    pub fn for_kernel_sockets(&self, scanner: &str) -> Self {
+        if self.bad_checksum {
+            warn!("{} scan cannot send bad checksums; only the TTL option applies", scanner);
+        }
+        Self {
+            ttl: self.ttl,
+            bad_checksum: false,
+        }
+    }
+
+    /// Classify who answered a probe sent with these options
+    pub fn classify(&self, responded: bool) -> ResponderClass {
+        match (responded, self.bad_checksum) {
+            (false, _) => ResponderClass::NoResponse,
+            (true, true) => ResponderClass::Middlebox,
+            (true, false) => ResponderClass::EndHost,
+        }
+    }
+
+    /// Outcome to attach to a result, if any option is active
+    pub fn outcome(&self, responded: bool) -> Option<EvasionOutcome> {
+        self.is_active().then(|| EvasionOutcome {
+            mode: *self,
+            responder: self.classify(responded),
+        })
+    }
+}
+
+impl fmt::Display for ProbeEvasion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(ttl) = self.ttl {
+            parts.push(format!("ttl={}", ttl));
+        }
+        if self.bad_checksum {
+            parts.push("badsum".to_string());
+        }
+        match parts.is_empty() {
+            true => write!(f, "none"),
+            false => write!(f, "{}", parts.join(" ")),
+        }
+    }
+}
+
+/// Who is believed to have answered an evasion probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponderClass {
+    /// Nothing answered
+    NoResponse,
+    /// The target's own stack answered
+    EndHost,
+    /// Something on the path answered a probe the host would have dropped
+    Middlebox,
+}
+
+impl fmt::Display for ResponderClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponderClass::NoResponse => write!(f, "no response"),
+            ResponderClass::EndHost => write!(f, "end host"),
+            ResponderClass::Middlebox => write!(f, "middlebox"),
+        }
+    }
+}
+
+/// Evasion mode used for a probe and the resulting classification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvasionOutcome {
+    pub mode: ProbeEvasion,
+    pub responder: ResponderClass,
+}
+
+impl fmt::Display for EvasionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.mode, self.responder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_responder() {
+        let badsum = ProbeEvasion { ttl: None, bad_checksum: true };
+        assert_eq!(badsum.classify(true), ResponderClass::Middlebox);
+        assert_eq!(badsum.classify(false), ResponderClass::NoResponse);
+
+        let ttl = ProbeEvasion { ttl: Some(3), bad_checksum: false };
+        assert_eq!(ttl.classify(true), ResponderClass::EndHost);
+        assert_eq!(ttl.outcome(true).unwrap().to_string(), "ttl=3: end host");
+
+        assert!(ProbeEvasion::default().outcome(true).is_none());
+        assert_eq!(badsum.for_kernel_sockets("UDP"), ProbeEvasion::default());
+    }
+}
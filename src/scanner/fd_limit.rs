@@ -0,0 +1,211 @@
+//! File descriptor aware concurrency
+//!
+//! Every TCP connect and UDP probe in flight holds a socket, so scanning
+//! `max_concurrent_targets` hosts `max_concurrent_scans` ports at a time
+//! needs that many file descriptors on top of the ones the process already
+//! holds. Past `ulimit -n` (1024 by default on most systems) probes fail
+//! with EMFILE halfway through a scan. Before its first scan the scanner
+//! compares the demand with RLIMIT_NOFILE, raises the soft limit toward the
+//! hard limit when that is enough, and otherwise clamps concurrency to what
+//! fits, with a warning. `scanner.ulimit_check` (`--ulimit-check`) turns
+//! the clamping into a warning only, or the check off.
+
+use crate::config::ScannerConfig;
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Descriptors kept free for log files, captures, DNS lookups and the like
+pub const FD_RESERVE: u64 = 64;
+
+/// What to do when the configured concurrency exceeds the descriptor limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UlimitCheck {
+    /// Lower concurrency to fit, with a warning
+    #[default]
+    Clamp,
+    /// Warn and keep the configured concurrency
+    Warn,
+    /// Do not check
+    Off,
+}
+
+impl fmt::Display for UlimitCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UlimitCheck::Clamp => "clamp",
+            UlimitCheck::Warn => "warn",
+            UlimitCheck::Off => "off",
+        })
+    }
+}
+
+impl FromStr for UlimitCheck {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> ScanResult<Self> {
+        match s.to_lowercase().as_str() {
+            "clamp" | "on" => Ok(UlimitCheck::Clamp),
+            "warn" => Ok(UlimitCheck::Warn),
+            "off" | "none" => Ok(UlimitCheck::Off),
+            _ => Err(ScanError::validation_error(
+                "ulimit_check",
+                format!("Unknown ulimit check '{}' (expected clamp, warn or off)", s),
+            )),
+        }
+    }
+}
+
+/// Targets scanned at once, and ports probed at once on each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Concurrency {
+    pub targets: usize,
+    pub ports: usize,
+}
+
+impl Concurrency {
+    /// Concurrency as configured
+    pub fn from_config(config: &ScannerConfig) -> Self {
+        Self {
+            targets: config.max_concurrent_targets.max(1),
+            ports: config.max_concurrent_scans.max(1),
+        }
+    }
+
+    /// Sockets in flight at once; a run-wide probe cap (`probes_total`,
+    /// 0 for none) bounds it too
+    pub fn demand(&self, probes_total: usize) -> u64 {
+        let demand = self.targets as u64 * self.ports as u64;
+        match probes_total {
+            0 => demand,
+            total => demand.min(total as u64),
+        }
+    }
+
+    /// Largest concurrency within `budget` sockets, cutting ports per
+    /// target first and then targets
+    pub fn clamp_to(self, budget: u64) -> Self {
+        let budget = budget.max(1);
+        let ports = (self.ports as u64).min(budget) as usize;
+        let targets = (budget / ports as u64).clamp(1, self.targets as u64) as usize;
+        Self { targets, ports }
+    }
+}
+
+impl fmt::Display for Concurrency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} targets x {} ports", self.targets, self.ports)
+    }
+}
+
+/// Soft and hard RLIMIT_NOFILE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NofileLimit {
+    pub soft: u64,
+    pub hard: u64,
+}
+
+/// The process's open file limit, where the platform has one
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+#[allow(clippy::unnecessary_cast)] // rlim_t is narrower on some platforms
+pub fn nofile_limit() -> Option<NofileLimit> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes into the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    Some(NofileLimit {
+        soft: limit.rlim_cur as u64,
+        hard: limit.rlim_max as u64,
+    })
+}
+
+#[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+pub fn nofile_limit() -> Option<NofileLimit> {
+    None
+}
+
+/// Raise the soft limit to `soft` (at most the hard limit)
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+fn raise_soft_limit(limit: NofileLimit, soft: u64) -> bool {
+    let raised = libc::rlimit {
+        rlim_cur: soft.min(limit.hard) as libc::rlim_t,
+        rlim_max: limit.hard as libc::rlim_t,
+    };
+    // SAFETY: setrlimit only reads the struct it is given
+    unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) == 0 }
+}
+
+#[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+fn raise_soft_limit(_limit: NofileLimit, _soft: u64) -> bool {
+    false
+}
+
+/// Concurrency that fits the descriptor limit, as `check` asks
+pub fn fit(requested: Concurrency, probes_total: usize, check: UlimitCheck) -> Concurrency {
+    if check == UlimitCheck::Off {
+        return requested;
+    }
+    let Some(mut limit) = nofile_limit() else {
+        return requested;
+    };
+    let in_use = super::resources::open_fds().unwrap_or(0) + FD_RESERVE;
+    let demand = requested.demand(probes_total);
+
+    let needed = in_use + demand;
+    if needed > limit.soft && limit.hard > limit.soft && raise_soft_limit(limit, needed) {
+        let soft = needed.min(limit.hard);
+        info!("Raised the open file limit from {} to {}", limit.soft, soft);
+        limit.soft = soft;
+    }
+
+    let budget = limit.soft.saturating_sub(in_use);
+    if demand <= budget {
+        return requested;
+    }
+    match check {
+        UlimitCheck::Clamp => {
+            let clamped = requested.clamp_to(budget);
+            warn!(
+                "Open file limit {} leaves room for {} sockets, not {}: scanning {} instead of {} \
+                 (raise `ulimit -n`, or pass --ulimit-check warn to keep the configured concurrency)",
+                limit.soft, budget, demand, clamped, requested
+            );
+            clamped
+        }
+        UlimitCheck::Warn | UlimitCheck::Off => {
+            warn!(
+                "Open file limit {} leaves room for {} sockets, but {} may be in flight: \
+                 expect EMFILE failures (raise `ulimit -n`)",
+                limit.soft, budget, demand
+            );
+            requested
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency_clamped_to_budget() {
+        let requested = Concurrency { targets: 64, ports: 1000 };
+        assert_eq!(requested.demand(0), 64_000);
+        assert_eq!(requested.demand(5000), 5000);
+
+        assert_eq!(requested.clamp_to(900), Concurrency { targets: 1, ports: 900 });
+        assert_eq!(requested.clamp_to(8000), Concurrency { targets: 8, ports: 1000 });
+        assert_eq!(requested.clamp_to(1_000_000), requested);
+        assert_eq!(requested.clamp_to(0), Concurrency { targets: 1, ports: 1 });
+
+        assert_eq!("WARN".parse::<UlimitCheck>().unwrap(), UlimitCheck::Warn);
+        assert!("sometimes".parse::<UlimitCheck>().is_err());
+        // A demand within any realistic limit is left alone
+        let small = Concurrency { targets: 1, ports: 4 };
+        assert_eq!(fit(small, 0, UlimitCheck::Clamp), small);
+    }
+}
@@ -5,8 +5,19 @@
 
 use crate::config::HostDiscoveryConfig;
 use crate::error::ScanResult;
+use super::discovery_probe::{DiscoveryProbe, ProbeVote};
+#[cfg(not(feature = "no-net"))]
+use super::ipv6_discovery::Ipv6Discovery;
+use super::ipv6_discovery::Ipv6Prefix;
+#[cfg(not(feature = "no-net"))]
+use super::ping::icmp_echo;
+use super::privileges::PrivilegeStatus;
+#[cfg(not(feature = "no-net"))]
+use super::resources;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(feature = "no-net"))]
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tracing::{debug, info, warn};
@@ -26,13 +37,19 @@ pub struct DiscoveryResult {
     pub status: HostStatus,
     pub response_time_ms: Option<u64>,
     pub method: String,
+    /// Individual probe verdicts when custom probes took part
+    pub votes: Vec<ProbeVote>,
 }
 
 /// Host discovery scanner
+#[cfg(not(feature = "no-net"))]
 pub struct HostDiscovery {
     config: HostDiscoveryConfig,
+    privileges: PrivilegeStatus,
+    probes: Vec<Arc<dyn DiscoveryProbe>>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl HostDiscovery {
     /// Create a new host discovery scanner
     pub fn new(config: HostDiscoveryConfig) -> Self {
@@ -40,7 +57,27 @@ impl HostDiscovery {
             "Initializing host discovery: method={}, timeout={}ms",
             config.method, config.timeout_ms
         );
-        Self { config }
+        Self {
+            config,
+            privileges: PrivilegeStatus::detect(),
+            probes: Vec::new(),
+        }
+    }
+
+    /// Register a custom discovery probe
+    ///
+    /// Custom probes run alongside the configured method and their votes are
+    /// combined using `host_discovery.consensus`.
+    pub fn with_probe(mut self, probe: Arc<dyn DiscoveryProbe>) -> Self {
+        info!("Registering custom discovery probe: {}", probe.name());
+        self.probes.push(probe);
+        self
+    }
+
+    /// Use an already-detected privilege status instead of probing again
+    pub fn with_privileges(mut self, privileges: PrivilegeStatus) -> Self {
+        self.privileges = privileges;
+        self
     }
 
     /// Discover if a host is up
@@ -57,14 +94,16 @@ impl HostDiscovery {
                 status: HostStatus::Up,
                 response_time_ms: None,
                 method: "disabled".to_string(),
+                votes: Vec::new(),
             });
         }
 
-        debug!("Discovering host: {} using method: {}", target, self.config.method);
+        let method = self.privileges.resolve_discovery_method(&self.config.method);
+        debug!("Discovering host: {} using method: {}", target, method);
 
         let start = std::time::Instant::now();
 
-        let status = match self.config.method.as_str() {
+        let status = match method.as_str() {
             "tcp" => self.tcp_discovery(target).await?,
             "icmp" => self.icmp_discovery(target).await?,
             "udp" => self.udp_discovery(target).await?,
@@ -75,6 +114,19 @@ impl HostDiscovery {
             }
         };
 
+        let (status, method, votes) = if self.probes.is_empty() {
+            (status, method, Vec::new())
+        } else {
+            let votes = self.collect_votes(target, ProbeVote::new(method, status)).await;
+            let status = self.config.consensus.decide(&votes);
+            let method = votes
+                .iter()
+                .map(|v| v.probe.as_str())
+                .collect::<Vec<_>>()
+                .join("+");
+            (status, method, votes)
+        };
+
         let elapsed = start.elapsed();
         let response_time_ms = if status == HostStatus::Up {
             Some(elapsed.as_millis() as u64)
@@ -86,7 +138,8 @@ impl HostDiscovery {
             target,
             status: status.clone(),
             response_time_ms,
-            method: self.config.method.clone(),
+            method,
+            votes,
         };
 
         match status {
@@ -104,6 +157,26 @@ impl HostDiscovery {
         Ok(result)
     }
 
+    /// Run the registered custom probes and gather their votes
+    ///
+    /// Probe errors are logged and counted as `Unknown` (abstaining).
+    async fn collect_votes(&self, target: IpAddr, builtin: ProbeVote) -> Vec<ProbeVote> {
+        let custom = futures::future::join_all(self.probes.iter().map(|probe| async move {
+            let status = match probe.probe(target).await {
+                Ok(status) => status,
+                Err(e) => {
+                    warn!("Discovery probe {} failed for {}: {}", probe.name(), target, e);
+                    HostStatus::Unknown
+                }
+            };
+            debug!("Discovery probe {} voted {} for {}", probe.name(), status, target);
+            ProbeVote::new(probe.name(), status)
+        }))
+        .await;
+
+        std::iter::once(builtin).chain(custom).collect()
+    }
+
     /// TCP-based host discovery (connect to common ports)
     /// 
     /// Attempts to connect to commonly open ports (80, 443, 22, 21)
@@ -113,6 +186,8 @@ impl HostDiscovery {
 
         for &port in &common_ports {
             let addr = SocketAddr::new(target, port);
+            resources::socket_opened();
+            resources::packet_sent(resources::tcp_segment_len(target));
             
             match timeout(timeout_duration, TcpStream::connect(addr)).await {
                 Ok(Ok(_stream)) => {
@@ -137,21 +212,28 @@ impl HostDiscovery {
 
     /// ICMP-based host discovery (ping)
     /// 
-    /// Note: This is a simplified implementation. For full ICMP support,
-    /// you would need raw sockets and elevated privileges.
+    /// Uses a raw ICMP socket when privileged, otherwise an unprivileged ICMP
+    /// datagram socket. Falls back to TCP if neither can be opened.
     async fn icmp_discovery(&self, target: IpAddr) -> ScanResult<HostStatus> {
-        debug!("ICMP discovery for {} (fallback to TCP)", target);
-        
-        // In a production implementation, this would use raw ICMP packets
-        // For now, we fall back to TCP discovery as ICMP requires privileges
-        // TODO: Implement actual ICMP ping using raw sockets
-        
-        warn!(
-            "ICMP discovery not fully implemented, falling back to TCP for {}",
-            target
-        );
-        
-        self.tcp_discovery(target).await
+        let Some(mode) = self.privileges.ping_mode() else {
+            warn!("No ICMP socket available, falling back to TCP for {}", target);
+            return self.tcp_discovery(target).await;
+        };
+
+        debug!("ICMP discovery for {} ({:?} socket)", target, mode);
+
+        for attempt in 0..=self.config.retries {
+            match icmp_echo(target, self.config.timeout_ms, mode).await {
+                Ok(Some(_rtt)) => return Ok(HostStatus::Up),
+                Ok(None) => debug!("No ICMP echo reply from {} (attempt {})", target, attempt + 1),
+                Err(e) => {
+                    warn!("ICMP echo failed for {}: {}, falling back to TCP", target, e);
+                    return self.tcp_discovery(target).await;
+                }
+            }
+        }
+
+        Ok(HostStatus::Down)
     }
 
     /// UDP-based host discovery
@@ -190,6 +272,39 @@ impl HostDiscovery {
         self.tcp_discovery(target).await
     }
 
+    /// Discover live hosts in an IPv6 prefix (opt-in strategy)
+    ///
+    /// Candidates come from the all-nodes multicast echo, the neighbor cache and
+    /// heuristic address generation; each candidate is then confirmed with the
+    /// configured discovery method.
+    ///
+    /// # Arguments
+    /// * `prefix` - IPv6 network to discover
+    /// * `max_concurrent` - Maximum number of concurrent confirmation probes
+    ///
+    /// # Returns
+    /// * `ScanResult<Vec<DiscoveryResult>>` - Discovery results for all candidates
+    pub async fn discover_ipv6(
+        &self,
+        prefix: &Ipv6Prefix,
+        max_concurrent: usize,
+    ) -> ScanResult<Vec<DiscoveryResult>> {
+        let targets = self.ipv6_candidates(prefix).await?;
+        self.discover_many(targets, max_concurrent).await
+    }
+
+    /// Candidate addresses in an IPv6 prefix, before they are confirmed
+    pub async fn ipv6_candidates(&self, prefix: &Ipv6Prefix) -> ScanResult<Vec<IpAddr>> {
+        let candidates = Ipv6Discovery::new(self.config.ipv6.clone())
+            .discover(prefix)
+            .await?;
+
+        Ok(candidates
+            .into_iter()
+            .map(|c| IpAddr::V6(c.address))
+            .collect())
+    }
+
     /// Discover multiple hosts concurrently
     /// 
     /// # Arguments
@@ -253,9 +368,10 @@ impl std::fmt::Display for DiscoveryResult {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
+    use crate::scanner::discovery_probe::ConsensusPolicy;
     use std::net::Ipv4Addr;
 
     fn create_test_config() -> HostDiscoveryConfig {
@@ -264,6 +380,23 @@ mod tests {
             method: "tcp".to_string(),
             timeout_ms: 1000,
             retries: 1,
+            ipv6: crate::config::Ipv6DiscoveryConfig::default(),
+            consensus: ConsensusPolicy::Any,
+            arp_warmup: false,
+            cache: Default::default(),
+        }
+    }
+
+    struct StaticProbe(HostStatus);
+
+    #[async_trait::async_trait]
+    impl DiscoveryProbe for StaticProbe {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        async fn probe(&self, _target: IpAddr) -> ScanResult<HostStatus> {
+            Ok(self.0.clone())
         }
     }
 
@@ -299,6 +432,30 @@ mod tests {
         assert_eq!(result.method, "disabled");
     }
 
+    #[tokio::test]
+    async fn test_custom_probe_consensus() {
+        let mut config = create_test_config();
+        config.timeout_ms = 100;
+        config.consensus = ConsensusPolicy::Any;
+        // The built-in TCP vote depends on the environment; the custom vote decides
+        let target = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+        let discovery = HostDiscovery::new(config.clone())
+            .with_privileges(PrivilegeStatus::assume_privileged())
+            .with_probe(Arc::new(StaticProbe(HostStatus::Up)));
+        let result = discovery.discover(target).await.unwrap();
+        assert_eq!(result.status, HostStatus::Up);
+        assert_eq!(result.method, "tcp+static");
+        assert_eq!(result.votes.len(), 2);
+
+        config.consensus = ConsensusPolicy::All;
+        let discovery = HostDiscovery::new(config)
+            .with_privileges(PrivilegeStatus::assume_privileged())
+            .with_probe(Arc::new(StaticProbe(HostStatus::Down)));
+        let result = discovery.discover(target).await.unwrap();
+        assert_eq!(result.status, HostStatus::Down);
+    }
+
     #[test]
     fn test_host_status_display() {
         assert_eq!(format!("{}", HostStatus::Up), "UP");
@@ -0,0 +1,150 @@
+//! ICMP rate limit detection for UDP scans
+//!
+//! Closed UDP ports are only told apart from filtered ones by the ICMP port
+//! unreachable they trigger, and most hosts rate-limit those (Linux sends
+//! about one a second per destination after a short burst). A fast scan then
+//! sees a trickle of unreachables and times out on every other closed port,
+//! reporting it open|filtered. The detector watches a host's first pass: when
+//! most probes timed out while unreachables arrived at a steady slow rate, it
+//! infers that rate, and the scanner re-probes the timed-out ports paced to
+//! it. The inferred limit is kept on the host's result.
+
+use crate::scanner::probe_error::ProbeError;
+use crate::scanner::udp_scan::UdpScanResult;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+#[cfg(not(feature = "no-net"))]
+use std::sync::Mutex;
+
+/// Fewest unreachables a rate is inferred from
+const MIN_ARRIVALS: usize = 4;
+
+/// Gaps between unreachables shorter than this are not a rate limit
+const MIN_GAP: Duration = Duration::from_millis(50);
+
+/// ICMP unreachable rate a host was found to be limited to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IcmpRateLimit {
+    /// Unreachables per second the host sends
+    pub per_second: f64,
+    /// Timed-out ports re-probed at that rate
+    pub reprobed_ports: usize,
+    /// Re-probed ports that answered this time
+    pub resolved_ports: usize,
+}
+
+impl fmt::Display for IcmpRateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~{:.1} unreachables/s; {} ports re-probed paced, {} resolved",
+            self.per_second, self.reprobed_ports, self.resolved_ports
+        )
+    }
+}
+
+/// Unreachable arrivals and timeouts of one host's UDP probes
+#[derive(Debug, Clone, Default)]
+pub struct IcmpRateDetector {
+    arrivals: Vec<Instant>,
+    timeouts: usize,
+}
+
+impl IcmpRateDetector {
+    /// Record a finished port probe, as it finishes
+    pub fn observe(&mut self, result: &UdpScanResult) {
+        if result.icmp.is_some() || result.error == Some(ProbeError::Refused) {
+            self.record_unreachable(Instant::now());
+        } else if result.error == Some(ProbeError::Timeout) {
+            self.timeouts += 1;
+        }
+    }
+
+    fn record_unreachable(&mut self, at: Instant) {
+        self.arrivals.push(at);
+    }
+
+    /// Unreachables per second the host appears limited to
+    ///
+    /// Needs at least as many timeouts as unreachables, and unreachables
+    /// spaced at least [`MIN_GAP`] apart in three of four gaps; the 75th
+    /// percentile gap skips the burst a token bucket lets through first.
+    pub fn inferred_rate(&self) -> Option<f64> {
+        if self.arrivals.len() < MIN_ARRIVALS || self.timeouts < self.arrivals.len() {
+            return None;
+        }
+        let mut arrivals = self.arrivals.clone();
+        arrivals.sort();
+        let mut gaps: Vec<Duration> = arrivals.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        gaps.sort();
+        let gap = gaps[(gaps.len() * 3 / 4).min(gaps.len() - 1)];
+        (gap >= MIN_GAP).then(|| 1.0 / gap.as_secs_f64())
+    }
+}
+
+/// Spaces probes to one host at a fixed interval
+#[cfg(not(feature = "no-net"))]
+#[derive(Debug)]
+pub struct ProbePacer {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl ProbePacer {
+    /// Pacer for `per_second` probes, with a tenth of an interval of slack
+    /// so probes do not race the host's token bucket
+    pub fn new(per_second: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.1 / per_second.max(f64::MIN_POSITIVE)),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Wait for the next probe slot
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_inferred_from_spaced_unreachables() {
+        let start = Instant::now();
+        let mut limited = IcmpRateDetector { timeouts: 40, ..Default::default() };
+        // A burst of five, then one a second
+        for i in 0..5 {
+            limited.record_unreachable(start + Duration::from_millis(i));
+        }
+        for i in 1..=10 {
+            limited.record_unreachable(start + Duration::from_secs(i));
+        }
+        let rate = limited.inferred_rate().unwrap();
+        assert!((rate - 1.0).abs() < 0.01, "{}", rate);
+
+        let mut few_timeouts = limited.clone();
+        few_timeouts.timeouts = 3;
+        assert_eq!(few_timeouts.inferred_rate(), None);
+
+        let mut unlimited = IcmpRateDetector { timeouts: 40, ..Default::default() };
+        for i in 0..20 {
+            unlimited.record_unreachable(start + Duration::from_millis(i * 2));
+        }
+        assert_eq!(unlimited.inferred_rate(), None);
+        assert_eq!(IcmpRateDetector::default().inferred_rate(), None);
+    }
+}
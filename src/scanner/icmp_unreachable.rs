@@ -0,0 +1,275 @@
+//! ICMP destination unreachable replies to port probes
+//!
+//! A probe that times out says little: the packet, its answer or both may
+//! have been dropped. An ICMP destination unreachable says who rejected it
+//! and why, which settles the port:
+//!
+//! | ICMP (type 3) code              | UDP      | TCP      |
+//! |---------------------------------|----------|----------|
+//! | 3 port unreachable              | closed   | filtered |
+//! | 1, 2 host/protocol unreachable  | filtered | filtered |
+//! | 9, 10, 13 admin prohibited      | filtered | filtered |
+//!
+//! ICMPv6 destination unreachable (type 1) codes map the same way. TCP
+//! ports answer for themselves with RST, so an ICMP port unreachable for a
+//! TCP probe comes from a firewall (iptables' default `REJECT`).
+//!
+//! UDP probe sockets pick the ICMP error and the address of the device that
+//! sent it from the kernel error queue (`IP_RECVERR`, Linux only); raw
+//! socket scans parse the quoted probe out of the ICMP packet.
+
+use super::port_frequency::TransportProtocol;
+use super::probe_error::ProbeError;
+use super::tcp_connect::PortStatus;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// ICMPv4 destination unreachable
+pub const ICMPV4_UNREACHABLE: u8 = 3;
+
+/// ICMPv6 destination unreachable
+pub const ICMPV6_UNREACHABLE: u8 = 1;
+
+/// An ICMP destination unreachable received for a probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IcmpUnreachable {
+    /// Device that sent the ICMP message: the target, a router or a firewall
+    pub from: IpAddr,
+    pub icmp_type: u8,
+    pub code: u8,
+}
+
+impl IcmpUnreachable {
+    /// A destination unreachable of either ICMP version; `None` for other types
+    pub fn new(from: IpAddr, icmp_type: u8, code: u8) -> Option<Self> {
+        let unreachable = match from {
+            IpAddr::V4(_) => ICMPV4_UNREACHABLE,
+            IpAddr::V6(_) => ICMPV6_UNREACHABLE,
+        };
+        (icmp_type == unreachable).then_some(Self { from, icmp_type, code })
+    }
+
+    /// Error class of the probe that drew this reply
+    pub fn error(&self, protocol: TransportProtocol) -> ProbeError {
+        let error = match self.from {
+            IpAddr::V4(_) => ProbeError::from_icmp_unreachable(self.code),
+            IpAddr::V6(_) => match self.code {
+                4 => ProbeError::Refused,
+                1 | 5 | 6 => ProbeError::AdminProhibited,
+                _ => ProbeError::Unreachable,
+            },
+        };
+        match (error, protocol) {
+            // Only a firewall answers a TCP SYN with port unreachable
+            (ProbeError::Refused, TransportProtocol::Tcp) => ProbeError::AdminProhibited,
+            (error, _) => error,
+        }
+    }
+
+    /// Port status this reply implies
+    pub fn status(&self, protocol: TransportProtocol) -> PortStatus {
+        self.error(protocol).status()
+    }
+
+    /// Parse an IPv4 packet from a raw ICMP socket, returning the reply and
+    /// the probe it quotes; `None` unless it is a destination unreachable
+    pub fn parse_ipv4(packet: &[u8]) -> Option<(Self, QuotedProbe)> {
+        let (source, icmp) = ipv4_payload(packet, 1)?;
+        if icmp.len() < 8 {
+            return None;
+        }
+        let reply = Self::new(IpAddr::V4(source), icmp[0], icmp[1])?;
+        // The message quotes the probe's IP header and first 8 payload bytes
+        let (destination, protocol, quoted) = ipv4_header(&icmp[8..])?;
+        if quoted.len() < 4 {
+            return None;
+        }
+        let probe = QuotedProbe {
+            destination: IpAddr::V4(destination),
+            protocol,
+            source_port: u16::from_be_bytes([quoted[0], quoted[1]]),
+            destination_port: u16::from_be_bytes([quoted[2], quoted[3]]),
+        };
+        Some((reply, probe))
+    }
+}
+
+impl fmt::Display for IcmpUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ICMP type {} code {} from {}", self.icmp_type, self.code, self.from)
+    }
+}
+
+/// The probe quoted in an ICMP error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotedProbe {
+    pub destination: IpAddr,
+    /// IP protocol number: 6 for TCP, 17 for UDP
+    pub protocol: u8,
+    pub source_port: u16,
+    pub destination_port: u16,
+}
+
+impl QuotedProbe {
+    /// Whether this is the probe sent to `target`:`port` over `protocol`
+    pub fn matches(&self, target: IpAddr, protocol: TransportProtocol, port: u16) -> bool {
+        let number = match protocol {
+            TransportProtocol::Tcp => 6,
+            TransportProtocol::Udp => 17,
+        };
+        self.destination == target && self.protocol == number && self.destination_port == port
+    }
+}
+
+/// Destination, protocol and payload of an IPv4 header
+fn ipv4_header(packet: &[u8]) -> Option<(Ipv4Addr, u8, &[u8])> {
+    let header_len = usize::from(packet.first()? & 0x0f) * 4;
+    if packet[0] >> 4 != 4 || header_len < 20 || packet.len() < header_len {
+        return None;
+    }
+    let destination = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+    Some((destination, packet[9], &packet[header_len..]))
+}
+
+/// Source and payload of an IPv4 packet carrying `protocol`
+fn ipv4_payload(packet: &[u8], protocol: u8) -> Option<(Ipv4Addr, &[u8])> {
+    let (_, packet_protocol, payload) = ipv4_header(packet)?;
+    (packet_protocol == protocol).then(|| (Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]), payload))
+}
+
+/// Have the kernel queue ICMP errors for a UDP probe socket
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_error_queue(socket: &impl std::os::fd::AsRawFd, ipv6: bool) -> std::io::Result<()> {
+    let (level, option) = match ipv6 {
+        false => (libc::SOL_IP, libc::IP_RECVERR),
+        true => (libc::SOL_IPV6, libc::IPV6_RECVERR),
+    };
+    let enable: libc::c_int = 1;
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            option,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    match rc {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn enable_error_queue<S>(_socket: &S, _ipv6: bool) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Take the ICMP destination unreachable queued on a socket, if any
+#[cfg(target_os = "linux")]
+pub(crate) fn take_queued_unreachable(socket: &impl std::os::fd::AsRawFd) -> Option<IcmpUnreachable> {
+    // u64 keeps the control buffer aligned for cmsghdr
+    let mut control = [0u64; 64];
+    let mut data = [0u8; 64];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    message.msg_controllen = std::mem::size_of_val(&control) as _;
+
+    let received = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+    if received < 0 {
+        return None;
+    }
+
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&message) };
+    while !cmsg.is_null() {
+        let header = unsafe { &*cmsg };
+        let is_error = (header.cmsg_level == libc::SOL_IP && header.cmsg_type == libc::IP_RECVERR)
+            || (header.cmsg_level == libc::SOL_IPV6 && header.cmsg_type == libc::IPV6_RECVERR);
+        if is_error {
+            let data = unsafe { libc::CMSG_DATA(cmsg) } as *const libc::sock_extended_err;
+            let error = unsafe { data.read_unaligned() };
+            if error.ee_origin == libc::SO_EE_ORIGIN_ICMP || error.ee_origin == libc::SO_EE_ORIGIN_ICMP6 {
+                // SO_EE_OFFENDER: the sender's address follows the error
+                let from = unsafe { offender_address(data.add(1) as *const libc::sockaddr) }?;
+                return IcmpUnreachable::new(from, error.ee_type, error.ee_code);
+            }
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&message, cmsg) };
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn take_queued_unreachable<S>(_socket: &S) -> Option<IcmpUnreachable> {
+    None
+}
+
+/// # Safety
+/// `address` must point into a buffer holding a sockaddr_in or sockaddr_in6
+#[cfg(target_os = "linux")]
+unsafe fn offender_address(address: *const libc::sockaddr) -> Option<IpAddr> {
+    match (*address).sa_family as libc::c_int {
+        libc::AF_INET => {
+            let address = (address as *const libc::sockaddr_in).read_unaligned();
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr))))
+        }
+        libc::AF_INET6 => {
+            let address = (address as *const libc::sockaddr_in6).read_unaligned();
+            Some(IpAddr::V6(address.sin6_addr.s6_addr.into()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_by_protocol() {
+        let router: IpAddr = "192.0.2.254".parse().unwrap();
+        let reply = |code| IcmpUnreachable::new(router, ICMPV4_UNREACHABLE, code).unwrap();
+
+        assert_eq!(reply(3).status(TransportProtocol::Udp), PortStatus::Closed);
+        assert_eq!(reply(3).status(TransportProtocol::Tcp), PortStatus::Filtered);
+        assert_eq!(reply(3).error(TransportProtocol::Tcp), ProbeError::AdminProhibited);
+        for code in [1, 2, 9, 10, 13] {
+            assert_eq!(reply(code).status(TransportProtocol::Udp), PortStatus::Filtered, "code {}", code);
+        }
+        assert_eq!(reply(13).error(TransportProtocol::Udp), ProbeError::AdminProhibited);
+        assert_eq!(reply(1).error(TransportProtocol::Udp), ProbeError::Unreachable);
+        // Echo replies and time exceeded are not unreachables
+        assert!(IcmpUnreachable::new(router, 11, 0).is_none());
+
+        let v6 = IcmpUnreachable::new("2001:db8::1".parse().unwrap(), ICMPV6_UNREACHABLE, 4).unwrap();
+        assert_eq!(v6.status(TransportProtocol::Udp), PortStatus::Closed);
+    }
+
+    #[test]
+    fn test_parse_ipv4_quoting_probe() {
+        // Outer IPv4 header from the firewall, protocol ICMP
+        let mut packet = vec![0x45, 0, 0, 56, 0, 0, 0, 0, 64, 1, 0, 0, 10, 0, 0, 1, 192, 0, 2, 10];
+        // ICMP type 3 code 13, unused word
+        packet.extend_from_slice(&[3, 13, 0, 0, 0, 0, 0, 0]);
+        // Quoted probe: IPv4 header to 198.51.100.7, protocol UDP, then ports
+        packet.extend_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 192, 0, 2, 10, 198, 51, 100, 7]);
+        packet.extend_from_slice(&[0xd4, 0x31, 0, 161, 0, 8, 0, 0]);
+
+        let (reply, probe) = IcmpUnreachable::parse_ipv4(&packet).unwrap();
+        assert_eq!(reply.from, "10.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(reply.error(TransportProtocol::Udp), ProbeError::AdminProhibited);
+        assert!(probe.matches("198.51.100.7".parse().unwrap(), TransportProtocol::Udp, 161));
+        assert!(!probe.matches("198.51.100.7".parse().unwrap(), TransportProtocol::Tcp, 161));
+        assert_eq!(reply.to_string(), "ICMP type 3 code 13 from 10.0.0.1");
+
+        packet[20] = 0; // echo reply
+        assert!(IcmpUnreachable::parse_ipv4(&packet).is_none());
+    }
+}
@@ -0,0 +1,449 @@
+//! IPv6 on-link discovery for NrMAP
+//!
+//! IPv6 subnets are far too large to sweep exhaustively, so this module builds
+//! a target list from three sources instead:
+//! - an ICMPv6 echo to the all-nodes multicast group (ff02::1) on an interface
+//! - the kernel neighbor (NDP) cache
+//! - heuristic address generation (low-byte, common host IDs and EUI-64/SLAAC)
+
+use crate::config::Ipv6DiscoveryConfig;
+use crate::error::{ScanError, ScanResult};
+use std::collections::HashSet;
+use std::net::{Ipv6Addr, SocketAddrV6};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// ICMPv6 echo request type
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+/// ICMPv6 echo reply type
+const ICMPV6_ECHO_REPLY: u8 = 129;
+/// All-nodes link-local multicast group
+const ALL_NODES_MULTICAST: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Interface IDs commonly assigned by hand to servers and routers
+const COMMON_HOST_IDS: [u128; 8] = [
+    0x53, 0x80, 0x443, 0x8080, 0xcafe, 0xbeef, 0xdead_beef, 0x1_0000_0001,
+];
+
+/// How an IPv6 candidate target was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Ipv6TargetSource {
+    MulticastEcho,
+    NeighborCache,
+    Heuristic,
+}
+
+/// Candidate IPv6 target produced by discovery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Target {
+    pub address: Ipv6Addr,
+    pub source: Ipv6TargetSource,
+}
+
+/// Entry from the kernel neighbor (NDP) cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Neighbor {
+    pub address: Ipv6Addr,
+    pub interface: String,
+    pub mac: Option<[u8; 6]>,
+    pub state: String,
+}
+
+/// IPv6 network prefix (address and prefix length)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv6Prefix {
+    pub network: Ipv6Addr,
+    pub len: u8,
+}
+
+impl Ipv6Prefix {
+    /// Create a prefix, masking off host bits
+    pub fn new(address: Ipv6Addr, len: u8) -> ScanResult<Self> {
+        if len > 128 {
+            return Err(ScanError::validation_error(
+                "ipv6_prefix",
+                format!("Invalid prefix length: {}", len),
+            ));
+        }
+        let network = Ipv6Addr::from(u128::from(address) & Self::mask(len));
+        Ok(Self { network, len })
+    }
+
+    /// Parse a prefix in CIDR notation (e.g. "2001:db8::/64")
+    pub fn parse(s: &str) -> ScanResult<Self> {
+        let (addr, len) = s.split_once('/').unwrap_or((s, "128"));
+        let address: Ipv6Addr = addr.trim().parse().map_err(|_| {
+            ScanError::invalid_target(s, "Invalid IPv6 address")
+        })?;
+        let len: u8 = len.trim().parse().map_err(|_| {
+            ScanError::validation_error("ipv6_prefix", format!("Invalid prefix length: {}", len))
+        })?;
+        Self::new(address, len)
+    }
+
+    fn mask(len: u8) -> u128 {
+        if len == 0 {
+            0
+        } else {
+            u128::MAX << (128 - len as u32)
+        }
+    }
+
+    /// Check whether an address belongs to this prefix
+    pub fn contains(&self, address: Ipv6Addr) -> bool {
+        u128::from(address) & Self::mask(self.len) == u128::from(self.network)
+    }
+
+    /// Combine the prefix with an interface ID, if the ID fits in the host bits
+    pub fn with_host_id(&self, host_id: u128) -> Option<Ipv6Addr> {
+        if host_id & Self::mask(self.len) != 0 {
+            return None;
+        }
+        Some(Ipv6Addr::from(u128::from(self.network) | host_id))
+    }
+}
+
+impl std::fmt::Display for Ipv6Prefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.len)
+    }
+}
+
+/// Build the modified EUI-64 interface ID for a MAC address (RFC 4291)
+pub fn eui64_interface_id(mac: [u8; 6]) -> u64 {
+    let bytes = [
+        mac[0] ^ 0x02,
+        mac[1],
+        mac[2],
+        0xff,
+        0xfe,
+        mac[3],
+        mac[4],
+        mac[5],
+    ];
+    u64::from_be_bytes(bytes)
+}
+
+/// Generate heuristic candidates within a prefix
+///
+/// # Arguments
+/// * `prefix` - Target network
+/// * `low_byte_count` - Number of low-byte addresses (::1, ::2, ...) to include
+/// * `macs` - Known MAC addresses used to derive SLAAC/EUI-64 addresses
+pub fn generate_candidates(prefix: &Ipv6Prefix, low_byte_count: u16, macs: &[[u8; 6]]) -> Vec<Ipv6Addr> {
+    let host_ids = (1..=low_byte_count as u128)
+        .chain(COMMON_HOST_IDS.iter().copied())
+        .chain(macs.iter().map(|mac| eui64_interface_id(*mac) as u128));
+
+    let mut seen = HashSet::new();
+    host_ids
+        .filter_map(|id| prefix.with_host_id(id))
+        .filter(|addr| seen.insert(*addr))
+        .collect()
+}
+
+/// Parse the output of `ip -6 neigh show`
+///
+/// Lines look like `fe80::1 dev eth0 lladdr 52:54:00:12:34:56 router REACHABLE`.
+/// Entries in FAILED or INCOMPLETE state are skipped.
+pub fn parse_neighbor_table(output: &str) -> Vec<Ipv6Neighbor> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let address: Ipv6Addr = fields.first()?.parse().ok()?;
+            let state = fields.last()?.to_string();
+            if state == "FAILED" || state == "INCOMPLETE" {
+                return None;
+            }
+
+            let value_of = |key: &str| {
+                fields
+                    .iter()
+                    .position(|f| *f == key)
+                    .and_then(|i| fields.get(i + 1))
+                    .copied()
+            };
+
+            Some(Ipv6Neighbor {
+                address,
+                interface: value_of("dev").unwrap_or_default().to_string(),
+                mac: value_of("lladdr").and_then(parse_mac),
+                state,
+            })
+        })
+        .collect()
+}
+
+fn parse_mac(s: &str) -> Option<[u8; 6]> {
+    let parts: Vec<u8> = s
+        .split(':')
+        .map(|p| u8::from_str_radix(p, 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    parts.try_into().ok()
+}
+
+/// Read the kernel neighbor (NDP) cache via `ip -6 neigh show`
+pub fn read_neighbor_table(interface: Option<&str>) -> ScanResult<Vec<Ipv6Neighbor>> {
+    let mut command = std::process::Command::new("ip");
+    command.args(["-6", "neigh", "show"]);
+    if let Some(iface) = interface {
+        command.args(["dev", iface]);
+    }
+
+    let output = command.output().map_err(|e| {
+        ScanError::scanner_error(format!("Failed to read IPv6 neighbor table: {}", e))
+    })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut neighbors = parse_neighbor_table(&stdout);
+
+    // `ip neigh show dev X` omits the dev field from each line
+    if let Some(iface) = interface {
+        for neighbor in &mut neighbors {
+            if neighbor.interface.is_empty() {
+                neighbor.interface = iface.to_string();
+            }
+        }
+    }
+
+    debug!("Read {} IPv6 neighbor entries", neighbors.len());
+    Ok(neighbors)
+}
+
+/// Resolve an interface name to its index
+#[cfg(not(feature = "no-net"))]
+fn interface_index(interface: &str) -> ScanResult<u32> {
+    let name = std::ffi::CString::new(interface).map_err(|_| {
+        ScanError::validation_error("interface", format!("Invalid interface name: {}", interface))
+    })?;
+    // SAFETY: `name` is a valid NUL-terminated string for the duration of the call
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(ScanError::validation_error(
+            "interface",
+            format!("Unknown interface: {}", interface),
+        ));
+    }
+    Ok(index)
+}
+
+/// Send an ICMPv6 echo request to ff02::1 on an interface and collect responders
+///
+/// Requires raw socket privileges (root or CAP_NET_RAW).
+#[cfg(not(feature = "no-net"))]
+pub async fn multicast_ping_sweep(interface: &str, timeout_ms: u64) -> ScanResult<Vec<Ipv6Addr>> {
+    let ifindex = interface_index(interface)?;
+    let interface = interface.to_string();
+
+    tokio::task::spawn_blocking(move || multicast_ping_blocking(&interface, ifindex, timeout_ms))
+        .await
+        .map_err(|e| ScanError::scanner_error(format!("Multicast ping task failed: {}", e)))?
+}
+
+#[cfg(not(feature = "no-net"))]
+fn multicast_ping_blocking(interface: &str, ifindex: u32, timeout_ms: u64) -> ScanResult<Vec<Ipv6Addr>> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    use std::mem::MaybeUninit;
+
+    let socket = Socket::new(Domain::IPV6, Type::RAW, Some(Protocol::ICMPV6)).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            ScanError::permission_denied("ICMPv6 multicast ping")
+        } else {
+            ScanError::Io(e)
+        }
+    })?;
+    socket.set_multicast_if_v6(ifindex)?;
+    socket.set_multicast_hops_v6(1)?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    // Echo request: type, code, checksum (filled in by the kernel), identifier, sequence
+    let identifier = rand::random::<u16>();
+    let id = identifier.to_be_bytes();
+    let request = [ICMPV6_ECHO_REQUEST, 0, 0, 0, id[0], id[1], 0, 1];
+    let destination = SocketAddrV6::new(ALL_NODES_MULTICAST, 0, 0, ifindex);
+    socket.send_to(&request, &destination.into())?;
+
+    info!("Sent ICMPv6 all-nodes echo on {}", interface);
+
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let mut responders = Vec::new();
+    let mut seen = HashSet::new();
+    let mut buf = [MaybeUninit::<u8>::uninit(); 1500];
+
+    while Instant::now() < deadline {
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(ScanError::Io(e)),
+        };
+        if len < 8 {
+            continue;
+        }
+
+        // SAFETY: the first `len` bytes were initialized by recv_from
+        let packet: Vec<u8> = buf[..len].iter().map(|b| unsafe { b.assume_init() }).collect();
+        if packet[0] != ICMPV6_ECHO_REPLY || packet[4..6] != id {
+            continue;
+        }
+
+        if let Some(addr) = from.as_socket_ipv6() {
+            if seen.insert(*addr.ip()) {
+                debug!("ICMPv6 echo reply from {}", addr.ip());
+                responders.push(*addr.ip());
+            }
+        }
+    }
+
+    info!("Multicast echo on {}: {} responders", interface, responders.len());
+    Ok(responders)
+}
+
+/// IPv6 discovery strategy combining multicast echo, NDP and heuristics
+#[cfg(not(feature = "no-net"))]
+pub struct Ipv6Discovery {
+    config: Ipv6DiscoveryConfig,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl Ipv6Discovery {
+    /// Create a new IPv6 discovery strategy
+    pub fn new(config: Ipv6DiscoveryConfig) -> Self {
+        Self { config }
+    }
+
+    /// Collect candidate targets for a prefix
+    ///
+    /// Multicast responders and neighbor cache entries are returned first,
+    /// followed by heuristic candidates that have not already been seen.
+    /// Link-local responders are included regardless of the prefix since they
+    /// are on-link hosts by definition.
+    ///
+    /// # Arguments
+    /// * `prefix` - IPv6 network to discover
+    ///
+    /// # Returns
+    /// * `ScanResult<Vec<Ipv6Target>>` - Deduplicated candidate targets
+    pub async fn discover(&self, prefix: &Ipv6Prefix) -> ScanResult<Vec<Ipv6Target>> {
+        if !self.config.enabled {
+            return Err(ScanError::validation_error(
+                "host_discovery.ipv6",
+                "IPv6 discovery is disabled in configuration",
+            ));
+        }
+
+        info!("Starting IPv6 discovery for {}", prefix);
+
+        let mut targets = Vec::new();
+        let mut seen = HashSet::new();
+        let mut push = |targets: &mut Vec<Ipv6Target>, address: Ipv6Addr, source| {
+            if seen.insert(address) {
+                targets.push(Ipv6Target { address, source });
+            }
+        };
+        let on_link = |addr: &Ipv6Addr| prefix.contains(*addr) || is_link_local(addr);
+
+        if self.config.multicast_ping {
+            match &self.config.interface {
+                Some(iface) => match multicast_ping_sweep(iface, self.config.timeout_ms).await {
+                    Ok(responders) => {
+                        for addr in responders.into_iter().filter(on_link) {
+                            push(&mut targets, addr, Ipv6TargetSource::MulticastEcho);
+                        }
+                    }
+                    Err(e) => warn!("IPv6 multicast ping failed: {}", e),
+                },
+                None => warn!("IPv6 multicast ping requires an interface, skipping"),
+            }
+        }
+
+        let mut macs = Vec::new();
+        if self.config.neighbor_cache {
+            match read_neighbor_table(self.config.interface.as_deref()) {
+                Ok(neighbors) => {
+                    for neighbor in neighbors {
+                        macs.extend(neighbor.mac);
+                        if on_link(&neighbor.address) {
+                            push(&mut targets, neighbor.address, Ipv6TargetSource::NeighborCache);
+                        }
+                    }
+                }
+                Err(e) => warn!("IPv6 neighbor cache read failed: {}", e),
+            }
+        }
+
+        if self.config.heuristic_targets {
+            for addr in generate_candidates(prefix, self.config.low_byte_count, &macs) {
+                push(&mut targets, addr, Ipv6TargetSource::Heuristic);
+            }
+        }
+
+        info!("IPv6 discovery for {} produced {} candidates", prefix, targets.len());
+        Ok(targets)
+    }
+}
+
+fn is_link_local(addr: &Ipv6Addr) -> bool {
+    addr.segments()[0] & 0xffc0 == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_parse_and_contains() {
+        let prefix = Ipv6Prefix::parse("2001:db8:1::abcd/64").unwrap();
+        assert_eq!(prefix.network, "2001:db8:1::".parse::<Ipv6Addr>().unwrap());
+        assert!(prefix.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!prefix.contains("2001:db8:2::1".parse().unwrap()));
+        assert!(Ipv6Prefix::parse("2001:db8::/129").is_err());
+        assert!(Ipv6Prefix::parse("not-an-address/64").is_err());
+    }
+
+    #[test]
+    fn test_eui64_interface_id() {
+        let id = eui64_interface_id([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+        assert_eq!(id, 0x5054_00ff_fe12_3456);
+    }
+
+    #[test]
+    fn test_generate_candidates() {
+        let prefix = Ipv6Prefix::parse("2001:db8::/64").unwrap();
+        let mac = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+        let candidates = generate_candidates(&prefix, 4, &[mac]);
+
+        assert_eq!(candidates[0], "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+        assert!(candidates.contains(&"2001:db8::4".parse().unwrap()));
+        assert!(candidates.contains(&"2001:db8::443".parse().unwrap()));
+        assert!(candidates.contains(&"2001:db8::5054:ff:fe12:3456".parse().unwrap()));
+
+        // Host IDs that do not fit in a /120 are dropped
+        let small = Ipv6Prefix::parse("2001:db8::/120").unwrap();
+        assert!(generate_candidates(&small, 4, &[mac]).iter().all(|a| small.contains(*a)));
+    }
+
+    #[test]
+    fn test_parse_neighbor_table() {
+        let output = "\
+fe80::1 dev eth0 lladdr 52:54:00:12:34:56 router REACHABLE
+2001:db8::5 dev eth0 lladdr 02:00:00:00:00:05 STALE
+2001:db8::6 dev eth0 FAILED
+garbage line
+";
+        let neighbors = parse_neighbor_table(output);
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].interface, "eth0");
+        assert_eq!(neighbors[0].mac, Some([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]));
+        assert_eq!(neighbors[1].state, "STALE");
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_discovery_disabled() {
+        let discovery = Ipv6Discovery::new(Ipv6DiscoveryConfig::default());
+        let prefix = Ipv6Prefix::parse("2001:db8::/64").unwrap();
+        assert!(discovery.discover(&prefix).await.is_err());
+    }
+}
@@ -0,0 +1,643 @@
+//! LAN device discovery over multicast
+//!
+//! Devices on the local segment announce themselves far more readily than
+//! they answer port scans: printers, cameras, TVs and speakers speak at
+//! least one of mDNS (Bonjour), SSDP (UPnP) and WS-Discovery. [`LanDiscovery`]
+//! sends each protocol's search to its multicast group, optionally listens
+//! for unsolicited announcements as well, and folds the answers into one
+//! [`LanDevice`] per address with the names, services and model strings it
+//! advertised. SSDP device descriptions are fetched for the model and
+//! manufacturer, and what the answers give away about the OS is kept as
+//! hints next to the active OS fingerprints.
+//!
+//! A device's JSON line carries its `ip`, which
+//! [`parse_target_list`](super::targets::parse_target_list) reads, so
+//! `nrmap lan-discover | nrmap scan-file -f -` scans what was found.
+
+use crate::detection::udp_fingerprint::{self, mdns_announcement, ssdp_header, ssdp_headers};
+use crate::error::{ScanError, ScanResult};
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddrV4};
+use std::str::FromStr;
+
+#[cfg(not(feature = "no-net"))]
+use {
+    futures::future::join_all,
+    std::time::Duration,
+    tokio::net::UdpSocket,
+    tokio::time::{timeout_at, Instant},
+    tracing::{debug, info, warn},
+};
+
+/// Service types queried alongside the DNS-SD enumeration, for the TXT
+/// records that carry model strings
+const MDNS_SERVICE_TYPES: &[&str] = &[
+    "_services._dns-sd._udp.local",
+    "_device-info._tcp.local",
+    "_googlecast._tcp.local",
+    "_airplay._tcp.local",
+    "_ipp._tcp.local",
+    "_printer._tcp.local",
+    "_hap._tcp.local",
+    "_workstation._tcp.local",
+];
+
+/// Advertised services that point at an operating system
+const SERVICE_OS_HINTS: &[(&str, &str)] = &[
+    ("_companion-link._tcp", "Apple iOS/macOS"),
+    ("_device-info._tcp", "Apple macOS"),
+    ("_workstation._tcp", "Linux (Avahi)"),
+    ("Computer", "Windows"),
+];
+
+/// A multicast discovery protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LanProtocol {
+    #[serde(rename = "mdns")]
+    Mdns,
+    #[serde(rename = "ssdp")]
+    Ssdp,
+    #[serde(rename = "wsd")]
+    WsDiscovery,
+}
+
+impl LanProtocol {
+    pub const ALL: [LanProtocol; 3] = [LanProtocol::Mdns, LanProtocol::Ssdp, LanProtocol::WsDiscovery];
+
+    /// Multicast group and port the protocol searches and announces on
+    pub fn group(self) -> SocketAddrV4 {
+        match self {
+            LanProtocol::Mdns => SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353),
+            LanProtocol::Ssdp => SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900),
+            LanProtocol::WsDiscovery => SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 3702),
+        }
+    }
+
+    /// The search message sent to the group
+    pub fn search(self) -> Vec<u8> {
+        match self {
+            LanProtocol::Mdns => {
+                // One query for several names, each asking for a unicast reply
+                let mut packet = vec![0x00, 0x00, 0x00, 0x00];
+                packet.extend_from_slice(&(MDNS_SERVICE_TYPES.len() as u16).to_be_bytes());
+                packet.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+                for name in MDNS_SERVICE_TYPES {
+                    for label in name.split('.') {
+                        packet.push(label.len() as u8);
+                        packet.extend_from_slice(label.as_bytes());
+                    }
+                    packet.extend_from_slice(&[0x00, 0x00, 0x0c, 0x80, 0x01]); // PTR, QU + IN
+                }
+                packet
+            }
+            LanProtocol::Ssdp => {
+                b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: ssdp:all\r\n\r\n"
+                    .to_vec()
+            }
+            LanProtocol::WsDiscovery => format!(
+                concat!(
+                    r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                    r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" "#,
+                    r#"xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" "#,
+                    r#"xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery">"#,
+                    r#"<soap:Header><wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To>"#,
+                    r#"<wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</wsa:Action>"#,
+                    r#"<wsa:MessageID>urn:uuid:{}</wsa:MessageID></soap:Header>"#,
+                    r#"<soap:Body><wsd:Probe/></soap:Body></soap:Envelope>"#
+                ),
+                uuid::Uuid::new_v4()
+            )
+            .into_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for LanProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LanProtocol::Mdns => "mdns",
+            LanProtocol::Ssdp => "ssdp",
+            LanProtocol::WsDiscovery => "wsd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for LanProtocol {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mdns" | "bonjour" => Ok(LanProtocol::Mdns),
+            "ssdp" | "upnp" => Ok(LanProtocol::Ssdp),
+            "wsd" | "ws-discovery" => Ok(LanProtocol::WsDiscovery),
+            _ => Err(ScanError::validation_error(
+                "protocol",
+                format!("unknown LAN discovery protocol {:?} (expected mdns, ssdp or wsd)", s),
+            )),
+        }
+    }
+}
+
+/// A device and everything it advertised
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanDevice {
+    pub ip: IpAddr,
+    pub protocols: Vec<LanProtocol>,
+    /// Host, instance and friendly names
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    /// Advertised service and device types, e.g. "_ipp._tcp" or
+    /// "urn:schemas-upnp-org:device:MediaRenderer:1"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// SSDP `SERVER` header, e.g. "Linux/3.14 UPnP/1.0 MiniUPnPd/2.1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// SSDP device description URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_url: Option<String>,
+    /// WS-Discovery service endpoints
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os_hints: Vec<String>,
+}
+
+fn push_unique(list: &mut Vec<String>, value: impl Into<String>) {
+    let value = value.into();
+    if !value.is_empty() && !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+impl LanDevice {
+    fn new(ip: IpAddr, protocol: LanProtocol) -> Self {
+        Self {
+            ip,
+            protocols: vec![protocol],
+            names: Vec::new(),
+            services: Vec::new(),
+            model: None,
+            manufacturer: None,
+            server: None,
+            description_url: None,
+            endpoints: Vec::new(),
+            os_hints: Vec::new(),
+        }
+    }
+
+    /// The device an answer or announcement from `ip` describes, if it
+    /// parses as `protocol`
+    pub fn from_answer(protocol: LanProtocol, ip: IpAddr, answer: &[u8]) -> Option<Self> {
+        let mut device = Self::new(ip, protocol);
+        match protocol {
+            LanProtocol::Mdns => {
+                let announcement = mdns_announcement(answer)?;
+                for name in announcement.instances.into_iter().chain(announcement.hosts) {
+                    push_unique(&mut device.names, name);
+                }
+                device.services = announcement.services;
+                device.model = announcement.model;
+            }
+            LanProtocol::Ssdp => {
+                let headers = ssdp_headers(answer)?;
+                device.server = ssdp_header(&headers, "server").map(str::to_string);
+                device.description_url = ssdp_header(&headers, "location").map(str::to_string);
+                // Answers carry the type in ST, announcements in NT
+                if let Some(kind) = ssdp_header(&headers, "st").or_else(|| ssdp_header(&headers, "nt")) {
+                    push_unique(&mut device.services, kind);
+                }
+                if let Some(os) = udp_fingerprint::fingerprint(1900, answer).and_then(|fp| fp.os_info) {
+                    push_unique(&mut device.os_hints, os);
+                }
+            }
+            LanProtocol::WsDiscovery => {
+                let text = std::str::from_utf8(answer).ok()?;
+                let document = Document::parse(text).ok()?;
+                let values = |element: &str| -> Vec<String> {
+                    document
+                        .descendants()
+                        .filter(|node| node.tag_name().name() == element)
+                        .filter_map(|node| node.text())
+                        .flat_map(str::split_whitespace)
+                        .map(str::to_string)
+                        .collect()
+                };
+                // "dn:NetworkVideoTransmitter" with its namespace prefix dropped
+                for kind in values("Types") {
+                    push_unique(&mut device.services, kind.rsplit(':').next().unwrap_or(&kind));
+                }
+                for scope in values("Scopes") {
+                    let Some(path) = scope.strip_prefix("onvif://www.onvif.org/") else { continue };
+                    match path.split_once('/') {
+                        Some(("name", name)) => push_unique(&mut device.names, percent_decode(name)),
+                        Some(("hardware", model)) => device.model = Some(percent_decode(model)),
+                        _ => {}
+                    }
+                }
+                device.endpoints = values("XAddrs");
+                if device.services.is_empty() && device.endpoints.is_empty() {
+                    return None;
+                }
+            }
+        }
+        for (service, os) in SERVICE_OS_HINTS {
+            if device.services.iter().any(|s| s == service) {
+                push_unique(&mut device.os_hints, *os);
+            }
+        }
+        Some(device)
+    }
+
+    /// Take in what another answer from the same address said
+    fn merge(&mut self, other: LanDevice) {
+        for protocol in other.protocols {
+            if !self.protocols.contains(&protocol) {
+                self.protocols.push(protocol);
+                self.protocols.sort();
+            }
+        }
+        for name in other.names {
+            push_unique(&mut self.names, name);
+        }
+        for service in other.services {
+            push_unique(&mut self.services, service);
+        }
+        for endpoint in other.endpoints {
+            push_unique(&mut self.endpoints, endpoint);
+        }
+        for hint in other.os_hints {
+            push_unique(&mut self.os_hints, hint);
+        }
+        self.model = self.model.take().or(other.model);
+        self.manufacturer = self.manufacturer.take().or(other.manufacturer);
+        self.server = self.server.take().or(other.server);
+        self.description_url = self.description_url.take().or(other.description_url);
+    }
+
+    /// Fill in the friendly name, manufacturer and model from a UPnP device
+    /// description document
+    pub fn apply_description(&mut self, xml: &str) {
+        let Ok(document) = Document::parse(xml) else { return };
+        let Some(device) = document.descendants().find(|node| node.tag_name().name() == "device") else { return };
+        let field = |name: &str| {
+            device
+                .children()
+                .find(|node| node.tag_name().name() == name)
+                .and_then(|node| node.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        if let Some(name) = field("friendlyName") {
+            push_unique(&mut self.names, name);
+        }
+        self.manufacturer = self.manufacturer.take().or_else(|| field("manufacturer"));
+        let model = match (field("modelName"), field("modelNumber")) {
+            (Some(name), Some(number)) if !name.contains(&number) => Some(format!("{} {}", name, number)),
+            (name, number) => name.or(number),
+        };
+        self.model = self.model.take().or(model);
+    }
+
+    /// One line of a target list
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// "192.0.2.10 [mdns, ssdp] Chromecast Ultra (Google) - Living Room; _googlecast._tcp"
+impl fmt::Display for LanDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocols: Vec<String> = self.protocols.iter().map(|p| p.to_string()).collect();
+        write!(f, "{} [{}]", self.ip, protocols.join(", "))?;
+        if let Some(ref model) = self.model {
+            write!(f, " {}", model)?;
+        }
+        if let Some(ref manufacturer) = self.manufacturer {
+            write!(f, " ({})", manufacturer)?;
+        }
+        let details: Vec<String> = [&self.names, &self.services]
+            .iter()
+            .filter(|list| !list.is_empty())
+            .map(|list| list.join(", "))
+            .collect();
+        if !details.is_empty() {
+            write!(f, " - {}", details.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// One device per address, in address order
+pub fn merge_devices(devices: impl IntoIterator<Item = LanDevice>) -> Vec<LanDevice> {
+    let mut merged: BTreeMap<IpAddr, LanDevice> = BTreeMap::new();
+    for device in devices {
+        match merged.get_mut(&device.ip) {
+            Some(known) => known.merge(device),
+            None => {
+                merged.insert(device.ip, device);
+            }
+        }
+    }
+    merged.into_values().collect()
+}
+
+/// Decode the `%XX` escapes of an ONVIF scope
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Searches the local segment over multicast for a fixed time
+#[cfg(not(feature = "no-net"))]
+#[derive(Debug, Clone)]
+pub struct LanDiscovery {
+    duration: Duration,
+    protocols: Vec<LanProtocol>,
+    listen: bool,
+    interface: Option<Ipv4Addr>,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl LanDiscovery {
+    /// Search with every protocol, collecting answers for `duration`
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            protocols: LanProtocol::ALL.to_vec(),
+            listen: false,
+            interface: None,
+        }
+    }
+
+    pub fn with_protocols(mut self, protocols: Vec<LanProtocol>) -> Self {
+        if !protocols.is_empty() {
+            self.protocols = protocols;
+        }
+        self
+    }
+
+    /// Also join the multicast groups and take in unsolicited announcements
+    pub fn with_listen(mut self, listen: bool) -> Self {
+        self.listen = listen;
+        self
+    }
+
+    /// Send from and listen on the interface with this address
+    pub fn with_interface(mut self, interface: Ipv4Addr) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Devices that answered or announced themselves, with their SSDP
+    /// descriptions applied; a protocol that cannot be used is logged and
+    /// skipped
+    pub async fn discover(&self) -> ScanResult<Vec<LanDevice>> {
+        info!(
+            "LAN discovery over {} for {:?}{}",
+            self.protocols.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "),
+            self.duration,
+            if self.listen { ", listening for announcements" } else { "" }
+        );
+        let deadline = Instant::now() + self.duration;
+        let mut runs = Vec::new();
+        for &protocol in &self.protocols {
+            runs.push(self.run(protocol, false, deadline));
+            if self.listen {
+                runs.push(self.run(protocol, true, deadline));
+            }
+        }
+
+        let mut found = Vec::new();
+        let mut failures = Vec::new();
+        for outcome in join_all(runs).await {
+            match outcome {
+                Ok(devices) => found.extend(devices),
+                Err(e) => {
+                    warn!("LAN discovery: {}", e);
+                    failures.push(e);
+                }
+            }
+        }
+        if found.is_empty() && failures.len() == self.protocols.len() * if self.listen { 2 } else { 1 } {
+            return Err(failures.remove(0));
+        }
+
+        let mut devices = merge_devices(found);
+        join_all(devices.iter_mut().map(describe)).await;
+        Ok(devices)
+    }
+
+    /// Search with `protocol`, or listen on its group, until `deadline`
+    async fn run(&self, protocol: LanProtocol, listen: bool, deadline: Instant) -> ScanResult<Vec<LanDevice>> {
+        let group = protocol.group();
+        let socket = self.socket(protocol, listen).map_err(|e| {
+            ScanError::network(format!(
+                "cannot {} {} on {}: {}",
+                if listen { "listen for" } else { "search with" },
+                protocol,
+                group,
+                e
+            ))
+        })?;
+        if !listen {
+            socket.send_to(&protocol.search(), group).await?;
+            debug!("Sent {} search to {}", protocol, group);
+        }
+
+        let mut devices = Vec::new();
+        let mut buffer = vec![0u8; 9000];
+        loop {
+            match timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+                Ok(Ok((len, from))) => match LanDevice::from_answer(protocol, from.ip(), &buffer[..len]) {
+                    Some(device) => devices.push(device),
+                    None => debug!("Ignoring unparsable {} message from {}", protocol, from),
+                },
+                Ok(Err(e)) => debug!("{} receive error: {}", protocol, e),
+                Err(_) => break,
+            }
+        }
+        Ok(devices)
+    }
+
+    /// An ephemeral socket for searching, or one bound to the group's port
+    /// and joined to the group for listening
+    fn socket(&self, protocol: LanProtocol, listen: bool) -> std::io::Result<UdpSocket> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        if listen {
+            // Shares the port with a local mDNS responder or UPnP stack
+            socket.set_reuse_address(true)?;
+            #[cfg(unix)]
+            socket.set_reuse_port(true)?;
+            socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, protocol.group().port()).into())?;
+            socket.join_multicast_v4(protocol.group().ip(), &interface)?;
+        } else {
+            socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0).into())?;
+        }
+        if self.interface.is_some() {
+            socket.set_multicast_if_v4(&interface)?;
+        }
+        socket.set_nonblocking(true)?;
+        UdpSocket::from_std(socket.into())
+    }
+}
+
+/// The device's SSDP description URL, if it is safe to fetch
+///
+/// LOCATION comes from an unauthenticated multicast answer, so anyone on
+/// the segment could point the scanner at an internal or remote service.
+/// Only plain http URLs on the answering device itself are fetched.
+fn description_url(device: &LanDevice) -> Option<&str> {
+    let url = device.description_url.as_deref()?;
+    let rest = url.strip_prefix("http://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    if authority.contains('@') {
+        return None;
+    }
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, port) = bracketed.split_once(']')?;
+            if !port.is_empty() {
+                port.strip_prefix(':')?.parse::<u16>().ok()?;
+            }
+            host
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => {
+                port.parse::<u16>().ok()?;
+                host
+            }
+            None => authority,
+        },
+    };
+    (host.parse::<IpAddr>().ok()? == device.ip).then_some(url)
+}
+
+/// Fetch and apply the device's SSDP description, if it has one
+#[cfg(not(feature = "no-net"))]
+async fn describe(device: &mut LanDevice) {
+    let Some(url) = description_url(device).map(str::to_string) else {
+        if let Some(url) = &device.description_url {
+            debug!("Not fetching device description {}: not a plain http URL on {}", url, device.ip);
+        }
+        return;
+    };
+    let fetch = url.clone();
+    let fetched = tokio::task::spawn_blocking(move || {
+        // No redirects: they could lead off the device
+        let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(3)).redirects(0).build();
+        agent.get(&fetch).call().map_err(|e| e.to_string())?.into_string().map_err(|e| e.to_string())
+    })
+    .await;
+    match fetched {
+        Ok(Ok(xml)) => device.apply_description(&xml),
+        Ok(Err(e)) => debug!("Fetching device description {} failed: {}", url, e),
+        Err(e) => debug!("Fetching device description {} failed: {}", url, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_from_answers() {
+        let ip: IpAddr = "192.0.2.10".parse().unwrap();
+        let ssdp = b"HTTP/1.1 200 OK\r\nST: urn:dial-multiscreen-org:service:dial:1\r\nSERVER: Linux/4.9 UPnP/1.0 quick_ssdp/1.0\r\nLOCATION: http://192.0.2.10:8008/ssdp/device-desc.xml\r\n\r\n";
+        let mut device = LanDevice::from_answer(LanProtocol::Ssdp, ip, ssdp).unwrap();
+        assert_eq!(device.description_url.as_deref(), Some("http://192.0.2.10:8008/ssdp/device-desc.xml"));
+        assert_eq!(description_url(&device), device.description_url.as_deref());
+        assert_eq!(device.os_hints, vec!["Linux 4.9"]);
+        device.apply_description(
+            r#"<?xml version="1.0"?><root xmlns="urn:schemas-upnp-org:device-1-0"><device>
+                <deviceType>urn:dial-multiscreen-org:device:dial:1</deviceType>
+                <friendlyName>Living Room</friendlyName><manufacturer>Google Inc.</manufacturer>
+                <modelName>Chromecast Ultra</modelName></device></root>"#,
+        );
+
+        let probe_match = r#"<?xml version="1.0" encoding="UTF-8"?>
+<env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope" xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:dn="http://www.onvif.org/ver10/network/wsdl">
+<env:Body><d:ProbeMatches><d:ProbeMatch>
+<d:Types>dn:NetworkVideoTransmitter</d:Types>
+<d:Scopes>onvif://www.onvif.org/type/video_encoder onvif://www.onvif.org/hardware/DS-2CD2042WD onvif://www.onvif.org/name/Front%20Door</d:Scopes>
+<d:XAddrs>http://192.0.2.20/onvif/device_service</d:XAddrs>
+</d:ProbeMatch></d:ProbeMatches></env:Body></env:Envelope>"#;
+        let camera: IpAddr = "192.0.2.20".parse().unwrap();
+        let onvif = LanDevice::from_answer(LanProtocol::WsDiscovery, camera, probe_match.as_bytes()).unwrap();
+        assert_eq!(onvif.to_string(), "192.0.2.20 [wsd] DS-2CD2042WD - Front Door; NetworkVideoTransmitter");
+        assert_eq!(onvif.endpoints, vec!["http://192.0.2.20/onvif/device_service"]);
+        assert!(LanDevice::from_answer(LanProtocol::WsDiscovery, camera, b"<a/>").is_none());
+
+        // The same address over mDNS: a PTR for a Cast instance
+        let mut mdns = vec![0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let owner: &[u8] = b"\x0b_googlecast\x04_tcp\x05local\x00";
+        mdns.extend_from_slice(owner);
+        let target = [&b"\x0bLiving Room"[..], &[0xc0, 0x0c]].concat();
+        mdns.extend_from_slice(&[0x00, 0x0c, 0x00, 0x01, 0, 0, 0x11, 0x94, 0x00, target.len() as u8]);
+        mdns.extend_from_slice(&target);
+        let cast = LanDevice::from_answer(LanProtocol::Mdns, ip, &mdns).unwrap();
+        assert_eq!(cast.services, vec!["_googlecast._tcp"]);
+
+        let devices = merge_devices([onvif, device, cast]);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(
+            devices[0].to_string(),
+            "192.0.2.10 [mdns, ssdp] Chromecast Ultra (Google Inc.) - Living Room; urn:dial-multiscreen-org:service:dial:1, _googlecast._tcp"
+        );
+        let line = devices[0].to_json_line();
+        assert!(line.starts_with(r#"{"ip":"192.0.2.10","protocols":["mdns","ssdp"]"#));
+        assert_eq!(super::super::targets::parse_target_list(&line).unwrap(), vec![ip]);
+
+        // Descriptions are only fetched from the answering device, over http
+        let mut elsewhere = LanDevice::from_answer(LanProtocol::Ssdp, ip, ssdp).unwrap();
+        for location in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://192.0.2.10.evil.example/desc.xml",
+            "http://admin@192.0.2.10/desc.xml",
+            "https://192.0.2.10/desc.xml",
+            "file:///etc/passwd",
+            "http://192.0.2.10:notaport/desc.xml",
+        ] {
+            elsewhere.description_url = Some(location.to_string());
+            assert_eq!(description_url(&elsewhere), None, "{}", location);
+        }
+        elsewhere.description_url = Some("http://192.0.2.10/desc.xml".to_string());
+        assert!(description_url(&elsewhere).is_some());
+        let mut v6 = LanDevice::from_answer(LanProtocol::Ssdp, "fe80::1".parse().unwrap(), ssdp).unwrap();
+        v6.description_url = Some("http://[fe80::1]:1400/xml/device_description.xml".to_string());
+        assert!(description_url(&v6).is_some());
+
+        assert_eq!("upnp".parse::<LanProtocol>().unwrap(), LanProtocol::Ssdp);
+        assert!("netbios".parse::<LanProtocol>().is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Round-trip time statistics
+//!
+//! Every probe that gets an answer (a completed connect, a RST, a SYN-ACK or a
+//! UDP reply) carries its round-trip time. These are aggregated per host and
+//! per report into min/avg/max/p95 latency plus jitter, which is also what the
+//! adaptive timeouts are derived from.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Aggregated round-trip times
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Number of probes with a measured RTT
+    pub samples: usize,
+    pub min_ms: u64,
+    pub avg_ms: f64,
+    pub max_ms: u64,
+    /// 95th percentile (nearest rank)
+    pub p95_ms: u64,
+    /// Standard deviation of the RTTs
+    pub jitter_ms: f64,
+}
+
+impl LatencyStats {
+    /// Aggregate RTT samples; `None` when there are none
+    pub fn from_samples(samples: &[u64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+
+        let count = sorted.len();
+        let avg_ms = sorted.iter().sum::<u64>() as f64 / count as f64;
+        let variance = sorted.iter().map(|&s| (s as f64 - avg_ms).powi(2)).sum::<f64>() / count as f64;
+        // Nearest rank: the smallest sample with at least 95% at or below it
+        let p95_rank = (count as f64 * 0.95).ceil() as usize;
+
+        Some(Self {
+            samples: count,
+            min_ms: sorted[0],
+            avg_ms,
+            max_ms: sorted[count - 1],
+            p95_ms: sorted[p95_rank.max(1) - 1],
+            jitter_ms: variance.sqrt(),
+        })
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {}ms / avg {:.1}ms / max {}ms / p95 {}ms, jitter {:.1}ms ({} samples)",
+            self.min_ms, self.avg_ms, self.max_ms, self.p95_ms, self.jitter_ms, self.samples
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        assert!(LatencyStats::from_samples(&[]).is_none());
+
+        let samples: Vec<u64> = (1..=20).rev().collect();
+        let stats = LatencyStats::from_samples(&samples).unwrap();
+        assert_eq!(stats.samples, 20);
+        assert_eq!(stats.min_ms, 1);
+        assert_eq!(stats.max_ms, 20);
+        assert_eq!(stats.p95_ms, 19);
+        assert!((stats.avg_ms - 10.5).abs() < 1e-9);
+        assert!((stats.jitter_ms - 5.766).abs() < 1e-3);
+
+        let single = LatencyStats::from_samples(&[7]).unwrap();
+        assert_eq!((single.p95_ms, single.jitter_ms), (7, 0.0));
+    }
+}
@@ -3,23 +3,124 @@
 /// This module provides the main scanner interface that coordinates
 /// host discovery, port scanning, and adaptive throttling.
 
+#[cfg(not(feature = "no-net"))]
+pub mod arp_warmup;
+pub mod authorized_scope;
+pub mod blackout;
+pub mod confidence;
+pub mod deception;
+pub mod deep_check;
+pub mod discovery_cache;
+pub mod discovery_probe;
+pub mod dns_discovery;
+pub mod evasion;
+pub mod fd_limit;
 pub mod host_discovery;
+pub mod icmp_ratelimit;
+pub mod icmp_unreachable;
+pub mod ipv6_discovery;
+pub mod lan_discovery;
+pub mod latency;
+pub mod order;
+#[cfg(not(feature = "no-net"))]
+pub mod os_detection;
+#[cfg(not(feature = "no-net"))]
+pub mod ping;
+pub mod privileges;
+pub mod probe_error;
+pub mod pause;
+pub mod phase_timing;
+pub mod port_frequency;
+pub mod port_expansion;
+pub mod port_presets;
+pub mod service_names;
+pub mod ssh_tunnel;
+#[cfg(not(feature = "no-net"))]
+pub mod syn_engine;
+pub mod progress;
+pub mod reason;
+pub mod resources;
+pub mod retry;
 pub mod tcp_connect;
 pub mod tcp_syn;
 pub mod udp_scan;
+pub mod targets;
 pub mod throttle;
+pub mod timing;
+pub mod verification;
 
-use crate::config::ScannerConfig;
-use host_discovery::{HostDiscovery, HostStatus};
-use tcp_connect::{PortStatus, TcpConnectResult, TcpConnectScanner};
-use tcp_syn::{TcpSynResult, TcpSynScanner};
-use udp_scan::{UdpScanResult, UdpScanner};
-use throttle::{AdaptiveThrottle, ThrottleStats};
+use crate::error::ScanError;
+use crate::enrichment::{DiscoveredHostname, GeoInfo, ObservedCertificate};
+pub use authorized_scope::AuthorizedScope;
+pub use blackout::{BlackoutCalendar, BlackoutWindow};
+pub use confidence::Confidence;
+pub use discovery_probe::{ConsensusPolicy, DiscoveryProbe, ProbeVote};
+pub use evasion::{EvasionOutcome, ProbeEvasion, ResponderClass};
+pub use fd_limit::{Concurrency, UlimitCheck};
+pub use order::ScanOrder;
+pub use pause::PauseControl;
+pub use probe_error::ProbeError;
+pub use phase_timing::PhaseTimings;
+pub use progress::{ProgressTracker, ScanProgress};
+pub use reason::PortReason;
+pub use resources::{ResourceMeter, ResourceUsage};
+pub use retry::RetryPolicy;
+pub use throttle::{ThrottleFactory, ThrottleScanResult, ThrottleStrategy};
+use host_discovery::HostStatus;
+use deception::DeceptionAssessment;
+use deep_check::LivenessCheck;
+use latency::LatencyStats;
+use port_expansion::PortExpansion;
+use crate::lan::HostMac;
+use icmp_ratelimit::IcmpRateLimit;
+use crate::os_fingerprint::OsMatchResult;
+use tcp_connect::{PortStatus, TcpConnectResult};
+use tcp_syn::TcpSynResult;
+use udp_scan::UdpScanResult;
+use throttle::ThrottleStats;
+use verification::PortVerification;
+use std::collections::BTreeMap;
 use std::net::IpAddr;
-use std::sync::Arc;
-use tracing::{info, warn};
+#[cfg(not(feature = "no-net"))]
+use network::*;
+
+/// Imports of the scanning engines, which the `no-net` core leaves out
+#[cfg(not(feature = "no-net"))]
+mod network {
+    pub(super) use crate::config::{AppConfig, ScannerConfig, ThrottlingConfig};
+    pub(super) use crate::enrichment::{
+        GeoIpEnricher, HostnameExclusion, ReverseDnsResolver, TlsEvidence, TlsHostnameCollector, VhostQueue,
+    };
+    pub(super) use crate::packet::{
+        pcap, CaptureConfig, CaptureFilter, PacketCapture, PacketEngine, PacketEngineConfig, PcapWriter,
+    };
+    pub(super) use super::arp_warmup::{ArpWarmup, ArpWarmupReport};
+    pub(super) use super::discovery_cache::DiscoveryCache;
+    pub(super) use super::host_discovery::{DiscoveryResult, HostDiscovery};
+    pub(super) use super::ipv6_discovery::Ipv6Prefix;
+    pub(super) use super::os_detection::{OsDetectionJob, OsDetectionQueue};
+    pub(super) use super::privileges::PrivilegeStatus;
+    pub(super) use super::ssh_tunnel::{SshTunnel, SshTunnelConfig};
+    pub(super) use super::tcp_connect::TcpConnectScanner;
+    pub(super) use super::tcp_syn::TcpSynScanner;
+    pub(super) use super::throttle::{AdaptiveThrottle, ScanRateLimiter};
+    pub(super) use super::timing::HostTimings;
+    pub(super) use super::udp_scan::UdpScanner;
+    pub(super) use std::collections::HashMap;
+    pub(super) use std::path::PathBuf;
+    pub(super) use std::sync::Arc;
+    pub(super) use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+    pub(super) use tokio_util::sync::CancellationToken;
+    pub(super) use tracing::{info, info_span, warn, Instrument};
+}
 use serde::{Deserialize, Serialize};
 
+/// Results of a port scan that came from at least one completed probe
+#[cfg(not(feature = "no-net"))]
+fn probed<T>(results: &[T], attempts: impl Fn(&T) -> u32) -> usize {
+    results.iter().filter(|result| attempts(result) > 0).count()
+}
+
 /// Scan type selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScanType {
@@ -28,6 +129,20 @@ pub enum ScanType {
     Udp,
 }
 
+/// Parses the CLI names: `tcp`/`connect`, `syn` and `udp`
+impl std::str::FromStr for ScanType {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" | "connect" => Ok(ScanType::TcpConnect),
+            "syn" => Ok(ScanType::TcpSyn),
+            "udp" => Ok(ScanType::Udp),
+            _ => Err(ScanError::validation_error("scan_type", format!("Unknown scan type: {}", s))),
+        }
+    }
+}
+
 /// Comprehensive scan result combining all scan types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompleteScanResult {
@@ -38,48 +153,759 @@ pub struct CompleteScanResult {
     pub udp_results: Vec<UdpScanResult>,
     pub scan_duration_ms: u64,
     pub throttle_stats: Option<ThrottleStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Exclusion rule that caused this host to be skipped, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excluded_by: Option<String>,
+    /// Hostnames observed in scan evidence (e.g. TLS certificate SANs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discovered_hostnames: Vec<DiscoveredHostname>,
+    /// Certificates seen on open TLS ports, with their expiry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tls_certificates: Vec<ObservedCertificate>,
+    /// OS matches, best first, when OS detection is enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os_matches: Vec<OsMatchResult>,
+    /// Why the host was reported down without being probed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_reason: Option<String>,
+    /// Open ports re-checked with the other TCP scan type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port_verifications: Vec<PortVerification>,
+    /// Protocol liveness of open ports (`--deep-check`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub liveness: Vec<LivenessCheck>,
+    /// Honeypot/deception suspicion, when any heuristic fired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deception: Option<DeceptionAssessment>,
+    /// Round-trip times of every answered probe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyStats>,
+    /// Time spent in each scan phase; absent for hosts that were skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase_timings: Option<PhaseTimings>,
+    /// Port results dropped at collection time (`open_ports_only`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dropped_ports: Option<DroppedPorts>,
+    /// Second pass over the rest of 1-65535 (`expand_ports`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expansion: Option<PortExpansion>,
+    /// MAC address of an on-link host resolved by ARP warm-up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<HostMac>,
+    /// ICMP unreachable rate limit inferred during the UDP scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icmp_rate_limit: Option<IcmpRateLimit>,
+}
+
+/// Counters standing in for port results that were not kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedPorts {
+    pub closed: usize,
+    pub filtered: usize,
+    pub unknown: usize,
+    /// Error classes of the dropped ports whose probes failed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<ProbeError, usize>,
+}
+
+impl DroppedPorts {
+    fn count(&mut self, status: &PortStatus, error: Option<ProbeError>) {
+        match status {
+            PortStatus::Open => return,
+            PortStatus::Closed => self.closed += 1,
+            PortStatus::Filtered => self.filtered += 1,
+            PortStatus::Unknown => self.unknown += 1,
+        }
+        if let Some(error) = error {
+            *self.errors.entry(error).or_insert(0) += 1;
+        }
+    }
+
+    /// Number of dropped port results
+    pub fn total(&self) -> usize {
+        self.closed + self.filtered + self.unknown
+    }
+}
+
+/// Hand-off from the scan passes to the thread running `on_result`
+#[cfg(not(feature = "no-net"))]
+struct Delivery {
+    sender: mpsc::UnboundedSender<(CompleteScanResult, OwnedSemaphorePermit)>,
+    /// `result_buffer` slots, one per host between its port scan and
+    /// `on_result`
+    slots: Arc<Semaphore>,
 }
 
 /// Main scanner orchestrator
+#[cfg(not(feature = "no-net"))]
 pub struct Scanner {
     config: ScannerConfig,
     host_discovery: HostDiscovery,
     tcp_scanner: TcpConnectScanner,
     syn_scanner: TcpSynScanner,
     udp_scanner: UdpScanner,
-    throttle: Option<Arc<AdaptiveThrottle>>,
+    throttle: Option<Arc<dyn ThrottleStrategy>>,
+    throttle_strategies: HashMap<String, ThrottleFactory>,
+    /// `[throttling]` thresholds the throttle is built with
+    throttling: ThrottlingConfig,
+    geoip: Option<Arc<GeoIpEnricher>>,
+    rdns: Option<ReverseDnsResolver>,
+    hostname_exclusion: HostnameExclusion,
+    authorized_scope: AuthorizedScope,
+    blackouts: BlackoutCalendar,
+    tls_hostnames: Option<TlsHostnameCollector>,
+    privileges: PrivilegeStatus,
+    pcap_output: Option<PathBuf>,
+    os_detection: Option<OsDetectionQueue>,
+    arp_warmup: Option<ArpWarmup>,
+    discovery_cache: Option<Arc<DiscoveryCache>>,
+    host_timings: Option<HostTimings>,
+    operator: Option<String>,
+    /// Correlation ID attached to every log event of a scan
+    scan_id: String,
+    progress: Arc<ProgressTracker>,
+    cancel: CancellationToken,
+    pause: PauseControl,
+    /// What the last finished scan run used
+    resources: std::sync::Mutex<Option<ResourceUsage>>,
+    /// Concurrency fitted to the open file limit, settled at the first scan
+    concurrency: std::sync::OnceLock<Concurrency>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl Scanner {
     /// Create a new scanner with configuration
     pub fn new(config: ScannerConfig) -> Self {
         info!("Initializing scanner with configuration");
 
-        let throttle = if config.adaptive_throttling {
-            Some(Arc::new(AdaptiveThrottle::new(
-                crate::config::ThrottlingConfig {
-                    enabled: true,
-                    success_threshold: 0.95,
-                    failure_threshold: 0.80,
-                    rate_increase_factor: 1.5,
-                    rate_decrease_factor: 0.5,
-                    window_size: 100,
-                    adjustment_interval_ms: 1000,
-                },
-                config.initial_pps,
-            )))
-        } else {
-            None
-        };
+        let privileges = PrivilegeStatus::detect();
+        let progress = Arc::new(ProgressTracker::new());
+        let cancel = CancellationToken::new();
+        let pause = PauseControl::new();
 
-        Self {
-            host_discovery: HostDiscovery::new(config.host_discovery.clone()),
-            tcp_scanner: TcpConnectScanner::new(config.tcp_connect.clone()),
-            syn_scanner: TcpSynScanner::new(config.tcp_syn.clone()),
-            udp_scanner: UdpScanner::new(config.udp.clone()),
-            throttle,
+        let mut scanner = Self {
+            host_discovery: HostDiscovery::new(config.host_discovery.clone())
+                .with_privileges(privileges),
+            tcp_scanner: TcpConnectScanner::new(config.tcp_connect.clone())
+                .with_evasion(config.evasion)
+                .with_retry_policy(config.retry_policy)
+                .with_progress(progress.clone())
+                .with_cancellation(cancel.clone())
+                .with_pause_control(pause.clone()),
+            syn_scanner: TcpSynScanner::new(config.tcp_syn.clone())
+                .with_evasion(config.evasion)
+                .with_retry_policy(config.retry_policy)
+                .with_progress(progress.clone())
+                .with_cancellation(cancel.clone())
+                .with_pause_control(pause.clone()),
+            udp_scanner: UdpScanner::new(config.udp.clone())
+                .with_evasion(config.evasion)
+                .with_retry_policy(config.retry_policy)
+                .with_progress(progress.clone())
+                .with_cancellation(cancel.clone())
+                .with_pause_control(pause.clone()),
+            throttle: None,
+            throttle_strategies: HashMap::from([(
+                throttle::ADAPTIVE_STRATEGY.to_string(),
+                Arc::new(|config: &ScannerConfig, throttling: &ThrottlingConfig| {
+                    Arc::new(AdaptiveThrottle::from_scanner_config(config, throttling)) as Arc<dyn ThrottleStrategy>
+                }) as ThrottleFactory,
+            )]),
+            throttling: ThrottlingConfig::default(),
+            geoip: None,
+            rdns: None,
+            hostname_exclusion: HostnameExclusion::default(),
+            authorized_scope: AuthorizedScope::default(),
+            blackouts: BlackoutCalendar::default(),
+            tls_hostnames: None,
+            privileges,
+            pcap_output: None,
+            os_detection: None,
+            arp_warmup: config
+                .host_discovery
+                .arp_warmup
+                .then(|| ArpWarmup::new(config.host_discovery.timeout_ms)),
+            discovery_cache: config
+                .host_discovery
+                .cache
+                .enabled
+                .then(|| Arc::new(DiscoveryCache::from_config(&config.host_discovery.cache, None))),
+            host_timings: None,
+            operator: None,
+            scan_id: uuid::Uuid::new_v4().to_string(),
+            progress,
+            cancel,
+            pause,
+            resources: std::sync::Mutex::new(None),
+            concurrency: std::sync::OnceLock::new(),
             config,
+        };
+        scanner.install_throttle();
+        let scanner = match scanner.config.adaptive_timeout.enabled {
+            true => {
+                let adaptive = scanner.config.adaptive_timeout.clone();
+                scanner.with_adaptive_timeouts(adaptive)
+            }
+            false => scanner,
+        };
+        let scanner = match (scanner.config.min_rate, scanner.config.max_rate) {
+            (None, None) => scanner,
+            (min_rate, max_rate) => scanner.with_rate_limits(min_rate, max_rate),
+        };
+        let scanner = match scanner.config.max_concurrent_probes_total {
+            0 => scanner,
+            total => scanner.with_max_concurrent_probes_total(total),
+        };
+        let scan_order = scanner.config.scan_order;
+        let scanner = scanner.with_scan_order(scan_order);
+        match scanner.config.ssh_tunnel.clone() {
+            Some(tunnel) => scanner.with_ssh_tunnel(tunnel),
+            None => scanner,
+        }
+    }
+
+    /// Scan through an SSH bastion
+    ///
+    /// TCP connects are made by the bastion over `direct-tcpip` channels.
+    /// The targets are assumed reachable only from there, so host discovery
+    /// and ARP warmup are skipped and other scan types are refused.
+    pub fn with_ssh_tunnel(mut self, tunnel: SshTunnelConfig) -> Self {
+        info!("Scanning through SSH bastion {}", tunnel.bastion);
+        self.tcp_scanner = self.tcp_scanner.with_ssh_tunnel(Arc::new(SshTunnel::new(tunnel.clone())));
+        self.arp_warmup = None;
+        self.config.ssh_tunnel = Some(tunnel);
+        self
+    }
+
+    /// Build the throttle selected by `throttle_strategy` and hand it to the
+    /// port scanners
+    fn install_throttle(&mut self) {
+        if !self.config.adaptive_throttling {
+            self.throttle = None;
+            return;
+        }
+        let factory = match self.throttle_strategies.get(&self.config.throttle_strategy) {
+            Some(factory) => factory,
+            None => {
+                warn!(
+                    "Unknown throttle strategy '{}', using '{}'",
+                    self.config.throttle_strategy,
+                    throttle::ADAPTIVE_STRATEGY
+                );
+                &self.throttle_strategies[throttle::ADAPTIVE_STRATEGY]
+            }
+        };
+        let throttle = factory(&self.config, &self.throttling);
+        info!("Using throttle strategy '{}'", throttle.name());
+
+        self.tcp_scanner.set_throttle(throttle.clone());
+        self.syn_scanner.set_throttle(throttle.clone());
+        self.udp_scanner.set_throttle(throttle.clone());
+        self.throttle = Some(throttle);
+    }
+
+    /// Register a custom throttle strategy under `name`
+    ///
+    /// The factory is called with the scanner and `[throttling]`
+    /// configuration whenever the throttle is (re)built; the strategy is
+    /// used when `throttle_strategy` selects `name`.
+    pub fn with_throttle_strategy(mut self, name: impl Into<String>, factory: ThrottleFactory) -> Self {
+        let name = name.into();
+        info!("Registering throttle strategy: {}", name);
+        let selected = name == self.config.throttle_strategy;
+        self.throttle_strategies.insert(name, factory);
+        if selected {
+            self.install_throttle();
+        }
+        self
+    }
+
+    /// Select a registered throttle strategy by name
+    pub fn with_throttle_strategy_name(mut self, name: impl Into<String>) -> Self {
+        self.config.throttle_strategy = name.into();
+        self.install_throttle();
+        self
+    }
+
+    /// Build the throttle with these `[throttling]` thresholds instead of
+    /// the defaults
+    pub fn with_throttling(mut self, throttling: ThrottlingConfig) -> Self {
+        self.throttling = throttling;
+        self.install_throttle();
+        self
+    }
+
+    /// Shuffle target and port order (`--randomize-ports`/`--randomize-hosts`)
+    ///
+    /// An unset seed is replaced by a random one, which is logged so the run
+    /// can be repeated with `--randomize-seed`.
+    pub fn with_scan_order(mut self, scan_order: ScanOrder) -> Self {
+        let scan_order = scan_order.with_resolved_seed();
+        if let (true, Some(seed)) = (scan_order.is_active(), scan_order.seed) {
+            info!(
+                "Randomized scan order (ports: {}, targets: {}), seed {}",
+                scan_order.randomize_ports, scan_order.randomize_targets, seed
+            );
+        }
+        self.config.scan_order = scan_order;
+        self
+    }
+
+    /// Turn soft failures into errors (`--strict`)
+    ///
+    /// Host discovery errors, failed scan types and privilege downgrades then
+    /// abort the scan instead of being logged and skipped.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// Apply `--min-rate`/`--max-rate` across every scanner of this run
+    ///
+    /// The maximum is enforced by one token bucket shared by all port
+    /// scanners; both values also bound the adaptive throttle.
+    pub fn with_rate_limits(mut self, min_rate: Option<u32>, max_rate: Option<u32>) -> Self {
+        self.config.min_rate = min_rate;
+        self.config.max_rate = max_rate;
+        self.install_throttle();
+        if let Some(max_rate) = max_rate {
+            let limiter = Arc::new(ScanRateLimiter::new(max_rate));
+            self.tcp_scanner = self.tcp_scanner.with_rate_limiter(limiter.clone());
+            self.syn_scanner = self.syn_scanner.with_rate_limiter(limiter.clone());
+            self.udp_scanner = self.udp_scanner.with_rate_limiter(limiter);
+        }
+        self
+    }
+
+    /// Send crafted probes as IP fragments of `size` payload bytes
+    pub fn with_fragmentation(mut self, size: usize) -> Self {
+        self.config.fragment_mtu = Some(size);
+        self
+    }
+
+    /// Subscribe to progress snapshots of the scan in flight
+    ///
+    /// Each `scan`/`scan_multiple` call restarts the counters, so with
+    /// concurrent calls on one scanner the numbers mix.
+    pub fn progress(&self) -> tokio::sync::watch::Receiver<ScanProgress> {
+        self.progress.subscribe()
+    }
+
+    /// Token that stops the scans of this scanner when cancelled
+    ///
+    /// Cancelling stops new hosts and probes from starting; probes already in
+    /// flight finish and `scan`/`scan_multiple` return what was collected.
+    /// A cancelled scanner stays cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Use `token` (e.g. a child of an application-wide token) for cancellation
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.tcp_scanner = self.tcp_scanner.with_cancellation(token.clone());
+        self.syn_scanner = self.syn_scanner.with_cancellation(token.clone());
+        self.udp_scanner = self.udp_scanner.with_cancellation(token.clone());
+        self.cancel = token;
+        self
+    }
+
+    /// Stop the scans in progress
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Whether the scan was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Sockets, packets, bytes, file descriptors and memory the last
+    /// finished scan run used
+    pub fn resource_usage(&self) -> Option<ResourceUsage> {
+        *self.resources.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record_resources(&self, meter: ResourceMeter) {
+        let usage = meter.finish();
+        info!("Resources used: {}", usage);
+        *self.resources.lock().unwrap_or_else(|e| e.into_inner()) = Some(usage);
+    }
+
+    /// Write the liveness a scan run learned to the discovery cache file
+    fn flush_discovery_cache(&self) {
+        if let Some(ref cache) = self.discovery_cache {
+            if let Err(e) = cache.flush() {
+                warn!("Failed to write discovery cache: {}", e);
+            }
+        }
+    }
+
+    /// Handle that pauses and resumes the scans of this scanner
+    ///
+    /// While paused, no new hosts or probes start and results gathered so far
+    /// are kept; probes in flight finish.
+    pub fn pause_control(&self) -> PauseControl {
+        self.pause.clone()
+    }
+
+    /// Stop issuing probes until [`resume`](Self::resume)
+    pub fn pause(&self) {
+        info!("Scan paused");
+        self.pause.pause();
+    }
+
+    /// Continue a paused scan
+    pub fn resume(&self) {
+        info!("Scan resumed");
+        self.pause.resume();
+    }
+
+    /// Port probes a host accounts for in progress totals
+    fn probes_per_target(ports: &[u16], scan_types: &[ScanType]) -> u64 {
+        ports.len() as u64 * scan_types.len() as u64
+    }
+
+    /// Port scan passes per run: two when port expansion is enabled
+    fn passes(&self) -> u8 {
+        if self.config.expand_ports { 2 } else { 1 }
+    }
+
+    /// Name the operator recorded in report metadata
+    pub fn with_operator(mut self, operator: Option<String>) -> Self {
+        self.operator = operator;
+        self
+    }
+
+    /// Use `scan_id` instead of a random ID to correlate log events
+    pub fn with_scan_id(mut self, scan_id: impl Into<String>) -> Self {
+        self.scan_id = scan_id.into();
+        self
+    }
+
+    /// ID every log event of this scanner's scans carries; use it as the
+    /// report's scan ID to match logs to reports
+    pub fn scan_id(&self) -> &str {
+        &self.scan_id
+    }
+
+    /// Span that scans run in, see [`crate::logging::scan_log`]
+    fn scan_span(&self) -> tracing::Span {
+        tracing::info_span!(crate::logging::scan_log::SCAN_SPAN, scan_id = self.scan_id.as_str())
+    }
+
+    /// Describe the scanning host for report metadata
+    ///
+    /// `route_target` (usually the first target) selects the source address
+    /// and interface recorded. Privileges are probed afresh, since the
+    /// scanner's own view may have the privilege check disabled.
+    pub fn scan_origin(&self, route_target: Option<IpAddr>) -> crate::report::ScanOrigin {
+        crate::report::ScanOrigin::detect(self.operator.as_deref(), route_target, &PrivilegeStatus::detect())
+    }
+
+    /// Back off exponentially between retries and re-probe filtered ports
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self.tcp_scanner = self.tcp_scanner.with_retry_policy(retry_policy);
+        self.syn_scanner = self.syn_scanner.with_retry_policy(retry_policy);
+        self.udp_scanner = self.udp_scanner.with_retry_policy(retry_policy);
+        self
+    }
+
+    /// Send probes with a chosen TTL and/or invalid checksums
+    pub fn with_evasion(mut self, evasion: ProbeEvasion) -> Self {
+        if evasion.is_active() {
+            info!("Probe evasion enabled: {}", evasion);
         }
+        self.config.evasion = evasion;
+        self.tcp_scanner = self.tcp_scanner.with_evasion(evasion);
+        self.syn_scanner = self.syn_scanner.with_evasion(evasion);
+        self.udp_scanner = self.udp_scanner.with_evasion(evasion);
+        self
+    }
+
+    /// Calibrate probe timeouts and retries per host from measured RTTs
+    pub fn with_adaptive_timeouts(mut self, config: crate::config::AdaptiveTimeoutConfig) -> Self {
+        info!(
+            "Adaptive timeouts enabled: {}-{}ms, {}-{} retries",
+            config.min_timeout_ms, config.max_timeout_ms, config.min_retries, config.max_retries
+        );
+        let timings = HostTimings::new(config.clone());
+        self.config.adaptive_timeout = crate::config::AdaptiveTimeoutConfig { enabled: true, ..config };
+        self.tcp_scanner = self.tcp_scanner.with_host_timings(timings.clone());
+        self.udp_scanner = self.udp_scanner.with_host_timings(timings.clone());
+        self.host_timings = Some(timings);
+        self
+    }
+
+    /// Per-host RTT estimates, when adaptive timeouts are enabled
+    pub fn host_timings(&self) -> Option<&HostTimings> {
+        self.host_timings.as_ref()
+    }
+
+    /// Override the configured per-probe timeout of every port scanner
+    pub fn with_probe_timeout(mut self, timeout_ms: u64) -> Self {
+        self.config.default_timeout_ms = timeout_ms;
+        self.config.tcp_connect.timeout_ms = timeout_ms;
+        self.config.tcp_syn.timeout_ms = timeout_ms;
+        self.config.udp.timeout_ms = timeout_ms;
+        self.tcp_scanner = self.tcp_scanner.with_timeout_ms(timeout_ms);
+        self.syn_scanner = self.syn_scanner.with_timeout_ms(timeout_ms);
+        self.udp_scanner = self.udp_scanner.with_timeout_ms(timeout_ms);
+        self
+    }
+
+    /// Limit how many ports are probed at once per host
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.config.max_concurrent_scans = max_concurrent.max(1);
+        self.concurrency = std::sync::OnceLock::new();
+        self
+    }
+
+    /// Limit how many targets `scan_multiple` scans at once
+    pub fn with_max_concurrent_targets(mut self, max_concurrent: usize) -> Self {
+        self.config.max_concurrent_targets = max_concurrent.max(1);
+        self.concurrency = std::sync::OnceLock::new();
+        self
+    }
+
+    /// Clamp concurrency to the open file limit, only warn about it, or
+    /// skip the check
+    pub fn with_ulimit_check(mut self, check: UlimitCheck) -> Self {
+        self.config.ulimit_check = check;
+        self.concurrency = std::sync::OnceLock::new();
+        self
+    }
+
+    /// Targets and ports per target scanned at once, lowered to fit the
+    /// open file limit unless `ulimit_check` says otherwise
+    pub fn concurrency(&self) -> Concurrency {
+        *self.concurrency.get_or_init(|| {
+            fd_limit::fit(
+                Concurrency::from_config(&self.config),
+                self.config.max_concurrent_probes_total,
+                self.config.ulimit_check,
+            )
+        })
+    }
+
+    /// Keep only open port results, counting the rest per host
+    pub fn with_open_ports_only(mut self, open_only: bool) -> Self {
+        self.config.open_ports_only = open_only;
+        self
+    }
+
+    /// Follow the requested ports with a pass over the rest of 1-65535 on
+    /// hosts found up or with an open port
+    pub fn with_port_expansion(mut self, enabled: bool) -> Self {
+        self.config.expand_ports = enabled;
+        self
+    }
+
+    /// Record why each port got its state, for `--reason`
+    pub fn with_port_reasons(mut self, enabled: bool) -> Self {
+        self.config.port_reasons = enabled;
+        self
+    }
+
+    /// Send SYN probes once each with SYN cookie sequence numbers, keeping
+    /// no per-probe state
+    pub fn with_stateless_syn(mut self, stateless: bool) -> Self {
+        self.config.tcp_syn.stateless = stateless;
+        self.syn_scanner = self.syn_scanner.with_stateless(stateless);
+        self
+    }
+
+    /// Cap port probes in flight across all targets of this run
+    ///
+    /// One semaphore is shared by every port scanner, so targets scanned at
+    /// once split the budget instead of each probing `max_concurrent_scans`
+    /// ports; 0 leaves only the per-target limit.
+    pub fn with_max_concurrent_probes_total(mut self, total: usize) -> Self {
+        self.config.max_concurrent_probes_total = total;
+        self.concurrency = std::sync::OnceLock::new();
+        let permits = (total > 0).then(|| {
+            info!("Limiting probes in flight to {} across all targets", total);
+            Arc::new(Semaphore::new(total))
+        });
+        self.tcp_scanner = self.tcp_scanner.with_probe_permits(permits.clone());
+        self.syn_scanner = self.syn_scanner.with_probe_permits(permits.clone());
+        self.udp_scanner = self.udp_scanner.with_probe_permits(permits);
+        self
+    }
+
+    /// Read banners from open TCP ports during connect scans
+    pub fn with_banner_grabbing(mut self, enabled: bool) -> Self {
+        self.tcp_scanner = self.tcp_scanner.with_banner_grabbing(enabled);
+        self
+    }
+
+    /// Confirm open ports with the other TCP scan type
+    pub fn with_port_verification(mut self, enabled: bool) -> Self {
+        self.config.verify_open_ports = enabled;
+        self
+    }
+
+    /// Complete a protocol exchange on open HTTP, SMTP, FTP and RDP ports
+    /// and record whether the service answered
+    pub fn with_deep_check(mut self, enabled: bool) -> Self {
+        self.config.deep_check.enabled = enabled;
+        self
+    }
+
+    /// Take up the hot-reloadable scanner and throttling settings of a
+    /// reloaded configuration; they apply from the next scan
+    pub fn apply_reloaded(&mut self, config: &AppConfig) {
+        self.config.verify_open_ports = config.scanner.verify_open_ports;
+        self.config.deep_check.enabled = config.scanner.deep_check.enabled;
+        if config.throttling != self.throttling {
+            self.throttling = config.throttling.clone();
+            self.install_throttle();
+        }
+    }
+
+    /// Report hosts the cache saw down recently as down without probing
+    /// them, and record the outcome of every scan in it
+    pub fn with_discovery_cache(mut self, cache: Arc<DiscoveryCache>) -> Self {
+        self.discovery_cache = Some(cache);
+        self
+    }
+
+    /// Packet engine for crafted probes, honouring the fragmentation and evasion settings
+    pub fn packet_engine(&self) -> PacketEngine {
+        PacketEngine::new(PacketEngineConfig {
+            enable_raw_sockets: self.privileges.raw_sockets,
+            fragment_size: self.config.fragment_mtu,
+            default_ttl: self.config.evasion.ttl.unwrap_or(64),
+            bad_checksum: self.config.evasion.bad_checksum,
+            ..Default::default()
+        })
+    }
+
+    /// Fingerprint the OS of scanned hosts that have an open port
+    pub fn with_os_detection(mut self, queue: OsDetectionQueue) -> Self {
+        self.os_detection = Some(queue);
+        self
+    }
+
+    /// Collect hostnames from certificates on open TLS ports
+    pub fn with_tls_hostnames(mut self, collector: TlsHostnameCollector) -> Self {
+        self.tls_hostnames = Some(collector);
+        self
+    }
+
+    /// Queue of virtual hosts discovered during scans, when enabled
+    pub fn vhost_queue(&self) -> Option<&VhostQueue> {
+        self.tls_hostnames.as_ref().and_then(|c| c.vhost_queue())
+    }
+
+    /// Record each scan's traffic to a pcapng file derived from `path`
+    ///
+    /// Every call to [`Scanner::scan`] writes its own file, named after the
+    /// target and start time (see [`pcap::rotated_path`]).
+    pub fn with_pcap_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pcap_output = Some(path.into());
+        self
+    }
+
+    /// Register a custom host discovery probe (see [`DiscoveryProbe`])
+    pub fn with_discovery_probe(mut self, probe: Arc<dyn DiscoveryProbe>) -> Self {
+        self.host_discovery = self.host_discovery.with_probe(probe);
+        self
+    }
+
+    /// Attach a GeoIP/ASN enricher used to annotate scan results
+    pub fn with_geoip(mut self, enricher: Arc<GeoIpEnricher>) -> Self {
+        self.geoip = Some(enricher);
+        self
+    }
+
+    /// Enable or disable privilege enforcement (`security.require_privileges_check`)
+    ///
+    /// When disabled, every scan type is attempted as requested and failures
+    /// surface from the individual scanners instead.
+    pub fn with_privilege_check(mut self, enabled: bool) -> Self {
+        if !enabled {
+            self.privileges = PrivilegeStatus::assume_privileged();
+            self.host_discovery = self.host_discovery.with_privileges(self.privileges);
+        }
+        self
+    }
+
+    /// Scanner configuration
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+
+    /// Privilege status detected at startup
+    pub fn privileges(&self) -> PrivilegeStatus {
+        self.privileges
+    }
+
+    /// Validate explicitly requested scan types against current privileges
+    ///
+    /// Returns an error early if a privileged scan type (e.g. SYN) was asked
+    /// for without raw socket access.
+    pub fn check_scan_types(&self, scan_types: &[ScanType]) -> crate::error::ScanResult<()> {
+        if self.tcp_scanner.is_tunneled() && scan_types.iter().any(|t| *t != ScanType::TcpConnect) {
+            return Err(ScanError::validation_error(
+                "scan_types",
+                "only TCP connect scans run through an SSH bastion",
+            ));
+        }
+        self.privileges.resolve_scan_types(scan_types, true).map(|_| ())
+    }
+
+    /// Resolve PTR names for scanned targets
+    pub fn with_reverse_dns(mut self, resolver: ReverseDnsResolver) -> Self {
+        self.rdns = Some(resolver);
+        self
+    }
+
+    /// Skip hosts whose PTR name matches an exclusion rule
+    ///
+    /// Exclusions are evaluated after reverse DNS, so a resolver must also be
+    /// attached with [`Scanner::with_reverse_dns`].
+    pub fn with_hostname_exclusion(mut self, exclusion: HostnameExclusion) -> Self {
+        self.hostname_exclusion = exclusion;
+        self
+    }
+
+    /// Refuse targets outside these address blocks (`security.scope`)
+    pub fn with_authorized_scope(mut self, scope: AuthorizedScope) -> Self {
+        self.authorized_scope = scope;
+        self
+    }
+
+    /// Scan targets outside the authorized scope anyway, with an audit entry
+    pub fn with_scope_override(mut self, overridden: bool) -> Self {
+        self.authorized_scope = self.authorized_scope.with_override(overridden);
+        self
+    }
+
+    /// Refuse scans, or hold hosts, during these windows (`security.blackouts`)
+    pub fn with_blackouts(mut self, blackouts: BlackoutCalendar) -> Self {
+        self.blackouts = blackouts;
+        self
+    }
+
+    /// Apply the installation's `[security]` settings: the privilege check,
+    /// the authorized scope and the blackout windows
+    pub fn with_security(self, security: &crate::config::SecurityConfig) -> Self {
+        self.with_privilege_check(security.require_privileges_check)
+            .with_authorized_scope(AuthorizedScope::new(security.scope.clone()))
+            .with_blackouts(BlackoutCalendar::new(security.blackouts.clone()))
+    }
+
+    /// Refuse `targets` outside the authorized scope or inside a blackout
+    /// window; every scan and discovery entry point checks here before
+    /// sending a probe
+    fn authorize(&self, targets: &[IpAddr]) -> crate::error::ScanResult<()> {
+        self.authorized_scope.check(targets, self.operator.as_deref())?;
+        self.blackouts.check(targets, chrono::Utc::now(), self.operator.as_deref())
     }
 
     /// Perform a comprehensive scan on a target
@@ -97,39 +923,234 @@ impl Scanner {
         ports: Vec<u16>,
         scan_types: Vec<ScanType>,
     ) -> crate::error::ScanResult<CompleteScanResult> {
+        let meter = ResourceMeter::start();
+        let result = self.scan_one(target, ports, scan_types).instrument(self.scan_span()).await;
+        self.record_resources(meter);
+        self.flush_discovery_cache();
+        result
+    }
+
+    async fn scan_one(
+        &self,
+        target: IpAddr,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+    ) -> crate::error::ScanResult<CompleteScanResult> {
+        self.authorize(&[target])?;
+        let probes = Self::probes_per_target(&ports, &scan_types);
+        self.progress.begin_pass(1, self.passes(), 1, probes);
+        let warmup = self.warm_up(&[target]).await;
+        let result = self
+            .scan_host(target, ports.clone(), scan_types.clone(), &warmup)
+            .instrument(info_span!("host", target = %target))
+            .await;
+        self.progress.host_done(target, probes);
+        let mut result = result?;
+        if self.is_cancelled() {
+            return Ok(result);
+        }
+        if self.config.expand_ports && port_expansion::qualifies(&result) {
+            let remaining = port_expansion::remaining_ports(&ports);
+            let probes = Self::probes_per_target(&remaining, &scan_types);
+            self.progress.begin_pass(2, 2, 1, probes);
+            let expanded = self
+                .expand_host(result, remaining, scan_types)
+                .instrument(info_span!("host", target = %target))
+                .await;
+            self.progress.host_done(target, probes);
+            result = expanded?;
+            if self.is_cancelled() {
+                return Ok(result);
+            }
+        }
+        if let Some(job) = self.submit_os_detection(&result) {
+            let (matches, elapsed) = job.wait_timed().await;
+            result.record_os_matches(matches, elapsed);
+        }
+        if self.config.open_ports_only {
+            result.drop_unopened_ports();
+        }
+        Ok(result)
+    }
+
+    /// Resolve ARP for on-link targets when the warm-up phase is enabled
+    async fn warm_up(&self, targets: &[IpAddr]) -> ArpWarmupReport {
+        let Some(ref warmup) = self.arp_warmup else {
+            return ArpWarmupReport::default();
+        };
+        warmup.run(targets).await.unwrap_or_else(|e| {
+            warn!("ARP warm-up failed: {}", e);
+            ArpWarmupReport::default()
+        })
+    }
+
+    /// Discovery, enrichment and port scans for one host, without OS detection
+    async fn scan_host(
+        &self,
+        target: IpAddr,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+        warmup: &ArpWarmupReport,
+    ) -> crate::error::ScanResult<CompleteScanResult> {
+        self.scan_host_pass(target, ports, scan_types, warmup, None).await
+    }
+
+    /// Port scan the `remaining` ports of 1-65535 on a host the first pass
+    /// found up or open, and merge them into its `result`
+    async fn expand_host(
+        &self,
+        mut result: CompleteScanResult,
+        remaining: Vec<u16>,
+        scan_types: Vec<ScanType>,
+    ) -> crate::error::ScanResult<CompleteScanResult> {
+        info!("Expanding {} to {} more ports", result.target, remaining.len());
+        let initial_ports = usize::from(u16::MAX) - remaining.len();
+        let expansion = self
+            .scan_host_pass(result.target, remaining, scan_types, &ArpWarmupReport::default(), Some(&result))
+            .await?;
+        port_expansion::merge(&mut result, expansion, initial_ports);
+        Ok(result)
+    }
+
+    /// One port scan pass over a host; discovery and enrichment are taken
+    /// from the `earlier` pass when there is one
+    async fn scan_host_pass(
+        &self,
+        target: IpAddr,
+        mut ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+        warmup: &ArpWarmupReport,
+        earlier: Option<&CompleteScanResult>,
+    ) -> crate::error::ScanResult<CompleteScanResult> {
+        // Hosts do not start discovery while the scan is paused
+        self.pause.wait_resumed(&self.cancel).await;
+        // ... nor while a blackout window covers them
+        self.blackouts.wait_clear(target, &self.cancel).await;
         let start = std::time::Instant::now();
         info!("Starting scan on {} for {} ports", target, ports.len());
+        // Each port keeps its identity through retries; only the send order changes
+        self.config.scan_order.shuffle_ports(target, &mut ports);
 
-        // Step 1: Host discovery
-        let host_status = match self.host_discovery.discover(target).await {
-            Ok(result) => {
-                if result.status == HostStatus::Down {
-                    warn!("Host {} appears to be down, continuing with scan anyway", target);
+        // An on-link host that never answered ARP cannot answer probes either
+        if warmup.is_unresolved(target) {
+            info!("Host {} did not answer ARP, marking down", target);
+            return Ok(CompleteScanResult {
+                down_reason: Some("no ARP reply".to_string()),
+                ..CompleteScanResult::unscanned(target, HostStatus::Down, start.elapsed())
+            });
+        }
+
+        // Hosts down on a recent scan stay down until the cache entry expires
+        if let Some(age) = self.discovery_cache.as_ref().and_then(|cache| cache.down_since(target)) {
+            info!("Host {} was down {}s ago, skipping (discovery cache)", target, age.as_secs());
+            return Ok(CompleteScanResult {
+                down_reason: Some(format!("down {} minutes ago (discovery cache)", age.as_secs() / 60)),
+                ..CompleteScanResult::unscanned(target, HostStatus::Down, start.elapsed())
+            });
+        }
+
+        // Downgrade scan types that need privileges we do not have
+        let mut scan_types = self.privileges.resolve_scan_types(&scan_types, self.config.strict)?;
+        if self.tcp_scanner.is_tunneled() {
+            self.check_scan_types(&scan_types).or_else(|e| match self.config.strict {
+                true => Err(e),
+                false => {
+                    warn!("{}; skipping the others for {}", e, target);
+                    Ok(())
                 }
-                result.status
-            }
-            Err(e) => {
-                warn!("Host discovery failed: {}, assuming host is up", e);
-                HostStatus::Unknown
+            })?;
+            scan_types.retain(|t| *t == ScanType::TcpConnect);
+        }
+        let mut traffic_capture = self.start_traffic_capture(target);
+
+        let mut phases = PhaseTimings::default();
+
+        let (host_status, hostname, geo) = match earlier {
+            Some(earlier) => (earlier.host_status.clone(), earlier.hostname.clone(), earlier.geo.clone()),
+            None => {
+                // Step 1: Host discovery
+                let phase_start = std::time::Instant::now();
+                let discovery = match self.tcp_scanner.is_tunneled() {
+                    // Local probes cannot see hosts behind the bastion
+                    true => Ok(DiscoveryResult {
+                        target,
+                        status: HostStatus::Unknown,
+                        response_time_ms: None,
+                        method: "ssh-tunnel".to_string(),
+                        votes: Vec::new(),
+                    }),
+                    false => self.host_discovery.discover(target).await,
+                };
+                let host_status = match discovery {
+                    Ok(result) => {
+                        // The discovery reply is the first calibration sample
+                        if let (Some(timings), Some(rtt)) = (&self.host_timings, result.response_time_ms) {
+                            timings.observe(target, rtt);
+                        }
+                        if result.status == HostStatus::Down {
+                            warn!("Host {} appears to be down, continuing with scan anyway", target);
+                        }
+                        result.status
+                    }
+                    Err(e) if self.config.strict => {
+                        return Err(ScanError::scanner_error(format!("Host discovery failed for {}: {}", target, e)));
+                    }
+                    Err(e) => {
+                        warn!("Host discovery failed: {}, assuming host is up", e);
+                        HostStatus::Unknown
+                    }
+                };
+
+                // Step 2: Reverse DNS enrichment and hostname exclusion
+                let hostname = match self.rdns {
+                    Some(ref resolver) => resolver.resolve(target).await,
+                    None => None,
+                };
+                let geo = self.geoip.as_ref().and_then(|geoip| geoip.lookup(target));
+                phases.discovery_ms = phase_timing::elapsed_ms(phase_start);
+
+                if let Some(rule) = hostname
+                    .as_deref()
+                    .and_then(|name| self.hostname_exclusion.matching_rule(name))
+                {
+                    info!(
+                        "Skipping {} ({}): matches exclusion rule {}",
+                        target,
+                        hostname.as_deref().unwrap_or_default(),
+                        rule
+                    );
+                    return Ok(CompleteScanResult {
+                        geo,
+                        excluded_by: Some(rule.to_string()),
+                        hostname,
+                        ..CompleteScanResult::unscanned(target, host_status, start.elapsed())
+                    });
+                }
+                (host_status, hostname, geo)
             }
         };
 
-        // Step 2: Port scanning based on requested scan types
+        // Step 3: Port scanning based on requested scan types
         let mut tcp_results = Vec::new();
         let mut syn_results = Vec::new();
         let mut udp_results = Vec::new();
+        let mut icmp_rate_limit = None;
 
         for scan_type in scan_types {
+            let phase_start = std::time::Instant::now();
             match scan_type {
                 ScanType::TcpConnect => {
                     info!("Performing TCP connect scan");
                     match self.tcp_scanner.scan_ports(
                         target,
                         ports.clone(),
-                        self.config.max_concurrent_scans,
+                        self.concurrency().ports,
                     ).await {
-                        Ok(results) => tcp_results = results,
-                        Err(e) => warn!("TCP connect scan failed: {}", e),
+                        Ok(results) => {
+                            self.check_coverage("TCP connect scan", target, ports.len(), probed(&results, |r| r.attempts))?;
+                            tcp_results = results;
+                        }
+                        Err(e) => self.soft_failure("TCP connect scan", target, e)?,
                     }
                 }
                 ScanType::TcpSyn => {
@@ -137,10 +1158,13 @@ impl Scanner {
                     match self.syn_scanner.scan_ports(
                         target,
                         ports.clone(),
-                        self.config.max_concurrent_scans,
+                        self.concurrency().ports,
                     ).await {
-                        Ok(results) => syn_results = results,
-                        Err(e) => warn!("TCP SYN scan failed: {}", e),
+                        Ok(results) => {
+                            self.check_coverage("TCP SYN scan", target, ports.len(), probed(&results, |r| r.attempts))?;
+                            syn_results = results;
+                        }
+                        Err(e) => self.soft_failure("TCP SYN scan", target, e)?,
                     }
                 }
                 ScanType::Udp => {
@@ -148,13 +1172,57 @@ impl Scanner {
                     match self.udp_scanner.scan_ports(
                         target,
                         ports.clone(),
-                        self.config.max_concurrent_scans,
+                        self.concurrency().ports,
                     ).await {
-                        Ok(results) => udp_results = results,
-                        Err(e) => warn!("UDP scan failed: {}", e),
+                        Ok(results) => {
+                            self.check_coverage("UDP scan", target, ports.len(), probed(&results, |r| r.attempts))?;
+                            udp_results = results;
+                            icmp_rate_limit = self.udp_scanner.take_icmp_rate_limit(target);
+                        }
+                        Err(e) => self.soft_failure("UDP scan", target, e)?,
                     }
                 }
             }
+            match scan_type {
+                ScanType::Udp => phases.udp_ms += phase_timing::elapsed_ms(phase_start),
+                ScanType::TcpConnect | ScanType::TcpSyn => phases.tcp_ms += phase_timing::elapsed_ms(phase_start),
+            }
+        }
+
+        // Step 3b: Confirm open ports with the other TCP scan type
+        // (follow-up probes are skipped once the scan is cancelled)
+        let cancelled = self.is_cancelled();
+        let phase_start = std::time::Instant::now();
+        let port_verifications = if self.config.verify_open_ports && !cancelled {
+            self.verify_open_ports(target, &tcp_results, &syn_results).await
+        } else {
+            Vec::new()
+        };
+
+        // Step 4: Hostnames from certificates on open TLS ports
+        let tls = match self.tls_hostnames {
+            Some(ref collector) if !cancelled => {
+                let open_ports: Vec<u16> = tcp_results
+                    .iter()
+                    .filter(|r| r.status == PortStatus::Open)
+                    .map(|r| r.port)
+                    .chain(syn_results.iter().filter(|r| r.status == PortStatus::Open).map(|r| r.port))
+                    .collect();
+                collector.collect(target, &open_ports).await
+            }
+            _ => TlsEvidence::default(),
+        };
+
+        // Step 4b: Protocol liveness of open ports
+        let liveness = if self.config.deep_check.enabled && !cancelled {
+            deep_check::check_open_ports(target, &tcp_results, &syn_results, &self.config.deep_check).await
+        } else {
+            Vec::new()
+        };
+        phases.detection_ms = phase_timing::elapsed_ms(phase_start);
+
+        if let Some(ref mut capture) = traffic_capture {
+            capture.stop();
         }
 
         let elapsed = start.elapsed();
@@ -170,7 +1238,7 @@ impl Scanner {
             elapsed.as_millis()
         );
 
-        Ok(CompleteScanResult {
+        let mut result = CompleteScanResult {
             target,
             host_status,
             tcp_results,
@@ -178,7 +1246,170 @@ impl Scanner {
             udp_results,
             scan_duration_ms: elapsed.as_millis() as u64,
             throttle_stats,
-        })
+            geo,
+            hostname,
+            excluded_by: None,
+            discovered_hostnames: tls.hostnames,
+            tls_certificates: tls.certificates,
+            os_matches: Vec::new(),
+            down_reason: None,
+            port_verifications,
+            liveness,
+            deception: None,
+            latency: None,
+            phase_timings: Some(phases),
+            dropped_ports: None,
+            expansion: None,
+            mac: warmup.host_mac(target),
+            icmp_rate_limit,
+        };
+        result.assess_confidence();
+        if self.config.port_reasons {
+            result.record_reasons();
+        }
+        result.deception = DeceptionAssessment::assess(&result);
+        result.latency = LatencyStats::from_samples(&result.rtt_samples());
+        if let Some(ref deception) = result.deception {
+            if deception.is_suspected() {
+                warn!("{} looks like a honeypot or decoy: {}", target, deception);
+            }
+        }
+        if let (Some(cache), None) = (&self.discovery_cache, earlier) {
+            cache.record(&result);
+        }
+        Ok(result)
+    }
+
+    /// Log a failure the scan can continue past, or fail in strict mode
+    fn soft_failure(&self, stage: &str, target: IpAddr, error: ScanError) -> crate::error::ScanResult<()> {
+        if self.config.strict {
+            return Err(ScanError::scanner_error(format!("{} failed for {}: {}", stage, target, error)));
+        }
+        warn!("{} failed: {}", stage, error);
+        Ok(())
+    }
+
+    /// In strict mode, fail when some ports got no result
+    ///
+    /// The port scanners report a port whose probes all errored out with its
+    /// [`ProbeError`] and zero attempts; such ports count as missing. Ports
+    /// left out because the scan was cancelled do not count.
+    fn check_coverage(&self, stage: &str, target: IpAddr, requested: usize, scanned: usize) -> crate::error::ScanResult<()> {
+        if self.config.strict && scanned < requested && !self.is_cancelled() {
+            return Err(ScanError::scanner_error(format!(
+                "{} failed for {} of {} ports on {}",
+                stage,
+                requested - scanned,
+                requested,
+                target
+            )));
+        }
+        Ok(())
+    }
+
+    /// Re-check open ports with the other TCP scan type
+    ///
+    /// Ports both scan types probed are compared directly; the rest are
+    /// probed again. SYN re-checks need raw sockets and are skipped otherwise.
+    async fn verify_open_ports(
+        &self,
+        target: IpAddr,
+        tcp_results: &[TcpConnectResult],
+        syn_results: &[TcpSynResult],
+    ) -> Vec<PortVerification> {
+        let mut checks = verification::cross_check(tcp_results, syn_results);
+        let tcp_ports: Vec<u16> = tcp_results.iter().map(|r| r.port).collect();
+        let syn_ports: Vec<u16> = syn_results.iter().map(|r| r.port).collect();
+
+        let syn_open = verification::unverified_open_ports(syn_results.iter().map(|r| (r.port, &r.status)), &tcp_ports);
+        for port in syn_open {
+            match self.tcp_scanner.scan_port(target, port).await {
+                Ok(result) => checks.push(PortVerification {
+                    port,
+                    reported_by: ScanType::TcpSyn,
+                    verified_with: ScanType::TcpConnect,
+                    verified_status: result.status,
+                }),
+                Err(e) => warn!("Could not verify {}:{} with TCP connect: {}", target, port, e),
+            }
+        }
+
+        let connect_open = verification::unverified_open_ports(tcp_results.iter().map(|r| (r.port, &r.status)), &syn_ports);
+        if !connect_open.is_empty() && !self.privileges.raw_sockets {
+            info!(
+                "SYN verification needs raw sockets; {} open ports on {} left unverified",
+                connect_open.len(),
+                target
+            );
+        } else {
+            for port in connect_open {
+                match self.syn_scanner.scan_port(target, port).await {
+                    Ok(result) => checks.push(PortVerification {
+                        port,
+                        reported_by: ScanType::TcpConnect,
+                        verified_with: ScanType::TcpSyn,
+                        verified_status: result.status,
+                    }),
+                    Err(e) => warn!("Could not verify {}:{} with TCP SYN: {}", target, port, e),
+                }
+            }
+        }
+
+        for check in checks.iter().filter(|c| !c.confirmed()) {
+            warn!("Verification disagreement on {}: {}", target, check);
+        }
+        checks
+    }
+
+    /// Queue OS fingerprinting for a scanned host
+    ///
+    /// Fingerprinting needs an open TCP port; a closed one is used for the
+    /// closed-port probes when available.
+    fn submit_os_detection(&self, result: &CompleteScanResult) -> Option<OsDetectionJob> {
+        let queue = self.os_detection.as_ref()?;
+        let first_port = |status: PortStatus| {
+            result
+                .tcp_results
+                .iter()
+                .map(|r| (r.port, &r.status))
+                .chain(result.syn_results.iter().map(|r| (r.port, &r.status)))
+                .find(|(_, s)| **s == status)
+                .map(|(port, _)| port)
+        };
+
+        let Some(open_port) = first_port(PortStatus::Open) else {
+            info!("No open TCP port on {}, skipping OS detection", result.target);
+            return None;
+        };
+        let closed_port = first_port(PortStatus::Closed);
+        Some(queue.submit(result.target, open_port, closed_port))
+    }
+
+    /// Start recording traffic for one scan when pcap output is enabled
+    fn start_traffic_capture(&self, target: IpAddr) -> Option<PacketCapture> {
+        let base = self.pcap_output.as_ref()?;
+        if !self.privileges.raw_sockets {
+            warn!("Recording scan traffic requires raw socket privileges; pcap output disabled");
+            return None;
+        }
+
+        let writer = match PcapWriter::create_shared(pcap::rotated_path(base, &target.to_string())) {
+            Ok(writer) => writer,
+            Err(e) => {
+                warn!("Cannot record traffic for {}: {}", target, e);
+                return None;
+            }
+        };
+
+        let mut capture = PacketCapture::new(CaptureConfig::default(), CaptureFilter::new([target]))
+            .with_pcap_writer(writer);
+        match capture.start() {
+            Ok(()) => Some(capture),
+            Err(e) => {
+                warn!("Cannot record traffic for {}: {}", target, e);
+                None
+            }
+        }
     }
 
     /// Scan multiple targets
@@ -196,40 +1427,245 @@ impl Scanner {
         ports: Vec<u16>,
         scan_types: Vec<ScanType>,
     ) -> crate::error::ScanResult<Vec<CompleteScanResult>> {
-        use futures::stream::{self, StreamExt};
+        let (sender, results) = std::sync::mpsc::channel();
+        self.scan_multiple_with(targets, ports, scan_types, move |result| {
+            let _ = sender.send(result);
+            Ok(())
+        })
+        .await?;
+        Ok(results.try_iter().collect())
+    }
+
+    /// Scan multiple targets, handing each host's result to `on_result` as
+    /// soon as it is complete
+    ///
+    /// Nothing is accumulated, so callers can spill results to disk or
+    /// stream them out and keep memory bounded on huge target sets.
+    /// `on_result` runs on the blocking thread pool, so a slow sink delays
+    /// the scan only once `result_buffer` hosts wait for it. An error from
+    /// `on_result` stops the scan. Returns the number of results handed over.
+    pub async fn scan_multiple_with<F>(
+        &self,
+        targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+        on_result: F,
+    ) -> crate::error::ScanResult<usize>
+    where
+        F: FnMut(CompleteScanResult) -> crate::error::ScanResult<()> + Send + 'static,
+    {
+        let meter = ResourceMeter::start();
+        let delivered = self
+            .scan_many(targets, ports, scan_types, on_result)
+            .instrument(self.scan_span())
+            .await;
+        self.record_resources(meter);
+        self.flush_discovery_cache();
+        delivered
+    }
 
+    async fn scan_many<F>(
+        &self,
+        mut targets: Vec<IpAddr>,
+        ports: Vec<u16>,
+        scan_types: Vec<ScanType>,
+        mut on_result: F,
+    ) -> crate::error::ScanResult<usize>
+    where
+        F: FnMut(CompleteScanResult) -> crate::error::ScanResult<()> + Send + 'static,
+    {
+        self.authorize(&targets)?;
         info!(
             "Starting scan on {} targets, {} ports per target",
             targets.len(),
             ports.len()
         );
+        self.config.scan_order.shuffle_targets(&mut targets);
+
+        let probes = Self::probes_per_target(&ports, &scan_types);
+        self.progress.begin_pass(1, self.passes(), targets.len(), probes);
+
+        let warmup = self.warm_up(&targets).await;
+
+        // Results are handed over on the blocking pool, each holding one of
+        // the `result_buffer` slots from the end of its port scan until
+        // `on_result` returns
+        let slots = Arc::new(Semaphore::new(self.config.result_buffer.max(1)));
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(CompleteScanResult, OwnedSemaphorePermit)>();
+        let open_ports_only = self.config.open_ports_only;
+        let delivery = tokio::task::spawn_blocking(move || {
+            let mut delivered = 0;
+            while let Some((mut result, _slot)) = receiver.blocking_recv() {
+                if open_ports_only {
+                    result.drop_unopened_ports();
+                }
+                on_result(result)?;
+                delivered += 1;
+            }
+            Ok::<_, ScanError>(delivered)
+        });
+        let deliver = Delivery { sender, slots };
+
+        // With port expansion, hosts found up or open wait for the second
+        // pass; the others are handed over straight away
+        let expand = |result: &CompleteScanResult| self.config.expand_ports && port_expansion::qualifies(result);
+        let hosts = targets.into_iter().map(|target| (target, None)).collect();
+        let scanned = async {
+            let deferred = self.scan_pass(hosts, &ports, &scan_types, &warmup, expand, &deliver).await?;
+            if !deferred.is_empty() && !deliver.sender.is_closed() {
+                let remaining = port_expansion::remaining_ports(&ports);
+                info!("Expanding {} hosts to {} more ports", deferred.len(), remaining.len());
+                let probes = Self::probes_per_target(&remaining, &scan_types);
+                self.progress.begin_pass(2, 2, deferred.len(), probes);
+                let hosts = deferred.into_iter().map(|result| (result.target, Some(result))).collect();
+                let no_warmup = ArpWarmupReport::default();
+                self.scan_pass(hosts, &remaining, &scan_types, &no_warmup, |_| false, &deliver).await?;
+            }
+            Ok::<_, ScanError>(())
+        }
+        .await;
+
+        // An error from `on_result` is why scanning stopped, so it wins
+        drop(deliver);
+        let delivered = delivery
+            .await
+            .map_err(|e| ScanError::scanner_error(format!("Result delivery failed: {}", e)))??;
+        scanned?;
+
+        if self.is_cancelled() {
+            warn!("Scan cancelled, returning results for {} targets", delivered);
+        } else {
+            info!("Completed scans on {} targets", delivered);
+        }
+
+        Ok(delivered)
+    }
 
-        let ports_clone = ports.clone();
-        let scan_types_clone = scan_types.clone();
+    /// Scan `hosts` over `ports`, handing results to `deliver` as they
+    /// finish and returning those `defer` holds back (before OS detection)
+    ///
+    /// The pass is a pipeline: each finished host takes a `result_buffer`
+    /// slot and moves on to the OS detection stage, which runs concurrently
+    /// and hands it to the delivery thread, so a long fingerprint or a slow
+    /// report sink overlaps the remaining probes rather than following them.
+    /// When every slot is taken, scanning waits for the consumer. Stops
+    /// early once the consumer has failed.
+    ///
+    /// A host paired with an earlier result gets the expansion pass merged
+    /// into it; once the scan is cancelled such hosts keep the earlier result.
+    async fn scan_pass(
+        &self,
+        hosts: Vec<(IpAddr, Option<CompleteScanResult>)>,
+        ports: &[u16],
+        scan_types: &[ScanType],
+        warmup: &ArpWarmupReport,
+        defer: impl Fn(&CompleteScanResult) -> bool,
+        deliver: &Delivery,
+    ) -> crate::error::ScanResult<Vec<CompleteScanResult>> {
+        use futures::stream::{self, StreamExt};
 
-        let results = stream::iter(targets)
-            .map(|target| {
-                let ports_ref = ports_clone.clone();
-                let scan_types_ref = scan_types_clone.clone();
+        let probes = Self::probes_per_target(ports, scan_types);
+        let defer = &defer;
+
+        // OS detection is queued as each host's port scan finishes and runs
+        // alongside the remaining port scans. Cancellation stops new hosts
+        // from starting; hosts in flight return their partial results.
+        let scanned = stream::iter(hosts)
+            .take_while(|(_, earlier)| std::future::ready(earlier.is_some() || !self.is_cancelled()))
+            .map(|(target, earlier)| {
+                let ports_ref = ports.to_vec();
+                let scan_types_ref = scan_types.to_vec();
                 async move {
-                    match self.scan(target, ports_ref, scan_types_ref).await {
-                        Ok(result) => Some(result),
+                    let result = match earlier {
+                        Some(earlier) if self.is_cancelled() => Ok(earlier),
+                        Some(earlier) => self.expand_host(earlier, ports_ref, scan_types_ref).await,
+                        None => self.scan_host(target, ports_ref, scan_types_ref, warmup).await,
+                    };
+                    self.progress.host_done(target, probes);
+                    match result {
+                        Ok(result) if defer(&result) => Some(Ok((result, None, true))),
+                        Ok(result) => {
+                            let job = (!self.is_cancelled()).then(|| self.submit_os_detection(&result)).flatten();
+                            Some(Ok((result, job, false)))
+                        }
+                        // Strict mode fails the whole run on the first host error
+                        Err(e) if self.config.strict => Some(Err(e)),
                         Err(e) => {
                             warn!("Scan failed for {}: {}", target, e);
                             None
                         }
                     }
                 }
+                .instrument(info_span!("host", target = %target))
             })
-            .buffer_unordered(10) // Limit concurrent target scans
-            .collect::<Vec<_>>()
-            .await;
+            .buffer_unordered(self.concurrency().targets);
 
-        let results: Vec<CompleteScanResult> = results.into_iter().flatten().collect();
-        
-        info!("Completed scans on {} targets", results.len());
+        // The slots bound everything between here and `on_result`, so the
+        // queue to the detection stage needs no bound of its own
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(CompleteScanResult, Option<OsDetectionJob>, OwnedSemaphorePermit)>();
+
+        // Scanning stage; hosts held for the expansion pass stay here
+        let scanning = async move {
+            let mut scanned = std::pin::pin!(scanned);
+            let mut deferred = Vec::new();
+            while let Some(scanned) = scanned.next().await {
+                // Strict mode fails the whole run on the first host error
+                let Some((result, job, held)) = scanned.transpose()? else {
+                    continue;
+                };
+                if held {
+                    deferred.push(result);
+                    continue;
+                }
+                let Ok(slot) = Arc::clone(&deliver.slots).acquire_owned().await else {
+                    break;
+                };
+                if deliver.sender.is_closed() || sender.send((result, job, slot)).is_err() {
+                    break;
+                }
+            }
+            Ok::<_, ScanError>(deferred)
+        };
+
+        // Detection stage: hosts are handed over in the order their
+        // fingerprints finish
+        let detecting = async move {
+            let received = stream::poll_fn(|cx| receiver.poll_recv(cx));
+            let finished = received
+                .map(|(mut result, job, slot): (CompleteScanResult, Option<OsDetectionJob>, OwnedSemaphorePermit)| async move {
+                    if let Some(job) = job {
+                        let (matches, elapsed) = job.wait_timed().await;
+                        result.record_os_matches(matches, elapsed);
+                    }
+                    (result, slot)
+                })
+                .buffer_unordered(self.config.result_buffer.max(1));
+            let mut finished = std::pin::pin!(finished);
+            while let Some(finished) = finished.next().await {
+                // A closed channel means `on_result` failed; the scanning
+                // stage stops on its own
+                let _ = deliver.sender.send(finished);
+            }
+        };
 
-        Ok(results)
+        let (deferred, ()) = futures::future::join(scanning, detecting).await;
+        deferred
+    }
+
+    /// Discover live hosts in an IPv6 prefix
+    ///
+    /// Requires `host_discovery.ipv6.enabled` in the configuration. The
+    /// prefix and every candidate found in it must be in scope.
+    pub async fn discover_ipv6(
+        &self,
+        prefix: &Ipv6Prefix,
+    ) -> crate::error::ScanResult<Vec<DiscoveryResult>> {
+        self.authorize(&[IpAddr::V6(prefix.network)])?;
+        let candidates = self.host_discovery.ipv6_candidates(prefix).await?;
+        self.authorize(&candidates)?;
+        self.host_discovery
+            .discover_many(candidates, self.concurrency().ports)
+            .await
     }
 
     /// Get current throttle statistics (if throttling is enabled)
@@ -242,11 +1678,158 @@ impl Scanner {
     }
 }
 
+impl CompleteScanResult {
+    /// Attach OS detection matches and the time fingerprinting took
+    fn record_os_matches(&mut self, matches: Vec<OsMatchResult>, elapsed: std::time::Duration) {
+        self.os_matches = matches;
+        if let Some(ref mut phases) = self.phase_timings {
+            phases.os_ms = elapsed.as_millis() as u64;
+        }
+    }
+
+    /// Result for a host that was not port scanned
+    pub(crate) fn unscanned(target: IpAddr, host_status: HostStatus, elapsed: std::time::Duration) -> Self {
+        Self {
+            target,
+            host_status,
+            tcp_results: Vec::new(),
+            syn_results: Vec::new(),
+            udp_results: Vec::new(),
+            scan_duration_ms: elapsed.as_millis() as u64,
+            throttle_stats: None,
+            geo: None,
+            hostname: None,
+            excluded_by: None,
+            discovered_hostnames: Vec::new(),
+            tls_certificates: Vec::new(),
+            os_matches: Vec::new(),
+            down_reason: None,
+            port_verifications: Vec::new(),
+            liveness: Vec::new(),
+            deception: None,
+            latency: None,
+            phase_timings: None,
+            dropped_ports: None,
+            expansion: None,
+            mac: None,
+            icmp_rate_limit: None,
+        }
+    }
+
+    /// Scanned host in `host_status` with `ports` open to a connect probe
+    #[cfg(test)]
+    pub(crate) fn with_open_tcp(target: IpAddr, host_status: HostStatus, ports: &[u16]) -> Self {
+        Self {
+            tcp_results: ports.iter().map(|&port| TcpConnectResult::open(target, port)).collect(),
+            ..Self::unscanned(target, host_status, std::time::Duration::ZERO)
+        }
+    }
+
+    /// Keep only open port results, counting the others in `dropped_ports`
+    ///
+    /// Latency statistics, verification and OS detection have already used
+    /// the closed ports by the time results are collected.
+    pub fn drop_unopened_ports(&mut self) {
+        let mut dropped = self.dropped_ports.take().unwrap_or_default();
+        self.tcp_results.retain(|r| {
+            dropped.count(&r.status, r.error);
+            r.status == PortStatus::Open
+        });
+        self.syn_results.retain(|r| {
+            dropped.count(&r.status, r.error);
+            r.status == PortStatus::Open
+        });
+        self.udp_results.retain(|r| {
+            dropped.count(&r.status, r.error);
+            r.status == PortStatus::Open
+        });
+        self.dropped_ports = (dropped.total() > 0).then_some(dropped);
+    }
+
+    /// Number of distinct open ports: TCP ports open to a connect or SYN
+    /// probe are counted once, plus open UDP ports
+    pub fn open_port_count(&self) -> usize {
+        let tcp: std::collections::BTreeSet<u16> = self
+            .tcp_results
+            .iter()
+            .filter(|r| r.status == PortStatus::Open)
+            .map(|r| r.port)
+            .chain(self.syn_results.iter().filter(|r| r.status == PortStatus::Open).map(|r| r.port))
+            .collect();
+        tcp.len() + self.udp_results.iter().filter(|r| r.status == PortStatus::Open).count()
+    }
+
+    /// Round-trip times of every probe that got an answer
+    pub fn rtt_samples(&self) -> Vec<u64> {
+        self.tcp_results
+            .iter()
+            .filter_map(|r| r.response_time_ms)
+            .chain(self.syn_results.iter().filter_map(|r| r.response_time_ms))
+            .chain(self.udp_results.iter().filter_map(|r| r.response_time_ms))
+            .collect()
+    }
+
+    /// Error class of every port whose probe failed
+    pub fn probe_errors(&self) -> impl Iterator<Item = ProbeError> + '_ {
+        self.tcp_results
+            .iter()
+            .filter_map(|r| r.error)
+            .chain(self.syn_results.iter().filter_map(|r| r.error))
+            .chain(self.udp_results.iter().filter_map(|r| r.error))
+    }
+
+    /// Open ports the verification pass could not confirm
+    pub fn disagreements(&self) -> impl Iterator<Item = &PortVerification> {
+        self.port_verifications.iter().filter(|v| !v.confirmed())
+    }
+}
+
 impl std::fmt::Display for CompleteScanResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Scan Results for {}", self.target)?;
-        writeln!(f, "  Host Status: {}", self.host_status)?;
+        match self.hostname {
+            Some(ref hostname) => writeln!(f, "Scan Results for {} ({})", self.target, hostname)?,
+            None => writeln!(f, "Scan Results for {}", self.target)?,
+        }
+        if let Some(ref rule) = self.excluded_by {
+            writeln!(f, "  Excluded: matches rule {}", rule)?;
+        }
+        match self.down_reason {
+            Some(ref reason) => writeln!(f, "  Host Status: {} ({})", self.host_status, reason)?,
+            None => writeln!(f, "  Host Status: {}", self.host_status)?,
+        }
+        if let Some(ref mac) = self.mac {
+            writeln!(f, "  MAC Address: {}", mac)?;
+        }
+        if let Some(ref limit) = self.icmp_rate_limit {
+            writeln!(f, "  ICMP Rate Limit: {}", limit)?;
+        }
         writeln!(f, "  Scan Duration: {}ms", self.scan_duration_ms)?;
+        if let Some(ref expansion) = self.expansion {
+            writeln!(f, "  Port Expansion: {}", expansion)?;
+        }
+        if let Some(ref geo) = self.geo {
+            writeln!(f, "  Location: {}", geo)?;
+        }
+        if !self.discovered_hostnames.is_empty() {
+            let names: Vec<&str> = self.discovered_hostnames.iter().map(|d| d.hostname.as_str()).collect();
+            writeln!(f, "  Discovered Hostnames: {}", names.join(", "))?;
+        }
+        if let Some(best) = self.os_matches.first() {
+            writeln!(f, "  OS: {} ({}, {:.0}%)", best.os_name, best.confidence, best.confidence_score * 100.0)?;
+        }
+        if let Some(ref latency) = self.latency {
+            writeln!(f, "  Latency: {}", latency)?;
+        }
+        if let Some(ref deception) = self.deception {
+            writeln!(f, "  Deception Suspicion: {}", deception)?;
+        }
+        if !self.port_verifications.is_empty() {
+            let confirmed = self.port_verifications.iter().filter(|v| v.confirmed()).count();
+            writeln!(f, "  Verified Open Ports: {}/{}", confirmed, self.port_verifications.len())?;
+            for disagreement in self.disagreements() {
+                writeln!(f, "    ! {}", disagreement)?;
+            }
+        }
         
         if !self.tcp_results.is_empty() {
             writeln!(f, "\n  TCP Connect Results:")?;
@@ -275,6 +1858,13 @@ impl std::fmt::Display for CompleteScanResult {
             }
         }
         
+        if !self.liveness.is_empty() {
+            writeln!(f, "\n  Deep Checks:")?;
+            for check in &self.liveness {
+                writeln!(f, "    {}", check)?;
+            }
+        }
+
         if let Some(ref stats) = self.throttle_stats {
             writeln!(f, "\n  {}", stats)?;
         }
@@ -283,7 +1873,7 @@ impl std::fmt::Display for CompleteScanResult {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
 
@@ -293,7 +1883,12 @@ mod tests {
         ScannerConfig {
             default_timeout_ms: 2000,
             max_concurrent_scans: 100,
+            max_concurrent_targets: 10,
+            max_concurrent_probes_total: 0,
+            result_buffer: 64,
+            ulimit_check: Default::default(),
             adaptive_throttling: false,
+            throttle_strategy: throttle::ADAPTIVE_STRATEGY.to_string(),
             initial_pps: 1000,
             max_pps: 10000,
             min_pps: 100,
@@ -302,6 +1897,10 @@ mod tests {
                 method: "tcp".to_string(),
                 timeout_ms: 1000,
                 retries: 1,
+                ipv6: Ipv6DiscoveryConfig::default(),
+                consensus: Default::default(),
+                arp_warmup: false,
+                cache: Default::default(),
             },
             tcp_connect: crate::config::TcpConnectConfig {
                 enabled: true,
@@ -314,13 +1913,34 @@ mod tests {
                 timeout_ms: 2000,
                 retries: 1,
                 retry_delay_ms: 50,
+                stateless: false,
             },
             udp: crate::config::UdpConfig {
                 enabled: false,
                 timeout_ms: 2000,
                 retries: 1,
                 retry_delay_ms: 200,
+                icmp_ratelimit_reprobes: 0,
             },
+            fragment_mtu: None,
+            os_detection: crate::config::OsDetectionConfig::default(),
+            evasion: ProbeEvasion::default(),
+            verify_open_ports: false,
+            adaptive_timeout: crate::config::AdaptiveTimeoutConfig::default(),
+            max_rate: None,
+            min_rate: None,
+            strict: false,
+            open_ports_only: false,
+            expand_ports: false,
+            port_reasons: false,
+            confirm_targets_above: 0,
+            scan_order: Default::default(),
+            retry_policy: Default::default(),
+            port_frequency_file: None,
+            services_file: None,
+            oui_file: None,
+            ssh_tunnel: None,
+            deep_check: Default::default(),
         }
     }
 
@@ -330,6 +1950,318 @@ mod tests {
         let _scanner = Scanner::new(config);
     }
 
+    #[test]
+    fn test_ssh_tunnel_allows_only_connect_scans() {
+        let scanner = Scanner::new(create_test_config()).with_ssh_tunnel(SshTunnelConfig::new("ops@jump.example.com"));
+        assert!(scanner.check_scan_types(&[ScanType::TcpConnect]).is_ok());
+        assert!(scanner.check_scan_types(&[ScanType::TcpConnect, ScanType::Udp]).is_err());
+    }
+
+    #[test]
+    fn test_drop_unopened_ports_counts_per_host() {
+        use crate::scanner::udp_scan::UdpScanResult;
+
+        let target = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+        let mut result = CompleteScanResult::unscanned(target, HostStatus::Up, std::time::Duration::ZERO);
+        let mut open = TcpConnectResult::failed(target, 22, ProbeError::Timeout);
+        open.status = PortStatus::Open;
+        open.error = None;
+        result.tcp_results = vec![open, TcpConnectResult::failed(target, 23, ProbeError::Refused)];
+        result.udp_results = vec![UdpScanResult::failed(target, 53, ProbeError::Timeout)];
+
+        result.drop_unopened_ports();
+        assert_eq!(result.tcp_results.len(), 1);
+        assert!(result.udp_results.is_empty());
+        let dropped = result.dropped_ports.clone().unwrap();
+        assert_eq!(dropped.total(), 2);
+        assert_eq!(dropped.errors[&ProbeError::Refused], 1);
+        assert_eq!(dropped.errors[&ProbeError::Timeout], 1);
+
+        // Dropping again keeps the earlier counts
+        result.drop_unopened_ports();
+        assert_eq!(result.dropped_ports, Some(dropped));
+    }
+
+    #[tokio::test]
+    async fn test_os_detection_needs_open_port() {
+        let queue = OsDetectionQueue::from_config(&crate::config::OsDetectionConfig::default());
+        let scanner = Scanner::new(create_test_config()).with_os_detection(queue);
+
+        // Nothing listens on port 1, so there is no port to fingerprint through
+        let target: IpAddr = "127.0.0.1".parse().unwrap();
+        let result = scanner
+            .scan_host(target, vec![1], vec![ScanType::TcpConnect], &ArpWarmupReport::default())
+            .await
+            .unwrap();
+        assert!(scanner.submit_os_detection(&result).is_none());
+        assert!(result.os_matches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_fails_on_scan_type_failure() {
+        let target = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        let warmup = ArpWarmupReport::default();
+        let scanner = Scanner::new(create_test_config()).with_privilege_check(false);
+
+        // SYN probes fail per port; normally that only leaves the ports unknown
+        let lenient = scanner
+            .scan_host(target, vec![1], vec![ScanType::TcpSyn], &warmup)
+            .await
+            .unwrap();
+        assert_eq!(lenient.syn_results.len(), 1);
+        assert_eq!(lenient.syn_results[0].status, PortStatus::Unknown);
+        assert_eq!(lenient.syn_results[0].error, Some(ProbeError::Other));
+        assert_eq!(lenient.probe_errors().collect::<Vec<_>>(), vec![ProbeError::Other]);
+
+        let strict = scanner.with_strict(true);
+        let err = strict
+            .scan_host(target, vec![1], vec![ScanType::TcpSyn], &warmup)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("TCP SYN scan failed for 1 of 1 ports"));
+    }
+
+    /// Counts probes instead of pacing them
+    #[derive(Default)]
+    struct CountingThrottle {
+        waits: std::sync::atomic::AtomicUsize,
+        successes: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl ThrottleStrategy for CountingThrottle {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn wait(&self) -> crate::error::ScanResult<()> {
+            self.waits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn record_result(&self, result: throttle::ThrottleScanResult) {
+            if result == throttle::ThrottleScanResult::Success {
+                self.successes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        async fn current_pps(&self) -> usize {
+            0
+        }
+
+        async fn get_stats(&self) -> ThrottleStats {
+            let successes = self.successes.load(std::sync::atomic::Ordering::SeqCst);
+            ThrottleStats {
+                current_pps: 0,
+                total_requests: self.waits.load(std::sync::atomic::Ordering::SeqCst),
+                total_successes: successes,
+                total_failures: 0,
+                success_rate: 1.0,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_throttle_strategy() {
+        let mut config = create_test_config();
+        config.adaptive_throttling = true;
+        config.throttle_strategy = "counting".to_string();
+
+        let counting = Arc::new(CountingThrottle::default());
+        let shared = counting.clone();
+        let scanner = Scanner::new(config).with_throttle_strategy(
+            "counting",
+            Arc::new(move |_: &ScannerConfig, _: &ThrottlingConfig| shared.clone() as Arc<dyn ThrottleStrategy>),
+        );
+
+        let target = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        let listener = std::net::TcpListener::bind((target, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let result = scanner
+            .scan_host(target, vec![port], vec![ScanType::TcpConnect], &ArpWarmupReport::default())
+            .await
+            .unwrap();
+
+        assert_eq!(counting.waits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(result.throttle_stats.unwrap().total_successes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reloaded_throttling_rebuilds_throttle() {
+        #[derive(Clone, Default)]
+        struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut config = create_test_config();
+        config.adaptive_throttling = true;
+        let current = AppConfig::default();
+        let mut scanner = Scanner::new(config).with_throttling(current.throttling.clone());
+        let mut new = current.clone();
+        new.throttling.success_threshold = 0.5;
+        new.throttling.failure_threshold = 0.1;
+        new.throttling.adjustment_interval_ms = 0;
+
+        let (live, outcome) = crate::config::reload::plan_reload(&current, &new);
+        assert!(outcome.rejected.is_empty());
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).with_ansi(false).finish();
+        tracing::subscriber::with_default(subscriber, || outcome.audit());
+        let audit = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            audit.lines().any(|line| line.contains("\"applied\"") && line.contains("key=throttling.success_threshold")),
+            "{}",
+            audit
+        );
+
+        scanner.apply_reloaded(&live);
+        let throttle = scanner.throttle.clone().unwrap();
+        let initial = throttle.current_pps().await;
+        throttle.record_result(ThrottleScanResult::Failure).await;
+        throttle.record_result(ThrottleScanResult::Success).await;
+        // Half the probes answered: enough to speed up again only with the
+        // reloaded threshold, where the old one would have backed off twice
+        assert!(throttle.current_pps().await > initial / 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_scan_returns_partial_results() {
+        let mut config = create_test_config();
+        config.strict = true;
+        let scanner = Scanner::new(config);
+        let target = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        scanner.cancellation_token().cancel();
+
+        // No new hosts start, and unprobed ports are not a strict-mode failure
+        let results = scanner
+            .scan_multiple(vec![target], vec![80, 443], vec![ScanType::TcpConnect])
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        let result = scanner
+            .scan_host(target, vec![80, 443], vec![ScanType::TcpConnect], &ArpWarmupReport::default())
+            .await
+            .unwrap();
+        assert!(result.tcp_results.is_empty());
+        assert!(scanner.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_every_entry_point_checks_scope() {
+        let mut security = crate::config::AppConfig::default().security;
+        security.require_privileges_check = false;
+        security.scope = vec!["10.0.0.0/8".parse().unwrap()];
+        let scanner = Scanner::new(create_test_config()).with_security(&security);
+        let outside = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+        let refused = |result: crate::error::ScanResult<()>| {
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("outside the authorized scope"), "{}", error);
+        };
+        refused(scanner.scan(outside, vec![80], vec![ScanType::TcpConnect]).await.map(drop));
+        refused(scanner.scan_multiple(vec![outside], vec![80], vec![ScanType::TcpConnect]).await.map(drop));
+        let prefix = Ipv6Prefix::new("fd00::".parse().unwrap(), 64).unwrap();
+        refused(scanner.discover_ipv6(&prefix).await.map(drop));
+    }
+
+    #[tokio::test]
+    async fn test_results_flow_through_bounded_queue() {
+        let mut config = create_test_config();
+        config.result_buffer = 2;
+        config.max_concurrent_targets = 4;
+        let scanner = Scanner::new(config);
+        let targets: Vec<IpAddr> = (1..=6).map(|i| IpAddr::from([127, 0, 0, i])).collect();
+
+        // The consumer runs on the blocking pool: while it is stuck on the
+        // first host, scanning goes on until the buffer is full
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let (seen_tx, seen) = std::sync::mpsc::channel();
+        let mut progress = scanner.progress();
+        let scan = scanner.scan_multiple_with(targets.clone(), vec![1], vec![ScanType::TcpConnect], move |result| {
+            // Blocks until released, then returns at once as the sender is gone
+            let _ = released.recv();
+            seen_tx.send(result.target).unwrap();
+            Ok(())
+        });
+        let unblock = async move {
+            progress.wait_for(|progress| progress.targets_done >= 3).await.unwrap();
+            release.send(()).unwrap();
+        };
+        let (delivered, ()) = tokio::time::timeout(std::time::Duration::from_secs(10), async { tokio::join!(scan, unblock) })
+            .await
+            .unwrap();
+        let mut seen: Vec<IpAddr> = seen.try_iter().collect();
+        seen.sort();
+        assert_eq!((delivered.unwrap(), seen), (6, targets));
+
+        // An error from the consumer stops the scan
+        let error = scanner
+            .scan_multiple_with(vec![IpAddr::from([127, 0, 0, 1])], vec![1], vec![ScanType::TcpConnect], |_| {
+                Err(ScanError::scanner_error("sink full"))
+            })
+            .await;
+        assert!(error.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_arp_marks_host_down() {
+        let scanner = Scanner::new(create_test_config());
+        let target = std::net::Ipv4Addr::new(192, 0, 2, 77);
+        let mut warmup = ArpWarmupReport::default();
+        warmup.failed.insert(target);
+
+        let result = scanner
+            .scan_host(IpAddr::V4(target), vec![80], vec![ScanType::TcpConnect], &warmup)
+            .await
+            .unwrap();
+        assert_eq!(result.host_status, HostStatus::Down);
+        assert_eq!(result.down_reason.as_deref(), Some("no ARP reply"));
+        assert!(result.tcp_results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verify_flags_unconfirmed_syn_open() {
+        let scanner = Scanner::new(create_test_config());
+        let target = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        let listener = std::net::TcpListener::bind((target, 0)).unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = {
+            let probe = std::net::TcpListener::bind((target, 0)).unwrap();
+            probe.local_addr().unwrap().port()
+        };
+        let syn = |port| TcpSynResult {
+            target,
+            port,
+            status: PortStatus::Open,
+            response_time_ms: None,
+            flags: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            icmp: None,
+            service: None,
+            confidence: None,
+            reason: None,
+        };
+
+        let checks = scanner
+            .verify_open_ports(target, &[], &[syn(open_port), syn(closed_port)])
+            .await;
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].confirmed());
+        assert_eq!(checks[1].verified_status, PortStatus::Closed);
+        assert_eq!(checks[1].verified_with, ScanType::TcpConnect);
+    }
+
     #[test]
     fn test_scan_type_equality() {
         assert_eq!(ScanType::TcpConnect, ScanType::TcpConnect);
@@ -0,0 +1,116 @@
+//! Randomized probe order
+//!
+//! Sequential sweeps (host .1, .2, .3 ... port 1, 2, 3 ...) are trivially
+//! spotted by an IDS and concentrate timing effects on the same hosts. The
+//! orchestrator can shuffle the target list and each host's port list before
+//! handing them to the scanners. Shuffling happens before any probe is sent,
+//! so a port keeps its identity through every retry and its result.
+//!
+//! A seed makes the order reproducible: each host's port order depends only on
+//! the seed and the host address, not on which hosts happen to run in
+//! parallel.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Which lists are shuffled, and with what seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOrder {
+    /// Shuffle each host's port list
+    pub randomize_ports: bool,
+    /// Shuffle the list of targets
+    pub randomize_targets: bool,
+    /// RNG seed; a random one is picked (and logged) when unset
+    pub seed: Option<u64>,
+}
+
+impl ScanOrder {
+    /// Whether anything is shuffled
+    pub fn is_active(&self) -> bool {
+        self.randomize_ports || self.randomize_targets
+    }
+
+    /// Fix the seed so every later shuffle of the run is reproducible
+    pub fn with_resolved_seed(mut self) -> Self {
+        if self.is_active() && self.seed.is_none() {
+            self.seed = Some(rand::thread_rng().gen());
+        }
+        self
+    }
+
+    /// Shuffle the target list in place when target randomization is on
+    pub fn shuffle_targets(&self, targets: &mut [IpAddr]) {
+        if self.randomize_targets {
+            targets.shuffle(&mut StdRng::seed_from_u64(self.seed.unwrap_or_default()));
+        }
+    }
+
+    /// Shuffle one host's port list in place when port randomization is on
+    pub fn shuffle_ports(&self, target: IpAddr, ports: &mut [u16]) {
+        if self.randomize_ports {
+            let seed = self.seed.unwrap_or_default() ^ address_key(target);
+            ports.shuffle(&mut StdRng::seed_from_u64(seed));
+        }
+    }
+}
+
+/// Fold an address into 64 bits for per-host seeding
+fn address_key(target: IpAddr) -> u64 {
+    match target {
+        IpAddr::V4(v4) => u32::from(v4) as u64,
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            (bits >> 64) as u64 ^ bits as u64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_shuffle_is_reproducible() {
+        let order = ScanOrder {
+            randomize_ports: true,
+            randomize_targets: true,
+            seed: Some(42),
+        };
+        let host_a: IpAddr = "192.0.2.1".parse().unwrap();
+        let host_b: IpAddr = "192.0.2.2".parse().unwrap();
+        let sequential: Vec<u16> = (1..=100).collect();
+
+        let mut first = sequential.clone();
+        let mut second = sequential.clone();
+        order.shuffle_ports(host_a, &mut first);
+        order.shuffle_ports(host_a, &mut second);
+        assert_eq!(first, second);
+        assert_ne!(first, sequential);
+
+        // Same ports, different order per host
+        let mut other = sequential.clone();
+        order.shuffle_ports(host_b, &mut other);
+        assert_ne!(other, first);
+        other.sort_unstable();
+        assert_eq!(other, sequential);
+
+        let mut targets: Vec<IpAddr> = (1..=50).map(|i| format!("192.0.2.{}", i).parse().unwrap()).collect();
+        let original = targets.clone();
+        order.shuffle_targets(&mut targets);
+        assert_ne!(targets, original);
+    }
+
+    #[test]
+    fn test_inactive_order_keeps_lists() {
+        let order = ScanOrder::default().with_resolved_seed();
+        assert_eq!(order.seed, None);
+
+        let mut ports = vec![1, 2, 3];
+        order.shuffle_ports("192.0.2.1".parse().unwrap(), &mut ports);
+        assert_eq!(ports, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,182 @@
+//! OS fingerprinting work queue for multi-host scans
+//!
+//! Fingerprinting a host takes several round trips per analyzer, so running it
+//! inline after each port scan keeps that host's scan slot busy. The queue
+//! instead runs fingerprint jobs as background tasks, bounded by their own
+//! semaphore and start rate, while the port scans of other hosts continue.
+
+use crate::config::OsDetectionConfig;
+use crate::os_fingerprint::{LearningStore, OsFingerprintEngine, OsMatchResult, OsResultCache, UserDatabase};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn, Instrument};
+
+/// Spaces out job starts to a maximum rate
+#[derive(Debug)]
+struct StartPacer {
+    interval: Duration,
+    next_start: Mutex<Instant>,
+}
+
+impl StartPacer {
+    fn new(per_second: u32) -> Option<Self> {
+        (per_second > 0).then(|| Self {
+            interval: Duration::from_secs(1) / per_second,
+            next_start: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Wait for the next free start slot
+    async fn wait(&self) {
+        let start = {
+            let mut next_start = self.next_start.lock().await;
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + self.interval;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+}
+
+/// A fingerprint job running on the queue
+pub struct OsDetectionJob {
+    target: IpAddr,
+    handle: JoinHandle<(Vec<OsMatchResult>, Duration)>,
+}
+
+impl OsDetectionJob {
+    /// Host being fingerprinted
+    pub fn target(&self) -> IpAddr {
+        self.target
+    }
+
+    /// Whether the job is done, so waiting for it returns at once
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the job's OS matches (empty if fingerprinting failed)
+    pub async fn wait(self) -> Vec<OsMatchResult> {
+        self.wait_timed().await.0
+    }
+
+    /// Wait for the job's OS matches and the time spent fingerprinting
+    ///
+    /// The duration excludes time the job sat in the queue.
+    pub async fn wait_timed(self) -> (Vec<OsMatchResult>, Duration) {
+        match self.handle.await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                warn!("OS detection task for {} failed: {}", self.target, e);
+                (Vec::new(), Duration::ZERO)
+            }
+        }
+    }
+}
+
+/// Runs OS fingerprinting jobs concurrently with bounded parallelism
+#[derive(Clone)]
+pub struct OsDetectionQueue {
+    engine: Arc<OsFingerprintEngine>,
+    permits: Arc<Semaphore>,
+    pacer: Option<Arc<StartPacer>>,
+    active_probes: bool,
+}
+
+impl OsDetectionQueue {
+    /// Create a queue using the given engine
+    pub fn new(engine: Arc<OsFingerprintEngine>, config: &OsDetectionConfig) -> Self {
+        Self {
+            engine,
+            permits: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+            pacer: StartPacer::new(config.max_per_second).map(Arc::new),
+            active_probes: config.active_probes,
+        }
+    }
+
+    /// Create a queue with the built-in signatures plus the configured user
+    /// database, caching results and recording unknown fingerprints as
+    /// configured; an unreadable user database is skipped with a warning
+    pub fn from_config(config: &OsDetectionConfig) -> Self {
+        let mut engine = OsFingerprintEngine::new();
+        if let Some(ref path) = config.user_database {
+            match UserDatabase::load(path) {
+                Ok(user) if !user.is_empty() => engine = engine.with_user_database(&user),
+                Ok(_) => {}
+                Err(e) => warn!("Ignoring user OS signature database {:?}: {}", path, e),
+            }
+        }
+        if config.cache.enabled {
+            let ttl = Duration::from_secs(config.cache.ttl_secs);
+            let cache = match config.cache.path {
+                Some(ref path) => OsResultCache::open(path, ttl).unwrap_or_else(|e| {
+                    warn!("OS detection cache {:?} unusable, caching in memory only: {}", path, e);
+                    OsResultCache::in_memory(ttl)
+                }),
+                None => OsResultCache::in_memory(ttl),
+            };
+            engine = engine.with_cache(Arc::new(cache));
+        }
+        if config.learning {
+            if let Some(ref path) = config.unknown_fingerprints {
+                engine = engine.with_learning(LearningStore::new(path), config.learning_threshold);
+            }
+        }
+        Self::new(Arc::new(engine), config)
+    }
+
+    /// Queue a host for fingerprinting and return immediately
+    ///
+    /// The job starts once a permit and a start slot are free.
+    pub fn submit(&self, target: IpAddr, open_port: u16, closed_port: Option<u16>) -> OsDetectionJob {
+        let engine = self.engine.clone();
+        let permits = self.permits.clone();
+        let pacer = self.pacer.clone();
+        let active_probes = self.active_probes;
+
+        let handle = tokio::spawn(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return (Vec::new(), Duration::ZERO);
+            };
+            if let Some(pacer) = pacer {
+                pacer.wait().await;
+            }
+
+            debug!("Fingerprinting {} via port {}", target, open_port);
+            let start = Instant::now();
+            let matches = match engine.detect_os(target, open_port, closed_port, active_probes).await {
+                Ok(matches) => matches,
+                Err(e) => {
+                    warn!("OS detection failed for {}: {}", target, e);
+                    Vec::new()
+                }
+            };
+            (matches, start.elapsed())
+        }.in_current_span());
+
+        OsDetectionJob { target, handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_start_pacer_spacing() {
+        let pacer = StartPacer::new(50).unwrap();
+        let begin = Instant::now();
+        for _ in 0..3 {
+            pacer.wait().await;
+        }
+        // Starts at 0ms, 20ms and 40ms
+        let elapsed = begin.elapsed();
+        assert!(elapsed >= Duration::from_millis(40));
+        assert!(elapsed < Duration::from_millis(500));
+        assert!(StartPacer::new(0).is_none());
+    }
+}
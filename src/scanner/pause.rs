@@ -0,0 +1,99 @@
+//! Pausing a running scan
+//!
+//! A [`PauseControl`] is shared by the orchestrator and the port scanners.
+//! While it is paused no new probe (or host) starts; probes already on the
+//! wire finish, and everything else — pending ports, partial results,
+//! progress — stays in memory until the scan is resumed. Cancellation still
+//! ends a paused scan.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Cloneable handle that suspends and resumes probe issuance
+#[derive(Debug, Clone)]
+pub struct PauseControl {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl Default for PauseControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PauseControl {
+    pub fn new() -> Self {
+        Self {
+            paused: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Stop issuing probes
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Continue issuing probes
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Pause a running scan or resume a paused one; returns whether it is now paused
+    pub fn toggle(&self) -> bool {
+        let mut now_paused = false;
+        self.paused.send_modify(|paused| {
+            *paused = !*paused;
+            now_paused = *paused;
+        });
+        now_paused
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Wait until the scan is not paused
+    ///
+    /// Returns `false` when `cancel` fired while waiting.
+    pub async fn wait_resumed(&self, cancel: &CancellationToken) -> bool {
+        if !self.is_paused() {
+            return true;
+        }
+        let mut paused = self.paused.subscribe();
+        tokio::select! {
+            _ = cancel.cancelled() => false,
+            _ = paused.wait_for(|paused| !paused) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pause_blocks_until_resumed() {
+        let control = PauseControl::new();
+        let cancel = CancellationToken::new();
+        assert!(control.wait_resumed(&cancel).await);
+
+        assert!(control.toggle());
+        let waiter = {
+            let (control, cancel) = (control.clone(), cancel.clone());
+            tokio::spawn(async move { control.wait_resumed(&cancel).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        control.resume();
+        assert!(waiter.await.unwrap());
+        assert!(!control.is_paused());
+
+        // Cancelling releases a paused waiter
+        control.pause();
+        cancel.cancel();
+        assert!(!control.wait_resumed(&cancel).await);
+    }
+}
@@ -0,0 +1,96 @@
+//! Per-host time spent in each scan phase
+//!
+//! Long scans are usually dominated by one phase — slow discovery timeouts
+//! against dead hosts, UDP retries, OS fingerprinting — and the total scan
+//! duration alone does not say which. Every scanned host records a
+//! [`PhaseTimings`] breakdown that the table and HTML reports render.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::Instant;
+
+/// Wall-clock milliseconds per phase for one host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Host discovery and reverse DNS
+    pub discovery_ms: u64,
+    /// TCP connect and SYN port scans
+    pub tcp_ms: u64,
+    pub udp_ms: u64,
+    /// Open-port verification and TLS hostname collection
+    pub detection_ms: u64,
+    /// OS fingerprinting, measured on the detection queue
+    pub os_ms: u64,
+}
+
+impl PhaseTimings {
+    /// Phase names paired with their durations, in scan order
+    pub fn phases(&self) -> [(&'static str, u64); 5] {
+        [
+            ("discovery", self.discovery_ms),
+            ("tcp", self.tcp_ms),
+            ("udp", self.udp_ms),
+            ("detection", self.detection_ms),
+            ("os", self.os_ms),
+        ]
+    }
+
+    /// The phase that took longest, if any took time at all
+    pub fn dominant(&self) -> Option<(&'static str, u64)> {
+        self.phases()
+            .into_iter()
+            .filter(|(_, ms)| *ms > 0)
+            .max_by_key(|(_, ms)| *ms)
+    }
+}
+
+/// Sums the phases of two passes over the same host (port expansion)
+impl AddAssign for PhaseTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.discovery_ms += other.discovery_ms;
+        self.tcp_ms += other.tcp_ms;
+        self.udp_ms += other.udp_ms;
+        self.detection_ms += other.detection_ms;
+        self.os_ms += other.os_ms;
+    }
+}
+
+impl fmt::Display for PhaseTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases: Vec<String> = self
+            .phases()
+            .iter()
+            .map(|(name, ms)| format!("{} {}ms", name, ms))
+            .collect();
+        write!(f, "{}", phases.join(", "))
+    }
+}
+
+/// Milliseconds elapsed since `start`
+pub(crate) fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dominant_phase() {
+        assert_eq!(PhaseTimings::default().dominant(), None);
+
+        let timings = PhaseTimings {
+            discovery_ms: 120,
+            tcp_ms: 900,
+            udp_ms: 4000,
+            detection_ms: 0,
+            os_ms: 300,
+        };
+        assert_eq!(timings.dominant(), Some(("udp", 4000)));
+        assert_eq!(
+            timings.to_string(),
+            "discovery 120ms, tcp 900ms, udp 4000ms, detection 0ms, os 300ms"
+        );
+    }
+}
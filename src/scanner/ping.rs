@@ -0,0 +1,157 @@
+//! ICMP echo ("ping") for host discovery
+//!
+//! Uses a raw ICMP socket when privileged and falls back to the unprivileged
+//! ICMP datagram socket otherwise (Linux `net.ipv4.ping_group_range`, macOS).
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::privileges::PingMode;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// Send a single ICMP echo request and wait for the matching reply
+///
+/// # Arguments
+/// * `target` - Host to ping
+/// * `timeout_ms` - How long to wait for a reply
+/// * `mode` - Raw or unprivileged datagram socket
+///
+/// # Returns
+/// * `ScanResult<Option<Duration>>` - Round-trip time, or `None` on timeout
+pub async fn icmp_echo(target: IpAddr, timeout_ms: u64, mode: PingMode) -> ScanResult<Option<Duration>> {
+    tokio::task::spawn_blocking(move || icmp_echo_blocking(target, timeout_ms, mode))
+        .await
+        .map_err(|e| ScanError::scanner_error(format!("ICMP echo task failed: {}", e)))?
+}
+
+fn icmp_echo_blocking(target: IpAddr, timeout_ms: u64, mode: PingMode) -> ScanResult<Option<Duration>> {
+    let (domain, protocol, request_type, reply_type) = match target {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4, ICMPV4_ECHO_REQUEST, ICMPV4_ECHO_REPLY),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6, ICMPV6_ECHO_REQUEST, ICMPV6_ECHO_REPLY),
+    };
+    let socket_type = match mode {
+        PingMode::Raw => Type::RAW,
+        PingMode::Unprivileged => Type::DGRAM,
+    };
+
+    let socket = Socket::new(domain, socket_type, Some(protocol)).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            ScanError::permission_denied("ICMP echo socket")
+        } else {
+            ScanError::Io(e)
+        }
+    })?;
+    socket.connect(&SockAddr::from(SocketAddr::new(target, 0)))?;
+
+    let identifier = rand::random::<u16>();
+    let request = build_echo_request(request_type, identifier, target.is_ipv4());
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(timeout_ms);
+    socket.send(&request)?;
+
+    let mut buf = [MaybeUninit::<u8>::uninit(); 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let len = match socket.recv(&mut buf) {
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(ScanError::Io(e)),
+        };
+
+        // SAFETY: the first `len` bytes were initialized by recv
+        let packet: Vec<u8> = buf[..len].iter().map(|b| unsafe { b.assume_init() }).collect();
+        let icmp = strip_ipv4_header(&packet, target.is_ipv4());
+        if icmp.len() < 8 || icmp[0] != reply_type {
+            continue;
+        }
+
+        // Datagram sockets rewrite the identifier, so only raw replies are matched on it
+        if mode == PingMode::Raw && icmp[4..6] != identifier.to_be_bytes() {
+            continue;
+        }
+
+        let rtt = start.elapsed();
+        debug!("ICMP echo reply from {} in {}ms", target, rtt.as_millis());
+        return Ok(Some(rtt));
+    }
+}
+
+/// Build an echo request; ICMPv6 checksums are filled in by the kernel
+fn build_echo_request(request_type: u8, identifier: u16, ipv4: bool) -> Vec<u8> {
+    let id = identifier.to_be_bytes();
+    let mut packet = vec![request_type, 0, 0, 0, id[0], id[1], 0, 1];
+    packet.extend_from_slice(b"nrmap-ping");
+
+    if ipv4 {
+        let checksum = internet_checksum(&packet).to_be_bytes();
+        packet[2] = checksum[0];
+        packet[3] = checksum[1];
+    }
+    packet
+}
+
+/// IPv4 raw sockets (and macOS datagram sockets) deliver the IP header too
+fn strip_ipv4_header(packet: &[u8], ipv4: bool) -> &[u8] {
+    if ipv4 && !packet.is_empty() && packet[0] >> 4 == 4 {
+        let header_len = ((packet[0] & 0x0f) as usize) * 4;
+        packet.get(header_len..).unwrap_or_default()
+    } else {
+        packet
+    }
+}
+
+/// RFC 1071 internet checksum
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| {
+            let hi = chunk[0] as u32;
+            let lo = chunk.get(1).copied().unwrap_or(0) as u32;
+            (hi << 8) | lo
+        })
+        .sum();
+
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_request_checksum() {
+        let packet = build_echo_request(ICMPV4_ECHO_REQUEST, 0x1234, true);
+        assert_eq!(packet[0], ICMPV4_ECHO_REQUEST);
+        // A packet including its own checksum sums to zero
+        assert_eq!(internet_checksum(&packet), 0);
+    }
+
+    #[test]
+    fn test_strip_ipv4_header() {
+        let mut packet = vec![0x45];
+        packet.extend_from_slice(&[0u8; 19]);
+        packet.extend_from_slice(&[ICMPV4_ECHO_REPLY, 0, 0, 0]);
+        assert_eq!(strip_ipv4_header(&packet, true), &[ICMPV4_ECHO_REPLY, 0, 0, 0]);
+
+        let icmp_only = [ICMPV4_ECHO_REPLY, 0, 0, 0];
+        assert_eq!(strip_ipv4_header(&icmp_only, true), &icmp_only);
+    }
+}
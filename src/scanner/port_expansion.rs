@@ -0,0 +1,138 @@
+//! Two-pass port expansion
+//!
+//! A fast first pass over the requested ports (typically `--top-ports`)
+//! picks out the hosts worth a closer look: those confirmed up and those
+//! with at least one open port. Only they get a second pass over the rest of
+//! 1-65535, so a sweep spends full-range probes where something answered.
+//! The second pass is merged into each host's result and summarised in
+//! [`PortExpansion`].
+
+use super::deception::DeceptionAssessment;
+use super::host_discovery::HostStatus;
+use super::latency::LatencyStats;
+use super::CompleteScanResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// What the expansion pass added to a host's result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortExpansion {
+    /// Ports probed by the first pass
+    pub initial_ports: usize,
+    /// Ports probed by the expansion pass
+    pub added_ports: usize,
+    /// Open ports only the expansion pass found
+    pub new_open_ports: usize,
+    pub duration_ms: u64,
+}
+
+impl fmt::Display for PortExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} more ports after {}, {} new open ({}ms)",
+            self.added_ports, self.initial_ports, self.new_open_ports, self.duration_ms
+        )
+    }
+}
+
+/// Whether a first-pass result earns the expansion pass
+pub fn qualifies(result: &CompleteScanResult) -> bool {
+    result.excluded_by.is_none() && (result.host_status == HostStatus::Up || result.open_port_count() > 0)
+}
+
+/// Ports of 1-65535 the first pass did not probe, in ascending order
+pub fn remaining_ports(initial: &[u16]) -> Vec<u16> {
+    let probed: HashSet<u16> = initial.iter().copied().collect();
+    (1..=u16::MAX).filter(|port| !probed.contains(port)).collect()
+}
+
+/// Fold the expansion pass over `initial_ports` more ports into the first
+/// pass result; host-level facts (status, names, location) stay from the
+/// first pass
+pub(crate) fn merge(result: &mut CompleteScanResult, expansion: CompleteScanResult, initial_ports: usize) {
+    let added_ports = expansion
+        .tcp_results
+        .len()
+        .max(expansion.syn_results.len())
+        .max(expansion.udp_results.len());
+    result.expansion = Some(PortExpansion {
+        initial_ports,
+        added_ports,
+        new_open_ports: expansion.open_port_count(),
+        duration_ms: expansion.scan_duration_ms,
+    });
+
+    result.tcp_results.extend(expansion.tcp_results);
+    result.syn_results.extend(expansion.syn_results);
+    result.udp_results.extend(expansion.udp_results);
+    result.scan_duration_ms += expansion.scan_duration_ms;
+    result.throttle_stats = expansion.throttle_stats.or(result.throttle_stats.take());
+    for name in expansion.discovered_hostnames {
+        if !result.discovered_hostnames.iter().any(|known| known.hostname == name.hostname) {
+            result.discovered_hostnames.push(name);
+        }
+    }
+    result.tls_certificates.extend(expansion.tls_certificates);
+    result.port_verifications.extend(expansion.port_verifications);
+    result.liveness.extend(expansion.liveness);
+    if let (Some(phases), Some(more)) = (result.phase_timings.as_mut(), expansion.phase_timings) {
+        *phases += more;
+    }
+    result.deception = DeceptionAssessment::assess(result);
+    result.latency = LatencyStats::from_samples(&result.rtt_samples());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    fn tcp(target: IpAddr, port: u16, status: PortStatus) -> TcpConnectResult {
+        TcpConnectResult {
+            target,
+            port,
+            status,
+            response_time_ms: Some(2),
+            banner: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            service: None,
+            error_class: None,
+            confidence: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_expansion_pass() {
+        let target: IpAddr = "192.0.2.7".parse().unwrap();
+        let mut first = CompleteScanResult::unscanned(target, HostStatus::Down, Duration::from_millis(40));
+        assert!(!qualifies(&first));
+        first.tcp_results = vec![tcp(target, 22, PortStatus::Open), tcp(target, 80, PortStatus::Closed)];
+        // An open port counts even when discovery saw nothing
+        assert!(qualifies(&first));
+
+        let remaining = remaining_ports(&[22, 80]);
+        assert_eq!(remaining.len(), 65533);
+        assert_eq!(&remaining[20..22], &[21, 23]);
+
+        let mut second = CompleteScanResult::unscanned(target, HostStatus::Up, Duration::from_millis(900));
+        second.tcp_results = vec![tcp(target, 8443, PortStatus::Open), tcp(target, 9000, PortStatus::Filtered)];
+        merge(&mut first, second, 2);
+
+        assert_eq!(first.host_status, HostStatus::Down);
+        assert_eq!(first.tcp_results.len(), 4);
+        assert_eq!(first.open_port_count(), 2);
+        assert_eq!(first.scan_duration_ms, 940);
+        assert_eq!(
+            first.expansion.map(|e| e.to_string()).as_deref(),
+            Some("2 more ports after 2, 1 new open (900ms)")
+        );
+        assert_eq!(first.latency.map(|l| l.samples), Some(4));
+    }
+}
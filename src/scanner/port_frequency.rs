@@ -0,0 +1,214 @@
+//! Port frequency data for top-ports selection
+//!
+//! Ranks TCP and UDP ports by how often they are found open, in the format
+//! of Nmap's `nmap-services` file (`service  port/proto  frequency`). A table
+//! is embedded; `scanner.port_frequency_file` replaces it, e.g. with a full
+//! `nmap-services`. `--top-ports N` and the `top1000` preset pick from it.
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::debug;
+
+const EMBEDDED_TABLE: &str = include_str!("port_frequency.txt");
+
+/// Transport protocol of a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportProtocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for TransportProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportProtocol::Tcp => f.pad("tcp"),
+            TransportProtocol::Udp => f.pad("udp"),
+        }
+    }
+}
+
+impl FromStr for TransportProtocol {
+    type Err = ScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" => Ok(TransportProtocol::Tcp),
+            "udp" => Ok(TransportProtocol::Udp),
+            _ => Err(ScanError::validation_error("protocol", format!("Unknown protocol: {}", s))),
+        }
+    }
+}
+
+/// One ranked port
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortFrequency {
+    pub port: u16,
+    pub protocol: TransportProtocol,
+    pub service: String,
+    /// Share of scanned hosts with the port open, from 0 to 1
+    pub frequency: f64,
+}
+
+/// Ports ranked by open frequency, per protocol
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortFrequencyTable {
+    tcp: Vec<PortFrequency>,
+    udp: Vec<PortFrequency>,
+}
+
+impl PortFrequencyTable {
+    /// Parse `nmap-services` formatted data
+    ///
+    /// Blank lines, `#` comments and protocols other than TCP and UDP (such
+    /// as SCTP) are skipped; lines without a frequency count as 0.
+    pub fn parse(content: &str) -> ScanResult<Self> {
+        let mut table = Self::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || {
+                ScanError::validation_error(
+                    "port_frequency",
+                    format!("Line {}: expected 'service port/protocol [frequency]'", number + 1),
+                )
+            };
+
+            let mut fields = line.split_whitespace();
+            let service = fields.next().ok_or_else(invalid)?;
+            let (port, protocol) = fields.next().and_then(|f| f.split_once('/')).ok_or_else(invalid)?;
+            let Ok(protocol) = protocol.parse::<TransportProtocol>() else {
+                continue;
+            };
+            let port = port.parse().map_err(|_| invalid())?;
+            let frequency = match fields.next() {
+                Some(frequency) => frequency.parse().map_err(|_| invalid())?,
+                None => 0.0,
+            };
+
+            table.ranked_mut(protocol).push(PortFrequency {
+                port,
+                protocol,
+                service: service.to_string(),
+                frequency,
+            });
+        }
+
+        for ranked in [&mut table.tcp, &mut table.udp] {
+            // Stable sort keeps file order among equal frequencies
+            ranked.sort_by(|a, b| b.frequency.total_cmp(&a.frequency));
+            let mut seen = HashSet::new();
+            ranked.retain(|entry| seen.insert(entry.port));
+        }
+        Ok(table)
+    }
+
+    /// The table shipped with nrmap
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_TABLE).expect("embedded port frequency table is valid")
+    }
+
+    /// Load an `nmap-services` style file
+    pub fn from_file(path: impl AsRef<Path>) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to read port frequency file {}: {}", path.display(), e))
+        })?;
+        Self::parse(&content)
+    }
+
+    /// Ranked entries of `protocol`, most frequent first
+    pub fn ranked(&self, protocol: TransportProtocol) -> &[PortFrequency] {
+        match protocol {
+            TransportProtocol::Tcp => &self.tcp,
+            TransportProtocol::Udp => &self.udp,
+        }
+    }
+
+    fn ranked_mut(&mut self, protocol: TransportProtocol) -> &mut Vec<PortFrequency> {
+        match protocol {
+            TransportProtocol::Tcp => &mut self.tcp,
+            TransportProtocol::Udp => &mut self.udp,
+        }
+    }
+
+    /// The `count` most frequently open ports of `protocol`, sorted by number
+    ///
+    /// When the table ranks fewer ports, the rest are the lowest-numbered
+    /// unranked ports, so exactly `count` (at most 65535) are returned.
+    pub fn top_ports(&self, protocol: TransportProtocol, count: usize) -> Vec<u16> {
+        let count = count.min(u16::MAX as usize);
+        let mut ports: Vec<u16> = self.ranked(protocol).iter().take(count).map(|e| e.port).collect();
+        let ranked: HashSet<u16> = ports.iter().copied().collect();
+        let missing = count - ports.len();
+        ports.extend((1..=u16::MAX).filter(|port| !ranked.contains(port)).take(missing));
+        ports.sort_unstable();
+        ports
+    }
+}
+
+fn active_table() -> &'static RwLock<Arc<PortFrequencyTable>> {
+    static TABLE: OnceLock<RwLock<Arc<PortFrequencyTable>>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(Arc::new(PortFrequencyTable::embedded())))
+}
+
+/// Table used by `--top-ports` and the `top1000` preset
+pub fn port_frequencies() -> Arc<PortFrequencyTable> {
+    active_table().read().unwrap().clone()
+}
+
+/// Replace the table used by `--top-ports` and the `top1000` preset
+pub fn install_port_frequencies(table: PortFrequencyTable) {
+    debug!(
+        "Installed port frequency table: {} TCP, {} UDP ports",
+        table.tcp.len(),
+        table.udp.len()
+    );
+    *active_table().write().unwrap() = Arc::new(table);
+}
+
+/// The `count` most frequently open ports of `protocol` in the active table
+pub fn top_ports(protocol: TransportProtocol, count: usize) -> Vec<u16> {
+    port_frequencies().top_ports(protocol, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_ranking() {
+        let table = PortFrequencyTable::embedded();
+        assert_eq!(table.ranked(TransportProtocol::Tcp)[0].port, 80);
+        assert_eq!(table.ranked(TransportProtocol::Udp)[0].service, "ipp");
+        assert_eq!(table.top_ports(TransportProtocol::Tcp, 3), vec![23, 80, 443]);
+
+        let top1000 = table.top_ports(TransportProtocol::Tcp, 1000);
+        assert_eq!(top1000.len(), 1000);
+        assert!(top1000.contains(&3389) && top1000.contains(&65389));
+        // Unranked entries complete the set before any numeric filler
+        assert!(!top1000.contains(&2));
+    }
+
+    #[test]
+    fn test_parse_nmap_services_format() {
+        let table = PortFrequencyTable::parse(
+            "# comment\n\
+             ssh\t22/tcp\t0.182286\t# Secure Shell\n\
+             sctp-thing\t22/sctp\t0.9\n\
+             http\t80/tcp\t0.484143\n\
+             snmp\t161/udp\n",
+        )
+        .unwrap();
+        assert_eq!(table.top_ports(TransportProtocol::Tcp, 1), vec![80]);
+        assert_eq!(table.top_ports(TransportProtocol::Udp, 2), vec![1, 161]);
+        assert!(PortFrequencyTable::parse("http eighty/tcp 0.5").is_err());
+    }
+}
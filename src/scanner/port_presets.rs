@@ -0,0 +1,168 @@
+//! Named port presets
+//!
+//! The built-in presets (`common`, `web`, ...) are fixed, apart from
+//! `top1000`, which follows the port frequency table; organizations add
+//! their own in the `[presets]` table of config.toml, e.g.
+//! `internal-standard = "22,443,3389,8443"`. [`init_library`](crate::init_library)
+//! registers them process-wide, so [`parse_port_preset`](crate::parse_port_preset)
+//! — and with it `--preset`, scan profiles and the Python bindings — resolves
+//! them like any built-in.
+
+use crate::error::{ScanError, ScanResult};
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+use tracing::debug;
+
+/// Names of the built-in presets, in listing order
+pub const BUILTIN_PRESETS: &[&str] = &["common", "top100", "top1000", "web", "mail", "database", "all"];
+
+/// Where a preset was defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetSource {
+    BuiltIn,
+    Config,
+}
+
+impl std::fmt::Display for PresetSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresetSource::BuiltIn => f.pad("built-in"),
+            PresetSource::Config => f.pad("config"),
+        }
+    }
+}
+
+/// A preset and the ports it expands to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortPreset {
+    pub name: String,
+    pub ports: Vec<u16>,
+    pub source: PresetSource,
+}
+
+fn organization_presets() -> &'static RwLock<BTreeMap<String, Vec<u16>>> {
+    static PRESETS: OnceLock<RwLock<BTreeMap<String, Vec<u16>>>> = OnceLock::new();
+    PRESETS.get_or_init(|| RwLock::new(BTreeMap::new()))
+}
+
+/// Ports of a built-in preset
+pub fn builtin_preset(name: &str) -> Option<Vec<u16>> {
+    match name.to_lowercase().as_str() {
+        "common" | "top100" => Some(vec![
+            21, 22, 23, 25, 53, 80, 110, 111, 135, 139,
+            143, 443, 445, 993, 995, 1723, 3306, 3389, 5900, 8080,
+        ]),
+        "web" => Some(vec![80, 443, 8000, 8080, 8443, 8888]),
+        "mail" => Some(vec![25, 110, 143, 465, 587, 993, 995]),
+        "top1000" => Some(super::port_frequency::top_ports(super::port_frequency::TransportProtocol::Tcp, 1000)),
+        "database" => Some(vec![1433, 3306, 5432, 27017, 6379]),
+        "all" => Some((1..=65535).collect()),
+        _ => None,
+    }
+}
+
+/// Replace the organization presets with `definitions` (name to port list)
+///
+/// Every definition is validated first; on error the previous presets stay
+/// in place. Names are case-insensitive and may not shadow a built-in.
+pub fn register_presets(definitions: &BTreeMap<String, String>) -> ScanResult<()> {
+    let mut presets = BTreeMap::new();
+    for (name, ports) in definitions {
+        let name = name.to_lowercase();
+        if builtin_preset(&name).is_some() {
+            return Err(ScanError::validation_error(
+                "presets",
+                format!("Preset '{}' would shadow the built-in preset", name),
+            ));
+        }
+        let ports = crate::parse_port_range(ports).map_err(|e| {
+            ScanError::validation_error("presets", format!("Preset '{}': {}", name, e))
+        })?;
+        presets.insert(name, ports);
+    }
+
+    debug!("Registered {} organization port presets", presets.len());
+    *organization_presets().write().unwrap() = presets;
+    Ok(())
+}
+
+/// Ports of the built-in or organization preset `name`
+pub fn resolve_preset(name: &str) -> Option<Vec<u16>> {
+    builtin_preset(name).or_else(|| organization_presets().read().unwrap().get(&name.to_lowercase()).cloned())
+}
+
+/// Every known preset: built-ins first, then organization presets by name
+pub fn list_presets() -> Vec<PortPreset> {
+    let builtin = BUILTIN_PRESETS.iter().filter_map(|name| {
+        builtin_preset(name).map(|ports| PortPreset {
+            name: name.to_string(),
+            ports,
+            source: PresetSource::BuiltIn,
+        })
+    });
+    let organization: Vec<PortPreset> = organization_presets()
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, ports)| PortPreset {
+            name: name.clone(),
+            ports: ports.clone(),
+            source: PresetSource::Config,
+        })
+        .collect();
+    builtin.chain(organization).collect()
+}
+
+/// Compact form of a sorted port list, the inverse of
+/// [`parse_port_range`](crate::parse_port_range): `[20, 21, 22, 80]` is "20-22,80"
+pub fn format_ports(ports: &[u16]) -> String {
+    let mut runs: Vec<(u16, u16)> = Vec::new();
+    for &port in ports {
+        match runs.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(port) => *end = port,
+            _ => runs.push((port, port)),
+        }
+    }
+    runs.iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_organization_presets() {
+        let definitions = BTreeMap::from([
+            ("Internal-Standard".to_string(), "22,443,3389,8443".to_string()),
+            ("legacy".to_string(), "20-23".to_string()),
+        ]);
+        register_presets(&definitions).unwrap();
+
+        assert_eq!(resolve_preset("internal-standard"), Some(vec![22, 443, 3389, 8443]));
+        assert_eq!(crate::parse_port_preset("LEGACY").unwrap(), vec![20, 21, 22, 23]);
+        assert!(list_presets()
+            .iter()
+            .any(|p| p.name == "legacy" && p.source == PresetSource::Config));
+
+        // Invalid definitions leave the registered presets alone
+        let shadowing = BTreeMap::from([("web".to_string(), "80".to_string())]);
+        assert!(register_presets(&shadowing).is_err());
+        let invalid = BTreeMap::from([("broken".to_string(), "22,no-such-service".to_string())]);
+        assert!(register_presets(&invalid).is_err());
+        assert!(resolve_preset("legacy").is_some());
+        assert!(resolve_preset("broken").is_none());
+    }
+
+    #[test]
+    fn test_format_ports() {
+        assert_eq!(format_ports(&[20, 21, 22, 80, 443, 444]), "20-22,80,443-444");
+        assert_eq!(format_ports(&builtin_preset("all").unwrap()), "1-65535");
+        assert_eq!(format_ports(&[]), "");
+    }
+}
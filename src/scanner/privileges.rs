@@ -0,0 +1,184 @@
+//! Raw socket privilege detection
+//!
+//! Detects at startup whether the process can open raw sockets
+//! (CAP_NET_RAW/root on Unix, Administrator on Windows) and whether
+//! unprivileged ICMP "ping" sockets are available, then maps requested scan
+//! types onto what can actually run.
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::ScanType;
+#[cfg(not(feature = "no-net"))]
+use socket2::{Domain, Protocol, Socket, Type};
+use tracing::{info, warn};
+
+/// How ICMP echo requests can be sent by this process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingMode {
+    /// Raw ICMP socket (privileged)
+    Raw,
+    /// ICMP datagram "ping" socket (unprivileged, Linux/macOS)
+    Unprivileged,
+}
+
+/// Result of the startup capability check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrivilegeStatus {
+    /// Raw sockets can be opened
+    pub raw_sockets: bool,
+    /// Unprivileged ICMP datagram sockets can be opened
+    pub unprivileged_icmp: bool,
+}
+
+impl PrivilegeStatus {
+    /// Probe the current process for raw and ICMP socket capabilities
+    ///
+    /// Opening a socket is the most reliable test: it honours CAP_NET_RAW,
+    /// `net.ipv4.ping_group_range` and Windows Administrator rights alike.
+    #[cfg(not(feature = "no-net"))]
+    pub fn detect() -> Self {
+        let raw_sockets = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).is_ok();
+        let unprivileged_icmp = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4)).is_ok();
+
+        let status = Self {
+            raw_sockets,
+            unprivileged_icmp,
+        };
+        info!(
+            "Privilege check: raw_sockets={}, unprivileged_icmp={}",
+            status.raw_sockets, status.unprivileged_icmp
+        );
+        status
+    }
+
+    /// Status that assumes every capability is available (checks disabled)
+    pub fn assume_privileged() -> Self {
+        Self {
+            raw_sockets: true,
+            unprivileged_icmp: true,
+        }
+    }
+
+    /// Short label for reports: "raw-sockets", "icmp-only" or "unprivileged"
+    pub fn level(&self) -> &'static str {
+        if self.raw_sockets {
+            "raw-sockets"
+        } else if self.unprivileged_icmp {
+            "icmp-only"
+        } else {
+            "unprivileged"
+        }
+    }
+
+    /// Best available way to send ICMP echo requests, if any
+    pub fn ping_mode(&self) -> Option<PingMode> {
+        if self.raw_sockets {
+            Some(PingMode::Raw)
+        } else if self.unprivileged_icmp {
+            Some(PingMode::Unprivileged)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a scan type needs raw sockets
+    pub fn requires_raw(scan_type: ScanType) -> bool {
+        matches!(scan_type, ScanType::TcpSyn)
+    }
+
+    /// Map requested scan types onto ones that can run with current privileges
+    ///
+    /// # Arguments
+    /// * `requested` - Scan types to run
+    /// * `explicit` - The user asked for these scan types directly; privileged
+    ///   types are then an error instead of being downgraded
+    ///
+    /// # Returns
+    /// * `ScanResult<Vec<ScanType>>` - Runnable scan types (SYN downgraded to connect)
+    pub fn resolve_scan_types(&self, requested: &[ScanType], explicit: bool) -> ScanResult<Vec<ScanType>> {
+        let mut resolved = Vec::with_capacity(requested.len());
+
+        for &scan_type in requested {
+            let scan_type = if Self::requires_raw(scan_type) && !self.raw_sockets {
+                if explicit {
+                    return Err(ScanError::permission_denied(format!(
+                        "{:?} scan requires raw sockets (run as root, grant CAP_NET_RAW, \
+                         or use a TCP connect scan)",
+                        scan_type
+                    )));
+                }
+                warn!(
+                    "{:?} scan requires raw socket privileges; downgrading to TCP connect scan",
+                    scan_type
+                );
+                ScanType::TcpConnect
+            } else {
+                scan_type
+            };
+
+            if !resolved.contains(&scan_type) {
+                resolved.push(scan_type);
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Map a configured host discovery method onto one that can run
+    pub fn resolve_discovery_method(&self, method: &str) -> String {
+        match method {
+            "icmp" if self.ping_mode().is_none() => {
+                warn!("ICMP discovery needs raw or ping sockets; downgrading to TCP discovery");
+                "tcp".to_string()
+            }
+            "arp" if !self.raw_sockets => {
+                warn!("ARP discovery requires raw socket privileges; downgrading to TCP discovery");
+                "tcp".to_string()
+            }
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNPRIVILEGED: PrivilegeStatus = PrivilegeStatus {
+        raw_sockets: false,
+        unprivileged_icmp: false,
+    };
+
+    #[test]
+    fn test_downgrade_syn_when_unprivileged() {
+        let resolved = UNPRIVILEGED
+            .resolve_scan_types(&[ScanType::TcpSyn, ScanType::TcpConnect, ScanType::Udp], false)
+            .unwrap();
+        assert_eq!(resolved, vec![ScanType::TcpConnect, ScanType::Udp]);
+    }
+
+    #[test]
+    fn test_explicit_syn_errors_when_unprivileged() {
+        assert!(UNPRIVILEGED.resolve_scan_types(&[ScanType::TcpSyn], true).is_err());
+        assert!(UNPRIVILEGED.resolve_scan_types(&[ScanType::Udp], true).is_ok());
+
+        let privileged = PrivilegeStatus::assume_privileged();
+        assert_eq!(
+            privileged.resolve_scan_types(&[ScanType::TcpSyn], true).unwrap(),
+            vec![ScanType::TcpSyn]
+        );
+    }
+
+    #[test]
+    fn test_discovery_method_downgrade() {
+        assert_eq!(UNPRIVILEGED.resolve_discovery_method("icmp"), "tcp");
+        assert_eq!(UNPRIVILEGED.resolve_discovery_method("arp"), "tcp");
+        assert_eq!(UNPRIVILEGED.resolve_discovery_method("udp"), "udp");
+
+        let ping_only = PrivilegeStatus {
+            raw_sockets: false,
+            unprivileged_icmp: true,
+        };
+        assert_eq!(ping_only.ping_mode(), Some(PingMode::Unprivileged));
+        assert_eq!(ping_only.resolve_discovery_method("icmp"), "icmp");
+    }
+}
@@ -0,0 +1,152 @@
+//! Why a port probe failed
+//!
+//! Every per-port result carries an optional [`ProbeError`] so a `filtered`
+//! or `unknown` port says why it ended up that way: no answer at all, an
+//! ICMP rejection from a firewall, a missing route, or a problem on the
+//! scanning host itself. Reports summarize the classes per scan.
+
+use super::tcp_connect::{ConnectErrorClass, PortStatus};
+use crate::error::ScanError;
+use std::fmt;
+use std::io::ErrorKind;
+
+/// Failure class of a port probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeError {
+    /// No answer before the probe timeout
+    Timeout,
+    /// Connection refused (TCP RST, ICMP port unreachable)
+    Refused,
+    /// Connection reset mid-handshake
+    Reset,
+    /// ICMP communication administratively prohibited (type 3 code 9, 10
+    /// or 13): a firewall rejected the probe
+    AdminProhibited,
+    /// ICMP host or network unreachable, or no local route
+    Unreachable,
+    /// The scanning host refused the operation (raw sockets, local firewall)
+    PermissionDenied,
+    /// Out of descriptors, buffers or ephemeral ports on the scanning host
+    ResourceExhausted,
+    /// Any other failure
+    Other,
+}
+
+impl ProbeError {
+    /// Classify an OS error from a probe socket
+    pub fn from_io(error: &std::io::Error) -> Self {
+        #[cfg(not(feature = "no-net"))]
+        match error.raw_os_error() {
+            Some(libc::ECONNREFUSED) => return Self::Refused,
+            Some(libc::ECONNRESET) | Some(libc::ECONNABORTED) => return Self::Reset,
+            Some(libc::EHOSTUNREACH) | Some(libc::ENETUNREACH) | Some(libc::EHOSTDOWN) => return Self::Unreachable,
+            Some(libc::ETIMEDOUT) => return Self::Timeout,
+            Some(libc::EACCES) | Some(libc::EPERM) => return Self::PermissionDenied,
+            Some(libc::EAGAIN) | Some(libc::EMFILE) | Some(libc::ENFILE) | Some(libc::ENOBUFS)
+            | Some(libc::ENOMEM) | Some(libc::EADDRNOTAVAIL) => return Self::ResourceExhausted,
+            _ => {}
+        }
+        match error.kind() {
+            ErrorKind::ConnectionRefused => Self::Refused,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => Self::Reset,
+            ErrorKind::TimedOut => Self::Timeout,
+            ErrorKind::PermissionDenied => Self::PermissionDenied,
+            ErrorKind::WouldBlock | ErrorKind::AddrNotAvailable | ErrorKind::OutOfMemory => Self::ResourceExhausted,
+            _ => Self::Other,
+        }
+    }
+
+    /// Classify a scanner error that ended a probe
+    pub fn from_scan_error(error: &ScanError) -> Self {
+        match error {
+            ScanError::Io(e) => Self::from_io(e),
+            ScanError::Timeout { .. } => Self::Timeout,
+            ScanError::PermissionDenied { .. } => Self::PermissionDenied,
+            ScanError::ResourceExhausted { .. } => Self::ResourceExhausted,
+            _ => Self::Other,
+        }
+    }
+
+    /// Class of an ICMP destination unreachable (type 3) code
+    pub fn from_icmp_unreachable(code: u8) -> Self {
+        match code {
+            3 => Self::Refused,
+            9 | 10 | 13 => Self::AdminProhibited,
+            _ => Self::Unreachable,
+        }
+    }
+
+    /// Port status a failure of this class implies
+    pub fn status(&self) -> PortStatus {
+        match self {
+            Self::Refused | Self::Reset => PortStatus::Closed,
+            Self::Timeout | Self::AdminProhibited | Self::Unreachable => PortStatus::Filtered,
+            // Failures on the scanning side say nothing about the port
+            Self::PermissionDenied | Self::ResourceExhausted | Self::Other => PortStatus::Unknown,
+        }
+    }
+
+    /// Whether the failure happened on the scanning host rather than the network
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::PermissionDenied | Self::ResourceExhausted)
+    }
+}
+
+impl From<ConnectErrorClass> for ProbeError {
+    fn from(class: ConnectErrorClass) -> Self {
+        match class {
+            ConnectErrorClass::Refused => Self::Refused,
+            ConnectErrorClass::Reset => Self::Reset,
+            ConnectErrorClass::Unreachable => Self::Unreachable,
+            ConnectErrorClass::TimedOut => Self::Timeout,
+            ConnectErrorClass::LocalResources => Self::ResourceExhausted,
+            ConnectErrorClass::Other => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Timeout => "timeout",
+            Self::Refused => "refused",
+            Self::Reset => "reset",
+            Self::AdminProhibited => "admin-prohibited",
+            Self::Unreachable => "unreachable",
+            Self::PermissionDenied => "permission denied",
+            Self::ResourceExhausted => "resource exhaustion",
+            Self::Other => "other",
+        };
+        f.pad(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification() {
+        #[cfg(not(feature = "no-net"))]
+        {
+            let os = |code| ProbeError::from_io(&std::io::Error::from_raw_os_error(code));
+            assert_eq!(os(libc::ECONNREFUSED), ProbeError::Refused);
+            assert_eq!(os(libc::ENETUNREACH), ProbeError::Unreachable);
+            assert_eq!(os(libc::EPERM), ProbeError::PermissionDenied);
+            assert_eq!(os(libc::EMFILE), ProbeError::ResourceExhausted);
+        }
+        assert_eq!(
+            ProbeError::from_scan_error(&ScanError::permission_denied("raw socket")),
+            ProbeError::PermissionDenied
+        );
+        assert_eq!(ProbeError::from_scan_error(&ScanError::timeout(500)), ProbeError::Timeout);
+        assert_eq!(ProbeError::from_icmp_unreachable(13), ProbeError::AdminProhibited);
+        assert_eq!(ProbeError::from_icmp_unreachable(1), ProbeError::Unreachable);
+        assert_eq!(ProbeError::from(ConnectErrorClass::LocalResources), ProbeError::ResourceExhausted);
+
+        assert_eq!(ProbeError::AdminProhibited.status(), PortStatus::Filtered);
+        assert_eq!(ProbeError::ResourceExhausted.status(), PortStatus::Unknown);
+        assert_eq!(serde_json::to_string(&ProbeError::AdminProhibited).unwrap(), "\"admin_prohibited\"");
+    }
+}
@@ -0,0 +1,235 @@
+//! Live scan progress
+//!
+//! The scanner publishes a [`ScanProgress`] snapshot on a `watch` channel
+//! whenever a port probe or a host finishes; the CLI renders it as a progress
+//! bar and library users can subscribe through
+//! [`Scanner::progress`](crate::Scanner::progress). Ports of hosts that are
+//! skipped (down, excluded, scan type unavailable) count as done when the
+//! host finishes, so the totals always converge. A scan with port expansion
+//! runs in two passes, and the counters restart for the second one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Snapshot of a running scan
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ScanProgress {
+    pub targets_done: usize,
+    pub targets_total: usize,
+    /// Port probes finished (one per port and scan type)
+    pub ports_done: u64,
+    pub ports_total: u64,
+    /// Average port probes per second since the scan started
+    pub current_pps: f64,
+    pub elapsed: Duration,
+    /// Estimated time to completion at the current rate
+    pub eta: Option<Duration>,
+    /// Current pass and number of passes (2 with port expansion)
+    pub pass: u8,
+    pub passes: u8,
+}
+
+impl ScanProgress {
+    /// Share of port probes done, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.ports_total == 0 {
+            return if self.targets_total > 0 && self.targets_done >= self.targets_total { 1.0 } else { 0.0 };
+        }
+        (self.ports_done as f64 / self.ports_total as f64).min(1.0)
+    }
+
+    /// Whether every target finished
+    pub fn is_complete(&self) -> bool {
+        self.targets_total > 0 && self.targets_done >= self.targets_total
+    }
+}
+
+impl fmt::Display for ScanProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passes > 1 {
+            write!(f, "pass {}/{}, ", self.pass, self.passes)?;
+        }
+        write!(
+            f,
+            "hosts {}/{}, ports {}/{}, {:.0} pps",
+            self.targets_done, self.targets_total, self.ports_done, self.ports_total, self.current_pps
+        )?;
+        if let Some(eta) = self.eta {
+            write!(f, ", ETA {}", crate::report::time::format_duration_ms(eta.as_millis() as u64))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TrackerState {
+    start: Instant,
+    targets_done: usize,
+    targets_total: usize,
+    ports_done: u64,
+    ports_total: u64,
+    /// Probes ticked per unfinished host, net of re-probe extensions
+    per_host: HashMap<IpAddr, i64>,
+    pass: u8,
+    passes: u8,
+}
+
+/// Counts finished work and publishes snapshots
+#[derive(Debug)]
+pub struct ProgressTracker {
+    sender: watch::Sender<ScanProgress>,
+    state: Mutex<TrackerState>,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self {
+            sender: watch::channel(ScanProgress::default()).0,
+            state: Mutex::new(TrackerState {
+                start: Instant::now(),
+                targets_done: 0,
+                targets_total: 0,
+                ports_done: 0,
+                ports_total: 0,
+                per_host: HashMap::new(),
+                pass: 1,
+                passes: 1,
+            }),
+        }
+    }
+
+    /// Receive progress snapshots; the latest one is always available
+    pub fn subscribe(&self) -> watch::Receiver<ScanProgress> {
+        self.sender.subscribe()
+    }
+
+    /// Current snapshot
+    pub fn snapshot(&self) -> ScanProgress {
+        *self.sender.borrow()
+    }
+
+    /// Start tracking a scan of `targets` hosts with `probes_per_target` each
+    pub fn begin(&self, targets: usize, probes_per_target: u64) {
+        self.begin_pass(1, 1, targets, probes_per_target);
+    }
+
+    /// Start pass `pass` of `passes`, counting its hosts and probes afresh
+    pub fn begin_pass(&self, pass: u8, passes: u8, targets: usize, probes_per_target: u64) {
+        self.update(|state| {
+            *state = TrackerState {
+                start: Instant::now(),
+                targets_done: 0,
+                targets_total: targets,
+                ports_done: 0,
+                ports_total: targets as u64 * probes_per_target,
+                per_host: HashMap::new(),
+                pass,
+                passes,
+            };
+        });
+    }
+
+    /// One port probe against `target` finished
+    pub fn port_done(&self, target: IpAddr) {
+        self.update(|state| {
+            state.ports_done += 1;
+            *state.per_host.entry(target).or_default() += 1;
+        });
+    }
+
+    /// `count` extra probes were scheduled for `target` (re-probes)
+    pub fn extend(&self, target: IpAddr, count: usize) {
+        self.update(|state| {
+            state.ports_total += count as u64;
+            *state.per_host.entry(target).or_default() -= count as i64;
+        });
+    }
+
+    /// `target` finished; its probes that never ran count as done
+    pub fn host_done(&self, target: IpAddr, expected_probes: u64) {
+        self.update(|state| {
+            let ticked = state.per_host.remove(&target).unwrap_or(0).max(0) as u64;
+            state.ports_done += expected_probes.saturating_sub(ticked);
+            state.targets_done += 1;
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut TrackerState)) {
+        let mut state = self.state.lock().unwrap();
+        change(&mut state);
+
+        let elapsed = state.start.elapsed();
+        let current_pps = match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => state.ports_done as f64 / secs,
+            _ => 0.0,
+        };
+        let remaining = state.ports_total.saturating_sub(state.ports_done);
+        let eta = (current_pps > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / current_pps));
+
+        self.sender.send_replace(ScanProgress {
+            targets_done: state.targets_done,
+            targets_total: state.targets_total,
+            ports_done: state.ports_done.min(state.ports_total),
+            ports_total: state.ports_total,
+            current_pps,
+            elapsed,
+            eta,
+            pass: state.pass,
+            passes: state.passes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_converges() {
+        let tracker = ProgressTracker::new();
+        let receiver = tracker.subscribe();
+        let up: IpAddr = "192.0.2.1".parse().unwrap();
+        let down: IpAddr = "192.0.2.2".parse().unwrap();
+
+        tracker.begin(2, 10);
+        assert_eq!(receiver.borrow().ports_total, 20);
+
+        for _ in 0..10 {
+            tracker.port_done(up);
+        }
+        // Two re-probes on the same host
+        tracker.extend(up, 2);
+        tracker.port_done(up);
+        tracker.port_done(up);
+        tracker.host_done(up, 10);
+
+        let halfway = *receiver.borrow();
+        assert_eq!((halfway.targets_done, halfway.ports_done, halfway.ports_total), (1, 12, 22));
+        assert!(!halfway.is_complete());
+
+        // A skipped host still completes its share
+        tracker.host_done(down, 10);
+        let done = tracker.snapshot();
+        assert_eq!(done.ports_done, 22);
+        assert!(done.is_complete());
+        assert_eq!(done.fraction(), 1.0);
+        assert_eq!(done.eta, Some(Duration::ZERO));
+        assert!(done.to_string().starts_with("hosts 2/2"));
+
+        // The expansion pass restarts the counters
+        tracker.begin_pass(2, 2, 1, 100);
+        let expanding = tracker.snapshot();
+        assert_eq!((expanding.targets_total, expanding.ports_done, expanding.ports_total), (1, 0, 100));
+        assert!(expanding.to_string().starts_with("pass 2/2, hosts 0/1"));
+    }
+}
@@ -0,0 +1,207 @@
+//! Why each port got its state, like nmap's `--reason`
+//!
+//! With `port_reasons` (`--reason`) every port result records the answer
+//! that settled it: a SYN-ACK, a RST, a refused connect, a UDP reply, an ICMP
+//! unreachable with its code, or silence after some retries. The JSON report
+//! carries it with each result, and the table and HTML reports list every
+//! port with its reason, which shows where a firewall drops or rejects.
+
+use super::icmp_unreachable::IcmpUnreachable;
+use super::probe_error::ProbeError;
+use super::tcp_connect::{PortStatus, TcpConnectResult};
+use super::tcp_syn::TcpSynResult;
+use super::udp_scan::UdpScanResult;
+use super::CompleteScanResult;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The answer that settled a port's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "kebab-case")]
+pub enum PortReason {
+    /// SYN-ACK in answer to a SYN, or a completed connect handshake
+    SynAck,
+    /// RST in answer to a SYN
+    Reset,
+    /// connect() refused: the kernel saw a RST
+    ConnRefused,
+    /// Connection reset mid-handshake
+    ConnReset,
+    /// Data in answer to a UDP probe
+    UdpResponse,
+    /// ICMP destination unreachable
+    IcmpUnreach { icmp_type: u8, icmp_code: u8 },
+    /// The connect or send failed as unreachable or prohibited, without
+    /// the ICMP message itself
+    HostUnreach,
+    AdminProhibited,
+    /// Nothing came back, after this many retries
+    NoResponse { retries: u32 },
+    /// The probe failed on the scanning host
+    LocalError { error: ProbeError },
+}
+
+impl fmt::Display for PortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortReason::SynAck => f.write_str("syn-ack"),
+            PortReason::Reset => f.write_str("reset"),
+            PortReason::ConnRefused => f.write_str("conn-refused"),
+            PortReason::ConnReset => f.write_str("conn-reset"),
+            PortReason::UdpResponse => f.write_str("udp-response"),
+            PortReason::IcmpUnreach { icmp_type, icmp_code } => {
+                write!(f, "icmp-unreach type {} code {}", icmp_type, icmp_code)
+            }
+            PortReason::HostUnreach => f.write_str("host-unreach"),
+            PortReason::AdminProhibited => f.write_str("admin-prohibited"),
+            PortReason::NoResponse { retries: 0 } => f.write_str("no-response"),
+            PortReason::NoResponse { retries } => write!(f, "no-response after {} retries", retries),
+            PortReason::LocalError { error } => write!(f, "local-error ({})", error),
+        }
+    }
+}
+
+impl From<&IcmpUnreachable> for PortReason {
+    fn from(icmp: &IcmpUnreachable) -> Self {
+        PortReason::IcmpUnreach {
+            icmp_type: icmp.icmp_type,
+            icmp_code: icmp.code,
+        }
+    }
+}
+
+/// Reason for a failed probe that no reply explains
+fn failure_reason(error: Option<ProbeError>, attempts: u32) -> PortReason {
+    match error {
+        Some(ProbeError::Refused) => PortReason::ConnRefused,
+        Some(ProbeError::Reset) => PortReason::ConnReset,
+        Some(ProbeError::Unreachable) => PortReason::HostUnreach,
+        Some(ProbeError::AdminProhibited) => PortReason::AdminProhibited,
+        Some(error @ (ProbeError::PermissionDenied | ProbeError::ResourceExhausted | ProbeError::Other)) => {
+            PortReason::LocalError { error }
+        }
+        Some(ProbeError::Timeout) | None => PortReason::NoResponse {
+            retries: attempts.saturating_sub(1),
+        },
+    }
+}
+
+impl TcpConnectResult {
+    /// Why the port got its status
+    pub fn reason(&self) -> PortReason {
+        match self.status {
+            PortStatus::Open => PortReason::SynAck,
+            _ => failure_reason(self.error, self.attempts),
+        }
+    }
+}
+
+impl TcpSynResult {
+    /// Why the port got its status
+    pub fn reason(&self) -> PortReason {
+        if let Some(ref icmp) = self.icmp {
+            return icmp.into();
+        }
+        match self.flags {
+            Some(flags) if flags.rst => PortReason::Reset,
+            Some(flags) if flags.syn && flags.ack => PortReason::SynAck,
+            _ => failure_reason(self.error, self.attempts),
+        }
+    }
+}
+
+impl UdpScanResult {
+    /// Why the port got its status
+    pub fn reason(&self) -> PortReason {
+        if let Some(ref icmp) = self.icmp {
+            return icmp.into();
+        }
+        match (&self.status, self.error) {
+            (PortStatus::Open, _) => PortReason::UdpResponse,
+            // The kernel turned an ICMP port unreachable into ECONNREFUSED
+            (_, Some(ProbeError::Refused)) => PortReason::IcmpUnreach {
+                icmp_type: if self.target.is_ipv4() { 3 } else { 1 },
+                icmp_code: if self.target.is_ipv4() { 3 } else { 4 },
+            },
+            (_, error) => failure_reason(error, self.attempts),
+        }
+    }
+}
+
+impl CompleteScanResult {
+    /// Record the reason of every port result
+    pub fn record_reasons(&mut self) {
+        for result in &mut self.tcp_results {
+            result.reason = Some(result.reason());
+        }
+        for result in &mut self.syn_results {
+            result.reason = Some(result.reason());
+        }
+        for result in &mut self.udp_results {
+            result.reason = Some(result.reason());
+        }
+    }
+
+    /// Whether any port result carries a reason
+    pub fn has_reasons(&self) -> bool {
+        self.tcp_results.iter().any(|r| r.reason.is_some())
+            || self.syn_results.iter().any(|r| r.reason.is_some())
+            || self.udp_results.iter().any(|r| r.reason.is_some())
+    }
+
+    /// Every port result with a recorded reason, as (port, scan type,
+    /// status, reason), by port
+    pub fn port_reasons(&self) -> Vec<(u16, &'static str, &PortStatus, PortReason)> {
+        let mut reasons: Vec<_> = self
+            .tcp_results
+            .iter()
+            .filter_map(|r| r.reason.map(|reason| (r.port, "connect", &r.status, reason)))
+            .chain(self.syn_results.iter().filter_map(|r| r.reason.map(|reason| (r.port, "syn", &r.status, reason))))
+            .chain(self.udp_results.iter().filter_map(|r| r.reason.map(|reason| (r.port, "udp", &r.status, reason))))
+            .collect();
+        reasons.sort_by_key(|&(port, scan_type, ..)| (port, scan_type));
+        reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::tcp_syn::TcpFlags;
+    use std::net::IpAddr;
+
+    #[test]
+    fn test_reasons() {
+        let target: IpAddr = "192.0.2.1".parse().unwrap();
+        let mut connect = TcpConnectResult::failed(target, 23, ProbeError::Refused);
+        connect.attempts = 1;
+        assert_eq!(connect.reason().to_string(), "conn-refused");
+
+        let mut syn = TcpSynResult::failed(target, 22, ProbeError::Timeout);
+        syn.attempts = 4;
+        assert_eq!(syn.reason().to_string(), "no-response after 3 retries");
+        syn.status = PortStatus::Open;
+        syn.error = None;
+        syn.flags = Some(TcpFlags { syn: true, ack: true, rst: false, fin: false });
+        assert_eq!(syn.reason(), PortReason::SynAck);
+        syn.icmp = IcmpUnreachable::new("198.51.100.1".parse().unwrap(), 3, 13);
+        assert_eq!(syn.reason().to_string(), "icmp-unreach type 3 code 13");
+
+        let udp = UdpScanResult::failed(target, 161, ProbeError::Refused);
+        assert_eq!(udp.reason(), PortReason::IcmpUnreach { icmp_type: 3, icmp_code: 3 });
+        let local = UdpScanResult::failed(target, 53, ProbeError::ResourceExhausted);
+        assert_eq!(local.reason().to_string(), "local-error (resource exhaustion)");
+
+        let json = serde_json::to_string(&PortReason::NoResponse { retries: 2 }).unwrap();
+        assert_eq!(json, r#"{"code":"no-response","retries":2}"#);
+
+        let mut result = CompleteScanResult::unscanned(target, crate::scanner::host_discovery::HostStatus::Up, Default::default());
+        result.tcp_results = vec![connect];
+        result.udp_results = vec![udp];
+        assert!(!result.has_reasons());
+        result.record_reasons();
+        let reasons = result.port_reasons();
+        assert_eq!((reasons[0].0, reasons[0].1), (23, "connect"));
+        assert_eq!(reasons[1].3.to_string(), "icmp-unreach type 3 code 3");
+    }
+}
@@ -0,0 +1,233 @@
+//! Resource accounting for scan runs
+//!
+//! The probe engines count sockets opened and packets and bytes sent and
+//! received into process-wide counters. A [`ResourceMeter`] started with a
+//! scan snapshots them, samples the number of open file descriptors while
+//! the scan runs, and turns the difference into a [`ResourceUsage`] for the
+//! report, so operators can size agents and set `ulimit -n` from real runs.
+//!
+//! Byte counts are at the IP layer: whole packets on raw sockets, and
+//! payload plus IP, TCP or UDP headers (without options) on kernel sockets.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often open file descriptors are counted during a scan
+const FD_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+static SOCKETS_OPENED: AtomicU64 = AtomicU64::new(0);
+static PACKETS_SENT: AtomicU64 = AtomicU64::new(0);
+static PACKETS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+/// Count a socket opened by a probe engine
+pub fn socket_opened() {
+    SOCKETS_OPENED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a packet of `bytes` sent
+pub fn packet_sent(bytes: usize) {
+    PACKETS_SENT.fetch_add(1, Ordering::Relaxed);
+    BYTES_SENT.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Count a packet of `bytes` received
+pub fn packet_received(bytes: usize) {
+    PACKETS_RECEIVED.fetch_add(1, Ordering::Relaxed);
+    BYTES_RECEIVED.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// IP and UDP header bytes of a datagram to or from `target`
+pub fn udp_overhead(target: IpAddr) -> usize {
+    ip_header_len(target) + 8
+}
+
+/// Bytes of a TCP segment without options or payload to or from `target`
+pub fn tcp_segment_len(target: IpAddr) -> usize {
+    ip_header_len(target) + 20
+}
+
+fn ip_header_len(target: IpAddr) -> usize {
+    match target {
+        IpAddr::V4(_) => 20,
+        IpAddr::V6(_) => 40,
+    }
+}
+
+/// Resources a scan run used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub sockets_opened: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Most file descriptors the process held open at once during the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_open_fds: Option<u64>,
+    /// Peak resident memory of the process (`VmHWM`), in KiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory_kb: Option<u64>,
+}
+
+impl ResourceUsage {
+    fn counters() -> Self {
+        Self {
+            sockets_opened: SOCKETS_OPENED.load(Ordering::Relaxed),
+            packets_sent: PACKETS_SENT.load(Ordering::Relaxed),
+            packets_received: PACKETS_RECEIVED.load(Ordering::Relaxed),
+            bytes_sent: BYTES_SENT.load(Ordering::Relaxed),
+            bytes_received: BYTES_RECEIVED.load(Ordering::Relaxed),
+            peak_open_fds: None,
+            peak_memory_kb: None,
+        }
+    }
+
+    /// Combine the usage of two runs, e.g. the jobs of one agent
+    pub fn add(&mut self, other: &ResourceUsage) {
+        self.sockets_opened += other.sockets_opened;
+        self.packets_sent += other.packets_sent;
+        self.packets_received += other.packets_received;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        self.peak_open_fds = self.peak_open_fds.max(other.peak_open_fds);
+        self.peak_memory_kb = self.peak_memory_kb.max(other.peak_memory_kb);
+    }
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sockets, {} packets / {} bytes sent, {} packets / {} bytes received",
+            self.sockets_opened, self.packets_sent, self.bytes_sent, self.packets_received, self.bytes_received
+        )?;
+        if let Some(fds) = self.peak_open_fds {
+            write!(f, ", peak {} open fds", fds)?;
+        }
+        if let Some(kb) = self.peak_memory_kb {
+            write!(f, ", peak memory {} MiB", kb / 1024)?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures the resources of one scan run
+#[derive(Debug)]
+pub struct ResourceMeter {
+    baseline: ResourceUsage,
+    peak_fds: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    sampler: Option<JoinHandle<()>>,
+}
+
+impl ResourceMeter {
+    /// Snapshot the counters and start sampling open file descriptors
+    pub fn start() -> Self {
+        let peak_fds = Arc::new(AtomicU64::new(open_fds().unwrap_or(0)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampler = open_fds().is_some().then(|| {
+            let (peak_fds, stop) = (Arc::clone(&peak_fds), Arc::clone(&stop));
+            std::thread::Builder::new()
+                .name("nrmap-fd-sampler".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if let Some(fds) = open_fds() {
+                            peak_fds.fetch_max(fds, Ordering::Relaxed);
+                        }
+                        std::thread::sleep(FD_SAMPLE_INTERVAL);
+                    }
+                })
+                .ok()
+        });
+        Self {
+            baseline: ResourceUsage::counters(),
+            peak_fds,
+            stop,
+            sampler: sampler.flatten(),
+        }
+    }
+
+    /// Stop sampling and report what the run used
+    pub fn finish(mut self) -> ResourceUsage {
+        self.stop_sampler();
+        let now = ResourceUsage::counters();
+        ResourceUsage {
+            sockets_opened: now.sockets_opened - self.baseline.sockets_opened,
+            packets_sent: now.packets_sent - self.baseline.packets_sent,
+            packets_received: now.packets_received - self.baseline.packets_received,
+            bytes_sent: now.bytes_sent - self.baseline.bytes_sent,
+            bytes_received: now.bytes_received - self.baseline.bytes_received,
+            peak_open_fds: self.sampler_ran().then(|| self.peak_fds.load(Ordering::Relaxed)),
+            peak_memory_kb: peak_memory_kb(),
+        }
+    }
+
+    fn sampler_ran(&self) -> bool {
+        self.peak_fds.load(Ordering::Relaxed) > 0
+    }
+
+    fn stop_sampler(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(sampler) = self.sampler.take() {
+            let _ = sampler.join();
+        }
+    }
+}
+
+impl Drop for ResourceMeter {
+    fn drop(&mut self) {
+        self.stop_sampler();
+    }
+}
+
+/// Number of file descriptors the process holds open (Linux only)
+pub fn open_fds() -> Option<u64> {
+    std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count() as u64)
+}
+
+/// Peak resident memory of the process in KiB (Linux only)
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_hwm(&status)
+}
+
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_counts_run() {
+        let meter = ResourceMeter::start();
+        let target: IpAddr = "192.0.2.1".parse().unwrap();
+        socket_opened();
+        packet_sent(12 + udp_overhead(target));
+        packet_received(tcp_segment_len(target));
+        let usage = meter.finish();
+        // Other tests count into the same counters concurrently
+        assert!(usage.sockets_opened >= 1);
+        assert!(usage.bytes_sent >= 40 && usage.bytes_received >= 40);
+        if cfg!(target_os = "linux") {
+            assert!(usage.peak_open_fds.is_some_and(|fds| fds > 0));
+            assert!(usage.peak_memory_kb.is_some());
+        }
+
+        assert_eq!(parse_vm_hwm("VmPeak:\t  20000 kB\nVmHWM:\t    6120 kB\n"), Some(6120));
+        let mut total = usage;
+        total.add(&usage);
+        assert_eq!(total.packets_sent, usage.packets_sent * 2);
+    }
+}
@@ -0,0 +1,195 @@
+//! Retry policy for port probes
+//!
+//! Each scanner's `retries` and `retry_delay_ms` set how often a failed probe
+//! is repeated; the [`RetryPolicy`] shapes the delays between attempts
+//! (exponential backoff with jitter, capped per delay and per port) and can
+//! run a second pass over ports that ended up `filtered` (`open|filtered` for
+//! UDP), since a single dropped packet is enough to misclassify a port.
+
+use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+use crate::scanner::tcp_syn::TcpSynResult;
+use crate::scanner::udp_scan::UdpScanResult;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How failed probes are repeated
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Factor applied to the retry delay after every attempt (1.0 keeps it fixed)
+    pub backoff_multiplier: f64,
+    /// Upper bound for a single retry delay
+    pub max_delay_ms: u64,
+    /// Randomize each delay by up to this fraction (0.0-1.0) either way
+    pub jitter: f64,
+    /// Stop retrying a port once this long has passed since its first probe
+    pub max_elapsed_ms: Option<u64>,
+    /// Probe ports classified filtered a second time after the first pass
+    pub reprobe_filtered: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            backoff_multiplier: 1.0,
+            max_delay_ms: 10_000,
+            jitter: 0.0,
+            max_elapsed_ms: None,
+            reprobe_filtered: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1 for the first retry)
+    pub fn delay(&self, base_delay_ms: u64, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let delay_ms = (base_delay_ms as f64 * self.backoff_multiplier.powi(exponent)).min(self.max_delay_ms as f64);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 + rand::thread_rng().gen_range(-jitter..=jitter)
+        } else {
+            1.0
+        };
+        Duration::from_millis((delay_ms * factor).round() as u64)
+    }
+
+    /// Whether another attempt fits in the time budget of one port
+    pub fn allows_retry(&self, elapsed: Duration) -> bool {
+        self.max_elapsed_ms.is_none_or(|max| elapsed < Duration::from_millis(max))
+    }
+
+    /// Check the policy values
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.backoff_multiplier.is_finite() || self.backoff_multiplier < 1.0 {
+            return Err("retry_policy.backoff_multiplier must be at least 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("retry_policy.jitter must be between 0.0 and 1.0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A per-port result that can be re-probed
+pub trait ProbeOutcome {
+    fn port(&self) -> u16;
+    fn status(&self) -> &PortStatus;
+    fn attempts_mut(&mut self) -> &mut u32;
+}
+
+macro_rules! impl_probe_outcome {
+    ($($result:ty),*) => {
+        $(impl ProbeOutcome for $result {
+            fn port(&self) -> u16 {
+                self.port
+            }
+
+            fn status(&self) -> &PortStatus {
+                &self.status
+            }
+
+            fn attempts_mut(&mut self) -> &mut u32 {
+                &mut self.attempts
+            }
+        })*
+    };
+}
+
+impl_probe_outcome!(TcpConnectResult, TcpSynResult, UdpScanResult);
+
+/// Ports a second pass should probe again
+pub fn filtered_ports<T: ProbeOutcome>(results: &[T]) -> Vec<u16> {
+    results
+        .iter()
+        .filter(|r| *r.status() == PortStatus::Filtered)
+        .map(ProbeOutcome::port)
+        .collect()
+}
+
+/// Fold second-pass results into the first pass
+///
+/// A port that answered this time takes the new result; either way its
+/// attempt count covers both passes. Returns how many ports were resolved.
+pub fn merge_reprobes<T: ProbeOutcome>(results: &mut [T], reprobed: Vec<T>) -> usize {
+    let positions: HashMap<u16, usize> = results.iter().enumerate().map(|(i, r)| (r.port(), i)).collect();
+    let mut resolved = 0;
+
+    for mut second in reprobed {
+        let Some(&index) = positions.get(&second.port()) else {
+            continue;
+        };
+        let attempts = *results[index].attempts_mut() + *second.attempts_mut();
+        if *second.status() != PortStatus::Filtered {
+            results[index] = second;
+            resolved += 1;
+        }
+        *results[index].attempts_mut() = attempts;
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delays() {
+        let policy = RetryPolicy {
+            backoff_multiplier: 2.0,
+            max_delay_ms: 500,
+            ..RetryPolicy::default()
+        };
+        let delays: Vec<u64> = (1..=5).map(|retry| policy.delay(100, retry).as_millis() as u64).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..50 {
+            let delay = jittered.delay(100, 2).as_millis();
+            assert!((100..=300).contains(&delay));
+        }
+
+        let bounded = RetryPolicy {
+            max_elapsed_ms: Some(1000),
+            ..RetryPolicy::default()
+        };
+        assert!(bounded.allows_retry(Duration::from_millis(999)));
+        assert!(!bounded.allows_retry(Duration::from_millis(1000)));
+        assert!(RetryPolicy { jitter: 1.5, ..RetryPolicy::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_reprobes() {
+        let result = |port, status| TcpConnectResult {
+            target: "192.0.2.1".parse().unwrap(),
+            port,
+            status,
+            response_time_ms: None,
+            banner: None,
+            evasion: None,
+            attempts: 2,
+            error: None,
+            service: None,
+            error_class: None,
+            confidence: None,
+            reason: None,
+        };
+        let mut results = vec![
+            result(22, PortStatus::Open),
+            result(80, PortStatus::Filtered),
+            result(443, PortStatus::Filtered),
+        ];
+        assert_eq!(filtered_ports(&results), vec![80, 443]);
+
+        let reprobed = vec![result(80, PortStatus::Closed), result(443, PortStatus::Filtered)];
+        assert_eq!(merge_reprobes(&mut results, reprobed), 1);
+
+        assert_eq!(results[1].status, PortStatus::Closed);
+        assert_eq!(results[1].attempts, 4);
+        assert_eq!(results[2].status, PortStatus::Filtered);
+        assert_eq!(results[2].attempts, 4);
+        assert_eq!(results[0].attempts, 2);
+    }
+}
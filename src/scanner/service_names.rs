@@ -0,0 +1,160 @@
+//! Service names for ports
+//!
+//! Maps `port/protocol` to IANA service names and back, so reports can show
+//! `22/tcp ssh` and port specs can say `ssh,http,https`. A table in
+//! `/etc/services` format is embedded; `scanner.services_file` merges a
+//! local file of the same format over it.
+
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::port_frequency::TransportProtocol;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::debug;
+
+const EMBEDDED_TABLE: &str = include_str!("service_names.txt");
+
+/// Service names by port and protocol
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceNames {
+    by_port: HashMap<(u16, TransportProtocol), String>,
+    /// Names and aliases, lowercased
+    by_name: HashMap<String, Vec<(u16, TransportProtocol)>>,
+}
+
+impl ServiceNames {
+    /// Parse `/etc/services` formatted data: `name port/protocol [aliases...]`
+    ///
+    /// The first entry for a port and protocol names it; comments and
+    /// protocols other than TCP and UDP are skipped.
+    pub fn parse(content: &str) -> ScanResult<Self> {
+        let mut names = Self::default();
+        names.merge_str(content)?;
+        Ok(names)
+    }
+
+    /// The table shipped with nrmap
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_TABLE).expect("embedded service names table is valid")
+    }
+
+    /// Add entries from a services file; its names replace existing ones
+    pub fn merge_file(&mut self, path: impl AsRef<Path>) -> ScanResult<()> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to read services file {}: {}", path.display(), e))
+        })?;
+        let overrides = Self::parse(&content)?;
+        for (key, name) in overrides.by_port {
+            self.by_port.insert(key, name);
+        }
+        for (name, ports) in overrides.by_name {
+            self.by_name.insert(name, ports);
+        }
+        Ok(())
+    }
+
+    fn merge_str(&mut self, content: &str) -> ScanResult<()> {
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || {
+                ScanError::validation_error(
+                    "services",
+                    format!("Line {}: expected 'name port/protocol [aliases]'", number + 1),
+                )
+            };
+
+            let mut fields = line.split_whitespace();
+            let name = fields.next().ok_or_else(invalid)?;
+            let (port, protocol) = fields.next().and_then(|f| f.split_once('/')).ok_or_else(invalid)?;
+            let Ok(protocol) = protocol.parse::<TransportProtocol>() else {
+                continue;
+            };
+            let port: u16 = port.parse().map_err(|_| invalid())?;
+
+            self.by_port.entry((port, protocol)).or_insert_with(|| name.to_string());
+            for alias in std::iter::once(name).chain(fields) {
+                let ports = self.by_name.entry(alias.to_lowercase()).or_default();
+                if !ports.contains(&(port, protocol)) {
+                    ports.push((port, protocol));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Service name of `port` over `protocol`
+    pub fn name(&self, port: u16, protocol: TransportProtocol) -> Option<&str> {
+        self.by_port.get(&(port, protocol)).map(String::as_str)
+    }
+
+    /// Port a service name or alias refers to, preferring its TCP assignment
+    pub fn port(&self, name: &str) -> Option<u16> {
+        let ports = self.by_name.get(&name.to_lowercase())?;
+        ports
+            .iter()
+            .find(|(_, protocol)| *protocol == TransportProtocol::Tcp)
+            .or_else(|| ports.first())
+            .map(|(port, _)| *port)
+    }
+}
+
+fn active_table() -> &'static RwLock<Arc<ServiceNames>> {
+    static TABLE: OnceLock<RwLock<Arc<ServiceNames>>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(Arc::new(ServiceNames::embedded())))
+}
+
+/// Table used for report output and port specs
+pub fn service_names() -> Arc<ServiceNames> {
+    active_table().read().unwrap().clone()
+}
+
+/// Replace the table used for report output and port specs
+pub fn install_service_names(names: ServiceNames) {
+    debug!("Installed service names table: {} entries", names.by_port.len());
+    *active_table().write().unwrap() = Arc::new(names);
+}
+
+/// Service name of `port` over `protocol` in the active table
+pub fn service_name(port: u16, protocol: TransportProtocol) -> Option<String> {
+    service_names().name(port, protocol).map(String::from)
+}
+
+/// Port the service `name` is assigned in the active table
+pub fn service_port(name: &str) -> Option<u16> {
+    service_names().port(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_names() {
+        let names = ServiceNames::embedded();
+        assert_eq!(names.name(22, TransportProtocol::Tcp), Some("ssh"));
+        assert_eq!(names.name(53, TransportProtocol::Udp), Some("domain"));
+        assert_eq!(names.port("HTTPS"), Some(443));
+        // Aliases resolve too
+        assert_eq!(names.port("www"), Some(80));
+        assert_eq!(names.port("no-such-service"), None);
+    }
+
+    #[test]
+    fn test_merge_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("services");
+        std::fs::write(&path, "intranet-web\t8080/tcp\tportal\nlegacy-app 4711/udp # ours\n").unwrap();
+
+        let mut names = ServiceNames::parse("http-alt 8080/tcp\nssh 22/tcp\nbroken 22/sctp\n").unwrap();
+        names.merge_file(&path).unwrap();
+        assert_eq!(names.name(8080, TransportProtocol::Tcp), Some("intranet-web"));
+        assert_eq!(names.name(22, TransportProtocol::Tcp), Some("ssh"));
+        assert_eq!(names.port("portal"), Some(8080));
+        assert_eq!(names.port("legacy-app"), Some(4711));
+        assert!(ServiceNames::parse("ssh twenty-two/tcp").is_err());
+    }
+}
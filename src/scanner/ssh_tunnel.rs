@@ -0,0 +1,429 @@
+//! TCP connect scanning through an SSH bastion
+//!
+//! Segments only a bastion host can reach are probed by asking the bastion
+//! to open a `direct-tcpip` channel to each target port: the bastion makes
+//! the connect, and whether the channel opens says whether the port is open.
+//! SSH reports failed channels only as "connect failed", without the
+//! underlying error, so a failure before the probe timeout counts as closed
+//! and no answer within it as filtered.
+//!
+//! Sessions are russh connections kept in a small pool; each probe
+//! borrows one for its channel.
+
+use crate::error::{ScanError, ScanResult};
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "no-net"))]
+use russh::client::{self, Handle};
+#[cfg(not(feature = "no-net"))]
+use russh::keys::agent::client::AgentClient;
+#[cfg(not(feature = "no-net"))]
+use russh::keys::agent::AgentIdentity;
+#[cfg(not(feature = "no-net"))]
+use russh::keys::{PrivateKeyWithHashAlg, PublicKeyOrCertificate};
+#[cfg(not(feature = "no-net"))]
+use russh::ChannelOpenFailure;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{debug, info};
+
+/// Bastion connection settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshTunnelConfig {
+    /// Bastion as `[user@]host[:port]`
+    pub bastion: String,
+    /// Private key to authenticate with; the SSH agent is used when unset
+    pub identity_file: Option<PathBuf>,
+    /// known_hosts file listing the bastion's host key
+    /// (`~/.ssh/known_hosts` when unset)
+    pub known_hosts: Option<PathBuf>,
+    /// SSH connections to the bastion that probes are spread over
+    pub sessions: usize,
+    /// Timeout for connecting and authenticating to the bastion (milliseconds)
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for SshTunnelConfig {
+    fn default() -> Self {
+        Self {
+            bastion: String::new(),
+            identity_file: None,
+            known_hosts: None,
+            sessions: 4,
+            connect_timeout_ms: 10000,
+        }
+    }
+}
+
+impl SshTunnelConfig {
+    /// Tunnel through `bastion`, given as `[user@]host[:port]`
+    pub fn new<S: Into<String>>(bastion: S) -> Self {
+        Self {
+            bastion: bastion.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Authenticate with this private key instead of the SSH agent
+    pub fn with_identity_file(mut self, path: PathBuf) -> Self {
+        self.identity_file = Some(path);
+        self
+    }
+
+    /// User, host and port of the bastion
+    ///
+    /// The user defaults to `$USER` and the port to 22; IPv6 hosts with a
+    /// port are written in brackets, e.g. `admin@[2001:db8::1]:2222`.
+    pub fn endpoint(&self) -> ScanResult<(String, String, u16)> {
+        let invalid = |reason: &str| ScanError::validation_error("ssh_tunnel.bastion", format!("'{}' {}", self.bastion, reason));
+        let (user, address) = match self.bastion.rsplit_once('@') {
+            Some((user, address)) => (user.to_string(), address),
+            None => (std::env::var("USER").unwrap_or_else(|_| "root".to_string()), self.bastion.as_str()),
+        };
+
+        let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+            let (host, port) = rest.split_once(']').ok_or_else(|| invalid("has an unclosed '['"))?;
+            (host, port.strip_prefix(':'))
+        } else if address.matches(':').count() == 1 {
+            let (host, port) = address.split_once(':').expect("one colon");
+            (host, Some(port))
+        } else {
+            // A bare IPv6 address or a plain host name
+            (address, None)
+        };
+
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| invalid("has an invalid port"))?,
+            None => 22,
+        };
+        if host.is_empty() || user.is_empty() {
+            return Err(invalid("needs a host and user"));
+        }
+        Ok((user, host.to_string(), port))
+    }
+}
+
+/// Outcome of one connect made by the bastion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunnelProbe {
+    /// The channel opened: the bastion connected
+    Open,
+    /// The bastion's connect failed before the timeout
+    Closed,
+    /// No answer before the timeout
+    TimedOut,
+}
+
+/// Pool of SSH sessions to a bastion, opening `direct-tcpip` channels
+#[cfg(not(feature = "no-net"))]
+pub struct SshTunnel {
+    config: SshTunnelConfig,
+    idle: Mutex<Vec<Handle<BastionKey>>>,
+    slots: Semaphore,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl std::fmt::Debug for SshTunnel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshTunnel").field("bastion", &self.config.bastion).finish()
+    }
+}
+
+#[cfg(not(feature = "no-net"))]
+impl SshTunnel {
+    /// Create a tunnel; sessions are opened on first use
+    pub fn new(config: SshTunnelConfig) -> Self {
+        let slots = Semaphore::new(config.sessions.max(1));
+        Self {
+            config,
+            idle: Mutex::new(Vec::new()),
+            slots,
+        }
+    }
+
+    /// Bastion settings
+    pub fn config(&self) -> &SshTunnelConfig {
+        &self.config
+    }
+
+    /// Ask the bastion to connect to `target:port`
+    ///
+    /// Errors mean the bastion could not be used (unreachable, refused
+    /// authentication, forwarding disabled), not that the port is closed.
+    pub async fn probe(self: &Arc<Self>, target: IpAddr, port: u16, timeout: Duration) -> ScanResult<TunnelProbe> {
+        // The semaphore is never closed
+        let _slot = self.slots.acquire().await.ok();
+        let pooled = self.idle.lock().unwrap().pop().filter(|session| !session.is_closed());
+        let session = match pooled {
+            Some(session) => session,
+            None => self.connect().await?,
+        };
+
+        let opened = session.channel_open_direct_tcpip(target.to_string(), port as u32, "127.0.0.1", 0);
+        let outcome = match tokio::time::timeout(timeout, opened).await {
+            Ok(Ok(channel)) => {
+                let _ = channel.close().await;
+                TunnelProbe::Open
+            }
+            Ok(Err(russh::Error::ChannelOpenFailure(ChannelOpenFailure::AdministrativelyProhibited))) => {
+                return Err(ScanError::permission_denied(format!(
+                    "TCP forwarding on SSH bastion {}",
+                    self.config.bastion
+                )));
+            }
+            Ok(Err(russh::Error::ChannelOpenFailure(_))) => TunnelProbe::Closed,
+            Ok(Err(e)) => return Err(ssh_error(&self.config.bastion, "open a channel", e)),
+            // The channel may still open later; the session is not reused
+            Err(_) => return Ok(TunnelProbe::TimedOut),
+        };
+        self.idle.lock().unwrap().push(session);
+        Ok(outcome)
+    }
+
+    async fn connect(&self) -> ScanResult<Handle<BastionKey>> {
+        let bastion = &self.config.bastion;
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        tokio::time::timeout(timeout, self.connect_session())
+            .await
+            .map_err(|_| ScanError::network(format!("SSH bastion {}: timed out connecting", bastion)))?
+    }
+
+    async fn connect_session(&self) -> ScanResult<Handle<BastionKey>> {
+        let (user, host, port) = self.config.endpoint()?;
+        let bastion = &self.config.bastion;
+        let known_hosts = self
+            .config
+            .known_hosts
+            .clone()
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".ssh/known_hosts")))
+            .ok_or_else(|| ScanError::validation_error("ssh_tunnel.known_hosts", "no known_hosts file"))?;
+
+        debug!("Connecting to SSH bastion {} ({}:{})", bastion, host, port);
+        let rejected = Arc::new(Mutex::new(None));
+        let handler = BastionKey {
+            bastion: bastion.clone(),
+            host: host.clone(),
+            port,
+            known_hosts,
+            rejected: Arc::clone(&rejected),
+        };
+        let mut session = match client::connect(Arc::new(client::Config::default()), (host.as_str(), port), handler).await {
+            Ok(session) => session,
+            Err(e) => {
+                let rejected = rejected.lock().unwrap().take();
+                return Err(rejected.unwrap_or_else(|| ssh_error(bastion, "handshake", e)));
+            }
+        };
+
+        let action = format!("authenticate as {}", user);
+        let authenticated = match self.config.identity_file {
+            Some(ref path) => {
+                let key = russh::keys::load_secret_key(path, None)
+                    .map_err(|e| ScanError::validation_error("ssh_tunnel.identity_file", format!("{}: {}", path.display(), e)))?;
+                let hash = session.best_supported_rsa_hash().await.map_err(|e| ssh_error(bastion, &action, e))?;
+                let key = PrivateKeyWithHashAlg::new(Arc::new(key), hash.flatten());
+                session.authenticate_publickey(&user, key).await.map_err(|e| ssh_error(bastion, &action, e))?.success()
+            }
+            None => authenticate_with_agent(&mut session, &user).await.map_err(|e| {
+                ScanError::network(format!("SSH bastion {}: could not {}: {}", bastion, action, e))
+            })?,
+        };
+        if !authenticated {
+            return Err(ScanError::permission_denied(format!("SSH bastion {} as {}", bastion, user)));
+        }
+        info!("Connected to SSH bastion {} as {}", bastion, user);
+        Ok(session)
+    }
+}
+
+/// Try each key the SSH agent holds until the bastion accepts one
+#[cfg(not(feature = "no-net"))]
+async fn authenticate_with_agent(session: &mut Handle<BastionKey>, user: &str) -> Result<bool, String> {
+    let mut agent = AgentClient::connect_env().await.map_err(|e| format!("no SSH agent: {}", e))?;
+    let identities = agent.request_identities().await.map_err(|e| e.to_string())?;
+    let hash = session.best_supported_rsa_hash().await.map_err(|e| e.to_string())?.flatten();
+    for identity in identities {
+        let AgentIdentity::PublicKey { key, .. } = identity else {
+            continue;
+        };
+        let result = session.authenticate_publickey_with(user, key, hash, &mut agent).await;
+        if result.map_err(|e| e.to_string())?.success() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Session handler checking the bastion's host key against known_hosts
+#[cfg(not(feature = "no-net"))]
+struct BastionKey {
+    bastion: String,
+    host: String,
+    port: u16,
+    known_hosts: PathBuf,
+    /// Why the key was refused, reported instead of the handshake error
+    rejected: Arc<Mutex<Option<ScanError>>>,
+}
+
+#[cfg(not(feature = "no-net"))]
+impl client::Handler for BastionKey {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &PublicKeyOrCertificate) -> Result<bool, Self::Error> {
+        let PublicKeyOrCertificate::PublicKey { key, .. } = key else {
+            *self.rejected.lock().unwrap() = Some(ScanError::network(format!(
+                "SSH bastion {} sent a host certificate; only known_hosts keys are accepted",
+                self.bastion
+            )));
+            return Ok(false);
+        };
+        let path = self.known_hosts.display();
+        let rejection = match russh::keys::check_known_hosts_path(&self.host, self.port, key, &self.known_hosts) {
+            Ok(true) => return Ok(true),
+            Ok(false) => format!(
+                "Host key of SSH bastion {} is not in {}; add it with ssh-keyscan first",
+                self.bastion, path
+            ),
+            Err(russh::keys::Error::KeyChanged { line }) => format!(
+                "Host key of SSH bastion {} does not match {} (line {})",
+                self.bastion, path, line
+            ),
+            Err(e) => format!("Could not check the host key of {}: {}", self.bastion, e),
+        };
+        *self.rejected.lock().unwrap() = Some(ScanError::network(rejection));
+        Ok(false)
+    }
+}
+
+#[cfg(not(feature = "no-net"))]
+fn ssh_error(bastion: &str, action: &str, error: russh::Error) -> ScanError {
+    ScanError::network(format!("SSH bastion {}: could not {}: {}", bastion, action, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bastion_endpoint() {
+        let endpoint = |bastion: &str| SshTunnelConfig::new(bastion).endpoint();
+        assert_eq!(endpoint("ops@jump.example.com").unwrap(), ("ops".into(), "jump.example.com".into(), 22));
+        assert_eq!(endpoint("ops@10.0.0.1:2222").unwrap(), ("ops".into(), "10.0.0.1".into(), 2222));
+        assert_eq!(endpoint("ops@[2001:db8::1]:2222").unwrap(), ("ops".into(), "2001:db8::1".into(), 2222));
+        assert_eq!(endpoint("ops@2001:db8::1").unwrap(), ("ops".into(), "2001:db8::1".into(), 22));
+        assert!(endpoint("ops@jump:ssh").is_err());
+        assert!(endpoint("ops@[2001:db8::1").is_err());
+        assert!(endpoint("ops@").is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_unreachable_bastion_is_an_error() {
+        // Nothing listens on the discard port of localhost
+        let tunnel = Arc::new(SshTunnel::new(SshTunnelConfig {
+            connect_timeout_ms: 500,
+            ..SshTunnelConfig::new("ops@127.0.0.1:9")
+        }));
+        let target = IpAddr::from([10, 0, 0, 5]);
+        assert!(tunnel.probe(target, 22, Duration::from_millis(100)).await.is_err());
+    }
+
+    /// Bastion answering channels by target port: 22 opens, 23 fails to
+    /// connect, 25 is prohibited and anything else never answers
+    #[cfg(not(feature = "no-net"))]
+    #[derive(Default)]
+    struct Bastion {
+        pending: Vec<russh::server::ChannelOpenHandle>,
+    }
+
+    #[cfg(not(feature = "no-net"))]
+    impl russh::server::Handler for Bastion {
+        type Error = russh::Error;
+
+        async fn auth_publickey(&mut self, _: &str, _: &russh::keys::PublicKey) -> Result<russh::server::Auth, Self::Error> {
+            Ok(russh::server::Auth::Accept)
+        }
+
+        async fn channel_open_direct_tcpip(
+            &mut self,
+            _: russh::Channel<russh::server::Msg>,
+            _: &str,
+            port: u32,
+            _: &str,
+            _: u32,
+            reply: russh::server::ChannelOpenHandle,
+            _: &mut russh::server::Session,
+        ) -> Result<(), Self::Error> {
+            match port {
+                22 => reply.accept().await,
+                23 => reply.reject(ChannelOpenFailure::ConnectFailed).await,
+                25 => reply.reject(ChannelOpenFailure::AdministrativelyProhibited).await,
+                _ => self.pending.push(reply),
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "no-net"))]
+    async fn test_channel_outcomes() {
+        use russh::keys::ssh_key::private::Ed25519Keypair;
+        use russh::keys::ssh_key::LineEnding;
+        use russh::keys::PrivateKey;
+
+        let host_key = PrivateKey::from(Ed25519Keypair::from_seed(&[1; 32]));
+        let user_key = PrivateKey::from(Ed25519Keypair::from_seed(&[2; 32]));
+        let config = Arc::new(russh::server::Config {
+            keys: vec![host_key.clone()],
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let config = Arc::clone(&config);
+                tokio::spawn(async move {
+                    if let Ok(session) = russh::server::run_stream(config, socket, Bastion::default()).await {
+                        let _ = session.await;
+                    }
+                });
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let identity = dir.path().join("id_ed25519");
+        let known_hosts = dir.path().join("known_hosts");
+        std::fs::write(&identity, user_key.to_openssh(LineEnding::LF).unwrap().as_bytes()).unwrap();
+        let entry = host_key.public_key().to_openssh().unwrap();
+        std::fs::write(&known_hosts, format!("[127.0.0.1]:{} {}\n", port, entry)).unwrap();
+
+        let config = SshTunnelConfig {
+            known_hosts: Some(known_hosts),
+            sessions: 1,
+            ..SshTunnelConfig::new(format!("ops@127.0.0.1:{}", port)).with_identity_file(identity)
+        };
+        let tunnel = Arc::new(SshTunnel::new(config.clone()));
+        let target = IpAddr::from([10, 0, 0, 5]);
+        let timeout = Duration::from_millis(300);
+        assert_eq!(tunnel.probe(target, 22, timeout).await.unwrap(), TunnelProbe::Open);
+        assert_eq!(tunnel.probe(target, 23, timeout).await.unwrap(), TunnelProbe::Closed);
+        assert_eq!(tunnel.probe(target, 80, timeout).await.unwrap(), TunnelProbe::TimedOut);
+        // A new session replaces the one abandoned after the timeout
+        assert_eq!(tunnel.probe(target, 22, timeout).await.unwrap(), TunnelProbe::Open);
+        let prohibited = tunnel.probe(target, 25, timeout).await.unwrap_err();
+        assert!(prohibited.to_string().contains("forwarding"), "{}", prohibited);
+
+        // A bastion missing from known_hosts is refused before authenticating
+        let stranger = dir.path().join("empty_known_hosts");
+        std::fs::write(&stranger, "").unwrap();
+        let tunnel = Arc::new(SshTunnel::new(SshTunnelConfig {
+            known_hosts: Some(stranger),
+            ..config
+        }));
+        let refused = tunnel.probe(target, 22, timeout).await.unwrap_err();
+        assert!(refused.to_string().contains("ssh-keyscan"), "{}", refused);
+    }
+}
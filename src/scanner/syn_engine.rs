@@ -0,0 +1,594 @@
+//! Split sender/receiver engine for SYN scans
+//!
+//! Instead of one task per port that sends a probe and waits out its own
+//! timeout, a SYN scan runs a single sender, paced by the scan's rate limiter
+//! and throttle, while the capture thread acts as the receiver. The receiver
+//! matches SYN-ACK, RST and ICMP unreachable replies against a [`ProbeTable`]
+//! of in-flight probes. The table is indexed by the sequence number each
+//! probe was sent with, so settling a probe is a bounds check and a
+//! compare-and-swap: no lock, no map and no timer per port. The sender waits
+//! one timeout per pass and resends whatever is still unanswered.
+//!
+//! Stateless scans drop the table too: each probe's sequence number is a
+//! [`SynCookie`], a keyed SipHash of its endpoints and the scan id, and the
+//! receiver validates replies by recomputing it.
+
+use crate::error::{ScanError, ScanResult};
+use crate::packet::capture::ProbeKey;
+use crate::packet::crafting::TcpFlags as CraftFlags;
+use crate::packet::parser::ParsedPacketRef;
+use crate::scanner::icmp_unreachable::IcmpUnreachable;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::resources;
+use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::tcp_syn::TcpFlags;
+use siphasher::sip::SipHasher24;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::collections::VecDeque;
+use std::hash::Hasher;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+
+const PENDING: u8 = 0;
+const SYN_ACK: u8 = 1;
+const RESET: u8 = 2;
+const UNREACHABLE: u8 = 3;
+
+const FLAG_SYN: u8 = 0x01;
+const FLAG_ACK: u8 = 0x02;
+const FLAG_RST: u8 = 0x04;
+const FLAG_FIN: u8 = 0x08;
+
+/// What, if anything, answered a probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeReply {
+    /// No reply yet
+    Pending,
+    /// SYN-ACK: the port is open
+    SynAck,
+    /// RST: the port is closed
+    Reset,
+    /// ICMP destination unreachable, from the target or a device on the path
+    Unreachable(IcmpUnreachable),
+}
+
+impl ProbeReply {
+    /// Port status the reply implies; unanswered probes are filtered
+    pub fn status(&self) -> PortStatus {
+        match self {
+            Self::SynAck => PortStatus::Open,
+            Self::Reset => PortStatus::Closed,
+            Self::Pending => PortStatus::Filtered,
+            Self::Unreachable(icmp) => icmp.error(TransportProtocol::Tcp).status(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProbeSlot {
+    port: u16,
+    state: AtomicU8,
+    /// TCP flags of the reply, as `FLAG_*` bits
+    flags: AtomicU8,
+    /// When the probe was last sent, in microseconds since the table's epoch
+    sent_us: AtomicU64,
+    /// Round trip of the reply, in microseconds
+    rtt_us: AtomicU64,
+    attempts: AtomicU32,
+    /// Whether the probe holds in-flight permits
+    holding: AtomicBool,
+    icmp: OnceLock<IcmpUnreachable>,
+}
+
+/// In-flight SYN probes to one target, indexed by sequence number
+///
+/// The probe for `ports[i]` is sent with sequence number `base + i`, where
+/// `base` is random per table, so a reply's acknowledgment number (or the
+/// sequence number quoted in an ICMP error) leads straight to its slot. The
+/// target address and port are checked too, so stray or spoofed replies do
+/// not settle a probe.
+#[derive(Debug)]
+pub struct ProbeTable {
+    target: IpAddr,
+    base: u32,
+    slots: Box<[ProbeSlot]>,
+    epoch: Instant,
+    unanswered: AtomicUsize,
+    settled: Notify,
+    permits: Vec<Arc<Semaphore>>,
+    /// Probes holding permits, in the order they were sent
+    in_flight: Mutex<VecDeque<usize>>,
+}
+
+impl ProbeTable {
+    /// Table for probing `ports` on `target`
+    pub fn new(target: IpAddr, ports: &[u16]) -> Self {
+        Self::with_base(target, ports, rand::random())
+    }
+
+    fn with_base(target: IpAddr, ports: &[u16], base: u32) -> Self {
+        Self {
+            target,
+            base,
+            slots: ports.iter().map(|&port| ProbeSlot { port, ..Default::default() }).collect(),
+            epoch: Instant::now(),
+            unanswered: AtomicUsize::new(ports.len()),
+            settled: Notify::new(),
+            permits: Vec::new(),
+            in_flight: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Hold a permit of each semaphore while a probe is in flight
+    ///
+    /// Permits are handed back as soon as the receiver settles the probe, or
+    /// once the probe has gone unanswered for the timeout.
+    pub fn with_permits(mut self, permits: Vec<Arc<Semaphore>>) -> Self {
+        self.permits = permits;
+        self
+    }
+
+    /// The probed host
+    pub fn target(&self) -> IpAddr {
+        self.target
+    }
+
+    /// Number of probes
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether the table has no probes
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Port probed at `index`
+    pub fn port(&self, index: usize) -> u16 {
+        self.slots[index].port
+    }
+
+    /// Sequence number the probe at `index` is sent with
+    pub fn sequence(&self, index: usize) -> u32 {
+        self.base.wrapping_add(index as u32)
+    }
+
+    /// Wait for in-flight permits for the probe at `index`
+    ///
+    /// While waiting, probes unanswered for `timeout` hand their permits
+    /// back, so filtered ports cannot hold the sender up. Returns false,
+    /// holding nothing, once `cancel` fires.
+    pub async fn acquire(&self, index: usize, timeout: Duration, cancel: &CancellationToken) -> bool {
+        let slot = &self.slots[index];
+        if slot.holding.load(Ordering::Acquire) {
+            return true;
+        }
+        let mut held: Vec<SemaphorePermit<'_>> = Vec::with_capacity(self.permits.len());
+        for permits in &self.permits {
+            loop {
+                let next_expiry = self.expire(timeout).unwrap_or(timeout);
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => return false,
+                    // The semaphores are never closed
+                    permit = permits.acquire() => {
+                        held.extend(permit.ok());
+                        break;
+                    }
+                    _ = tokio::time::sleep(next_expiry) => {}
+                }
+            }
+        }
+        held.into_iter().for_each(SemaphorePermit::forget);
+        slot.holding.store(true, Ordering::Release);
+        true
+    }
+
+    /// Hand back the permits of probes sent at least `timeout` ago
+    ///
+    /// Returns how long until the next probe in flight times out.
+    fn expire(&self, timeout: Duration) -> Option<Duration> {
+        let now = self.elapsed_us();
+        let timeout_us = timeout.as_micros() as u64;
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(&index) = in_flight.front() {
+            let slot = &self.slots[index];
+            if slot.holding.load(Ordering::Acquire) {
+                let deadline = slot.sent_us.load(Ordering::Acquire) + timeout_us;
+                if deadline > now {
+                    return Some(Duration::from_micros(deadline - now));
+                }
+                self.release(slot);
+            }
+            in_flight.pop_front();
+        }
+        None
+    }
+
+    fn release(&self, slot: &ProbeSlot) {
+        if slot.holding.swap(false, Ordering::AcqRel) {
+            for permits in &self.permits {
+                permits.add_permits(1);
+            }
+        }
+    }
+
+    /// Record that the probe at `index` was (re)sent just now
+    pub fn mark_sent(&self, index: usize) {
+        let slot = &self.slots[index];
+        slot.sent_us.store(self.elapsed_us(), Ordering::Release);
+        slot.attempts.fetch_add(1, Ordering::Relaxed);
+        if slot.holding.load(Ordering::Acquire) {
+            self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).push_back(index);
+        }
+    }
+
+    fn elapsed_us(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64
+    }
+
+    /// Settle the probe a captured reply answers
+    ///
+    /// Called by the receiver for every captured packet. Returns whether the
+    /// packet answered a probe that was still unanswered.
+    pub fn record(&self, packet: &ParsedPacketRef<'_>) -> bool {
+        let Some(slot) = ProbeKey::from_response_ref(packet).and_then(|key| self.lookup(&key)) else {
+            return false;
+        };
+        let Some((reply, flags)) = classify(packet) else {
+            return false;
+        };
+
+        let state = match reply {
+            ProbeReply::SynAck => SYN_ACK,
+            ProbeReply::Reset => RESET,
+            ProbeReply::Unreachable(unreachable) => {
+                // Only read once the state says UNREACHABLE
+                let _ = slot.icmp.set(unreachable);
+                UNREACHABLE
+            }
+            ProbeReply::Pending => return false,
+        };
+
+        // Retransmitted probes may be answered twice; the first reply wins
+        if slot.state.compare_exchange(PENDING, state, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return false;
+        }
+        slot.flags.store(flags, Ordering::Relaxed);
+        let sent = slot.sent_us.load(Ordering::Acquire);
+        slot.rtt_us.store(self.elapsed_us().saturating_sub(sent).max(1), Ordering::Relaxed);
+        self.release(slot);
+        if self.unanswered.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.settled.notify_one();
+        }
+        true
+    }
+
+    fn lookup(&self, key: &ProbeKey) -> Option<&ProbeSlot> {
+        let slot = self.slots.get(key.seq.wrapping_sub(self.base) as usize)?;
+        (key.ip == self.target && key.port == slot.port).then_some(slot)
+    }
+
+    /// Indices of probes without a reply
+    pub fn unanswered(&self) -> Vec<usize> {
+        (0..self.slots.len())
+            .filter(|&index| self.slots[index].state.load(Ordering::Acquire) == PENDING)
+            .collect()
+    }
+
+    /// Wait until every probe is answered or `timeout` passes
+    ///
+    /// # Returns
+    /// * `bool` - True if every probe was answered
+    pub async fn wait_settled(&self, timeout: Duration) -> bool {
+        let settled = async {
+            loop {
+                let notified = self.settled.notified();
+                if self.unanswered.load(Ordering::Acquire) == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, settled).await.is_ok()
+    }
+
+    /// Hand back the permits of probes that are still unanswered
+    pub fn release_unanswered(&self) {
+        for slot in self.slots.iter() {
+            self.release(slot);
+        }
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Reply recorded for the probe at `index`
+    pub fn reply(&self, index: usize) -> ProbeReply {
+        let slot = &self.slots[index];
+        match slot.state.load(Ordering::Acquire) {
+            SYN_ACK => ProbeReply::SynAck,
+            RESET => ProbeReply::Reset,
+            UNREACHABLE => slot.icmp.get().copied().map_or(ProbeReply::Pending, ProbeReply::Unreachable),
+            _ => ProbeReply::Pending,
+        }
+    }
+
+    /// TCP flags of the reply to the probe at `index`, if it was a TCP reply
+    pub fn flags(&self, index: usize) -> Option<TcpFlags> {
+        flags_from_bits(self.slots[index].flags.load(Ordering::Relaxed))
+    }
+
+    /// Round trip of the reply to the probe at `index`
+    pub fn rtt(&self, index: usize) -> Option<Duration> {
+        match self.slots[index].rtt_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Probes sent for `index`, including resends
+    pub fn attempts(&self, index: usize) -> u32 {
+        self.slots[index].attempts.load(Ordering::Relaxed)
+    }
+}
+
+/// Keyed SipHash-2-4 of a probe's endpoints, used as its sequence number
+///
+/// The hash covers the target address and port, the local port and the scan
+/// id under a key drawn per scanner, so a reply acknowledging `cookie + 1`
+/// (or an ICMP error quoting `cookie`) provably answers one of this scan's
+/// probes without anything being remembered about the probe.
+#[derive(Debug, Clone, Copy)]
+pub struct SynCookie {
+    key: (u64, u64),
+    scan_id: u64,
+}
+
+impl SynCookie {
+    /// Cookies for scan `scan_id` under a fresh random key
+    pub fn new(scan_id: u64) -> Self {
+        Self::with_key(rand::random(), rand::random(), scan_id)
+    }
+
+    fn with_key(key0: u64, key1: u64, scan_id: u64) -> Self {
+        Self { key: (key0, key1), scan_id }
+    }
+
+    /// Sequence number of the probe from `source_port` to `target:port`
+    pub fn sequence(&self, target: IpAddr, port: u16, source_port: u16) -> u32 {
+        let mut hasher = SipHasher24::new_with_keys(self.key.0, self.key.1);
+        match target {
+            IpAddr::V4(v4) => hasher.write(&v4.octets()),
+            IpAddr::V6(v6) => hasher.write(&v6.octets()),
+        }
+        hasher.write_u16(port);
+        hasher.write_u16(source_port);
+        hasher.write_u64(self.scan_id);
+        hasher.finish() as u32
+    }
+
+    /// Validate a captured reply to a probe sent from `source_port`
+    ///
+    /// # Returns
+    /// * `Option<(u16, ProbeReply, Option<TcpFlags>)>` - The probed port, the
+    ///   reply and its TCP flags, or None if the packet answers no probe
+    pub fn check(&self, packet: &ParsedPacketRef<'_>, source_port: u16) -> Option<(u16, ProbeReply, Option<TcpFlags>)> {
+        let key = ProbeKey::from_response_ref(packet)?;
+        if self.sequence(key.ip, key.port, source_port) != key.seq {
+            return None;
+        }
+        let (reply, flags) = classify(packet)?;
+        Some((key.port, reply, flags_from_bits(flags)))
+    }
+}
+
+/// What a captured packet says about the probe it answers, with its TCP
+/// flags as `FLAG_*` bits
+fn classify(packet: &ParsedPacketRef<'_>) -> Option<(ProbeReply, u8)> {
+    match (&packet.tcp_info, &packet.icmp_info) {
+        (Some(tcp), _) => match (tcp.flags.rst, tcp.flags.syn && tcp.flags.ack) {
+            (true, _) => Some((ProbeReply::Reset, flag_bits(&tcp.flags))),
+            (false, true) => Some((ProbeReply::SynAck, flag_bits(&tcp.flags))),
+            (false, false) => None,
+        },
+        (None, Some(icmp)) => IcmpUnreachable::new(packet.source_ip, icmp.icmp_type, icmp.code)
+            .map(|unreachable| (ProbeReply::Unreachable(unreachable), 0)),
+        (None, None) => None,
+    }
+}
+
+fn flags_from_bits(bits: u8) -> Option<TcpFlags> {
+    (bits != 0).then(|| {
+        TcpFlags::from_packet(bits & FLAG_SYN != 0, bits & FLAG_ACK != 0, bits & FLAG_RST != 0, bits & FLAG_FIN != 0)
+    })
+}
+
+fn flag_bits(flags: &CraftFlags) -> u8 {
+    [(flags.syn, FLAG_SYN), (flags.ack, FLAG_ACK), (flags.rst, FLAG_RST), (flags.fin, FLAG_FIN)]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |bits, (_, bit)| bits | bit)
+}
+
+/// Sends crafted probes for the engine
+pub trait ProbeTransmitter: Send {
+    /// Send a complete IP packet to `destination`
+    fn transmit(&mut self, packet: &[u8], destination: IpAddr) -> ScanResult<()>;
+}
+
+/// Transmitter writing complete IP packets to raw sockets
+///
+/// One socket per address family is opened on first use.
+#[derive(Debug, Default)]
+pub struct RawTransmitter {
+    v4: Option<Socket>,
+    v6: Option<Socket>,
+}
+
+impl RawTransmitter {
+    /// Create a transmitter; sockets are opened lazily
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn socket(&mut self, destination: IpAddr) -> ScanResult<&Socket> {
+        let (socket, domain) = match destination {
+            IpAddr::V4(_) => (&mut self.v4, Domain::IPV4),
+            IpAddr::V6(_) => (&mut self.v6, Domain::IPV6),
+        };
+        if socket.is_none() {
+            // IPPROTO_RAW: the packet carries its own IP header
+            let opened = Socket::new(domain, Type::RAW, Some(Protocol::from(libc::IPPROTO_RAW))).map_err(|e| {
+                match e.kind() {
+                    std::io::ErrorKind::PermissionDenied => ScanError::permission_denied("Raw socket send"),
+                    _ => ScanError::Io(e),
+                }
+            })?;
+            resources::socket_opened();
+            *socket = Some(opened);
+        }
+        Ok(socket.as_ref().expect("socket opened above"))
+    }
+}
+
+impl ProbeTransmitter for RawTransmitter {
+    fn transmit(&mut self, packet: &[u8], destination: IpAddr) -> ScanResult<()> {
+        let address = SockAddr::from(SocketAddr::new(destination, 0));
+        self.socket(destination)?.send_to(packet, &address)?;
+        resources::packet_sent(packet.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::crafting::{PacketBuilder, TcpPacket};
+    use crate::packet::parser::PacketParser;
+    use std::net::Ipv4Addr;
+
+    const TARGET: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10));
+
+    fn reply(table: &ProbeTable, index: usize, flags: CraftFlags, source: IpAddr) -> Vec<u8> {
+        let tcp = TcpPacket {
+            source_port: table.port(index),
+            dest_port: 40000,
+            sequence: 7,
+            acknowledgment: table.sequence(index).wrapping_add(1),
+            flags,
+            window: 1024,
+            urgent_pointer: 0,
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+        PacketBuilder::new()
+            .source(source)
+            .destination(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)))
+            .build_tcp(&tcp)
+            .unwrap()
+    }
+
+    fn record(table: &ProbeTable, packet: &[u8]) -> bool {
+        table.record(&PacketParser::new(false).parse_ref(packet).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_replies_settle_probes_once() {
+        let table = ProbeTable::with_base(TARGET, &[22, 80, 443], u32::MAX - 1);
+        (0..3).for_each(|index| table.mark_sent(index));
+
+        let rst_ack = CraftFlags { rst: true, ack: true, ..Default::default() };
+        assert!(record(&table, &reply(&table, 0, CraftFlags::syn_ack(), TARGET)));
+        assert!(record(&table, &reply(&table, 2, rst_ack, TARGET)));
+        // A duplicate answer to a resent probe is ignored
+        assert!(!record(&table, &reply(&table, 0, rst_ack, TARGET)));
+        assert_eq!(table.reply(0), ProbeReply::SynAck);
+        assert_eq!(table.reply(2), ProbeReply::Reset);
+        assert_eq!(table.flags(2), Some(TcpFlags::from_packet(false, true, true, false)));
+        assert!(table.rtt(0).is_some());
+        assert_eq!(table.unanswered(), vec![1]);
+        assert!(!table.wait_settled(Duration::from_millis(10)).await);
+
+        assert!(record(&table, &reply(&table, 1, CraftFlags::syn_ack(), TARGET)));
+        assert!(table.wait_settled(Duration::from_secs(1)).await);
+    }
+
+    #[test]
+    fn test_mismatched_replies_are_ignored() {
+        let table = ProbeTable::new(TARGET, &[80, 443]);
+        let mut wrong_port = reply(&table, 0, CraftFlags::syn_ack(), TARGET);
+        // Source port of the reply: 443 answering the probe sent to 80
+        wrong_port[20..22].copy_from_slice(&443u16.to_be_bytes());
+        let other_host = reply(&table, 0, CraftFlags::syn_ack(), IpAddr::V4(Ipv4Addr::new(192, 0, 2, 99)));
+        let bare_ack = reply(&table, 1, CraftFlags { ack: true, ..Default::default() }, TARGET);
+
+        for packet in [wrong_port, other_host, bare_ack] {
+            assert!(!record(&table, &packet));
+        }
+        assert_eq!(table.unanswered(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_cookie_validates_replies_without_state() {
+        let cookie = SynCookie::with_key(1, 2, 42);
+        let table = ProbeTable::new(TARGET, &[443]);
+        let answer = |sequence: u32| {
+            let mut packet = reply(&table, 0, CraftFlags::syn_ack(), TARGET);
+            // Acknowledgment number of the reply, at TCP offset 8
+            packet[28..32].copy_from_slice(&sequence.wrapping_add(1).to_be_bytes());
+            packet
+        };
+        let check = |packet: &[u8]| cookie.check(&PacketParser::new(false).parse_ref(packet).unwrap(), 40000);
+
+        let sequence = cookie.sequence(TARGET, 443, 40000);
+        let (port, reply, flags) = check(&answer(sequence)).unwrap();
+        assert_eq!((port, reply), (443, ProbeReply::SynAck));
+        assert_eq!(flags, Some(TcpFlags::from_packet(true, true, false, false)));
+
+        // Another scan, port or local port yields another cookie
+        assert_ne!(SynCookie::with_key(1, 2, 43).sequence(TARGET, 443, 40000), sequence);
+        assert_ne!(cookie.sequence(TARGET, 444, 40000), sequence);
+        assert!(check(&answer(cookie.sequence(TARGET, 443, 40001))).is_none());
+        assert!(check(&answer(sequence.wrapping_add(1))).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_permits_released_on_reply() {
+        let permits = Arc::new(Semaphore::new(2));
+        let table = ProbeTable::new(TARGET, &[80, 443]).with_permits(vec![Arc::clone(&permits)]);
+        let cancel = CancellationToken::new();
+        let timeout = Duration::from_secs(60);
+        assert!(table.acquire(0, timeout, &cancel).await);
+        assert!(table.acquire(1, timeout, &cancel).await);
+        assert_eq!(permits.available_permits(), 0);
+
+        assert!(record(&table, &reply(&table, 0, CraftFlags::syn_ack(), TARGET)));
+        assert_eq!(permits.available_permits(), 1);
+        table.release_unanswered();
+        assert_eq!(permits.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_probes_release_permits_after_timeout() {
+        let permits = Arc::new(Semaphore::new(1));
+        let table = ProbeTable::new(TARGET, &[80, 443, 8080]).with_permits(vec![Arc::clone(&permits)]);
+        let cancel = CancellationToken::new();
+        let timeout = Duration::from_millis(20);
+
+        // Port 80 never answers; its permit comes back after the timeout
+        assert!(table.acquire(0, timeout, &cancel).await);
+        table.mark_sent(0);
+        let waited = tokio::time::timeout(Duration::from_secs(5), table.acquire(1, timeout, &cancel)).await;
+        assert_eq!(waited.ok(), Some(true));
+        table.mark_sent(1);
+
+        // A cancelled wait gives up without holding a permit
+        cancel.cancel();
+        assert!(!table.acquire(2, Duration::from_secs(60), &cancel).await);
+        table.release_unanswered();
+        assert_eq!(permits.available_permits(), 1);
+    }
+}
@@ -0,0 +1,351 @@
+//! Target expansion and scan scope estimates
+//!
+//! Target lists may mix single addresses with CIDR blocks. A block expands to
+//! every address it covers, so one mistyped prefix length (`/8` for `/24`)
+//! turns a small job into millions of hosts; [`ScanScope`] summarises what a
+//! run would cover and roughly how long it could take before it starts.
+//!
+//! Target lists are read one entry per line. Lines may also be JSON, as
+//! resolvers such as dnsx print them, so their output can be piped in
+//! directly.
+
+use crate::config::ScannerConfig;
+use crate::error::{ScanError, ScanResult};
+use crate::report::time::format_duration_ms;
+use crate::scanner::ScanType;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use tracing::warn;
+
+/// Largest number of host bits a single block may expand (a /8 in IPv4)
+pub const MAX_EXPANSION_BITS: u32 = 24;
+
+/// An address block such as `10.0.0.0/24`; a plain address is a block of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpNetwork {
+    /// First address of the block
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl IpNetwork {
+    /// Prefix length in bits
+    pub fn prefix(&self) -> u32 {
+        self.prefix
+    }
+
+    /// Number of host bits the block spans
+    pub fn host_bits(&self) -> u32 {
+        width(&self.network) - self.prefix
+    }
+
+    /// Whether `ip` falls inside the block
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let mask = |width: u32| if self.prefix == 0 { 0 } else { u128::MAX << (width - self.prefix) };
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => (u32::from(ip) as u128 & mask(32)) == u32::from(net) as u128,
+            (IpAddr::V6(net), IpAddr::V6(ip)) => (u128::from(ip) & mask(128)) == u128::from(net),
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for IpNetwork {
+    type Err = ScanError;
+
+    fn from_str(spec: &str) -> ScanResult<Self> {
+        let spec = spec.trim();
+        let Some((addr, prefix)) = spec.split_once('/') else {
+            let network: IpAddr = spec
+                .parse()
+                .map_err(|_| ScanError::invalid_target(spec, "Invalid IP address"))?;
+            return Ok(Self { network, prefix: width(&network) });
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| ScanError::invalid_target(spec, "Invalid network address"))?;
+        let width = width(&addr);
+        let prefix: u32 = prefix
+            .parse()
+            .ok()
+            .filter(|p| *p <= width)
+            .ok_or_else(|| ScanError::invalid_target(spec, format!("Prefix length must be 0-{}", width)))?;
+
+        let host_mask = if prefix == 0 { u128::MAX } else { (1u128 << (width - prefix)) - 1 };
+        let network = match addr {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from((u32::from(v4) as u128 & !host_mask) as u32)),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !host_mask)),
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl TryFrom<String> for IpNetwork {
+    type Error = ScanError;
+
+    fn try_from(spec: String) -> ScanResult<Self> {
+        spec.parse()
+    }
+}
+
+impl From<IpNetwork> for String {
+    fn from(network: IpNetwork) -> Self {
+        network.to_string()
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+fn width(addr: &IpAddr) -> u32 {
+    if addr.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+/// Expand one target: a plain address or a CIDR block such as `10.0.0.0/24`
+pub fn expand_target(spec: &str) -> ScanResult<Vec<IpAddr>> {
+    let block: IpNetwork = spec.parse()?;
+    let host_bits = block.host_bits();
+    if host_bits > MAX_EXPANSION_BITS {
+        return Err(ScanError::invalid_target(
+            spec.trim(),
+            format!("Block is too large to expand (more than /{} host bits)", MAX_EXPANSION_BITS),
+        ));
+    }
+
+    let count = 1u128 << host_bits;
+    let targets = match block.network {
+        IpAddr::V4(v4) => {
+            let base = u32::from(v4) as u128;
+            (0..count).map(|i| IpAddr::V4(Ipv4Addr::from((base + i) as u32))).collect()
+        }
+        IpAddr::V6(v6) => {
+            let base = u128::from(v6);
+            (0..count).map(|i| IpAddr::V6(Ipv6Addr::from(base + i))).collect()
+        }
+    };
+    Ok(targets)
+}
+
+/// Parse a target list: one address or CIDR block per line, blank lines and
+/// `#` comments skipped
+///
+/// A line may also be JSON: a string, or an object whose `ip` field or `a`
+/// and `aaaa` arrays hold the addresses (its `host` is used when it is an
+/// address itself). Objects without any address, such as unresolved names,
+/// are skipped with a warning.
+pub fn parse_target_list(content: &str) -> ScanResult<Vec<IpAddr>> {
+    let mut targets = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(['{', '"']) {
+            targets.extend(expand_target(line)?);
+            continue;
+        }
+
+        let value: serde_json::Value = serde_json::from_str(line)
+            .map_err(|e| ScanError::invalid_target(line, format!("Invalid JSON target: {}", e)))?;
+        let specs = json_target_specs(&value);
+        if specs.is_empty() {
+            warn!("Skipping target record without an address: {}", line);
+        }
+        for spec in specs {
+            targets.extend(expand_target(spec)?);
+        }
+    }
+    Ok(targets)
+}
+
+/// Addresses named by one JSON target record
+fn json_target_specs(value: &serde_json::Value) -> Vec<&str> {
+    if let Some(spec) = value.as_str() {
+        return vec![spec];
+    }
+    let mut specs: Vec<&str> = ["a", "aaaa"]
+        .iter()
+        .filter_map(|field| value.get(field).and_then(|v| v.as_array()))
+        .flatten()
+        .chain(value.get("ip"))
+        .filter_map(|v| v.as_str())
+        .collect();
+    if specs.is_empty() {
+        specs.extend(value.get("host").and_then(|v| v.as_str()).filter(|host| host.parse::<IpAddr>().is_ok()));
+    }
+    specs
+}
+
+/// What a run is about to scan
+#[derive(Debug, Clone)]
+pub struct ScanScope {
+    pub targets: usize,
+    pub ports: usize,
+    pub scan_types: Vec<ScanType>,
+    /// Worst-case duration, assuming every probe waits for its full timeout
+    pub estimated_duration: Duration,
+}
+
+impl ScanScope {
+    /// Describe a run of `ports` x `scan_types` against `targets` hosts
+    pub fn new(targets: usize, ports: usize, scan_types: &[ScanType], config: &ScannerConfig) -> Self {
+        let host_rounds = (targets as u64).div_ceil(config.max_concurrent_targets.max(1) as u64);
+        let port_rounds = (ports as u64).div_ceil(config.max_concurrent_scans.max(1) as u64);
+        let timeout_ms: u64 = scan_types
+            .iter()
+            .map(|scan_type| match scan_type {
+                ScanType::TcpConnect => config.tcp_connect.timeout_ms,
+                ScanType::TcpSyn => config.tcp_syn.timeout_ms,
+                ScanType::Udp => config.udp.timeout_ms,
+            })
+            .sum();
+        let mut estimated_ms = host_rounds.saturating_mul(port_rounds).saturating_mul(timeout_ms);
+
+        // So does a run-wide cap on probes in flight
+        if config.max_concurrent_probes_total > 0 {
+            let probe_rounds = ((targets as u64) * (ports as u64)).div_ceil(config.max_concurrent_probes_total as u64);
+            estimated_ms = estimated_ms.max(probe_rounds.saturating_mul(timeout_ms));
+        }
+
+        // A rate cap bounds the run from below no matter how fast hosts answer
+        if let Some(max_rate) = config.max_rate.filter(|rate| *rate > 0) {
+            let probes = (targets as u64) * (ports as u64) * scan_types.len() as u64;
+            estimated_ms = estimated_ms.max(probes.saturating_mul(1000) / max_rate as u64);
+        }
+
+        Self {
+            targets,
+            ports,
+            scan_types: scan_types.to_vec(),
+            estimated_duration: Duration::from_millis(estimated_ms),
+        }
+    }
+
+    /// Total probes the run sends (before retries)
+    pub fn probes(&self) -> u64 {
+        self.targets as u64 * self.ports as u64 * self.scan_types.len() as u64
+    }
+}
+
+impl fmt::Display for ScanScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scan_types: Vec<String> = self.scan_types.iter().map(|t| format!("{:?}", t)).collect();
+        write!(
+            f,
+            "{} hosts x {} ports ({}) = {} probes, up to ~{}",
+            self.targets,
+            self.ports,
+            scan_types.join(", "),
+            self.probes(),
+            format_duration_ms(self.estimated_duration.as_millis() as u64)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn test_parse_target_list() {
+        let input = r#"
+# plain lines
+192.0.2.1
+192.0.2.8/31
+{"host":"example.com","a":["198.51.100.1","198.51.100.2"],"aaaa":["2001:db8::1"]}
+{"ip":"203.0.113.5","port":443}
+{"host":"203.0.113.9"}
+{"host":"unresolved.example.com"}
+"203.0.113.10"
+"#;
+        let targets: Vec<String> = parse_target_list(input).unwrap().iter().map(IpAddr::to_string).collect();
+        assert_eq!(
+            targets,
+            vec![
+                "192.0.2.1",
+                "192.0.2.8",
+                "192.0.2.9",
+                "198.51.100.1",
+                "198.51.100.2",
+                "2001:db8::1",
+                "203.0.113.5",
+                "203.0.113.9",
+                "203.0.113.10",
+            ]
+        );
+        assert!(parse_target_list("not-an-address").is_err());
+        assert!(parse_target_list("{\"a\": [").is_err());
+    }
+
+    #[test]
+    fn test_expand_target() {
+        assert_eq!(expand_target(" 192.0.2.7 ").unwrap(), vec!["192.0.2.7".parse::<IpAddr>().unwrap()]);
+
+        let block = expand_target("192.0.2.77/30").unwrap();
+        let expected: Vec<IpAddr> = (76..80).map(|i| format!("192.0.2.{}", i).parse().unwrap()).collect();
+        assert_eq!(block, expected);
+
+        assert_eq!(expand_target("10.0.0.0/16").unwrap().len(), 65536);
+        assert_eq!(expand_target("2001:db8::/126").unwrap().len(), 4);
+
+        assert!(expand_target("10.0.0.0/33").is_err());
+        assert!(expand_target("10.0.0.0/7").is_err());
+        assert!(expand_target("2001:db8::/64").is_err());
+        assert!(expand_target("not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_ip_network_contains() {
+        let block: IpNetwork = "192.0.2.77/28".parse().unwrap();
+        assert_eq!(block.to_string(), "192.0.2.64/28");
+        assert!(block.contains("192.0.2.79".parse().unwrap()));
+        assert!(!block.contains("192.0.2.80".parse().unwrap()));
+        assert!(!block.contains("::ffff:192.0.2.70".parse().unwrap()));
+
+        let single: IpNetwork = "2001:db8::1".parse().unwrap();
+        assert_eq!(single.to_string(), "2001:db8::1/128");
+        assert!(single.contains("2001:db8::1".parse().unwrap()));
+        assert!(!single.contains("2001:db8::2".parse().unwrap()));
+
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains("203.0.113.9".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn test_scope_estimate() {
+        let mut config = AppConfig::default().scanner;
+        config.max_concurrent_scans = 100;
+        config.tcp_connect.timeout_ms = 1000;
+
+        let scope = ScanScope::new(65536, 1000, &[ScanType::TcpConnect], &config);
+        assert_eq!(scope.probes(), 65_536_000);
+        // 6554 host rounds x 10 port rounds x 1s
+        assert_eq!(scope.estimated_duration, Duration::from_secs(65_540));
+        assert!(scope.to_string().starts_with("65536 hosts x 1000 ports (TcpConnect) = 65536000 probes"));
+
+        config.max_rate = Some(500);
+        let capped = ScanScope::new(65536, 1000, &[ScanType::TcpConnect], &config);
+        assert_eq!(capped.estimated_duration, Duration::from_secs(131_072));
+
+        // 100 hosts x 100 ports at once would take one round; 2000 probes
+        // in flight across the run need five
+        config.max_rate = None;
+        config.max_concurrent_targets = 100;
+        let parallel = ScanScope::new(100, 100, &[ScanType::TcpConnect], &config);
+        assert_eq!(parallel.estimated_duration, Duration::from_secs(1));
+        config.max_concurrent_probes_total = 2000;
+        let budgeted = ScanScope::new(100, 100, &[ScanType::TcpConnect], &config);
+        assert_eq!(budgeted.estimated_duration, Duration::from_secs(5));
+    }
+}
@@ -6,10 +6,30 @@
 
 use crate::config::TcpConnectConfig;
 use crate::error::{ScanError, ScanResult};
+use crate::scanner::confidence::Confidence;
+use crate::scanner::evasion::{EvasionOutcome, ProbeEvasion};
+use crate::scanner::pause::PauseControl;
+use crate::scanner::probe_error::ProbeError;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::progress::ProgressTracker;
+use crate::scanner::reason::PortReason;
+#[cfg(not(feature = "no-net"))]
+use crate::scanner::resources;
+use crate::scanner::retry::{filtered_ports, merge_reprobes, RetryPolicy};
+use crate::scanner::service_names::service_name;
+#[cfg(not(feature = "no-net"))]
+use crate::scanner::ssh_tunnel::{SshTunnel, TunnelProbe};
+use crate::scanner::throttle::{ScanRateLimiter, ThrottleScanResult, ThrottleStrategy};
+use crate::scanner::timing::{HostTimings, ProbeTiming};
+use std::io::ErrorKind;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::TcpStream;
+#[cfg(not(feature = "no-net"))]
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::sync::Semaphore;
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
 /// Port scan result
@@ -29,13 +49,120 @@ pub struct TcpConnectResult {
     pub status: PortStatus,
     pub response_time_ms: Option<u64>,
     pub banner: Option<String>,
+    /// Evasion mode the probe was sent with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evasion: Option<EvasionOutcome>,
+    /// Probes sent for this port, including retries and re-probes
+    #[serde(default)]
+    pub attempts: u32,
+    /// Why the last connect attempt failed, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<ConnectErrorClass>,
+    /// IANA service name of the port, e.g. "ssh"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Why the probe failed, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ProbeError>,
+    /// How well the evidence supports the status, 0-100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    /// The answer that settled the status (`--reason`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<PortReason>,
+}
+
+/// Why a connect() attempt failed
+///
+/// Transient classes are retried per the retry policy; the others settle
+/// the port's status on the first attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectErrorClass {
+    /// RST in answer to the SYN: closed
+    Refused,
+    /// Connection reset mid-handshake, often by a middlebox or overloaded host
+    Reset,
+    /// ICMP host/network unreachable: filtered
+    Unreachable,
+    /// No answer before the probe timeout: filtered
+    TimedOut,
+    /// Local resource shortage (EAGAIN, out of descriptors, buffers or
+    /// ephemeral ports); says nothing about the target
+    LocalResources,
+    /// Any other error
+    Other,
+}
+
+impl ConnectErrorClass {
+    /// Classify an error returned by connect()
+    pub fn classify(error: &std::io::Error) -> Self {
+        #[cfg(not(feature = "no-net"))]
+        match error.raw_os_error() {
+            Some(libc::ECONNREFUSED) => return Self::Refused,
+            Some(libc::ECONNRESET) | Some(libc::ECONNABORTED) => return Self::Reset,
+            Some(libc::EHOSTUNREACH) | Some(libc::ENETUNREACH) => return Self::Unreachable,
+            Some(libc::ETIMEDOUT) => return Self::TimedOut,
+            Some(libc::EAGAIN) | Some(libc::EMFILE) | Some(libc::ENFILE) | Some(libc::ENOBUFS)
+            | Some(libc::EADDRNOTAVAIL) => return Self::LocalResources,
+            _ => {}
+        }
+        match error.kind() {
+            ErrorKind::ConnectionRefused => Self::Refused,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => Self::Reset,
+            ErrorKind::TimedOut => Self::TimedOut,
+            ErrorKind::WouldBlock | ErrorKind::AddrNotAvailable => Self::LocalResources,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether another attempt may get a different answer
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Reset | Self::LocalResources)
+    }
+
+    /// Port status a failure of this class implies
+    fn status(&self) -> PortStatus {
+        match self {
+            Self::Refused | Self::Reset | Self::Other => PortStatus::Closed,
+            Self::Unreachable | Self::TimedOut => PortStatus::Filtered,
+            Self::LocalResources => PortStatus::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Refused => "refused",
+            Self::Reset => "reset",
+            Self::Unreachable => "unreachable",
+            Self::TimedOut => "timed out",
+            Self::LocalResources => "local resources",
+            Self::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 /// TCP connect scanner
+#[cfg(not(feature = "no-net"))]
 pub struct TcpConnectScanner {
     config: TcpConnectConfig,
+    evasion: ProbeEvasion,
+    timings: Option<HostTimings>,
+    rate_limiter: Option<Arc<ScanRateLimiter>>,
+    probe_permits: Option<Arc<Semaphore>>,
+    throttle: Option<Arc<dyn ThrottleStrategy>>,
+    retry: RetryPolicy,
+    progress: Option<Arc<ProgressTracker>>,
+    cancel: CancellationToken,
+    pause: PauseControl,
+    grab_banners: bool,
+    tunnel: Option<Arc<SshTunnel>>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl TcpConnectScanner {
     /// Create a new TCP connect scanner
     pub fn new(config: TcpConnectConfig) -> Self {
@@ -43,7 +170,118 @@ impl TcpConnectScanner {
             "Initializing TCP connect scanner: timeout={}ms, retries={}",
             config.timeout_ms, config.retries
         );
-        Self { config }
+        Self {
+            config,
+            evasion: ProbeEvasion::default(),
+            timings: None,
+            rate_limiter: None,
+            probe_permits: None,
+            throttle: None,
+            retry: RetryPolicy::default(),
+            progress: None,
+            cancel: CancellationToken::new(),
+            pause: PauseControl::new(),
+            grab_banners: true,
+            tunnel: None,
+        }
+    }
+
+    /// Send probes with the given evasion options
+    ///
+    /// Connect scans go through the kernel, so only the TTL applies.
+    pub fn with_evasion(mut self, evasion: ProbeEvasion) -> Self {
+        self.evasion = evasion.for_kernel_sockets("TCP connect");
+        self
+    }
+
+    /// Derive per-host timeouts and retries from measured RTTs
+    pub fn with_host_timings(mut self, timings: HostTimings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Pace every connection attempt through a run-wide rate limiter
+    pub fn with_rate_limiter(mut self, limiter: Arc<ScanRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Hold a permit of a run-wide semaphore while probing each port
+    pub fn with_probe_permits(mut self, permits: Option<Arc<Semaphore>>) -> Self {
+        self.probe_permits = permits;
+        self
+    }
+
+    /// Report probe outcomes to, and wait on, a congestion-control strategy
+    pub fn with_throttle(mut self, throttle: Arc<dyn ThrottleStrategy>) -> Self {
+        self.set_throttle(throttle);
+        self
+    }
+
+    /// Replace the throttle, e.g. after a configuration reload
+    pub(super) fn set_throttle(&mut self, throttle: Arc<dyn ThrottleStrategy>) {
+        self.throttle = Some(throttle);
+    }
+
+    /// Back off between retries and optionally re-probe filtered ports
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Count finished port probes towards the scan's progress
+    pub fn with_progress(mut self, progress: Arc<ProgressTracker>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop starting new probes and retries once `cancel` fires
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Hold new probes while `pause` is paused
+    pub fn with_pause_control(mut self, pause: PauseControl) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Override the configured per-probe timeout
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Read a banner from open ports (on by default)
+    pub fn with_banner_grabbing(mut self, enabled: bool) -> Self {
+        self.grab_banners = enabled;
+        self
+    }
+
+    /// Have an SSH bastion make the connects instead of this host
+    ///
+    /// No banners are read and the TTL option does not apply to tunneled
+    /// probes.
+    pub fn with_ssh_tunnel(mut self, tunnel: Arc<SshTunnel>) -> Self {
+        self.tunnel = Some(tunnel);
+        self
+    }
+
+    /// Whether connects go through an SSH bastion
+    pub fn is_tunneled(&self) -> bool {
+        self.tunnel.is_some()
+    }
+
+    /// Timeout and retries for the next probe to `target`
+    fn timing(&self, target: IpAddr) -> ProbeTiming {
+        match self.timings {
+            Some(ref timings) => timings.timing_for(target, self.config.timeout_ms, self.config.retries),
+            None => ProbeTiming {
+                timeout: Duration::from_millis(self.config.timeout_ms),
+                retries: self.config.retries,
+            },
+        }
     }
 
     /// Scan a single port on a target host
@@ -62,17 +300,66 @@ impl TcpConnectScanner {
         debug!("TCP connect scan: {}:{}", target, port);
 
         let mut last_error = None;
+        let mut last_transient = None;
         let start = std::time::Instant::now();
+        let timing = self.timing(target);
 
-        // Attempt scan with retries
-        for attempt in 0..=self.config.retries {
+        // Attempt scan with retries; only transient connect errors are retried
+        let mut attempts = 0;
+        for attempt in 0..=timing.retries {
             if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(self.config.retry_delay_ms)).await;
+                if self.cancel.is_cancelled() {
+                    break;
+                }
+                if !self.retry.allows_retry(start.elapsed()) {
+                    debug!("Retry budget for {}:{} exhausted after {} attempts", target, port, attempts);
+                    break;
+                }
+                tokio::time::sleep(self.retry.delay(self.config.retry_delay_ms, attempt)).await;
                 debug!("Retrying {}:{} (attempt {})", target, port, attempt + 1);
             }
+            if let Some(ref limiter) = self.rate_limiter {
+                limiter.acquire().await;
+            }
+            if let Some(ref throttle) = self.throttle {
+                throttle.wait().await?;
+            }
+            // Checked last so probes queued on the rate limiter hold too
+            if !self.pause.wait_resumed(&self.cancel).await {
+                break;
+            }
+            attempts += 1;
 
-            match self.try_connect(target, port).await {
-                Ok(result) => {
+            let outcome = self.try_connect(target, port, timing.timeout).await;
+            if let Some(ref throttle) = self.throttle {
+                let result = match outcome {
+                    Ok(ref result) if !result.error_class.is_some_and(|c| c.is_transient()) => {
+                        ThrottleScanResult::from_status(&result.status)
+                    }
+                    _ => ThrottleScanResult::Failure,
+                };
+                throttle.record_result(result).await;
+            }
+
+            match outcome {
+                Ok(mut result) if result.error_class.is_some_and(|c| c.is_transient()) => {
+                    result.attempts = attempts;
+                    result.service = service_name(port, TransportProtocol::Tcp);
+                    debug!(
+                        "Transient connect error on {}:{} ({})",
+                        target,
+                        port,
+                        result.error_class.unwrap_or(ConnectErrorClass::Other)
+                    );
+                    last_transient = Some(result);
+                    continue;
+                }
+                Ok(mut result) => {
+                    result.attempts = attempts;
+                    result.service = service_name(port, TransportProtocol::Tcp);
+                    if let (Some(timings), Some(rtt)) = (&self.timings, result.response_time_ms) {
+                        timings.observe(target, rtt);
+                    }
                     let elapsed = start.elapsed();
                     crate::log_scan_event!(
                         tracing::Level::INFO,
@@ -90,28 +377,47 @@ impl TcpConnectScanner {
             }
         }
 
+        // Retries used up on transient errors: report the last class seen
+        if let Some(result) = last_transient {
+            debug!(
+                "Port {}:{} still failing after {} attempts, reporting {}",
+                target, port, result.attempts, result.status
+            );
+            return Ok(result);
+        }
+
         // All retries failed
         let error = last_error.unwrap_or_else(|| {
             ScanError::tcp_scan_failed(target, port, "All retries exhausted")
         });
 
-        warn!("TCP connect scan failed for {}:{} after {} retries", target, port, self.config.retries);
+        warn!("TCP connect scan failed for {}:{} after {} retries", target, port, timing.retries);
         Err(error)
     }
 
     /// Attempt a single TCP connect
-    async fn try_connect(&self, target: IpAddr, port: u16) -> ScanResult<TcpConnectResult> {
+    async fn try_connect(&self, target: IpAddr, port: u16, timeout_duration: Duration) -> ScanResult<TcpConnectResult> {
+        if let Some(ref tunnel) = self.tunnel {
+            return self.try_tunneled(tunnel, target, port, timeout_duration).await;
+        }
         let addr = SocketAddr::new(target, port);
-        let timeout_duration = Duration::from_millis(self.config.timeout_ms);
         let start = std::time::Instant::now();
+        resources::socket_opened();
+        resources::packet_sent(resources::tcp_segment_len(target));
 
-        match timeout(timeout_duration, TcpStream::connect(addr)).await {
+        match timeout(timeout_duration, self.connect(addr)).await {
             Ok(Ok(mut stream)) => {
                 let elapsed = start.elapsed();
                 debug!("Port {}:{} is OPEN", target, port);
+                // SYN-ACK in, handshake ACK out
+                resources::packet_received(resources::tcp_segment_len(target));
+                resources::packet_sent(resources::tcp_segment_len(target));
 
                 // Try to grab banner (first few bytes of response)
-                let banner = self.grab_banner(&mut stream).await;
+                let banner = match self.grab_banners {
+                    true => self.grab_banner(&mut stream).await,
+                    false => None,
+                };
 
                 Ok(TcpConnectResult {
                     target,
@@ -119,18 +425,43 @@ impl TcpConnectScanner {
                     status: PortStatus::Open,
                     response_time_ms: Some(elapsed.as_millis() as u64),
                     banner,
+                    evasion: self.evasion.outcome(true),
+                    attempts: 1,
+                    error: None,
+                    service: None,
+                    error_class: None,
+                    confidence: None,
+                    reason: None,
                 })
             }
             Ok(Err(e)) => {
-                // Connection refused = port is closed
-                debug!("Port {}:{} is CLOSED: {}", target, port, e);
-                
+                let class = ConnectErrorClass::classify(&e);
+                let status = match class {
+                    // With a short TTL the probe usually expires on the way
+                    ConnectErrorClass::Refused => PortStatus::Closed,
+                    _ if self.evasion.is_active() => PortStatus::Filtered,
+                    _ => class.status(),
+                };
+                debug!("Port {}:{} is {} ({}): {}", target, port, status, class, e);
+
+                // A refusal still gives an RTT
+                let answered = matches!(class, ConnectErrorClass::Refused | ConnectErrorClass::Reset);
+                if answered {
+                    resources::packet_received(resources::tcp_segment_len(target));
+                }
                 Ok(TcpConnectResult {
                     target,
                     port,
-                    status: PortStatus::Closed,
-                    response_time_ms: None,
+                    status,
+                    response_time_ms: answered.then(|| start.elapsed().as_millis() as u64),
                     banner: None,
+                    evasion: self.evasion.outcome(answered),
+                    attempts: 1,
+                    error: Some(class.into()),
+                    service: None,
+                    error_class: Some(class),
+                    confidence: None,
+                    reason: None,
                 })
             }
             Err(_) => {
@@ -143,11 +474,69 @@ impl TcpConnectScanner {
                     status: PortStatus::Filtered,
                     response_time_ms: None,
                     banner: None,
+                    evasion: self.evasion.outcome(false),
+                    attempts: 1,
+                    error: Some(ProbeError::Timeout),
+                    service: None,
+                    error_class: Some(ConnectErrorClass::TimedOut),
+                    confidence: None,
+                    reason: None,
                 })
             }
         }
     }
 
+    /// Attempt a connect made by the SSH bastion
+    async fn try_tunneled(
+        &self,
+        tunnel: &Arc<SshTunnel>,
+        target: IpAddr,
+        port: u16,
+        timeout_duration: Duration,
+    ) -> ScanResult<TcpConnectResult> {
+        let start = std::time::Instant::now();
+        let (status, class) = match tunnel.probe(target, port, timeout_duration).await? {
+            TunnelProbe::Open => (PortStatus::Open, None),
+            TunnelProbe::Closed => (PortStatus::Closed, Some(ConnectErrorClass::Refused)),
+            TunnelProbe::TimedOut => (PortStatus::Filtered, Some(ConnectErrorClass::TimedOut)),
+        };
+        debug!("Port {}:{} is {} via SSH bastion {}", target, port, status, tunnel.config().bastion);
+
+        let answered = class != Some(ConnectErrorClass::TimedOut);
+        Ok(TcpConnectResult {
+            target,
+            port,
+            status,
+            response_time_ms: answered.then(|| start.elapsed().as_millis() as u64),
+            banner: None,
+            evasion: None,
+            attempts: 1,
+            error: class.map(Into::into),
+            service: None,
+            error_class: class,
+            confidence: None,
+            reason: None,
+        })
+    }
+
+    /// Open a connection, applying the configured TTL first
+    async fn connect(&self, addr: SocketAddr) -> std::io::Result<TcpStream> {
+        let Some(ttl) = self.evasion.ttl else {
+            return TcpStream::connect(addr).await;
+        };
+
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        let sock_ref = socket2::SockRef::from(&socket);
+        match addr {
+            SocketAddr::V4(_) => sock_ref.set_ttl(ttl as u32)?,
+            SocketAddr::V6(_) => sock_ref.set_unicast_hops_v6(ttl as u32)?,
+        }
+        socket.connect(addr).await
+    }
+
     /// Attempt to grab service banner from an open connection
     async fn grab_banner(&self, stream: &mut TcpStream) -> Option<String> {
         use tokio::io::AsyncReadExt;
@@ -157,6 +546,9 @@ impl TcpConnectScanner {
 
         match timeout(read_timeout, stream.read(&mut buffer)).await {
             Ok(Ok(n)) if n > 0 => {
+                if let Ok(peer) = stream.peer_addr() {
+                    resources::packet_received(n + resources::tcp_segment_len(peer.ip()));
+                }
                 let banner = String::from_utf8_lossy(&buffer[..n])
                     .trim()
                     .to_string();
@@ -189,8 +581,6 @@ impl TcpConnectScanner {
         ports: Vec<u16>,
         max_concurrent: usize,
     ) -> ScanResult<Vec<TcpConnectResult>> {
-        use futures::stream::{self, StreamExt};
-
         info!(
             "TCP connect scan: {} ports on {} with concurrency {}",
             ports.len(),
@@ -198,21 +588,19 @@ impl TcpConnectScanner {
             max_concurrent
         );
 
-        let results = stream::iter(ports)
-            .map(|port| async move {
-                match self.scan_port(target, port).await {
-                    Ok(result) => Some(result),
-                    Err(e) => {
-                        warn!("Scan failed for {}:{} - {}", target, port, e);
-                        None
-                    }
+        let mut results = self.probe_ports(target, ports, max_concurrent).await;
+        if self.retry.reprobe_filtered {
+            let filtered = filtered_ports(&results);
+            if !filtered.is_empty() {
+                debug!("Re-probing {} filtered ports on {}", filtered.len(), target);
+                if let Some(ref progress) = self.progress {
+                    progress.extend(target, filtered.len());
                 }
-            })
-            .buffer_unordered(max_concurrent)
-            .collect::<Vec<_>>()
-            .await;
-
-        let results: Vec<TcpConnectResult> = results.into_iter().flatten().collect();
+                let reprobed = self.probe_ports(target, filtered, max_concurrent).await;
+                let resolved = merge_reprobes(&mut results, reprobed);
+                debug!("Re-probe resolved {} ports on {}", resolved, target);
+            }
+        }
         
         let open_count = results
             .iter()
@@ -229,6 +617,39 @@ impl TcpConnectScanner {
         Ok(results)
     }
 
+    /// One pass over `ports`, dropping ports whose probes errored out
+    async fn probe_ports(&self, target: IpAddr, ports: Vec<u16>, max_concurrent: usize) -> Vec<TcpConnectResult> {
+        use futures::stream::{self, StreamExt};
+
+        // In-flight probes finish; no new ones start after cancellation
+        let results = stream::iter(ports)
+            .take_until(self.cancel.cancelled())
+            .map(|port| async move {
+                // Run-wide cap on probes in flight; the semaphore is never closed
+                let _permit = match self.probe_permits {
+                    Some(ref permits) => permits.acquire().await.ok(),
+                    None => None,
+                };
+                let result = self.scan_port(target, port).await;
+                if let Some(ref progress) = self.progress {
+                    progress.port_done(target);
+                }
+                match result {
+                    Ok(result) => Some(result),
+                    Err(_) if self.cancel.is_cancelled() => None,
+                    Err(e) => {
+                        warn!("Scan failed for {}:{} - {}", target, port, e);
+                        Some(TcpConnectResult::failed(target, port, ProbeError::from_scan_error(&e)))
+                    }
+                }
+            })
+            .buffer_unordered(max_concurrent)
+            .collect::<Vec<_>>()
+            .await;
+
+        results.into_iter().flatten().collect()
+    }
+
     /// Scan a port range on a target host
     /// 
     /// # Arguments
@@ -269,13 +690,44 @@ impl std::fmt::Display for PortStatus {
     }
 }
 
+impl TcpConnectResult {
+    /// Result for a port whose probes all failed with `error`
+    pub fn failed(target: IpAddr, port: u16, error: ProbeError) -> Self {
+        Self {
+            target,
+            port,
+            status: error.status(),
+            response_time_ms: None,
+            banner: None,
+            evasion: None,
+            attempts: 0,
+            error: Some(error),
+            service: service_name(port, TransportProtocol::Tcp),
+            error_class: None,
+            confidence: None,
+            reason: None,
+        }
+    }
+
+    /// Result for a port that accepted the connection
+    pub fn open(target: IpAddr, port: u16) -> Self {
+        Self {
+            status: PortStatus::Open,
+            attempts: 1,
+            error: None,
+            ..Self::failed(target, port, ProbeError::Timeout)
+        }
+    }
+}
+
 impl std::fmt::Display for TcpConnectResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}:{} - {} ({}ms)",
+            "{}:{}/tcp{} - {} ({}ms)",
             self.target,
             self.port,
+            self.service.as_deref().map_or(String::new(), |s| format!(" {}", s)),
             self.status,
             self.response_time_ms
                 .map_or("N/A".to_string(), |t| t.to_string())
@@ -284,12 +736,20 @@ impl std::fmt::Display for TcpConnectResult {
         if let Some(ref banner) = self.banner {
             write!(f, " [{}]", banner.chars().take(30).collect::<String>())?;
         }
+
+        if let Some(ref evasion) = self.evasion {
+            write!(f, " <{}>", evasion)?;
+        }
+
+        if let Some(class) = self.error_class.filter(|c| *c != ConnectErrorClass::Refused) {
+            write!(f, " ({})", class)?;
+        }
         
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
     use std::net::Ipv4Addr;
@@ -340,6 +800,81 @@ mod tests {
         assert_eq!(format!("{}", PortStatus::Unknown), "unknown");
     }
 
+    #[tokio::test]
+    async fn test_connect_with_ttl() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let scanner = TcpConnectScanner::new(create_test_config()).with_evasion(ProbeEvasion {
+            ttl: Some(2),
+            bad_checksum: true,
+        });
+
+        let result = scanner.scan_port(IpAddr::V4(Ipv4Addr::LOCALHOST), port).await.unwrap();
+        assert_eq!(result.status, PortStatus::Open);
+        assert_eq!(result.attempts, 1);
+        let evasion = result.evasion.unwrap();
+        assert_eq!(evasion.mode.ttl, Some(2));
+        // Bad checksums cannot be sent through a connect() scan
+        assert!(!evasion.mode.bad_checksum);
+        assert_eq!(evasion.responder, crate::scanner::evasion::ResponderClass::EndHost);
+    }
+
+    #[test]
+    fn test_classify_connect_errors() {
+        let os = |code| ConnectErrorClass::classify(&std::io::Error::from_raw_os_error(code));
+        assert_eq!(os(libc::ECONNREFUSED), ConnectErrorClass::Refused);
+        assert_eq!(os(libc::ECONNRESET), ConnectErrorClass::Reset);
+        assert_eq!(os(libc::EAGAIN), ConnectErrorClass::LocalResources);
+        assert_eq!(os(libc::EHOSTUNREACH), ConnectErrorClass::Unreachable);
+        assert_eq!(
+            ConnectErrorClass::classify(&std::io::Error::from(ErrorKind::ConnectionReset)),
+            ConnectErrorClass::Reset
+        );
+
+        assert!(ConnectErrorClass::Reset.is_transient());
+        assert!(ConnectErrorClass::LocalResources.is_transient());
+        assert!(!ConnectErrorClass::Refused.is_transient());
+        assert!(!ConnectErrorClass::TimedOut.is_transient());
+        assert_eq!(ConnectErrorClass::Unreachable.status(), PortStatus::Filtered);
+        assert_eq!(ConnectErrorClass::LocalResources.status(), PortStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_probes_wait_for_shared_permits() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let permits = Arc::new(Semaphore::new(1));
+        let scanner = TcpConnectScanner::new(create_test_config()).with_probe_permits(Some(permits.clone()));
+
+        // Another target holds the only permit
+        let held = permits.clone().acquire_owned().await.unwrap();
+        let scan = scanner.scan_ports(IpAddr::V4(Ipv4Addr::LOCALHOST), vec![port], 10);
+        tokio::pin!(scan);
+        assert!(tokio::time::timeout(Duration::from_millis(100), &mut scan).await.is_err());
+
+        drop(held);
+        let results = scan.await.unwrap();
+        assert_eq!(results[0].status, PortStatus::Open);
+        assert_eq!(permits.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refused_port_is_not_retried() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let mut config = create_test_config();
+        config.retries = 3;
+        let result = TcpConnectScanner::new(config)
+            .scan_port(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+            .await
+            .unwrap();
+        assert_eq!(result.status, PortStatus::Closed);
+        assert_eq!(result.error_class, Some(ConnectErrorClass::Refused));
+        assert_eq!(result.attempts, 1);
+    }
+
     // Note: More comprehensive tests would require a test server
     // or mocking the network layer
 }
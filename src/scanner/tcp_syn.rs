@@ -3,14 +3,42 @@
 /// This module implements the TCP SYN scan (half-open scan), which sends
 /// SYN packets and analyzes responses without completing the handshake.
 /// This is stealthier than connect() scan but requires elevated privileges.
+/// Probes go through the sender/receiver engine in `syn_engine`.
 
 use crate::config::TcpSynConfig;
 use crate::error::{ScanError, ScanResult};
+#[cfg(not(feature = "no-net"))]
+use crate::packet::capture::{CaptureConfig, CaptureFilter, PacketCapture};
+#[cfg(not(feature = "no-net"))]
+use crate::packet::crafting::{PacketBuilder, TcpFlags as CraftFlags, TcpPacket};
+use crate::report::origin;
+use crate::scanner::confidence::Confidence;
+use crate::scanner::evasion::{EvasionOutcome, ProbeEvasion};
+use crate::scanner::icmp_unreachable::IcmpUnreachable;
+use crate::scanner::pause::PauseControl;
+use crate::scanner::probe_error::ProbeError;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::progress::ProgressTracker;
+use crate::scanner::reason::PortReason;
+use crate::scanner::retry::{filtered_ports, merge_reprobes, RetryPolicy};
+use crate::scanner::service_names::service_name;
+#[cfg(not(feature = "no-net"))]
+use crate::scanner::syn_engine::{ProbeReply, ProbeTable, ProbeTransmitter, RawTransmitter, SynCookie};
 use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::throttle::{ScanRateLimiter, ThrottleScanResult, ThrottleStrategy};
+use rand::Rng;
+use std::collections::HashMap;
 use std::net::IpAddr;
-use std::time::Duration;
+use std::ops::RangeInclusive;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
+/// Local ports SYN probes are sent from, one per sweep
+const SOURCE_PORTS: RangeInclusive<u16> = 40000..=59999;
+
 /// TCP SYN scan result
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TcpSynResult {
@@ -19,6 +47,27 @@ pub struct TcpSynResult {
     pub status: PortStatus,
     pub response_time_ms: Option<u64>,
     pub flags: Option<TcpFlags>,
+    /// Evasion mode the probe was sent with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evasion: Option<EvasionOutcome>,
+    /// Probes sent for this port, including retries and re-probes
+    #[serde(default)]
+    pub attempts: u32,
+    /// IANA service name of the port, e.g. "ssh"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Why the probe failed, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ProbeError>,
+    /// ICMP destination unreachable that settled the port, with its sender
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icmp: Option<IcmpUnreachable>,
+    /// How well the evidence supports the status, 0-100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    /// The answer that settled the status (`--reason`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<PortReason>,
 }
 
 /// TCP flags observed in response
@@ -34,10 +83,21 @@ pub struct TcpFlags {
 /// 
 /// Note: This scanner requires elevated privileges (root/administrator)
 /// to create raw sockets for sending SYN packets and receiving responses.
+#[cfg(not(feature = "no-net"))]
 pub struct TcpSynScanner {
     config: TcpSynConfig,
+    evasion: ProbeEvasion,
+    rate_limiter: Option<Arc<ScanRateLimiter>>,
+    probe_permits: Option<Arc<Semaphore>>,
+    throttle: Option<Arc<dyn ThrottleStrategy>>,
+    retry: RetryPolicy,
+    progress: Option<Arc<ProgressTracker>>,
+    cancel: CancellationToken,
+    pause: PauseControl,
+    cookie: SynCookie,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl TcpSynScanner {
     /// Create a new TCP SYN scanner
     pub fn new(config: TcpSynConfig) -> Self {
@@ -56,7 +116,83 @@ impl TcpSynScanner {
             }
         }
         
-        Self { config }
+        Self {
+            config,
+            evasion: ProbeEvasion::default(),
+            rate_limiter: None,
+            probe_permits: None,
+            throttle: None,
+            retry: RetryPolicy::default(),
+            progress: None,
+            cancel: CancellationToken::new(),
+            pause: PauseControl::new(),
+            cookie: SynCookie::new(rand::random()),
+        }
+    }
+
+    /// Send crafted SYN probes with the given TTL and checksum options
+    pub fn with_evasion(mut self, evasion: ProbeEvasion) -> Self {
+        self.evasion = evasion;
+        self
+    }
+
+    /// Pace every SYN probe through a run-wide rate limiter
+    pub fn with_rate_limiter(mut self, limiter: Arc<ScanRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Hold a permit of a run-wide semaphore while probing each port
+    pub fn with_probe_permits(mut self, permits: Option<Arc<Semaphore>>) -> Self {
+        self.probe_permits = permits;
+        self
+    }
+
+    /// Report probe outcomes to, and wait on, a congestion-control strategy
+    pub fn with_throttle(mut self, throttle: Arc<dyn ThrottleStrategy>) -> Self {
+        self.set_throttle(throttle);
+        self
+    }
+
+    /// Replace the throttle, e.g. after a configuration reload
+    pub(super) fn set_throttle(&mut self, throttle: Arc<dyn ThrottleStrategy>) {
+        self.throttle = Some(throttle);
+    }
+
+    /// Back off between retries and optionally re-probe filtered ports
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Count finished port probes towards the scan's progress
+    pub fn with_progress(mut self, progress: Arc<ProgressTracker>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop starting new probes and retries once `cancel` fires
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Hold new probes while `pause` is paused
+    pub fn with_pause_control(mut self, pause: PauseControl) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Send probes statelessly, validating replies by their SYN cookie
+    pub fn with_stateless(mut self, stateless: bool) -> Self {
+        self.config.stateless = stateless;
+        self
+    }
+
+    /// Override the configured per-probe timeout
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
     }
 
     /// Check if we have the necessary privileges for raw socket operations
@@ -88,6 +224,21 @@ impl TcpSynScanner {
     /// # Returns
     /// * `ScanResult<TcpSynResult>` - Scan result with port status
     pub async fn scan_port(&self, target: IpAddr, port: u16) -> ScanResult<TcpSynResult> {
+        debug!("TCP SYN scan: {}:{}", target, port);
+        let result = self.sweep(target, &[port], 1).await?.pop().ok_or_else(|| {
+            ScanError::syn_scan_failed(target, port, "Scan cancelled before the probe was sent")
+        })?;
+        crate::log_scan_event!(
+            tracing::Level::INFO,
+            target,
+            port,
+            result.status.to_string(),
+            format!("TCP SYN scan completed after {} probes", result.attempts)
+        );
+        Ok(result)
+    }
+
+    fn check_ready(&self) -> ScanResult<()> {
         if !self.config.enabled {
             return Err(ScanError::scanner_error("TCP SYN scan is disabled"));
         }
@@ -97,74 +248,237 @@ impl TcpSynScanner {
                 "TCP SYN scan (requires root/administrator privileges)"
             ));
         }
+        Ok(())
+    }
+
+    /// Probe `ports` with the split sender/receiver engine
+    ///
+    /// A capture thread on the interface routing to `target` is the
+    /// receiver, recording replies into the probe table while this task
+    /// sends. Ports not probed before cancellation are left out.
+    async fn sweep(&self, target: IpAddr, ports: &[u16], max_concurrent: usize) -> ScanResult<Vec<TcpSynResult>> {
+        self.check_ready()?;
+        let source = origin::source_address(Some(target))
+            .ok_or_else(|| ScanError::scanner_error(format!("No local address routes to {}", target)))?;
+        let source_port = rand::thread_rng().gen_range(SOURCE_PORTS);
+        let builder = self.evasion.apply(PacketBuilder::new().source(source).destination(target));
+        let capture_config = CaptureConfig {
+            interface: origin::interface_for(source),
+            ..CaptureConfig::default()
+        };
+        let filter = CaptureFilter::new([target]).with_source_ports(source_port, source_port);
+        let mut capture = PacketCapture::new(capture_config, filter);
+
+        if self.config.stateless {
+            let (replies_tx, replies) = mpsc::channel();
+            let cookie = self.cookie;
+            capture.start_with(move |packet| match cookie.check(packet, source_port) {
+                Some(reply) => replies_tx.send(reply).is_ok(),
+                None => false,
+            })?;
+            let outcome = self
+                .send_stateless(target, ports, &builder, source_port, &mut RawTransmitter::new(), replies)
+                .await;
+            capture.stop();
+            return outcome;
+        }
 
-        debug!("TCP SYN scan: {}:{}", target, port);
+        let mut permits = vec![Arc::new(Semaphore::new(max_concurrent.max(1)))];
+        permits.extend(self.probe_permits.clone());
+        let table = Arc::new(ProbeTable::new(target, ports).with_permits(permits));
+        let receiver = Arc::clone(&table);
+        capture.start_with(move |packet| receiver.record(packet))?;
+
+        let outcome = self.send_probes(&table, &builder, source_port, &mut RawTransmitter::new()).await;
+        capture.stop();
+        outcome?;
 
-        let mut last_error = None;
-        let start = std::time::Instant::now();
+        Ok((0..table.len())
+            .filter(|&index| table.attempts(index) > 0)
+            .map(|index| self.result(&table, index))
+            .collect())
+    }
+
+    /// Sender half of the engine: probe every port in `table`, resending
+    /// unanswered probes up to the configured retries
+    ///
+    /// Each pass waits a single timeout for the whole batch; the receiver
+    /// settles probes in the table meanwhile.
+    pub(crate) async fn send_probes(
+        &self,
+        table: &ProbeTable,
+        builder: &PacketBuilder,
+        source_port: u16,
+        transmitter: &mut dyn ProbeTransmitter,
+    ) -> ScanResult<()> {
+        let target = table.target();
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let start = Instant::now();
+        let mut probe = syn_probe(source_port);
+        let mut buffer = vec![0u8; builder.tcp_packet_len(&probe)?];
+        let mut finished = vec![false; table.len()];
+
+        for pass in 0..=self.config.retries {
+            let unanswered = table.unanswered();
+            if unanswered.is_empty() || self.cancel.is_cancelled() {
+                break;
+            }
+            if pass > 0 {
+                if !self.retry.allows_retry(start.elapsed()) {
+                    debug!("Retry budget for {} exhausted after {} passes", target, pass);
+                    break;
+                }
+                tokio::time::sleep(self.retry.delay(self.config.retry_delay_ms, pass)).await;
+                debug!("Resending {} unanswered SYN probes to {} (pass {})", unanswered.len(), target, pass + 1);
+            }
 
-        // Attempt scan with retries
-        for attempt in 0..=self.config.retries {
-            if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(self.config.retry_delay_ms)).await;
-                debug!("Retrying SYN scan {}:{} (attempt {})", target, port, attempt + 1);
+            let mut sent = Vec::with_capacity(unanswered.len());
+            for index in unanswered {
+                if let Some(ref limiter) = self.rate_limiter {
+                    limiter.acquire().await;
+                }
+                if let Some(ref throttle) = self.throttle {
+                    throttle.wait().await?;
+                }
+                // Checked last so probes queued on the rate limiter hold too
+                if !self.pause.wait_resumed(&self.cancel).await {
+                    break;
+                }
+                if !table.acquire(index, timeout, &self.cancel).await {
+                    break;
+                }
+                probe.dest_port = table.port(index);
+                probe.sequence = table.sequence(index);
+                let len = builder.build_tcp_into(&probe, &mut buffer)?;
+                table.mark_sent(index);
+                if let Err(e) = transmitter.transmit(&buffer[..len], target) {
+                    table.release_unanswered();
+                    return Err(e);
+                }
+                sent.push(index);
             }
 
-            match self.try_syn_scan(target, port).await {
-                Ok(result) => {
-                    let elapsed = start.elapsed();
-                    crate::log_scan_event!(
-                        tracing::Level::INFO,
-                        target,
-                        port,
-                        result.status.to_string(),
-                        format!("TCP SYN scan completed in {}ms", elapsed.as_millis())
-                    );
-                    return Ok(result);
+            table.wait_settled(timeout).await;
+            table.release_unanswered();
+            for index in sent {
+                let status = table.reply(index).status();
+                if let Some(ref throttle) = self.throttle {
+                    throttle.record_result(ThrottleScanResult::from_status(&status)).await;
                 }
-                Err(e) => {
-                    last_error = Some(e);
-                    continue;
+                if table.reply(index) != ProbeReply::Pending {
+                    self.port_finished(target, &mut finished[index]);
                 }
             }
         }
 
-        // All retries failed
-        let error = last_error.unwrap_or_else(|| {
-            ScanError::syn_scan_failed(target, port, "All retries exhausted")
-        });
+        // Ports still unanswered after the last pass are filtered
+        for (index, finished) in finished.iter_mut().enumerate() {
+            if table.attempts(index) > 0 {
+                self.port_finished(target, finished);
+            }
+        }
+        Ok(())
+    }
 
-        warn!("TCP SYN scan failed for {}:{} after {} retries", target, port, self.config.retries);
-        Err(error)
+    /// Stateless sender: one probe per port, sequence numbers from the
+    /// scanner's [`SynCookie`], then a single timeout for all replies
+    ///
+    /// `replies` carries the validated replies the receiver saw. Ports
+    /// without one are filtered; there are no retries and no response
+    /// times, and the run-wide in-flight cap does not apply.
+    pub(crate) async fn send_stateless(
+        &self,
+        target: IpAddr,
+        ports: &[u16],
+        builder: &PacketBuilder,
+        source_port: u16,
+        transmitter: &mut dyn ProbeTransmitter,
+        replies: mpsc::Receiver<(u16, ProbeReply, Option<TcpFlags>)>,
+    ) -> ScanResult<Vec<TcpSynResult>> {
+        let mut probe = syn_probe(source_port);
+        let mut buffer = vec![0u8; builder.tcp_packet_len(&probe)?];
+        let mut sent = 0;
+        for &port in ports {
+            if let Some(ref limiter) = self.rate_limiter {
+                limiter.acquire().await;
+            }
+            if let Some(ref throttle) = self.throttle {
+                throttle.wait().await?;
+            }
+            if !self.pause.wait_resumed(&self.cancel).await {
+                break;
+            }
+            probe.dest_port = port;
+            probe.sequence = self.cookie.sequence(target, port, source_port);
+            let len = builder.build_tcp_into(&probe, &mut buffer)?;
+            transmitter.transmit(&buffer[..len], target)?;
+            sent += 1;
+        }
+        tokio::time::sleep(Duration::from_millis(self.config.timeout_ms)).await;
+
+        let mut answered = HashMap::new();
+        for (port, reply, flags) in replies.try_iter() {
+            // Duplicate replies: the first one wins
+            answered.entry(port).or_insert((reply, flags));
+        }
+        let mut results = Vec::with_capacity(sent);
+        for &port in &ports[..sent] {
+            let (reply, flags) = answered.get(&port).copied().unwrap_or((ProbeReply::Pending, None));
+            if let Some(ref throttle) = self.throttle {
+                throttle.record_result(ThrottleScanResult::from_status(&reply.status())).await;
+            }
+            if let Some(ref progress) = self.progress {
+                progress.port_done(target);
+            }
+            results.push(self.reply_result(target, port, reply, flags, None, 1));
+        }
+        debug!("Stateless SYN sweep of {}: {} of {} probes answered", target, answered.len(), sent);
+        Ok(results)
     }
 
-    /// Attempt a single SYN scan
-    /// 
-    /// This is a placeholder implementation. A full implementation would:
-    /// 1. Create a raw socket
-    /// 2. Craft a TCP SYN packet with the appropriate IP and TCP headers
-    /// 3. Send the packet
-    /// 4. Listen for SYN-ACK (open), RST (closed), or timeout (filtered)
-    /// 5. Send RST to close the half-open connection
-    async fn try_syn_scan(&self, target: IpAddr, port: u16) -> ScanResult<TcpSynResult> {
-        // TODO: Implement actual SYN scan using raw sockets
-        // This requires:
-        // - pnet or similar library for packet crafting
-        // - Raw socket creation with proper privileges
-        // - TCP/IP packet construction
-        // - Response packet parsing
-        
-        warn!(
-            "TCP SYN scan not fully implemented for {}:{}. \
-             This requires raw socket support and packet crafting.",
-            target, port
-        );
+    fn port_finished(&self, target: IpAddr, finished: &mut bool) {
+        if !std::mem::replace(finished, true) {
+            if let Some(ref progress) = self.progress {
+                progress.port_done(target);
+            }
+        }
+    }
 
-        // For now, return an error indicating the feature needs implementation
-        Err(ScanError::scanner_error(
-            "TCP SYN scan requires raw socket implementation. \
-             Please use TCP connect scan or implement raw socket support."
-        ))
+    fn result(&self, table: &ProbeTable, index: usize) -> TcpSynResult {
+        let rtt_ms = table.rtt(index).map(|rtt| rtt.as_millis() as u64);
+        self.reply_result(
+            table.target(),
+            table.port(index),
+            table.reply(index),
+            table.flags(index),
+            rtt_ms,
+            table.attempts(index),
+        )
+    }
+
+    fn reply_result(
+        &self,
+        target: IpAddr,
+        port: u16,
+        reply: ProbeReply,
+        flags: Option<TcpFlags>,
+        rtt_ms: Option<u64>,
+        attempts: u32,
+    ) -> TcpSynResult {
+        let mut result = match reply {
+            ProbeReply::Pending => TcpSynResult::failed(target, port, ProbeError::Timeout),
+            ProbeReply::Unreachable(icmp) => TcpSynResult::unreachable(target, port, icmp, rtt_ms.unwrap_or(0)),
+            ProbeReply::SynAck | ProbeReply::Reset => TcpSynResult {
+                status: reply.status(),
+                response_time_ms: rtt_ms,
+                flags,
+                error: None,
+                ..TcpSynResult::failed(target, port, ProbeError::Refused)
+            },
+        };
+        result.attempts = attempts;
+        result.evasion = self.evasion.outcome(reply != ProbeReply::Pending);
+        result
     }
 
     /// Scan multiple ports on a single host using SYN scan
@@ -182,8 +496,6 @@ impl TcpSynScanner {
         ports: Vec<u16>,
         max_concurrent: usize,
     ) -> ScanResult<Vec<TcpSynResult>> {
-        use futures::stream::{self, StreamExt};
-
         info!(
             "TCP SYN scan: {} ports on {} with concurrency {}",
             ports.len(),
@@ -191,21 +503,19 @@ impl TcpSynScanner {
             max_concurrent
         );
 
-        let results = stream::iter(ports)
-            .map(|port| async move {
-                match self.scan_port(target, port).await {
-                    Ok(result) => Some(result),
-                    Err(e) => {
-                        warn!("SYN scan failed for {}:{} - {}", target, port, e);
-                        None
-                    }
+        let mut results = self.probe_ports(target, ports, max_concurrent).await;
+        if self.retry.reprobe_filtered {
+            let filtered = filtered_ports(&results);
+            if !filtered.is_empty() {
+                debug!("Re-probing {} filtered ports on {}", filtered.len(), target);
+                if let Some(ref progress) = self.progress {
+                    progress.extend(target, filtered.len());
                 }
-            })
-            .buffer_unordered(max_concurrent)
-            .collect::<Vec<_>>()
-            .await;
-
-        let results: Vec<TcpSynResult> = results.into_iter().flatten().collect();
+                let reprobed = self.probe_ports(target, filtered, max_concurrent).await;
+                let resolved = merge_reprobes(&mut results, reprobed);
+                debug!("Re-probe resolved {} ports on {}", resolved, target);
+            }
+        }
         
         let open_count = results
             .iter()
@@ -222,6 +532,23 @@ impl TcpSynScanner {
         Ok(results)
     }
 
+    /// One pass over `ports`; when the engine cannot run, every port gets a
+    /// failed result carrying the reason
+    async fn probe_ports(&self, target: IpAddr, ports: Vec<u16>, max_concurrent: usize) -> Vec<TcpSynResult> {
+        match self.sweep(target, &ports, max_concurrent).await {
+            Ok(results) => results,
+            Err(_) if self.cancel.is_cancelled() => Vec::new(),
+            Err(e) => {
+                warn!("SYN scan of {} failed - {}", target, e);
+                let error = ProbeError::from_scan_error(&e);
+                if let Some(ref progress) = self.progress {
+                    ports.iter().for_each(|_| progress.port_done(target));
+                }
+                ports.into_iter().map(|port| TcpSynResult::failed(target, port, error)).collect()
+            }
+        }
+    }
+
     /// Scan a port range on a target host using SYN scan
     pub async fn scan_port_range(
         &self,
@@ -242,6 +569,22 @@ impl TcpSynScanner {
     }
 }
 
+/// Bare SYN from `source_port`; the sender fills in port and sequence
+#[cfg(not(feature = "no-net"))]
+fn syn_probe(source_port: u16) -> TcpPacket {
+    TcpPacket {
+        source_port,
+        dest_port: 0,
+        sequence: 0,
+        acknowledgment: 0,
+        flags: CraftFlags::syn(),
+        window: 1024,
+        urgent_pointer: 0,
+        options: Vec::new(),
+        payload: Vec::new(),
+    }
+}
+
 impl TcpFlags {
     /// Create TCP flags from response packet
     pub fn from_packet(syn: bool, ack: bool, rst: bool, fin: bool) -> Self {
@@ -270,13 +613,44 @@ impl std::fmt::Display for TcpFlags {
     }
 }
 
+impl TcpSynResult {
+    /// Result for a probe answered with an ICMP destination unreachable
+    pub fn unreachable(target: IpAddr, port: u16, icmp: IcmpUnreachable, response_time_ms: u64) -> Self {
+        let error = icmp.error(TransportProtocol::Tcp);
+        Self {
+            response_time_ms: Some(response_time_ms),
+            icmp: Some(icmp),
+            ..Self::failed(target, port, error)
+        }
+    }
+
+    /// Result for a port whose probes all failed with `error`
+    pub fn failed(target: IpAddr, port: u16, error: ProbeError) -> Self {
+        Self {
+            target,
+            port,
+            status: error.status(),
+            response_time_ms: None,
+            flags: None,
+            evasion: None,
+            attempts: 0,
+            error: Some(error),
+            icmp: None,
+            service: service_name(port, TransportProtocol::Tcp),
+            confidence: None,
+            reason: None,
+        }
+    }
+}
+
 impl std::fmt::Display for TcpSynResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}:{} - {} ({}ms)",
+            "{}:{}/tcp{} - {} ({}ms)",
             self.target,
             self.port,
+            self.service.as_deref().map_or(String::new(), |s| format!(" {}", s)),
             self.status,
             self.response_time_ms
                 .map_or("N/A".to_string(), |t| t.to_string())
@@ -285,12 +659,25 @@ impl std::fmt::Display for TcpSynResult {
         if let Some(flags) = self.flags {
             write!(f, " [{}]", flags)?;
         }
+
+        if let Some(ref evasion) = self.evasion {
+            write!(f, " <{}>", evasion)?;
+        }
+
+        // Refusals and timeouts are what closed and filtered already say
+        match (self.error, self.icmp) {
+            (Some(error), Some(icmp)) => write!(f, " ({}, {})", error, icmp)?,
+            (Some(error), None) if !matches!(error, ProbeError::Refused | ProbeError::Timeout) => {
+                write!(f, " ({})", error)?
+            }
+            _ => {}
+        }
         
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
 
@@ -300,6 +687,7 @@ mod tests {
             timeout_ms: 2000,
             retries: 1,
             retry_delay_ms: 50,
+            stateless: false,
         }
     }
 
@@ -334,5 +722,174 @@ mod tests {
         // Just verify this doesn't panic
         let _has_privs = TcpSynScanner::check_privileges();
     }
-}
 
+    #[test]
+    fn test_icmp_unreachable_filters_port() {
+        let target = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 10));
+        let firewall = "192.0.2.1".parse().unwrap();
+        let icmp = IcmpUnreachable::new(firewall, 3, 3).unwrap();
+        let result = TcpSynResult::unreachable(target, 443, icmp, 12);
+        assert_eq!(result.status, PortStatus::Filtered);
+        assert_eq!(result.error, Some(ProbeError::AdminProhibited));
+        assert!(result.to_string().ends_with("(admin-prohibited, ICMP type 3 code 3 from 192.0.2.1)"));
+    }
+
+    #[test]
+    fn test_icmp_from_firewall_reaches_probe_table() {
+        let target = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 10));
+        let firewall: IpAddr = "198.51.100.1".parse().unwrap();
+        let source_port = 40000;
+        let table = ProbeTable::new(target, &[443]);
+        let cookie = SynCookie::new(7);
+        table.mark_sent(0);
+
+        // Destination unreachable (admin prohibited) from the firewall,
+        // quoting the IPv4 header and first 8 TCP bytes of a probe
+        let icmp_error = |sequence: u32| {
+            let probe = TcpPacket {
+                source_port,
+                dest_port: 443,
+                sequence,
+                acknowledgment: 0,
+                flags: CraftFlags::syn(),
+                window: 1024,
+                urgent_pointer: 0,
+                options: Vec::new(),
+                payload: Vec::new(),
+            };
+            let probe = PacketBuilder::new()
+                .source(IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1)))
+                .destination(target)
+                .build_tcp(&probe)
+                .unwrap();
+            let mut packet = vec![0x45, 0, 0, 56, 0, 0, 0, 0, 64, 1, 0, 0, 198, 51, 100, 1, 192, 0, 2, 1];
+            packet.extend_from_slice(&[3, 13, 0, 0, 0, 0, 0, 0]);
+            packet.extend_from_slice(&probe[..28]);
+            packet
+        };
+        let parser = crate::packet::parser::PacketParser::new(false);
+        let filter = CaptureFilter::new([target]).with_source_ports(source_port, source_port);
+
+        let stateful = icmp_error(table.sequence(0));
+        let packet = parser.parse_ref(&stateful).unwrap();
+        assert!(filter.matches_ref(&packet));
+        assert!(table.record(&packet));
+        let icmp = IcmpUnreachable::new(firewall, 3, 13).unwrap();
+        assert_eq!(table.reply(0), ProbeReply::Unreachable(icmp));
+
+        let stateless = icmp_error(cookie.sequence(target, 443, source_port));
+        let packet = parser.parse_ref(&stateless).unwrap();
+        assert!(filter.matches_ref(&packet));
+        assert_eq!(cookie.check(&packet, source_port), Some((443, ProbeReply::Unreachable(icmp), None)));
+
+        // An ICMP error about a datagram to some other host stays out
+        let mut unrelated = stateful.clone();
+        unrelated[44..48].copy_from_slice(&[203, 0, 113, 5]);
+        assert!(!filter.matches_ref(&parser.parse_ref(&unrelated).unwrap()));
+    }
+
+    /// Answers SYNs to port 80 with SYN-ACK and to 443 with RST; drops the rest
+    struct Responder {
+        receiver: Box<dyn Fn(&crate::packet::parser::ParsedPacketRef<'_>) -> bool + Send>,
+        sent: Vec<u16>,
+    }
+
+    impl Responder {
+        fn new(receiver: impl Fn(&crate::packet::parser::ParsedPacketRef<'_>) -> bool + Send + 'static) -> Self {
+            Self { receiver: Box::new(receiver), sent: Vec::new() }
+        }
+    }
+
+    impl ProbeTransmitter for Responder {
+        fn transmit(&mut self, packet: &[u8], destination: IpAddr) -> ScanResult<()> {
+            let syn = crate::packet::parser::PacketParser::new(false).parse_ref(packet)?;
+            let tcp = syn.tcp_info.unwrap();
+            self.sent.push(tcp.dest_port);
+            let flags = match tcp.dest_port {
+                80 => CraftFlags::syn_ack(),
+                443 => CraftFlags { rst: true, ack: true, ..Default::default() },
+                _ => return Ok(()),
+            };
+            let reply = TcpPacket {
+                source_port: tcp.dest_port,
+                dest_port: tcp.source_port,
+                sequence: 1,
+                acknowledgment: tcp.sequence.wrapping_add(1),
+                flags,
+                window: 1024,
+                urgent_pointer: 0,
+                options: Vec::new(),
+                payload: Vec::new(),
+            };
+            let reply = PacketBuilder::new().source(destination).destination(syn.source_ip).build_tcp(&reply)?;
+            (self.receiver)(&crate::packet::parser::PacketParser::new(false).parse_ref(&reply)?);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_engine_resends_only_unanswered_probes() {
+        let target = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 10));
+        let config = TcpSynConfig { timeout_ms: 20, retries: 2, retry_delay_ms: 1, ..create_test_config() };
+        let progress = Arc::new(ProgressTracker::new());
+        progress.begin(1, 3);
+        let scanner = TcpSynScanner::new(config).with_progress(Arc::clone(&progress));
+        let table = Arc::new(ProbeTable::new(target, &[80, 443, 8080]));
+        let builder = PacketBuilder::new().source(IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1))).destination(target);
+        let receiver = Arc::clone(&table);
+        let mut responder = Responder::new(move |packet| receiver.record(packet));
+
+        scanner.send_probes(&table, &builder, 40000, &mut responder).await.unwrap();
+        assert_eq!(responder.sent, [80, 443, 8080, 8080, 8080]);
+        assert_eq!(progress.snapshot().ports_done, 3);
+
+        let results: Vec<_> = (0..3).map(|index| scanner.result(&table, index)).collect();
+        let statuses: Vec<_> = results.iter().map(|r| (r.status.clone(), r.attempts)).collect();
+        assert_eq!(
+            statuses,
+            [(PortStatus::Open, 1), (PortStatus::Closed, 1), (PortStatus::Filtered, 3)]
+        );
+        assert_eq!(results[0].flags, Some(TcpFlags::from_packet(true, true, false, false)));
+        assert_eq!(results[2].error, Some(ProbeError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_stateless_sweep_validates_cookies() {
+        let target = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 10));
+        let config = TcpSynConfig { timeout_ms: 10, retries: 2, ..create_test_config() };
+        let scanner = TcpSynScanner::new(config).with_stateless(true);
+        let builder = PacketBuilder::new().source(IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1))).destination(target);
+        let (replies_tx, replies) = mpsc::channel();
+        let cookie = scanner.cookie;
+        let mut responder = Responder::new(move |packet| match cookie.check(packet, 40000) {
+            Some(reply) => replies_tx.send(reply).is_ok(),
+            None => false,
+        });
+
+        let results = scanner
+            .send_stateless(target, &[80, 443, 8080], &builder, 40000, &mut responder, replies)
+            .await
+            .unwrap();
+        // No retries: the unanswered port is probed once
+        assert_eq!(responder.sent, [80, 443, 8080]);
+        let statuses: Vec<_> = results.iter().map(|r| (r.port, r.status.clone(), r.attempts)).collect();
+        assert_eq!(
+            statuses,
+            [(80, PortStatus::Open, 1), (443, PortStatus::Closed, 1), (8080, PortStatus::Filtered, 1)]
+        );
+        assert!(results.iter().all(|r| r.response_time_ms.is_none()));
+
+        // Replies to another scan's probes fail validation
+        let other = SynCookie::new(7);
+        let syn = TcpPacket { dest_port: 80, sequence: other.sequence(target, 80, 40000), ..syn_probe(40000) };
+        let validated = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = Arc::clone(&validated);
+        let mut stranger = Responder::new(move |packet| {
+            flag.store(cookie.check(packet, 40000).is_some(), std::sync::atomic::Ordering::SeqCst);
+            true
+        });
+        stranger.transmit(&builder.build_tcp(&syn).unwrap(), target).unwrap();
+        assert_eq!(stranger.sent, [80]);
+        assert!(!validated.load(std::sync::atomic::Ordering::SeqCst));
+    }
+}
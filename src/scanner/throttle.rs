@@ -3,13 +3,21 @@
 /// This module implements intelligent rate limiting that automatically adjusts
 /// scan speed based on success/failure rates to optimize performance while
 /// avoiding network congestion and rate limiting.
-
-use crate::config::ThrottlingConfig;
-use crate::error::ScanError;
-use std::sync::Arc;
+///
+/// The congestion-control logic sits behind the [`ThrottleStrategy`] trait;
+/// [`AdaptiveThrottle`] is the built-in implementation, and library users can
+/// register their own strategies (AIMD variants, BBR-like pacing, ...) on the
+/// [`Scanner`](crate::Scanner) and select them by name with
+/// `scanner.throttle_strategy`.
+
+use crate::config::{ScannerConfig, ThrottlingConfig};
+use crate::error::{ScanError, ScanResult};
+use crate::scanner::tcp_connect::PortStatus;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tokio::time::sleep;
+use tokio::time::{sleep, sleep_until};
 use tracing::{debug, info, warn};
 
 /// Result of a scan operation for throttling purposes
@@ -19,6 +27,47 @@ pub enum ThrottleScanResult {
     Failure,
 }
 
+impl ThrottleScanResult {
+    /// A probe succeeded when the target answered, open or closed
+    pub fn from_status(status: &PortStatus) -> Self {
+        match status {
+            PortStatus::Open | PortStatus::Closed => ThrottleScanResult::Success,
+            PortStatus::Filtered | PortStatus::Unknown => ThrottleScanResult::Failure,
+        }
+    }
+}
+
+/// Name of the built-in [`AdaptiveThrottle`] strategy
+pub const ADAPTIVE_STRATEGY: &str = "adaptive";
+
+/// Congestion control for probe sending
+///
+/// The port scanners call [`wait`](ThrottleStrategy::wait) before every probe
+/// attempt and report its outcome through
+/// [`record_result`](ThrottleStrategy::record_result); how the rate reacts is
+/// up to the implementation. One instance is shared by all scanners of a run.
+#[async_trait]
+pub trait ThrottleStrategy: Send + Sync {
+    /// Key used to select the strategy in configuration
+    fn name(&self) -> &str;
+
+    /// Wait before sending the next probe
+    async fn wait(&self) -> ScanResult<()>;
+
+    /// Record the outcome of a probe
+    async fn record_result(&self, result: ThrottleScanResult);
+
+    /// Current packets per second rate
+    async fn current_pps(&self) -> usize;
+
+    /// Statistics reported with scan results
+    async fn get_stats(&self) -> ThrottleStats;
+}
+
+/// Builds a strategy for a run from the scanner and `[throttling]`
+/// configuration
+pub type ThrottleFactory = Arc<dyn Fn(&ScannerConfig, &ThrottlingConfig) -> Arc<dyn ThrottleStrategy> + Send + Sync>;
+
 /// Adaptive throttle controller
 /// 
 /// Monitors scan results and dynamically adjusts the rate of operations
@@ -26,8 +75,16 @@ pub enum ThrottleScanResult {
 pub struct AdaptiveThrottle {
     config: ThrottlingConfig,
     state: Arc<RwLock<ThrottleState>>,
+    min_pps: usize,
+    max_pps: usize,
 }
 
+/// Default lower bound the adaptive throttle backs off to
+const DEFAULT_MIN_PPS: usize = 10;
+
+/// Default upper bound the adaptive throttle speeds up to
+const DEFAULT_MAX_PPS: usize = 1_000_000;
+
 #[derive(Debug, Clone)]
 struct ThrottleState {
     current_pps: usize,
@@ -60,11 +117,102 @@ impl AdaptiveThrottle {
                 total_successes: 0,
                 total_failures: 0,
             })),
+            min_pps: DEFAULT_MIN_PPS,
+            max_pps: DEFAULT_MAX_PPS,
+        }
+    }
+
+    /// Built-in throttle for a scanner run with the `[throttling]`
+    /// thresholds, bounded by `--min-rate`/`--max-rate`
+    pub fn from_scanner_config(config: &ScannerConfig, throttling: &ThrottlingConfig) -> Self {
+        Self::new(throttling.clone(), config.initial_pps).with_rate_bounds(
+            config.min_rate.map(|r| r as usize),
+            config.max_rate.map(|r| r as usize),
+        )
+    }
+
+    /// Keep the adaptive rate within `--min-rate`/`--max-rate`
+    ///
+    /// The bounds replace the built-in floor and ceiling, so the throttle
+    /// never backs off below the minimum nor speeds up past the maximum.
+    pub fn with_rate_bounds(mut self, min_pps: Option<usize>, max_pps: Option<usize>) -> Self {
+        self.min_pps = min_pps.unwrap_or(DEFAULT_MIN_PPS);
+        self.max_pps = max_pps.unwrap_or(DEFAULT_MAX_PPS).max(self.min_pps);
+        let state = Arc::get_mut(&mut self.state).expect("throttle state is not shared yet").get_mut();
+        state.current_pps = state.current_pps.clamp(self.min_pps, self.max_pps);
+        self
+    }
+
+    /// Adjust the rate based on recent success/failure ratio
+    async fn adjust_rate(&self, state: &mut ThrottleState) {
+        if state.results_window.is_empty() {
+            return;
+        }
+
+        let success_count = state
+            .results_window
+            .iter()
+            .filter(|&&r| r == ThrottleScanResult::Success)
+            .count();
+        
+        let success_rate = success_count as f64 / state.results_window.len() as f64;
+        let old_pps = state.current_pps;
+
+        if success_rate >= self.config.success_threshold {
+            // High success rate: increase speed
+            let new_pps = (state.current_pps as f64 * self.config.rate_increase_factor) as usize;
+            state.current_pps = new_pps.min(self.max_pps);
+            
+            debug!(
+                old_pps = old_pps,
+                new_pps = state.current_pps,
+                success_rate = success_rate,
+                "Increasing scan rate (high success rate)"
+            );
+            
+            crate::log_rate_limit!(state.current_pps, success_rate, "increase");
+        } else if success_rate <= self.config.failure_threshold {
+            // Low success rate: decrease speed
+            let new_pps = (state.current_pps as f64 * self.config.rate_decrease_factor) as usize;
+            state.current_pps = new_pps.max(self.min_pps);
+            
+            warn!(
+                old_pps = old_pps,
+                new_pps = state.current_pps,
+                success_rate = success_rate,
+                "Decreasing scan rate (low success rate)"
+            );
+            
+            crate::log_rate_limit!(state.current_pps, success_rate, "decrease");
         }
     }
 
+    /// Manually set the rate (for testing or manual control)
+    pub async fn set_rate(&self, pps: usize) {
+        let mut state = self.state.write().await;
+        state.current_pps = pps;
+        info!("Manually set rate to {} pps", pps);
+    }
+
+    /// Reset throttle statistics
+    pub async fn reset(&self) {
+        let mut state = self.state.write().await;
+        state.results_window.clear();
+        state.total_requests = 0;
+        state.total_successes = 0;
+        state.total_failures = 0;
+        info!("Throttle statistics reset");
+    }
+}
+
+#[async_trait]
+impl ThrottleStrategy for AdaptiveThrottle {
+    fn name(&self) -> &str {
+        ADAPTIVE_STRATEGY
+    }
+
     /// Wait before sending the next packet (rate limiting)
-    pub async fn wait(&self) -> crate::error::ScanResult<()> {
+    async fn wait(&self) -> ScanResult<()> {
         if !self.config.enabled {
             return Ok(());
         }
@@ -88,7 +236,7 @@ impl AdaptiveThrottle {
     }
 
     /// Record the result of a scan operation
-    pub async fn record_result(&self, result: ThrottleScanResult) {
+    async fn record_result(&self, result: ThrottleScanResult) {
         if !self.config.enabled {
             return;
         }
@@ -116,57 +264,13 @@ impl AdaptiveThrottle {
         }
     }
 
-    /// Adjust the rate based on recent success/failure ratio
-    async fn adjust_rate(&self, state: &mut ThrottleState) {
-        if state.results_window.is_empty() {
-            return;
-        }
-
-        let success_count = state
-            .results_window
-            .iter()
-            .filter(|&&r| r == ThrottleScanResult::Success)
-            .count();
-        
-        let success_rate = success_count as f64 / state.results_window.len() as f64;
-        let old_pps = state.current_pps;
-
-        if success_rate >= self.config.success_threshold {
-            // High success rate: increase speed
-            let new_pps = (state.current_pps as f64 * self.config.rate_increase_factor) as usize;
-            state.current_pps = new_pps.min(1_000_000); // Absolute max cap
-            
-            debug!(
-                old_pps = old_pps,
-                new_pps = state.current_pps,
-                success_rate = success_rate,
-                "Increasing scan rate (high success rate)"
-            );
-            
-            crate::log_rate_limit!(state.current_pps, success_rate, "increase");
-        } else if success_rate <= self.config.failure_threshold {
-            // Low success rate: decrease speed
-            let new_pps = (state.current_pps as f64 * self.config.rate_decrease_factor) as usize;
-            state.current_pps = new_pps.max(10); // Minimum 10 pps
-            
-            warn!(
-                old_pps = old_pps,
-                new_pps = state.current_pps,
-                success_rate = success_rate,
-                "Decreasing scan rate (low success rate)"
-            );
-            
-            crate::log_rate_limit!(state.current_pps, success_rate, "decrease");
-        }
-    }
-
     /// Get current packets per second rate
-    pub async fn current_pps(&self) -> usize {
+    async fn current_pps(&self) -> usize {
         self.state.read().await.current_pps
     }
 
     /// Get statistics about throttling performance
-    pub async fn get_stats(&self) -> ThrottleStats {
+    async fn get_stats(&self) -> ThrottleStats {
         let state = self.state.read().await;
         let success_rate = if state.total_requests > 0 {
             state.total_successes as f64 / state.total_requests as f64
@@ -182,22 +286,59 @@ impl AdaptiveThrottle {
             success_rate,
         }
     }
+}
 
-    /// Manually set the rate (for testing or manual control)
-    pub async fn set_rate(&self, pps: usize) {
-        let mut state = self.state.write().await;
-        state.current_pps = pps;
-        info!("Manually set rate to {} pps", pps);
+/// Check `--min-rate`/`--max-rate` values
+pub fn validate_rate_limits(min_rate: Option<u32>, max_rate: Option<u32>) -> crate::error::ScanResult<()> {
+    if min_rate == Some(0) || max_rate == Some(0) {
+        return Err(ScanError::validation_error("rate", "rates must be at least 1 packet per second"));
+    }
+    if let (Some(min), Some(max)) = (min_rate, max_rate) {
+        if min > max {
+            return Err(ScanError::validation_error(
+                "rate",
+                format!("min-rate {} exceeds max-rate {}", min, max),
+            ));
+        }
     }
+    Ok(())
+}
 
-    /// Reset throttle statistics
-    pub async fn reset(&self) {
-        let mut state = self.state.write().await;
-        state.results_window.clear();
-        state.total_requests = 0;
-        state.total_successes = 0;
-        state.total_failures = 0;
-        info!("Throttle statistics reset");
+/// Run-wide probe rate cap shared by every scanner (`--max-rate`)
+///
+/// A token bucket holding a single token: probes are spaced at least
+/// `1 / max_pps` apart across all scanners and tasks, so bursts never exceed
+/// the configured rate even after an idle period.
+#[derive(Debug)]
+pub struct ScanRateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl ScanRateLimiter {
+    /// Create a limiter allowing `max_pps` probes per second
+    pub fn new(max_pps: u32) -> Self {
+        info!("Limiting probe rate to {} pps", max_pps);
+        Self {
+            interval: Duration::from_secs(1) / max_pps.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for permission to send one probe
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        sleep_until(slot.into()).await;
+    }
+
+    /// Configured rate in probes per second
+    pub fn max_pps(&self) -> f64 {
+        1.0 / self.interval.as_secs_f64()
     }
 }
 
@@ -313,6 +454,37 @@ mod tests {
         assert_eq!(pps, 5000);
     }
 
+    #[tokio::test]
+    async fn test_rate_bounds_override() {
+        let throttle = AdaptiveThrottle::new(create_test_config(), 1000).with_rate_bounds(Some(800), Some(1200));
+
+        for _ in 0..15 {
+            throttle.record_result(ThrottleScanResult::Failure).await;
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        throttle.record_result(ThrottleScanResult::Failure).await;
+
+        // Backing off stops at --min-rate instead of halving to 500
+        assert_eq!(throttle.current_pps().await, 800);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_spacing() {
+        let limiter = Arc::new(ScanRateLimiter::new(100));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire().await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // Six probes at 100 pps span at least five 10ms intervals
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
     #[tokio::test]
     async fn test_reset() {
         let config = create_test_config();
@@ -0,0 +1,157 @@
+//! Per-host adaptive probe timeouts
+//!
+//! Each target gets a TCP-style RTT estimator (SRTT/RTTVAR, RFC 6298) fed by
+//! its host discovery reply and every answered probe. Once a host is
+//! calibrated its probes wait `SRTT + 4 * RTTVAR` instead of the global
+//! timeout, so LAN hosts are scanned quickly and slow WAN hosts are not
+//! mistaken for filtered ones. Retries drop to the configured minimum for
+//! hosts that answer consistently.
+
+use crate::config::AdaptiveTimeoutConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Samples needed before a host's estimate replaces the configured timeout
+pub const CALIBRATION_SAMPLES: usize = 3;
+
+/// Smoothed RTT estimate for one host
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RttEstimator {
+    /// Smoothed round-trip time in milliseconds
+    pub srtt_ms: f64,
+    /// Round-trip time variation in milliseconds
+    pub rttvar_ms: f64,
+    /// Number of samples seen
+    pub samples: usize,
+}
+
+impl RttEstimator {
+    /// Feed one RTT measurement
+    pub fn observe(&mut self, rtt_ms: u64) {
+        let rtt = rtt_ms as f64;
+        if self.samples == 0 {
+            self.srtt_ms = rtt;
+            self.rttvar_ms = rtt / 2.0;
+        } else {
+            self.rttvar_ms = 0.75 * self.rttvar_ms + 0.25 * (self.srtt_ms - rtt).abs();
+            self.srtt_ms = 0.875 * self.srtt_ms + 0.125 * rtt;
+        }
+        self.samples += 1;
+    }
+
+    /// Whether enough samples were seen to trust the estimate
+    pub fn is_calibrated(&self) -> bool {
+        self.samples >= CALIBRATION_SAMPLES
+    }
+
+    /// Retransmission timeout: `SRTT + 4 * RTTVAR` (at least 1ms of slack)
+    pub fn timeout_ms(&self) -> u64 {
+        (self.srtt_ms + (4.0 * self.rttvar_ms).max(1.0)).ceil() as u64
+    }
+}
+
+/// Timeout and retry budget for one probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeTiming {
+    pub timeout: Duration,
+    pub retries: usize,
+}
+
+/// RTT estimators for every target of a run, shared by the scanners
+#[derive(Debug, Clone)]
+pub struct HostTimings {
+    config: AdaptiveTimeoutConfig,
+    estimators: Arc<Mutex<HashMap<IpAddr, RttEstimator>>>,
+}
+
+impl HostTimings {
+    /// Create an empty table using the given bounds
+    pub fn new(config: AdaptiveTimeoutConfig) -> Self {
+        Self {
+            config,
+            estimators: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Record an RTT measured against `target`
+    pub fn observe(&self, target: IpAddr, rtt_ms: u64) {
+        self.estimators.lock().unwrap().entry(target).or_default().observe(rtt_ms);
+    }
+
+    /// Current estimate for `target`, if it answered anything yet
+    pub fn estimate(&self, target: IpAddr) -> Option<RttEstimator> {
+        self.estimators.lock().unwrap().get(&target).copied()
+    }
+
+    /// Timing for the next probe to `target`
+    ///
+    /// Uncalibrated hosts keep the scanner's own timeout and retries, clamped
+    /// to the configured bounds.
+    pub fn timing_for(&self, target: IpAddr, default_timeout_ms: u64, default_retries: usize) -> ProbeTiming {
+        let (timeout_ms, retries) = match self.estimate(target) {
+            Some(estimate) if estimate.is_calibrated() => {
+                // A jittery host gets one extra attempt
+                let jittery = estimate.rttvar_ms > estimate.srtt_ms / 2.0;
+                (estimate.timeout_ms(), self.config.min_retries + jittery as usize)
+            }
+            _ => (default_timeout_ms, default_retries),
+        };
+
+        ProbeTiming {
+            timeout: Duration::from_millis(timeout_ms.clamp(self.config.min_timeout_ms, self.config.max_timeout_ms)),
+            retries: retries.clamp(self.config.min_retries, self.config.max_retries),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AdaptiveTimeoutConfig {
+        AdaptiveTimeoutConfig {
+            enabled: true,
+            min_timeout_ms: 50,
+            max_timeout_ms: 5000,
+            min_retries: 0,
+            max_retries: 2,
+        }
+    }
+
+    #[test]
+    fn test_rtt_estimator() {
+        let mut estimator = RttEstimator::default();
+        estimator.observe(100);
+        assert_eq!((estimator.srtt_ms, estimator.rttvar_ms), (100.0, 50.0));
+        assert_eq!(estimator.timeout_ms(), 300);
+
+        estimator.observe(100);
+        assert_eq!(estimator.srtt_ms, 100.0);
+        assert_eq!(estimator.rttvar_ms, 37.5);
+        assert!(!estimator.is_calibrated());
+    }
+
+    #[test]
+    fn test_timing_for_calibrated_host() {
+        let timings = HostTimings::new(config());
+        let lan: IpAddr = "192.0.2.10".parse().unwrap();
+        let wan: IpAddr = "198.51.100.10".parse().unwrap();
+
+        // Unknown hosts use the scanner defaults within bounds
+        let default = timings.timing_for(lan, 10_000, 3);
+        assert_eq!(default, ProbeTiming { timeout: Duration::from_millis(5000), retries: 2 });
+
+        for _ in 0..10 {
+            timings.observe(lan, 1);
+            timings.observe(wan, 800);
+        }
+        let lan_timing = timings.timing_for(lan, 2000, 1);
+        assert_eq!(lan_timing.timeout, Duration::from_millis(50));
+        assert_eq!(lan_timing.retries, 0);
+        let wan_timing = timings.timing_for(wan, 2000, 1);
+        assert!(wan_timing.timeout >= Duration::from_millis(800));
+        assert!(wan_timing.timeout < Duration::from_millis(2000));
+    }
+}
@@ -5,12 +5,38 @@
 /// The scanner uses timeouts and ICMP port unreachable messages to detect closed ports.
 
 use crate::config::UdpConfig;
+use crate::detection::udp_fingerprint;
+use crate::detection::ServiceFingerprint;
 use crate::error::{ScanError, ScanResult};
+use crate::scanner::confidence::Confidence;
+use crate::scanner::evasion::{EvasionOutcome, ProbeEvasion};
+#[cfg(not(feature = "no-net"))]
+use crate::scanner::icmp_ratelimit::{IcmpRateDetector, IcmpRateLimit, ProbePacer};
+use crate::scanner::icmp_unreachable::{self, IcmpUnreachable};
+use crate::scanner::pause::PauseControl;
+use crate::scanner::probe_error::ProbeError;
+use crate::scanner::port_frequency::TransportProtocol;
+use crate::scanner::progress::ProgressTracker;
+use crate::scanner::reason::PortReason;
+#[cfg(not(feature = "no-net"))]
+use crate::scanner::resources;
+use crate::scanner::retry::{filtered_ports, merge_reprobes, RetryPolicy};
+use crate::scanner::service_names::service_name;
 use crate::scanner::tcp_connect::PortStatus;
+use crate::scanner::throttle::{ScanRateLimiter, ThrottleScanResult, ThrottleStrategy};
+use crate::scanner::timing::{HostTimings, ProbeTiming};
+#[cfg(not(feature = "no-net"))]
+use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
+#[cfg(not(feature = "no-net"))]
+use std::sync::Mutex;
+use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(feature = "no-net"))]
 use tokio::net::UdpSocket;
+use tokio::sync::Semaphore;
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
 /// UDP scan result
@@ -21,6 +47,30 @@ pub struct UdpScanResult {
     pub status: PortStatus,
     pub response_time_ms: Option<u64>,
     pub response_data: Option<Vec<u8>>,
+    /// Evasion mode the probe was sent with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evasion: Option<EvasionOutcome>,
+    /// Probes sent for this port, including retries and re-probes
+    #[serde(default)]
+    pub attempts: u32,
+    /// IANA service name of the port, e.g. "ssh"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Why the probe failed, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ProbeError>,
+    /// ICMP destination unreachable that settled the port, with its sender
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icmp: Option<IcmpUnreachable>,
+    /// Service, product and device details parsed from the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<ServiceFingerprint>,
+    /// How well the evidence supports the status, 0-100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    /// The answer that settled the status (`--reason`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<PortReason>,
 }
 
 /// UDP scanner
@@ -29,10 +79,23 @@ pub struct UdpScanResult {
 /// - Open|Filtered: No response or any response (can't distinguish without ICMP)
 /// - Closed: ICMP port unreachable (requires raw socket to detect)
 /// - Open: Only if service responds to our probe
+#[cfg(not(feature = "no-net"))]
 pub struct UdpScanner {
     config: UdpConfig,
+    evasion: ProbeEvasion,
+    timings: Option<HostTimings>,
+    rate_limiter: Option<Arc<ScanRateLimiter>>,
+    probe_permits: Option<Arc<Semaphore>>,
+    throttle: Option<Arc<dyn ThrottleStrategy>>,
+    retry: RetryPolicy,
+    progress: Option<Arc<ProgressTracker>>,
+    cancel: CancellationToken,
+    pause: PauseControl,
+    /// ICMP rate limits found on scanned hosts, until their results collect them
+    icmp_limits: Mutex<HashMap<IpAddr, IcmpRateLimit>>,
 }
 
+#[cfg(not(feature = "no-net"))]
 impl UdpScanner {
     /// Create a new UDP scanner
     pub fn new(config: UdpConfig) -> Self {
@@ -40,7 +103,97 @@ impl UdpScanner {
             "Initializing UDP scanner: timeout={}ms, retries={}",
             config.timeout_ms, config.retries
         );
-        Self { config }
+        Self {
+            config,
+            evasion: ProbeEvasion::default(),
+            timings: None,
+            rate_limiter: None,
+            probe_permits: None,
+            throttle: None,
+            retry: RetryPolicy::default(),
+            progress: None,
+            cancel: CancellationToken::new(),
+            pause: PauseControl::new(),
+            icmp_limits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Send probes with the given evasion options
+    ///
+    /// Probes go through a kernel socket, so only the TTL applies.
+    pub fn with_evasion(mut self, evasion: ProbeEvasion) -> Self {
+        self.evasion = evasion.for_kernel_sockets("UDP");
+        self
+    }
+
+    /// Derive per-host timeouts and retries from measured RTTs
+    pub fn with_host_timings(mut self, timings: HostTimings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Pace every probe through a run-wide rate limiter
+    pub fn with_rate_limiter(mut self, limiter: Arc<ScanRateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Hold a permit of a run-wide semaphore while probing each port
+    pub fn with_probe_permits(mut self, permits: Option<Arc<Semaphore>>) -> Self {
+        self.probe_permits = permits;
+        self
+    }
+
+    /// Report probe outcomes to, and wait on, a congestion-control strategy
+    pub fn with_throttle(mut self, throttle: Arc<dyn ThrottleStrategy>) -> Self {
+        self.set_throttle(throttle);
+        self
+    }
+
+    /// Replace the throttle, e.g. after a configuration reload
+    pub(super) fn set_throttle(&mut self, throttle: Arc<dyn ThrottleStrategy>) {
+        self.throttle = Some(throttle);
+    }
+
+    /// Back off between retries and optionally re-probe filtered ports
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Count finished port probes towards the scan's progress
+    pub fn with_progress(mut self, progress: Arc<ProgressTracker>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop starting new probes and retries once `cancel` fires
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Hold new probes while `pause` is paused
+    pub fn with_pause_control(mut self, pause: PauseControl) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Override the configured per-probe timeout
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Timeout and retries for the next probe to `target`
+    fn timing(&self, target: IpAddr) -> ProbeTiming {
+        match self.timings {
+            Some(ref timings) => timings.timing_for(target, self.config.timeout_ms, self.config.retries),
+            None => ProbeTiming {
+                timeout: Duration::from_millis(self.config.timeout_ms),
+                retries: self.config.retries,
+            },
+        }
     }
 
     /// Scan a single UDP port on a target host
@@ -52,6 +205,12 @@ impl UdpScanner {
     /// # Returns
     /// * `ScanResult<UdpScanResult>` - Scan result with port status
     pub async fn scan_port(&self, target: IpAddr, port: u16) -> ScanResult<UdpScanResult> {
+        self.probe_port(target, port, None).await
+    }
+
+    /// Scan a port; a paced probe waits for its slot and is sent only once,
+    /// since the pacing rather than a retry is what gets it an answer
+    async fn probe_port(&self, target: IpAddr, port: u16, pacer: Option<&ProbePacer>) -> ScanResult<UdpScanResult> {
         if !self.config.enabled {
             return Err(ScanError::scanner_error("UDP scan is disabled"));
         }
@@ -59,16 +218,57 @@ impl UdpScanner {
         debug!("UDP scan: {}:{}", target, port);
 
         let start = std::time::Instant::now();
+        let mut timing = self.timing(target);
+        if pacer.is_some() {
+            timing.retries = 0;
+        }
 
         // Attempt scan with retries
-        for attempt in 0..=self.config.retries {
+        let mut attempts = 0;
+        let mut last_error = None;
+        for attempt in 0..=timing.retries {
             if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(self.config.retry_delay_ms)).await;
+                if self.cancel.is_cancelled() {
+                    break;
+                }
+                if !self.retry.allows_retry(start.elapsed()) {
+                    debug!("Retry budget for {}:{} exhausted after {} attempts", target, port, attempts);
+                    break;
+                }
+                tokio::time::sleep(self.retry.delay(self.config.retry_delay_ms, attempt)).await;
                 debug!("Retrying UDP scan {}:{} (attempt {})", target, port, attempt + 1);
             }
+            if let Some(ref limiter) = self.rate_limiter {
+                limiter.acquire().await;
+            }
+            if let Some(ref throttle) = self.throttle {
+                throttle.wait().await?;
+            }
+            if let Some(pacer) = pacer {
+                pacer.wait().await;
+            }
+            // Checked last so probes queued on the rate limiter hold too
+            if !self.pause.wait_resumed(&self.cancel).await {
+                break;
+            }
+            attempts += 1;
+
+            let outcome = self.try_udp_probe(target, port, timing.timeout).await;
+            if let Some(ref throttle) = self.throttle {
+                let result = match outcome {
+                    Ok(ref result) => ThrottleScanResult::from_status(&result.status),
+                    Err(_) => ThrottleScanResult::Failure,
+                };
+                throttle.record_result(result).await;
+            }
 
-            match self.try_udp_probe(target, port).await {
-                Ok(result) => {
+            match outcome {
+                Ok(mut result) => {
+                    result.attempts = attempts;
+                    result.service = service_name(port, TransportProtocol::Udp);
+                    if let (Some(timings), Some(rtt)) = (&self.timings, result.response_time_ms) {
+                        timings.observe(target, rtt);
+                    }
                     let elapsed = start.elapsed();
                     crate::log_scan_event!(
                         tracing::Level::INFO,
@@ -79,7 +279,8 @@ impl UdpScanner {
                     );
                     return Ok(result);
                 }
-                Err(_e) => {
+                Err(e) => {
+                    last_error = Some(ProbeError::from_scan_error(&e));
                     continue;
                 }
             }
@@ -90,37 +291,70 @@ impl UdpScanner {
         Ok(UdpScanResult {
             target,
             port,
-            status: PortStatus::Filtered,
+            status: last_error.map_or(PortStatus::Filtered, |e| e.status()),
             response_time_ms: None,
             response_data: None,
+            evasion: self.evasion.outcome(false),
+            attempts,
+            error: last_error,
+            icmp: None,
+            service: service_name(port, TransportProtocol::Udp),
+            fingerprint: None,
+            confidence: None,
+            reason: None,
         })
     }
 
     /// Attempt a single UDP probe
-    async fn try_udp_probe(&self, target: IpAddr, port: u16) -> ScanResult<UdpScanResult> {
+    async fn try_udp_probe(&self, target: IpAddr, port: u16, timeout_duration: Duration) -> ScanResult<UdpScanResult> {
         // Bind to a local UDP socket
         let local_addr = match target {
             IpAddr::V4(_) => "0.0.0.0:0",
             IpAddr::V6(_) => "[::]:0",
         };
 
-        let socket = UdpSocket::bind(local_addr).await.map_err(|e| {
-            ScanError::udp_scan_failed(target, port, format!("Failed to bind socket: {}", e))
+        let socket = UdpSocket::bind(local_addr).await.map_err(|e| match ProbeError::from_io(&e) {
+            ProbeError::ResourceExhausted => ScanError::ResourceExhausted {
+                resource: "UDP socket".to_string(),
+                details: e.to_string(),
+            },
+            _ => ScanError::udp_scan_failed(target, port, format!("Failed to bind socket: {}", e)),
         })?;
+        resources::socket_opened();
+        if let Some(ttl) = self.evasion.ttl {
+            let sock_ref = socket2::SockRef::from(&socket);
+            match target {
+                IpAddr::V4(_) => sock_ref.set_ttl(ttl as u32),
+                IpAddr::V6(_) => sock_ref.set_unicast_hops_v6(ttl as u32),
+            }
+            .map_err(|e| ScanError::udp_scan_failed(target, port, format!("Failed to set TTL: {}", e)))?;
+        }
+        // Lets ICMP unreachables be told apart and traced to their sender
+        if let Err(e) = icmp_unreachable::enable_error_queue(&socket, target.is_ipv6()) {
+            debug!("ICMP error queue unavailable for {}:{}: {}", target, port, e);
+        }
 
         let target_addr = SocketAddr::new(target, port);
-        let timeout_duration = Duration::from_millis(self.config.timeout_ms);
         let start = std::time::Instant::now();
 
         // Send UDP probe packet
         // For better results, we should send service-specific probes
         let probe = self.create_probe_packet(port);
         
-        socket.send_to(&probe, target_addr).await.map_err(|e| {
-            ScanError::udp_scan_failed(target, port, format!("Failed to send probe: {}", e))
-        })?;
+        // Connected, so the kernel reports ICMP errors for the probe to us
+        let sent = match socket.connect(target_addr).await {
+            Ok(()) => socket.send(&probe).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            // No route or a local firewall settles the port without an answer
+            let error = ProbeError::from_io(&e);
+            debug!("UDP probe to {}:{} not sent ({}): {}", target, port, error, e);
+            return Ok(UdpScanResult::failed(target, port, error));
+        }
 
         debug!("Sent UDP probe to {}:{}", target, port);
+        resources::packet_sent(probe.len() + resources::udp_overhead(target));
 
         // Try to receive a response
         let mut buffer = vec![0u8; 4096];
@@ -129,6 +363,11 @@ impl UdpScanner {
             Ok(Ok((len, _addr))) => {
                 let elapsed = start.elapsed();
                 debug!("UDP port {}:{} responded with {} bytes", target, port, len);
+                resources::packet_received(len + resources::udp_overhead(target));
+                let mut fingerprint = udp_fingerprint::fingerprint(port, &buffer[..len]);
+                if let (123, Some(ref mut fingerprint)) = (port, fingerprint.as_mut()) {
+                    self.probe_ntp_details(&socket, target, fingerprint, timeout_duration).await;
+                }
                 
                 Ok(UdpScanResult {
                     target,
@@ -136,9 +375,27 @@ impl UdpScanner {
                     status: PortStatus::Open,
                     response_time_ms: Some(elapsed.as_millis() as u64),
                     response_data: Some(buffer[..len].to_vec()),
+                    evasion: self.evasion.outcome(true),
+                    attempts: 1,
+                    error: None,
+                    icmp: None,
+                    service: None,
+                    fingerprint,
+                    confidence: None,
+                    reason: None,
                 })
             }
             Ok(Err(e)) => {
+                if let Some(icmp) = icmp_unreachable::take_queued_unreachable(&socket) {
+                    let error = icmp.error(TransportProtocol::Udp);
+                    debug!("UDP port {}:{} is {} ({}, {})", target, port, error.status(), error, icmp);
+                    return Ok(UdpScanResult {
+                        response_time_ms: Some(start.elapsed().as_millis() as u64),
+                        evasion: self.evasion.outcome(true),
+                        icmp: Some(icmp),
+                        ..UdpScanResult::failed(target, port, error)
+                    });
+                }
                 // Check if we got ICMP port unreachable (ConnectionRefused)
                 if e.kind() == std::io::ErrorKind::ConnectionRefused {
                     debug!("UDP port {}:{} is CLOSED (ICMP port unreachable)", target, port);
@@ -146,15 +403,24 @@ impl UdpScanner {
                         target,
                         port,
                         status: PortStatus::Closed,
-                        response_time_ms: None,
+                        response_time_ms: Some(start.elapsed().as_millis() as u64),
                         response_data: None,
+                        evasion: self.evasion.outcome(true),
+                        attempts: 1,
+                        error: Some(ProbeError::Refused),
+                        icmp: None,
+                        service: None,
+                        fingerprint: None,
+                        confidence: None,
+                        reason: None,
                     })
                 } else {
-                    Err(ScanError::udp_scan_failed(
-                        target,
-                        port,
-                        format!("Socket error: {}", e),
-                    ))
+                    let error = ProbeError::from_io(&e);
+                    debug!("UDP port {}:{} is {} ({}): {}", target, port, error.status(), error, e);
+                    Ok(UdpScanResult {
+                        evasion: self.evasion.outcome(false),
+                        ..UdpScanResult::failed(target, port, error)
+                    })
                 }
             }
             Err(_) => {
@@ -166,11 +432,38 @@ impl UdpScanner {
                     status: PortStatus::Filtered,
                     response_time_ms: None,
                     response_data: None,
+                    evasion: self.evasion.outcome(false),
+                    attempts: 1,
+                    error: Some(ProbeError::Timeout),
+                    icmp: None,
+                    service: None,
+                    fingerprint: None,
+                    confidence: None,
+                    reason: None,
                 })
             }
         }
     }
 
+    /// Ask an NTP server that answered for its system variables (mode 6)
+    /// and whether it serves monlist (mode 7), on the probe's socket
+    async fn probe_ntp_details(&self, socket: &UdpSocket, target: IpAddr, fingerprint: &mut ServiceFingerprint, wait: Duration) {
+        let mut buffer = vec![0u8; 4096];
+        for probe in [udp_fingerprint::NTP_READVAR, udp_fingerprint::NTP_MONLIST] {
+            if let Some(ref limiter) = self.rate_limiter {
+                limiter.acquire().await;
+            }
+            if socket.send(probe).await.is_err() {
+                return;
+            }
+            resources::packet_sent(probe.len() + resources::udp_overhead(target));
+            if let Ok(Ok(len)) = timeout(wait, socket.recv(&mut buffer)).await {
+                resources::packet_received(len + resources::udp_overhead(target));
+                udp_fingerprint::merge_ntp(fingerprint, &buffer[..len]);
+            }
+        }
+    }
+
     /// Create a service-specific probe packet
     /// 
     /// Different UDP services respond to different probes.
@@ -205,16 +498,43 @@ impl UdpScanner {
                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 ]
             }
+            137 => {
+                // NetBIOS node status (NBSTAT) query for the wildcard name
+                let mut packet = vec![
+                    0x13, 0x37, // Transaction ID
+                    0x00, 0x00, // Flags: query
+                    0x00, 0x01, // Questions: 1
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x20, b'C', b'K', // "*" in first-level encoding
+                ];
+                packet.extend_from_slice(&[b'A'; 30]);
+                packet.extend_from_slice(&[0x00, 0x00, 0x21, 0x00, 0x01]); // NBSTAT, IN
+                packet
+            }
             161 => {
-                // SNMP GetRequest
+                // SNMP GetNextRequest for 1.3.6.1.2.1, answered with sysDescr.0
                 vec![
                     0x30, 0x26, 0x02, 0x01, 0x00, 0x04, 0x06, 0x70,
-                    0x75, 0x62, 0x6c, 0x69, 0x63, 0xa0, 0x19, 0x02,
+                    0x75, 0x62, 0x6c, 0x69, 0x63, 0xa1, 0x19, 0x02,
                     0x04, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01, 0x00,
                     0x02, 0x01, 0x00, 0x30, 0x0b, 0x30, 0x09, 0x06,
                     0x05, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x05, 0x00,
                 ]
             }
+            1900 => {
+                // SSDP discovery for every device type
+                b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: ssdp:all\r\n\r\n".to_vec()
+            }
+            5353 => {
+                // mDNS DNS-SD service enumeration, asking for a unicast reply
+                let mut packet = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+                for label in ["_services", "_dns-sd", "_udp", "local"] {
+                    packet.push(label.len() as u8);
+                    packet.extend_from_slice(label.as_bytes());
+                }
+                packet.extend_from_slice(&[0x00, 0x00, 0x0c, 0x80, 0x01]); // PTR, QU + IN
+                packet
+            }
             _ => {
                 // Generic probe - empty packet or minimal data
                 // Some services respond to any data
@@ -238,8 +558,6 @@ impl UdpScanner {
         ports: Vec<u16>,
         max_concurrent: usize,
     ) -> ScanResult<Vec<UdpScanResult>> {
-        use futures::stream::{self, StreamExt};
-
         info!(
             "UDP scan: {} ports on {} with concurrency {}",
             ports.len(),
@@ -247,21 +565,30 @@ impl UdpScanner {
             max_concurrent
         );
 
-        let results = stream::iter(ports)
-            .map(|port| async move {
-                match self.scan_port(target, port).await {
-                    Ok(result) => Some(result),
-                    Err(e) => {
-                        warn!("UDP scan failed for {}:{} - {}", target, port, e);
-                        None
-                    }
+        let detector = Mutex::new(IcmpRateDetector::default());
+        let mut results = self.probe_ports(target, ports, max_concurrent, Some(&detector), None).await;
+        let detector = detector.into_inner().unwrap();
+        let paced = match detector.inferred_rate() {
+            Some(per_second) if self.config.icmp_ratelimit_reprobes > 0 && !self.cancel.is_cancelled() => {
+                let limit = self.reprobe_paced(target, &mut results, per_second, max_concurrent).await;
+                self.icmp_limits.lock().unwrap().insert(target, limit);
+                true
+            }
+            _ => false,
+        };
+        // Ports the paced pass skipped are better left than hammered again
+        if self.retry.reprobe_filtered && !paced {
+            let filtered = filtered_ports(&results);
+            if !filtered.is_empty() {
+                debug!("Re-probing {} filtered ports on {}", filtered.len(), target);
+                if let Some(ref progress) = self.progress {
+                    progress.extend(target, filtered.len());
                 }
-            })
-            .buffer_unordered(max_concurrent)
-            .collect::<Vec<_>>()
-            .await;
-
-        let results: Vec<UdpScanResult> = results.into_iter().flatten().collect();
+                let reprobed = self.probe_ports(target, filtered, max_concurrent, None, None).await;
+                let resolved = merge_reprobes(&mut results, reprobed);
+                debug!("Re-probe resolved {} ports on {}", resolved, target);
+            }
+        }
         
         let open_count = results
             .iter()
@@ -284,6 +611,101 @@ impl UdpScanner {
         Ok(results)
     }
 
+    /// Re-probe the ports that timed out at the host's ICMP unreachable rate
+    async fn reprobe_paced(
+        &self,
+        target: IpAddr,
+        results: &mut [UdpScanResult],
+        per_second: f64,
+        max_concurrent: usize,
+    ) -> IcmpRateLimit {
+        let timed_out: Vec<u16> = results
+            .iter()
+            .filter(|r| r.error == Some(ProbeError::Timeout))
+            .map(|r| r.port)
+            .take(self.config.icmp_ratelimit_reprobes)
+            .collect();
+        let pacer = ProbePacer::new(per_second);
+        // Enough probes in flight to fill every slot within one timeout
+        let timeout = self.timing(target).timeout;
+        let in_flight = (timeout.as_secs_f64() / pacer.interval().as_secs_f64()).ceil() as usize + 1;
+        warn!(
+            "{} limits ICMP unreachables to ~{:.1}/s: re-probing {} timed-out UDP ports one per {}ms",
+            target,
+            per_second,
+            timed_out.len(),
+            pacer.interval().as_millis()
+        );
+
+        let reprobed_ports = timed_out.len();
+        if let Some(ref progress) = self.progress {
+            progress.extend(target, reprobed_ports);
+        }
+        let reprobed = self
+            .probe_ports(target, timed_out, in_flight.min(max_concurrent.max(1)), None, Some(&pacer))
+            .await;
+        let resolved_ports = merge_reprobes(results, reprobed);
+        debug!("Paced re-probe resolved {} of {} ports on {}", resolved_ports, reprobed_ports, target);
+        IcmpRateLimit {
+            per_second,
+            reprobed_ports,
+            resolved_ports,
+        }
+    }
+
+    /// ICMP rate limit found on `target` by its last scan, handed over once
+    pub fn take_icmp_rate_limit(&self, target: IpAddr) -> Option<IcmpRateLimit> {
+        self.icmp_limits.lock().unwrap().remove(&target)
+    }
+
+    /// One pass over `ports`, dropping ports whose probes errored out
+    ///
+    /// Finished probes are shown to `detector` as they finish, and
+    /// `pacer` spaces the probes out.
+    async fn probe_ports(
+        &self,
+        target: IpAddr,
+        ports: Vec<u16>,
+        max_concurrent: usize,
+        detector: Option<&Mutex<IcmpRateDetector>>,
+        pacer: Option<&ProbePacer>,
+    ) -> Vec<UdpScanResult> {
+        use futures::stream::{self, StreamExt};
+
+        // In-flight probes finish; no new ones start after cancellation
+        let results = stream::iter(ports)
+            .take_until(self.cancel.cancelled())
+            .map(|port| async move {
+                // Run-wide cap on probes in flight; the semaphore is never closed
+                let _permit = match self.probe_permits {
+                    Some(ref permits) => permits.acquire().await.ok(),
+                    None => None,
+                };
+                let result = self.probe_port(target, port, pacer).await;
+                if let Some(ref progress) = self.progress {
+                    progress.port_done(target);
+                }
+                match result {
+                    Ok(result) => {
+                        if let Some(detector) = detector {
+                            detector.lock().unwrap().observe(&result);
+                        }
+                        Some(result)
+                    }
+                    Err(_) if self.cancel.is_cancelled() => None,
+                    Err(e) => {
+                        warn!("UDP scan failed for {}:{} - {}", target, port, e);
+                        Some(UdpScanResult::failed(target, port, ProbeError::from_scan_error(&e)))
+                    }
+                }
+            })
+            .buffer_unordered(max_concurrent)
+            .collect::<Vec<_>>()
+            .await;
+
+        results.into_iter().flatten().collect()
+    }
+
     /// Scan a port range on a target host using UDP
     pub async fn scan_port_range(
         &self,
@@ -304,13 +726,35 @@ impl UdpScanner {
     }
 }
 
+impl UdpScanResult {
+    /// Result for a port whose probes all failed with `error`
+    pub fn failed(target: IpAddr, port: u16, error: ProbeError) -> Self {
+        Self {
+            target,
+            port,
+            status: error.status(),
+            response_time_ms: None,
+            response_data: None,
+            evasion: None,
+            attempts: 0,
+            error: Some(error),
+            icmp: None,
+            service: service_name(port, TransportProtocol::Udp),
+            fingerprint: None,
+            confidence: None,
+            reason: None,
+        }
+    }
+}
+
 impl std::fmt::Display for UdpScanResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}:{} - {} ({}ms)",
+            "{}:{}/udp{} - {} ({}ms)",
             self.target,
             self.port,
+            self.service.as_deref().map_or(String::new(), |s| format!(" {}", s)),
             self.status,
             self.response_time_ms
                 .map_or("N/A".to_string(), |t| t.to_string())
@@ -319,12 +763,29 @@ impl std::fmt::Display for UdpScanResult {
         if let Some(ref data) = self.response_data {
             write!(f, " [{} bytes]", data.len())?;
         }
+
+        if let Some(ref fingerprint) = self.fingerprint {
+            write!(f, " {{{}}}", fingerprint)?;
+        }
+
+        if let Some(ref evasion) = self.evasion {
+            write!(f, " <{}>", evasion)?;
+        }
+
+        // Refusals and timeouts are what closed and filtered already say
+        match (self.error, self.icmp) {
+            (Some(error), Some(icmp)) => write!(f, " ({}, {})", error, icmp)?,
+            (Some(error), None) if !matches!(error, ProbeError::Refused | ProbeError::Timeout) => {
+                write!(f, " ({})", error)?
+            }
+            _ => {}
+        }
         
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no-net")))]
 mod tests {
     use super::*;
     use std::net::Ipv4Addr;
@@ -335,6 +796,7 @@ mod tests {
             timeout_ms: 2000,
             retries: 2,
             retry_delay_ms: 200,
+            icmp_ratelimit_reprobes: 16,
         }
     }
 
@@ -362,6 +824,21 @@ mod tests {
         assert!(!generic_probe.is_empty());
     }
 
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_icmp_port_unreachable_closes_port() {
+        let scanner = UdpScanner::new(create_test_config());
+        // Reserve a port, then free it so nothing listens there
+        let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+        let target = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let result = scanner.scan_port(target, port).await.unwrap();
+        assert_eq!(result.status, PortStatus::Closed);
+        assert_eq!(result.error, Some(ProbeError::Refused));
+        let icmp = result.icmp.unwrap();
+        assert_eq!((icmp.from, icmp.icmp_type, icmp.code), (target, 3, 3));
+    }
+
     #[tokio::test]
     async fn test_scan_disabled() {
         let mut config = create_test_config();
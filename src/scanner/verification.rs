@@ -0,0 +1,159 @@
+//! Open port verification with an alternate scan type
+//!
+//! IDS and deception products answer SYNs with forged SYN-ACKs for ports that
+//! do not really accept connections. A verification pass re-checks every port
+//! one scan type reported open with the other TCP scan type (SYN results with
+//! a full connect, connect results with a SYN probe) and records where the
+//! two disagree.
+
+use crate::scanner::tcp_connect::{PortStatus, TcpConnectResult};
+use crate::scanner::tcp_syn::TcpSynResult;
+use crate::scanner::ScanType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Second opinion on a port one scan type reported open
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortVerification {
+    pub port: u16,
+    /// Scan type that reported the port open
+    pub reported_by: ScanType,
+    /// Scan type used to re-check it
+    pub verified_with: ScanType,
+    /// Status seen by the verifying scan
+    pub verified_status: PortStatus,
+}
+
+impl PortVerification {
+    /// Whether the verifying scan also saw the port open
+    pub fn confirmed(&self) -> bool {
+        self.verified_status == PortStatus::Open
+    }
+}
+
+impl fmt::Display for PortVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "port {} open by {} but {} by {}",
+            self.port,
+            scan_type_name(self.reported_by),
+            self.verified_status,
+            scan_type_name(self.verified_with)
+        )?;
+        if self.reported_by == ScanType::TcpSyn {
+            write!(f, " (possible fake SYN-ACK responder)")?;
+        }
+        Ok(())
+    }
+}
+
+fn scan_type_name(scan_type: ScanType) -> &'static str {
+    match scan_type {
+        ScanType::TcpConnect => "TCP connect",
+        ScanType::TcpSyn => "TCP SYN",
+        ScanType::Udp => "UDP",
+    }
+}
+
+/// Compare ports both TCP scan types already probed
+///
+/// Only ports reported open by at least one side are checked.
+pub fn cross_check(tcp_results: &[TcpConnectResult], syn_results: &[TcpSynResult]) -> Vec<PortVerification> {
+    let mut verifications = Vec::new();
+    for syn in syn_results {
+        let Some(tcp) = tcp_results.iter().find(|t| t.port == syn.port) else {
+            continue;
+        };
+        if syn.status == PortStatus::Open {
+            verifications.push(PortVerification {
+                port: syn.port,
+                reported_by: ScanType::TcpSyn,
+                verified_with: ScanType::TcpConnect,
+                verified_status: tcp.status.clone(),
+            });
+        } else if tcp.status == PortStatus::Open {
+            verifications.push(PortVerification {
+                port: tcp.port,
+                reported_by: ScanType::TcpConnect,
+                verified_with: ScanType::TcpSyn,
+                verified_status: syn.status.clone(),
+            });
+        }
+    }
+    verifications
+}
+
+/// Open ports from one result set that the other did not probe
+pub fn unverified_open_ports<'a>(
+    open: impl Iterator<Item = (u16, &'a PortStatus)>,
+    probed: &[u16],
+) -> Vec<u16> {
+    open.filter(|(port, status)| **status == PortStatus::Open && !probed.contains(port))
+        .map(|(port, _)| port)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    const TARGET: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn tcp(port: u16, status: PortStatus) -> TcpConnectResult {
+        TcpConnectResult {
+            target: TARGET,
+            port,
+            status,
+            response_time_ms: None,
+            banner: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            service: None,
+            error_class: None,
+            confidence: None,
+            reason: None,
+        }
+    }
+
+    fn syn(port: u16, status: PortStatus) -> TcpSynResult {
+        TcpSynResult {
+            target: TARGET,
+            port,
+            status,
+            response_time_ms: None,
+            flags: None,
+            evasion: None,
+            attempts: 1,
+            error: None,
+            icmp: None,
+            service: None,
+            confidence: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_cross_check_flags_fake_syn_ack() {
+        let tcp_results = vec![tcp(22, PortStatus::Open), tcp(80, PortStatus::Closed), tcp(443, PortStatus::Closed)];
+        let syn_results = vec![syn(22, PortStatus::Open), syn(80, PortStatus::Open), syn(443, PortStatus::Closed)];
+
+        let checks = cross_check(&tcp_results, &syn_results);
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].confirmed());
+        assert!(!checks[1].confirmed());
+        assert_eq!(
+            checks[1].to_string(),
+            "port 80 open by TCP SYN but closed by TCP connect (possible fake SYN-ACK responder)"
+        );
+    }
+
+    #[test]
+    fn test_unverified_open_ports() {
+        let syn_results = [syn(22, PortStatus::Open), syn(25, PortStatus::Open), syn(80, PortStatus::Filtered)];
+        let open = unverified_open_ports(syn_results.iter().map(|r| (r.port, &r.status)), &[22]);
+        assert_eq!(open, vec![25]);
+    }
+}
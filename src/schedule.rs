@@ -0,0 +1,401 @@
+//! Recurring scans
+//!
+//! `nrmap schedule FILE` runs the scans saved in a schedule file whenever
+//! their cron expression comes due. Each run is recorded in the asset
+//! history and compared with the same scan's previous run. When something
+//! changed, a [`ScanChangeEvent`] goes to the configured notification
+//! channels.
+//!
+//! ```toml
+//! # Asset history and every scan's last results are kept here
+//! state_dir = "/var/lib/nrmap"
+//!
+//! [notify]
+//! webhooks = ["https://hooks.example.com/nrmap"]
+//!
+//! [[scans]]
+//! name = "dmz"
+//! cron = "0 */6 * * *"
+//! targets = ["203.0.113.0/24"]
+//! ports = "22,80,443,8000-8100"
+//!
+//! [[scans]]
+//! name = "dns-nightly"
+//! cron = "@daily"
+//! targets = ["10.0.0.53", "10.0.1.53"]
+//! ports = "53"
+//! scan_types = ["tcp", "udp"]
+//! # UDP answers are sparse; only notify about well-supported changes
+//! min_confidence = 80
+//! ```
+//!
+//! Cron expressions have five fields (`minute hour day month weekday`) and
+//! are evaluated in local time; `@hourly`, `@daily`, `@weekly` and similar
+//! nicknames work too.
+
+use crate::error::{ScanError, ScanResult};
+use crate::notify::{Notifier, NotifyConfig};
+use crate::report::diff::{AssetChange, ScanDiff};
+use crate::report::history::AssetHistory;
+pub use crate::scanner::blackout::CronSchedule;
+use crate::scanner::targets::expand_target;
+use crate::scanner::{CompleteScanResult, Confidence, ScanType, Scanner};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+/// A saved scan definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledScan {
+    /// Unique name; also names the scan's state file
+    pub name: String,
+    pub cron: CronSchedule,
+    /// Addresses and CIDR blocks
+    pub targets: Vec<String>,
+    /// Ports as on the command line, e.g. "22,80,443", "1-1024" or "ssh,https"
+    pub ports: String,
+    /// `tcp`, `syn` or `udp`
+    #[serde(default = "default_scan_types")]
+    pub scan_types: Vec<String>,
+    /// Ignore port changes found with less confidence than this (0-100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<Confidence>,
+}
+
+fn default_scan_types() -> Vec<String> {
+    vec!["tcp".to_string()]
+}
+
+/// What a scheduled scan expands to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanPlan {
+    pub targets: Vec<IpAddr>,
+    pub ports: Vec<u16>,
+    pub scan_types: Vec<ScanType>,
+}
+
+impl ScheduledScan {
+    /// Expand targets, ports and scan types
+    pub fn plan(&self) -> ScanResult<ScanPlan> {
+        let mut targets = Vec::new();
+        for target in &self.targets {
+            targets.extend(expand_target(target)?);
+        }
+        let scan_types = self
+            .scan_types
+            .iter()
+            .map(|t| t.parse())
+            .collect::<ScanResult<Vec<ScanType>>>()?;
+        Ok(ScanPlan {
+            targets,
+            ports: crate::parse_port_range(&self.ports)?,
+            scan_types,
+        })
+    }
+
+    fn state_file(&self, state_dir: &Path) -> PathBuf {
+        state_dir.join(format!("{}.last.json", self.name))
+    }
+}
+
+/// A schedule file: where state lives, who to notify and what to scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleFile {
+    /// Directory for the asset history and every scan's last results
+    pub state_dir: PathBuf,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub scans: Vec<ScheduledScan>,
+}
+
+impl ScheduleFile {
+    /// Load a schedule from a TOML or YAML file, chosen by extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ScanResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ScanError::validation_error("schedule", format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let invalid =
+            |e: String| ScanError::validation_error("schedule", format!("Invalid schedule {}: {}", path.display(), e));
+
+        let schedule: ScheduleFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            _ => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+        };
+        schedule.validate().map_err(|e| invalid(e.to_string()))?;
+        Ok(schedule)
+    }
+
+    /// Check that names are unique and usable as file names, and that
+    /// every scan expands
+    pub fn validate(&self) -> ScanResult<()> {
+        let mut names = HashSet::new();
+        for scan in &self.scans {
+            let usable = !scan.name.is_empty()
+                && scan.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                && !scan.name.starts_with('.');
+            if !usable {
+                return Err(ScanError::validation_error(
+                    "name",
+                    format!("'{}' must use only letters, digits, '-', '_' and '.'", scan.name),
+                ));
+            }
+            if !names.insert(scan.name.as_str()) {
+                return Err(ScanError::validation_error("name", format!("duplicate scan '{}'", scan.name)));
+            }
+            let plan = scan.plan()?;
+            if plan.targets.is_empty() || plan.ports.is_empty() {
+                return Err(ScanError::validation_error(
+                    "scans",
+                    format!("scan '{}' has no targets or no ports", scan.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sent when a scheduled scan found something different from its last run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanChangeEvent {
+    /// Always `scan_changed`
+    pub event: String,
+    pub scan: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub hosts_scanned: usize,
+    pub changes: Vec<AssetChange>,
+}
+
+/// Runs the scans of a schedule file when they come due
+pub struct RecurringScans {
+    schedule: ScheduleFile,
+    scanner: Scanner,
+    notifier: Notifier,
+}
+
+impl RecurringScans {
+    /// Run `schedule` with `scanner`; cancelling the scanner stops the loop
+    pub fn new(schedule: ScheduleFile, scanner: Scanner) -> Self {
+        let notifier = Notifier::new(schedule.notify.clone());
+        Self {
+            schedule,
+            scanner,
+            notifier,
+        }
+    }
+
+    /// Scans in the schedule
+    pub fn scans(&self) -> &[ScheduledScan] {
+        &self.schedule.scans
+    }
+
+    /// Wait for each scan's next due time and run it, until cancelled
+    ///
+    /// A failed run is logged and the scan is retried at its next due time.
+    pub async fn run(&self) -> ScanResult<()> {
+        let token = self.scanner.cancellation_token();
+        let mut due: Vec<Option<DateTime<Local>>> = self
+            .schedule
+            .scans
+            .iter()
+            .map(|scan| scan.cron.next_after(&Local::now()))
+            .collect();
+        for (scan, next) in self.schedule.scans.iter().zip(&due) {
+            match next {
+                Some(next) => info!("Scan '{}' ({}) first runs at {}", scan.name, scan.cron, next),
+                None => warn!("Scan '{}' ({}) never comes due", scan.name, scan.cron),
+            }
+        }
+
+        loop {
+            let Some(next) = due.iter().flatten().min().copied() else {
+                info!("No scan comes due again");
+                return Ok(());
+            };
+            let wait = (next - Local::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(wait) => {}
+            }
+
+            for (index, scan) in self.schedule.scans.iter().enumerate() {
+                if due[index].is_some_and(|at| at <= Local::now()) {
+                    if let Err(e) = self.run_scan(scan).await {
+                        error!("Scheduled scan '{}' failed: {}", scan.name, e);
+                    }
+                    if token.is_cancelled() {
+                        return Ok(());
+                    }
+                    due[index] = scan.cron.next_after(&Local::now());
+                }
+            }
+        }
+    }
+
+    /// Run one scan now: record it, compare it with the previous run and
+    /// notify about changes
+    ///
+    /// Returns the differences, or `None` for the first run of a scan, which
+    /// only records a baseline.
+    pub async fn run_scan(&self, scan: &ScheduledScan) -> ScanResult<Option<ScanDiff>> {
+        let plan = scan.plan()?;
+        let started_at = Utc::now();
+        info!(
+            "Running scheduled scan '{}': {} targets, {} ports",
+            scan.name,
+            plan.targets.len(),
+            plan.ports.len()
+        );
+        let results = self
+            .scanner
+            .scan_multiple(plan.targets, plan.ports, plan.scan_types)
+            .await?;
+        if self.scanner.is_cancelled() {
+            warn!("Scheduled scan '{}' was cancelled; partial results are not recorded", scan.name);
+            return Ok(None);
+        }
+        let finished_at = Utc::now();
+
+        let state_dir = &self.schedule.state_dir;
+        std::fs::create_dir_all(state_dir).map_err(|e| {
+            ScanError::scanner_error(format!("Failed to create state directory {}: {}", state_dir.display(), e))
+        })?;
+        let history_path = state_dir.join("history.json");
+        let mut history = AssetHistory::load(&history_path)?;
+        history.record(&results, finished_at);
+        history.save(&history_path)?;
+
+        let state_file = scan.state_file(state_dir);
+        let previous = load_results(&state_file)?;
+        save_results(&state_file, &results)?;
+        let Some(previous) = previous else {
+            info!("Scheduled scan '{}' recorded its first results as the baseline", scan.name);
+            return Ok(None);
+        };
+
+        let mut diff = ScanDiff::between(&previous, &results);
+        if let Some(min) = scan.min_confidence {
+            diff.retain_confident(&results, min);
+        }
+        if diff.is_empty() {
+            info!("Scheduled scan '{}' found no changes", scan.name);
+            return Ok(Some(diff));
+        }
+        info!("Scheduled scan '{}' found {} changes: {}", scan.name, diff.changes.len(), diff);
+        let event = ScanChangeEvent {
+            event: "scan_changed".to_string(),
+            scan: scan.name.clone(),
+            started_at,
+            finished_at,
+            hosts_scanned: results.len(),
+            changes: diff.changes.clone(),
+        };
+        if let Err(e) = self.notifier.notify(&event).await {
+            warn!("Scheduled scan '{}': {}", scan.name, e);
+        }
+        Ok(Some(diff))
+    }
+}
+
+fn load_results(path: &Path) -> ScanResult<Option<Vec<CompleteScanResult>>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(ScanError::scanner_error(format!("Failed to read {}: {}", path.display(), e)));
+        }
+    };
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| ScanError::scanner_error(format!("Invalid results in {}: {}", path.display(), e)))
+}
+
+/// Replace the file in one rename, so a crash never leaves it half written
+fn save_results(path: &Path, results: &[CompleteScanResult]) -> ScanResult<()> {
+    let json = serde_json::to_string(results)
+        .map_err(|e| ScanError::scanner_error(format!("Failed to serialize results: {}", e)))?;
+    let partial = path.with_extension("json.tmp");
+    std::fs::write(&partial, json)
+        .and_then(|_| std::fs::rename(&partial, path))
+        .map_err(|e| ScanError::scanner_error(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use chrono::TimeZone;
+
+    const SCHEDULE: &str = r#"
+        state_dir = "STATE"
+
+        [notify]
+        stdout = false
+
+        [[scans]]
+        name = "loopback"
+        cron = "*/5 * * * *"
+        targets = ["127.0.0.1"]
+        ports = "PORT"
+    "#;
+
+    #[test]
+    fn test_cron_schedule() {
+        let hourly: CronSchedule = "15 * * * *".parse().unwrap();
+        let at = Utc.with_ymd_and_hms(2026, 5, 1, 10, 20, 0).unwrap();
+        assert_eq!(hourly.next_after(&at), Some(Utc.with_ymd_and_hms(2026, 5, 1, 11, 15, 0).unwrap()));
+
+        let daily: CronSchedule = "@daily".parse().unwrap();
+        assert_eq!(daily.next_after(&at), Some(Utc.with_ymd_and_hms(2026, 5, 2, 0, 0, 0).unwrap()));
+        assert_eq!(daily.to_string(), "@daily");
+
+        assert!("61 * * * *".parse::<CronSchedule>().is_err());
+        assert!("every hour".parse::<CronSchedule>().is_err());
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        let schedule = |name: &str, ports: &str| -> ScanResult<ScheduleFile> {
+            let source = SCHEDULE.replace("loopback", name).replace("PORT", ports);
+            let schedule: ScheduleFile = toml::from_str(&source).unwrap();
+            schedule.validate().map(|_| schedule)
+        };
+
+        let plan = schedule("web.daily", "80,https").unwrap().scans[0].plan().unwrap();
+        assert_eq!(plan.ports, vec![80, 443]);
+        assert_eq!(plan.scan_types, vec![ScanType::TcpConnect]);
+        assert!(schedule("../escape", "80").is_err());
+        assert!(schedule("web", "not-a-port").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_scan_diffs_against_previous_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let source = SCHEDULE
+            .replace("STATE", &dir.path().display().to_string())
+            .replace("PORT", &port.to_string());
+        let schedule: ScheduleFile = toml::from_str(&source).unwrap();
+
+        let mut config = AppConfig::default().scanner;
+        config.host_discovery.enabled = false;
+        config.tcp_connect.timeout_ms = 500;
+        let runner = RecurringScans::new(schedule, Scanner::new(config).with_privilege_check(false));
+        let scan = runner.scans()[0].clone();
+
+        assert_eq!(runner.run_scan(&scan).await.unwrap(), None);
+        assert!(dir.path().join("loopback.last.json").exists());
+        assert!(dir.path().join("history.json").exists());
+        assert!(runner.run_scan(&scan).await.unwrap().unwrap().is_empty());
+
+        drop(listener);
+        let diff = runner.run_scan(&scan).await.unwrap().unwrap();
+        assert!(matches!(diff.changes[..], [AssetChange::PortClosed { port: closed, .. }] if closed == port));
+    }
+}
@@ -0,0 +1,116 @@
+//! End-to-end scanner tests against simulated hosts in network namespaces
+//!
+//! Run with `sudo -E cargo test --features netns-tests --test netns_scan`.
+//! Tests skip themselves when root or the `ip` tool is unavailable.
+
+#![cfg(all(target_os = "linux", feature = "netns-tests"))]
+
+mod netns;
+
+use netns::SimulatedHost;
+use nrmap::config::{TcpConnectConfig, TcpSynConfig, UdpConfig};
+use nrmap::scanner::privileges::PingMode;
+use nrmap::scanner::tcp_connect::{PortStatus, TcpConnectScanner};
+use nrmap::scanner::tcp_syn::TcpSynScanner;
+use nrmap::scanner::udp_scan::UdpScanner;
+
+const TIMEOUT_MS: u64 = 500;
+
+fn tcp_scanner() -> TcpConnectScanner {
+    TcpConnectScanner::new(TcpConnectConfig {
+        enabled: true,
+        timeout_ms: TIMEOUT_MS,
+        retries: 0,
+        retry_delay_ms: 0,
+    })
+}
+
+fn udp_scanner() -> UdpScanner {
+    UdpScanner::new(UdpConfig {
+        enabled: true,
+        timeout_ms: TIMEOUT_MS,
+        retries: 0,
+        retry_delay_ms: 0,
+        icmp_ratelimit_reprobes: 0,
+    })
+}
+
+#[tokio::test]
+async fn test_tcp_connect_open_closed_filtered() {
+    let Some(host) = SimulatedHost::create() else {
+        return;
+    };
+    host.tcp_service(22, b"SSH-2.0-OpenSSH_9.6 netns\r\n");
+
+    let scanner = tcp_scanner();
+    let open = scanner.scan_port(host.ip(), 22).await.unwrap();
+    assert_eq!(open.status, PortStatus::Open);
+    assert!(open.banner.unwrap_or_default().starts_with("SSH-2.0"));
+
+    let closed = scanner.scan_port(host.ip(), 23).await.unwrap();
+    assert_eq!(closed.status, PortStatus::Closed);
+
+    if host.drop_inbound("-p tcp --dport 81") {
+        let filtered = scanner.scan_port(host.ip(), 81).await.unwrap();
+        assert_eq!(filtered.status, PortStatus::Filtered);
+    } else {
+        eprintln!("no iptables/nft in namespace; skipping filtered port check");
+    }
+}
+
+#[tokio::test]
+async fn test_udp_open_and_closed() {
+    let Some(host) = SimulatedHost::create() else {
+        return;
+    };
+    host.udp_echo(5353);
+
+    let scanner = udp_scanner();
+    let open = scanner.scan_port(host.ip(), 5353).await.unwrap();
+    assert_eq!(open.status, PortStatus::Open);
+    assert!(open.response_data.is_some());
+
+    // The kernel answers with ICMP port unreachable; an unconnected socket may
+    // not surface it, so closed and open|filtered are both acceptable here
+    let closed = scanner.scan_port(host.ip(), 5354).await.unwrap();
+    assert!(matches!(closed.status, PortStatus::Closed | PortStatus::Filtered));
+}
+
+#[tokio::test]
+async fn test_icmp_echo_and_firewall() {
+    let Some(host) = SimulatedHost::create() else {
+        return;
+    };
+
+    let rtt = nrmap::scanner::ping::icmp_echo(host.ip(), TIMEOUT_MS, PingMode::Raw)
+        .await
+        .unwrap();
+    assert!(rtt.is_some(), "simulated host should answer ICMP echo");
+
+    if host.drop_inbound("-p icmp") {
+        let rtt = nrmap::scanner::ping::icmp_echo(host.ip(), TIMEOUT_MS, PingMode::Raw)
+            .await
+            .unwrap();
+        assert!(rtt.is_none(), "firewalled host should not answer ICMP echo");
+    }
+}
+
+#[tokio::test]
+async fn test_tcp_syn_open_and_closed() {
+    let Some(host) = SimulatedHost::create() else {
+        return;
+    };
+    host.tcp_service(443, b"");
+
+    let scanner = TcpSynScanner::new(TcpSynConfig {
+        enabled: true,
+        timeout_ms: TIMEOUT_MS,
+        retries: 0,
+        retry_delay_ms: 0,
+        stateless: false,
+    });
+    let open = scanner.scan_port(host.ip(), 443).await.unwrap();
+    assert_eq!(open.status, PortStatus::Open);
+    let closed = scanner.scan_port(host.ip(), 444).await.unwrap();
+    assert_eq!(closed.status, PortStatus::Closed);
+}